@@ -0,0 +1,55 @@
+//! `cargo bench --bench ahead_behind`: commits ahead and behind main on a
+//! deep history, as when pushing or sending a short feature branch
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use git2::Oid;
+use ngit::git::{Repo, RepoActions, oid_to_sha1};
+use test_utils::git::{GitTestRepo, joe_signature};
+
+/// commits on main after the feature branched off near its root
+static HISTORY_DEPTH: usize = 100_000;
+
+fn deep_history(test_repo: &GitTestRepo) -> (Oid, Oid) {
+    let mut tip = test_repo.populate().unwrap();
+    let tree = test_repo.git_repo.find_commit(tip).unwrap().tree().unwrap();
+    let sig = joe_signature();
+    let commit_on = |parent: Oid, message: &str| -> Oid {
+        test_repo
+            .git_repo
+            .commit(
+                None,
+                &sig,
+                &sig,
+                message,
+                &tree,
+                &[&test_repo.git_repo.find_commit(parent).unwrap()],
+            )
+            .unwrap()
+    };
+    let mut feature = commit_on(tip, "feature 1");
+    feature = commit_on(feature, "feature 2");
+    for i in 0..HISTORY_DEPTH {
+        tip = commit_on(tip, &format!("commit {i}"));
+    }
+    (tip, feature)
+}
+
+fn ahead_behind(c: &mut Criterion) {
+    let test_repo = GitTestRepo::default();
+    let (main, feature) = deep_history(&test_repo);
+    let git_repo = Repo::from_path(&test_repo.dir).unwrap();
+    let (main, feature) = (oid_to_sha1(&main), oid_to_sha1(&feature));
+
+    let mut group = c.benchmark_group("get_commits_ahead_behind");
+    group.sample_size(10);
+    group.bench_function("feature branched off deep history", |b| {
+        b.iter(|| git_repo.get_commits_ahead_behind(&main, &feature).unwrap());
+    });
+    group.bench_function("branch up to date with main", |b| {
+        b.iter(|| git_repo.get_commits_ahead_behind(&main, &main).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ahead_behind);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! `cargo bench --bench dedup`: deduplicating the same events returned by
+//! several relays
+
+use std::time::Instant;
+
+use ngit::client::{STATE_KIND, get_dedup_events};
+use nostr::{EventBuilder, Keys, Kind, Tag};
+
+fn main() {
+    let keys = Keys::generate();
+    let events: Vec<nostr::Event> = (0..20_000)
+        .map(|i| {
+            if i % 4 == 0 {
+                // a few addressable events replaced many times
+                EventBuilder::new(STATE_KIND, "")
+                    .tags([Tag::identifier(format!("repo-{}", i % 40))])
+                    .custom_created_at(nostr::Timestamp::from(i))
+            } else {
+                EventBuilder::new(Kind::GitPatch, format!("patch {i}"))
+            }
+            .sign_with_keys(&keys)
+            .unwrap()
+        })
+        .collect();
+    let relay_results = (0..5).map(|_| Ok(events.clone())).collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let dedup_events = get_dedup_events(relay_results);
+    println!(
+        "deduplicated {} events from 5 relays to {} in {:?}",
+        events.len() * 5,
+        dedup_events.len(),
+        start.elapsed()
+    );
+    assert_eq!(dedup_events.len(), 15_000 + 40);
+}
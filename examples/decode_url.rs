@@ -0,0 +1,21 @@
+//! resolve a nostr git url to the repository's announcement coordinate
+//!
+//! `cargo run --example decode_url -- nostr://npub1.../relay.damus.io/ngit`
+use anyhow::{Context, Result};
+use ngit::git::nostr_url::NostrUrlDecoded;
+use nostr::ToBech32;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let url = std::env::args()
+        .nth(1)
+        .context("usage: decode_url nostr://<npub>/<identifier>")?;
+    let decoded = NostrUrlDecoded::parse_and_resolve(&url, &None).await?;
+    println!("identifier: {}", decoded.coordinate.identifier);
+    println!("maintainer: {}", decoded.coordinate.public_key.to_bech32()?);
+    for relay in &decoded.coordinate.relays {
+        println!("relay: {relay}");
+    }
+    println!("naddr: {}", decoded.coordinate.to_bech32()?);
+    Ok(())
+}
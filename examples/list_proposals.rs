@@ -0,0 +1,33 @@
+//! list the proposals cached by `ngit list` or `git fetch` in the current
+//! repository
+//!
+//! `cargo run --example list_proposals`
+use anyhow::Result;
+use ngit::{
+    client::{Client, Connect, get_proposals_and_revisions_from_cache, get_repo_ref_from_cache},
+    git::{Repo, RepoActions},
+    git_events::{event_is_revision_root, event_to_cover_letter},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let git_repo = Repo::discover()?;
+    let git_repo_path = git_repo.get_path()?;
+    let client = Client::default();
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+    println!("{}", repo_ref.name);
+    for proposal in get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?
+    .iter()
+    .filter(|e| !event_is_revision_root(e))
+    {
+        let cover_letter = event_to_cover_letter(proposal)?;
+        println!("{} {}", &proposal.id.to_hex()[..7], cover_letter.title);
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{Context, Result, bail};
+use ngit::{
+    blossom::{download_blob, upload_blob},
+    client::get_state_from_cache,
+    download::partial_download_dir,
+    git::{Repo, RepoActions, str_to_sha1},
+    repo_ref::RepoRef,
+};
+use nostr_sdk::{NostrSigner, hashes::sha1::Hash as Sha1Hash};
+
+use crate::{reporter::Reporter, utils::join_with_and};
+
+fn state_commits(state: &HashMap<String, String>) -> Vec<Sha1Hash> {
+    state
+        .values()
+        .filter(|v| !v.starts_with("ref: "))
+        .filter_map(|v| str_to_sha1(v).ok())
+        .collect()
+}
+
+/// upload a packfile of objects added since `existing_state` to the repo's
+/// blossom servers. returns the hashes of all packs needed for `new_state`.
+pub async fn push_pack_to_blossom_servers(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    existing_state: &HashMap<String, String>,
+    new_state: &HashMap<String, String>,
+    signer: &Arc<dyn NostrSigner>,
+    term: &Reporter,
+) -> Result<Vec<String>> {
+    let mut packs =
+        if let Ok(nostr_state) = get_state_from_cache(Some(git_repo.get_path()?), repo_ref).await {
+            nostr_state.packs
+        } else {
+            vec![]
+        };
+
+    let tips = state_commits(new_state);
+    let exclude = state_commits(existing_state);
+    if tips.iter().all(|tip| exclude.contains(tip)) {
+        return Ok(packs);
+    }
+    let pack = git_repo
+        .create_pack(&tips, &exclude)
+        .context("failed to create packfile for blossom servers")?;
+
+    let mut hash = None;
+    let mut errors = vec![];
+    for server in &repo_ref.blossoms {
+        term.write_line(format!("blossom: uploading packfile to {server}...").as_str())?;
+        let res = upload_blob(server, pack.clone(), signer).await;
+        term.clear_last_lines(1)?;
+        match res {
+            Ok(h) => {
+                term.write_line(format!("blossom: uploaded packfile to {server}").as_str())?;
+                hash = Some(h);
+            }
+            Err(error) => {
+                term.write_line(
+                    format!("blossom: failed to upload packfile to {server}: {error}").as_str(),
+                )?;
+                errors.push(error);
+            }
+        }
+    }
+    if let Some(hash) = hash {
+        if !packs.contains(&hash) {
+            packs.push(hash);
+        }
+        Ok(packs)
+    } else {
+        bail!(
+            "failed to upload packfile to blossom servers: {}",
+            join_with_and(&errors)
+        )
+    }
+}
+
+/// download and index packfiles listed in the nostr state event that haven't
+/// been fetched already
+pub async fn fetch_from_blossom_servers(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    term: &Reporter,
+) -> Result<()> {
+    let nostr_state = get_state_from_cache(Some(git_repo.get_path()?), repo_ref)
+        .await
+        .context("cannot find packfiles on blossom servers without a nostr state event")?;
+
+    let mut fetched: Vec<String> = git_repo
+        .get_git_config_item("nostr.blossom-packs", Some(false))?
+        .unwrap_or_default()
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+
+    for hash in &nostr_state.packs {
+        if fetched.contains(hash) {
+            continue;
+        }
+        let mut errors = vec![];
+        for server in &repo_ref.blossoms {
+            term.write_line(format!("blossom: downloading packfile from {server}...").as_str())?;
+            let res = download_blob(
+                server,
+                hash,
+                &partial_download_dir(git_repo.git_repo.path()),
+            )
+            .await;
+            term.clear_last_lines(1)?;
+            match res {
+                Ok(pack) => {
+                    git_repo
+                        .index_pack(&pack)
+                        .context(format!("failed to index packfile {hash}"))?;
+                    fetched.push(hash.clone());
+                    git_repo.save_git_config_item(
+                        "nostr.blossom-packs",
+                        &fetched.join(","),
+                        false,
+                    )?;
+                    errors.clear();
+                    break;
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        if !errors.is_empty() {
+            bail!(
+                "failed to download packfile {hash} from blossom servers: {}",
+                join_with_and(&errors)
+            );
+        }
+    }
+    Ok(())
+}
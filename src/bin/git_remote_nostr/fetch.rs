@@ -2,31 +2,40 @@ use core::str;
 use std::{
     collections::HashMap,
     io::Stdin,
+    net::{TcpStream, ToSocketAddrs},
     sync::{Arc, Mutex},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result, anyhow, bail};
 use auth_git2::GitAuthenticator;
 use git2::{Progress, Repository};
 use ngit::{
-    cli_interactor::count_lines_per_msg_vec,
     git::{
         Repo, RepoActions,
+        bundle::fetch_from_bundle_uri,
         nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
-        utils::check_ssh_keys,
+        pack_cache::{fetch_from_pack_cache, pack_cache_dir, save_to_pack_cache},
+        patch_progress::PatchProgress,
     },
     git_events::tag_value,
+    interrupt::is_interrupted,
     login::get_curent_user,
     repo_ref::RepoRef,
 };
 use nostr::nips::nip19;
 use nostr_sdk::{Event, ToBech32};
 
-use crate::utils::{
-    Direction, fetch_or_list_error_is_not_authentication_failure,
-    find_proposal_and_patches_by_branch_name, get_oids_from_fetch_batch, get_open_proposals,
-    get_read_protocols_to_try, join_with_and, set_protocol_preference,
+use crate::{
+    blossom::fetch_from_blossom_servers,
+    reporter::Reporter,
+    utils::{
+        Direction, HelperOptions, SshIdentity, credentials_callback,
+        fetch_or_list_error_is_not_authentication_failure,
+        find_proposal_and_patches_by_branch_name, get_git_servers_to_try,
+        get_oids_from_fetch_batch, get_open_proposals, get_read_protocols_to_try, join_with_and,
+        report_credential_outcome, set_protocol_preference,
+    },
 };
 
 pub async fn run_fetch(
@@ -36,6 +45,7 @@ pub async fn run_fetch(
     stdin: &Stdin,
     oid: &str,
     refstr: &str,
+    options: &HelperOptions,
 ) -> Result<()> {
     let mut fetch_batch = get_oids_from_fetch_batch(stdin, oid, refstr)?;
 
@@ -46,10 +56,59 @@ pub async fn run_fetch(
         .collect::<Vec<String>>();
 
     let mut errors = vec![];
-    let term = console::Term::stderr();
+    let term = Reporter::from_options(options);
+
+    let mut fetched_bundles = false;
+    if !repo_ref.bundles.is_empty()
+        && git_repo
+            .get_git_config_item("nostr.bundle-fetched", Some(false))?
+            .is_none()
+        && oids_from_git_servers
+            .iter()
+            .any(|oid| !git_repo.does_commit_exist(oid).unwrap())
+    {
+        fetch_from_bundles(git_repo, repo_ref, &term).await;
+        fetched_bundles = true;
+    }
+
+    let mut git_servers = get_git_servers_to_try(git_repo, &repo_ref.git_server);
+
+    // another working copy may have already fetched the same tips
+    if let Ok(cache_dir) = git_repo.get_path().and_then(pack_cache_dir) {
+        for git_server_url in &git_servers {
+            if oids_from_git_servers
+                .iter()
+                .all(|oid| git_repo.does_commit_exist(oid).unwrap())
+            {
+                break;
+            }
+            match fetch_from_pack_cache(
+                git_repo,
+                &cache_dir,
+                git_server_url,
+                &oids_from_git_servers,
+            ) {
+                Ok(true) => {
+                    tracing::debug!(url = %git_server_url, "reused objects from the pack cache");
+                    break;
+                }
+                Ok(false) => {}
+                Err(error) => {
+                    tracing::warn!(url = %git_server_url, "failed to use pack cache: {error:#}");
+                }
+            }
+        }
+    }
 
-    for git_server_url in &repo_ref.git_server {
-        let term = console::Term::stderr();
+    // an explicit server order is respected rather than racing servers
+    if git_servers.len() > 1
+        && git_repo
+            .get_git_config_item("nostr.git-server-order", Some(false))?
+            .is_none()
+    {
+        git_servers = order_by_connect_latency(&git_servers);
+    }
+    for git_server_url in &git_servers {
         if let Err(error) = fetch_from_git_server(
             git_repo,
             &oids_from_git_servers,
@@ -63,6 +122,27 @@ pub async fn run_fetch(
         }
     }
 
+    if !repo_ref.blossoms.is_empty()
+        && oids_from_git_servers
+            .iter()
+            .any(|oid| !git_repo.does_commit_exist(oid).unwrap())
+    {
+        if let Err(error) = fetch_from_blossom_servers(git_repo, repo_ref, &term).await {
+            errors.push(error);
+        }
+    }
+
+    // bundles may be stale but could contain the commits in the nostr state if
+    // the git servers are unreachable
+    if !fetched_bundles
+        && !repo_ref.bundles.is_empty()
+        && oids_from_git_servers
+            .iter()
+            .any(|oid| !git_repo.does_commit_exist(oid).unwrap())
+    {
+        fetch_from_bundles(git_repo, repo_ref, &term).await;
+    }
+
     if oids_from_git_servers
         .iter()
         .any(|oid| !git_repo.does_commit_exist(oid).unwrap())
@@ -86,10 +166,33 @@ pub async fn run_fetch(
     Ok(())
 }
 
+/// seed an initial clone from a bundle so only recent history is fetched from
+/// git servers
+async fn fetch_from_bundles(git_repo: &Repo, repo_ref: &RepoRef, term: &Reporter) {
+    for uri in &repo_ref.bundles {
+        let _ = term.write_line(format!("fetching bundle {uri}...").as_str());
+        let res = fetch_from_bundle_uri(git_repo, uri).await;
+        let _ = term.clear_last_lines(1);
+        match res {
+            Ok(()) => {
+                let _ = term.write_line(format!("fetched bundle {uri}").as_str());
+                let _ = git_repo.save_git_config_item("nostr.bundle-fetched", uri, false);
+                break;
+            }
+            Err(error) => {
+                // the git servers are fetched from in full instead
+                tracing::warn!(%uri, "failed to fetch bundle: {error:#}");
+            }
+        }
+    }
+}
+
 pub fn make_commits_for_proposal(
     git_repo: &Repo,
     repo_ref: &RepoRef,
     patches_ancestor_last: &[Event],
+    // listed in a summary once the commits have been made
+    branch_name: Option<&str>,
 ) -> Result<String> {
     let patches_ancestor_first: Vec<&Event> = patches_ancestor_last.iter().rev().collect();
     let mut tip_commit_id = if let Ok(parent_commit) = tag_value(
@@ -106,7 +209,9 @@ pub fn make_commits_for_proposal(
         hash.to_string()
     };
 
+    let mut progress = PatchProgress::new(patches_ancestor_first.len());
     for patch in &patches_ancestor_first {
+        progress.start(patch);
         let commit_id = git_repo
             .create_commit_from_patch(patch, Some(tip_commit_id.clone()))
             .context(format!(
@@ -125,13 +230,15 @@ pub fn make_commits_for_proposal(
                 .unwrap_or_default()
             ))?;
         tip_commit_id = commit_id.to_string();
+        progress.applied(patch, &tip_commit_id);
     }
+    progress.finish(branch_name);
     Ok(tip_commit_id)
 }
 
 async fn fetch_proposals(
     git_repo: &Repo,
-    term: &console::Term,
+    term: &Reporter,
     repo_ref: &RepoRef,
     proposal_refs: &HashMap<String, String>,
 ) -> Result<()> {
@@ -146,7 +253,12 @@ async fn fetch_proposals(
                 &open_proposals,
                 current_user.as_ref(),
             ) {
-                if let Err(error) = make_commits_for_proposal(git_repo, repo_ref, patches) {
+                if let Err(error) = make_commits_for_proposal(
+                    git_repo,
+                    repo_ref,
+                    patches,
+                    Some(refstr.trim_start_matches("refs/heads/")),
+                ) {
                     term.write_line(
                         format!("WARNING: failed to create branch for {refstr}, error: {error}",)
                             .as_str(),
@@ -159,12 +271,53 @@ async fn fetch_proposals(
     Ok(())
 }
 
+/// how long a git server has to accept a connection when ranking servers
+static CONNECT_LATENCY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// time taken to open a connection to the git server, if it can be reached
+fn connect_latency(git_server_url: &str) -> Option<Duration> {
+    let server_url = git_server_url.parse::<CloneUrl>().ok()?;
+    let port = match server_url.protocol() {
+        ServerProtocol::Filesystem => return Some(Duration::ZERO),
+        ServerProtocol::Ssh => 22,
+        ServerProtocol::Http | ServerProtocol::UnauthHttp => 80,
+        ServerProtocol::Git => 9418,
+        _ => 443,
+    };
+    let address = (server_url.domain(), server_url.port().unwrap_or(port))
+        .to_socket_addrs()
+        .ok()?
+        .next()?;
+    let start = Instant::now();
+    TcpStream::connect_timeout(&address, CONNECT_LATENCY_TIMEOUT).ok()?;
+    Some(start.elapsed())
+}
+
+/// `git_servers` fastest to connect to first, with unreachable ones last in
+/// their original order. connections are raced rather than fetches so only
+/// one fetch writes to the repository at a time
+fn order_by_connect_latency(git_servers: &[String]) -> Vec<String> {
+    let latencies: Vec<Option<Duration>> = std::thread::scope(|scope| {
+        git_servers
+            .iter()
+            .map(|git_server_url| scope.spawn(move || connect_latency(git_server_url)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().ok().flatten())
+            .collect()
+    });
+    let mut ranked: Vec<(Option<Duration>, &String)> =
+        latencies.into_iter().zip(git_servers).collect();
+    ranked.sort_by_key(|(latency, _)| latency.unwrap_or(Duration::MAX));
+    ranked.into_iter().map(|(_, url)| url.clone()).collect()
+}
+
 pub fn fetch_from_git_server(
     git_repo: &Repo,
     oids: &[String],
     git_server_url: &str,
     decoded_nostr_url: &NostrUrlDecoded,
-    term: &console::Term,
+    term: &Reporter,
 ) -> Result<()> {
     let already_have_oids = oids
         .iter()
@@ -177,22 +330,28 @@ pub fn fetch_from_git_server(
 
     let protocols_to_attempt = get_read_protocols_to_try(git_repo, &server_url, decoded_nostr_url);
 
+    let ssh_identity = SshIdentity::for_nostr_url(git_repo, decoded_nostr_url)?;
+
     let mut failed_protocols = vec![];
     let mut success = false;
     for protocol in &protocols_to_attempt {
-        term.write_line(
-            format!("fetching {} over {protocol}...", server_url.short_name(),).as_str(),
-        )?;
+        let title = format!("fetching {} over {protocol}...", server_url.short_name());
+        term.set_section(&title, vec![])?;
 
         let formatted_url = server_url.format_as(protocol, &decoded_nostr_url.user)?;
+        let _span = tracing::debug_span!("git_fetch", url = %formatted_url, %protocol).entered();
         let res = fetch_from_git_server_url(
             &git_repo.git_repo,
             oids,
             &formatted_url,
             [ServerProtocol::UnauthHttps, ServerProtocol::UnauthHttp].contains(protocol),
+            &ssh_identity,
             term,
+            &title,
         );
+        term.finish_section(&title)?;
         if let Err(error) = res {
+            tracing::debug!("failed: {error:#}");
             term.write_line(
                 format!("fetch: {formatted_url} failed over {protocol}: {error}").as_str(),
             )?;
@@ -205,6 +364,9 @@ pub fn fetch_from_git_server(
             }
         } else {
             success = true;
+            if let Ok(cache_dir) = git_repo.get_path().and_then(pack_cache_dir) {
+                let _ = save_to_pack_cache(git_repo, &cache_dir, git_server_url, oids);
+            }
             if !failed_protocols.is_empty() {
                 term.write_line(format!("fetch: succeeded over {protocol}").as_str())?;
                 let _ = set_protocol_preference(git_repo, protocol, &server_url, &Direction::Push);
@@ -300,43 +462,31 @@ fn report_on_transfer_progress(
 struct FetchReporter<'a> {
     remote_msgs: Vec<String>,
     transfer_progress_msgs: Vec<String>,
-    term: &'a console::Term,
+    term: &'a Reporter,
+    title: String,
     start_time: Option<Instant>,
     end_time: Option<Instant>,
 }
 impl<'a> FetchReporter<'a> {
-    fn new(term: &'a console::Term) -> Self {
+    fn new(term: &'a Reporter, title: &str) -> Self {
         Self {
             remote_msgs: vec![],
             transfer_progress_msgs: vec![],
             term,
+            title: title.to_string(),
             start_time: None,
             end_time: None,
         }
     }
-    fn write_all(&self, lines_to_clear: usize) {
-        let _ = self.term.clear_last_lines(lines_to_clear);
-        for msg in &self.remote_msgs {
-            let _ = self.term.write_line(format!("remote: {msg}").as_str());
-        }
-        for msg in &self.transfer_progress_msgs {
-            let _ = self.term.write_line(msg);
-        }
-    }
-    fn count_all_existing_lines(&self) -> usize {
-        let width = self.term.size().1;
-        count_lines_per_msg_vec(width, &self.remote_msgs, "remote: ".len())
-            + count_lines_per_msg_vec(width, &self.transfer_progress_msgs, 0)
-    }
-    fn just_write_transfer_progress(&self, lines_to_clear: usize) {
-        let _ = self.term.clear_last_lines(lines_to_clear);
-        for msg in &self.transfer_progress_msgs {
-            let _ = self.term.write_line(msg);
-        }
-    }
-    fn just_count_transfer_progress(&self) -> usize {
-        let width = self.term.size().1;
-        count_lines_per_msg_vec(width, &self.transfer_progress_msgs, 0)
+    fn write_all(&self) {
+        let _ = self.term.set_section(
+            &self.title,
+            self.remote_msgs
+                .iter()
+                .map(|msg| format!("remote: {msg}"))
+                .chain(self.transfer_progress_msgs.iter().cloned())
+                .collect(),
+        );
     }
     fn process_remote_msg(&mut self, data: &[u8]) {
         if let Ok(data) = str::from_utf8(data) {
@@ -346,7 +496,6 @@ impl<'a> FetchReporter<'a> {
                 .filter(|line| !line.trim().is_empty())
                 .collect::<Vec<&str>>();
             for data in data {
-                let existing_lines = self.count_all_existing_lines();
                 let msg = data.to_string();
                 if let Some(last) = self.remote_msgs.last() {
                     // if previous line begins with x but doesnt finish with y then its part of the
@@ -371,7 +520,7 @@ impl<'a> FetchReporter<'a> {
                 } else {
                     self.remote_msgs.push(msg);
                 }
-                self.write_all(existing_lines);
+                self.write_all();
             }
         }
     }
@@ -379,7 +528,6 @@ impl<'a> FetchReporter<'a> {
         if self.start_time.is_none() {
             self.start_time = Some(Instant::now());
         }
-        let existing_lines = self.just_count_transfer_progress();
         let updated = report_on_transfer_progress(
             progress_stats,
             &self.start_time.unwrap(),
@@ -393,7 +541,7 @@ impl<'a> FetchReporter<'a> {
             // reporting on it so we want to keep the old report
             self.transfer_progress_msgs = updated;
         }
-        self.just_write_transfer_progress(existing_lines);
+        self.write_all();
     }
 }
 
@@ -402,23 +550,26 @@ fn fetch_from_git_server_url(
     oids: &[String],
     git_server_url: &str,
     dont_authenticate: bool,
-    term: &console::Term,
+    ssh_identity: &SshIdentity,
+    term: &Reporter,
+    title: &str,
 ) -> Result<()> {
-    if git_server_url.parse::<CloneUrl>()?.protocol() == ServerProtocol::Ssh && !check_ssh_keys() {
-        bail!("no ssh keys found");
+    if git_server_url.parse::<CloneUrl>()?.protocol() == ServerProtocol::Ssh {
+        ssh_identity.check_available()?;
     }
     let git_config = git_repo.config()?;
     let mut git_server_remote = git_repo.remote_anonymous(git_server_url)?;
     let auth = GitAuthenticator::default();
+    let used_credential = Mutex::new(None);
     let mut fetch_options = git2::FetchOptions::new();
     let mut remote_callbacks = git2::RemoteCallbacks::new();
-    let fetch_reporter = Arc::new(Mutex::new(FetchReporter::new(term)));
+    let fetch_reporter = Arc::new(Mutex::new(FetchReporter::new(term, title)));
     remote_callbacks.sideband_progress({
         let fetch_reporter = Arc::clone(&fetch_reporter);
         move |data| {
             let mut reporter = fetch_reporter.lock().unwrap();
             reporter.process_remote_msg(data);
-            true
+            !is_interrupted()
         }
     });
     remote_callbacks.transfer_progress({
@@ -426,15 +577,22 @@ fn fetch_from_git_server_url(
         move |stats| {
             let mut reporter = fetch_reporter.lock().unwrap();
             reporter.process_transfer_progress_update(&stats);
-            true
+            !is_interrupted()
         }
     });
 
     if !dont_authenticate {
-        remote_callbacks.credentials(auth.credentials(&git_config));
+        remote_callbacks.credentials(credentials_callback(
+            &auth,
+            &git_config,
+            ssh_identity.key.as_deref(),
+            &used_credential,
+        ));
     }
     fetch_options.remote_callbacks(remote_callbacks);
-    git_server_remote.download(oids, Some(&mut fetch_options))?;
+    let res = git_server_remote.download(oids, Some(&mut fetch_options));
+    report_credential_outcome(&used_credential, &res);
+    res?;
 
     git_server_remote.disconnect()?;
     Ok(())
@@ -445,9 +603,26 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn servers_are_ordered_by_connect_latency_with_unreachable_ones_last() -> Result<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let reachable_port = listener.local_addr()?.port();
+        // nothing listens on the port once its listener is dropped
+        let unreachable_port = std::net::TcpListener::bind("127.0.0.1:0")?
+            .local_addr()?
+            .port();
+        let reachable = format!("http://127.0.0.1:{reachable_port}/repo.git");
+        let unreachable = format!("http://127.0.0.1:{unreachable_port}/repo.git");
+        assert_eq!(
+            order_by_connect_latency(&[unreachable.clone(), reachable.clone()]),
+            vec![reachable, unreachable]
+        );
+        Ok(())
+    }
+
     fn pass_through_fetch_reporter_proces_remote_msg(msgs: Vec<&str>) -> Vec<String> {
-        let term = console::Term::stdout();
-        let mut reporter = FetchReporter::new(&term);
+        let term = Reporter::new(console::Term::stdout());
+        let mut reporter = FetchReporter::new(&term, "fetching test over https...");
         for msg in msgs {
             reporter.process_remote_msg(msg.as_bytes());
         }
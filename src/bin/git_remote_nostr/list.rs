@@ -1,5 +1,5 @@
 use core::str;
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Mutex};
 
 use anyhow::{Context, Result, anyhow};
 use auth_git2::GitAuthenticator;
@@ -10,21 +10,26 @@ use ngit::{
     git::{
         self,
         nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
+        protocol_v2::{LIST_REF_PREFIXES, ls_refs_over_http},
     },
-    git_events::event_to_cover_letter,
+    git_events::{DEFAULT_BRANCH_NAME_TEMPLATE, event_to_cover_letter},
     login::get_curent_user,
-    repo_ref,
+    repo_ref::{self, get_proposal_lineage_warning},
 };
-use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use nostr_sdk::{Event, PublicKey, hashes::sha1::Hash as Sha1Hash};
 use repo_ref::RepoRef;
 
 use crate::{
+    blossom::fetch_from_blossom_servers,
     fetch::{fetch_from_git_server, make_commits_for_proposal},
     git::Repo,
+    reporter::Reporter,
     utils::{
-        Direction, fetch_or_list_error_is_not_authentication_failure, get_open_proposals,
-        get_read_protocols_to_try, get_short_git_server_name, join_with_and,
-        set_protocol_preference,
+        Direction, HelperOptions, SshIdentity, credentials_callback,
+        fetch_or_list_error_is_not_authentication_failure, get_git_servers_to_try,
+        get_open_proposals, get_proposals_with_status, get_read_protocols_to_try,
+        get_short_git_server_name, join_with_and, report_credential_outcome,
+        set_protocol_preference, status_kind_to_str,
     },
 };
 
@@ -33,6 +38,7 @@ pub async fn run_list(
     repo_ref: &RepoRef,
     decoded_nostr_url: &NostrUrlDecoded,
     for_push: bool,
+    options: &HelperOptions,
 ) -> Result<HashMap<String, HashMap<String, String>>> {
     let nostr_state =
         if let Ok(nostr_state) = get_state_from_cache(Some(git_repo.get_path()?), repo_ref).await {
@@ -41,11 +47,18 @@ pub async fn run_list(
             None
         };
 
-    let term = console::Term::stderr();
+    let term = Reporter::from_options(options);
 
-    let remote_states = list_from_remotes(&term, git_repo, &repo_ref.git_server, decoded_nostr_url);
+    let git_servers = get_git_servers_to_try(git_repo, &repo_ref.git_server);
+
+    let remote_states = list_from_remotes(&term, git_repo, &git_servers, decoded_nostr_url).await;
 
     let mut state = if let Some(nostr_state) = nostr_state {
+        if remote_states.is_empty() && !git_servers.is_empty() {
+            term.write_line(
+                "WARNING: no git servers responded. using refs from the nostr state event",
+            )?;
+        }
         for (name, value) in &nostr_state.state {
             for (url, remote_state) in &remote_states {
                 let remote_name = get_short_git_server_name(git_repo, url);
@@ -75,8 +88,7 @@ pub async fn run_list(
         }
         nostr_state.state
     } else {
-        repo_ref
-            .git_server
+        git_servers
             .iter()
             .filter_map(|server| remote_states.get(server))
             .cloned()
@@ -88,12 +100,34 @@ pub async fn run_list(
 
     state.retain(|k, _| !k.starts_with("refs/heads/pr/"));
 
-    let proposals_state =
-        get_open_proposals_state(&term, git_repo, repo_ref, decoded_nostr_url, &remote_states)
-            .await?;
+    if !repo_ref.blossoms.is_empty()
+        && state
+            .values()
+            .any(|v| !v.starts_with("ref: ") && !git_repo.does_commit_exist(v).unwrap_or(false))
+    {
+        if let Err(error) = fetch_from_blossom_servers(git_repo, repo_ref, &term).await {
+            term.write_line(format!("WARNING: {error}").as_str())?;
+        }
+    }
+
+    let proposals_state = get_open_proposals_state(
+        &term,
+        git_repo,
+        repo_ref,
+        decoded_nostr_url,
+        &git_servers,
+        &remote_states,
+    )
+    .await?;
 
     state.extend(proposals_state);
 
+    if for_push {
+        if let Err(error) = report_proposal_statuses(&term, git_repo, repo_ref).await {
+            term.write_line(format!("WARNING: failed to get proposal statuses: {error}").as_str())?;
+        }
+    }
+
     // TODO 'for push' should we check with the git servers to see if any of them
     // allow push from the user?
     for (name, value) in state {
@@ -111,10 +145,11 @@ pub async fn run_list(
 }
 
 async fn get_open_proposals_state(
-    term: &console::Term,
+    term: &Reporter,
     git_repo: &Repo,
     repo_ref: &RepoRef,
     decoded_nostr_url: &NostrUrlDecoded,
+    git_servers: &[String],
     remote_states: &HashMap<String, HashMap<String, String>>,
 ) -> Result<HashMap<String, String>> {
     // we cannot use commit_id in the latest patch in a proposal because:
@@ -124,7 +159,10 @@ async fn get_open_proposals_state(
 
     // without trusting commit_id we must apply each patch which requires the oid of
     // the parent so we much do a fetch
-    for (git_server_url, oids_from_git_servers) in remote_states {
+    for (git_server_url, oids_from_git_servers) in git_servers
+        .iter()
+        .filter_map(|url| remote_states.get(url).map(|state| (url, state)))
+    {
         if fetch_from_git_server(
             git_repo,
             &oids_from_git_servers
@@ -146,36 +184,75 @@ async fn get_open_proposals_state(
     let open_proposals = get_open_proposals(git_repo, repo_ref).await?;
     let current_user = get_curent_user(git_repo)?;
     for (_, (proposal, patches)) in open_proposals {
-        if let Ok(cl) = event_to_cover_letter(&proposal) {
-            if let Ok(mut branch_name) = cl.get_branch_name() {
-                branch_name = if let Some(public_key) = current_user {
-                    if proposal.pubkey.eq(&public_key) {
-                        format!("pr/{}", cl.branch_name)
-                    } else {
-                        branch_name
-                    }
-                } else {
-                    branch_name
-                };
-                match make_commits_for_proposal(git_repo, repo_ref, &patches) {
-                    Ok(tip) => {
-                        state.insert(format!("refs/heads/{branch_name}"), tip);
-                    }
-                    Err(error) => {
-                        let _ = term.write_line(
-                            format!("WARNING: failed to fetch branch {branch_name} error: {error}")
-                                .as_str(),
-                        );
-                    }
-                };
+        if let Some(branch_name) = proposal_branch_name(&proposal, current_user.as_ref()) {
+            // can't ask as git is on stdin, so don't offer it to be fetched
+            if let Some(warning) = get_proposal_lineage_warning(git_repo, repo_ref, &proposal) {
+                let _ = term.write_line(
+                    format!("WARNING: not listing branch {branch_name}: {warning}").as_str(),
+                );
+                continue;
             }
+            match make_commits_for_proposal(git_repo, repo_ref, &patches, None) {
+                Ok(tip) => {
+                    state.insert(format!("refs/heads/{branch_name}"), tip);
+                }
+                Err(error) => {
+                    let _ = term.write_line(
+                        format!("WARNING: failed to fetch branch {branch_name} error: {error}")
+                            .as_str(),
+                    );
+                }
+            };
         }
     }
     Ok(state)
 }
 
-pub fn list_from_remotes(
-    term: &console::Term,
+/// branch name the proposal is listed under. with the default template, the
+/// current user's own proposals are listed without the id suffix
+fn proposal_branch_name(proposal: &Event, current_user: Option<&PublicKey>) -> Option<String> {
+    let cl = event_to_cover_letter(proposal).ok()?;
+    let branch_name = cl.get_branch_name().ok()?;
+    if current_user.is_some_and(|public_key| proposal.pubkey.eq(public_key))
+        && cl
+            .get_branch_name_from_template(DEFAULT_BRANCH_NAME_TEMPLATE, None)
+            .is_ok_and(|default| default.eq(&branch_name))
+    {
+        Some(format!("pr/{}", cl.branch_name))
+    } else {
+        Some(branch_name)
+    }
+}
+
+/// show the status of proposals which have a local branch so users can see
+/// whether the proposal they are pushing to is still active
+async fn report_proposal_statuses(
+    term: &Reporter,
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+) -> Result<()> {
+    let local_branches = git_repo.get_local_branch_names()?;
+    let current_user = get_curent_user(git_repo)?;
+    let mut lines = vec![];
+    for (proposal, status) in get_proposals_with_status(git_repo, repo_ref).await? {
+        if let Some(branch_name) = proposal_branch_name(&proposal, current_user.as_ref()) {
+            if local_branches.contains(&branch_name) {
+                lines.push(format!("  {branch_name} {}", status_kind_to_str(status)));
+            }
+        }
+    }
+    if !lines.is_empty() {
+        lines.sort();
+        term.write_line("proposals:")?;
+        for line in lines {
+            term.write_line(&line)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn list_from_remotes(
+    term: &Reporter,
     git_repo: &Repo,
     git_servers: &Vec<String>,
     decoded_nostr_url: &NostrUrlDecoded, // Add this parameter
@@ -183,7 +260,7 @@ pub fn list_from_remotes(
     let mut remote_states = HashMap::new();
     let mut errors = HashMap::new();
     for url in git_servers {
-        match list_from_remote(term, git_repo, url, decoded_nostr_url) {
+        match list_from_remote(term, git_repo, url, decoded_nostr_url).await {
             Err(error) => {
                 errors.insert(url, error);
             }
@@ -195,8 +272,8 @@ pub fn list_from_remotes(
     remote_states
 }
 
-pub fn list_from_remote(
-    term: &console::Term,
+pub async fn list_from_remote(
+    term: &Reporter,
     git_repo: &Repo,
     git_server_url: &str,
     decoded_nostr_url: &NostrUrlDecoded, // Add this parameter
@@ -204,30 +281,33 @@ pub fn list_from_remote(
     let server_url = git_server_url.parse::<CloneUrl>()?;
     let protocols_to_attempt = get_read_protocols_to_try(git_repo, &server_url, decoded_nostr_url);
 
+    let ssh_identity = SshIdentity::for_nostr_url(git_repo, decoded_nostr_url)?;
+
     let mut failed_protocols = vec![];
     let mut remote_state: Option<HashMap<String, String>> = None;
 
     for protocol in &protocols_to_attempt {
-        term.write_line(
-            format!(
-                "fetching {} ref list over {protocol}...",
-                server_url.short_name(),
-            )
-            .as_str(),
-        )?;
+        let title = format!(
+            "fetching {} ref list over {protocol}...",
+            server_url.short_name(),
+        );
+        term.set_section(&title, vec![])?;
 
         let formatted_url = server_url.format_as(protocol, &decoded_nostr_url.user)?;
         let res = list_from_remote_url(
             git_repo,
             &formatted_url,
             [ServerProtocol::UnauthHttps, ServerProtocol::UnauthHttp].contains(protocol),
+            &ssh_identity,
             term,
-        );
+            &title,
+        )
+        .await;
+        term.remove_section(&title)?;
 
         match res {
             Ok(state) => {
                 remote_state = Some(state);
-                term.clear_last_lines(1)?;
                 if !failed_protocols.is_empty() {
                     term.write_line(
                         format!(
@@ -242,7 +322,6 @@ pub fn list_from_remote(
                 break;
             }
             Err(error) => {
-                term.clear_last_lines(1)?;
                 term.write_line(
                     format!("list: {formatted_url} failed over {protocol}: {error}").as_str(),
                 )?;
@@ -257,9 +336,6 @@ pub fn list_from_remote(
         }
     }
     if let Some(remote_state) = remote_state {
-        if failed_protocols.is_empty() {
-            term.clear_last_lines(1)?;
-        }
         Ok(remote_state)
     } else {
         let error = anyhow!(
@@ -277,24 +353,44 @@ pub fn list_from_remote(
     }
 }
 
-fn list_from_remote_url(
+async fn list_from_remote_url(
     git_repo: &Repo,
     git_server_remote_url: &str,
     dont_authenticate: bool,
-    term: &console::Term,
+    ssh_identity: &SshIdentity,
+    term: &Reporter,
+    title: &str,
 ) -> Result<HashMap<String, String>> {
+    if git_server_remote_url.parse::<CloneUrl>()?.protocol() == ServerProtocol::Ssh {
+        ssh_identity.check_available()?;
+    }
+    if dont_authenticate {
+        // protocol v2 lets the server filter refs we dont need
+        term.set_section(title, vec!["list: connecting...".to_string()])?;
+        if let Ok(state) = ls_refs_over_http(git_server_remote_url, &LIST_REF_PREFIXES).await {
+            return Ok(state);
+        }
+    }
     let git_config = git_repo.git_repo.config()?;
 
     let mut git_server_remote = git_repo.git_repo.remote_anonymous(git_server_remote_url)?;
     // authentication may be required
     let auth = GitAuthenticator::default();
+    let used_credential = Mutex::new(None);
     let mut remote_callbacks = git2::RemoteCallbacks::new();
     if !dont_authenticate {
-        remote_callbacks.credentials(auth.credentials(&git_config));
+        remote_callbacks.credentials(credentials_callback(
+            &auth,
+            &git_config,
+            ssh_identity.key.as_deref(),
+            &used_credential,
+        ));
     }
-    term.write_line("list: connecting...")?;
-    git_server_remote.connect_auth(git2::Direction::Fetch, Some(remote_callbacks), None)?;
-    term.clear_last_lines(1)?;
+    term.set_section(title, vec!["list: connecting...".to_string()])?;
+    let res = git_server_remote.connect_auth(git2::Direction::Fetch, Some(remote_callbacks), None);
+    report_credential_outcome(&used_credential, &res);
+    res?;
+    term.set_section(title, vec![])?;
     let mut state = HashMap::new();
     for head in git_server_remote.list()? {
         if let Some(symbolic_reference) = head.symref_target() {
@@ -15,18 +15,24 @@ use anyhow::{Context, Result, bail};
 use client::{Connect, consolidate_fetch_reports, get_repo_ref_from_cache};
 use git::{RepoActions, nostr_url::NostrUrlDecoded};
 use ngit::{client, git, login::existing::load_existing_login};
-use nostr::nips::nip01::Coordinate;
-use utils::read_line;
+use nostr::{nips::nip01::Coordinate, signer::SignerBackend};
+use utils::{HelperOptions, read_line};
 
 use crate::{client::Client, git::Repo};
 
+mod blossom;
 mod fetch;
 mod list;
 mod push;
+mod reporter;
 mod utils;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    ngit::logging::init(None)?;
+    ngit::theme::init(None)?;
+    ngit::interrupt::exit_on_ctrl_c();
+
     let Some((decoded_nostr_url, git_repo)) = process_args().await? else {
         return Ok(());
     };
@@ -47,8 +53,12 @@ async fn main() -> Result<()> {
     )
     .await
     {
-        // signer for to respond to relay auth request
-        client.set_signer(signer).await;
+        // signer for to respond to relay auth request. only local keys are
+        // used, as remote signers, extensions and devices may need each
+        // request approving by hand and reads shouldn't prompt
+        if matches!(signer.backend(), SignerBackend::Keys) {
+            client.set_signer(signer).await;
+        }
     }
 
     fetching_with_report_for_helper(git_repo_path, &client, &decoded_nostr_url.coordinate).await?;
@@ -60,6 +70,7 @@ async fn main() -> Result<()> {
     let mut line = String::new();
 
     let mut list_outputs = None;
+    let mut options = HelperOptions::default();
     loop {
         let tokens = read_line(&stdin, &mut line)?;
 
@@ -70,9 +81,11 @@ async fn main() -> Result<()> {
                 println!("fetch");
                 println!();
             }
-            ["option", "verbosity"] => {
-                println!("ok");
-            }
+            ["option", name, value] => match options.set(name, value) {
+                Ok(true) => println!("ok"),
+                Ok(false) => println!("unsupported"),
+                Err(error) => println!("error {error}"),
+            },
             ["option", ..] => {
                 println!("unsupported");
             }
@@ -84,6 +97,7 @@ async fn main() -> Result<()> {
                     &stdin,
                     oid,
                     refstr,
+                    &options,
                 )
                 .await?;
             }
@@ -96,16 +110,21 @@ async fn main() -> Result<()> {
                     refspec,
                     &client,
                     list_outputs.clone(),
+                    &options,
                 )
                 .await?;
             }
             ["list"] => {
-                list_outputs =
-                    Some(list::run_list(&git_repo, &repo_ref, &decoded_nostr_url, false).await?);
+                list_outputs = Some(
+                    list::run_list(&git_repo, &repo_ref, &decoded_nostr_url, false, &options)
+                        .await?,
+                );
             }
             ["list", "for-push"] => {
-                list_outputs =
-                    Some(list::run_list(&git_repo, &repo_ref, &decoded_nostr_url, true).await?);
+                list_outputs = Some(
+                    list::run_list(&git_repo, &repo_ref, &decoded_nostr_url, true, &options)
+                        .await?,
+                );
             }
             [] => {
                 return Ok(());
@@ -160,6 +179,10 @@ async fn fetching_with_report_for_helper(
     trusted_maintainer_coordinate: &Coordinate,
 ) -> Result<()> {
     let term = console::Term::stderr();
+    let _ = ngit::recent_repos::record_used(git_repo_path);
+    if ngit::recent_repos::refreshed_by_daemon(git_repo_path) {
+        return Ok(());
+    }
     term.write_line("nostr: fetching...")?;
     let (relay_reports, progress_reporter) = client
         .fetch_all(
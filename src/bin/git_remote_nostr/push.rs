@@ -11,23 +11,25 @@ use auth_git2::GitAuthenticator;
 use client::{
     STATE_KIND, get_events_from_local_cache, get_state_from_cache, send_events, sign_event,
 };
-use console::Term;
 use git::{RepoActions, sha1_to_oid};
 use git_events::{
-    generate_cover_letter_and_patch_events, generate_patch_event, get_commit_id_from_patch,
+    generate_cover_letter_and_patch_events, generate_patch_events_continuing_proposal,
+    get_commit_id_from_patch,
 };
 use git2::{Oid, Repository};
 use ngit::{
-    cli_interactor::count_lines_per_msg_vec,
     client::{self, get_event_from_cache_by_id},
     git::{
-        self,
-        nostr_url::{CloneUrl, NostrUrlDecoded},
+        self, get_remote_name_by_url,
+        nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
         oid_to_shorthand_string,
     },
-    git_events::{self, event_to_cover_letter, get_event_root},
+    git_events::{
+        self, event_is_patch_set_root, event_is_revision_root, event_to_cover_letter,
+        format_event_for_dry_run, get_event_root,
+    },
     login::{self, get_curent_user, user::UserRef},
-    repo_ref::{self, get_repo_config_from_yaml},
+    repo_ref::{self, get_repo_config_from_yaml, get_viewer_url},
     repo_state,
 };
 use nostr::nips::nip10::Marker;
@@ -39,13 +41,17 @@ use repo_ref::RepoRef;
 use repo_state::RepoState;
 
 use crate::{
+    blossom::push_pack_to_blossom_servers,
     client::Client,
+    fetch::make_commits_for_proposal,
     git::Repo,
     list::list_from_remotes,
+    reporter::Reporter,
     utils::{
-        Direction, find_proposal_and_patches_by_branch_name, get_all_proposals,
-        get_remote_name_by_url, get_short_git_server_name, get_write_protocols_to_try,
-        join_with_and, push_error_is_not_authentication_failure, read_line,
+        Direction, HelperOptions, SshIdentity, credentials_callback,
+        find_proposal_and_patches_by_branch_name, get_all_proposals, get_git_servers_to_try,
+        get_short_git_server_name, get_write_protocols_to_try, join_with_and,
+        push_error_is_not_authentication_failure, read_line, report_credential_outcome,
         set_protocol_preference,
     },
 };
@@ -58,26 +64,30 @@ pub async fn run_push(
     initial_refspec: &str,
     client: &Client,
     list_outputs: Option<HashMap<String, HashMap<String, String>>>,
+    options: &HelperOptions,
 ) -> Result<()> {
+    let atomic = options.atomic;
     let refspecs = get_refspecs_from_push_batch(stdin, initial_refspec)?;
 
     let proposal_refspecs = refspecs
         .iter()
-        .filter(|r| r.contains("refs/heads/pr/"))
+        .filter(|r| is_proposal_refspec(r))
         .cloned()
         .collect::<Vec<String>>();
 
     let mut git_server_refspecs = refspecs
         .iter()
-        .filter(|r| !r.contains("refs/heads/pr/"))
+        .filter(|r| !is_proposal_refspec(r))
         .cloned()
         .collect::<Vec<String>>();
 
-    let term = console::Term::stderr();
+    let term = Reporter::from_options(options);
+
+    let git_servers = get_git_servers_to_try(git_repo, &repo_ref.git_server);
 
     let list_outputs = match list_outputs {
         Some(outputs) => outputs,
-        _ => list_from_remotes(&term, git_repo, &repo_ref.git_server, decoded_nostr_url),
+        _ => list_from_remotes(&term, git_repo, &git_servers, decoded_nostr_url).await,
     };
 
     let nostr_state = get_state_from_cache(Some(git_repo.get_path()?), repo_ref).await;
@@ -86,21 +96,23 @@ pub async fn run_push(
         // if no state events - create from first git server listed
         if let Ok(nostr_state) = &nostr_state {
             nostr_state.state.clone()
-        } else if let Some(url) = repo_ref
-            .git_server
+        } else if let Some(url) = git_servers
             .iter()
             .find(|&url| list_outputs.contains_key(url))
         {
             list_outputs.get(url).unwrap().to_owned()
+        } else if !repo_ref.blossoms.is_empty() {
+            // blossom only repository without a state event yet
+            HashMap::new()
         } else {
             bail!(
                 "failed to connect to git servers: {}",
-                repo_ref.git_server.join(" ")
+                git_servers.join(" ")
             );
         }
     };
 
-    let (rejected_refspecs, remote_refspecs) = create_rejected_refspecs_and_remotes_refspecs(
+    let (rejected_refspecs, mut remote_refspecs) = create_rejected_refspecs_and_remotes_refspecs(
         &term,
         git_repo,
         &git_server_refspecs,
@@ -108,6 +120,31 @@ pub async fn run_push(
         &list_outputs,
     )?;
 
+    if git_repo
+        .get_git_config_item("nostr.push-to-all-git-servers", Some(false))?
+        .is_some_and(|value| value == "false")
+    {
+        // only push to the first reachable server in order of preference
+        let first = git_servers
+            .iter()
+            .find(|url| remote_refspecs.contains_key(*url))
+            .cloned();
+        remote_refspecs.retain(|url, _| Some(url) == first.as_ref());
+    }
+
+    if atomic && !rejected_refspecs.is_empty() {
+        for refspec in &refspecs {
+            let (_, to) = refspec_to_from_to(refspec)?;
+            if let Some(rejected) = rejected_refspecs.get(refspec) {
+                println!("error {to} {} out of sync with nostr", rejected.join(" "));
+            } else {
+                println!("error {to} atomic push failed as other refs were rejected");
+            }
+        }
+        println!();
+        return Ok(());
+    }
+
     git_server_refspecs.retain(|refspec| {
         if let Some(rejected) = rejected_refspecs.get(&refspec.to_string()) {
             let (_, to) = refspec_to_from_to(refspec).unwrap();
@@ -118,13 +155,25 @@ pub async fn run_push(
         }
     });
 
+    if options.dry_run {
+        // a real push reaches the git servers and nostr state only to be
+        // rejected, so report it the way git would
+        for (refspec, reason) in
+            dry_run_rejected_refspecs(git_repo, &git_server_refspecs, &existing_state)?
+        {
+            let (_, to) = refspec_to_from_to(&refspec)?;
+            println!("error {to} {reason}");
+            git_server_refspecs.retain(|r| r != &refspec);
+        }
+    }
+
     if git_server_refspecs.is_empty() && proposal_refspecs.is_empty() {
         // all refspecs rejected
         println!();
         return Ok(());
     }
 
-    let (rejected_proposal_refspecs, rejected) = create_and_publish_events(
+    let (events, rejected_proposal_refspecs, user_ref) = match create_events(
         git_repo,
         repo_ref,
         decoded_nostr_url,
@@ -132,51 +181,224 @@ pub async fn run_push(
         &proposal_refspecs,
         client,
         existing_state,
+        &options.leases,
+        options.dry_run,
         &term,
     )
-    .await?;
+    .await?
+    {
+        Some(created) => created,
+        None => {
+            println!();
+            return Ok(());
+        }
+    };
 
-    if !rejected {
+    if options.dry_run {
+        report_dry_run(
+            git_repo,
+            &git_servers,
+            &remote_refspecs,
+            &git_server_refspecs,
+            &events,
+            &term,
+        )?;
         for refspec in &[git_server_refspecs.clone(), proposal_refspecs.clone()].concat() {
-            if rejected_proposal_refspecs.contains(refspec) {
-                continue;
+            if !rejected_proposal_refspecs.contains(refspec) {
+                let (_, to) = refspec_to_from_to(refspec)?;
+                println!("ok {to}");
             }
+        }
+        println!();
+        return Ok(());
+    }
+
+    if atomic && !rejected_proposal_refspecs.is_empty() {
+        for refspec in &refspecs {
             let (_, to) = refspec_to_from_to(refspec)?;
-            println!("ok {to}");
-            update_remote_refs_pushed(
-                &git_repo.git_repo,
-                refspec,
-                &decoded_nostr_url.original_string,
-            )
-            .context("could not update remote_ref locally")?;
+            if !rejected_proposal_refspecs.contains(refspec) {
+                println!("error {to} atomic push failed as other refs were rejected");
+            }
+        }
+        println!();
+        return Ok(());
+    }
+
+    // nostr events are only published once every git server has every ref
+    let pushed = if atomic {
+        match push_to_git_servers_atomically(
+            git_repo,
+            decoded_nostr_url,
+            &remote_refspecs,
+            &git_server_refspecs,
+            &list_outputs,
+            &term,
+        ) {
+            Ok(pushed) => pushed,
+            Err(error) => {
+                term.write_line(format!("push: {error}").as_str())?;
+                for refspec in &refspecs {
+                    let (_, to) = refspec_to_from_to(refspec)?;
+                    println!("error {to} atomic push failed");
+                }
+                println!();
+                return Ok(());
+            }
         }
+    } else {
+        vec![]
+    };
 
+    if let Err(error) = publish_events(client, git_repo, repo_ref, &user_ref, events, &term).await {
+        if !atomic {
+            return Err(error);
+        }
+        // the git servers mustn't be left ahead of nostr
+        term.write_line(format!("push: failed to publish to nostr: {error:#}").as_str())?;
+        roll_back_git_servers(git_repo, decoded_nostr_url, &pushed, &list_outputs, &term)?;
+        for refspec in &refspecs {
+            let (_, to) = refspec_to_from_to(refspec)?;
+            println!("error {to} atomic push failed");
+        }
+        println!();
+        return Ok(());
+    }
+
+    for refspec in &[git_server_refspecs.clone(), proposal_refspecs.clone()].concat() {
+        if rejected_proposal_refspecs.contains(refspec) {
+            continue;
+        }
+        let (_, to) = refspec_to_from_to(refspec)?;
+        println!("ok {to}");
+        if to.starts_with(NEW_PROPOSAL_REF_PREFIX) {
+            // each push to refs/for/<branch> is a new proposal so there is no
+            // remote ref to track
+            continue;
+        }
+        update_remote_refs_pushed(
+            &git_repo.git_repo,
+            refspec,
+            &decoded_nostr_url.original_string,
+        )
+        .context("could not update remote_ref locally")?;
+    }
+
+    if !atomic {
         // TODO make async - check gitlib2 callbacks work async
 
-        for (git_server_url, remote_refspecs) in remote_refspecs {
+        let mut results = vec![];
+        for git_server_url in &git_servers {
+            let short_name = get_short_git_server_name(git_repo, git_server_url);
+            let Some(remote_refspecs) = remote_refspecs.get(git_server_url) else {
+                if !list_outputs.contains_key(git_server_url) {
+                    results.push((short_name, Some("unreachable".to_string())));
+                }
+                continue;
+            };
             let remote_refspecs = remote_refspecs
                 .iter()
                 .filter(|refspec| git_server_refspecs.contains(refspec))
                 .cloned()
                 .collect::<Vec<String>>();
-            if !refspecs.is_empty() {
-                let _ = push_to_remote(
+            if !remote_refspecs.is_empty() {
+                let res = push_to_remote(
                     git_repo,
-                    &git_server_url,
+                    git_server_url,
                     decoded_nostr_url,
                     &remote_refspecs,
                     &term,
                 );
+                results.push((short_name, res.err().map(|_| "failed".to_string())));
             }
         }
+        if git_servers.len() > 1 {
+            term.set_section("push summary:", format_push_summary(&results))?;
+            term.finish_section("push summary:")?;
+        }
     }
 
     println!();
     Ok(())
 }
 
+/// refspecs that aren't forced and don't fast-forward `to` from its oid in
+/// `remote_state`, with the reason git reports for each: `fetch first` when
+/// the remote tip isn't available locally, otherwise `non-fast forward`
+fn dry_run_rejected_refspecs(
+    git_repo: &impl RepoActions,
+    refspecs: &[String],
+    remote_state: &HashMap<String, String>,
+) -> Result<Vec<(String, &'static str)>> {
+    let mut rejected = vec![];
+    for refspec in refspecs {
+        let (from, to) = refspec_to_from_to(refspec)?;
+        if refspec.starts_with('+') || from.is_empty() {
+            continue;
+        }
+        let Some(remote_tip) = remote_state.get(to).filter(|v| !v.starts_with("ref: ")) else {
+            continue;
+        };
+        let local_tip = git_repo.get_commit_or_tip_of_reference(from)?;
+        if local_tip.to_string() == *remote_tip {
+            continue;
+        }
+        if !git_repo.does_commit_exist(remote_tip)? {
+            rejected.push((refspec.clone(), "fetch first"));
+        } else if !git_repo.ancestor_of(&local_tip, &remote_tip.parse::<Sha1Hash>()?)? {
+            rejected.push((refspec.clone(), "non-fast forward"));
+        }
+    }
+    Ok(rejected)
+}
+
+/// list the git pushes and nostr events a push would make without making them
+fn report_dry_run(
+    git_repo: &Repo,
+    git_servers: &[String],
+    remote_refspecs: &HashMapUrlRefspecs,
+    git_server_refspecs: &[String],
+    events: &[Event],
+    term: &Reporter,
+) -> Result<()> {
+    for git_server_url in git_servers {
+        let Some(refspecs) = remote_refspecs.get(git_server_url) else {
+            continue;
+        };
+        let refspecs: Vec<&String> = refspecs
+            .iter()
+            .filter(|refspec| git_server_refspecs.contains(refspec))
+            .collect();
+        if refspecs.is_empty() {
+            continue;
+        }
+        term.write_line(&format!(
+            "dry run: would push to {}:",
+            get_short_git_server_name(git_repo, git_server_url)
+        ))?;
+        for refspec in refspecs {
+            term.write_line(&format!("  {refspec}"))?;
+        }
+    }
+    if !events.is_empty() {
+        term.write_line(&format!(
+            "dry run: would publish {} event{} to nostr relays:",
+            events.len(),
+            if events.len() == 1 { "" } else { "s" }
+        ))?;
+        for event in events {
+            for line in format_event_for_dry_run(event) {
+                term.write_line(&format!("  {line}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// returns nostr events to publish and rejected proposal refspecs, or None if
+/// the push is rejected because the user isn't a maintainer. with `dry_run`
+/// packfiles aren't uploaded to blossom servers
 #[allow(clippy::too_many_arguments)]
-async fn create_and_publish_events(
+async fn create_events(
     git_repo: &Repo,
     repo_ref: &RepoRef,
     decoded_nostr_url: &NostrUrlDecoded,
@@ -184,8 +406,10 @@ async fn create_and_publish_events(
     proposal_refspecs: &Vec<String>,
     client: &Client,
     existing_state: HashMap<String, String>,
-    term: &Term,
-) -> Result<(Vec<String>, bool)> {
+    leases: &HashMap<String, String>,
+    dry_run: bool,
+    term: &Reporter,
+) -> Result<Option<(Vec<Event>, Vec<String>, UserRef)>> {
     let (signer, user_ref, _) =
         login::login_or_signup(&Some(git_repo), &None, &None, Some(client), true).await?;
 
@@ -198,7 +422,7 @@ async fn create_and_publish_events(
             );
         }
         if proposal_refspecs.is_empty() {
-            return Ok((vec![], true));
+            return Ok(None);
         }
     }
 
@@ -215,8 +439,30 @@ async fn create_and_publish_events(
             };
 
         if store_state {
+            let packs = if repo_ref.blossoms.is_empty() {
+                vec![]
+            } else if dry_run {
+                term.write_line(&format!(
+                    "dry run: would upload a packfile to {}",
+                    repo_ref.blossoms.join(" ")
+                ))?;
+                get_state_from_cache(Some(git_repo.get_path()?), repo_ref)
+                    .await
+                    .map(|state| state.packs)
+                    .unwrap_or_default()
+            } else {
+                push_pack_to_blossom_servers(
+                    git_repo,
+                    repo_ref,
+                    &existing_state,
+                    &new_state,
+                    &signer,
+                    term,
+                )
+                .await?
+            };
             let new_repo_state =
-                RepoState::build(repo_ref.identifier.clone(), new_state, &signer).await?;
+                RepoState::build(repo_ref.identifier.clone(), new_state, packs, &signer).await?;
             events.push(new_repo_state.event);
         }
 
@@ -251,6 +497,8 @@ async fn create_and_publish_events(
         git_repo,
         repo_ref,
         proposal_refspecs,
+        &existing_state,
+        leases,
         &user_ref,
         &signer,
         term,
@@ -259,10 +507,25 @@ async fn create_and_publish_events(
     for e in proposal_events {
         events.push(e);
     }
+    Ok(Some((events, rejected_proposal_refspecs, user_ref)))
+}
 
+async fn publish_events(
+    client: &Client,
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    user_ref: &UserRef,
+    events: Vec<Event>,
+    term: &Reporter,
+) -> Result<()> {
     // TODO check whether tip of each branch pushed is on at least one git server
     // before broadcasting the nostr state
     if !events.is_empty() {
+        let new_proposals: Vec<EventId> = events
+            .iter()
+            .filter(|e| event_is_patch_set_root(e) && !event_is_revision_root(e))
+            .map(|e| e.id)
+            .collect();
         term.write_line("broadcast to nostr relays:")?;
         send_events(
             client,
@@ -274,8 +537,14 @@ async fn create_and_publish_events(
             false,
         )
         .await?;
+        let viewer_url = get_viewer_url(&Some(git_repo));
+        for id in new_proposals {
+            let (web, _, uri) = repo_ref.event_links(&viewer_url, &id, Kind::GitPatch)?;
+            term.write_line(&format!("view proposal: {web}"))?;
+            term.write_line(&format!("share with: {uri}"))?;
+        }
     }
-    Ok((rejected_proposal_refspecs, false))
+    Ok(())
 }
 
 #[allow(clippy::too_many_lines)]
@@ -283,9 +552,11 @@ async fn process_proposal_refspecs(
     git_repo: &Repo,
     repo_ref: &RepoRef,
     proposal_refspecs: &Vec<String>,
+    existing_state: &HashMap<String, String>,
+    leases: &HashMap<String, String>,
     user_ref: &UserRef,
     signer: &Arc<dyn NostrSigner>,
-    term: &Term,
+    term: &Reporter,
 ) -> Result<(Vec<Event>, Vec<String>)> {
     let mut events = vec![];
     let mut rejected_proposal_refspecs = vec![];
@@ -299,7 +570,41 @@ async fn process_proposal_refspecs(
         let (from, to) = refspec_to_from_to(refspec).unwrap();
         let tip_of_pushed_branch = git_repo.get_commit_or_tip_of_reference(from)?;
 
-        if let Some((_, (proposal, patches))) =
+        if let Some(target_branch) = to.strip_prefix(NEW_PROPOSAL_REF_PREFIX) {
+            let Some(target_tip) = existing_state
+                .get(&format!("refs/heads/{target_branch}"))
+                .and_then(|oid| git_repo.get_commit_or_tip_of_reference(oid).ok())
+            else {
+                println!("error {to} branch {target_branch} not found in repository state");
+                rejected_proposal_refspecs.push(refspec.to_string());
+                continue;
+            };
+            let (mut ahead, _) =
+                git_repo.get_commits_ahead_behind(&target_tip, &tip_of_pushed_branch)?;
+            if ahead.is_empty() {
+                println!("error {to} no new commits on top of {target_branch}");
+                rejected_proposal_refspecs.push(refspec.to_string());
+                continue;
+            }
+            ahead.reverse();
+            let commit_messages = ahead
+                .iter()
+                .map(|commit| git_repo.get_commit_message(commit))
+                .collect::<Result<Vec<String>>>()?;
+            for patch in generate_cover_letter_and_patch_events(
+                Some(cover_letter_from_commit_messages(&commit_messages)),
+                git_repo,
+                &ahead,
+                signer,
+                repo_ref,
+                &None,
+                &[],
+            )
+            .await?
+            {
+                events.push(patch);
+            }
+        } else if let Some((_, (proposal, patches))) =
             find_proposal_and_patches_by_branch_name(to, &all_proposals, current_user.as_ref())
         {
             if [repo_ref.maintainers.clone(), vec![proposal.pubkey]]
@@ -308,6 +613,22 @@ async fn process_proposal_refspecs(
             {
                 if refspec.starts_with('+') {
                     // force push
+                    if let Some(expected) = leases.get(to) {
+                        // the tip `list` advertised, which git compares the lease with
+                        let tip_of_proposal =
+                            make_commits_for_proposal(git_repo, repo_ref, patches, None)?;
+                        if !lease_matches(expected, &tip_of_proposal) {
+                            term.write_line(
+                                format!(
+                                    "WARNING: new patches were added to proposal {to} since it was last fetched"
+                                )
+                                .as_str(),
+                            )?;
+                            println!("error {to} stale info");
+                            rejected_proposal_refspecs.push(refspec.to_string());
+                            continue;
+                        }
+                    }
                     let (_, main_tip) = git_repo.get_main_or_master_branch()?;
                     let (mut ahead, _) =
                         git_repo.get_commits_ahead_behind(&main_tip, &tip_of_pushed_branch)?;
@@ -341,30 +662,20 @@ async fn process_proposal_refspecs(
                             // tip patch is the root proposal
                             tip_patch.id
                         };
-                        let mut parent_patch = tip_patch.clone();
                         ahead.reverse();
-                        for (i, commit) in ahead.iter().enumerate() {
-                            let new_patch = generate_patch_event(
+                        events.extend(
+                            generate_patch_events_continuing_proposal(
                                 git_repo,
-                                &git_repo.get_root_commit()?,
-                                commit,
-                                Some(thread_id),
+                                &ahead,
+                                thread_id,
+                                tip_patch.id,
+                                (patches.len() + 1).try_into()?,
+                                (patches.len() + ahead.len()).try_into()?,
                                 signer,
                                 repo_ref,
-                                Some(parent_patch.id),
-                                Some((
-                                    (patches.len() + i + 1).try_into().unwrap(),
-                                    (patches.len() + ahead.len()).try_into().unwrap(),
-                                )),
-                                None,
-                                &None,
-                                &[],
                             )
-                            .await
-                            .context("failed to make patch event from commit")?;
-                            events.push(new_patch.clone());
-                            parent_patch = new_patch;
-                        }
+                            .await?,
+                        );
                     } else {
                         // we shouldn't get here
                         term.write_line(
@@ -411,43 +722,63 @@ async fn process_proposal_refspecs(
     Ok((events, rejected_proposal_refspecs))
 }
 
+/// returns how many ref updates the git server rejected. they are reported
+/// as they happen
 fn push_to_remote(
     git_repo: &Repo,
     git_server_url: &str,
     decoded_nostr_url: &NostrUrlDecoded,
     remote_refspecs: &[String],
-    term: &Term,
-) -> Result<()> {
+    term: &Reporter,
+) -> Result<usize> {
     let server_url = git_server_url.parse::<CloneUrl>()?;
     let protocols_to_attempt = get_write_protocols_to_try(git_repo, &server_url, decoded_nostr_url);
 
+    let ssh_identity = SshIdentity::for_nostr_url(git_repo, decoded_nostr_url)?;
+
     let mut failed_protocols = vec![];
-    let mut success = false;
+    let mut rejected = None;
 
     for protocol in &protocols_to_attempt {
-        term.write_line(format!("push: {} over {protocol}...", server_url.short_name(),).as_str())?;
+        let title = format!("push: {} over {protocol}...", server_url.short_name());
+        term.set_section(&title, vec![])?;
 
         let formatted_url = server_url.format_as(protocol, &decoded_nostr_url.user)?;
+        let _span = tracing::debug_span!("git_push", url = %formatted_url, %protocol).entered();
 
-        if let Err(error) = push_to_remote_url(git_repo, &formatted_url, remote_refspecs, term) {
-            term.write_line(
-                format!("push: {formatted_url} failed over {protocol}: {error}").as_str(),
-            )?;
-            failed_protocols.push(protocol);
-            if push_error_is_not_authentication_failure(&error) {
-                break;
+        let res = push_to_remote_url(
+            git_repo,
+            &formatted_url,
+            remote_refspecs,
+            &ssh_identity,
+            term,
+            &title,
+        );
+        term.finish_section(&title)?;
+        match res {
+            Err(error) => {
+                tracing::debug!("failed: {error:#}");
+                term.write_line(
+                    format!("push: {formatted_url} failed over {protocol}: {error}").as_str(),
+                )?;
+                failed_protocols.push(protocol);
+                if push_error_is_not_authentication_failure(&error) {
+                    break;
+                }
             }
-        } else {
-            success = true;
-            if !failed_protocols.is_empty() {
-                term.write_line(format!("push: succeeded over {protocol}").as_str())?;
-                let _ = set_protocol_preference(git_repo, protocol, &server_url, &Direction::Push);
+            Ok(rejected_updates) => {
+                rejected = Some(rejected_updates);
+                if !failed_protocols.is_empty() {
+                    term.write_line(format!("push: succeeded over {protocol}").as_str())?;
+                    let _ =
+                        set_protocol_preference(git_repo, protocol, &server_url, &Direction::Push);
+                }
+                break;
             }
-            break;
         }
     }
-    if success {
-        Ok(())
+    if let Some(rejected) = rejected {
+        Ok(rejected)
     } else {
         let error = anyhow!(
             "{} failed over {}{}",
@@ -468,28 +799,38 @@ fn push_to_remote_url(
     git_repo: &Repo,
     git_server_url: &str,
     remote_refspecs: &[String],
-    term: &Term,
-) -> Result<()> {
+    ssh_identity: &SshIdentity,
+    term: &Reporter,
+    title: &str,
+) -> Result<usize> {
+    if git_server_url.parse::<CloneUrl>()?.protocol() == ServerProtocol::Ssh {
+        ssh_identity.check_available()?;
+    }
     let git_config = git_repo.git_repo.config()?;
     let mut git_server_remote = git_repo.git_repo.remote_anonymous(git_server_url)?;
     let auth = GitAuthenticator::default();
+    let used_credential = Mutex::new(None);
     let mut push_options = git2::PushOptions::new();
     let mut remote_callbacks = git2::RemoteCallbacks::new();
-    let push_reporter = Arc::new(Mutex::new(PushReporter::new(term)));
+    let push_reporter = Arc::new(Mutex::new(PushReporter::new(term, title)));
 
-    remote_callbacks.credentials(auth.credentials(&git_config));
+    remote_callbacks.credentials(credentials_callback(
+        &auth,
+        &git_config,
+        ssh_identity.key.as_deref(),
+        &used_credential,
+    ));
 
     remote_callbacks.push_update_reference({
         let push_reporter = Arc::clone(&push_reporter);
         move |name, error| {
             let mut reporter = push_reporter.lock().unwrap();
             if let Some(error) = error {
-                let existing_lines = reporter.count_all_existing_lines();
                 reporter.update_reference_errors.push(format!(
                     "WARNING: {} failed to push {name} error: {error}",
                     get_short_git_server_name(git_repo, git_server_url),
                 ));
-                reporter.write_all(existing_lines);
+                reporter.write_all();
             }
             Ok(())
         }
@@ -499,8 +840,6 @@ fn push_to_remote_url(
         let push_reporter = Arc::clone(&push_reporter);
         move |updates| {
             let mut reporter = push_reporter.lock().unwrap();
-            let existing_lines = reporter.count_all_existing_lines();
-
             for update in updates {
                 let dst_refname = update
                     .dst_refname()
@@ -541,7 +880,7 @@ fn push_to_remote_url(
                 // ! [rejected]        <branch-name> -> <branch-name> (non-fast-forward)
                 reporter.negotiation.push(msg);
             }
-            reporter.write_all(existing_lines);
+            reporter.write_all();
             Ok(())
         }
     });
@@ -564,11 +903,176 @@ fn push_to_remote_url(
         }
     });
     push_options.remote_callbacks(remote_callbacks);
-    git_server_remote.push(remote_refspecs, Some(&mut push_options))?;
+    let res = git_server_remote.push(remote_refspecs, Some(&mut push_options));
+    report_credential_outcome(&used_credential, &res);
+    res?;
     let _ = git_server_remote.disconnect();
+    let rejected = push_reporter.lock().unwrap().update_reference_errors.len();
+    Ok(rejected)
+}
+
+/// push to every git server, restoring refs on servers that accepted the push
+/// if any server fails so they stay consistent with nostr. returns the
+/// refspecs pushed to each server
+fn push_to_git_servers_atomically<'a>(
+    git_repo: &Repo,
+    decoded_nostr_url: &NostrUrlDecoded,
+    remote_refspecs: &'a HashMapUrlRefspecs,
+    git_server_refspecs: &[String],
+    list_outputs: &HashMap<String, HashMap<String, String>>,
+    term: &Reporter,
+) -> Result<Vec<(&'a String, Vec<String>)>> {
+    let mut pushed = vec![];
+    for (git_server_url, refspecs) in remote_refspecs {
+        let refspecs = refspecs
+            .iter()
+            .filter(|refspec| git_server_refspecs.contains(refspec))
+            .cloned()
+            .collect::<Vec<String>>();
+        if refspecs.is_empty() {
+            continue;
+        }
+        let res = push_to_remote(git_repo, git_server_url, decoded_nostr_url, &refspecs, term)
+            .and_then(|rejected| {
+                if rejected == 0 {
+                    Ok(())
+                } else {
+                    // some refs may have been updated
+                    pushed.push((git_server_url, refspecs.clone()));
+                    Err(anyhow!(
+                        "{} rejected {rejected} ref update(s)",
+                        get_short_git_server_name(git_repo, git_server_url)
+                    ))
+                }
+            });
+        if let Err(error) = res {
+            roll_back_git_servers(git_repo, decoded_nostr_url, &pushed, list_outputs, term)?;
+            return Err(error);
+        }
+        pushed.push((git_server_url, refspecs));
+    }
+    Ok(pushed)
+}
+
+/// restore the refs updated by `pushed` refspecs to their state in
+/// `list_outputs`, warning about servers that couldn't be restored
+fn roll_back_git_servers(
+    git_repo: &Repo,
+    decoded_nostr_url: &NostrUrlDecoded,
+    pushed: &[(&String, Vec<String>)],
+    list_outputs: &HashMap<String, HashMap<String, String>>,
+    term: &Reporter,
+) -> Result<()> {
+    for (git_server_url, refspecs) in pushed {
+        let previous_state = list_outputs
+            .get(*git_server_url)
+            .cloned()
+            .unwrap_or_default();
+        let res = roll_back_git_server(
+            git_repo,
+            git_server_url,
+            decoded_nostr_url,
+            refspecs,
+            &previous_state,
+            term,
+        );
+        if !matches!(res, Ok(0)) {
+            if let Err(error) = &res {
+                tracing::debug!(url = %git_server_url, "rollback failed: {error:#}");
+            }
+            term.write_line(
+                format!(
+                    "WARNING: failed to roll back {}",
+                    get_short_git_server_name(git_repo, git_server_url)
+                )
+                .as_str(),
+            )?;
+        }
+    }
     Ok(())
 }
 
+/// libgit2 can only push refs, so previous oids are pushed from temporary
+/// local refs under this prefix
+static ROLLBACK_REF_PREFIX: &str = "refs/nostr-rollback/";
+
+fn roll_back_git_server(
+    git_repo: &Repo,
+    git_server_url: &str,
+    decoded_nostr_url: &NostrUrlDecoded,
+    refspecs: &[String],
+    previous_state: &HashMap<String, String>,
+    term: &Reporter,
+) -> Result<usize> {
+    let mut rollback_refs = vec![];
+    for refspec in refspecs {
+        let (_, to) = refspec_to_from_to(refspec)?;
+        if let Some(oid) = previous_state.get(to) {
+            let rollback_ref = git_repo.git_repo.reference(
+                &rollback_ref_name(to),
+                Oid::from_str(oid)?,
+                true,
+                "ngit atomic push rollback",
+            )?;
+            rollback_refs.push(rollback_ref);
+        }
+    }
+    let res = push_to_remote(
+        git_repo,
+        git_server_url,
+        decoded_nostr_url,
+        &rollback_refspecs(refspecs, previous_state)?,
+        term,
+    );
+    for mut rollback_ref in rollback_refs {
+        let _ = rollback_ref.delete();
+    }
+    res
+}
+
+fn rollback_ref_name(to: &str) -> String {
+    format!(
+        "{ROLLBACK_REF_PREFIX}{}",
+        to.strip_prefix("refs/").unwrap_or(to)
+    )
+}
+
+/// a line per git server with `None` for success or the reason it failed
+fn format_push_summary(results: &[(String, Option<String>)]) -> Vec<String> {
+    let width = results
+        .iter()
+        .map(|(short_name, _)| short_name.len())
+        .max()
+        .unwrap_or_default();
+    results
+        .iter()
+        .map(|(short_name, failure)| {
+            format!(
+                "  {short_name:<width$} {}",
+                failure.as_deref().unwrap_or("ok")
+            )
+        })
+        .collect()
+}
+
+/// refspecs that restore refs updated by `refspecs` to `previous_state`
+fn rollback_refspecs(
+    refspecs: &[String],
+    previous_state: &HashMap<String, String>,
+) -> Result<Vec<String>> {
+    refspecs
+        .iter()
+        .map(|refspec| {
+            let (_, to) = refspec_to_from_to(refspec)?;
+            Ok(if previous_state.contains_key(to) {
+                format!("+{}:{to}", rollback_ref_name(to))
+            } else {
+                format!(":{to}")
+            })
+        })
+        .collect()
+}
+
 #[allow(clippy::cast_precision_loss)]
 #[allow(clippy::float_cmp)]
 #[allow(clippy::needless_pass_by_value)]
@@ -615,44 +1119,35 @@ struct PushReporter<'a> {
     negotiation: Vec<String>,
     transfer_progress_msgs: Vec<String>,
     update_reference_errors: Vec<String>,
-    term: &'a console::Term,
+    term: &'a Reporter,
+    title: String,
     start_time: Option<Instant>,
     end_time: Option<Instant>,
 }
 impl<'a> PushReporter<'a> {
-    fn new(term: &'a console::Term) -> Self {
+    fn new(term: &'a Reporter, title: &str) -> Self {
         Self {
             remote_msgs: vec![],
             negotiation: vec![],
             transfer_progress_msgs: vec![],
             update_reference_errors: vec![],
             term,
+            title: title.to_string(),
             start_time: None,
             end_time: None,
         }
     }
-    fn write_all(&self, lines_to_clear: usize) {
-        let _ = self.term.clear_last_lines(lines_to_clear);
-        for msg in &self.remote_msgs {
-            let _ = self.term.write_line(format!("remote: {msg}").as_str());
-        }
-        for msg in &self.negotiation {
-            let _ = self.term.write_line(msg);
-        }
-        for msg in &self.transfer_progress_msgs {
-            let _ = self.term.write_line(msg);
-        }
-        for msg in &self.update_reference_errors {
-            let _ = self.term.write_line(msg);
-        }
-    }
-
-    fn count_all_existing_lines(&self) -> usize {
-        let width = self.term.size().1;
-        count_lines_per_msg_vec(width, &self.remote_msgs, "remote: ".len())
-            + count_lines_per_msg_vec(width, &self.negotiation, 0)
-            + count_lines_per_msg_vec(width, &self.transfer_progress_msgs, 0)
-            + count_lines_per_msg_vec(width, &self.update_reference_errors, 0)
+    fn write_all(&self) {
+        let _ = self.term.set_section(
+            &self.title,
+            self.remote_msgs
+                .iter()
+                .map(|msg| format!("remote: {msg}"))
+                .chain(self.negotiation.iter().cloned())
+                .chain(self.transfer_progress_msgs.iter().cloned())
+                .chain(self.update_reference_errors.iter().cloned())
+                .collect(),
+        );
     }
     fn process_remote_msg(&mut self, data: &[u8]) {
         if let Ok(data) = str::from_utf8(data) {
@@ -662,7 +1157,6 @@ impl<'a> PushReporter<'a> {
                 .filter(|line| !line.trim().is_empty())
                 .collect::<Vec<&str>>();
             for data in data {
-                let existing_lines = self.count_all_existing_lines();
                 let msg = data.to_string();
                 if let Some(last) = self.remote_msgs.last() {
                     if (last.contains('%') && !last.contains("100%"))
@@ -676,7 +1170,7 @@ impl<'a> PushReporter<'a> {
                 } else {
                     self.remote_msgs.push(msg);
                 }
-                self.write_all(existing_lines);
+                self.write_all();
             }
         }
     }
@@ -691,12 +1185,11 @@ impl<'a> PushReporter<'a> {
             &self.start_time.unwrap(),
             self.end_time.as_ref(),
         ) {
-            let existing_lines = self.count_all_existing_lines();
             if report.contains("100%") {
                 self.end_time = Some(Instant::now());
             }
             self.transfer_progress_msgs = vec![report];
-            self.write_all(existing_lines);
+            self.write_all();
         }
     }
 }
@@ -705,8 +1198,8 @@ type HashMapUrlRefspecs = HashMap<String, Vec<String>>;
 
 #[allow(clippy::too_many_lines)]
 fn create_rejected_refspecs_and_remotes_refspecs(
-    term: &console::Term,
-    git_repo: &Repo,
+    term: &Reporter,
+    git_repo: &impl RepoActions,
     refspecs: &Vec<String>,
     nostr_state: &HashMap<String, String>,
     list_outputs: &HashMap<String, HashMap<String, String>>,
@@ -897,31 +1390,30 @@ fn generate_updated_state(
                 new_state.remove(&format!("{to}{}", "^{}"));
             }
         } else if to.contains("refs/tags") {
-            new_state.insert(
-                format!("{to}{}", "^{}"),
-                git_repo
-                    .get_commit_or_tip_of_reference(from)
-                    .unwrap()
-                    .to_string(),
-            );
-            new_state.insert(
-                to.to_string(),
-                git_repo
-                    .git_repo
-                    .find_reference(to)
-                    .unwrap()
-                    .peel(git2::ObjectType::Tag)
-                    .unwrap()
-                    .id()
-                    .to_string(),
-            );
+            let commit = git_repo
+                .get_commit_or_tip_of_reference(from)
+                .context(format!("failed to get commit of {from}"))?
+                .to_string();
+            let tag = git_repo
+                .git_repo
+                .revparse_single(from)
+                .context(format!("failed to find {from}"))?;
+            if tag.kind() == Some(git2::ObjectType::Tag) {
+                // annotated tag
+                new_state.insert(to.to_string(), tag.id().to_string());
+                new_state.insert(format!("{to}{}", "^{}"), commit);
+            } else {
+                // lightweight tag
+                new_state.insert(to.to_string(), commit);
+                new_state.remove(&format!("{to}{}", "^{}"));
+            }
         } else {
             // add or update
             new_state.insert(
                 to.to_string(),
                 git_repo
                     .get_commit_or_tip_of_reference(from)
-                    .unwrap()
+                    .context(format!("failed to get commit of {from}"))?
                     .to_string(),
             );
         }
@@ -930,7 +1422,7 @@ fn generate_updated_state(
 }
 
 async fn get_maintainers_yaml_update(
-    term: &console::Term,
+    term: &Reporter,
     decoded_nostr_url: &NostrUrlDecoded,
     repo_ref: &RepoRef,
     git_repo: &Repo,
@@ -999,7 +1491,7 @@ async fn get_maintainers_yaml_update(
 }
 
 async fn get_merged_status_events(
-    term: &console::Term,
+    term: &Reporter,
     decoded_nostr_url: &NostrUrlDecoded,
     repo_ref: &RepoRef,
     git_repo: &Repo,
@@ -1161,7 +1653,7 @@ fn get_patch_author(event: &Event) -> Result<Vec<String>> {
 }
 
 async fn create_merge_events(
-    term: &console::Term,
+    term: &Reporter,
     git_repo: &Repo,
     repo_ref: &RepoRef,
     signer: &Arc<dyn NostrSigner>,
@@ -1436,6 +1928,40 @@ fn update_remote_refs_pushed(
     Ok(())
 }
 
+/// whether the tip expected by `--force-with-lease` is the tip of the
+/// proposal branch as advertised to git. an empty expectation means the ref
+/// shouldn't exist yet.
+fn lease_matches(expected: &str, current_tip: &str) -> bool {
+    !expected.is_empty() && current_tip.starts_with(expected)
+}
+
+/// pushing to `refs/for/<branch>` opens a new proposal against `<branch>`
+static NEW_PROPOSAL_REF_PREFIX: &str = "refs/for/";
+
+fn is_proposal_refspec(refspec: &str) -> bool {
+    refspec.contains("refs/heads/pr/") || refspec.contains(&format!(":{NEW_PROPOSAL_REF_PREFIX}"))
+}
+
+/// cover letter title and description for commits, oldest first. titled
+/// after the first commit and listing every commit if there are several.
+fn cover_letter_from_commit_messages(commit_messages: &[String]) -> (String, String) {
+    let summary = |message: &String| message.lines().next().unwrap_or_default().to_string();
+    let title = commit_messages.first().map(summary).unwrap_or_default();
+    let description = if let [message] = commit_messages {
+        message
+            .split_once('\n')
+            .map(|(_, body)| body.trim().to_string())
+            .unwrap_or_default()
+    } else {
+        commit_messages
+            .iter()
+            .map(|message| format!("- {}", summary(message)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    (title, description)
+}
+
 fn refspec_to_from_to(refspec: &str) -> Result<(&str, &str)> {
     if !refspec.contains(':') {
         bail!(
@@ -1518,6 +2044,7 @@ trait BuildRepoState {
     async fn build(
         identifier: String,
         state: HashMap<String, String>,
+        packs: Vec<String>,
         signer: &Arc<dyn NostrSigner>,
     ) -> Result<RepoState>;
 }
@@ -1525,6 +2052,7 @@ impl BuildRepoState for RepoState {
     async fn build(
         identifier: String,
         state: HashMap<String, String>,
+        packs: Vec<String>,
         signer: &Arc<dyn NostrSigner>,
     ) -> Result<RepoState> {
         let mut tags = vec![Tag::identifier(identifier.clone())];
@@ -1533,10 +2061,17 @@ impl BuildRepoState for RepoState {
                 value.clone(),
             ]));
         }
+        for hash in &packs {
+            tags.push(Tag::custom(
+                nostr_sdk::TagKind::Custom(std::borrow::Cow::Borrowed("pack")),
+                vec![hash.clone()],
+            ));
+        }
         let event = sign_event(EventBuilder::new(STATE_KIND, "").tags(tags), signer).await?;
         Ok(RepoState {
             identifier,
             state,
+            packs,
             event,
         })
     }
@@ -1555,4 +2090,334 @@ mod tests {
             assert_eq!(from, "testing");
         }
     }
+
+    mod cover_letter_from_commit_messages {
+        use super::*;
+
+        #[test]
+        fn single_commit_uses_summary_and_body() {
+            assert_eq!(
+                cover_letter_from_commit_messages(
+                    &["fix: typo\n\ncorrects spelling\n".to_string()]
+                ),
+                ("fix: typo".to_string(), "corrects spelling".to_string())
+            );
+        }
+
+        #[test]
+        fn multiple_commits_are_listed() {
+            assert_eq!(
+                cover_letter_from_commit_messages(&[
+                    "feat: add a\n\ndetail".to_string(),
+                    "feat: add b".to_string(),
+                ]),
+                (
+                    "feat: add a".to_string(),
+                    "- feat: add a\n- feat: add b".to_string()
+                )
+            );
+        }
+    }
+
+    mod lease_matches {
+        use super::*;
+
+        #[test]
+        fn matches_current_tip() {
+            assert!(lease_matches(
+                "431b84edc0d2fa118d63faa3c2db9c73d630a5ae",
+                "431b84edc0d2fa118d63faa3c2db9c73d630a5ae"
+            ));
+        }
+
+        #[test]
+        fn rejects_stale_or_empty_expectation() {
+            let tip = "431b84edc0d2fa118d63faa3c2db9c73d630a5ae";
+            assert!(!lease_matches(
+                "fe8ab1b0da5b8a8d4e3e2e3c2bb1db2f0b3a9c11",
+                tip
+            ));
+            assert!(!lease_matches("", tip));
+        }
+    }
+
+    mod generate_updated_state {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        #[test]
+        fn lightweight_and_annotated_tags() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let tip = test_repo.populate()?;
+            let commit = test_repo.git_repo.find_commit(tip)?;
+            test_repo
+                .git_repo
+                .tag_lightweight("v1.0.0", commit.as_object(), false)?;
+            let tag_oid = test_repo.git_repo.tag(
+                "v1.1.0",
+                commit.as_object(),
+                &test_utils::git::joe_signature(),
+                "release",
+                false,
+            )?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            let state = generate_updated_state(
+                &git_repo,
+                &HashMap::new(),
+                &vec![
+                    "refs/tags/v1.0.0:refs/tags/v1.0.0".to_string(),
+                    "refs/tags/v1.1.0:refs/tags/v1.1.0".to_string(),
+                ],
+            )?;
+
+            assert_eq!(state.get("refs/tags/v1.0.0"), Some(&tip.to_string()));
+            assert_eq!(state.get("refs/tags/v1.0.0^{}"), None);
+            assert_eq!(state.get("refs/tags/v1.1.0"), Some(&tag_oid.to_string()));
+            assert_eq!(state.get("refs/tags/v1.1.0^{}"), Some(&tip.to_string()));
+            Ok(())
+        }
+    }
+
+    mod format_push_summary {
+        use super::*;
+
+        #[test]
+        fn aligns_outcomes() {
+            assert_eq!(
+                format_push_summary(&[
+                    ("github.com/a/repo".to_string(), None),
+                    (
+                        "codeberg.org/a/repo".to_string(),
+                        Some("failed".to_string())
+                    ),
+                ]),
+                vec!["  github.com/a/repo   ok", "  codeberg.org/a/repo failed"]
+            );
+        }
+    }
+
+    mod rollback_refspecs {
+        use super::*;
+
+        #[test]
+        fn restores_previous_oid_or_deletes_new_ref() -> Result<()> {
+            let previous_state = HashMap::from([(
+                "refs/heads/main".to_string(),
+                "431b84edc0d2fa118d63faa3c2db9c73d630a5ae".to_string(),
+            )]);
+            assert_eq!(
+                rollback_refspecs(
+                    &[
+                        "refs/heads/main:refs/heads/main".to_string(),
+                        "refs/tags/v1.2.3:refs/tags/v1.2.3".to_string(),
+                    ],
+                    &previous_state,
+                )?,
+                vec![
+                    "+refs/nostr-rollback/heads/main:refs/heads/main",
+                    ":refs/tags/v1.2.3",
+                ]
+            );
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "memory-repo")]
+    mod create_rejected_refspecs_and_remotes_refspecs {
+        use ngit::git::memory::MemoryRepo;
+
+        use super::*;
+
+        static URL: &str = "https://example.com/repo.git";
+        static REFSPEC: &str = "refs/heads/main:refs/heads/main";
+
+        fn run(
+            git_repo: &MemoryRepo,
+            nostr_tip: &Sha1Hash,
+            remote_tip: &Sha1Hash,
+        ) -> Result<(HashMapUrlRefspecs, HashMapUrlRefspecs)> {
+            create_rejected_refspecs_and_remotes_refspecs(
+                &Reporter::new(console::Term::stdout()),
+                git_repo,
+                &vec![REFSPEC.to_string()],
+                &HashMap::from([("refs/heads/main".to_string(), nostr_tip.to_string())]),
+                &HashMap::from([(
+                    URL.to_string(),
+                    HashMap::from([("refs/heads/main".to_string(), remote_tip.to_string())]),
+                )]),
+            )
+        }
+
+        #[test]
+        fn remote_in_sync_with_nostr_is_pushed_to() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let nostr_tip = git_repo.commit("main", "initial commit");
+            git_repo.commit("main", "new commit");
+
+            let (rejected, remote_refspecs) = run(&git_repo, &nostr_tip, &nostr_tip)?;
+            assert!(rejected.is_empty());
+            assert_eq!(remote_refspecs.get(URL), Some(&vec![REFSPEC.to_string()]));
+            Ok(())
+        }
+
+        #[test]
+        fn remote_in_sync_with_nostr_is_force_pushed_to_when_local_diverges() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let base = git_repo.commit("main", "initial commit");
+            git_repo.create_branch_at_commit("amended", &base.to_string())?;
+            let nostr_tip = git_repo.commit("amended", "original commit");
+            git_repo.checkout("main")?;
+            git_repo.commit("main", "amended commit");
+
+            let (rejected, remote_refspecs) = run(&git_repo, &nostr_tip, &nostr_tip)?;
+            assert!(rejected.is_empty());
+            assert_eq!(remote_refspecs.get(URL), Some(&vec![format!("+{REFSPEC}")]));
+            Ok(())
+        }
+
+        #[test]
+        fn remote_with_commits_missing_from_nostr_and_local_is_rejected() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let nostr_tip = git_repo.commit("main", "initial commit");
+            git_repo.create_branch_at_commit("remote", &nostr_tip.to_string())?;
+            let remote_tip = git_repo.commit("remote", "commit only on git server");
+            git_repo.checkout("main")?;
+            git_repo.commit("main", "new commit");
+
+            let (rejected, remote_refspecs) = run(&git_repo, &nostr_tip, &remote_tip)?;
+            assert_eq!(rejected.get(REFSPEC), Some(&vec![URL.to_string()]));
+            assert!(!remote_refspecs.contains_key(URL));
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "memory-repo")]
+    mod dry_run_rejected_refspecs {
+        use ngit::git::memory::MemoryRepo;
+
+        use super::*;
+
+        static REFSPEC: &str = "refs/heads/main:refs/heads/main";
+
+        fn run(
+            git_repo: &MemoryRepo,
+            refspec: &str,
+            remote_tip: &str,
+        ) -> Result<Vec<&'static str>> {
+            Ok(dry_run_rejected_refspecs(
+                git_repo,
+                &[refspec.to_string()],
+                &HashMap::from([("refs/heads/main".to_string(), remote_tip.to_string())]),
+            )?
+            .into_iter()
+            .map(|(_, reason)| reason)
+            .collect())
+        }
+
+        #[test]
+        fn fast_forward_and_up_to_date_are_accepted() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let remote_tip = git_repo.commit("main", "initial commit");
+            assert!(run(&git_repo, REFSPEC, &remote_tip.to_string())?.is_empty());
+            git_repo.commit("main", "new commit");
+            assert!(run(&git_repo, REFSPEC, &remote_tip.to_string())?.is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn diverged_is_non_fast_forward_unless_forced() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let base = git_repo.commit("main", "initial commit");
+            git_repo.create_branch_at_commit("remote", &base.to_string())?;
+            let remote_tip = git_repo.commit("remote", "commit on remote");
+            git_repo.checkout("main")?;
+            git_repo.commit("main", "local commit");
+            assert_eq!(
+                run(&git_repo, REFSPEC, &remote_tip.to_string())?,
+                vec!["non-fast forward"]
+            );
+            assert!(run(&git_repo, &format!("+{REFSPEC}"), &remote_tip.to_string())?.is_empty());
+            Ok(())
+        }
+
+        #[test]
+        fn remote_tip_missing_locally_needs_fetch_first() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            git_repo.commit("main", "initial commit");
+            assert_eq!(
+                run(
+                    &git_repo,
+                    REFSPEC,
+                    "5e664e5a7845cd1373c79f580ca4fe29ab5b34d2"
+                )?,
+                vec!["fetch first"]
+            );
+            Ok(())
+        }
+    }
+
+    mod roll_back_git_servers {
+        use std::fs;
+
+        use nostr::nips::nip01::Coordinate;
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        #[test]
+        fn restores_ref_on_filesystem_server() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let previous_tip = test_repo.populate()?;
+            let server_repo = GitTestRepo::recreate_as_bare(&test_repo)?;
+            fs::write(test_repo.dir.join("t2.md"), "some content")?;
+            let new_tip = test_repo.stage_and_commit("add t2.md")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let server_url = server_repo.dir.to_string_lossy().to_string();
+            let decoded_nostr_url = NostrUrlDecoded {
+                original_string: "nostr://npub/test".to_string(),
+                coordinate: Coordinate {
+                    identifier: "test".to_string(),
+                    public_key: nostr::Keys::generate().public_key(),
+                    kind: Kind::GitRepoAnnouncement,
+                    relays: vec![],
+                },
+                protocol: None,
+                user: None,
+                nip05: None,
+            };
+            let term = Reporter::new(console::Term::stdout());
+            let refspecs = vec!["refs/heads/main:refs/heads/main".to_string()];
+
+            push_to_remote(&git_repo, &server_url, &decoded_nostr_url, &refspecs, &term)?;
+            assert_eq!(
+                server_repo.git_repo.refname_to_id("refs/heads/main")?,
+                new_tip
+            );
+
+            roll_back_git_servers(
+                &git_repo,
+                &decoded_nostr_url,
+                &[(&server_url, refspecs)],
+                &HashMap::from([(
+                    server_url.clone(),
+                    HashMap::from([("refs/heads/main".to_string(), previous_tip.to_string())]),
+                )]),
+                &term,
+            )?;
+            assert_eq!(
+                server_repo.git_repo.refname_to_id("refs/heads/main")?,
+                previous_tip
+            );
+            // the temporary local ref used for the rollback is removed
+            assert!(
+                test_repo
+                    .git_repo
+                    .find_reference("refs/nostr-rollback/heads/main")
+                    .is_err()
+            );
+            Ok(())
+        }
+    }
 }
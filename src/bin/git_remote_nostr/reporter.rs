@@ -0,0 +1,287 @@
+use std::{io, sync::Mutex};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::utils::HelperOptions;
+
+/// finished rows beyond this many from the bottom are handed over to the
+/// terminal, so a long fetch or push doesn't keep every line it wrote
+static MAX_ROWS: usize = 16;
+
+/// a line written with `write_line`, or a section of progress lines beneath a
+/// title, drawn as one multi-line bar
+struct Row {
+    title: Option<String>,
+    lines: Vec<String>,
+    /// won't be updated again
+    finished: bool,
+    bar: ProgressBar,
+}
+
+impl Row {
+    fn all_lines(&self) -> Vec<String> {
+        self.title
+            .iter()
+            .chain(self.lines.iter())
+            .cloned()
+            .collect()
+    }
+
+    fn redraw(&self) {
+        self.bar.set_message(self.all_lines().join("\n"));
+    }
+
+    fn clear(&self, multi: &MultiProgress) {
+        self.bar.finish_and_clear();
+        multi.remove(&self.bar);
+    }
+}
+
+/// terminal output shared by list, fetch and push. progress for each git
+/// server is kept in its own section, which is redrawn in place when it
+/// updates, so reports relating to different servers don't clobber each other.
+pub struct Reporter {
+    term: console::Term,
+    multi: MultiProgress,
+    style: ProgressStyle,
+    rows: Mutex<Vec<Row>>,
+    show_progress: bool,
+}
+
+fn find_section(rows: &[Row], title: &str) -> Option<usize> {
+    rows.iter()
+        .position(|r| !r.finished && r.title.as_deref() == Some(title))
+}
+
+impl Default for Reporter {
+    fn default() -> Self {
+        Self::new(console::Term::stderr())
+    }
+}
+
+impl Reporter {
+    pub fn new(term: console::Term) -> Self {
+        Self {
+            multi: MultiProgress::with_draw_target(ProgressDrawTarget::term(term.clone(), 20)),
+            style: ProgressStyle::with_template("{msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+            term,
+            rows: Mutex::new(vec![]),
+            show_progress: true,
+        }
+    }
+
+    /// reporter which hides progress sections if `options` ask for it
+    pub fn from_options(options: &HelperOptions) -> Self {
+        Self {
+            show_progress: options.show_progress(),
+            ..Self::default()
+        }
+    }
+
+    fn add_row(&self, rows: &mut Vec<Row>, title: Option<String>, lines: Vec<String>) -> usize {
+        rows.push(Row {
+            title,
+            lines,
+            finished: false,
+            bar: self
+                .multi
+                .add(ProgressBar::new(0).with_style(self.style.clone())),
+        });
+        rows.len() - 1
+    }
+
+    /// drop finished rows more than [`MAX_ROWS`] from the bottom, which won't
+    /// be cleared or redrawn. they stay on screen as indicatif leaves finished
+    /// bars drawn
+    fn trim(rows: &mut Vec<Row>) {
+        let excess = rows.len().saturating_sub(MAX_ROWS);
+        let mut position = 0;
+        rows.retain(|row| {
+            position += 1;
+            position > excess || !row.finished
+        });
+    }
+
+    /// write a line which won't be updated later
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        let mut rows = self.rows.lock().unwrap();
+        let position = self.add_row(&mut rows, None, vec![line.to_string()]);
+        rows[position].finished = true;
+        rows[position].bar.finish_with_message(line.to_string());
+        Self::trim(&mut rows);
+        if self.term.is_term() {
+            Ok(())
+        } else {
+            // nothing is drawn so write it as is, eg. for logs
+            self.term.write_line(line)
+        }
+    }
+
+    /// remove the last `n` lines written
+    pub fn clear_last_lines(&self, n: usize) -> io::Result<()> {
+        let mut rows = self.rows.lock().unwrap();
+        let mut removed = 0;
+        while removed < n {
+            let Some(row) = rows.last_mut() else {
+                break;
+            };
+            if row.lines.pop().is_some() || row.title.take().is_some() {
+                removed += 1;
+            }
+            if row.lines.is_empty() && row.title.is_none() {
+                row.clear(&self.multi);
+                rows.pop();
+            } else if removed == n {
+                row.redraw();
+            }
+        }
+        Ok(())
+    }
+
+    /// replace the lines displayed beneath `title`. if no section with `title`
+    /// exists it is added to the bottom.
+    pub fn set_section(&self, title: &str, lines: Vec<String>) -> io::Result<()> {
+        if !self.show_progress {
+            return Ok(());
+        }
+        let mut rows = self.rows.lock().unwrap();
+        let position = if let Some(position) = find_section(&rows, title) {
+            rows[position].lines = lines;
+            position
+        } else {
+            self.add_row(&mut rows, Some(title.to_string()), lines)
+        };
+        rows[position].redraw();
+        Ok(())
+    }
+
+    /// leave the section with `title` as it is. a later section with the same
+    /// title is added to the bottom
+    pub fn finish_section(&self, title: &str) -> io::Result<()> {
+        let mut rows = self.rows.lock().unwrap();
+        if let Some(position) = find_section(&rows, title) {
+            let row = &mut rows[position];
+            row.finished = true;
+            row.bar.finish_with_message(row.all_lines().join("\n"));
+            if !self.term.is_term() {
+                for line in row.all_lines() {
+                    self.term.write_line(&line)?;
+                }
+            }
+            Self::trim(&mut rows);
+        }
+        Ok(())
+    }
+
+    /// remove the section with `title` and the lines beneath it
+    pub fn remove_section(&self, title: &str) -> io::Result<()> {
+        let mut rows = self.rows.lock().unwrap();
+        if let Some(position) = find_section(&rows, title) {
+            rows.remove(position).clear(&self.multi);
+        }
+        Ok(())
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        self.term.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn displayed_lines(reporter: &Reporter) -> Vec<String> {
+        reporter
+            .rows
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(Row::all_lines)
+            .collect()
+    }
+
+    #[test]
+    fn set_section_replaces_lines_in_place() -> io::Result<()> {
+        let reporter = Reporter::new(console::Term::stdout());
+        reporter.set_section("fetching a over https...", vec!["a: 1%".to_string()])?;
+        reporter.set_section("fetching b over https...", vec!["b: 1%".to_string()])?;
+        reporter.set_section("fetching a over https...", vec!["a: 50%".to_string()])?;
+        assert_eq!(
+            displayed_lines(&reporter),
+            vec![
+                "fetching a over https...",
+                "a: 50%",
+                "fetching b over https...",
+                "b: 1%",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn clear_last_lines_spans_sections() -> io::Result<()> {
+        let reporter = Reporter::new(console::Term::stdout());
+        reporter.write_line("first")?;
+        reporter.set_section("title", vec!["progress".to_string()])?;
+        reporter.clear_last_lines(2)?;
+        assert_eq!(displayed_lines(&reporter), vec!["first"]);
+        reporter.set_section("title", vec!["a".to_string(), "b".to_string()])?;
+        reporter.clear_last_lines(1)?;
+        assert_eq!(displayed_lines(&reporter), vec!["first", "title", "a"]);
+        Ok(())
+    }
+
+    #[test]
+    fn sections_hidden_without_progress() -> io::Result<()> {
+        let reporter = Reporter {
+            show_progress: false,
+            ..Reporter::new(console::Term::stdout())
+        };
+        reporter.set_section("title", vec!["progress".to_string()])?;
+        reporter.write_line("warning")?;
+        assert_eq!(displayed_lines(&reporter), vec!["warning"]);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_section_keeps_later_lines() -> io::Result<()> {
+        let reporter = Reporter::new(console::Term::stdout());
+        reporter.set_section("title", vec!["progress".to_string()])?;
+        reporter.write_line("after")?;
+        reporter.remove_section("title")?;
+        assert_eq!(displayed_lines(&reporter), vec!["after"]);
+        Ok(())
+    }
+
+    #[test]
+    fn finished_rows_are_trimmed_but_open_sections_kept() -> io::Result<()> {
+        let reporter = Reporter::new(console::Term::stdout());
+        reporter.set_section("open", vec!["progress".to_string()])?;
+        reporter.set_section("done", vec!["100%".to_string()])?;
+        reporter.finish_section("done")?;
+        for i in 0..100 {
+            reporter.write_line(&format!("line {i}"))?;
+        }
+        let rows = reporter.rows.lock().unwrap();
+        assert_eq!(rows.len(), MAX_ROWS + 1);
+        assert_eq!(rows[0].title.as_deref(), Some("open"));
+        assert_eq!(rows[1].lines, vec!["line 85"]);
+        assert_eq!(rows[MAX_ROWS].lines, vec!["line 99"]);
+        Ok(())
+    }
+
+    #[test]
+    fn finished_section_isnt_updated_again() -> io::Result<()> {
+        let reporter = Reporter::new(console::Term::stdout());
+        reporter.set_section("push summary:", vec!["a: ok".to_string()])?;
+        reporter.finish_section("push summary:")?;
+        reporter.set_section("push summary:", vec!["b: ok".to_string()])?;
+        assert_eq!(
+            displayed_lines(&reporter),
+            vec!["push summary:", "a: ok", "push summary:", "b: ok"]
+        );
+        Ok(())
+    }
+}
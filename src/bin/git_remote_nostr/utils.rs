@@ -3,30 +3,35 @@ use std::{
     collections::HashMap,
     fmt,
     io::{self, Stdin},
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::Mutex,
 };
 
 use anyhow::{Context, Result, bail};
-use git2::Repository;
+use auth_git2::GitAuthenticator;
 use ngit::{
     client::{
         get_all_proposal_patch_events_from_cache, get_events_from_local_cache,
         get_proposals_and_revisions_from_cache,
     },
+    error::{AuthError, is_authentication_error},
     git::{
         Repo, RepoActions,
+        credential_helper::GitCredential,
         nostr_url::{CloneUrl, NostrUrlDecoded, ServerProtocol},
+        utils::{check_ssh_keys, expand_home_dir},
     },
     git_events::{
-        event_is_revision_root, get_most_recent_patch_with_ancestors,
-        is_event_proposal_root_for_branch, status_kinds,
+        event_is_revision_root, get_most_recent_patch_with_ancestors, get_proposal_status,
+        is_event_proposal_root_for_branch, sort_newest_first, status_kinds,
     },
     repo_ref::RepoRef,
 };
 use nostr_sdk::{Event, EventId, Kind, PublicKey, Url};
 
-pub fn get_short_git_server_name(git_repo: &Repo, url: &str) -> std::string::String {
-    if let Ok(name) = get_remote_name_by_url(&git_repo.git_repo, url) {
+pub fn get_short_git_server_name(git_repo: &impl RepoActions, url: &str) -> std::string::String {
+    if let Ok(name) = git_repo.get_remote_name_by_url(url) {
         return name;
     }
     if let Ok(url) = Url::parse(url) {
@@ -37,26 +42,6 @@ pub fn get_short_git_server_name(git_repo: &Repo, url: &str) -> std::string::Str
     url.to_string()
 }
 
-pub fn get_remote_name_by_url(git_repo: &Repository, url: &str) -> Result<String> {
-    let remotes = git_repo.remotes()?;
-    Ok(remotes
-        .iter()
-        .find(|r| {
-            if let Some(name) = r {
-                if let Some(remote_url) = git_repo.find_remote(name).unwrap().url() {
-                    url == remote_url
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        })
-        .context("could not find remote with matching url")?
-        .context("remote with matching url must be named")?
-        .to_string())
-}
-
 pub fn get_oids_from_fetch_batch(
     stdin: &Stdin,
     initial_oid: &str,
@@ -94,18 +79,22 @@ pub fn read_line<'a>(stdin: &io::Stdin, line: &'a mut String) -> io::Result<Vec<
     Ok(tokens)
 }
 
-pub async fn get_open_proposals(
+/// proposal root events paired with the kind of their most recent status set
+/// by a maintainer or, unless it's applied, the proposal author
+pub async fn get_proposals_with_status(
     git_repo: &Repo,
     repo_ref: &RepoRef,
-) -> Result<HashMap<EventId, (Event, Vec<Event>)>> {
+) -> Result<Vec<(Event, Kind)>> {
     let git_repo_path = git_repo.get_path()?;
-    let proposals: Vec<nostr::Event> =
-        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
-            .await?
-            .iter()
-            .filter(|e| !event_is_revision_root(e))
-            .cloned()
-            .collect();
+    let proposals: Vec<nostr::Event> = get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?
+    .iter()
+    .filter(|e| !event_is_revision_root(e))
+    .cloned()
+    .collect();
 
     let statuses: Vec<nostr::Event> = {
         let mut statuses = get_events_from_local_cache(git_repo_path, vec![
@@ -114,28 +103,36 @@ pub async fn get_open_proposals(
                 .events(proposals.iter().map(|e| e.id)),
         ])
         .await?;
-        statuses.sort_by_key(|e| e.created_at);
-        statuses.reverse();
+        sort_newest_first(&mut statuses);
         statuses
     };
+
+    Ok(proposals
+        .into_iter()
+        .map(|proposal| {
+            let (status, _) = get_proposal_status(&proposal, &statuses, &repo_ref.maintainers);
+            (proposal, status)
+        })
+        .collect())
+}
+
+pub fn status_kind_to_str(kind: Kind) -> &'static str {
+    match kind {
+        Kind::GitStatusApplied => "applied",
+        Kind::GitStatusClosed => "closed",
+        Kind::GitStatusDraft => "draft",
+        _ => "open",
+    }
+}
+
+pub async fn get_open_proposals(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+) -> Result<HashMap<EventId, (Event, Vec<Event>)>> {
+    let git_repo_path = git_repo.get_path()?;
     let mut open_proposals = HashMap::new();
 
-    for proposal in proposals {
-        let status = if let Some(e) = statuses
-            .iter()
-            .filter(|e| {
-                status_kinds().contains(&e.kind)
-                    && e.tags.iter().any(|t| {
-                        t.as_slice().len() > 1 && t.as_slice()[1].eq(&proposal.id.to_string())
-                    })
-            })
-            .collect::<Vec<&nostr::Event>>()
-            .first()
-        {
-            e.kind
-        } else {
-            Kind::GitStatusOpen
-        };
+    for (proposal, status) in get_proposals_with_status(git_repo, repo_ref).await? {
         if status.eq(&Kind::GitStatusOpen) {
             if let Ok(commits_events) =
                 get_all_proposal_patch_events_from_cache(git_repo_path, repo_ref, &proposal.id)
@@ -158,13 +155,15 @@ pub async fn get_all_proposals(
     repo_ref: &RepoRef,
 ) -> Result<HashMap<EventId, (Event, Vec<Event>)>> {
     let git_repo_path = git_repo.get_path()?;
-    let proposals: Vec<nostr::Event> =
-        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates())
-            .await?
-            .iter()
-            .filter(|e| !event_is_revision_root(e))
-            .cloned()
-            .collect();
+    let proposals: Vec<nostr::Event> = get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?
+    .iter()
+    .filter(|e| !event_is_revision_root(e))
+    .cloned()
+    .collect();
 
     let mut all_proposals = HashMap::new();
 
@@ -211,6 +210,63 @@ pub fn join_with_and<T: ToString>(items: &[T]) -> String {
     }
 }
 
+/// get the announced git servers in the order they should be tried, applying
+/// the per-repo git config items `nostr.git-server-order` and
+/// `nostr.git-server-exclude`
+pub fn get_git_servers_to_try(git_repo: &Repo, git_servers: &[String]) -> Vec<String> {
+    let order = git_repo
+        .get_git_config_item("nostr.git-server-order", Some(false))
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let exclude = git_repo
+        .get_git_config_item("nostr.git-server-exclude", Some(false))
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    order_git_servers(git_servers, &order, &exclude)
+}
+
+/// `order` and `exclude` are comma seperated lists of server urls, short names
+/// (eg. github.com/user/repo.git) or domains. servers not in `order` keep
+/// their announcement order after those that are.
+pub fn order_git_servers(git_servers: &[String], order: &str, exclude: &str) -> Vec<String> {
+    let split = |s: &str| {
+        s.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect::<Vec<String>>()
+    };
+    let order = split(order);
+    let exclude = split(exclude);
+
+    let mut servers: Vec<String> = git_servers
+        .iter()
+        .filter(|url| !exclude.iter().any(|e| git_server_matches(url, e)))
+        .cloned()
+        .collect();
+    // stable sort so unlisted servers retain announcement order
+    servers.sort_by_key(|url| {
+        order
+            .iter()
+            .position(|o| git_server_matches(url, o))
+            .unwrap_or(order.len())
+    });
+    servers
+}
+
+fn git_server_matches(url: &str, name: &str) -> bool {
+    if url == name {
+        return true;
+    }
+    if let Ok(clone_url) = url.parse::<CloneUrl>() {
+        clone_url.short_name() == name || clone_url.domain() == name
+    } else {
+        false
+    }
+}
+
 /// get an ordered vector of server protocols to attempt
 pub fn get_read_protocols_to_try(
     git_repo: &Repo,
@@ -286,6 +342,71 @@ pub fn get_write_protocols_to_try(
     }
 }
 
+/// settings git sends with the `option` command
+#[derive(Debug)]
+pub struct HelperOptions {
+    pub verbosity: u8,
+    pub progress: bool,
+    pub dry_run: bool,
+    pub atomic: bool,
+    /// expected tips from `--force-with-lease`
+    pub leases: HashMap<String, String>,
+}
+
+impl Default for HelperOptions {
+    fn default() -> Self {
+        Self {
+            verbosity: 1,
+            progress: true,
+            dry_run: false,
+            atomic: false,
+            leases: HashMap::new(),
+        }
+    }
+}
+
+impl HelperOptions {
+    /// returns false if the option isn't supported
+    pub fn set(&mut self, name: &str, value: &str) -> Result<bool> {
+        let parse_bool = |value: &str| match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => bail!("invalid value {value} for option {name}"),
+        };
+        match name {
+            "verbosity" => {
+                self.verbosity = value
+                    .parse()
+                    .context(format!("invalid verbosity {value}"))?;
+                // git's default is 1 and `--quiet` 0
+                ngit::output::set_verbosity(
+                    i8::try_from(self.verbosity)
+                        .unwrap_or(i8::MAX)
+                        .saturating_sub(1),
+                );
+            }
+            "progress" => self.progress = parse_bool(value)?,
+            "dry-run" => self.dry_run = parse_bool(value)?,
+            "atomic" => self.atomic = parse_bool(value)?,
+            // --force-with-lease=<refname>:<expected oid>
+            "cas" => {
+                let (refname, expected) = value
+                    .split_once(':')
+                    .context(format!("invalid cas option {value}"))?;
+                self.leases
+                    .insert(refname.to_string(), expected.to_string());
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// progress is hidden when git is run with `--quiet` or `--no-progress`
+    pub fn show_progress(&self) -> bool {
+        self.progress && self.verbosity > 0
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Direction {
     Push,
@@ -364,26 +485,180 @@ pub fn push_error_is_not_authentication_failure(error: &anyhow::Error) -> bool {
     !error_might_be_authentication_related(error)
 }
 
-pub fn error_might_be_authentication_related(error: &anyhow::Error) -> bool {
-    let error_str = error.to_string();
-    for s in [
-        "no ssh keys found",
-        "invalid or unknown remote ssh hostkey",
-        "authentication",
-        "Permission",
-        "permission",
-        "not found",
-    ] {
-        if error_str.contains(s) {
-            return true;
+/// ssh identity for a nostr remote, set with `nostr.<remote>.ssh-key`
+pub struct SshIdentity {
+    remote_name: Option<String>,
+    pub key: Option<PathBuf>,
+}
+
+impl SshIdentity {
+    pub fn for_nostr_url(git_repo: &Repo, decoded_nostr_url: &NostrUrlDecoded) -> Result<Self> {
+        let remote_name = git_repo
+            .get_remote_name_by_url(&decoded_nostr_url.original_string)
+            .ok();
+        let key = if let Some(remote_name) = &remote_name {
+            let config_key = format!("nostr.{remote_name}.ssh-key");
+            if let Some(path) = git_repo.get_git_config_item(&config_key, None)? {
+                let path = expand_home_dir(&path);
+                if !path.exists() {
+                    bail!(
+                        "ssh key {} set in {config_key} doesnt exist",
+                        path.display()
+                    );
+                }
+                Some(path)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        Ok(Self { remote_name, key })
+    }
+
+    /// fail with guidance when no key is configured, found in ~/.ssh or
+    /// available via ssh-agent
+    pub fn check_available(&self) -> Result<()> {
+        if self.key.is_some() || check_ssh_keys() || std::env::var_os("SSH_AUTH_SOCK").is_some() {
+            return Ok(());
+        }
+        bail!(AuthError::NoSshKeys {
+            remote: self
+                .remote_name
+                .clone()
+                .unwrap_or_else(|| "<remote>".to_string()),
+        })
+    }
+}
+
+static CREDENTIAL_PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+/// `~/.ssh/id.pem` has its public key at `~/.ssh/id.pem.pub`
+fn ssh_public_key_path(ssh_key: &Path) -> PathBuf {
+    let mut public_key = ssh_key.as_os_str().to_owned();
+    public_key.push(".pub");
+    PathBuf::from(public_key)
+}
+
+/// credentials callback that gets http(s) credentials from git credential
+/// helpers and uses auth_git2 for ssh, unless `ssh_key` is set. the credential
+/// used is recorded in `used` so it can be stored or erased with
+/// `report_credential_outcome`.
+pub fn credentials_callback<'a>(
+    auth: &'a GitAuthenticator,
+    git_config: &'a git2::Config,
+    ssh_key: Option<&'a Path>,
+    used: &'a Mutex<Option<GitCredential>>,
+) -> impl FnMut(&str, Option<&str>, git2::CredentialType) -> Result<git2::Cred, git2::Error> + 'a {
+    let mut auth_credentials = auth.credentials(git_config);
+    let mut ssh_key_tried = false;
+    move |url, username_from_url, allowed_types| {
+        // git servers may be fetched from in parallel so only prompt for one at a time
+        let _prompt_guard = CREDENTIAL_PROMPT_LOCK.lock();
+        if let Some(ssh_key) = ssh_key {
+            if allowed_types.contains(git2::CredentialType::SSH_KEY) {
+                // libgit2 asks again if the server rejected the key
+                if ssh_key_tried {
+                    return Err(git2::Error::new(
+                        git2::ErrorCode::Auth,
+                        git2::ErrorClass::Ssh,
+                        format!(
+                            "authentication failed: git server rejected ssh key {}",
+                            ssh_key.display()
+                        ),
+                    ));
+                }
+                ssh_key_tried = true;
+                let public_key = ssh_public_key_path(ssh_key);
+                return git2::Cred::ssh_key(
+                    username_from_url.unwrap_or("git"),
+                    public_key.exists().then_some(public_key.as_path()),
+                    ssh_key,
+                    None,
+                );
+            }
+        }
+        if allowed_types.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+            && (url.starts_with("https://") || url.starts_with("http://"))
+        {
+            let mut used = used.lock().unwrap();
+            // libgit2 asks again if the server rejected the last credential
+            if let Some(rejected) = used.take() {
+                let _ = rejected.reject();
+                return Err(git2::Error::from_str(
+                    "authentication failed: git server rejected credentials",
+                ));
+            }
+            let credential = GitCredential::fill(url, username_from_url)
+                .map_err(|error| git2::Error::from_str(&error.to_string()))?;
+            let cred = git2::Cred::userpass_plaintext(&credential.username, &credential.password);
+            *used = Some(credential);
+            cred
+        } else {
+            auth_credentials(url, username_from_url, allowed_types)
+        }
+    }
+}
+
+/// tell credential helpers whether the credential used worked
+pub fn report_credential_outcome<T>(
+    used: &Mutex<Option<GitCredential>>,
+    res: &std::result::Result<T, git2::Error>,
+) {
+    if let Some(credential) = used.lock().unwrap().take() {
+        match res {
+            Ok(_) => {
+                let _ = credential.approve();
+            }
+            Err(error) if error.code() == git2::ErrorCode::Auth => {
+                let _ = credential.reject();
+            }
+            Err(_) => {}
         }
     }
-    false
+}
+
+pub fn error_might_be_authentication_related(error: &anyhow::Error) -> bool {
+    if is_authentication_error(error) {
+        return true;
+    }
+    // libgit2 reports ssh permission failures and repositories hidden from
+    // unauthenticated users without a distinct error code
+    let error_str = error.to_string();
+    ["authentication", "Permission", "permission", "not found"]
+        .iter()
+        .any(|s| error_str.contains(s))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    mod helper_options {
+        use super::*;
+
+        #[test]
+        fn sets_supported_options() -> Result<()> {
+            let mut options = HelperOptions::default();
+            assert!(options.set("verbosity", "0")?);
+            assert!(options.set("dry-run", "true")?);
+            assert!(options.set("cas", "refs/heads/pr/a:431b84ed")?);
+            assert!(!options.show_progress());
+            assert!(options.dry_run);
+            assert_eq!(
+                options.leases.get("refs/heads/pr/a"),
+                Some(&"431b84ed".to_string())
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn rejects_unknown_options_and_invalid_values() {
+            let mut options = HelperOptions::default();
+            assert!(!options.set("depth", "1").unwrap());
+            assert!(options.set("progress", "maybe").is_err());
+        }
+    }
     mod join_with_and {
         use super::*;
         #[test]
@@ -422,4 +697,100 @@ mod tests {
             assert_eq!(join_with_and(&items), "one, two, three, four and five");
         }
     }
+
+    mod order_git_servers {
+        use super::*;
+
+        fn servers() -> Vec<String> {
+            vec![
+                "https://github.com/user/repo.git".to_string(),
+                "https://codeberg.org/user/repo.git".to_string(),
+                "https://gitlab.com/user/repo.git".to_string(),
+            ]
+        }
+
+        #[test]
+        fn no_config_retains_announcement_order() {
+            assert_eq!(order_git_servers(&servers(), "", ""), servers());
+        }
+
+        #[test]
+        fn pinned_domain_moves_to_front() {
+            assert_eq!(order_git_servers(&servers(), "gitlab.com", ""), vec![
+                "https://gitlab.com/user/repo.git".to_string(),
+                "https://github.com/user/repo.git".to_string(),
+                "https://codeberg.org/user/repo.git".to_string(),
+            ]);
+        }
+
+        #[test]
+        fn order_accepts_short_names_and_urls() {
+            assert_eq!(
+                order_git_servers(
+                    &servers(),
+                    "codeberg.org/user/repo.git, https://gitlab.com/user/repo.git",
+                    ""
+                ),
+                vec![
+                    "https://codeberg.org/user/repo.git".to_string(),
+                    "https://gitlab.com/user/repo.git".to_string(),
+                    "https://github.com/user/repo.git".to_string(),
+                ]
+            );
+        }
+
+        #[test]
+        fn excluded_servers_removed() {
+            assert_eq!(
+                order_git_servers(&servers(), "gitlab.com", "github.com"),
+                vec![
+                    "https://gitlab.com/user/repo.git".to_string(),
+                    "https://codeberg.org/user/repo.git".to_string(),
+                ]
+            );
+        }
+    }
+
+    mod ssh_identity {
+        use super::*;
+
+        #[test]
+        fn public_key_path_appends_pub_to_the_file_name() {
+            assert_eq!(
+                ssh_public_key_path(Path::new("/home/user/.ssh/id.pem")),
+                PathBuf::from("/home/user/.ssh/id.pem.pub")
+            );
+            assert_eq!(
+                ssh_public_key_path(Path::new("/home/user/.ssh/id_ed25519")),
+                PathBuf::from("/home/user/.ssh/id_ed25519.pub")
+            );
+        }
+
+        #[test]
+        fn configured_key_is_only_tried_once() -> Result<()> {
+            let auth = GitAuthenticator::default();
+            let git_config = git2::Config::new()?;
+            let used = Mutex::new(None);
+            let mut callback = credentials_callback(
+                &auth,
+                &git_config,
+                Some(Path::new("/home/user/.ssh/nostr_key")),
+                &used,
+            );
+            let url = "ssh://git@example.com/repo.git";
+            assert!(callback(url, Some("git"), git2::CredentialType::SSH_KEY).is_ok());
+            let error = callback(url, Some("git"), git2::CredentialType::SSH_KEY).unwrap_err();
+            assert_eq!(error.code(), git2::ErrorCode::Auth);
+            Ok(())
+        }
+
+        #[test]
+        fn configured_key_is_available() {
+            let identity = SshIdentity {
+                remote_name: Some("origin".to_string()),
+                key: Some(PathBuf::from("/home/user/.ssh/nostr_key")),
+            };
+            assert!(identity.check_available().is_ok());
+        }
+    }
 }
@@ -23,12 +23,38 @@ pub struct Cli {
     /// nsec or hex private key
     #[arg(short, long, global = true)]
     pub nsec: Option<String>,
+    /// serial device path of a hardware signer, eg. /dev/ttyACM0
+    #[arg(long, global = true)]
+    pub hardware_signer: Option<String>,
     /// password to decrypt nsec
     #[arg(short, long, global = true, hide = true)]
     pub password: Option<String>,
     /// disable spinner animations
     #[arg(long, action, hide = true)]
     pub disable_cli_spinners: bool,
+    /// log to stderr at error, warn, info, debug or trace, or with filter
+    /// directives such as ngit=debug. defaults to NGIT_LOG
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+    /// use colors: auto, always or never. defaults to never when NO_COLOR is
+    /// set, otherwise the `color` setting or auto
+    #[arg(long, global = true, value_name = "WHEN", value_parser = ["auto", "always", "never"])]
+    pub color: Option<String>,
+    /// only print errors and results, without progress bars
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// log relays tried, filters sent and protocol fallbacks. -vv logs
+    /// everything
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// print results and errors as json on stdout, leaving progress on stderr.
+    /// supported by init, send, list and account login
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// never prompt: take each prompt's default or fail naming the missing
+    /// value. also set by NGIT_NONINTERACTIVE
+    #[arg(long, visible_alias = "yes", global = true)]
+    pub non_interactive: bool,
 }
 
 pub fn extract_signer_cli_arguments(args: &Cli) -> Result<Option<SignerInfo>> {
@@ -48,6 +74,8 @@ pub fn extract_signer_cli_arguments(args: &Cli) -> Result<Option<SignerInfo>> {
         } else {
             bail!("cli argument bunker-app-key must be supplied when bunker-uri is")
         }
+    } else if let Some(device) = args.hardware_signer.clone() {
+        Ok(Some(SignerInfo::Hardware { device, npub: None }))
     } else if args.bunker_app_key.is_some() {
         bail!("cli argument bunker-uri must be supplied when bunker-app-key is")
     } else {
@@ -62,9 +90,34 @@ pub enum Commands {
     /// submit PR with advanced options
     Send(sub_commands::send::SubCommandArgs),
     /// list PRs; checkout, apply or download selected
-    List,
+    List(sub_commands::list::SubCommandArgs),
+    /// list the #numbers of PRs and issues, or publish them for every clone
+    Numbers(sub_commands::numbers::SubCommandArgs),
     /// login, logout or export keys
     Account(AccountSubCommandArgs),
+    /// create or fetch git bundles to speed up initial clones
+    Bundle(BundleSubCommandArgs),
+    /// check the repo's relays and republish the announcement without dead ones
+    PruneRelays(sub_commands::prune_relays::SubCommandArgs),
+    /// republish proposals from older ngit versions or other git-nostr tools
+    Migrate(sub_commands::migrate::SubCommandArgs),
+    /// manage organizations whose members maintain repositories together
+    Org(OrgSubCommandArgs),
+    /// sync proposals with pull requests on other git forges
+    Bridge(BridgeSubCommandArgs),
+    /// print web links and nostr uris to share the repo, a PR or an issue
+    Url(sub_commands::url::SubCommandArgs),
+    /// render recent repo activity as an atom feed
+    Feed(sub_commands::feed::SubCommandArgs),
+    /// keep caches of recently used repos warm by fetching in the background
+    Daemon(sub_commands::daemon::SubCommandArgs),
+    /// install the latest ngit release published on nostr
+    SelfUpdate(sub_commands::self_update::SubCommandArgs),
+    /// write man pages or a markdown reference for ngit and git-remote-nostr
+    Man(sub_commands::man::SubCommandArgs),
+    /// run `ngit-<name>` from PATH
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
 #[derive(Subcommand)]
@@ -72,9 +125,19 @@ pub enum AccountCommands {
     /// login with nsec or nostr connect
     Login(sub_commands::login::SubCommandArgs),
     /// remove nostr account details stored in git config
-    Logout,
+    Logout(sub_commands::logout::SubCommandArgs),
     /// export nostr keys to login to other nostr clients
     ExportKeys,
+    /// export keys as a password protected ncryptsec to move to another machine
+    Export,
+    /// login with an ncryptsec from `ngit account export`
+    Import(sub_commands::import::SubCommandArgs),
+    /// delegate signing to a bot / CI key with NIP-26
+    Delegate(sub_commands::delegate::SubCommandArgs),
+    /// hold unlocked keys in memory until `nostr.unlock-timeout` passes.
+    /// started automatically
+    #[command(hide = true)]
+    UnlockAgent,
 }
 
 #[derive(clap::Parser)]
@@ -82,3 +145,51 @@ pub struct AccountSubCommandArgs {
     #[command(subcommand)]
     pub account_command: AccountCommands,
 }
+
+#[derive(Subcommand)]
+pub enum BundleCommands {
+    /// create a bundle of all branches and tags
+    Create(sub_commands::bundle::CreateSubCommandArgs),
+    /// fetch objects from a bundle
+    Fetch(sub_commands::bundle::FetchSubCommandArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct BundleSubCommandArgs {
+    #[command(subcommand)]
+    pub bundle_command: BundleCommands,
+}
+
+#[derive(Subcommand)]
+pub enum OrgCommands {
+    /// publish or update an organization and its members
+    Create(sub_commands::org::CreateSubCommandArgs),
+    /// list an organization's members and repositories
+    Repos(sub_commands::org::ReposSubCommandArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct OrgSubCommandArgs {
+    #[command(subcommand)]
+    pub org_command: OrgCommands,
+}
+
+#[derive(Subcommand)]
+pub enum BridgeCommands {
+    /// import GitHub pull requests, export proposals and mirror their statuses
+    Github(sub_commands::bridge::GitHubSubCommandArgs),
+    /// import open GitLab merge requests as proposals
+    Gitlab(sub_commands::bridge::GitLabSubCommandArgs),
+    /// import open Forgejo or Gitea pull requests as proposals
+    Forgejo(sub_commands::bridge::ForgejoSubCommandArgs),
+    /// import Radicle patches as proposals and export proposals as patches
+    Radicle(sub_commands::bridge::RadicleSubCommandArgs),
+    /// update Patchwork patch states from proposal statuses or export an mbox
+    Patchwork(sub_commands::bridge::PatchworkSubCommandArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct BridgeSubCommandArgs {
+    #[command(subcommand)]
+    pub bridge_command: BridgeCommands,
+}
@@ -4,7 +4,7 @@
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{AccountCommands, Cli, Commands};
+use cli::{AccountCommands, BridgeCommands, BundleCommands, Cli, Commands, OrgCommands};
 
 mod cli;
 use ngit::{cli_interactor, client, git, git_events, login, repo_ref};
@@ -14,14 +14,83 @@ mod sub_commands;
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    ngit::output::set_verbosity(if cli.quiet {
+        -1
+    } else {
+        i8::try_from(cli.verbose).unwrap_or(i8::MAX)
+    });
+    ngit::logging::init(
+        cli.log_level
+            .as_deref()
+            .or_else(ngit::output::verbose_log_level),
+    )?;
+    ngit::theme::init(cli.color.as_deref())?;
+    ngit::interrupt::exit_on_ctrl_c();
+    if cli.json {
+        ngit::output::enable_json()?;
+    }
+    if cli.non_interactive {
+        cli_interactor::set_non_interactive();
+    }
+    let result = run(&cli).await;
+    if let (true, Err(error)) = (cli.json, &result) {
+        ngit::output::emit(&ngit::output::error_value(error))?;
+        std::process::exit(1);
+    }
+    result
+}
+
+async fn run(cli: &Cli) -> Result<()> {
     match &cli.command {
         Commands::Account(args) => match &args.account_command {
-            AccountCommands::Login(sub_args) => sub_commands::login::launch(&cli, sub_args).await,
-            AccountCommands::Logout => sub_commands::logout::launch().await,
+            AccountCommands::Login(sub_args) => sub_commands::login::launch(cli, sub_args).await,
+            AccountCommands::Logout(sub_args) => sub_commands::logout::launch(sub_args).await,
             AccountCommands::ExportKeys => sub_commands::export_keys::launch().await,
+            AccountCommands::Export => sub_commands::export::launch().await,
+            AccountCommands::Import(sub_args) => sub_commands::import::launch(sub_args).await,
+            AccountCommands::Delegate(sub_args) => {
+                sub_commands::delegate::launch(cli, sub_args).await
+            }
+            AccountCommands::UnlockAgent => login::session_lock::run_agent(),
+        },
+        Commands::Bridge(args) => match &args.bridge_command {
+            BridgeCommands::Github(sub_args) => {
+                sub_commands::bridge::launch_github(cli, sub_args).await
+            }
+            BridgeCommands::Gitlab(sub_args) => {
+                sub_commands::bridge::launch_gitlab(cli, sub_args).await
+            }
+            BridgeCommands::Forgejo(sub_args) => {
+                sub_commands::bridge::launch_forgejo(cli, sub_args).await
+            }
+            BridgeCommands::Radicle(sub_args) => {
+                sub_commands::bridge::launch_radicle(cli, sub_args).await
+            }
+            BridgeCommands::Patchwork(sub_args) => {
+                sub_commands::bridge::launch_patchwork(sub_args).await
+            }
+        },
+        Commands::Bundle(args) => match &args.bundle_command {
+            BundleCommands::Create(sub_args) => {
+                sub_commands::bundle::launch_create(cli, sub_args).await
+            }
+            BundleCommands::Fetch(sub_args) => sub_commands::bundle::launch_fetch(sub_args).await,
+        },
+        Commands::Daemon(args) => sub_commands::daemon::launch(args).await,
+        Commands::Man(args) => sub_commands::man::launch(args),
+        Commands::Migrate(args) => sub_commands::migrate::launch(cli, args).await,
+        Commands::Feed(args) => sub_commands::feed::launch(args).await,
+        Commands::Init(args) => sub_commands::init::launch(cli, args).await,
+        Commands::Org(args) => match &args.org_command {
+            OrgCommands::Create(sub_args) => sub_commands::org::launch_create(cli, sub_args).await,
+            OrgCommands::Repos(sub_args) => sub_commands::org::launch_repos(sub_args).await,
         },
-        Commands::Init(args) => sub_commands::init::launch(&cli, args).await,
-        Commands::List => sub_commands::list::launch().await,
-        Commands::Send(args) => sub_commands::send::launch(&cli, args, false).await,
+        Commands::List(args) => sub_commands::list::launch(cli, args).await,
+        Commands::Numbers(args) => sub_commands::numbers::launch(cli, args).await,
+        Commands::PruneRelays(args) => sub_commands::prune_relays::launch(cli, args).await,
+        Commands::SelfUpdate(args) => sub_commands::self_update::launch(args).await,
+        Commands::Send(args) => sub_commands::send::launch(cli, args, false).await,
+        Commands::Url(args) => sub_commands::url::launch(args).await,
+        Commands::External(args) => sub_commands::external::launch(args).await,
     }
 }
@@ -0,0 +1,760 @@
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result, bail};
+use ngit::{
+    client::{
+        get_all_proposal_patch_events_from_cache, get_proposals_and_revisions_from_cache,
+        sign_event,
+    },
+    email::{message_id, render_patch_emails, to_mbox},
+    forge::{IMPORTED_FROM_TAG, PullRequest, PullRequestState},
+    forgejo::ForgejoClient,
+    git_events::{
+        CoverLetter, event_is_cover_letter, event_is_revision_root, event_to_cover_letter,
+        generate_cover_letter_and_patch_events, get_most_recent_patch_with_ancestors,
+        get_proposal_status, patch_supports_commit_ids, sort_newest_first, status_kinds, tag_value,
+    },
+    github::{
+        GitHubClient, GitHubRepo, bridged_proposal_id, fetch_pull_request,
+        proposal_id_from_pr_body, proposal_marker, push_branch,
+    },
+    gitlab::GitLabClient,
+    login::user::{UserRef, get_author_display_name},
+    patchwork::{PatchworkClient, patchwork_state},
+    radicle::{DEFAULT_RADICLE_SEED, RadicleClient, get_radicle_identities, open_patch},
+};
+use nostr::{Event, EventBuilder, Kind, PublicKey, Tag, TagKind, TagStandard, nips::nip10::Marker};
+use nostr_sdk::{NostrSigner, hashes::sha1::Hash as Sha1Hash};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{
+        Client, Connect, fetching_with_report, get_events_from_local_cache,
+        get_repo_ref_from_cache, send_events,
+    },
+    git::{Repo, RepoActions, str_to_sha1},
+    login,
+    repo_ref::{RepoRef, get_repo_coordinates_when_remote_unknown},
+};
+
+#[derive(Debug, clap::Args)]
+pub struct GitHubSubCommandArgs {
+    #[clap(long)]
+    /// GitHub repository as owner/name. defaults to git config
+    /// nostr.github-repo
+    repo: Option<String>,
+    #[clap(long)]
+    /// personal access token with pull request and contents write access.
+    /// defaults to GITHUB_TOKEN or git config nostr.github-token
+    token: Option<String>,
+    #[clap(long, action)]
+    /// don't import GitHub pull requests as proposals
+    no_import: bool,
+    #[clap(long, action)]
+    /// don't export proposals as GitHub pull requests
+    no_export: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct GitLabSubCommandArgs {
+    /// web url of the project, eg. https://gitlab.example.com/group/project
+    url: String,
+    #[clap(long)]
+    /// access token for private projects. defaults to GITLAB_TOKEN
+    token: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ForgejoSubCommandArgs {
+    /// web url of the repository, eg. https://codeberg.org/owner/name
+    url: String,
+    #[clap(long)]
+    /// access token for private repositories. defaults to FORGEJO_TOKEN
+    token: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RadicleSubCommandArgs {
+    /// Radicle repository id, eg. rad:z3gqcJUoA1n9HaHKufZs5FCSGazv5. defaults
+    /// to git config nostr.radicle-rid
+    rid: Option<String>,
+    #[clap(long, default_value = DEFAULT_RADICLE_SEED)]
+    /// seed node whose http api lists the patches
+    seed: String,
+    #[clap(long, default_value = "rad")]
+    /// git remote that opens Radicle patches on push
+    remote: String,
+    #[clap(long, action)]
+    /// don't export proposals as Radicle patches. Radicle identities are
+    /// mapped to nostr accounts with git config radicle-identity.<did>.npub
+    no_export: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PatchworkSubCommandArgs {
+    #[clap(long, required_unless_present = "mbox")]
+    /// url of the Patchwork instance, eg. https://patchwork.kernel.org
+    url: Option<String>,
+    #[clap(long, required_unless_present = "mbox")]
+    /// Patchwork project linkname
+    project: Option<String>,
+    #[clap(long)]
+    /// api token of a project maintainer. defaults to PATCHWORK_TOKEN
+    token: Option<String>,
+    #[clap(long)]
+    /// write every proposal as an mbox for Patchwork's parsearchive instead
+    mbox: Option<PathBuf>,
+}
+
+struct Bridge {
+    git_repo: Repo,
+    client: Client,
+    repo_ref: RepoRef,
+    signer: Arc<dyn NostrSigner>,
+    user_ref: UserRef,
+    /// proposal roots, excluding revisions
+    proposals: Vec<Event>,
+}
+
+impl Bridge {
+    async fn load(cli_args: &Cli, git_repo: Repo) -> Result<Self> {
+        let git_repo_path = git_repo.get_path()?;
+
+        let client = Client::default();
+
+        let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+        fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+
+        let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+        let (signer, user_ref, _) = login::login_or_signup(
+            &Some(&git_repo),
+            &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+            &cli_args.password,
+            Some(&client),
+            true,
+        )
+        .await?;
+
+        let proposals: Vec<Event> = get_proposals_and_revisions_from_cache(
+            git_repo_path,
+            repo_ref.coordinates_including_previous_identifiers(),
+        )
+        .await?
+        .into_iter()
+        .filter(|e| !event_is_revision_root(e))
+        .collect();
+
+        Ok(Self {
+            git_repo,
+            client,
+            repo_ref,
+            signer,
+            user_ref,
+            proposals,
+        })
+    }
+
+    /// proposals and patches for open pull requests that haven't already been
+    /// imported and weren't exported from nostr
+    async fn import(
+        &self,
+        pull_requests: &[PullRequest],
+        fetch: impl Fn(&PullRequest) -> Result<Sha1Hash>,
+    ) -> Result<Vec<Event>> {
+        let (main_branch_name, main_tip) = self.git_repo.get_main_or_master_branch()?;
+        let mut events = vec![];
+        for pull_request in pull_requests.iter().filter(|pr| {
+            pr.state.eq(&PullRequestState::Open)
+                && proposal_id_from_pr_body(&pr.body).is_none()
+                && !self
+                    .proposals
+                    .iter()
+                    .any(|p| tag_value(p, IMPORTED_FROM_TAG).is_ok_and(|url| url.eq(&pr.html_url)))
+        }) {
+            let tip = fetch(pull_request)?;
+            let Ok((mut ahead, _)) = self.git_repo.get_commits_ahead_behind(&main_tip, &tip) else {
+                println!(
+                    "skipping {} as it isn't based on {main_branch_name}",
+                    pull_request.html_url
+                );
+                continue;
+            };
+            if ahead.is_empty() {
+                continue;
+            }
+            ahead.reverse();
+            events.append(
+                &mut generate_cover_letter_and_patch_events(
+                    Some((
+                        pull_request.title.clone(),
+                        pull_request.imported_description(),
+                    )),
+                    &self.git_repo,
+                    &ahead,
+                    &self.signer,
+                    &self.repo_ref,
+                    &None,
+                    &[
+                        pull_request.attribution_tags(),
+                        vec![Tag::custom(
+                            TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
+                            vec![pull_request.head_ref.clone()],
+                        )],
+                    ]
+                    .concat(),
+                )
+                .await?,
+            );
+            println!(
+                "imported {} with {} commit{}",
+                pull_request.html_url,
+                ahead.len(),
+                if ahead.len() == 1 { "" } else { "s" },
+            );
+        }
+        Ok(events)
+    }
+
+    /// cover letter and tip commit of the latest revision of `proposal`,
+    /// recreated as commits on its parent commit
+    async fn recreate_proposal(&self, proposal: &Event) -> Result<Option<(CoverLetter, Sha1Hash)>> {
+        let git_repo_path = self.git_repo.get_path()?;
+        let Ok(cover_letter) = event_to_cover_letter(proposal) else {
+            return Ok(None);
+        };
+        let Ok(chain) = get_most_recent_patch_with_ancestors(
+            get_all_proposal_patch_events_from_cache(git_repo_path, &self.repo_ref, &proposal.id)
+                .await?,
+        ) else {
+            return Ok(None);
+        };
+        if chain.iter().any(|patch| !patch_supports_commit_ids(patch)) {
+            println!(
+                "skipping proposal \"{}\" as it can't be recreated as commits",
+                cover_letter.title
+            );
+            return Ok(None);
+        }
+        let oldest_first: Vec<Event> = chain.into_iter().rev().collect();
+        let Some(parent) = oldest_first
+            .first()
+            .and_then(|patch| tag_value(patch, "parent-commit").ok())
+            .and_then(|parent| str_to_sha1(&parent).ok())
+            .filter(|parent| {
+                self.git_repo
+                    .does_commit_exist(&parent.to_string())
+                    .is_ok_and(|e| e)
+            })
+        else {
+            println!(
+                "skipping proposal \"{}\" as its parent commit isn't in the local repository",
+                cover_letter.title
+            );
+            return Ok(None);
+        };
+        let tip = self
+            .git_repo
+            .create_commits_from_patches(&oldest_first, &parent)?;
+        Ok(Some((cover_letter, tip)))
+    }
+
+    async fn publish(self, cli_args: &Cli, events: Vec<Event>) -> Result<()> {
+        if events.is_empty() {
+            println!("nothing new to publish to nostr");
+            return Ok(());
+        }
+        let Self {
+            git_repo,
+            mut client,
+            repo_ref,
+            signer,
+            user_ref,
+            ..
+        } = self;
+        client.set_signer(signer).await;
+        send_events(
+            &client,
+            Some(git_repo.get_path()?),
+            events,
+            user_ref.relays.write(),
+            repo_ref.relays.clone(),
+            !cli_args.disable_cli_spinners,
+            false,
+        )
+        .await
+    }
+}
+
+/// import open GitLab merge requests as proposals
+pub async fn launch_gitlab(cli_args: &Cli, args: &GitLabSubCommandArgs) -> Result<()> {
+    let token = args
+        .token
+        .clone()
+        .or_else(|| std::env::var("GITLAB_TOKEN").ok());
+    let gitlab = GitLabClient::new(&args.url, token.as_deref())?;
+    let bridge = Bridge::load(
+        cli_args,
+        Repo::discover().context("failed to find a git repository")?,
+    )
+    .await?;
+    let merge_requests = gitlab.list_open_merge_requests().await?;
+    let events = bridge
+        .import(&merge_requests, |mr| {
+            gitlab.fetch_merge_request(&bridge.git_repo, mr.number)
+        })
+        .await?;
+    bridge.publish(cli_args, events).await
+}
+
+/// import open Forgejo or Gitea pull requests as proposals
+pub async fn launch_forgejo(cli_args: &Cli, args: &ForgejoSubCommandArgs) -> Result<()> {
+    let token = args
+        .token
+        .clone()
+        .or_else(|| std::env::var("FORGEJO_TOKEN").ok());
+    let forgejo = ForgejoClient::new(&args.url, token.as_deref())?;
+    let bridge = Bridge::load(
+        cli_args,
+        Repo::discover().context("failed to find a git repository")?,
+    )
+    .await?;
+    let pull_requests = forgejo.list_open_pull_requests().await?;
+    let events = bridge
+        .import(&pull_requests, |pr| {
+            forgejo.fetch_pull_request(&bridge.git_repo, pr.number)
+        })
+        .await?;
+    bridge.publish(cli_args, events).await
+}
+
+/// import open GitHub pull requests as proposals, export open proposals as
+/// pull requests and mirror closed / merged statuses in both directions
+#[allow(clippy::too_many_lines)]
+pub async fn launch_github(cli_args: &Cli, args: &GitHubSubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let github_repo = GitHubRepo::parse(&if let Some(repo) = &args.repo {
+        repo.clone()
+    } else {
+        git_repo.get_git_config_item("nostr.github-repo", None)?.context(
+            "no GitHub repository specified. use --repo owner/name or set git config nostr.github-repo",
+        )?
+    })?;
+    let token = if let Some(token) = &args.token {
+        token.clone()
+    } else if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        token
+    } else {
+        git_repo
+            .get_git_config_item("nostr.github-token", None)?
+            .context(
+                "no GitHub token. use --token, set GITHUB_TOKEN or git config nostr.github-token",
+            )?
+    };
+
+    let bridge = Bridge::load(cli_args, git_repo).await?;
+    let Bridge {
+        git_repo,
+        repo_ref,
+        signer,
+        user_ref,
+        proposals,
+        ..
+    } = &bridge;
+    let git_repo_path = git_repo.get_path()?;
+
+    if !repo_ref.maintainers.contains(&user_ref.public_key) {
+        bail!(
+            "your nostr account {} isn't listed as a maintainer of the repo",
+            user_ref.metadata.name
+        );
+    }
+
+    let github = GitHubClient::new(&token);
+    let bridge_login = github.authenticated_user().await?;
+    let pull_requests = github.list_pull_requests(&github_repo).await?;
+
+    let statuses = get_statuses_from_cache(git_repo_path, proposals).await?;
+    let status_of = |proposal: &Event| proposal_status(repo_ref, &statuses, proposal);
+
+    let mut events = vec![];
+    let mut linked_proposals: HashSet<nostr::EventId> = HashSet::new();
+
+    // mirror statuses of proposals and pull requests already bridged
+    for pull_request in &pull_requests {
+        let Some(proposal) = proposals.iter().find(|p| {
+            bridged_proposal_id(pull_request, &bridge_login).is_some_and(|id| id.eq(&p.id))
+                || tag_value(p, IMPORTED_FROM_TAG).is_ok_and(|url| url.eq(&pull_request.html_url))
+        }) else {
+            continue;
+        };
+        linked_proposals.insert(proposal.id);
+        let status = status_of(proposal);
+        match (pull_request.state, status) {
+            (PullRequestState::Open, Kind::GitStatusClosed | Kind::GitStatusApplied) => {
+                github
+                    .close_pull_request(
+                        &github_repo,
+                        pull_request.number,
+                        &format!(
+                            "{} on nostr: {}",
+                            if status.eq(&Kind::GitStatusApplied) {
+                                "applied"
+                            } else {
+                                "closed"
+                            },
+                            nevent(proposal, &repo_ref)?,
+                        ),
+                    )
+                    .await?;
+                println!("closed GitHub pull request #{}", pull_request.number);
+            }
+            (PullRequestState::Merged, Kind::GitStatusOpen | Kind::GitStatusDraft) => {
+                events.push(
+                    create_status_event(
+                        &signer,
+                        &repo_ref,
+                        proposal,
+                        Kind::GitStatusApplied,
+                        &format!("merged on GitHub: {}", pull_request.html_url),
+                    )
+                    .await?,
+                );
+            }
+            (PullRequestState::Closed, Kind::GitStatusOpen | Kind::GitStatusDraft) => {
+                events.push(
+                    create_status_event(
+                        &signer,
+                        &repo_ref,
+                        proposal,
+                        Kind::GitStatusClosed,
+                        &format!("closed on GitHub: {}", pull_request.html_url),
+                    )
+                    .await?,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    if !args.no_import {
+        events.append(
+            &mut bridge
+                .import(&pull_requests, |pr| {
+                    fetch_pull_request(git_repo, &github_repo, &token, pr.number)
+                })
+                .await?,
+        );
+    }
+
+    if !args.no_export {
+        let (main_branch_name, _) = git_repo.get_main_or_master_branch()?;
+        let base = main_branch_name.trim_start_matches("origin/").to_string();
+        for proposal in proposals.iter().filter(|p| {
+            !linked_proposals.contains(&p.id)
+                && tag_value(p, IMPORTED_FROM_TAG).is_err()
+                && status_of(p).eq(&Kind::GitStatusOpen)
+        }) {
+            let Some((cover_letter, tip)) = bridge.recreate_proposal(proposal).await? else {
+                continue;
+            };
+            let branch = format!(
+                "nostr/{}-{}",
+                cover_letter.branch_name,
+                &proposal.id.to_hex()[..8]
+            );
+            push_branch(&git_repo, &github_repo, &token, &tip, &branch)?;
+            let pull_request = github
+                .create_pull_request(
+                    &github_repo,
+                    &cover_letter.title,
+                    &format!(
+                        "{}\n\nopened on nostr by {}: {}\n\n{}",
+                        cover_letter.description,
+                        get_author_display_name(Some(git_repo_path), &proposal.pubkey).await,
+                        nevent(proposal, &repo_ref)?,
+                        proposal_marker(&proposal.id),
+                    ),
+                    &branch,
+                    &base,
+                )
+                .await?;
+            println!(
+                "exported proposal \"{}\" as GitHub pull request #{}",
+                cover_letter.title, pull_request.number
+            );
+        }
+    }
+
+    bridge.publish(cli_args, events).await
+}
+
+/// import open Radicle patches as proposals and export open proposals as
+/// Radicle patches
+pub async fn launch_radicle(cli_args: &Cli, args: &RadicleSubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let rid = if let Some(rid) = &args.rid {
+        rid.clone()
+    } else {
+        git_repo.get_git_config_item("nostr.radicle-rid", None)?.context(
+            "no Radicle repository specified. pass a rad: id or set git config nostr.radicle-rid",
+        )?
+    };
+    let radicle = RadicleClient::new(&args.seed, &rid)?;
+    let identities = get_radicle_identities(&git_repo)?;
+
+    let bridge = Bridge::load(cli_args, git_repo).await?;
+    let git_repo_path = bridge.git_repo.get_path()?;
+
+    let mut patches = vec![];
+    for state in ["open", "draft", "archived", "merged"] {
+        patches.append(&mut radicle.list_patches(state).await?);
+    }
+    let pull_requests: Vec<PullRequest> = patches
+        .iter()
+        .map(|patch| PullRequest {
+            author_npub: identities
+                .iter()
+                .find(|(did, _)| {
+                    patch
+                        .pull_request
+                        .author_name
+                        .as_ref()
+                        .is_some_and(|a| a.eq(did))
+                })
+                .map(|(_, npub)| *npub),
+            ..patch.pull_request.clone()
+        })
+        .collect();
+    let events = bridge
+        .import(&pull_requests, |pr| {
+            let patch = patches
+                .iter()
+                .find(|p| p.pull_request.html_url.eq(&pr.html_url))
+                .context("Radicle patch not found")?;
+            radicle.fetch_patch(&bridge.git_repo, patch)
+        })
+        .await?;
+
+    if !args.no_export {
+        if !bridge
+            .repo_ref
+            .maintainers
+            .contains(&bridge.user_ref.public_key)
+        {
+            bail!(
+                "your nostr account {} isn't listed as a maintainer of the repo. use --no-export to only import",
+                bridge.user_ref.metadata.name
+            );
+        }
+        let statuses = get_statuses_from_cache(git_repo_path, &bridge.proposals).await?;
+        for proposal in bridge.proposals.iter().filter(|p| {
+            tag_value(p, IMPORTED_FROM_TAG).is_err()
+                && proposal_status(&bridge.repo_ref, &statuses, p).eq(&Kind::GitStatusOpen)
+                && !pull_requests
+                    .iter()
+                    .any(|pr| proposal_id_from_pr_body(&pr.body).is_some_and(|id| id.eq(&p.id)))
+        }) {
+            let Some((cover_letter, tip)) = bridge.recreate_proposal(proposal).await? else {
+                continue;
+            };
+            let author = if let Some((did, _)) = identities
+                .iter()
+                .find(|(_, npub)| npub.eq(&proposal.pubkey))
+            {
+                did.clone()
+            } else {
+                get_author_display_name(Some(git_repo_path), &proposal.pubkey).await
+            };
+            open_patch(
+                &bridge.git_repo,
+                &args.remote,
+                &tip,
+                &cover_letter.title,
+                &format!(
+                    "{}\n\nopened on nostr by {author}: {}\n\n{}",
+                    cover_letter.description,
+                    nevent(proposal, &bridge.repo_ref)?,
+                    proposal_marker(&proposal.id),
+                ),
+            )?;
+            println!(
+                "exported proposal \"{}\" as a Radicle patch",
+                cover_letter.title
+            );
+        }
+    }
+
+    bridge.publish(cli_args, events).await
+}
+
+/// push proposal statuses to the Patchwork patches parsed from the mailing
+/// list, or write the proposals as an mbox to import into Patchwork
+pub async fn launch_patchwork(args: &PatchworkSubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    let proposals: Vec<Event> = get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?
+    .into_iter()
+    .filter(|e| !event_is_revision_root(e))
+    .collect();
+    let statuses = get_statuses_from_cache(git_repo_path, &proposals).await?;
+
+    let mut series = vec![];
+    for proposal in &proposals {
+        let Ok(chain) = get_most_recent_patch_with_ancestors(
+            get_all_proposal_patch_events_from_cache(git_repo_path, &repo_ref, &proposal.id)
+                .await?,
+        ) else {
+            continue;
+        };
+        let mut events: Vec<Event> = chain.into_iter().rev().collect();
+        if event_is_cover_letter(proposal) {
+            events.insert(0, proposal.clone());
+        }
+        series.push((proposal, events));
+    }
+
+    if let Some(path) = &args.mbox {
+        let emails: Vec<String> = series
+            .iter()
+            .flat_map(|(_, events)| render_patch_emails(events, None, &[]))
+            .collect();
+        std::fs::write(path, to_mbox(&emails))
+            .context(format!("failed to write mbox to {}", path.display()))?;
+        println!(
+            "wrote {} proposal{} to {}. import with `python manage.py parsearchive --list-id=<list-id> {}`",
+            series.len(),
+            if series.len() == 1 { "" } else { "s" },
+            path.display(),
+            path.display(),
+        );
+        return Ok(());
+    }
+
+    let (Some(url), Some(project)) = (&args.url, &args.project) else {
+        bail!("--url and --project are required unless --mbox is used");
+    };
+    let token = args
+        .token
+        .clone()
+        .or_else(|| std::env::var("PATCHWORK_TOKEN").ok())
+        .context("no Patchwork token. use --token or set PATCHWORK_TOKEN")?;
+    let patchwork = PatchworkClient::new(url, project, Some(&token));
+
+    let mut missing = 0;
+    for (proposal, events) in &series {
+        let state = patchwork_state(proposal_status(&repo_ref, &statuses, proposal));
+        for event in events.iter().filter(|e| !event_is_cover_letter(e)) {
+            let Some(patch) = patchwork.find_patch(&message_id(event)).await? else {
+                missing += 1;
+                continue;
+            };
+            if patch.state != state {
+                patchwork.set_patch_state(patch.id, state).await?;
+                println!("marked Patchwork patch {} as {state}", patch.id);
+            }
+        }
+    }
+    if missing > 0 {
+        println!(
+            "{missing} patch{} not in Patchwork yet. send proposals to the mailing list with `ngit send --email` or import them with `ngit bridge patchwork --mbox`",
+            if missing == 1 { " is" } else { "es are" },
+        );
+    }
+    Ok(())
+}
+
+/// status events for `proposals`, most recent first
+async fn get_statuses_from_cache(
+    git_repo_path: &std::path::Path,
+    proposals: &[Event],
+) -> Result<Vec<Event>> {
+    let mut statuses = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            nostr::Filter::default()
+                .kinds(status_kinds().clone())
+                .events(proposals.iter().map(|e| e.id)),
+        ],
+    )
+    .await?;
+    sort_newest_first(&mut statuses);
+    Ok(statuses)
+}
+
+/// latest status of `proposal` set by its author or a maintainer
+fn proposal_status(repo_ref: &RepoRef, statuses: &[Event], proposal: &Event) -> Kind {
+    get_proposal_status(proposal, statuses, &repo_ref.maintainers).0
+}
+
+fn nevent(proposal: &Event, repo_ref: &RepoRef) -> Result<String> {
+    Ok(format!("nostr:{}", repo_ref.nevent(&proposal.id)?))
+}
+
+async fn create_status_event(
+    signer: &Arc<dyn NostrSigner>,
+    repo_ref: &RepoRef,
+    proposal: &Event,
+    kind: Kind,
+    content: &str,
+) -> Result<Event> {
+    let public_keys: HashSet<PublicKey> = repo_ref
+        .maintainers
+        .iter()
+        .copied()
+        .chain([proposal.pubkey])
+        .collect();
+    sign_event(
+        EventBuilder::new(kind, content).tags(
+            [
+                vec![
+                    Tag::custom(
+                        TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
+                        vec![format!(
+                            "git proposal {}",
+                            if kind.eq(&Kind::GitStatusApplied) {
+                                "merged / applied"
+                            } else {
+                                "closed"
+                            }
+                        )],
+                    ),
+                    Tag::from_standardized(TagStandard::Event {
+                        event_id: proposal.id,
+                        relay_url: repo_ref.relays.first().cloned(),
+                        marker: Some(Marker::Root),
+                        public_key: None,
+                        uppercase: false,
+                    }),
+                    Tag::from_standardized(TagStandard::Reference(
+                        repo_ref.root_commit.to_string(),
+                    )),
+                ],
+                public_keys.iter().map(|pk| Tag::public_key(*pk)).collect(),
+                repo_ref
+                    .coordinates()
+                    .iter()
+                    .map(|c| Tag::coordinate(c.clone()))
+                    .collect::<Vec<Tag>>(),
+            ]
+            .concat(),
+        ),
+        signer,
+    )
+    .await
+    .context("failed to create status event")
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use ngit::{
+    blossom::upload_blob,
+    git::bundle::{create_bundle, fetch_from_bundle_uri},
+};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{
+        Client, Connect, fetching_with_report, get_repo_ref_from_cache, resolve_repo_trust,
+        send_events,
+    },
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct CreateSubCommandArgs {
+    #[clap(short, long, default_value = "ngit.bundle")]
+    /// path to write the bundle to
+    output: PathBuf,
+    #[clap(long, action)]
+    /// upload to the repository's blossom servers and advertise in the
+    /// repository announcement
+    upload: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct FetchSubCommandArgs {
+    /// bundle url or path. defaults to bundles listed in the repository
+    /// announcement
+    uri: Option<String>,
+}
+
+pub async fn launch_create(cli_args: &Cli, args: &CreateSubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    create_bundle(&git_repo, &args.output)?;
+    println!("created bundle {}", args.output.display());
+
+    if !args.upload {
+        return Ok(());
+    }
+
+    let mut client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    let fetch_report = fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    resolve_repo_trust(&git_repo, &fetch_report, &repo_coordinates).await?;
+
+    let mut repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    if repo_ref.blossoms.is_empty() {
+        bail!(
+            "repository announcement lists no blossom servers. add some with `ngit init --blossoms` or host the bundle elsewhere and use `ngit init --bundles`"
+        );
+    }
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    if !repo_ref.maintainers.contains(&user_ref.public_key) {
+        bail!(
+            "your nostr account {} isn't listed as a maintainer of the repo",
+            user_ref.metadata.name
+        );
+    }
+
+    let bundle = std::fs::read(&args.output).context("failed to read bundle")?;
+
+    let mut bundles = vec![];
+    for server in &repo_ref.blossoms {
+        match upload_blob(server, bundle.clone(), &signer).await {
+            Ok(hash) => {
+                let url = format!("{}/{hash}", server.trim_end_matches('/'));
+                println!("uploaded bundle to {url}");
+                bundles.push(url);
+            }
+            Err(error) => eprintln!("failed to upload bundle to {server}: {error}"),
+        }
+    }
+    if bundles.is_empty() {
+        bail!("failed to upload bundle to any blossom server");
+    }
+
+    repo_ref.bundles = bundles;
+    let repo_event = repo_ref.to_event(&signer).await?;
+
+    client.set_signer(signer).await;
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![repo_event],
+        user_ref.relays.write(),
+        repo_ref.relays.clone(),
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn launch_fetch(args: &FetchSubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let uris = if let Some(uri) = &args.uri {
+        vec![uri.clone()]
+    } else {
+        let client = Client::default();
+
+        let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+        let fetch_report = fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+        resolve_repo_trust(&git_repo, &fetch_report, &repo_coordinates).await?;
+
+        let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+        if repo_ref.bundles.is_empty() {
+            bail!("repository announcement lists no bundles");
+        }
+        repo_ref.bundles
+    };
+
+    for uri in &uris {
+        match fetch_from_bundle_uri(&git_repo, uri).await {
+            Ok(()) => {
+                println!("fetched bundle {uri} into refs/nostr-bundle/");
+                return Ok(());
+            }
+            Err(error) => eprintln!("failed to fetch bundle {uri}: {error}"),
+        }
+    }
+    bail!("failed to fetch any bundle")
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashSet, path::Path, time::Duration};
+
+use anyhow::{Result, bail};
+use ngit::{
+    client::consolidate_fetch_reports,
+    recent_repos::{get_recent_repos, record_refreshed},
+};
+
+use crate::{
+    client::{Client, Connect},
+    git::Repo,
+    repo_ref::try_and_get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// seconds between refreshes
+    #[clap(long, default_value_t = 60)]
+    interval: u64,
+    /// only refresh repositories used within this many days
+    #[clap(long, default_value_t = 14)]
+    days: u64,
+    /// refresh once and exit, eg. when run from cron
+    #[clap(long)]
+    once: bool,
+}
+
+/// keep the caches of recently used repositories warm so other commands and
+/// the remote helper can skip fetching from relays
+pub async fn launch(args: &SubCommandArgs) -> Result<()> {
+    loop {
+        for repo in get_recent_repos(args.days * 24 * 60 * 60)? {
+            match refresh(&repo.path).await {
+                Ok(report) => println!("{}: {report}", repo.path.display()),
+                Err(error) => eprintln!("{}: {error:#}", repo.path.display()),
+            }
+        }
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+    }
+}
+
+async fn refresh(git_repo_path: &Path) -> Result<String> {
+    let git_repo = Repo::from_path(&git_repo_path.to_path_buf())?;
+    let repo_coordinates = try_and_get_repo_coordinates_when_remote_unknown(&git_repo).await?;
+    let client = Client::default();
+    let (relay_reports, _) = client
+        .fetch_all(
+            Some(git_repo_path),
+            Some(&repo_coordinates),
+            &HashSet::new(),
+        )
+        .await?;
+    client.disconnect().await?;
+    if relay_reports.iter().all(std::result::Result::is_err) {
+        bail!("no relays responded");
+    }
+    record_refreshed(git_repo_path)?;
+    let report = consolidate_fetch_reports(relay_reports).to_string();
+    Ok(if report.is_empty() {
+        "no updates".to_string()
+    } else {
+        format!("updates: {report}")
+    })
+}
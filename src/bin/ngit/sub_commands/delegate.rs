@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use ngit::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptPasswordParms},
+    delegation::{DEFAULT_DELEGATED_KINDS, create_delegation},
+    login::key_encryption::decrypt_key,
+};
+use nostr::{Keys, PublicKey, Timestamp};
+
+use crate::cli::Cli;
+
+#[derive(clap::Args)]
+pub struct SubCommandArgs {
+    /// npub of the bot / CI key allowed to sign on your behalf
+    delegatee: String,
+
+    /// event kinds to delegate. defaults to patches and statuses
+    #[arg(long, value_delimiter = ',')]
+    kinds: Vec<u16>,
+
+    /// number of days the delegation is valid for
+    #[arg(long, default_value_t = 90)]
+    days: u64,
+}
+
+pub async fn launch(args: &Cli, command_args: &SubCommandArgs) -> Result<()> {
+    let delegatee = PublicKey::parse(&command_args.delegatee).context("invalid delegatee npub")?;
+
+    let nsec = if let Some(nsec) = &args.nsec {
+        nsec.clone()
+    } else {
+        Interactor::default()
+            .password(
+                PromptPasswordParms::default()
+                    .with_prompt("your nsec or ncryptsec")
+                    .dont_report(),
+            )
+            .context("failed to get nsec input from interactor.password")?
+    };
+    let delegator = if nsec.starts_with("ncryptsec") {
+        let password = if let Some(password) = &args.password {
+            password.clone()
+        } else {
+            Interactor::default()
+                .password(
+                    PromptPasswordParms::default()
+                        .with_prompt("password")
+                        .dont_report(),
+                )
+                .context("failed to get password input from interactor.password")?
+        };
+        decrypt_key(&nsec, &password).context("failed to decrypt ncryptsec")?
+    } else {
+        Keys::from_str(&nsec).context("invalid nsec")?
+    };
+
+    let kinds = if command_args.kinds.is_empty() {
+        DEFAULT_DELEGATED_KINDS.to_vec()
+    } else {
+        command_args.kinds.clone()
+    };
+    let delegation = create_delegation(
+        &delegator,
+        &delegatee,
+        &kinds,
+        Timestamp::now() + command_args.days * 24 * 60 * 60,
+    )?;
+
+    println!("{}", delegation.as_json());
+    eprintln!(
+        "on the bot / CI machine run `git config --global nostr.delegation '<tag above>'` and login with its own nsec"
+    );
+    Ok(())
+}
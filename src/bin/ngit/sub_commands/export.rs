@@ -0,0 +1,66 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptPasswordParms},
+    login::{
+        SignerInfo,
+        existing::get_signer_info,
+        key_encryption::{decrypt_key, encrypt_key},
+        os_keyring::resolve_nsec,
+    },
+};
+
+use crate::git::Repo;
+
+pub async fn launch() -> Result<()> {
+    let git_repo = Repo::discover().ok();
+
+    let Ok((signer_info, _)) = get_signer_info(&git_repo.as_ref(), &None, &None, &None) else {
+        bail!("not logged in so no keys are stored");
+    };
+    let (nsec, password, npub) = match signer_info {
+        SignerInfo::Nsec {
+            nsec,
+            password,
+            npub,
+        } => (nsec, password, npub),
+        SignerInfo::Bunker { .. } => {
+            bail!("logged in using nostr connect so your keys are stored in a remote signer")
+        }
+        SignerInfo::Hardware { .. } => {
+            bail!("logged in using a hardware signer so your keys never leave the device")
+        }
+        SignerInfo::BrowserExtension { .. } => {
+            bail!("logged in using a browser extension so your keys are stored in the extension")
+        }
+    };
+
+    let nsec = resolve_nsec(&nsec, &npub)?;
+    let keys = if nsec.contains("ncryptsec") {
+        let password = if let Some(password) = password {
+            password
+        } else {
+            Interactor::default()
+                .password(PromptPasswordParms::default().with_prompt("current password"))
+                .context("failed to get password input from interactor.password")?
+        };
+        decrypt_key(&nsec, &password).context("failed to decrypt stored ncryptsec")?
+    } else {
+        nostr::Keys::parse(&nsec).context("stored nsec is invalid")?
+    };
+
+    let password = Interactor::default()
+        .password(
+            PromptPasswordParms::default()
+                .with_prompt("password to encrypt exported key")
+                .with_confirm(),
+        )
+        .context("failed to get password input from interactor.password")?;
+    if password.is_empty() {
+        bail!("a password is required to export an encrypted key");
+    }
+
+    // print to stdout so it can be redirected to a file
+    println!("{}", encrypt_key(&keys, &password)?);
+    eprintln!("import on another machine with `ngit account import`");
+    Ok(())
+}
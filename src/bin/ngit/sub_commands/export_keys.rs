@@ -5,6 +5,7 @@ use ngit::{
         SignerInfo, SignerInfoSource,
         existing::{get_signer_info, load_existing_login},
         fresh::generate_qr,
+        os_keyring::resolve_nsec,
     },
 };
 
@@ -52,11 +53,24 @@ pub async fn launch() -> Result<()> {
                     );
                     return Ok(());
                 }
+                SignerInfo::Hardware { device: _, npub: _ } => {
+                    eprintln!(
+                        "failed: {logged_in_msg} using a hardware signer so your keys never leave the device"
+                    );
+                    return Ok(());
+                }
+                SignerInfo::BrowserExtension { npub: _ } => {
+                    eprintln!(
+                        "failed: {logged_in_msg} using a browser extension so your keys are stored in the extension"
+                    );
+                    return Ok(());
+                }
                 SignerInfo::Nsec {
                     nsec,
                     password: _,
-                    npub: _,
+                    npub,
                 } => {
+                    let nsec = resolve_nsec(&nsec, &npub)?;
                     match Interactor::default().choice(
                         PromptChoiceParms::default()
                             .with_default(0)
@@ -0,0 +1,51 @@
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use ngit::{client::get_global_cache_path, login::get_curent_user};
+use nostr::ToBech32;
+
+use crate::{
+    git::{Repo, RepoActions},
+    repo_ref::try_and_get_repo_coordinates_when_remote_unknown,
+};
+
+/// run `ngit-<name>` from PATH with the remaining arguments, passing context in
+/// `NGIT_REPO_PATH`, `NGIT_REPO_COORDINATE`, `NGIT_NPUB` and `NGIT_CACHE_PATH`
+/// when known. exits with the plugin's exit code
+pub async fn launch(args: &[String]) -> Result<()> {
+    let Some((name, plugin_args)) = args.split_first() else {
+        bail!("no subcommand given. see 'ngit --help'");
+    };
+    let executable = format!("ngit-{name}");
+    let mut command = Command::new(&executable);
+    command.args(plugin_args);
+
+    let git_repo = Repo::discover().ok();
+    if let Some(git_repo) = &git_repo {
+        let git_repo_path = git_repo.get_path()?;
+        command.env("NGIT_REPO_PATH", git_repo_path);
+        if let Ok(coordinate) = try_and_get_repo_coordinates_when_remote_unknown(git_repo).await {
+            command.env("NGIT_REPO_COORDINATE", coordinate.to_bech32()?);
+        }
+        if let Ok(Some(public_key)) = get_curent_user(git_repo) {
+            command.env("NGIT_NPUB", public_key.to_bech32()?);
+        }
+    }
+    if let Ok(cache_path) = get_global_cache_path(
+        git_repo
+            .as_ref()
+            .and_then(|git_repo| git_repo.get_path().ok()),
+    ) {
+        command.env("NGIT_CACHE_PATH", cache_path);
+    }
+
+    let status = match command.status() {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            bail!(
+                "'{name}' is not an ngit command and {executable} isn't in PATH. see 'ngit --help'"
+            )
+        }
+        status => status.context(format!("failed to run {executable}"))?,
+    };
+    std::process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use ngit::{
+    client::get_proposals_and_revisions_from_cache,
+    delegation::event_author,
+    feed::{FeedEntry, render_atom_feed},
+    git_events::{event_is_revision_root, event_to_cover_letter, status_kinds, tag_value},
+    login::user::get_author_display_name,
+    repo_ref::get_viewer_url,
+};
+use nostr::{Event, Filter, Kind, SingleLetterTag};
+
+use crate::{
+    client::{Client, fetching_with_report, get_events_from_local_cache, get_repo_ref_from_cache},
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    #[clap(short, long)]
+    /// file to write the feed to. defaults to stdout
+    output: Option<PathBuf>,
+    #[clap(long, default_value_t = 50)]
+    /// maximum number of entries
+    limit: usize,
+    #[clap(long)]
+    /// web client to link entries to. defaults to git config
+    /// nostr.viewer-url or https://gitworkshop.dev
+    viewer_url: Option<String>,
+    #[clap(long, action)]
+    /// use cached events without fetching updates
+    offline: bool,
+}
+
+/// render recent proposals, issues and statuses as an atom feed
+pub async fn launch(args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    if !args.offline {
+        fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    }
+
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    let viewer_url = args
+        .viewer_url
+        .clone()
+        .unwrap_or_else(|| get_viewer_url(&Some(&git_repo)));
+
+    let proposals: Vec<Event> = get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?
+    .into_iter()
+    .filter(|e| !event_is_revision_root(e))
+    .collect();
+    let title_of = |proposal: &Event| {
+        event_to_cover_letter(proposal).map_or_else(|_| "untitled".to_string(), |c| c.title)
+    };
+
+    let issues = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            Filter::default().kind(Kind::GitIssue).custom_tag(
+                SingleLetterTag::lowercase(nostr_sdk::Alphabet::A),
+                repo_ref
+                    .coordinates_including_previous_identifiers()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>(),
+            ),
+        ],
+    )
+    .await?;
+
+    let statuses = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            Filter::default()
+                .kinds(status_kinds())
+                .events(proposals.iter().map(|e| e.id)),
+        ],
+    )
+    .await?;
+
+    let mut events: Vec<&Event> = proposals
+        .iter()
+        .chain(issues.iter())
+        .chain(statuses.iter().filter(|s| {
+            // statuses from others can't change a proposal's status
+            proposals.iter().any(|p| {
+                (event_author(s).eq(&event_author(p))
+                    || repo_ref.maintainers.contains(&event_author(s)))
+                    && s.tags.event_ids().any(|id| id.eq(&p.id))
+            })
+        }))
+        .collect();
+    events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    events.truncate(args.limit);
+
+    let mut entries = vec![];
+    for event in events {
+        let author = get_author_display_name(Some(git_repo_path), &event_author(event)).await;
+        let (link_id, title, summary) = if status_kinds().contains(&event.kind) {
+            let Some(proposal) = proposals
+                .iter()
+                .find(|p| event.tags.event_ids().any(|id| id.eq(&p.id)))
+            else {
+                continue;
+            };
+            (
+                proposal.id,
+                format!(
+                    "{} {}",
+                    match event.kind {
+                        Kind::GitStatusApplied => "applied",
+                        Kind::GitStatusClosed => "closed",
+                        Kind::GitStatusDraft => "marked as draft",
+                        _ => "reopened",
+                    },
+                    title_of(proposal),
+                ),
+                event.content.clone(),
+            )
+        } else if event.kind.eq(&Kind::GitIssue) {
+            (
+                event.id,
+                format!(
+                    "issue: {}",
+                    tag_value(event, "subject").unwrap_or_else(|_| {
+                        event.content.lines().next().unwrap_or_default().to_string()
+                    })
+                ),
+                event.content.clone(),
+            )
+        } else {
+            (
+                event.id,
+                format!("proposal: {}", title_of(event)),
+                event_to_cover_letter(event)
+                    .map(|c| c.description)
+                    .unwrap_or_default(),
+            )
+        };
+        let (link, _, _) = repo_ref.event_links(
+            &viewer_url,
+            &link_id,
+            if event.kind.eq(&Kind::GitIssue) {
+                Kind::GitIssue
+            } else {
+                Kind::GitPatch
+            },
+        )?;
+        entries.push(FeedEntry {
+            id: format!("nostr:{}", repo_ref.nevent(&event.id)?),
+            title,
+            link,
+            author,
+            updated: event.created_at.as_u64(),
+            summary,
+        });
+    }
+
+    let (repo_link, repo_uri) = repo_ref.links(&viewer_url)?;
+    let feed = render_atom_feed(&repo_uri, &repo_ref.name, &repo_link, &entries);
+    if let Some(output) = &args.output {
+        std::fs::write(output, feed)
+            .context(format!("failed to write feed to {}", output.display()))?;
+        eprintln!(
+            "wrote {} entr{} to {}",
+            entries.len(),
+            if entries.len() == 1 { "y" } else { "ies" },
+            output.display()
+        );
+    } else {
+        print!("{feed}");
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptInputParms, PromptPasswordParms},
+    login::{SignerInfo, fresh::fresh_login_or_signup, key_encryption::decrypt_key},
+};
+use nostr_sdk::ToBech32;
+
+use crate::{
+    client::{Client, Connect},
+    git::Repo,
+};
+
+#[derive(clap::Args)]
+pub struct SubCommandArgs {
+    /// ncryptsec created by `ngit account export`. prompted for if missing
+    ncryptsec: Option<String>,
+
+    /// login to the local git repository only
+    #[arg(long, action)]
+    local: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+
+    let ncryptsec = if let Some(ncryptsec) = &args.ncryptsec {
+        ncryptsec.clone()
+    } else {
+        Interactor::default()
+            .input(PromptInputParms::default().with_prompt("ncryptsec"))
+            .context("failed to get ncryptsec input from interactor")?
+    };
+    if !ncryptsec.trim().starts_with("ncryptsec") {
+        bail!("expected an ncryptsec created by `ngit account export`");
+    }
+
+    let password = Interactor::default()
+        .password(
+            PromptPasswordParms::default()
+                .with_prompt("password")
+                .dont_report(),
+        )
+        .context("failed to get password input from interactor.password")?;
+    let keys = decrypt_key(ncryptsec.trim(), &password)
+        .context("failed to decrypt ncryptsec with provided password")?;
+
+    let client = Client::default();
+    fresh_login_or_signup(
+        &git_repo.as_ref(),
+        Some(&client),
+        Some(SignerInfo::Nsec {
+            nsec: keys.secret_key().to_bech32()?,
+            password: None,
+            npub: Some(keys.public_key().to_bech32()?),
+        }),
+        args.local,
+        None,
+    )
+    .await?;
+    client.disconnect().await?;
+    Ok(())
+}
@@ -1,10 +1,13 @@
 use std::collections::{HashMap, HashSet};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use console::{Style, Term};
 use ngit::{
     cli_interactor::PromptConfirmParms,
     git::nostr_url::{NostrUrlDecoded, save_nip05_to_git_config_cache},
+    github::{GitHubClient, GitHubRepo, GitHubRepoMetadata},
+    org::fetch_org,
+    output, theme,
 };
 use nostr::{
     FromBech32, PublicKey, ToBech32,
@@ -14,11 +17,15 @@ use nostr::{
     },
 };
 use nostr_sdk::{Kind, RelayUrl};
+use serde_json::json;
 
 use crate::{
     cli::{Cli, extract_signer_cli_arguments},
     cli_interactor::{Interactor, InteractorPrompt, PromptInputParms},
-    client::{Client, Connect, fetching_with_report, get_repo_ref_from_cache, send_events},
+    client::{
+        Client, Connect, fetching_with_report, get_repo_ref_from_cache, resolve_repo_trust,
+        send_events,
+    },
     git::{Repo, RepoActions, nostr_url::convert_clone_url_to_https},
     login,
     repo_ref::{
@@ -27,7 +34,7 @@ use crate::{
     },
 };
 
-#[derive(Debug, clap::Args)]
+#[derive(Clone, Debug, clap::Args)]
 pub struct SubCommandArgs {
     #[clap(short, long)]
     /// name of repository
@@ -38,6 +45,12 @@ pub struct SubCommandArgs {
     #[clap(long)]
     /// git server url users can clone from
     clone_url: Vec<String>,
+    #[clap(long, value_parser, num_args = 1..)]
+    /// blossom servers to host git packfiles (enables use without a git server)
+    blossoms: Vec<String>,
+    #[clap(long, value_parser, num_args = 1..)]
+    /// git bundle urls to speed up initial clones
+    bundles: Vec<String>,
     #[clap(short, long, value_parser, num_args = 1..)]
     /// homepage
     web: Vec<String>,
@@ -48,11 +61,41 @@ pub struct SubCommandArgs {
     /// npubs of other maintainers
     other_maintainers: Vec<String>,
     #[clap(long)]
+    /// SPDX license identifier eg. MIT
+    license: Option<String>,
+    #[clap(long)]
+    /// primary programming language
+    language: Option<String>,
+    #[clap(long, value_parser, num_args = 1..)]
+    /// topics to help others discover the repository
+    topics: Vec<String>,
+    #[clap(long)]
+    /// project homepage, if different from the repo website
+    homepage: Option<String>,
+    #[clap(long)]
+    /// how to contact the maintainers eg. email address
+    contact: Option<String>,
+    #[clap(long)]
+    /// fill in the name, description, topics, homepage, license and language
+    /// from the GitHub repository origin points to, where not already set
+    from_github: bool,
+    #[clap(long)]
+    /// naddr or nostr:// url of the repository this is a fork of
+    upstream: Option<String>,
+    #[clap(long)]
+    /// naddr of an organization from `ngit org create`. its members are
+    /// added as maintainers
+    org: Option<String>,
+    #[clap(long)]
     /// usually root commit but will be more recent commit for forks
     earliest_unique_commit: Option<String>,
     #[clap(short, long)]
     /// shortname with no spaces or special characters
     identifier: Option<String>,
+    #[clap(long, conflicts_with = "identifier")]
+    /// publish under a new identifier, linking the old one so its proposals
+    /// stay discoverable, and update local nostr remotes
+    rename: Option<String>,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -78,7 +121,8 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
     };
 
     let repo_ref = if let Some(repo_coordinate) = &repo_coordinate {
-        fetching_with_report(git_repo_path, &client, repo_coordinate).await?;
+        let fetch_report = fetching_with_report(git_repo_path, &client, repo_coordinate).await?;
+        resolve_repo_trust(&git_repo, &fetch_report, repo_coordinate).await?;
         if let Ok(repo_ref) = get_repo_ref_from_cache(Some(git_repo_path), repo_coordinate).await {
             Some(repo_ref)
         } else {
@@ -100,6 +144,44 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
     let repo_config_result = get_repo_config_from_yaml(&git_repo);
     // TODO: check for other claims
 
+    let github = if args.from_github {
+        Some(fetch_github_metadata(&git_repo).await?)
+    } else {
+        None
+    };
+
+    // a new announcement can usually be derived from the remotes and commits
+    // so only needs confirming
+    let derived_args = if repo_ref.is_none() && repo_config_result.is_err() {
+        derive_new_announcement_args(
+            args,
+            &git_repo,
+            github.as_ref(),
+            &root_commit.to_string(),
+            &user_ref.public_key,
+            &if user_ref.relays.read().is_empty() {
+                client.get_fallback_relays().clone()
+            } else {
+                user_ref.relays.read()
+            },
+        )?
+    } else {
+        None
+    };
+    let args = derived_args.as_ref().unwrap_or(args);
+
+    let renamed_from = if args.rename.is_some() {
+        let repo_ref = repo_ref.as_ref().context(
+            "cannot rename as no existing repository announcement was found. run `ngit init` first",
+        )?;
+        if !repo_ref.maintainers.contains(&user_ref.public_key) {
+            bail!("only a maintainer can rename the repository");
+        }
+        Some(repo_ref.identifier.clone())
+    } else {
+        None
+    };
+
     let name = match &args.title {
         Some(t) => t.clone(),
         None => Interactor::default().input(
@@ -107,15 +189,17 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                 .with_prompt("repo name")
                 .with_default(if let Some(repo_ref) = &repo_ref {
                     repo_ref.name.clone()
+                } else if let Some(github) = github.as_ref().filter(|g| !g.name.is_empty()) {
+                    github.name.clone()
                 } else if let Some(coordinate) = &repo_coordinate {
                     coordinate.identifier.clone()
                 } else {
-                    String::new()
+                    name_from_remote_or_directory(&git_repo).unwrap_or_default()
                 }),
         )?,
     };
 
-    let identifier = match &args.identifier {
+    let identifier = match args.rename.as_ref().or(args.identifier.as_ref()) {
         Some(t) => t.clone(),
         None => Interactor::default().input(
             PromptInputParms::default()
@@ -127,18 +211,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                 } else if let Some(repo_coordinate) = &repo_coordinate {
                     repo_coordinate.identifier.clone()
                 } else {
-                    let fallback = name
-                        .clone()
-                        .replace(' ', "-")
-                        .chars()
-                        .map(|c| {
-                            if c.is_ascii_alphanumeric() || c.eq(&'/') {
-                                c
-                            } else {
-                                '-'
-                            }
-                        })
-                        .collect();
+                    let fallback = identifier_from_name(&name);
                     if let Ok(config) = &repo_config_result {
                         if let Some(identifier) = &config.identifier {
                             identifier.to_string()
@@ -158,11 +231,14 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
             PromptInputParms::default()
                 .with_prompt("repo description (one sentance)")
                 .optional()
-                .with_default(if let Some(repo_ref) = &repo_ref {
-                    repo_ref.description.clone()
-                } else {
-                    String::new()
-                }),
+                .with_default(
+                    repo_ref
+                        .as_ref()
+                        .map(|r| r.description.clone())
+                        .filter(|d| !d.is_empty())
+                        .or_else(|| github.as_ref().and_then(|g| g.description.clone()))
+                        .unwrap_or_default(),
+                ),
         )?,
     };
 
@@ -286,18 +362,8 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
                     .with_prompt("git server remote url(s) (space seperated)")
                     .with_default(if let Some(repo_ref) = &repo_ref {
                         repo_ref.git_server.clone().join(" ")
-                    } else if let Ok(url) = git_repo.get_origin_url() {
-                        if let Ok(fetch_url) = convert_clone_url_to_https(&url) {
-                            fetch_url
-                        } else if url.starts_with("nostr://") {
-                            // nostr added as origin remote before repo announcement sent
-                            String::new()
-                        } else {
-                            // local repo or custom protocol
-                            url
-                        }
                     } else {
-                        String::new()
+                        git_servers_from_remotes(&git_repo).join(" ")
                     }),
             )?
             .split(' ')
@@ -354,6 +420,26 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         }
     };
 
+    let blossoms: Vec<String> = if args.blossoms.is_empty() {
+        if let Some(repo_ref) = &repo_ref {
+            repo_ref.blossoms.clone()
+        } else {
+            vec![]
+        }
+    } else {
+        args.blossoms.clone()
+    };
+
+    let bundles: Vec<String> = if args.bundles.is_empty() {
+        if let Some(repo_ref) = &repo_ref {
+            repo_ref.bundles.clone()
+        } else {
+            vec![]
+        }
+    } else {
+        args.bundles.clone()
+    };
+
     let web: Vec<String> = if args.web.is_empty() {
         Interactor::default()
             .input(
@@ -373,6 +459,51 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         args.web.clone()
     };
 
+    let license = args
+        .license
+        .clone()
+        .or_else(|| repo_ref.as_ref().and_then(|r| r.license.clone()))
+        .or_else(|| github.as_ref().and_then(|g| g.license.clone()));
+    let language = args
+        .language
+        .clone()
+        .or_else(|| repo_ref.as_ref().and_then(|r| r.language.clone()))
+        .or_else(|| github.as_ref().and_then(|g| g.language.clone()));
+    let homepage = args
+        .homepage
+        .clone()
+        .or_else(|| repo_ref.as_ref().and_then(|r| r.homepage.clone()))
+        .or_else(|| github.as_ref().and_then(|g| g.homepage.clone()));
+    let contact = args
+        .contact
+        .clone()
+        .or_else(|| repo_ref.as_ref().and_then(|r| r.contact.clone()));
+    let upstream = if let Some(upstream) = &args.upstream {
+        Some(if upstream.starts_with("nostr://") {
+            NostrUrlDecoded::parse_and_resolve(upstream, &Some(&git_repo))
+                .await
+                .context("invalid upstream nostr url")?
+                .coordinate
+        } else {
+            Coordinate::parse(upstream.trim_start_matches("nostr:"))
+                .context("invalid upstream. it should be an naddr or nostr:// url")?
+        })
+    } else {
+        repo_ref.as_ref().and_then(|r| r.upstream.clone())
+    };
+    let hashtags: Vec<String> = if args.topics.is_empty() {
+        match (&repo_ref, &github) {
+            (Some(repo_ref), _) if !repo_ref.hashtags.is_empty() => repo_ref.hashtags.clone(),
+            (_, Some(github)) => github.topics.clone(),
+            _ => vec![],
+        }
+    } else {
+        args.topics
+            .iter()
+            .map(|t| t.trim_start_matches('#').to_string())
+            .collect()
+    };
+
     let earliest_unique_commit = if let Some(t) = &args.earliest_unique_commit {
         t.clone()
     } else {
@@ -404,6 +535,28 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         }
     };
 
+    let org = if let Some(org) = &args.org {
+        Some(
+            Coordinate::parse(org.trim_start_matches("nostr:"))
+                .context("invalid org. it should be an naddr")?,
+        )
+    } else {
+        repo_ref.as_ref().and_then(|r| r.org.clone())
+    };
+    // members aren't copied into the announcement. they are looked up from
+    // the organization's maintainer list whenever the repository is loaded
+    if let Some(org) = &org {
+        let org = fetch_org(&client, org)
+            .await
+            .context("failed to find organization")?;
+        if !org.members.contains(&user_ref.public_key) {
+            bail!(
+                "your nostr account isn't a member of organization {}",
+                org.name
+            );
+        }
+    }
+
     println!("publishing repostory reference...");
 
     let mut repo_ref = RepoRef {
@@ -412,21 +565,58 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         description,
         root_commit: earliest_unique_commit,
         git_server,
+        blossoms,
+        bundles,
         web,
+        license,
+        language,
+        hashtags,
+        homepage,
+        contact,
+        previous_identifiers: if let Some(renamed_from) = &renamed_from {
+            let mut previous_identifiers = repo_ref
+                .as_ref()
+                .map(|r| r.previous_identifiers.clone())
+                .unwrap_or_default();
+            previous_identifiers.retain(|i| i.ne(&identifier));
+            if !previous_identifiers.contains(renamed_from) {
+                previous_identifiers.push(renamed_from.clone());
+            }
+            previous_identifiers
+        } else {
+            repo_ref
+                .as_ref()
+                .map(|r| r.previous_identifiers.clone())
+                .unwrap_or_default()
+        },
+        renamed_to: None,
+        upstream,
+        org,
         relays: relays.clone(),
         trusted_maintainer: user_ref.public_key,
         maintainers: maintainers.clone(),
         events: HashMap::new(),
         nostr_git_url: None,
     };
-    let repo_event = repo_ref.to_event(&signer).await?;
+    let mut events = vec![repo_ref.to_event(&signer).await?];
+    if let Some(renamed_from) = &renamed_from {
+        events.push(
+            RepoRef {
+                identifier: renamed_from.clone(),
+                ..repo_ref.clone()
+            }
+            .to_rename_tombstone(&identifier)
+            .to_event(&signer)
+            .await?,
+        );
+    }
 
     client.set_signer(signer).await;
 
     send_events(
         &client,
         Some(git_repo_path),
-        vec![repo_event],
+        events,
         user_ref.relays.write(),
         relays.clone(),
         !cli_args.disable_cli_spinners,
@@ -497,6 +687,10 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
         }
     };
 
+    if let Some(renamed_from) = &renamed_from {
+        update_remotes_after_rename(&git_repo, renamed_from, &repo_ref).await?;
+    }
+
     prompt_to_set_nostr_url_as_origin(&repo_ref, &git_repo).await?;
 
     if !hint_for_nip05_address.is_empty() {
@@ -543,6 +737,43 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
             "this optional file helps in identifying who the maintainers are over time through the commit history"
         );
     }
+    if output::json() {
+        output::emit(&json!({
+            "coordinate": repo_ref.coordinate_with_hint().to_bech32()?,
+            "identifier": identifier,
+            "nostr_url": repo_ref.to_nostr_git_url(&Some(&git_repo)),
+            "maintainers": maintainers
+                .iter()
+                .map(ToBech32::to_bech32)
+                .collect::<Result<Vec<_>, _>>()?,
+            "relays": relays,
+        }))?;
+    }
+    Ok(())
+}
+
+/// point nostr remotes using the old identifier at the renamed repository
+async fn update_remotes_after_rename(
+    git_repo: &Repo,
+    renamed_from: &str,
+    repo_ref: &RepoRef,
+) -> Result<()> {
+    for name in git_repo.git_repo.remotes()?.iter().flatten() {
+        let Some(url) = git_repo.git_repo.find_remote(name)?.url().map(String::from) else {
+            continue;
+        };
+        if let Ok(nostr_url) = NostrUrlDecoded::parse_and_resolve(&url, &Some(git_repo)).await {
+            if nostr_url.coordinate.identifier == renamed_from
+                && repo_ref
+                    .maintainers
+                    .contains(&nostr_url.coordinate.public_key)
+            {
+                let new_url = repo_ref.to_nostr_git_url(&Some(git_repo));
+                git_repo.git_repo.remote_set_url(name, &new_url)?;
+                println!("updated remote \"{name}\" to {new_url}");
+            }
+        }
+    }
     Ok(())
 }
 
@@ -615,3 +846,204 @@ fn ask_to_create_new_origin_remote(repo_ref: &RepoRef, git_repo: &Repo) -> Resul
     }
     Ok(())
 }
+
+/// metadata of the GitHub repository the origin remote points to. a token
+/// from GITHUB_TOKEN or git config nostr.github-token is used when set, which
+/// allows private repositories
+async fn fetch_github_metadata(git_repo: &Repo) -> Result<GitHubRepoMetadata> {
+    let origin = git_repo
+        .get_origin_url()
+        .context("--from-github needs an origin remote pointing at GitHub")?;
+    if !origin.contains("github.com") {
+        bail!("--from-github needs an origin remote pointing at GitHub but origin is {origin}");
+    }
+    let github_repo = GitHubRepo::parse(&origin)?;
+    let token = if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        token
+    } else {
+        git_repo
+            .get_git_config_item("nostr.github-token", None)?
+            .unwrap_or_default()
+    };
+    let metadata = GitHubClient::new(&token)
+        .get_repo_metadata(&github_repo)
+        .await
+        .context(format!("failed to fetch {github_repo} from GitHub"))?;
+    output::info(&format!("using details of {github_repo} from GitHub"));
+    Ok(metadata)
+}
+
+/// shortname with spaces and special characters replaced by hyphens
+fn identifier_from_name(name: &str) -> String {
+    name.replace(' ', "-")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c.eq(&'/') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// last path segment of the origin url, or the name of the repository's
+/// directory
+fn name_from_remote_or_directory(git_repo: &Repo) -> Option<String> {
+    git_repo
+        .get_origin_url()
+        .ok()
+        .filter(|url| !url.starts_with("nostr://"))
+        .and_then(|url| {
+            url.trim_end_matches('/')
+                .trim_end_matches(".git")
+                .rsplit(['/', ':'])
+                .next()
+                .map(str::to_string)
+        })
+        // a host without a path, eg. https://localhost:1000
+        .filter(|name| !name.is_empty() && !name.chars().all(|c| c.is_ascii_digit()))
+        .or_else(|| {
+            git_repo
+                .get_path()
+                .ok()?
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+}
+
+/// fetch urls of the repository's remotes, origin first. nostr remotes are
+/// skipped as the announcement hasn't been sent yet
+fn git_servers_from_remotes(git_repo: &Repo) -> Vec<String> {
+    let mut names: Vec<String> = git_repo
+        .git_repo
+        .remotes()
+        .map(|names| names.iter().flatten().map(str::to_string).collect())
+        .unwrap_or_default();
+    names.sort_by_key(|name| name.as_str() != "origin");
+    let mut git_servers = vec![];
+    for name in names {
+        let Some(url) = git_repo
+            .git_repo
+            .find_remote(&name)
+            .ok()
+            .and_then(|remote| remote.url().map(str::to_string))
+        else {
+            continue;
+        };
+        if url.starts_with("nostr://") {
+            continue;
+        }
+        // local repos and custom protocols are kept as they are
+        let url = convert_clone_url_to_https(&url).unwrap_or(url);
+        if !git_servers.contains(&url) {
+            git_servers.push(url);
+        }
+    }
+    git_servers
+}
+
+/// `args` with everything a new announcement needs that wasn't given filled in
+/// from the remotes, root commit and user's relays, once the user confirms
+/// them. `None` to prompt for each instead or when there is no git server to
+/// derive
+fn derive_new_announcement_args(
+    args: &SubCommandArgs,
+    git_repo: &Repo,
+    github: Option<&GitHubRepoMetadata>,
+    root_commit: &str,
+    user: &PublicKey,
+    user_relays: &[String],
+) -> Result<Option<SubCommandArgs>> {
+    let nothing_to_derive = args.title.is_some()
+        && args.identifier.is_some()
+        && args.description.is_some()
+        && !args.clone_url.is_empty()
+        && !args.relays.is_empty()
+        && !args.web.is_empty()
+        && !args.other_maintainers.is_empty()
+        && args.earliest_unique_commit.is_some();
+    if nothing_to_derive {
+        return Ok(None);
+    }
+    let clone_url = if args.clone_url.is_empty() {
+        git_servers_from_remotes(git_repo)
+    } else {
+        args.clone_url.clone()
+    };
+    if clone_url.is_empty() {
+        return Ok(None);
+    }
+    let Some(title) = args.title.clone().or_else(|| {
+        github
+            .map(|g| g.name.clone())
+            .filter(|name| !name.is_empty())
+            .or_else(|| name_from_remote_or_directory(git_repo))
+    }) else {
+        return Ok(None);
+    };
+    let identifier = args
+        .identifier
+        .clone()
+        .unwrap_or_else(|| identifier_from_name(&title));
+    let derived = SubCommandArgs {
+        description: args
+            .description
+            .clone()
+            .or_else(|| github.and_then(|g| g.description.clone()))
+            .or(Some(String::new())),
+        relays: if args.relays.is_empty() {
+            user_relays.to_vec()
+        } else {
+            args.relays.clone()
+        },
+        web: if args.web.is_empty() {
+            vec![format!("https://gitworkshop.dev/repo/{identifier}")]
+        } else {
+            args.web.clone()
+        },
+        other_maintainers: if args.other_maintainers.is_empty() {
+            vec![user.to_bech32()?]
+        } else {
+            args.other_maintainers.clone()
+        },
+        earliest_unique_commit: args
+            .earliest_unique_commit
+            .clone()
+            .or(Some(root_commit.to_string())),
+        title: Some(title),
+        identifier: Some(identifier),
+        clone_url,
+        ..args.clone()
+    };
+
+    let dim = theme::dim();
+    let row =
+        |label: &str, value: &str| println!("{} {value}", dim.apply_to(format!("{label:<23}")));
+    row("name", derived.title.as_deref().unwrap_or_default());
+    row(
+        "identifier",
+        derived.identifier.as_deref().unwrap_or_default(),
+    );
+    if let Some(description) = derived.description.as_deref().filter(|d| !d.is_empty()) {
+        row("description", description);
+    }
+    row("git servers", &derived.clone_url.join(" "));
+    row("relays", &derived.relays.join(" "));
+    row("website", &derived.web.join(" "));
+    row("maintainers", &derived.other_maintainers.join(" "));
+    row(
+        "earliest unique commit",
+        derived
+            .earliest_unique_commit
+            .as_deref()
+            .unwrap_or_default(),
+    );
+    Ok(Interactor::default()
+        .confirm(
+            PromptConfirmParms::default()
+                .with_prompt("publish the repository announcement with these details?")
+                .with_default(true),
+        )?
+        .then_some(derived))
+}
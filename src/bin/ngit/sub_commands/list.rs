@@ -1,60 +1,175 @@
-use std::{io::Write, ops::Add};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    ops::Add,
+    path::Path,
+};
 
 use anyhow::{Context, Result, bail};
 use ngit::{
-    client::{get_all_proposal_patch_events_from_cache, get_proposals_and_revisions_from_cache},
+    client::{
+        fetch_missing_profiles, fetch_upstream_enabled, get_all_proposal_patch_events_from_cache,
+        get_proposals_and_revisions_from_cache,
+    },
+    delegation::event_author,
     git_events::{
-        get_commit_id_from_patch, get_most_recent_patch_with_ancestors, status_kinds, tag_value,
+        event_id_from_user_input, event_is_cover_letter, event_is_patch_set_root,
+        generate_deletion_event, get_commit_id_from_patch, get_concurrent_revision_roots,
+        get_duplicate_proposals, get_event_root, get_most_recent_patch_with_ancestors,
+        get_patches_of_revision, get_proposal_status, get_reaction_tallies, sort_newest_first,
+        status_kinds, tag_value,
     },
+    login::{
+        get_curent_user,
+        user::{get_author_display_name, get_user_ref_from_cache},
+    },
+    output,
+    settings::settings,
+    short_numbers::{get_short_numbers, parse_short_number},
+    theme,
+    trailers::{append_trailers, approvers, render_trailers, reviewer},
 };
-use nostr_sdk::Kind;
+use nostr::nips::{nip01::Coordinate, nip19};
+use nostr_sdk::{EventId, Kind, ToBech32, hashes::sha1::Hash as Sha1Hash};
+use serde_json::json;
 
 use crate::{
-    cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms},
+    cli::{Cli, extract_signer_cli_arguments},
+    cli_interactor::{
+        Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms, is_non_interactive,
+    },
     client::{
-        Client, Connect, fetching_with_report, get_events_from_local_cache, get_repo_ref_from_cache,
+        Client, Connect, fetching_with_report, get_events_from_local_cache,
+        get_repo_ref_from_cache, resolve_repo_trust, save_event_in_local_cache, send_events,
+    },
+    git::{
+        ParentCommitMissing, PatchConflicts, Repo, RepoActions, get_diffstat_of_patch,
+        get_paths_changed_in_patch, str_to_sha1,
     },
-    git::{Repo, RepoActions, str_to_sha1},
     git_events::{
-        commit_msg_from_patch_oneliner, event_is_revision_root, event_to_cover_letter,
+        CoverLetter, commit_msg_from_patch_oneliner, event_is_revision_root, event_to_cover_letter,
         patch_supports_commit_ids,
     },
-    repo_ref::get_repo_coordinates_when_remote_unknown,
+    login,
+    repo_ref::{RepoRef, get_proposal_lineage_warning, get_repo_coordinates_when_remote_unknown},
 };
 
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// #number, nevent, note, event id or web link of a proposal to open
+    /// directly
+    proposal: Option<String>,
+    #[clap(long)]
+    /// also list proposals sent to the upstream repository this is a fork of
+    upstream: bool,
+    #[clap(long)]
+    /// retry patches that don't apply ignoring whitespace changes
+    ignore_whitespace: bool,
+}
+
+fn proposal_title(proposal: &nostr::Event) -> String {
+    if let Ok(cl) = event_to_cover_letter(proposal) {
+        cl.title
+    } else if let Ok(msg) = tag_value(proposal, "description") {
+        msg.split('\n').collect::<Vec<&str>>()[0].to_string()
+    } else {
+        proposal.id.to_string()
+    }
+}
+
 #[allow(clippy::too_many_lines)]
-pub async fn launch() -> Result<()> {
-    let git_repo = Repo::discover().context("failed to find a git repository")?;
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover()
+        .context("failed to find a git repository")?
+        .with_ignore_whitespace(args.ignore_whitespace);
     let git_repo_path = git_repo.get_path()?;
 
     // TODO: check for empty repo
     // TODO: check for existing maintaiers file
     // TODO: check for other claims
 
-    let client = Client::default();
+    let mut client = Client::default();
 
     let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
 
-    fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    let fetch_report = fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    resolve_repo_trust(&git_repo, &fetch_report, &repo_coordinates).await?;
+    if fetch_report.is_incomplete() {
+        println!(
+            "warning: a relay timed out so proposals, revisions or statuses may be missing. try again later"
+        );
+    }
 
     let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
 
-    let proposals_and_revisions: Vec<nostr::Event> =
-        get_proposals_and_revisions_from_cache(git_repo_path, repo_ref.coordinates()).await?;
+    if let Some(summary) = repo_ref.metadata_summary() {
+        println!("{}: {summary}", repo_ref.name);
+    }
+
+    let mut preselected_proposal = if let Some(input) = &args.proposal {
+        find_proposal_from_user_input(&client, git_repo_path, &repo_ref, input).await?
+    } else {
+        None
+    };
+
+    let mut proposals_and_revisions: Vec<nostr::Event> = get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?;
+
+    let mut upstream_ref = None;
+    let mut upstream_proposal_ids = HashSet::new();
+    if args.upstream {
+        let upstream = repo_ref.upstream.clone().context(
+            "this repository isn't a fork. set its upstream with `ngit init --upstream`",
+        )?;
+        if !fetch_upstream_enabled(Some(git_repo_path)) {
+            client
+                .fetch_all(Some(git_repo_path), Some(&upstream), &HashSet::new())
+                .await?;
+        }
+        let upstream_coordinates =
+            if let Ok(r) = get_repo_ref_from_cache(Some(git_repo_path), &upstream).await {
+                let coordinates = r.coordinates();
+                upstream_ref = Some(r);
+                coordinates
+            } else {
+                HashSet::from([upstream])
+            };
+        for proposal in
+            get_proposals_and_revisions_from_cache(git_repo_path, upstream_coordinates).await?
+        {
+            if !proposals_and_revisions
+                .iter()
+                .any(|e| e.id.eq(&proposal.id))
+            {
+                upstream_proposal_ids.insert(proposal.id);
+                proposals_and_revisions.push(proposal);
+            }
+        }
+        proposals_and_revisions.sort_by_key(|e| e.created_at);
+        proposals_and_revisions.reverse();
+    }
     if proposals_and_revisions.is_empty() {
+        if output::json() {
+            return output::emit(&json!({ "proposals": [] }));
+        }
         println!("no proposals found... create one? try `ngit send`");
         return Ok(());
     }
 
     let statuses: Vec<nostr::Event> = {
-        let mut statuses = get_events_from_local_cache(git_repo_path, vec![
-            nostr::Filter::default()
-                .kinds(status_kinds().clone())
-                .events(proposals_and_revisions.iter().map(|e| e.id)),
-        ])
+        let mut statuses = get_events_from_local_cache(
+            git_repo_path,
+            vec![
+                nostr::Filter::default()
+                    .kinds(status_kinds().clone())
+                    .events(proposals_and_revisions.iter().map(|e| e.id)),
+            ],
+        )
         .await?;
-        statuses.sort_by_key(|e| e.created_at);
-        statuses.reverse();
+        sort_newest_first(&mut statuses);
         statuses
     };
 
@@ -69,22 +184,60 @@ pub async fn launch() -> Result<()> {
         .cloned()
         .collect();
 
-    for proposal in &proposals {
-        let status = if let Some(e) = statuses
+    let numbers = get_short_numbers(git_repo_path, &repo_ref)
+        .await
+        .unwrap_or_default();
+
+    let reactions = get_reaction_tallies(
+        &get_events_from_local_cache(
+            git_repo_path,
+            vec![
+                nostr::Filter::default()
+                    .kind(Kind::Reaction)
+                    .events(proposals.iter().map(|e| e.id)),
+            ],
+        )
+        .await?,
+    );
+
+    // duplicates are listed once, under the earliest root
+    let cover_letter_patches = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            nostr::Filter::default().kind(Kind::GitPatch).events(
+                proposals
+                    .iter()
+                    .filter(|e| event_is_cover_letter(e))
+                    .map(|e| e.id),
+            ),
+        ],
+    )
+    .await?;
+    let mut duplicates = get_duplicate_proposals(&proposals, &cover_letter_patches);
+    let duplicate_ids: HashSet<EventId> = duplicates.values().flatten().map(|e| e.id).collect();
+    if let Some(id) = preselected_proposal {
+        if let Some((original, _)) = duplicates
             .iter()
-            .filter(|e| {
-                status_kinds().contains(&e.kind)
-                    && e.tags.iter().any(|t| {
-                        t.as_slice().len() > 1 && t.as_slice()[1].eq(&proposal.id.to_string())
-                    })
-            })
-            .collect::<Vec<&nostr::Event>>()
-            .first()
+            .find(|(_, roots)| roots.iter().any(|e| e.id.eq(&id)))
         {
-            e.kind
-        } else {
-            Kind::GitStatusOpen
+            preselected_proposal = Some(*original);
+        }
+    }
+
+    // statuses from anyone other than a maintainer or the author don't change
+    // the listed state but are shown when the proposal is selected
+    let mut third_party_statuses: HashMap<EventId, Vec<nostr::Event>> = HashMap::new();
+    for proposal in proposals.iter().filter(|e| !duplicate_ids.contains(&e.id)) {
+        let maintainers = match &upstream_ref {
+            Some(upstream_ref) if upstream_proposal_ids.contains(&proposal.id) => {
+                &upstream_ref.maintainers
+            }
+            _ => &repo_ref.maintainers,
         };
+        let (status, third_party) = get_proposal_status(proposal, &statuses, maintainers);
+        if !third_party.is_empty() {
+            third_party_statuses.insert(proposal.id, third_party.into_iter().cloned().collect());
+        }
         if status.eq(&Kind::GitStatusOpen) {
             open_proposals.push(proposal);
         } else if status.eq(&Kind::GitStatusClosed) {
@@ -97,6 +250,54 @@ pub async fn launch() -> Result<()> {
     }
 
     let mut selected_status = Kind::GitStatusOpen;
+    if let Some(id) = &preselected_proposal {
+        for (proposals_for_status, status) in [
+            (&open_proposals, Kind::GitStatusOpen),
+            (&draft_proposals, Kind::GitStatusDraft),
+            (&closed_proposals, Kind::GitStatusClosed),
+            (&applied_proposals, Kind::GitStatusApplied),
+        ] {
+            if proposals_for_status.iter().any(|e| e.id.eq(id)) {
+                selected_status = status;
+            }
+        }
+        if !proposals.iter().any(|e| e.id.eq(id)) {
+            bail!("proposal {id} isn't a proposal for this repository");
+        }
+    }
+
+    if output::json() {
+        let mut listed = vec![];
+        for (proposals_for_status, status) in [
+            (&open_proposals, "open"),
+            (&draft_proposals, "draft"),
+            (&closed_proposals, "closed"),
+            (&applied_proposals, "applied"),
+        ] {
+            for e in proposals_for_status
+                .iter()
+                .filter(|e| !preselected_proposal.is_some_and(|id| id != e.id))
+            {
+                listed.push(json!({
+                    "id": e.id.to_hex(),
+                    "nevent": repo_ref.nevent(&e.id)?,
+                    "title": proposal_title(e),
+                    "branch_name": event_to_cover_letter(e).ok().map(|cl| cl.branch_name),
+                    "author": e.pubkey.to_bech32()?,
+                    "created_at": e.created_at.as_u64(),
+                    "status": status,
+                    "number": numbers.number_of(&e.id),
+                    "reactions": reactions.get(&e.id).map(|tally| json!({
+                        "likes": tally.likes,
+                        "dislikes": tally.dislikes,
+                    })),
+                    "upstream": upstream_proposal_ids.contains(&e.id),
+                    "times_sent": duplicates.get(&e.id).map_or(1, |d| d.len() + 1),
+                }));
+            }
+        }
+        return output::emit(&json!({ "proposals": listed }));
+    }
 
     loop {
         let proposals_for_status = if selected_status == Kind::GitStatusOpen {
@@ -130,12 +331,24 @@ pub async fn launch() -> Result<()> {
         let mut choices: Vec<String> = proposals_for_status
             .iter()
             .map(|e| {
-                if let Ok(cl) = event_to_cover_letter(e) {
-                    cl.title
-                } else if let Ok(msg) = tag_value(e, "description") {
-                    msg.split('\n').collect::<Vec<&str>>()[0].to_string()
+                let title = proposal_title(e);
+                let title = if let Some(duplicates) = duplicates.get(&e.id) {
+                    format!("{title} (sent {} times)", duplicates.len() + 1)
                 } else {
-                    e.id.to_string()
+                    title
+                };
+                let title = match numbers.number_of(&e.id) {
+                    Some(number) => format!("#{number} {title}"),
+                    None => title,
+                };
+                let title = match reactions.get(&e.id) {
+                    Some(tally) => format!("{title} {tally}"),
+                    None => title,
+                };
+                if upstream_proposal_ids.contains(&e.id) {
+                    format!("[upstream] {title}")
+                } else {
+                    title
                 }
             })
             .collect();
@@ -156,12 +369,19 @@ pub async fn launch() -> Result<()> {
             ));
         }
 
-        let selected_index = Interactor::default().choice(
-            PromptChoiceParms::default()
-                .with_prompt(prompt)
-                .with_default(0)
-                .with_choices(choices.clone()),
-        )?;
+        let selected_index = if let Some(id) = preselected_proposal.take() {
+            proposals_for_status
+                .iter()
+                .position(|e| e.id.eq(&id))
+                .context("failed to find selected proposal")?
+        } else {
+            Interactor::default().choice(
+                PromptChoiceParms::default()
+                    .with_prompt(prompt)
+                    .with_default(0)
+                    .with_choices(choices.clone()),
+            )?
+        };
 
         if (selected_index + 1).gt(&proposals_for_status.len()) {
             if choices[selected_index].contains("Open") {
@@ -179,13 +399,147 @@ pub async fn launch() -> Result<()> {
         let cover_letter = event_to_cover_letter(proposals_for_status[selected_index])
             .context("failed to extract proposal details from proposal root event")?;
 
-        let commits_events: Vec<nostr::Event> = get_all_proposal_patch_events_from_cache(
+        let third_party = third_party_statuses
+            .get(&proposals_for_status[selected_index].id)
+            .cloned()
+            .unwrap_or_default();
+        fetch_missing_profiles(
+            &client,
             git_repo_path,
             &repo_ref,
+            &[
+                vec![event_author(&proposals_for_status[selected_index])],
+                third_party.iter().map(|s| s.pubkey).collect(),
+            ]
+            .concat(),
+        )
+        .await?;
+        println!(
+            "by {}",
+            get_author_display_name(
+                Some(git_repo_path),
+                &event_author(&proposals_for_status[selected_index])
+            )
+            .await
+        );
+        if let Some(tally) = reactions.get(&proposals_for_status[selected_index].id) {
+            println!("reactions: {tally}");
+        }
+        for status in &third_party {
+            println!(
+                "third-party claim, ignored as not from a maintainer: marked {} by {}",
+                match status.kind {
+                    Kind::GitStatusApplied => "applied",
+                    Kind::GitStatusClosed => "closed",
+                    Kind::GitStatusDraft => "draft",
+                    _ => "open",
+                },
+                get_author_display_name(Some(git_repo_path), &status.pubkey).await
+            );
+        }
+
+        if let Some(warning) = get_proposal_lineage_warning(
+            &git_repo,
+            &repo_ref,
+            &proposals_for_status[selected_index],
+        ) {
+            println!("warning: {warning}");
+            if !Interactor::default().confirm(
+                PromptConfirmParms::default()
+                    .with_default(false)
+                    .with_prompt("continue with this proposal anyway?"),
+            )? {
+                if is_non_interactive() {
+                    bail!("refusing to apply a proposal that may be for a different repository");
+                }
+                continue;
+            }
+        }
+
+        if let Some(proposal_duplicates) = duplicates
+            .get(&proposals_for_status[selected_index].id)
+            .cloned()
+        {
+            println!(
+                "this proposal was sent {} times. the earliest is shown",
+                proposal_duplicates.len() + 1
+            );
+            if get_curent_user(&git_repo)?
+                .is_some_and(|user| user.eq(&proposals_for_status[selected_index].pubkey))
+                && Interactor::default().confirm(
+                    PromptConfirmParms::default()
+                        .with_prompt(format!(
+                            "retract the later {} with a deletion request?",
+                            if proposal_duplicates.len() > 1 {
+                                "copies"
+                            } else {
+                                "copy"
+                            }
+                        ))
+                        .with_default(false),
+                )?
+            {
+                retract_duplicates(
+                    cli_args,
+                    &mut client,
+                    &git_repo,
+                    &repo_ref,
+                    &proposal_duplicates,
+                )
+                .await?;
+                duplicates.remove(&proposals_for_status[selected_index].id);
+            }
+        }
+
+        let mut commits_events: Vec<nostr::Event> = get_all_proposal_patch_events_from_cache(
+            git_repo_path,
+            match &upstream_ref {
+                Some(upstream_ref)
+                    if upstream_proposal_ids.contains(&proposals_for_status[selected_index].id) =>
+                {
+                    upstream_ref
+                }
+                _ => &repo_ref,
+            },
             &proposals_for_status[selected_index].id,
         )
         .await?;
 
+        let concurrent_revisions = get_concurrent_revision_roots(&commits_events);
+        if !concurrent_revisions.is_empty() {
+            println!(
+                "warning: {} revisions of this proposal were published concurrently. the latest is selected by default",
+                concurrent_revisions.len()
+            );
+            fetch_missing_profiles(
+                &client,
+                git_repo_path,
+                &repo_ref,
+                &concurrent_revisions
+                    .iter()
+                    .map(|revision| revision.pubkey)
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+            let mut choices = vec![];
+            for revision in &concurrent_revisions {
+                choices.push(format!(
+                    "{} by {} ({} patches)",
+                    revision.created_at.to_human_datetime(),
+                    get_author_display_name(Some(git_repo_path), &revision.pubkey).await,
+                    get_patches_of_revision(&commits_events, &revision.id).len(),
+                ));
+            }
+            let selected = Interactor::default().choice(
+                PromptChoiceParms::default()
+                    .with_prompt("revision")
+                    .with_default(0)
+                    .with_choices(choices),
+            )?;
+            commits_events =
+                get_patches_of_revision(&commits_events, &concurrent_revisions[selected].id);
+        }
+
         let Ok(most_recent_proposal_patch_chain) =
             get_most_recent_patch_with_ancestors(commits_events.clone())
         else {
@@ -224,6 +578,7 @@ pub async fn launch() -> Result<()> {
                         "learn why 'patch only' proposals can't be checked out".to_string(),
                         format!("apply to current branch with `git am`"),
                         format!("download to ./patches"),
+                        PREVIEW_CHOICE.to_string(),
                         "back".to_string(),
                     ]),
             )? {
@@ -248,9 +603,23 @@ pub async fn launch() -> Result<()> {
                     )?;
                     continue;
                 }
-                1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
+                1 => {
+                    launch_git_am_with_patches(
+                        &client,
+                        git_repo_path,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        most_recent_proposal_patch_chain,
+                    )
+                    .await
+                }
                 2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                3 => {
+                    print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                    preselected_proposal = Some(proposals_for_status[selected_index].id);
+                    continue;
+                }
+                4 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -287,12 +656,27 @@ pub async fn launch() -> Result<()> {
                     ),
                     format!("apply to current branch with `git am`"),
                     format!("download to ./patches"),
+                    PREVIEW_CHOICE.to_string(),
                     "back".to_string(),
                 ],
             ))? {
-                0 | 3 => continue,
-                1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
+                0 | 4 => continue,
+                1 => {
+                    launch_git_am_with_patches(
+                        &client,
+                        git_repo_path,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        most_recent_proposal_patch_chain,
+                    )
+                    .await
+                }
                 2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
+                3 => {
+                    print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                    preselected_proposal = Some(proposals_for_status[selected_index].id);
+                    continue;
+                }
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -321,26 +705,41 @@ pub async fn launch() -> Result<()> {
                 ),
                 format!("apply to current branch with `git am`"),
                 format!("download to ./patches"),
+                PREVIEW_CHOICE.to_string(),
                 "back".to_string(),
             ]))? {
                 0 => {
-                    check_clean(&git_repo)?;
-                    let _ = git_repo
-                        .apply_patch_chain(
-                            &cover_letter.get_branch_name()?,
-                            most_recent_proposal_patch_chain,
-                        )
-                        .context("failed to apply patch chain")?;
+                    let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
+                    let _ = apply_patch_chain_or_offer_main(
+                        &git_repo,
+                        &cover_letter.get_branch_name()?,
+                        most_recent_proposal_patch_chain,
+                    )
+                    .context("failed to apply patch chain")?;
 
                     println!(
                         "checked out proposal as '{}' branch",
                         cover_letter.get_branch_name()?
                     );
-                    Ok(())
+                    offer_to_pop_stash(&git_repo, stash)
+                }
+                1 => {
+                    launch_git_am_with_patches(
+                        &client,
+                        git_repo_path,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        most_recent_proposal_patch_chain,
+                    )
+                    .await
                 }
-                1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
                 2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                3 => {
+                    print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                    preselected_proposal = Some(proposals_for_status[selected_index].id);
+                    continue;
+                }
+                4 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -377,21 +776,36 @@ pub async fn launch() -> Result<()> {
                         ),
                         format!("apply to current branch with `git am`"),
                         format!("download to ./patches"),
+                        PREVIEW_CHOICE.to_string(),
                         "back".to_string(),
                     ]),
             )? {
                 0 => {
-                    check_clean(&git_repo)?;
+                    let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
                     git_repo.checkout(&cover_letter.get_branch_name()?)?;
                     println!(
                         "checked out proposal as '{}' branch",
                         cover_letter.get_branch_name()?
                     );
-                    Ok(())
+                    offer_to_pop_stash(&git_repo, stash)
+                }
+                1 => {
+                    launch_git_am_with_patches(
+                        &client,
+                        git_repo_path,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        most_recent_proposal_patch_chain,
+                    )
+                    .await
                 }
-                1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
                 2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                3 => {
+                    print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                    preselected_proposal = Some(proposals_for_status[selected_index].id);
+                    continue;
+                }
+                4 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -414,29 +828,44 @@ pub async fn launch() -> Result<()> {
                         format!("checkout proposal branch and apply {} appendments", &index,),
                         format!("apply to current branch with `git am`"),
                         format!("download to ./patches"),
+                        PREVIEW_CHOICE.to_string(),
                         "back".to_string(),
                     ]),
             )? {
                 0 => {
-                    check_clean(&git_repo)?;
+                    let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
                     git_repo.checkout(&cover_letter.get_branch_name()?)?;
-                    let _ = git_repo
-                        .apply_patch_chain(
-                            &cover_letter.get_branch_name()?,
-                            most_recent_proposal_patch_chain,
-                        )
-                        .context("failed to apply patch chain")?;
+                    let _ = apply_patch_chain_or_offer_main(
+                        &git_repo,
+                        &cover_letter.get_branch_name()?,
+                        most_recent_proposal_patch_chain,
+                    )
+                    .context("failed to apply patch chain")?;
                     println!(
                         "checked out proposal branch and applied {} appendments ({} ahead {} behind '{main_branch_name}')",
                         &index,
                         local_ahead_of_main.len().add(&index),
                         local_beind_main.len(),
                     );
-                    Ok(())
+                    offer_to_pop_stash(&git_repo, stash)
+                }
+                1 => {
+                    launch_git_am_with_patches(
+                        &client,
+                        git_repo_path,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        most_recent_proposal_patch_chain,
+                    )
+                    .await
                 }
-                1 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
                 2 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                3 => continue,
+                3 => {
+                    print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                    preselected_proposal = Some(proposals_for_status[selected_index].id);
+                    continue;
+                }
+                4 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -466,23 +895,24 @@ pub async fn launch() -> Result<()> {
                         format!("checkout existing outdated proposal branch"),
                         format!("apply to current branch with `git am`"),
                         format!("download to ./patches"),
+                        PREVIEW_CHOICE.to_string(),
                         "back".to_string(),
                     ]),
             )? {
                 0 => {
-                    check_clean(&git_repo)?;
+                    let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
                     git_repo.create_branch_at_commit(
                         &cover_letter.get_branch_name()?,
                         &proposal_base_commit.to_string(),
                     )?;
                     git_repo.checkout(&cover_letter.get_branch_name()?)?;
                     let chain_length = most_recent_proposal_patch_chain.len();
-                    let _ = git_repo
-                        .apply_patch_chain(
-                            &cover_letter.get_branch_name()?,
-                            most_recent_proposal_patch_chain,
-                        )
-                        .context("failed to apply patch chain")?;
+                    let _ = apply_patch_chain_or_offer_main(
+                        &git_repo,
+                        &cover_letter.get_branch_name()?,
+                        most_recent_proposal_patch_chain,
+                    )
+                    .context("failed to apply patch chain")?;
                     println!(
                         "checked out new version of proposal ({} ahead {} behind '{main_branch_name}'), replacing old version ({} ahead {} behind '{main_branch_name}')",
                         chain_length,
@@ -490,21 +920,35 @@ pub async fn launch() -> Result<()> {
                         local_ahead_of_main.len(),
                         local_beind_main.len(),
                     );
-                    Ok(())
+                    offer_to_pop_stash(&git_repo, stash)
                 }
                 1 => {
-                    check_clean(&git_repo)?;
+                    let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
                     git_repo.checkout(&cover_letter.get_branch_name()?)?;
                     println!(
                         "checked out old proposal in existing branch ({} ahead {} behind '{main_branch_name}')",
                         local_ahead_of_main.len(),
                         local_beind_main.len(),
                     );
-                    Ok(())
+                    offer_to_pop_stash(&git_repo, stash)
+                }
+                2 => {
+                    launch_git_am_with_patches(
+                        &client,
+                        git_repo_path,
+                        &repo_ref,
+                        proposals_for_status[selected_index],
+                        most_recent_proposal_patch_chain,
+                    )
+                    .await
                 }
-                2 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
                 3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-                4 => continue,
+                4 => {
+                    print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                    preselected_proposal = Some(proposals_for_status[selected_index].id);
+                    continue;
+                }
+                5 => continue,
                 _ => {
                     bail!("unexpected choice")
                 }
@@ -512,13 +956,9 @@ pub async fn launch() -> Result<()> {
         }
         // tip of proposal in branch in history (local appendments made to up-to-date
         // proposal)
-        else if git_repo.ancestor_of(&local_branch_tip, &proposal_tip)? {
-            let (local_ahead_of_proposal, _) = git_repo
-                .get_commits_ahead_behind(&proposal_tip, &local_branch_tip)
-                .context(
-                    "failed to get commits ahead behind for propsal_top and local_branch_tip",
-                )?;
-
+        else if let Some(local_ahead_of_proposal) =
+            unpublished_commits_on_proposal(&git_repo, &proposal_tip, &local_branch_tip)?
+        {
             println!(
                 "local proposal branch exists with {} unpublished commits on top of the most up-to-date version of the proposal ({} ahead {} behind '{main_branch_name}')",
                 local_ahead_of_proposal.len(),
@@ -591,40 +1031,64 @@ pub async fn launch() -> Result<()> {
 
         println!("if you are confident in your changes consider running `ngit push --force`");
 
+        let proposal_commit_ids: HashSet<String> = commits_events
+            .iter()
+            .filter_map(|patch| get_commit_id_from_patch(patch).ok())
+            .collect();
+        let mut local_only_commits: Vec<Sha1Hash> = local_ahead_of_main
+            .iter()
+            .filter(|c| !proposal_commit_ids.contains(&c.to_string()))
+            .copied()
+            .collect();
+        local_only_commits.reverse();
+        let rebased_tip = preview_update(
+            &git_repo,
+            &proposal_base_commit,
+            &most_recent_proposal_patch_chain,
+            &local_only_commits,
+        )?;
+        let latest_branch_name = format!("{}-latest", cover_letter.get_branch_name()?);
+
         return match Interactor::default().choice(
             PromptChoiceParms::default()
                 .with_default(0)
                 .with_choices(vec![
                     format!("checkout local branch with unpublished changes"),
                     format!("discard unpublished changes and checkout new revision",),
+                    format!(
+                        "rebase {} local-only commits onto the new revision",
+                        local_only_commits.len()
+                    ),
+                    format!("checkout new revision as '{latest_branch_name}'"),
                     format!("apply to current branch with `git am`"),
                     format!("download to ./patches"),
+                    PREVIEW_CHOICE.to_string(),
                     "back".to_string(),
                 ]),
         )? {
             0 => {
-                check_clean(&git_repo)?;
+                let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
                 git_repo.checkout(&cover_letter.get_branch_name()?)?;
                 println!(
                     "checked out old proposal in existing branch ({} ahead {} behind '{main_branch_name}')",
                     local_ahead_of_main.len(),
                     local_beind_main.len(),
                 );
-                Ok(())
+                offer_to_pop_stash(&git_repo, stash)
             }
             1 => {
-                check_clean(&git_repo)?;
+                let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
                 git_repo.create_branch_at_commit(
                     &cover_letter.get_branch_name()?,
                     &proposal_base_commit.to_string(),
                 )?;
                 let chain_length = most_recent_proposal_patch_chain.len();
-                let _ = git_repo
-                    .apply_patch_chain(
-                        &cover_letter.get_branch_name()?,
-                        most_recent_proposal_patch_chain,
-                    )
-                    .context("failed to apply patch chain")?;
+                let _ = apply_patch_chain_or_offer_main(
+                    &git_repo,
+                    &cover_letter.get_branch_name()?,
+                    most_recent_proposal_patch_chain,
+                )
+                .context("failed to apply patch chain")?;
 
                 git_repo.checkout(&cover_letter.get_branch_name()?)?;
                 println!(
@@ -634,11 +1098,57 @@ pub async fn launch() -> Result<()> {
                     local_ahead_of_main.len(),
                     local_beind_main.len(),
                 );
-                Ok(())
+                offer_to_pop_stash(&git_repo, stash)
             }
-            2 => launch_git_am_with_patches(most_recent_proposal_patch_chain),
-            3 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
-            4 => continue,
+            2 => {
+                let Some(rebased_tip) = rebased_tip else {
+                    bail!(
+                        "local-only commits conflict with the new revision. checkout the new revision as '{latest_branch_name}' and cherry-pick them instead"
+                    );
+                };
+                let stash = stash_if_dirty(&git_repo, &cover_letter.get_branch_name()?)?;
+                git_repo.create_branch_at_commit(
+                    &cover_letter.get_branch_name()?,
+                    &rebased_tip.to_string(),
+                )?;
+                git_repo.checkout(&cover_letter.get_branch_name()?)?;
+                println!(
+                    "rebased {} local-only commits onto the new revision of the proposal",
+                    local_only_commits.len(),
+                );
+                offer_to_pop_stash(&git_repo, stash)
+            }
+            3 => {
+                let stash = stash_if_dirty(&git_repo, &latest_branch_name)?;
+                let _ = apply_patch_chain_or_offer_main(
+                    &git_repo,
+                    &latest_branch_name,
+                    most_recent_proposal_patch_chain,
+                )
+                .context("failed to apply patch chain")?;
+                println!(
+                    "checked out new revision as '{latest_branch_name}'. your unpublished changes remain on '{}'",
+                    cover_letter.get_branch_name()?
+                );
+                offer_to_pop_stash(&git_repo, stash)
+            }
+            4 => {
+                launch_git_am_with_patches(
+                    &client,
+                    git_repo_path,
+                    &repo_ref,
+                    proposals_for_status[selected_index],
+                    most_recent_proposal_patch_chain,
+                )
+                .await
+            }
+            5 => save_patches_to_dir(most_recent_proposal_patch_chain, &git_repo),
+            6 => {
+                print_proposal_preview(&cover_letter, &most_recent_proposal_patch_chain)?;
+                preselected_proposal = Some(proposals_for_status[selected_index].id);
+                continue;
+            }
+            7 => continue,
             _ => {
                 bail!("unexpected choice")
             }
@@ -646,7 +1156,180 @@ pub async fn launch() -> Result<()> {
     }
 }
 
-fn launch_git_am_with_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
+/// proposal root event id from a reference to the proposal, one of its patches
+/// or revisions. fetched from relays if it isn't cached. None if `input` is an
+/// naddr for this repository
+async fn find_proposal_from_user_input(
+    client: &Client,
+    git_repo_path: &Path,
+    repo_ref: &RepoRef,
+    input: &str,
+) -> Result<Option<EventId>> {
+    if let Ok(coordinate) = Coordinate::parse(input.trim()) {
+        if repo_ref.coordinates().iter().any(|c| {
+            c.public_key.eq(&coordinate.public_key) && c.identifier.eq(&coordinate.identifier)
+        }) {
+            return Ok(None);
+        }
+        bail!("{input} is a different repository. run `ngit list` in a clone of it instead");
+    }
+    let (id, relays) = if let Some(number) = parse_short_number(input) {
+        (
+            get_short_numbers(git_repo_path, repo_ref)
+                .await?
+                .id_of(number)
+                .context(format!(
+                    "there is no proposal #{number}. `ngit numbers` lists them"
+                ))?,
+            vec![],
+        )
+    } else {
+        event_id_from_user_input(input)?
+    };
+    let event = if let Some(event) =
+        get_events_from_local_cache(git_repo_path, vec![nostr::Filter::default().id(id)])
+            .await?
+            .first()
+    {
+        event.clone()
+    } else {
+        let events = client
+            .get_events(
+                [
+                    relays
+                        .iter()
+                        .map(|r| r.to_string())
+                        .collect::<Vec<String>>(),
+                    repo_ref.relays.iter().map(|r| r.to_string()).collect(),
+                ]
+                .concat(),
+                vec![
+                    nostr::Filter::default().id(id),
+                    nostr::Filter::default().kind(Kind::GitPatch).event(id),
+                ],
+            )
+            .await?;
+        for event in &events {
+            save_event_in_local_cache(git_repo_path, event).await?;
+        }
+        events.into_iter().find(|e| e.id.eq(&id)).context(format!(
+            "failed to find event {id} in the cache or on relays"
+        ))?
+    };
+    if event_is_patch_set_root(&event) && !event_is_revision_root(&event) {
+        Ok(Some(event.id))
+    } else {
+        Ok(Some(get_event_root(&event).context(
+            "event isn't a proposal, or a patch or revision of one",
+        )?))
+    }
+}
+
+/// send a NIP-09 deletion request for duplicate proposal roots and the
+/// patches their author added to them
+async fn retract_duplicates(
+    cli_args: &Cli,
+    client: &mut Client,
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    duplicates: &[nostr::Event],
+) -> Result<()> {
+    let git_repo_path = git_repo.get_path()?;
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&*client),
+        true,
+    )
+    .await?;
+
+    let mut events = vec![];
+    for duplicate in duplicates {
+        events.push(duplicate.clone());
+        events.extend(
+            get_all_proposal_patch_events_from_cache(git_repo_path, repo_ref, &duplicate.id)
+                .await?
+                .into_iter()
+                .filter(|e| e.pubkey.eq(&duplicate.pubkey) && !e.id.eq(&duplicate.id)),
+        );
+    }
+    let deletion =
+        generate_deletion_event(&events, "duplicate proposal", repo_ref, &signer).await?;
+
+    client.set_signer(signer).await;
+    send_events(
+        client,
+        Some(git_repo_path),
+        vec![deletion.clone()],
+        user_ref.relays.write(),
+        repo_ref.relays.clone(),
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+    save_event_in_local_cache(git_repo_path, &deletion).await?;
+    println!("deletion request sent");
+    Ok(())
+}
+
+/// trailers from the `trailers` template for commits applied from `proposal`,
+/// with reviewers taken from maintainers' `+` reactions to it
+async fn proposal_trailers(
+    client: &Client,
+    git_repo_path: &Path,
+    repo_ref: &RepoRef,
+    proposal: &nostr::Event,
+    patches: &[nostr::Event],
+) -> Result<Vec<String>> {
+    let Some(template) = &settings().trailers else {
+        return Ok(vec![]);
+    };
+    let reactions = client
+        .get_events(
+            repo_ref.relays.iter().map(ToString::to_string).collect(),
+            vec![
+                nostr::Filter::default()
+                    .kind(Kind::Reaction)
+                    .events(patches.iter().map(|e| e.id).chain([proposal.id])),
+            ],
+        )
+        .await
+        .context("failed to fetch reactions to the proposal for its trailers")?;
+    let approvers = approvers(proposal, &reactions, &repo_ref.maintainers);
+    fetch_missing_profiles(client, git_repo_path, repo_ref, &approvers).await?;
+    let mut reviewers = vec![];
+    for public_key in &approvers {
+        let npub = public_key.to_bech32()?;
+        reviewers.push(
+            match get_user_ref_from_cache(Some(git_repo_path), public_key).await {
+                Ok(user_ref) => reviewer(&user_ref.metadata.name, &npub),
+                _ => npub,
+            },
+        );
+    }
+    let nevent = nip19::Nip19Event {
+        event_id: proposal.id,
+        author: Some(proposal.pubkey),
+        kind: Some(proposal.kind),
+        relays: repo_ref
+            .relays
+            .first()
+            .map(|relay| vec![relay.to_string()])
+            .unwrap_or_default(),
+    }
+    .to_bech32()?;
+    Ok(render_trailers(template, &nevent, &reviewers))
+}
+
+async fn launch_git_am_with_patches(
+    client: &Client,
+    git_repo_path: &Path,
+    repo_ref: &RepoRef,
+    proposal: &nostr::Event,
+    mut patches: Vec<nostr::Event>,
+) -> Result<()> {
+    let trailers = proposal_trailers(client, git_repo_path, repo_ref, proposal, &patches).await?;
     println!("applying to current branch with `git am`");
     // TODO: add PATCH x/n to appended patches
     patches.reverse();
@@ -666,7 +1349,7 @@ fn launch_git_am_with_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
 
     for patch in patches {
         stdin
-            .write(format!("{}\n\n", patch.content).as_bytes())
+            .write(format!("{}\n\n", append_trailers(&patch.content, &trailers)).as_bytes())
             .context("failed to write patch content into git am stdin buffer")?;
     }
     stdin.flush()?;
@@ -677,11 +1360,37 @@ fn launch_git_am_with_patches(mut patches: Vec<nostr::Event>) -> Result<()> {
     Ok(())
 }
 
+static PREVIEW_CHOICE: &str = "preview cover letter and diffstat";
+
+/// the cover letter and each patch's diffstat, oldest first
+fn print_proposal_preview(cover_letter: &CoverLetter, patches: &[nostr::Event]) -> Result<()> {
+    println!("{}", console::style(&cover_letter.title).bold());
+    if !cover_letter.description.trim().is_empty() {
+        println!("\n{}", cover_letter.description.trim());
+    }
+    for patch in patches.iter().rev() {
+        println!(
+            "\n{} {}",
+            theme::dim().apply_to(
+                get_commit_id_from_patch(patch)
+                    .unwrap_or_default()
+                    .chars()
+                    .take(7)
+                    .collect::<String>()
+            ),
+            commit_msg_from_patch_oneliner(patch)?,
+        );
+        print!("{}", get_diffstat_of_patch(patch).unwrap_or_default());
+    }
+    println!();
+    Ok(())
+}
+
 fn event_id_extra_shorthand(event: &nostr::Event) -> String {
     event.id.to_string()[..5].to_string()
 }
 
-fn save_patches_to_dir(mut patches: Vec<nostr::Event>, git_repo: &Repo) -> Result<()> {
+fn save_patches_to_dir(mut patches: Vec<nostr::Event>, git_repo: &impl RepoActions) -> Result<()> {
     // TODO: add PATCH x/n to appended patches
     patches.reverse();
     let path = git_repo.get_path()?.join("patches");
@@ -712,11 +1421,180 @@ fn save_patches_to_dir(mut patches: Vec<nostr::Event>, git_repo: &Repo) -> Resul
     Ok(())
 }
 
-fn check_clean(git_repo: &Repo) -> Result<()> {
-    if git_repo.has_outstanding_changes()? {
-        bail!(
-            "failed to pull proposal branch when repository is not clean. discard or stash (un)staged changes and try again."
+/// commits on the local proposal branch on top of the latest published
+/// version, newest first, or None if the branch doesn't build on it
+fn unpublished_commits_on_proposal(
+    git_repo: &impl RepoActions,
+    proposal_tip: &Sha1Hash,
+    local_branch_tip: &Sha1Hash,
+) -> Result<Option<Vec<Sha1Hash>>> {
+    if !git_repo.ancestor_of(local_branch_tip, proposal_tip)? {
+        return Ok(None);
+    }
+    let (local_ahead_of_proposal, _) = git_repo
+        .get_commits_ahead_behind(proposal_tip, local_branch_tip)
+        .context("failed to get commits ahead behind for propsal_top and local_branch_tip")?;
+    Ok(Some(local_ahead_of_proposal))
+}
+
+/// dry-run applying the latest revision and rebasing `local_only_commits`
+/// (oldest first) on top, reporting conflicts with them and with uncommitted
+/// changes. returns the rebased tip if there are no conflicts.
+fn preview_update(
+    git_repo: &impl RepoActions,
+    proposal_base_commit: &Sha1Hash,
+    patch_chain: &[nostr::Event],
+    local_only_commits: &[Sha1Hash],
+) -> Result<Option<Sha1Hash>> {
+    let mut patches = patch_chain.to_vec();
+    patches.reverse();
+
+    if !local_only_commits.is_empty() {
+        println!(
+            "{} local-only commits would be affected by updating:",
+            local_only_commits.len()
+        );
+        for commit in local_only_commits {
+            println!(
+                "  {} {}",
+                &commit.to_string()[..7],
+                git_repo.get_commit_message_summary(commit)?
+            );
+        }
+    }
+
+    let rebased_tip = match git_repo
+        .create_commits_from_patches(&patches, proposal_base_commit)
+        .and_then(|new_tip| git_repo.rebase_commits_onto(local_only_commits, &new_tip))
+    {
+        Ok(tip) => {
+            if !local_only_commits.is_empty() {
+                println!("they can be rebased onto the new revision without conflicts");
+            }
+            Some(tip)
+        }
+        Err(error) => {
+            if let Some(conflicts) = error.downcast_ref::<PatchConflicts>() {
+                println!(
+                    "rebasing them onto the new revision would conflict in: {}",
+                    conflicts.paths.join(", ")
+                );
+            } else {
+                println!("failed to preview update: {error}");
+            }
+            None
+        }
+    };
+
+    let mut changed_paths = vec![];
+    for patch in &patches {
+        changed_paths.extend(get_paths_changed_in_patch(patch)?);
+    }
+    let uncommitted_conflicts: Vec<String> = git_repo
+        .get_outstanding_changes_paths()?
+        .into_iter()
+        .filter(|path| changed_paths.contains(path))
+        .collect();
+    if !uncommitted_conflicts.is_empty() {
+        println!(
+            "uncommitted changes to files changed by the proposal may conflict when they are restored: {}",
+            uncommitted_conflicts.join(", ")
         );
     }
+    Ok(rebased_tip)
+}
+
+/// apply the patches where the proposal was created or, if that commit isn't
+/// in the local history, offer to apply them on top of main instead
+fn apply_patch_chain_or_offer_main(
+    git_repo: &impl RepoActions,
+    branch_name: &str,
+    patch_and_ancestors: Vec<nostr::Event>,
+) -> Result<Vec<nostr::Event>> {
+    match git_repo.apply_patch_chain(branch_name, patch_and_ancestors.clone()) {
+        Err(error) => {
+            let Some(missing) = error.downcast_ref::<ParentCommitMissing>() else {
+                return Err(error);
+            };
+            if Interactor::default().confirm(
+                PromptConfirmParms::default()
+                    .with_prompt(format!(
+                        "the proposal's parent commit {} isn't in your local history. apply it on top of the main branch instead of running git pull?",
+                        missing.commit_id
+                    ))
+                    .with_default(false),
+            )? {
+                git_repo.apply_patch_chain_onto_main(branch_name, patch_and_ancestors)
+            } else {
+                Err(error)
+            }
+        }
+        result => result,
+    }
+}
+
+/// stash uncommitted changes so a proposal branch can be checked out
+fn stash_if_dirty(git_repo: &impl RepoActions, branch_name: &str) -> Result<Option<Sha1Hash>> {
+    let stash = git_repo.stash_outstanding_changes(&format!(
+        "ngit: uncommitted changes before checking out {branch_name}"
+    ))?;
+    if stash.is_some() {
+        println!("stashed uncommitted changes. they can be restored with `git stash pop`");
+    }
+    Ok(stash)
+}
+
+fn offer_to_pop_stash(git_repo: &impl RepoActions, stash: Option<Sha1Hash>) -> Result<()> {
+    if let Some(stash) = stash {
+        if Interactor::default().confirm(
+            PromptConfirmParms::default()
+                .with_prompt("apply stashed changes to the proposal branch?")
+                .with_default(true),
+        )? {
+            git_repo.pop_stash(&stash).context(
+                "failed to apply stashed changes. they are still available with `git stash list`",
+            )?;
+            println!("applied stashed changes");
+        }
+    }
     Ok(())
 }
+
+#[cfg(all(test, feature = "memory-repo"))]
+mod tests {
+    use ngit::git::memory::MemoryRepo;
+
+    use super::*;
+
+    mod unpublished_commits_on_proposal {
+        use super::*;
+
+        #[test]
+        fn commits_added_on_top_of_the_proposal() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            git_repo.commit("main", "initial commit");
+            let proposal_tip = git_repo.commit("main", "proposal commit");
+            let first = git_repo.commit("main", "unpublished commit");
+            let second = git_repo.commit("main", "another unpublished commit");
+            assert_eq!(
+                unpublished_commits_on_proposal(&git_repo, &proposal_tip, &second)?,
+                Some(vec![second, first])
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn none_when_the_proposal_was_amended() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let base = git_repo.commit("main", "initial commit");
+            let proposal_tip = git_repo.commit("main", "proposal commit");
+            git_repo.create_branch_at_commit("amended", &base.to_string())?;
+            let amended = git_repo.commit("amended", "amended proposal commit");
+            assert_eq!(
+                unpublished_commits_on_proposal(&git_repo, &proposal_tip, &amended)?,
+                None
+            );
+            Ok(())
+        }
+    }
+}
@@ -1,10 +1,20 @@
 use anyhow::{Context, Result};
 use clap;
 use ngit::{
-    cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms},
+    cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms, PromptPasswordParms},
     git::{get_git_config_item, remove_git_config_item},
-    login::{SignerInfoSource, existing::load_existing_login},
+    login::{
+        SignerInfo, SignerInfoSource,
+        existing::load_existing_login,
+        fresh::{RemoteSignerMethod, get_fresh_nip46_signer_with_method},
+        get_curent_user,
+        mnemonic::keys_from_mnemonic,
+        os_keyring::delete_nsec_from_keyring_if_unused,
+    },
+    output,
 };
+use nostr_sdk::ToBech32;
+use serde_json::json;
 
 use crate::{
     cli::{Cli, extract_signer_cli_arguments},
@@ -22,6 +32,24 @@ pub struct SubCommandArgs {
     /// don't fetch user metadata and relay list from relays
     #[arg(long, action)]
     offline: bool,
+
+    /// show a nostrconnect:// QR code to scan with a remote signer app (eg.
+    /// Amber)
+    #[arg(long, action)]
+    connect: bool,
+
+    /// save as a named identity and use it for this repository. select it
+    /// elsewhere with `git config nostr.identity <name>`
+    #[arg(long)]
+    identity: Option<String>,
+
+    /// derive the key from a BIP-39 seed phrase (NIP-06)
+    #[arg(long, action)]
+    mnemonic: bool,
+
+    /// account index used with --mnemonic
+    #[arg(long, default_value_t = 0, requires = "mnemonic")]
+    account_index: u32,
 }
 
 pub async fn launch(args: &Cli, command_args: &SubCommandArgs) -> Result<()> {
@@ -39,15 +67,47 @@ pub async fn launch(args: &Cli, command_args: &SubCommandArgs) -> Result<()> {
         }
     };
 
-    let (logged_out, log_in_locally_only) = logout(git_repo.as_ref(), command_args.local).await?;
-    if logged_out || log_in_locally_only {
-        fresh_login_or_signup(
+    let user_ref = if let Some(identity) = &command_args.identity {
+        // other logins are left in place
+        let (_, user_ref, _) = fresh_login_or_signup(
             &git_repo.as_ref(),
             client.as_ref(),
-            extract_signer_cli_arguments(args)?,
-            log_in_locally_only || command_args.local,
+            get_signer_info_from_args(args, command_args, client.as_ref()).await?,
+            true,
+            Some(identity),
         )
         .await?;
+        Some(user_ref)
+    } else {
+        let (logged_out, log_in_locally_only) =
+            logout(git_repo.as_ref(), command_args.local).await?;
+        if logged_out || log_in_locally_only {
+            let (_, user_ref, _) = fresh_login_or_signup(
+                &git_repo.as_ref(),
+                client.as_ref(),
+                get_signer_info_from_args(args, command_args, client.as_ref()).await?,
+                log_in_locally_only || command_args.local,
+                None,
+            )
+            .await?;
+            Some(user_ref)
+        } else {
+            None
+        }
+    };
+
+    if output::json() {
+        let public_key = match &user_ref {
+            Some(user_ref) => Some(user_ref.public_key),
+            None => git_repo
+                .as_ref()
+                .and_then(|git_repo| get_curent_user(git_repo).ok().flatten()),
+        };
+        output::emit(&json!({
+            "npub": public_key.map(|public_key| public_key.to_bech32()).transpose()?,
+            "name": user_ref.as_ref().map(|user_ref| user_ref.metadata.name.clone()),
+            "changed": user_ref.is_some(),
+        }))?;
     }
 
     // If not offline, disconnect the client
@@ -57,9 +117,35 @@ pub async fn launch(args: &Cli, command_args: &SubCommandArgs) -> Result<()> {
     Ok(())
 }
 
+async fn get_signer_info_from_args(
+    args: &Cli,
+    command_args: &SubCommandArgs,
+    client: Option<&Client>,
+) -> Result<Option<SignerInfo>> {
+    if command_args.connect {
+        let (_, _, signer_info, _) =
+            get_fresh_nip46_signer_with_method(client, &RemoteSignerMethod::QrCode).await?;
+        Ok(Some(signer_info))
+    } else if command_args.mnemonic {
+        let mnemonic = Interactor::default().password(
+            PromptPasswordParms::default()
+                .with_prompt("seed phrase")
+                .dont_report(),
+        )?;
+        let keys = keys_from_mnemonic(&mnemonic, command_args.account_index)?;
+        Ok(Some(SignerInfo::Nsec {
+            nsec: keys.secret_key().to_bech32()?,
+            password: None,
+            npub: Some(keys.public_key().to_bech32()?),
+        }))
+    } else {
+        extract_signer_cli_arguments(args)
+    }
+}
+
 /// return ( bool - logged out, bool - log in to local git locally)
 async fn logout(git_repo: Option<&Repo>, local_only: bool) -> Result<(bool, bool)> {
-    for source in if local_only || std::env::var("NGITTEST").is_ok() {
+    for source in if local_only || ngit::settings::settings().test_mode {
         vec![SignerInfoSource::GitLocal]
     } else {
         vec![SignerInfoSource::GitLocal, SignerInfoSource::GitGlobal]
@@ -107,6 +193,9 @@ async fn logout(git_repo: Option<&Repo>, local_only: bool) -> Result<(bool, bool
                         "nostr.npub",
                         "nostr.bunker-uri",
                         "nostr.bunker-app-key",
+                        "nostr.nip07-bridge",
+                        "nostr.hardware-signer",
+                        "nostr.identity",
                     ] {
                         if let Err(error) = remove_git_config_item(
                             if source == SignerInfoSource::GitLocal {
@@ -144,6 +233,9 @@ async fn logout(git_repo: Option<&Repo>, local_only: bool) -> Result<(bool, bool
                             }
                         }
                     }
+                    if let Ok(npub) = user_ref.public_key.to_bech32() {
+                        let _ = delete_nsec_from_keyring_if_unused(&git_repo, &npub);
+                    }
                 }
                 1 => return Ok((false, local_only)),
                 _ => return Ok((false, true)),
@@ -159,6 +251,8 @@ pub fn get_global_login_config_items_set() -> Vec<&'static str> {
         "nostr.npub",
         "nostr.bunker-uri",
         "nostr.bunker-app-key",
+        "nostr.nip07-bridge",
+        "nostr.hardware-signer",
     ]
     .iter()
     .copied()
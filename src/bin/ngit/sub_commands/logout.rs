@@ -1,15 +1,33 @@
 use anyhow::{Context, Result};
 use ngit::{
     git::remove_git_config_item,
-    login::{SignerInfoSource, existing::load_existing_login},
+    login::{
+        SignerInfoSource, existing::load_existing_login,
+        os_keyring::delete_nsec_from_keyring_if_unused, session_lock::lock_keys,
+    },
 };
+use nostr_sdk::ToBech32;
 
 use crate::{
     git::Repo,
     sub_commands::login::{format_items_as_list, get_global_login_config_items_set},
 };
 
-pub async fn launch() -> Result<()> {
+#[derive(clap::Args)]
+pub struct SubCommandArgs {
+    /// only forget unlocked keys so the password is required again
+    #[arg(long, action)]
+    lock: bool,
+}
+
+pub async fn launch(args: &SubCommandArgs) -> Result<()> {
+    if let Err(error) = lock_keys() {
+        eprintln!("warning: failed to lock unlocked keys: {error:#}");
+    }
+    if args.lock {
+        println!("locked. your password will be required next time");
+        return Ok(());
+    }
     let git_repo_result = Repo::discover().context("failed to find a git repository");
     let git_repo = {
         match git_repo_result {
@@ -21,7 +39,7 @@ pub async fn launch() -> Result<()> {
 }
 
 async fn logout(git_repo: Option<&Repo>) -> Result<()> {
-    for source in if std::env::var("NGITTEST").is_ok() {
+    for source in if ngit::settings::settings().test_mode {
         vec![SignerInfoSource::GitLocal]
     } else {
         vec![SignerInfoSource::GitLocal, SignerInfoSource::GitGlobal]
@@ -43,6 +61,9 @@ async fn logout(git_repo: Option<&Repo>) -> Result<()> {
                 "nostr.npub",
                 "nostr.bunker-uri",
                 "nostr.bunker-app-key",
+                "nostr.nip07-bridge",
+                "nostr.hardware-signer",
+                "nostr.identity",
             ] {
                 if let Err(error) = remove_git_config_item(
                     if source == SignerInfoSource::GitLocal {
@@ -74,6 +95,9 @@ async fn logout(git_repo: Option<&Repo>) -> Result<()> {
                     return Ok(());
                 }
             }
+            if let Ok(npub) = user_ref.public_key.to_bech32() {
+                let _ = delete_nsec_from_keyring_if_unused(&git_repo, &npub);
+            }
             println!(
                 "logged out {}as {}",
                 if source == SignerInfoSource::GitLocal {
@@ -0,0 +1,199 @@
+use std::{fmt::Write as _, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Arg, Command, CommandFactory};
+
+use crate::cli::Cli;
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    #[clap(short, long, default_value = "man")]
+    /// directory to write the pages to
+    output: PathBuf,
+    #[clap(long)]
+    /// write a single markdown reference, ngit.md, instead of man pages
+    markdown: bool,
+}
+
+static ABOUT: &str = "nostr plugin for git";
+
+/// write a man page, or a markdown section, for ngit, every visible
+/// subcommand and git-remote-nostr, generated from the clap definitions
+pub fn launch(args: &SubCommandArgs) -> Result<()> {
+    let mut ngit = Cli::command().about(ABOUT);
+    ngit.build();
+    let commands = all_commands(&ngit, vec![]);
+
+    std::fs::create_dir_all(&args.output)
+        .context(format!("failed to create {}", args.output.display()))?;
+    let version = ngit.get_version().unwrap_or_default().to_string();
+    let files = if args.markdown {
+        let mut markdown = String::new();
+        for (path, command) in &commands {
+            markdown.push_str(&render_markdown(path, command));
+        }
+        markdown.push_str(&render_markdown_remote_helper());
+        vec![("ngit.md".to_string(), markdown)]
+    } else {
+        let mut pages = vec![];
+        for (path, command) in &commands {
+            pages.push((
+                format!("{}.1", path.join("-")),
+                render_man_page(path, command)?,
+            ));
+        }
+        pages.push((
+            "git-remote-nostr.1".to_string(),
+            render_man_page_remote_helper(&version)?,
+        ));
+        pages
+    };
+    for (name, contents) in files {
+        let path = args.output.join(name);
+        std::fs::write(&path, contents).context(format!("failed to write {}", path.display()))?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// `command` and its visible descendants, each with its name path from ngit
+fn all_commands(command: &Command, parent: Vec<String>) -> Vec<(Vec<String>, Command)> {
+    let mut path = parent;
+    path.push(command.get_name().to_string());
+    let mut commands = vec![(path.clone(), command.clone())];
+    for subcommand in visible_subcommands(command) {
+        commands.extend(all_commands(subcommand, path.clone()));
+    }
+    commands
+}
+
+fn visible_subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set() && subcommand.get_name() != "help")
+}
+
+fn visible_args(command: &Command) -> impl Iterator<Item = &Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && !matches!(arg.get_id().as_str(), "help" | "version"))
+}
+
+fn about(command: &Command) -> String {
+    command
+        .get_long_about()
+        .or(command.get_about())
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+fn usage(command: &Command) -> String {
+    command
+        .clone()
+        .render_usage()
+        .to_string()
+        .trim_start_matches("Usage: ")
+        .to_string()
+}
+
+/// `-n, --nsec <NSEC>` or `<PROPOSAL>`
+fn arg_signature(arg: &Arg) -> String {
+    let value = || {
+        arg.get_value_names()
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| format!("<{name}>"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_else(|| format!("<{}>", arg.get_id().as_str().to_uppercase()))
+    };
+    let mut flags = vec![];
+    if let Some(short) = arg.get_short() {
+        flags.push(format!("-{short}"));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("--{long}"));
+    }
+    if flags.is_empty() {
+        return value();
+    }
+    let mut signature = flags.join(", ");
+    if arg.get_action().takes_values() {
+        signature.push(' ');
+        signature.push_str(&value());
+    }
+    signature
+}
+
+fn arg_help(arg: &Arg) -> String {
+    let mut help = arg
+        .get_long_help()
+        .or(arg.get_help())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let possible: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !possible.is_empty() && arg.get_action().takes_values() {
+        let _ = write!(help, " [possible values: {}]", possible.join(", "));
+    }
+    let defaults: Vec<String> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy().to_string())
+        .collect();
+    if !defaults.is_empty() && arg.get_action().takes_values() {
+        let _ = write!(help, " [default: {}]", defaults.join(", "));
+    }
+    help
+}
+
+/// `command` retitled with its full name so its page is, eg. ngit-init(1)
+fn render_man_page(path: &[String], command: &Command) -> Result<String> {
+    let mut page = vec![];
+    clap_mangen::Man::new(command.clone().name(path.join("-")))
+        .manual("ngit manual")
+        .render(&mut page)
+        .context("failed to render man page")?;
+    String::from_utf8(page).context("man page is not utf-8")
+}
+
+fn render_markdown(path: &[String], command: &Command) -> String {
+    let mut section = format!(
+        "{} `{}`\n\n{}\n\n```\n{}\n```\n\n",
+        "#".repeat(path.len()),
+        path.join(" "),
+        about(command),
+        usage(command),
+    );
+    for arg in visible_args(command) {
+        let _ = writeln!(section, "- `{}` {}", arg_signature(arg), arg_help(arg));
+    }
+    section.push('\n');
+    section
+}
+
+/// git-remote-nostr isn't a clap command so its page is described here
+static REMOTE_HELPER_DESCRIPTION: &str = "git runs git-remote-nostr for remotes whose url starts with nostr://, eg. nostr://npub123/identifier. it fetches the repository announcement and state from nostr relays, and pushes to the git servers listed in the announcement while publishing the new state.
+remote branches beginning with `pr/` are open proposals from contributors. pushing a branch with the `pr/` prefix sends it as a proposal.";
+
+fn render_man_page_remote_helper(version: &str) -> Result<String> {
+    let remote_helper = Command::new("git-remote-nostr")
+        .version(version.to_string())
+        .about("git remote helper for nostr repositories")
+        .long_about(REMOTE_HELPER_DESCRIPTION)
+        .arg(Arg::new("remote").required(true))
+        .arg(Arg::new("url").required(true));
+    render_man_page(&["git-remote-nostr".to_string()], &remote_helper)
+}
+
+fn render_markdown_remote_helper() -> String {
+    format!(
+        "# `git-remote-nostr`\n\ngit remote helper for nostr repositories\n\n```\ngit-remote-nostr <remote> <url>\n```\n\n{REMOTE_HELPER_DESCRIPTION}\n"
+    )
+}
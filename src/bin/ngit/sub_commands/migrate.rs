@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use ngit::{
+    client::sign_events,
+    git_events::{commit_msg_from_patch_oneliner, format_event_for_dry_run},
+    login::user::get_author_display_name,
+    migrate::{
+        get_filter_patches_for_migration, get_migrated_from, is_legacy_patch, legacy_series,
+        migrated_from, migrated_series,
+    },
+};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    cli_interactor::{Interactor, InteractorPrompt, PromptConfirmParms},
+    client::{
+        Client, Connect, fetching_with_report, get_events_from_local_cache,
+        get_repo_ref_from_cache, resolve_repo_trust, send_events,
+    },
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    #[arg(long, action)]
+    /// print the events that would be published instead of publishing them
+    dry_run: bool,
+}
+
+/// find proposals published by older ngit versions or other git-nostr tools
+/// and republish them, signed by you, as proposals current clients list
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let mut client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    let fetch_report = fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    resolve_repo_trust(&git_repo, &fetch_report, &repo_coordinates).await?;
+
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+    let root_commit = git_repo
+        .get_root_commit()
+        .context("failed to get root commit of the repository")?
+        .to_string();
+
+    let filters = get_filter_patches_for_migration(&repo_ref.coordinates(), &root_commit);
+    let mut patches = get_events_from_local_cache(git_repo_path, filters.clone()).await?;
+    // older tools didn't tag the announcement, so these aren't fetched by default
+    match client
+        .get_events(
+            repo_ref.relays.iter().map(ToString::to_string).collect(),
+            filters,
+        )
+        .await
+    {
+        Ok(events) => patches.extend(events),
+        Err(error) => eprintln!("failed to search the repository relays: {error}"),
+    }
+    let mut seen = HashSet::new();
+    patches.retain(|e| seen.insert(e.id));
+
+    let already_migrated = get_migrated_from(&patches);
+    let series: Vec<_> = legacy_series(
+        &patches
+            .iter()
+            .filter(|e| is_legacy_patch(e, &repo_ref.coordinates()))
+            .cloned()
+            .collect::<Vec<_>>(),
+    )
+    .into_iter()
+    .filter(|series| migrated_from(&series[0]).is_ok_and(|id| !already_migrated.contains(&id)))
+    .collect();
+
+    if series.is_empty() {
+        println!("no proposals from older ngit versions or other git-nostr tools found");
+        return Ok(());
+    }
+    println!(
+        "found {} proposal{} in an older format:",
+        series.len(),
+        if series.len() == 1 { "" } else { "s" }
+    );
+    for patches in &series {
+        println!(
+            "  {} ({} patch{}) by {}",
+            commit_msg_from_patch_oneliner(&patches[0]).unwrap_or_default(),
+            patches.len(),
+            if patches.len() == 1 { "" } else { "es" },
+            get_author_display_name(Some(git_repo_path), &patches[0].pubkey).await,
+        );
+    }
+
+    if !args.dry_run
+        && !Interactor::default().confirm(
+            PromptConfirmParms::default()
+                .with_prompt("republish them as proposals signed by you, linking to the originals?")
+                .with_default(true),
+        )?
+    {
+        return Ok(());
+    }
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    let mut unsigned = vec![];
+    for patches in &series {
+        unsigned.extend(migrated_series(
+            patches,
+            &repo_ref,
+            &root_commit,
+            &user_ref.public_key,
+        )?);
+    }
+    let events = sign_events(unsigned, &signer).await?;
+
+    if args.dry_run {
+        println!(
+            "dry run: would publish {} event{} to nostr relays:",
+            events.len(),
+            if events.len() == 1 { "" } else { "s" }
+        );
+        for event in &events {
+            for line in format_event_for_dry_run(event) {
+                println!("  {line}");
+            }
+        }
+        return Ok(());
+    }
+
+    client.set_signer(signer).await;
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        events,
+        user_ref.relays.write(),
+        repo_ref.relays.clone(),
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+
+    println!(
+        "migrated {} proposal{}. `ngit list` now shows {}",
+        series.len(),
+        if series.len() == 1 { "" } else { "s" },
+        if series.len() == 1 { "it" } else { "them" }
+    );
+    Ok(())
+}
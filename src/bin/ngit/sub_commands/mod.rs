@@ -1,6 +1,21 @@
+pub mod bridge;
+pub mod bundle;
+pub mod daemon;
+pub mod delegate;
+pub mod export;
 pub mod export_keys;
+pub mod external;
+pub mod feed;
+pub mod import;
 pub mod init;
 pub mod list;
 pub mod login;
 pub mod logout;
+pub mod man;
+pub mod migrate;
+pub mod numbers;
+pub mod org;
+pub mod prune_relays;
+pub mod self_update;
 pub mod send;
+pub mod url;
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    git_events::{commit_msg_from_patch_oneliner, event_to_cover_letter, tag_value},
+    short_numbers::get_short_numbers,
+};
+use nostr::{Event, Filter, Kind};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{
+        Client, Connect, fetching_with_report, get_events_from_local_cache,
+        get_repo_ref_from_cache, send_events,
+    },
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    #[clap(long)]
+    /// publish this numbering so every clone uses it. for maintainers
+    publish: bool,
+}
+
+fn title(event: &Event) -> String {
+    if event.kind == Kind::GitIssue {
+        tag_value(event, "subject")
+            .unwrap_or_else(|_| event.content.lines().next().unwrap_or_default().to_string())
+    } else if let Ok(cover_letter) = event_to_cover_letter(event) {
+        cover_letter.title
+    } else {
+        commit_msg_from_patch_oneliner(event).unwrap_or_else(|_| event.id.to_string())
+    }
+}
+
+/// list the numbers of proposals and issues, which `ngit list` and `ngit url`
+/// accept in place of event ids
+pub async fn launch(cli_args: &Cli, args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let mut client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+
+    let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    let numbers = get_short_numbers(git_repo_path, &repo_ref).await?;
+    let events = get_events_from_local_cache(
+        git_repo_path,
+        vec![Filter::default().ids(numbers.iter().map(|(_, id)| *id))],
+    )
+    .await?;
+    let mut listed = 0;
+    for (number, id) in numbers.iter() {
+        if let Some(event) = events.iter().find(|e| e.id.eq(id)) {
+            println!(
+                "#{number} {} {}",
+                if event.kind == Kind::GitIssue {
+                    "issue"
+                } else {
+                    "proposal"
+                },
+                title(event)
+            );
+            listed += 1;
+        }
+    }
+    if listed == 0 {
+        println!("no proposals or issues found");
+        return Ok(());
+    }
+    if !args.publish {
+        return Ok(());
+    }
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    if !repo_ref.maintainers.contains(&user_ref.public_key) {
+        bail!(
+            "your nostr account {} isn't listed as a maintainer of the repo",
+            user_ref.metadata.name
+        );
+    }
+
+    let event = numbers.to_index_event(&repo_ref, &signer).await?;
+
+    client.set_signer(signer).await;
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![event],
+        user_ref.relays.write(),
+        repo_ref.relays.clone(),
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+
+    println!("published the numbering. other clones adopt it when they next fetch");
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result, bail};
+use ngit::{
+    login::user::get_author_display_name,
+    org::{Org, fetch_org, fetch_org_repos},
+};
+use nostr::{FromBech32, PublicKey, ToBech32, nips::nip01::Coordinate};
+use nostr_sdk::RelayUrl;
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    client::{Client, Connect, send_events},
+    git::{Repo, RepoActions},
+    login,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct CreateSubCommandArgs {
+    /// shortname with no spaces or special characters
+    identifier: String,
+    #[clap(short, long)]
+    /// display name of the organization
+    name: Option<String>,
+    #[clap(short, long, value_parser, num_args = 1..)]
+    /// npubs of members who can maintain the organization's repositories
+    members: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReposSubCommandArgs {
+    /// naddr of the organization
+    org: String,
+}
+
+/// publish or update an organization maintainer list. repositories join it
+/// with `ngit init --org`
+pub async fn launch_create(cli_args: &Cli, args: &CreateSubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().ok();
+    let git_repo_path = if let Some(git_repo) = &git_repo {
+        Some(git_repo.get_path()?)
+    } else {
+        None
+    };
+
+    let mut client = Client::default();
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &git_repo.as_ref(),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    let mut members = vec![user_ref.public_key];
+    for npub in &args.members {
+        let member = PublicKey::from_bech32(npub)
+            .or_else(|_| PublicKey::from_hex(npub))
+            .context(format!("invalid member npub {npub}"))?;
+        if !members.contains(&member) {
+            members.push(member);
+        }
+    }
+
+    let org = Org {
+        identifier: args.identifier.clone(),
+        name: args.name.clone().unwrap_or_else(|| args.identifier.clone()),
+        public_key: user_ref.public_key,
+        members,
+    };
+    let event = org.to_event(&signer).await?;
+
+    client.set_signer(signer).await;
+
+    send_events(
+        &client,
+        git_repo_path,
+        vec![event],
+        user_ref.relays.write(),
+        vec![],
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+
+    let relays: Vec<RelayUrl> = user_ref
+        .relays
+        .write()
+        .iter()
+        .filter_map(|r| RelayUrl::parse(r).ok())
+        .take(1)
+        .collect();
+    println!(
+        "published organization {}. add repositories to it with `ngit init --org {}`",
+        org.name,
+        Coordinate {
+            relays,
+            ..org.coordinate()
+        }
+        .to_bech32()?
+    );
+    Ok(())
+}
+
+pub async fn launch_repos(args: &ReposSubCommandArgs) -> Result<()> {
+    let client = Client::default();
+    let Ok(coordinate) = Coordinate::parse(args.org.trim_start_matches("nostr:")) else {
+        bail!("invalid org. it should be an naddr");
+    };
+    let org = fetch_org(&client, &coordinate).await?;
+    let git_repo = Repo::discover().ok();
+    let git_repo_path = if let Some(git_repo) = &git_repo {
+        Some(git_repo.get_path()?)
+    } else {
+        None
+    };
+
+    println!("{}", org.name);
+    let mut member_names = vec![];
+    for member in &org.members {
+        member_names.push(get_author_display_name(git_repo_path, member).await);
+    }
+    println!("members: {}", member_names.join(", "));
+
+    let repos = fetch_org_repos(&client, &org, &coordinate).await?;
+    if repos.is_empty() {
+        println!("no repositories found");
+    }
+    for repo_ref in repos {
+        println!(
+            "{} - {}",
+            repo_ref.name,
+            repo_ref.to_nostr_git_url(&git_repo.as_ref())
+        );
+        if !repo_ref.description.is_empty() {
+            println!("  {}", repo_ref.description);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result, bail};
+use indicatif::MultiProgress;
+use nostr_sdk::{EventBuilder, Kind, RelayUrl};
+
+use crate::{
+    cli::{Cli, extract_signer_cli_arguments},
+    cli_interactor::{Interactor, InteractorPrompt, PromptConfirmParms},
+    client::{
+        Client, Connect, fetching_with_report, get_repo_ref_from_cache, resolve_repo_trust,
+        send_events, sign_event,
+    },
+    git::{Repo, RepoActions},
+    login,
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+/// an ephemeral kind, which relays don't store
+static WRITE_PROBE_KIND: u16 = 20_617;
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {}
+
+pub async fn launch(cli_args: &Cli, _args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let mut client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    let fetch_report = fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+    resolve_repo_trust(&git_repo, &fetch_report, &repo_coordinates).await?;
+
+    let mut repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
+
+    let (signer, user_ref, _) = login::login_or_signup(
+        &Some(&git_repo),
+        &extract_signer_cli_arguments(cli_args).unwrap_or(None),
+        &cli_args.password,
+        Some(&client),
+        true,
+    )
+    .await?;
+
+    if !repo_ref.maintainers.contains(&user_ref.public_key) {
+        bail!(
+            "your nostr account {} isn't listed as a maintainer of the repo",
+            user_ref.metadata.name
+        );
+    }
+
+    // relays acknowledge ephemeral events without storing them, so sending
+    // one checks writes without republishing anything
+    let probe_event = sign_event(
+        EventBuilder::new(Kind::from(WRITE_PROBE_KIND), "ngit relay write check"),
+        &signer,
+    )
+    .await
+    .context("failed to sign relay write check")?;
+
+    let mut dead_relays: Vec<RelayUrl> = vec![];
+    for relay in &repo_ref.relays {
+        let problem = if client.connect(relay).await.is_err() {
+            Some("failed to connect")
+        } else if client
+            .get_events_per_relay(
+                vec![relay.clone()],
+                vec![
+                    nostr::Filter::default()
+                        .kind(Kind::GitRepoAnnouncement)
+                        .limit(1),
+                ],
+                MultiProgress::new(),
+            )
+            .await
+            .map(|(results, _)| results.into_iter().all(|r| r.is_err()))
+            .unwrap_or(true)
+        {
+            Some("failed to read")
+        } else if client
+            .send_event_to(Some(git_repo_path), relay.as_str(), probe_event.clone())
+            .await
+            .is_err()
+        {
+            Some("failed to write")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            println!("{relay}: dead ({problem})");
+            dead_relays.push(relay.clone());
+        } else {
+            println!("{relay}: ok");
+        }
+    }
+
+    if dead_relays.is_empty() {
+        println!("all repository relays are working");
+        return Ok(());
+    }
+    if dead_relays.len() == repo_ref.relays.len() {
+        bail!(
+            "all repository relays failed. check your connection or replace them with `ngit init --relays`"
+        );
+    }
+    // `--yes` republishes without asking
+    if !Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "republish repository announcement without {} dead relay{}?",
+                dead_relays.len(),
+                if dead_relays.len() > 1 { "s" } else { "" }
+            ))
+            .with_default(true),
+    )? {
+        return Ok(());
+    }
+
+    repo_ref.relays.retain(|r| !dead_relays.contains(r));
+    let repo_event = repo_ref.to_event(&signer).await?;
+
+    client.set_signer(signer).await;
+
+    send_events(
+        &client,
+        Some(git_repo_path),
+        vec![repo_event],
+        user_ref.relays.write(),
+        repo_ref.relays.clone(),
+        !cli_args.disable_cli_spinners,
+        false,
+    )
+    .await?;
+
+    Ok(())
+}
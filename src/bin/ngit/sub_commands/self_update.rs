@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use ngit::self_update::{current_arch, download_release, fetch_latest_releases, is_newer_version};
+
+use crate::client::{Client, Connect};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    #[clap(long)]
+    /// only report whether a newer release is available
+    check: bool,
+}
+
+/// replace this ngit, and git-remote-nostr when installed alongside it, with
+/// the latest release published by ngit's maintainers on nostr
+pub async fn launch(args: &SubCommandArgs) -> Result<()> {
+    let client = Client::default();
+    let releases = fetch_latest_releases(&client).await;
+    client.disconnect().await?;
+    let releases = releases?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let Some(latest) = releases.iter().find(|release| release.bin == "ngit") else {
+        bail!(
+            "no ngit release for {} found on nostr. see https://gitworkshop.dev/ngit for other install options",
+            current_arch()
+        );
+    };
+    if !is_newer_version(current, &latest.version) {
+        println!("ngit v{current} is up to date");
+        return Ok(());
+    }
+    if args.check {
+        println!(
+            "ngit v{} is available (you have v{current}). run `ngit self-update` to upgrade",
+            latest.version
+        );
+        return Ok(());
+    }
+
+    let ngit_path = std::env::current_exe().context("failed to find the ngit executable")?;
+    let install_dir = ngit_path
+        .parent()
+        .context("failed to find the directory ngit is installed in")?;
+    for release in releases
+        .iter()
+        .filter(|release| release.version == latest.version)
+    {
+        // the bin tag comes from the release event so it is never used as a
+        // path. git-remote-nostr is only updated where it is already installed
+        let path = match release.bin.as_str() {
+            "ngit" => ngit_path.clone(),
+            "git-remote-nostr" => {
+                let path =
+                    install_dir.join(format!("git-remote-nostr{}", std::env::consts::EXE_SUFFIX));
+                if !path.exists() {
+                    continue;
+                }
+                path
+            }
+            _ => continue,
+        };
+        println!("downloading {} v{}...", release.bin, release.version);
+        let binary = download_release(release).await?;
+        install(&path, &binary).context(format!(
+            "failed to replace {}. is it writable by you?",
+            path.display()
+        ))?;
+        println!("updated {} to v{}", path.display(), release.version);
+    }
+    Ok(())
+}
+
+/// write `binary` beside `path` and move it into place, so an interrupted
+/// update never leaves a partial executable
+fn install(path: &Path, binary: &[u8]) -> Result<()> {
+    let staged = path.with_extension("ngit-update");
+    std::fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // windows won't overwrite a running executable but will rename it
+    if cfg!(windows) && path.exists() {
+        let old = path.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(path, &old)?;
+    }
+    std::fs::rename(&staged, path)?;
+    Ok(())
+}
@@ -2,25 +2,37 @@ use std::path::Path;
 
 use anyhow::{Context, Result, bail};
 use console::Style;
-use ngit::{client::send_events, git_events::generate_cover_letter_and_patch_events};
-use nostr::{
-    ToBech32,
-    nips::{nip10::Marker, nip19::Nip19Event},
+use ngit::{
+    client::{
+        get_all_proposal_patch_events_from_cache, get_proposals_and_revisions_from_cache,
+        send_events,
+    },
+    email::{get_email_sender, render_patch_emails, send_emails},
+    git::jj::get_change_id,
+    git_events::{
+        event_is_revision_root, event_to_cover_letter, format_event_for_dry_run,
+        generate_cover_letter_and_patch_events, tag_value,
+    },
+    output, theme,
 };
+use nostr::nips::nip10::Marker;
 use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use serde_json::json;
 
 use crate::{
     cli::{Cli, extract_signer_cli_arguments},
     cli_interactor::{
-        Interactor, InteractorPrompt, PromptConfirmParms, PromptInputParms, PromptMultiChoiceParms,
+        Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms, PromptInputParms,
+        PromptMultiChoiceParms,
     },
     client::{
-        Client, Connect, fetching_with_report, get_events_from_local_cache, get_repo_ref_from_cache,
+        Client, Connect, fetching_with_report, get_events_from_local_cache,
+        get_repo_ref_from_cache, get_user_forks_from_cache, resolve_repo_trust,
     },
     git::{Repo, RepoActions, identify_ahead_behind},
     git_events::{event_is_patch_set_root, event_tag_from_nip19_or_hex},
     login,
-    repo_ref::get_repo_coordinates_when_remote_unknown,
+    repo_ref::{RepoRef, get_repo_coordinates_when_remote_unknown, get_viewer_url},
 };
 
 #[derive(Debug, clap::Args)]
@@ -41,6 +53,14 @@ pub struct SubCommandArgs {
     #[clap(short, long)]
     /// optional cover letter description
     pub(crate) description: Option<String>,
+    #[clap(long, value_parser, action = clap::ArgAction::Append)]
+    /// also email the series to this address like `git send-email`. repeat
+    /// for more recipients. uses git config sendemail.smtpserver if set,
+    /// otherwise sendmail
+    pub(crate) email: Vec<String>,
+    #[arg(long, action)]
+    /// print the events that would be published instead of publishing them
+    pub(crate) dry_run: bool,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -57,7 +77,8 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
     let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
 
     if !no_fetch {
-        fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+        let fetch_report = fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+        resolve_repo_trust(&git_repo, &fetch_report, &repo_coordinates).await?;
     }
 
     let (root_proposal_id, mention_tags) =
@@ -72,13 +93,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
 
     let mut commits: Vec<Sha1Hash> = {
         if args.since_or_range.is_empty() {
-            let branch_name = git_repo.get_checked_out_branch_name()?;
-            let proposed_commits = if branch_name.eq(main_branch_name) {
-                vec![main_tip]
-            } else {
-                let (_, _, ahead, _) = identify_ahead_behind(&git_repo, &None, &None)?;
-                ahead
-            };
+            let proposed_commits = commits_to_propose(&git_repo, main_branch_name, main_tip)?;
             choose_commits(&git_repo, proposed_commits)?
         } else {
             git_repo
@@ -92,7 +107,7 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
     }
     println!("creating proposal from {} commits:", commits.len());
 
-    let dim = Style::new().color256(247);
+    let dim = theme::dim();
     for commit in &commits {
         println!(
             "{} {}",
@@ -189,6 +204,18 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
 
     let repo_ref = get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?;
 
+    let root_proposal_id = if root_proposal_id.is_none() {
+        find_proposal_revised_by_jj_changes(&git_repo, &repo_ref, &commits).await?
+    } else {
+        root_proposal_id
+    };
+
+    let email_sender = if args.email.is_empty() {
+        None
+    } else {
+        Some(get_email_sender(&git_repo)?)
+    };
+
     // oldest first
     commits.reverse();
 
@@ -203,6 +230,30 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
     )
     .await?;
 
+    if args.dry_run {
+        if output::json() {
+            output::emit(&json!({ "dry_run": true, "events": events }))?;
+        } else {
+            println!(
+                "dry run: would publish {} event{} to nostr relays:",
+                events.len(),
+                if events.len() == 1 { "" } else { "s" }
+            );
+            for event in &events {
+                for line in format_event_for_dry_run(event) {
+                    println!("  {line}");
+                }
+            }
+            if !args.email.is_empty() {
+                println!(
+                    "dry run: would email the series to {}",
+                    args.email.join(", ")
+                );
+            }
+        }
+        return Ok(());
+    }
+
     println!(
         "posting {} patch{} {} a covering letter...",
         if cover_letter_title_description.is_none() {
@@ -235,35 +286,132 @@ pub async fn launch(cli_args: &Cli, args: &SubCommandArgs, no_fetch: bool) -> Re
     )
     .await?;
 
+    if let Some(email_sender) = email_sender {
+        send_emails(
+            &git_repo,
+            &render_patch_emails(&events, Some(&email_sender), &args.email),
+            &args.email,
+        )?;
+        println!("emailed the series to {}", args.email.join(", "));
+    }
+
     if root_proposal_id.is_none() {
         if let Some(event) = events.first() {
-            let event_bech32 = if let Some(relay) = repo_ref.relays.first() {
-                Nip19Event::new(event.id, vec![relay.to_string()]).to_bech32()?
-            } else {
-                event.id.to_bech32()?
-            };
+            let viewer_url = get_viewer_url(&Some(&git_repo));
+            let (web, njump, uri) = repo_ref.event_links(&viewer_url, &event.id, event.kind)?;
             println!(
                 "{}",
                 dim.apply_to(format!(
-                    "view in gitworkshop.dev: https://gitworkshop.dev/repo/{}/proposal/{}",
-                    repo_ref.coordinate_with_hint().to_bech32()?,
-                    &event_bech32,
+                    "view in {}: {web}",
+                    viewer_url
+                        .trim_start_matches("https://")
+                        .trim_start_matches("http://")
+                        .trim_end_matches('/'),
                 ))
             );
             println!(
                 "{}",
-                dim.apply_to(format!(
-                    "view in another client:  https://njump.me/{}",
-                    &event_bech32,
-                ))
+                dim.apply_to(format!("view in another client:  {njump}"))
+            );
+            println!(
+                "{}",
+                dim.apply_to(format!("share with:              {uri}"))
             );
         }
     }
+    if !repo_ref.maintainers.contains(&user_ref.public_key) {
+        let forks: Vec<RepoRef> =
+            get_user_forks_from_cache(Some(git_repo_path), &repo_ref, &user_ref.public_key)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|fork| !fork.git_server.is_empty())
+                .collect();
+        if let Some(tip) = commits.last() {
+            let branch_name = events
+                .iter()
+                .find_map(|e| tag_value(e, "branch-name").ok())
+                .map_or_else(|| git_repo.get_checked_out_branch_name(), Ok)?;
+            offer_to_push_via_fork(&git_repo, &forks, &branch_name, tip)?;
+        }
+    }
+    if output::json() {
+        output::emit(&json!({
+            "proposal_id": root_proposal_id
+                .clone()
+                .or_else(|| events.first().map(|e| e.id.to_hex())),
+            "revision": root_proposal_id.is_some(),
+            "events": events.iter().map(|e| e.id.to_hex()).collect::<Vec<_>>(),
+            "emailed_to": args.email,
+        }))?;
+    }
     // TODO check if there is already a similarly named
     Ok(())
 }
 
-fn choose_commits(git_repo: &Repo, proposed_commits: Vec<Sha1Hash>) -> Result<Vec<Sha1Hash>> {
+/// contributors can't push to the repository's git servers so offer to push
+/// the proposal branch to the git server of one of their own forks, for
+/// anyone who would rather fetch it than apply the patches
+fn offer_to_push_via_fork(
+    git_repo: &impl RepoActions,
+    forks: &[RepoRef],
+    branch_name: &str,
+    tip: &Sha1Hash,
+) -> Result<()> {
+    if forks.is_empty() {
+        return Ok(());
+    }
+    let mut choices: Vec<String> = forks
+        .iter()
+        .map(|fork| format!("push via my fork {} ({})", fork.name, fork.git_server[0]))
+        .collect();
+    choices.push("don't push".to_string());
+    let selected = Interactor::default().choice(
+        PromptChoiceParms::default()
+            .with_prompt(format!(
+                "push proposal branch '{branch_name}' via your fork's git server?"
+            ))
+            .with_default(forks.len())
+            .with_choices(choices),
+    )?;
+    let Some(fork) = forks.get(selected) else {
+        return Ok(());
+    };
+    let git_server = &fork.git_server[0];
+    let status = std::process::Command::new("git")
+        .current_dir(git_repo.get_path()?)
+        .args([
+            "push",
+            git_server,
+            &format!("{tip}:refs/heads/{branch_name}"),
+        ])
+        .status()
+        .context("failed to run git push")?;
+    if !status.success() {
+        bail!("failed to push '{branch_name}' to {git_server}");
+    }
+    Ok(())
+}
+
+/// the commits preselected for the proposal: those on the checked out branch
+/// that aren't on main, or the tip of main if it is checked out
+fn commits_to_propose(
+    git_repo: &impl RepoActions,
+    main_branch_name: &str,
+    main_tip: Sha1Hash,
+) -> Result<Vec<Sha1Hash>> {
+    if git_repo.get_checked_out_branch_name()?.eq(main_branch_name) {
+        Ok(vec![main_tip])
+    } else {
+        let (_, _, ahead, _) = identify_ahead_behind(git_repo, &None, &None)?;
+        Ok(ahead)
+    }
+}
+
+fn choose_commits(
+    git_repo: &impl RepoActions,
+    proposed_commits: Vec<Sha1Hash>,
+) -> Result<Vec<Sha1Hash>> {
     let mut proposed_commits = if proposed_commits.len().gt(&10) {
         vec![]
     } else {
@@ -328,9 +476,12 @@ fn choose_commits(git_repo: &Repo, proposed_commits: Vec<Sha1Hash>) -> Result<Ve
     Ok(selected_commits)
 }
 
-fn summarise_commit_for_selection(git_repo: &Repo, commit: &Sha1Hash) -> Result<String> {
+fn summarise_commit_for_selection(
+    git_repo: &impl RepoActions,
+    commit: &Sha1Hash,
+) -> Result<String> {
     let references = git_repo.get_refs(commit)?;
-    let dim = Style::new().color256(247);
+    let dim = theme::dim();
     let prefix = format!("({})", git_repo.get_commit_author(commit)?[0],);
     let references_string = if references.is_empty() {
         String::new()
@@ -410,3 +561,100 @@ async fn get_root_proposal_id_and_mentions_from_in_reply_to(
 // - file relays
 // - find repo events
 // -
+
+/// a proposal with patches for the same jj changes as `commits`, if the user
+/// confirms the commits are a rebased revision of it
+async fn find_proposal_revised_by_jj_changes(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    commits: &[Sha1Hash],
+) -> Result<Option<String>> {
+    let change_ids: Vec<String> = commits
+        .iter()
+        .filter_map(|commit| get_change_id(git_repo, commit))
+        .map(|(change_id, _)| change_id)
+        .collect();
+    if change_ids.is_empty() {
+        return Ok(None);
+    }
+    let git_repo_path = git_repo.get_path()?;
+    for proposal in get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await?
+    .iter()
+    .filter(|e| !event_is_revision_root(e))
+    {
+        if get_all_proposal_patch_events_from_cache(git_repo_path, repo_ref, &proposal.id)
+            .await?
+            .iter()
+            .any(|patch| {
+                tag_value(patch, "change-id").is_ok_and(|change_id| change_ids.contains(&change_id))
+            })
+        {
+            let title = event_to_cover_letter(proposal)
+                .map_or_else(|_| proposal.id.to_hex(), |cover_letter| cover_letter.title);
+            if Interactor::default().confirm(
+                PromptConfirmParms::default()
+                    .with_prompt(format!(
+                        "jj changes match proposal \"{title}\". send as a new revision of it?"
+                    ))
+                    .with_default(true),
+            )? {
+                println!("creating proposal revision for: {title}");
+                return Ok(Some(proposal.id.to_hex()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(all(test, feature = "memory-repo"))]
+mod tests {
+    use ngit::git::memory::MemoryRepo;
+
+    use super::*;
+
+    mod commits_to_propose {
+        use super::*;
+
+        #[test]
+        fn tip_of_main_when_main_is_checked_out() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            git_repo.commit("main", "initial commit");
+            let tip = git_repo.commit("main", "second commit");
+            assert_eq!(commits_to_propose(&git_repo, "main", tip)?, vec![tip]);
+            Ok(())
+        }
+
+        #[test]
+        fn commits_ahead_of_main_newest_first() -> Result<()> {
+            let git_repo = MemoryRepo::default();
+            let main_tip = git_repo.commit("main", "initial commit");
+            git_repo.create_branch_at_commit("feature", &main_tip.to_string())?;
+            let first = git_repo.commit("feature", "first");
+            let second = git_repo.commit("feature", "second");
+            assert_eq!(
+                commits_to_propose(&git_repo, "main", main_tip)?,
+                vec![second, first]
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn summarise_commit_for_selection_lists_author_summary_refs_and_short_id() -> Result<()> {
+        let git_repo = MemoryRepo::default();
+        let tip = git_repo.commit("main", "add feature\n\nwith details");
+        git_repo.create_branch_at_commit("feature", &tip.to_string())?;
+        assert_eq!(
+            console::strip_ansi_codes(&summarise_commit_for_selection(&git_repo, &tip)?),
+            format!(
+                "(Joe Bloggs) add feature [feature] [main] {}",
+                &tip.to_string()[..7]
+            )
+        );
+        Ok(())
+    }
+}
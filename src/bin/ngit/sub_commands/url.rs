@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use ngit::{
+    git_events::{event_id_from_user_input, event_is_patch_set_root, get_event_root},
+    repo_ref::get_viewer_url,
+    short_numbers::{get_short_numbers, parse_short_number},
+};
+use nostr::{Filter, Kind};
+
+use crate::{
+    client::{Client, fetching_with_report, get_events_from_local_cache, get_repo_ref_from_cache},
+    git::{Repo, RepoActions},
+    repo_ref::get_repo_coordinates_when_remote_unknown,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct SubCommandArgs {
+    /// proposal or issue as a #number, nevent, note, hex id or link. defaults
+    /// to the repository
+    target: Option<String>,
+    #[clap(long)]
+    /// web client to link to. defaults to git config nostr.viewer-url or
+    /// https://gitworkshop.dev
+    viewer_url: Option<String>,
+}
+
+/// print shareable links for the repository or one of its proposals or
+/// issues
+pub async fn launch(args: &SubCommandArgs) -> Result<()> {
+    let git_repo = Repo::discover().context("failed to find a git repository")?;
+    let git_repo_path = git_repo.get_path()?;
+
+    let client = Client::default();
+
+    let repo_coordinates = get_repo_coordinates_when_remote_unknown(&git_repo, &client).await?;
+
+    let repo_ref = if let Ok(repo_ref) =
+        get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await
+    {
+        repo_ref
+    } else {
+        fetching_with_report(git_repo_path, &client, &repo_coordinates).await?;
+        get_repo_ref_from_cache(Some(git_repo_path), &repo_coordinates).await?
+    };
+
+    let viewer_url = args
+        .viewer_url
+        .clone()
+        .unwrap_or_else(|| get_viewer_url(&Some(&git_repo)));
+
+    let Some(target) = &args.target else {
+        let (web, uri) = repo_ref.links(&viewer_url)?;
+        println!("{web}");
+        println!("{uri}");
+        return Ok(());
+    };
+
+    let mut event_id = if let Some(number) = parse_short_number(target) {
+        get_short_numbers(git_repo_path, &repo_ref)
+            .await?
+            .id_of(number)
+            .context(format!("there is no #{number}. `ngit numbers` lists them"))?
+    } else {
+        event_id_from_user_input(target)?.0
+    };
+    let mut kind = Kind::GitPatch;
+    if let Some(event) =
+        get_events_from_local_cache(git_repo_path, vec![Filter::default().id(event_id)])
+            .await?
+            .first()
+    {
+        kind = event.kind;
+        if event.kind.eq(&Kind::GitPatch) && !event_is_patch_set_root(event) {
+            if let Ok(root) = get_event_root(event) {
+                event_id = root;
+            }
+        }
+    }
+    let (web, njump, uri) = repo_ref.event_links(&viewer_url, &event_id, kind)?;
+    println!("{web}");
+    println!("{njump}");
+    println!("{uri}");
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use nostr::hashes::{Hash, sha256::Hash as Sha256Hash};
+use nostr_sdk::{EventBuilder, JsonUtil, Kind, NostrSigner, Tag, Timestamp};
+
+use crate::{client::sign_event, download::download_resumable};
+
+/// BUD-01 authorization event kind
+pub static BLOSSOM_AUTH_KIND: Kind = Kind::Custom(24242);
+
+pub fn sha256_hex(blob: &[u8]) -> String {
+    Sha256Hash::hash(blob).to_string()
+}
+
+async fn generate_authorization_header(
+    signer: &Arc<dyn NostrSigner>,
+    verb: &str,
+    hash: &str,
+) -> Result<String> {
+    let event = sign_event(
+        EventBuilder::new(BLOSSOM_AUTH_KIND, format!("{verb} git packfile")).tags(vec![
+            Tag::hashtag(verb),
+            Tag::custom(
+                nostr::TagKind::Custom(std::borrow::Cow::Borrowed("x")),
+                vec![hash.to_string()],
+            ),
+            Tag::expiration(Timestamp::now() + Duration::from_secs(5 * 60)),
+        ]),
+        signer,
+    )
+    .await
+    .context("failed to sign blossom authorization event")?;
+    Ok(format!("Nostr {}", STANDARD.encode(event.as_json())))
+}
+
+/// upload blob to a blossom server (BUD-02) returning its sha256 hash
+pub async fn upload_blob(
+    server: &str,
+    blob: Vec<u8>,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<String> {
+    let hash = sha256_hex(&blob);
+    let authorization = generate_authorization_header(signer, "upload", &hash).await?;
+    let response = reqwest::Client::new()
+        .put(format!("{}/upload", server.trim_end_matches('/')))
+        .header(reqwest::header::AUTHORIZATION, authorization)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(blob)
+        .send()
+        .await
+        .context(format!("failed to connect to blossom server {server}"))?;
+    if !response.status().is_success() {
+        bail!(
+            "blossom server {server} rejected upload with status {}",
+            response.status()
+        );
+    }
+    Ok(hash)
+}
+
+/// download blob from a blossom server and check it matches `hash`. partial
+/// data is kept in `partial_dir` so interrupted downloads can resume.
+pub async fn download_blob(server: &str, hash: &str, partial_dir: &Path) -> Result<Vec<u8>> {
+    let blob = download_resumable(
+        &format!("{}/{hash}", server.trim_end_matches('/')),
+        partial_dir,
+    )
+    .await
+    .context(format!(
+        "failed to download {hash} from blossom server {server}"
+    ))?;
+    if sha256_hex(&blob) != hash {
+        bail!("blob downloaded from blossom server {server} does not match hash {hash}");
+    }
+    Ok(blob)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_hex_of_empty_blob() {
+        assert_eq!(
+            sha256_hex(&[]),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
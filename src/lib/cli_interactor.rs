@@ -1,11 +1,46 @@
-use anyhow::{Context, Result};
-use dialoguer::{Confirm, Input, Password, theme::ColorfulTheme};
-use indicatif::TermLike;
+//! terminal prompts. without the `cli` feature there is no terminal ui so
+//! every prompt is answered as if `--non-interactive` was passed
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "cli")]
+use anyhow::Context;
+use anyhow::{Result, bail};
+#[cfg(feature = "cli")]
+use dialoguer::{
+    Confirm, Input, Password,
+    theme::{ColorfulTheme, Theme},
+};
 #[cfg(test)]
 use mockall::*;
 
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// answer every prompt with its default, or fail if it has none, as
+/// `--non-interactive` does
+pub fn set_non_interactive() {
+    NON_INTERACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// `--non-interactive`, `--yes` or `NGIT_NONINTERACTIVE` set to anything but
+/// empty, `0` or `false`. always without the `cli` feature
+pub fn is_non_interactive() -> bool {
+    !cfg!(feature = "cli")
+        || NON_INTERACTIVE.load(Ordering::Relaxed)
+        || std::env::var("NGIT_NONINTERACTIVE")
+            .is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"))
+}
+
+fn bail_unanswerable<T>(prompt: &str) -> Result<T> {
+    bail!(
+        "\"{}\" needs an answer but ngit is running non-interactively. pass the value as an argument or run without --non-interactive / NGIT_NONINTERACTIVE",
+        prompt.trim()
+    )
+}
+
 #[derive(Default)]
 pub struct Interactor {
+    #[cfg(feature = "cli")]
     theme: ColorfulTheme,
 }
 
@@ -15,10 +50,66 @@ pub trait InteractorPrompt {
     fn password(&self, parms: PromptPasswordParms) -> Result<String>;
     fn confirm(&self, params: PromptConfirmParms) -> Result<bool>;
     fn choice(&self, params: PromptChoiceParms) -> Result<usize>;
+    /// like `choice` but first asks for text to narrow down the choices with
+    fn fuzzy_choice(&self, params: PromptChoiceParms) -> Result<usize>;
     fn multi_choice(&self, params: PromptMultiChoiceParms) -> Result<Vec<usize>>;
 }
+
+/// the answers given when running non-interactively
+fn default_input(parms: PromptInputParms) -> Result<String> {
+    if !parms.default.is_empty() || parms.optional {
+        Ok(parms.default)
+    } else {
+        bail_unanswerable(&parms.prompt)
+    }
+}
+
+fn default_choice(parms: &PromptChoiceParms) -> Result<usize> {
+    match parms.default {
+        Some(default) if default < parms.choices.len() => Ok(default),
+        _ => bail_unanswerable(&parms.prompt),
+    }
+}
+
+fn default_multi_choice(parms: PromptMultiChoiceParms) -> Result<Vec<usize>> {
+    match parms.defaults {
+        Some(defaults) => Ok(defaults
+            .iter()
+            .enumerate()
+            .filter_map(|(i, selected)| selected.then_some(i))
+            .collect()),
+        None => bail_unanswerable(&parms.prompt),
+    }
+}
+
+#[cfg(not(feature = "cli"))]
 impl InteractorPrompt for Interactor {
     fn input(&self, parms: PromptInputParms) -> Result<String> {
+        default_input(parms)
+    }
+    fn password(&self, parms: PromptPasswordParms) -> Result<String> {
+        bail_unanswerable(&parms.prompt)
+    }
+    fn confirm(&self, params: PromptConfirmParms) -> Result<bool> {
+        Ok(params.default)
+    }
+    fn choice(&self, parms: PromptChoiceParms) -> Result<usize> {
+        default_choice(&parms)
+    }
+    fn fuzzy_choice(&self, parms: PromptChoiceParms) -> Result<usize> {
+        default_choice(&parms)
+    }
+    fn multi_choice(&self, parms: PromptMultiChoiceParms) -> Result<Vec<usize>> {
+        default_multi_choice(parms)
+    }
+}
+
+#[cfg(feature = "cli")]
+impl InteractorPrompt for Interactor {
+    fn input(&self, parms: PromptInputParms) -> Result<String> {
+        if is_non_interactive() {
+            return default_input(parms);
+        }
         let mut input = Input::with_theme(&self.theme);
         input.with_prompt(parms.prompt).allow_empty(parms.optional);
         if !parms.default.is_empty() {
@@ -28,6 +119,9 @@ impl InteractorPrompt for Interactor {
         Ok(input.interact_text()?)
     }
     fn password(&self, parms: PromptPasswordParms) -> Result<String> {
+        if is_non_interactive() {
+            return bail_unanswerable(&parms.prompt);
+        }
         let mut p = Password::with_theme(&self.theme);
         p.with_prompt(parms.prompt);
         p.report(parms.report);
@@ -38,6 +132,9 @@ impl InteractorPrompt for Interactor {
         Ok(pass)
     }
     fn confirm(&self, params: PromptConfirmParms) -> Result<bool> {
+        if is_non_interactive() {
+            return Ok(params.default);
+        }
         let confirm: bool = Confirm::with_theme(&self.theme)
             .with_prompt(params.prompt)
             .default(params.default)
@@ -45,19 +142,64 @@ impl InteractorPrompt for Interactor {
         Ok(confirm)
     }
     fn choice(&self, parms: PromptChoiceParms) -> Result<usize> {
+        if is_non_interactive() {
+            return default_choice(&parms);
+        }
         let mut choice = dialoguer::Select::with_theme(&self.theme);
         choice
             .with_prompt(parms.prompt)
             .report(parms.report)
             .items(&parms.choices);
         if let Some(default) = parms.default {
-            if std::env::var("NGITTEST").is_err() {
+            if !crate::settings::settings().test_mode {
                 choice.default(default);
             }
         }
         choice.interact().context("failed to get choice")
     }
+    fn fuzzy_choice(&self, parms: PromptChoiceParms) -> Result<usize> {
+        if is_non_interactive() {
+            return self.choice(parms);
+        }
+        loop {
+            let query = self.input(
+                PromptInputParms::default()
+                    .with_prompt(format!("{} (type to filter)", parms.prompt))
+                    .optional()
+                    .dont_report(),
+            )?;
+            let mut matches: Vec<(i64, usize)> = parms
+                .choices
+                .iter()
+                .enumerate()
+                .filter_map(|(i, choice)| fuzzy_score(&query, choice).map(|score| (score, i)))
+                .collect();
+            // stable sort keeps the original order for equal scores
+            matches.sort_by_key(|(score, _)| -score);
+            if matches.is_empty() {
+                eprintln!("no matches for \"{query}\"");
+                continue;
+            }
+            let mut choices: Vec<String> = matches
+                .iter()
+                .map(|(_, i)| parms.choices[*i].clone())
+                .collect();
+            choices.push("search again".to_string());
+            let selected = self.choice(
+                PromptChoiceParms::default()
+                    .with_prompt(parms.prompt.clone())
+                    .with_choices(choices)
+                    .with_default(0),
+            )?;
+            if let Some((_, i)) = matches.get(selected) {
+                return Ok(*i);
+            }
+        }
+    }
     fn multi_choice(&self, parms: PromptMultiChoiceParms) -> Result<Vec<usize>> {
+        if is_non_interactive() {
+            return default_multi_choice(parms);
+        }
         // the colorful theme is not very clear so falling back to default
         let mut choice = dialoguer::MultiSelect::default();
         choice
@@ -262,7 +404,7 @@ impl Printer {
     pub fn clear_all(&mut self) {
         let term = console::Term::stderr();
         let _ = term.clear_last_lines(count_lines_per_msg_vec(
-            term.width(),
+            term.size().1,
             &self.printed_lines,
             0,
         ));
@@ -270,6 +412,53 @@ impl Printer {
     }
 }
 
+/// `label` answered with `value`, styled like the answer to a prompt
+pub fn format_prompt_selection(label: &str, value: &str) -> String {
+    #[cfg(feature = "cli")]
+    {
+        let mut s = String::new();
+        let _ = ColorfulTheme::default().format_input_prompt_selection(&mut s, label, value);
+        s
+    }
+    #[cfg(not(feature = "cli"))]
+    format!("{label}: {value}")
+}
+
+/// styled like a prompt's validation error
+pub fn format_prompt_error(message: &str) -> String {
+    #[cfg(feature = "cli")]
+    {
+        let mut s = String::new();
+        let _ = ColorfulTheme::default().format_error(&mut s, message);
+        s
+    }
+    #[cfg(not(feature = "cli"))]
+    format!("error: {message}")
+}
+
+/// characters of `query` must appear in `candidate` in order, ignoring case.
+/// consecutive and word-start matches score higher. empty queries match
+/// everything.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = candidate[position..].iter().position(|c| *c == q)? + position;
+        score += 1;
+        if previous_match.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
 pub fn count_lines_per_msg(width: u16, msg: &str, prefix_len: usize) -> usize {
     if width == 0 {
         return 1;
@@ -283,3 +472,52 @@ pub fn count_lines_per_msg_vec(width: u16, msgs: &[String], prefix_len: usize) -
         .map(|msg| count_lines_per_msg(width, msg, prefix_len))
         .sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_matches_characters_in_order() {
+        assert!(fuzzy_score("ngcli", "ngit-cli").is_some());
+        assert!(fuzzy_score("NGIT", "ngit-cli").is_some());
+        assert!(fuzzy_score("ilc", "ngit-cli").is_none());
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_consecutive_and_word_start_matches() {
+        assert!(fuzzy_score("cli", "ngit-cli").unwrap() > fuzzy_score("cli", "coolish").unwrap());
+    }
+
+    #[test]
+    fn non_interactive_prompts_take_defaults_or_fail() {
+        set_non_interactive();
+        let interactor = Interactor::default();
+        assert!(
+            interactor
+                .confirm(PromptConfirmParms::default().with_default(true))
+                .unwrap()
+        );
+        assert_eq!(
+            interactor
+                .input(PromptInputParms::default().with_default("main"))
+                .unwrap(),
+            "main"
+        );
+        assert_eq!(
+            interactor
+                .choice(
+                    PromptChoiceParms::default()
+                        .with_choices(vec!["a".to_string(), "b".to_string()])
+                        .with_default(1)
+                )
+                .unwrap(),
+            1
+        );
+        let error = interactor
+            .input(PromptInputParms::default().with_prompt("repo name"))
+            .unwrap_err();
+        assert!(error.to_string().contains("\"repo name\" needs an answer"));
+    }
+}
@@ -1,51 +1,57 @@
-// have you considered
-
-// TO USE ASYNC
-
-// in traits (required for mocking unit tests)
-// https://rust-lang.github.io/async-book/07_workarounds/05_async_in_traits.html
-// https://github.com/dtolnay/async-trait
-// see https://blog.rust-lang.org/inside-rust/2022/11/17/async-fn-in-trait-nightly.html
-// I think we can use the async-trait crate and switch to the native feature
-// which is currently in nightly. alternatively we can use nightly as it looks
-// certain that the implementation is going to make it to stable but we don't
-// want to inadvertlty use other features of nightly that might be removed.
 use std::{
     collections::{HashMap, HashSet},
     fmt::{Display, Write},
     fs::create_dir_all,
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
 
 use anyhow::{Context, Result, bail};
-use async_trait::async_trait;
-use console::Style;
 use futures::{
-    future::join_all,
+    future::{join_all, try_join_all},
     stream::{self, StreamExt},
 };
-use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
-#[cfg(test)]
-use mockall::*;
-use nostr::{Event, nips::nip01::Coordinate, signer::SignerBackend};
+use nostr::{Event, UnsignedEvent, nips::nip01::Coordinate, signer::SignerBackend};
 use nostr_database::NostrEventsDatabase;
 use nostr_lmdb::NostrLMDB;
 use nostr_sdk::{
     EventBuilder, EventId, Kind, NostrSigner, Options, PublicKey, RelayUrl, SingleLetterTag,
-    Timestamp, prelude::RelayLimits,
+    Timestamp, ToBech32, prelude::RelayLimits,
 };
 
 use crate::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptConfirmParms},
+    delegation::build_with_delegation,
+    error::{CacheError, RelayError},
     get_dirs,
-    git::{Repo, RepoActions},
+    git::{Repo, RepoActions, get_git_config_item},
     git_events::{
-        event_is_cover_letter, event_is_patch_set_root, event_is_revision_root, status_kinds,
+        event_is_cover_letter, event_is_patch_set_root, event_is_revision_root, is_future_dated,
+        status_kinds, without_future_dated,
+    },
+    hooks::run_event_hooks,
+    interrupt,
+    login::{
+        get_likely_logged_in_user,
+        user::{get_author_display_name, get_user_ref_from_cache},
+    },
+    maintainer_trust::{
+        MaintainerTrust, get_maintainer_trust, reject_maintainer, trust_maintainer,
+    },
+    notifications::{desktop_notify, fetch_notifications, notifications_enabled},
+    org::{Org, get_filter_org},
+    output,
+    progress::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle},
+    recent_repos,
+    repo_ref::{
+        RepoRef, choose_announcement_if_conflicting, get_announcement_source, get_lineage_warnings,
     },
-    login::{get_likely_logged_in_user, user::get_user_ref_from_cache},
-    repo_ref::RepoRef,
     repo_state::RepoState,
+    self_update::update_hint,
+    settings::settings,
+    signing_policy::{confirm_signing, confirm_signing_enabled},
+    theme,
 };
 
 #[allow(clippy::struct_field_names)]
@@ -57,8 +63,8 @@ pub struct Client {
     fallback_signer_relays: Vec<String>,
 }
 
-#[cfg_attr(test, automock)]
-#[async_trait]
+// only used with concrete clients so the futures needn't be boxed or `Send`
+#[allow(async_fn_in_trait)]
 pub trait Connect {
     fn default() -> Self;
     fn new(opts: Params) -> Self;
@@ -100,56 +106,17 @@ pub trait Connect {
     ) -> Result<FetchReport>;
 }
 
-#[async_trait]
 impl Connect for Client {
     fn default() -> Self {
-        let fallback_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec![
-                "ws://localhost:8051".to_string(),
-                "ws://localhost:8052".to_string(),
-            ]
-        } else {
-            vec![
-                "wss://relay.damus.io".to_string(), /* free, good reliability, have been known
-                                                     * to delete all messages */
-                "wss://nos.lol".to_string(),
-                "wss://relay.nostr.band".to_string(),
-            ]
-        };
-
-        let more_fallback_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec![
-                "ws://localhost:8055".to_string(),
-                "ws://localhost:8056".to_string(),
-            ]
-        } else {
-            vec![
-                "wss://purplerelay.com".to_string(), // free but reliability not tested
-                "wss://purplepages.es".to_string(),  // for profile events but unreliable
-                "wss://relayable.org".to_string(),   // free but not always reliable
-            ]
-        };
-
-        let blaster_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec!["ws://localhost:8057".to_string()]
-        } else {
-            vec![]
-        };
-
-        let fallback_signer_relays: Vec<String> = if std::env::var("NGITTEST").is_ok() {
-            vec!["ws://localhost:8051".to_string()]
-        } else {
-            vec!["wss://relay.nsec.app".to_string()]
-        };
-
+        let settings = settings();
         Client {
             client: nostr_sdk::ClientBuilder::new()
                 .opts(Options::new().relay_limits(RelayLimits::disable()))
                 .build(),
-            fallback_relays,
-            more_fallback_relays,
-            blaster_relays,
-            fallback_signer_relays,
+            fallback_relays: settings.fallback_relays.clone(),
+            more_fallback_relays: settings.more_fallback_relays.clone(),
+            blaster_relays: settings.blaster_relays.clone(),
+            fallback_signer_relays: settings.fallback_signer_relays.clone(),
         }
     }
     fn new(opts: Params) -> Self {
@@ -173,6 +140,7 @@ impl Connect for Client {
     }
 
     async fn connect(&self, relay_url: &RelayUrl) -> Result<()> {
+        tracing::debug!(relay = %relay_url, "connecting");
         self.client
             .add_relay(relay_url)
             .await
@@ -188,7 +156,7 @@ impl Connect for Client {
         }
 
         if !relay.is_connected() {
-            bail!("connection timeout");
+            bail!(RelayError::ConnectionTimeout);
         }
         Ok(())
     }
@@ -246,7 +214,7 @@ impl Connect for Client {
             .get_events_per_relay(
                 relays.iter().map(|r| RelayUrl::parse(r).unwrap()).collect(),
                 filters,
-                MultiProgress::new(),
+                new_progress_reporter(),
             )
             .await?;
         Ok(get_dedup_events(relay_results))
@@ -275,7 +243,7 @@ impl Connect for Client {
             .filter(|r| !r.as_str().contains("nostr.mutinywallet.com"))
             .map(|r| (relays_map.get(r).unwrap(), filters.clone()))
             .map(|(relay, filters)| async {
-                let pb = if std::env::var("NGITTEST").is_err() {
+                let pb = if !settings().test_mode {
                     let pb = progress_reporter.add(
                         ProgressBar::new(1)
                             .with_prefix(format!("{: <11}{}", "connecting", relay.url()))
@@ -293,12 +261,12 @@ impl Connect for Client {
                             pb.set_style(pb_after_style(false));
                             pb.set_prefix(format!("{: <11}{}", "error", relay.url()));
                             pb.finish_with_message(
-                                console::style(
-                                    error.to_string().replace("relay pool error:", "error:"),
-                                )
-                                .for_stderr()
-                                .red()
-                                .to_string(),
+                                theme::error()
+                                    .for_stderr()
+                                    .apply_to(
+                                        error.to_string().replace("relay pool error:", "error:"),
+                                    )
+                                    .to_string(),
                             );
                         }
                         Err(error)
@@ -346,7 +314,7 @@ impl Connect for Client {
         )
         .await?;
 
-        let progress_reporter = MultiProgress::new();
+        let progress_reporter = new_progress_reporter();
 
         let mut processed_relays = HashSet::new();
 
@@ -377,7 +345,7 @@ impl Connect for Client {
                     .context("failed to add relay")?;
             }
 
-            let dim = Style::new().color256(247);
+            let dim = theme::dim();
 
             let futures: Vec<_> = relays
                 .iter()
@@ -387,6 +355,7 @@ impl Connect for Client {
                         FetchRequest {
                             selected_relay: Some(r.to_owned()),
                             repo_coordinates_without_relays: vec![],
+                            org: None,
                             proposals: HashSet::new(),
                             missing_contributor_profiles: request
                                 .missing_contributor_profiles
@@ -416,7 +385,7 @@ impl Connect for Client {
                         .clone()
                         .context("fetch_all_from_relay called without a relay")?;
 
-                    let pb = if std::env::var("NGITTEST").is_err() {
+                    let pb = if !settings().test_mode {
                         let pb = progress_reporter.add(
                             ProgressBar::new(1)
                                 .with_prefix(
@@ -444,12 +413,14 @@ impl Connect for Client {
                                         .to_string(),
                                 );
                                 pb.finish_with_message(
-                                    console::style(
-                                        error.to_string().replace("relay pool error:", "error:"),
-                                    )
-                                    .for_stderr()
-                                    .red()
-                                    .to_string(),
+                                    theme::error()
+                                        .for_stderr()
+                                        .apply_to(
+                                            error
+                                                .to_string()
+                                                .replace("relay pool error:", "error:"),
+                                        )
+                                        .to_string(),
                                 );
                             }
                             Err(error)
@@ -498,6 +469,7 @@ impl Connect for Client {
         Ok((relay_reports, progress_reporter))
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(relay = ?request.selected_relay))]
     async fn fetch_all_from_relay<'a>(
         &self,
         git_repo_path: Option<&'a Path>,
@@ -522,6 +494,8 @@ impl Connect for Client {
             .collect();
 
         let mut report = FetchReport::default();
+        // the organization's maintainer list only needs requesting once
+        let mut org_filter = request.org.as_ref().map(get_filter_org);
 
         let relay_url = request
             .selected_relay
@@ -532,11 +506,19 @@ impl Connect for Client {
 
         self.connect(&relay_url).await?;
 
-        let dim = Style::new().color256(247);
+        let dim = theme::dim();
 
         loop {
-            let filters =
+            if interrupt::is_interrupted() {
+                bail!("interrupted");
+            }
+            let mut filters =
                 get_fetch_filters(&fresh_coordinates, &fresh_proposal_roots, &fresh_profiles);
+            filters.extend(org_filter.take());
+            tracing::debug!(
+                filters = %serde_json::to_string(&filters).unwrap_or_default(),
+                "requesting"
+            );
 
             if let Some(pb) = &pb {
                 pb.set_prefix(
@@ -558,25 +540,95 @@ impl Connect for Client {
             fresh_profiles = HashSet::new();
 
             let relay = self.client.relay(&relay_url).await?;
-            let events: Vec<nostr::Event> = get_events_of(&relay, filters.clone(), &None)
-                .await?
-                .iter()
-                // don't process events that don't match filters
-                .filter(|e| filters.iter().any(|f| f.match_event(e)))
-                .cloned()
-                .collect();
-            // TODO: try reconcile
-
-            process_fetched_events(
-                events,
-                &request,
-                git_repo_path,
-                &mut fresh_coordinates,
-                &mut fresh_proposal_roots,
-                &mut fresh_profiles,
-                &mut report,
-            )
-            .await?;
+            let mut seen: HashSet<EventId> = HashSet::new();
+            let mut replies = vec![];
+            // state is checked against the maintainers once the announcements
+            // that may list them have been processed, whatever order the
+            // relay sends them in
+            let mut deferred_state_events = vec![];
+            // the stream ends on EOSE or timeout without saying which, so a
+            // stream that lasted the whole timeout is assumed to be cut short
+            // and is requested again with a longer one. events already seen
+            // are skipped
+            for (attempt, timeout) in [GET_EVENTS_TIMEOUT, GET_EVENTS_RETRY_TIMEOUT]
+                .into_iter()
+                .enumerate()
+            {
+                let timeout = std::time::Duration::from_secs(timeout);
+                let started = std::time::Instant::now();
+                let mut events = stream_events_of(&relay, filters.clone(), timeout).await?;
+                // TODO: try reconcile
+                while let Some(event) = events.next().await {
+                    if interrupt::is_interrupted() {
+                        bail!("interrupted");
+                    }
+                    // don't process events that don't match filters
+                    if !filters.iter().any(|f| f.match_event(&event)) || !seen.insert(event.id) {
+                        continue;
+                    }
+                    if event.kind.eq(&STATE_KIND) {
+                        deferred_state_events.push(event);
+                        continue;
+                    }
+                    if let Err(error) = check_fetched_event(&event, &request, &report) {
+                        tracing::debug!(id = %event.id, %error, "ignored event");
+                        continue;
+                    }
+                    process_fetched_event(
+                        &event,
+                        &request,
+                        git_repo_path,
+                        &mut fresh_coordinates,
+                        &mut fresh_proposal_roots,
+                        &mut fresh_profiles,
+                        &mut report,
+                    )
+                    .await?;
+                    if let Some(reply) = FetchedReply::from_event(&event, &request) {
+                        replies.push(reply);
+                    }
+                    if let Some(pb) = &pb {
+                        pb.set_prefix(
+                            dim.apply_to(format!(
+                                "{: <relay_column_width$} fetching... {} events{}",
+                                &relay_url,
+                                seen.len(),
+                                if report.to_string().is_empty() {
+                                    String::new()
+                                } else {
+                                    format!(" updates: {report}")
+                                },
+                            ))
+                            .to_string(),
+                        );
+                    }
+                }
+                if started.elapsed() < timeout {
+                    break;
+                }
+                tracing::debug!(attempt, "timed out before EOSE");
+                if attempt > 0 {
+                    report.timed_out = true;
+                }
+            }
+            for event in deferred_state_events {
+                if let Err(error) = check_fetched_event(&event, &request, &report) {
+                    tracing::debug!(id = %event.id, %error, "ignored event");
+                    continue;
+                }
+                process_fetched_event(
+                    &event,
+                    &request,
+                    git_repo_path,
+                    &mut fresh_coordinates,
+                    &mut fresh_proposal_roots,
+                    &mut fresh_profiles,
+                    &mut report,
+                )
+                .await?;
+            }
+            tracing::debug!(filters = filters.len(), events = seen.len(), "fetched");
+            classify_fetched_replies(replies, &mut report);
 
             if fresh_coordinates.is_empty()
                 && fresh_proposal_roots.is_empty()
@@ -591,30 +643,148 @@ impl Connect for Client {
                 dim.apply_to(format!(
                     "{: <relay_column_width$} {}",
                     relay_url,
-                    if report.to_string().is_empty() {
-                        "no new events".to_string()
-                    } else {
-                        format!("new events: {report}")
+                    match (report.to_string().is_empty(), report.timed_out) {
+                        (true, false) => "no new events".to_string(),
+                        (true, true) => "timed out".to_string(),
+                        (false, false) => format!("new events: {report}"),
+                        (false, true) => format!("timed out after new events: {report}"),
                     },
                 ))
                 .to_string(),
             );
             pb.finish_with_message("");
         }
+        tracing::debug!(%report, "fetch complete");
         Ok(report)
     }
 }
 
+/// client for unit tests answering `get_events` from `events` and recording
+/// what is sent rather than connecting to relays
+#[cfg(test)]
+pub struct MockConnect {
+    pub fallback_relays: Vec<String>,
+    pub events: Vec<nostr::Event>,
+    pub sent: std::sync::Mutex<Vec<(String, nostr::Event)>>,
+}
+
+#[cfg(test)]
+impl Connect for MockConnect {
+    fn default() -> Self {
+        Self {
+            fallback_relays: vec![],
+            events: vec![],
+            sent: std::sync::Mutex::new(vec![]),
+        }
+    }
+
+    fn new(opts: Params) -> Self {
+        Self {
+            fallback_relays: opts.fallback_relays,
+            ..<Self as Connect>::default()
+        }
+    }
+
+    async fn set_signer(&mut self, _signer: Arc<dyn NostrSigner>) {}
+
+    async fn connect(&self, _relay_url: &RelayUrl) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_fallback_relays(&self) -> &Vec<String> {
+        &self.fallback_relays
+    }
+
+    fn get_more_fallback_relays(&self) -> &Vec<String> {
+        &self.fallback_relays
+    }
+
+    fn get_blaster_relays(&self) -> &Vec<String> {
+        &self.fallback_relays
+    }
+
+    fn get_fallback_signer_relays(&self) -> &Vec<String> {
+        &self.fallback_relays
+    }
+
+    async fn send_event_to<'a>(
+        &self,
+        _git_repo_path: Option<&'a Path>,
+        url: &str,
+        event: nostr::event::Event,
+    ) -> Result<nostr::EventId> {
+        let id = event.id;
+        self.sent
+            .lock()
+            .map_err(|_| anyhow::anyhow!("mock client sent events lock poisoned"))?
+            .push((url.to_string(), event));
+        Ok(id)
+    }
+
+    async fn get_events(
+        &self,
+        _relays: Vec<String>,
+        filters: Vec<nostr::Filter>,
+    ) -> Result<Vec<nostr::Event>> {
+        Ok(self
+            .events
+            .iter()
+            .filter(|e| filters.iter().any(|f| f.match_event(e)))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_events_per_relay(
+        &self,
+        relays: Vec<RelayUrl>,
+        filters: Vec<nostr::Filter>,
+        progress_reporter: MultiProgress,
+    ) -> Result<(Vec<Result<Vec<nostr::Event>>>, MultiProgress)> {
+        let mut results = vec![];
+        for _ in &relays {
+            results.push(self.get_events(vec![], filters.clone()).await);
+        }
+        Ok((results, progress_reporter))
+    }
+
+    async fn fetch_all<'a>(
+        &self,
+        _git_repo_path: Option<&'a Path>,
+        _repo_coordinates: Option<&'a Coordinate>,
+        _user_profiles: &HashSet<PublicKey>,
+    ) -> Result<(Vec<Result<FetchReport>>, MultiProgress)> {
+        Ok((vec![], MultiProgress::new()))
+    }
+
+    async fn fetch_all_from_relay<'a>(
+        &self,
+        _git_repo_path: Option<&'a Path>,
+        _request: FetchRequest,
+        _pb: &Option<ProgressBar>,
+    ) -> Result<FetchReport> {
+        Ok(FetchReport::default())
+    }
+}
+
 static CONNECTION_TIMEOUT: u64 = 3;
 static GET_EVENTS_TIMEOUT: u64 = 7;
+/// used when a relay didn't reach EOSE within `GET_EVENTS_TIMEOUT`
+static GET_EVENTS_RETRY_TIMEOUT: u64 = 20;
 
-async fn get_events_of(
-    relay: &nostr_sdk::Relay,
-    filters: Vec<nostr::Filter>,
-    pb: &Option<ProgressBar>,
-) -> Result<Vec<Event>> {
-    // relay.reconcile(filter, opts).await?;
+/// hidden with `-q`
+fn new_progress_reporter() -> MultiProgress {
+    if output::quiet() {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
 
+async fn connect_relay(relay: &nostr_sdk::Relay) -> Result<()> {
     if !relay.is_connected() {
         #[allow(clippy::large_futures)]
         relay
@@ -623,8 +793,20 @@ async fn get_events_of(
     }
 
     if !relay.is_connected() {
-        bail!("connection timeout");
-    } else if let Some(pb) = pb {
+        bail!(RelayError::ConnectionTimeout);
+    }
+    Ok(())
+}
+
+async fn get_events_of(
+    relay: &nostr_sdk::Relay,
+    filters: Vec<nostr::Filter>,
+    pb: &Option<ProgressBar>,
+) -> Result<Vec<Event>> {
+    // relay.reconcile(filter, opts).await?;
+
+    connect_relay(relay).await?;
+    if let Some(pb) = pb {
         pb.set_prefix(format!("connected  {}", relay.url()));
     }
     let events = relay
@@ -639,6 +821,19 @@ async fn get_events_of(
     Ok(events)
 }
 
+/// events as the relay sends them, so they can be processed without holding
+/// the whole response in memory
+async fn stream_events_of(
+    relay: &nostr_sdk::Relay,
+    filters: Vec<nostr::Filter>,
+    timeout: std::time::Duration,
+) -> Result<impl futures::Stream<Item = Event>> {
+    connect_relay(relay).await?;
+    Ok(relay
+        .stream_events(filters, timeout, nostr_sdk::FilterOptions::ExitOnEOSE)
+        .await?)
+}
+
 #[derive(Default)]
 pub struct Params {
     pub keys: Option<nostr::Keys>,
@@ -648,11 +843,35 @@ pub struct Params {
     pub fallback_signer_relays: Vec<String>,
 }
 
-fn get_dedup_events(relay_results: Vec<Result<Vec<nostr::Event>>>) -> Vec<Event> {
+/// events from every relay without duplicates, keeping only the newest
+/// version of replaceable and addressable events
+pub fn get_dedup_events(relay_results: Vec<Result<Vec<nostr::Event>>>) -> Vec<Event> {
     let mut dedup_events: Vec<Event> = vec![];
+    let mut ids: HashSet<EventId> = HashSet::new();
+    let mut replaceable: HashMap<(Kind, PublicKey, String), usize> = HashMap::new();
     for events in relay_results.into_iter().flatten() {
         for event in events {
-            if !dedup_events.iter().any(|e| event.id.eq(&e.id)) {
+            if !ids.insert(event.id) {
+                continue;
+            }
+            if !event.kind.is_replaceable() && !event.kind.is_addressable() {
+                dedup_events.push(event);
+                continue;
+            }
+            let key = (
+                event.kind,
+                event.pubkey,
+                event.tags.identifier().unwrap_or_default().to_string(),
+            );
+            if let Some(index) = replaceable.get(&key) {
+                let existing = &dedup_events[*index];
+                if (!is_future_dated(&event), event.created_at, event.id)
+                    > (!is_future_dated(existing), existing.created_at, existing.id)
+                {
+                    dedup_events[*index] = event;
+                }
+            } else {
+                replaceable.insert(key, dedup_events.len());
                 dedup_events.push(event);
             }
         }
@@ -664,23 +883,71 @@ pub async fn sign_event(
     event_builder: EventBuilder,
     signer: &Arc<dyn NostrSigner>,
 ) -> Result<nostr::Event> {
+    let unsigned = build_with_delegation(event_builder, signer.get_public_key().await?)?;
+    if confirm_signing_enabled() {
+        confirm_signing(&unsigned)?;
+    }
     if signer.backend() == SignerBackend::NostrConnect {
         let term = console::Term::stderr();
         term.write_line("signing event with remote signer...")?;
         let event = signer
-            .sign_event(event_builder.build(signer.get_public_key().await?))
+            .sign_event(unsigned)
             .await
             .context("failed to sign event")?;
         term.clear_last_lines(1)?;
         Ok(event)
     } else {
         signer
-            .sign_event(event_builder.build(signer.get_public_key().await?))
+            .sign_event(unsigned)
             .await
             .context("failed to sign event")
     }
 }
 
+/// sign several events at once. NIP-46 has no batch request, so a remote
+/// signer is sent every request concurrently rather than one round trip after
+/// another, and the perms requested at login stop it prompting for each
+pub async fn sign_events(
+    unsigned_events: Vec<UnsignedEvent>,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<Vec<nostr::Event>> {
+    if confirm_signing_enabled() {
+        for unsigned in &unsigned_events {
+            confirm_signing(unsigned)?;
+        }
+    }
+    let remote = signer.backend() == SignerBackend::NostrConnect;
+    let term = console::Term::stderr();
+    if remote {
+        term.write_line(&format!(
+            "signing {} events with remote signer...",
+            unsigned_events.len()
+        ))?;
+    }
+    let events = if signer.backend() == SignerBackend::Keys {
+        // local keys sign on the cpu so a long series is spread across threads
+        try_join_all(unsigned_events.into_iter().map(|unsigned| {
+            let signer = signer.clone();
+            tokio::spawn(async move { signer.sign_event(unsigned).await })
+        }))
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+    } else {
+        try_join_all(
+            unsigned_events
+                .into_iter()
+                .map(|unsigned| signer.sign_event(unsigned)),
+        )
+        .await
+    }
+    .context("failed to sign events")?;
+    if remote {
+        term.clear_last_lines(1)?;
+    }
+    Ok(events)
+}
+
 pub async fn fetch_public_key(signer: &Arc<dyn NostrSigner>) -> Result<nostr::PublicKey> {
     if signer.backend() == SignerBackend::NostrConnect {
         let term = console::Term::stderr();
@@ -705,7 +972,7 @@ fn pb_style() -> Result<ProgressStyle> {
             "timeout_in",
             |state: &ProgressState, w: &mut dyn Write| {
                 if state.elapsed().as_secs() > 3 && state.elapsed().as_secs() < GET_EVENTS_TIMEOUT {
-                    let dim = Style::new().color256(247);
+                    let dim = theme::dim();
                     write!(
                         w,
                         "{}",
@@ -721,20 +988,14 @@ fn pb_style() -> Result<ProgressStyle> {
     )
 }
 
-fn pb_after_style(succeed: bool) -> indicatif::ProgressStyle {
+fn pb_after_style(succeed: bool) -> ProgressStyle {
     ProgressStyle::with_template(
         format!(
             " {} {}",
             if succeed {
-                console::style("✔".to_string())
-                    .for_stderr()
-                    .green()
-                    .to_string()
+                theme::success().for_stderr().apply_to("✔").to_string()
             } else {
-                console::style("✘".to_string())
-                    .for_stderr()
-                    .red()
-                    .to_string()
+                theme::error().for_stderr().apply_to("✘").to_string()
             },
             "{prefix} {msg}",
         )
@@ -743,15 +1004,23 @@ fn pb_after_style(succeed: bool) -> indicatif::ProgressStyle {
     .unwrap()
 }
 
+static LOCAL_CACHE_PATH: &str = ".git/nostr-cache.lmdb";
+
 async fn get_local_cache_database(git_repo_path: &Path) -> Result<NostrLMDB> {
-    NostrLMDB::open(git_repo_path.join(".git/nostr-cache.lmdb"))
-        .context("failed to open or create nostr cache database at .git/nostr-cache.lmdb")
+    NostrLMDB::open(git_repo_path.join(LOCAL_CACHE_PATH)).map_err(|error| {
+        CacheError::Open {
+            path: LOCAL_CACHE_PATH.to_string(),
+            source: error.into(),
+        }
+        .into()
+    })
 }
 
-async fn get_global_cache_database(git_repo_path: Option<&Path>) -> Result<NostrLMDB> {
-    let path = if std::env::var("NGITTEST").is_ok() {
+/// lmdb cache shared by every repository
+pub fn get_global_cache_path(git_repo_path: Option<&Path>) -> Result<PathBuf> {
+    if settings().test_mode {
         if let Some(git_repo_path) = git_repo_path {
-            git_repo_path.join(".git/test-global-cache.lmdb")
+            Ok(git_repo_path.join(".git/test-global-cache.lmdb"))
         } else {
             bail!("git_repo must be supplied to get_global_cache_database during integration tests")
         }
@@ -760,62 +1029,102 @@ async fn get_global_cache_database(git_repo_path: Option<&Path>) -> Result<Nostr
             "failed to create cache directory in: {:?}",
             get_dirs()?.cache_dir()
         ))?;
-        get_dirs()?.cache_dir().join("nostr-cache.lmdb")
-    };
+        Ok(get_dirs()?.cache_dir().join("nostr-cache.lmdb"))
+    }
+}
 
-    NostrLMDB::open(path).context("failed to open ngit global nostr cache database")
+async fn get_global_cache_database(git_repo_path: Option<&Path>) -> Result<NostrLMDB> {
+    let path = get_global_cache_path(git_repo_path)?;
+    NostrLMDB::open(&path).map_err(|error| {
+        CacheError::Open {
+            path: path.display().to_string(),
+            source: error.into(),
+        }
+        .into()
+    })
 }
 
+#[tracing::instrument(level = "trace", skip_all, fields(filters = filters.len()))]
 pub async fn get_events_from_local_cache(
     git_repo_path: &Path,
     filters: Vec<nostr::Filter>,
 ) -> Result<Vec<nostr::Event>> {
-    Ok(get_local_cache_database(git_repo_path)
+    let events = get_local_cache_database(git_repo_path)
         .await?
         .query(filters.clone())
         .await
-        .context(
-            "failed to execute query on opened git repo nostr cache database .git/nostr-cache.lmdb",
-        )?
-        .to_vec())
+        .map_err(|error| CacheError::Query {
+            path: LOCAL_CACHE_PATH.to_string(),
+            source: error.into(),
+        })?
+        .to_vec();
+    tracing::trace!(events = events.len(), "queried local cache");
+    Ok(events)
 }
 
+#[tracing::instrument(level = "trace", skip_all, fields(filters = filters.len()))]
 pub async fn get_event_from_global_cache(
     git_repo_path: Option<&Path>,
     filters: Vec<nostr::Filter>,
 ) -> Result<Vec<nostr::Event>> {
-    Ok(get_global_cache_database(git_repo_path)
+    let events = get_global_cache_database(git_repo_path)
         .await?
         .query(filters.clone())
         .await
-        .context("failed to execute query on opened ngit nostr cache database")?
-        .to_vec())
+        .map_err(|error| CacheError::Query {
+            path: get_global_cache_path(git_repo_path)
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            source: error.into(),
+        })?
+        .to_vec();
+    tracing::trace!(events = events.len(), "queried global cache");
+    Ok(events)
 }
 
+#[tracing::instrument(level = "trace", skip_all, fields(id = %event.id, kind = %event.kind))]
 pub async fn save_event_in_local_cache(git_repo_path: &Path, event: &nostr::Event) -> Result<bool> {
-    get_local_cache_database(git_repo_path)
+    Ok(get_local_cache_database(git_repo_path)
         .await?
         .save_event(event)
         .await
-        .context("failed to save event in local cache")
+        .map_err(|error| CacheError::Save {
+            path: LOCAL_CACHE_PATH.to_string(),
+            source: error.into(),
+        })?)
 }
 
+#[tracing::instrument(level = "trace", skip_all, fields(id = %event.id, kind = %event.kind))]
 pub async fn save_event_in_global_cache(
     git_repo_path: Option<&Path>,
     event: &nostr::Event,
 ) -> Result<bool> {
-    get_global_cache_database(git_repo_path)
+    Ok(get_global_cache_database(git_repo_path)
         .await?
         .save_event(event)
         .await
-        .context("failed to save event in local cache")
+        .map_err(|error| CacheError::Save {
+            path: get_global_cache_path(git_repo_path)
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            source: error.into(),
+        })?)
 }
 
-pub async fn get_repo_ref_from_cache(
+/// announcements from trusted maintainers, the trusted maintainers and those
+/// listed only by co-maintainers that the user hasn't confirmed, mapped to who
+/// listed them
+async fn get_repo_events_and_maintainers(
     git_repo_path: Option<&Path>,
     repo_coordinate: &Coordinate,
-) -> Result<RepoRef> {
+) -> Result<(
+    Vec<nostr::Event>,
+    HashSet<PublicKey>,
+    HashMap<PublicKey, PublicKey>,
+)> {
+    let git_repo = git_repo_path.and_then(|path| Repo::from_path(&path.to_path_buf()).ok());
     let mut maintainers = HashSet::new();
+    let mut unconfirmed = HashMap::new();
     let mut new_coordinate: bool;
 
     maintainers.insert(repo_coordinate.public_key);
@@ -844,8 +1153,21 @@ pub async fn get_repo_ref_from_cache(
         for e in events {
             if let Ok(repo_ref) = RepoRef::try_from((e.clone(), None)) {
                 for m in repo_ref.maintainers {
-                    if maintainers.insert(m) {
-                        new_coordinate = true;
+                    match get_maintainer_trust(
+                        &git_repo.as_ref(),
+                        &m,
+                        &e.pubkey,
+                        &repo_coordinate.public_key,
+                    ) {
+                        MaintainerTrust::Trusted => {
+                            if maintainers.insert(m) {
+                                new_coordinate = true;
+                            }
+                        }
+                        MaintainerTrust::Unconfirmed => {
+                            unconfirmed.insert(m, e.pubkey);
+                        }
+                        MaintainerTrust::Rejected => {}
                     }
                 }
                 repo_events.push(e);
@@ -855,10 +1177,35 @@ pub async fn get_repo_ref_from_cache(
             break;
         }
     }
+    unconfirmed.retain(|m, _| !maintainers.contains(m));
+    Ok((repo_events, maintainers, unconfirmed))
+}
+
+/// maintainers listed only by co-maintainers that need confirming, with who
+/// listed them
+pub async fn get_unconfirmed_maintainers(
+    git_repo_path: Option<&Path>,
+    repo_coordinate: &Coordinate,
+) -> Result<HashMap<PublicKey, PublicKey>> {
+    let (_, _, unconfirmed) =
+        get_repo_events_and_maintainers(git_repo_path, repo_coordinate).await?;
+    Ok(unconfirmed)
+}
+
+pub async fn get_repo_ref_from_cache(
+    git_repo_path: Option<&Path>,
+    repo_coordinate: &Coordinate,
+) -> Result<RepoRef> {
+    let (repo_events, maintainers, _) =
+        get_repo_events_and_maintainers(git_repo_path, repo_coordinate).await?;
+    let mut repo_events = without_future_dated(repo_events);
     repo_events.sort_by_key(|e| e.created_at);
+    let git_repo = git_repo_path.and_then(|path| Repo::from_path(&path.to_path_buf()).ok());
+    let announcement_source = get_announcement_source(&git_repo.as_ref());
     let repo_ref = RepoRef::try_from((
-        repo_events
-            .first()
+        announcement_source
+            .and_then(|pk| repo_events.iter().find(|e| e.pubkey.eq(&pk)))
+            .or(repo_events.first())
             .context("no repo announcement event found at specified coordinates. if you are the repository maintainer consider running `ngit init` to create one")?
             .clone(),
         Some(repo_coordinate.public_key),
@@ -879,15 +1226,81 @@ pub async fn get_repo_ref_from_cache(
         }
     }
 
+    let mut maintainers = maintainers.iter().copied().collect::<Vec<PublicKey>>();
+    if let Some(org) = &repo_ref.org {
+        for member in get_org_members_from_cache(git_repo_path, org).await? {
+            if !maintainers.contains(&member) {
+                maintainers.push(member);
+            }
+        }
+    }
+
     Ok(RepoRef {
         // use all maintainers from all events found, not just maintainers in the most
-        // recent event
-        maintainers: maintainers.iter().copied().collect::<Vec<PublicKey>>(),
+        // recent event, and the current members of the organization
+        maintainers,
         events,
         ..repo_ref
     })
 }
 
+/// members of the most recent cached maintainer list of the organization at
+/// `org`, so changes to the organization apply to its repositories
+async fn get_org_members_from_cache(
+    git_repo_path: Option<&Path>,
+    org: &Coordinate,
+) -> Result<Vec<PublicKey>> {
+    let Some(git_repo_path) = git_repo_path else {
+        return Ok(vec![]);
+    };
+    let events = get_events_from_local_cache(git_repo_path, vec![get_filter_org(org)]).await?;
+    Ok(without_future_dated(events)
+        .iter()
+        .max_by_key(|e| e.created_at)
+        .and_then(|e| Org::try_from(e).ok())
+        .map(|org| org.members)
+        .unwrap_or_default())
+}
+
+/// announcements published by `public_key` for forks of `repo_ref`, ie. that
+/// name it as upstream or share its earliest unique commit under another
+/// identifier
+pub async fn get_user_forks_from_cache(
+    git_repo_path: Option<&Path>,
+    repo_ref: &RepoRef,
+    public_key: &PublicKey,
+) -> Result<Vec<RepoRef>> {
+    let filter = nostr::Filter::default()
+        .kind(Kind::GitRepoAnnouncement)
+        .author(*public_key);
+    let events = [
+        get_event_from_global_cache(git_repo_path, vec![filter.clone()]).await?,
+        if let Some(git_repo_path) = git_repo_path {
+            get_events_from_local_cache(git_repo_path, vec![filter]).await?
+        } else {
+            vec![]
+        },
+    ]
+    .concat();
+    let coordinates = repo_ref.coordinates();
+    let mut forks: Vec<RepoRef> = vec![];
+    for event in events {
+        let Ok(fork) = RepoRef::try_from((event, None)) else {
+            continue;
+        };
+        let is_fork = fork.upstream.as_ref().is_some_and(|upstream| {
+            coordinates.iter().any(|c| {
+                c.identifier.eq(&upstream.identifier) && c.public_key.eq(&upstream.public_key)
+            })
+        }) || (fork.root_commit.eq(&repo_ref.root_commit)
+            && !fork.identifier.eq(&repo_ref.identifier));
+        if is_fork && !forks.iter().any(|f| f.identifier.eq(&fork.identifier)) {
+            forks.push(fork);
+        }
+    }
+    Ok(forks)
+}
+
 pub async fn get_state_from_cache(
     git_repo_path: Option<&Path>,
     repo_ref: &RepoRef,
@@ -909,6 +1322,14 @@ pub async fn get_state_from_cache(
     }
 }
 
+/// also fetch proposals sent to the upstream of a fork. enabled with
+/// `git config nostr.fetch-upstream true`
+pub fn fetch_upstream_enabled(git_repo_path: Option<&Path>) -> bool {
+    let git_repo = git_repo_path.and_then(|path| Repo::from_path(&path.to_path_buf()).ok());
+    get_git_config_item(&git_repo.as_ref(), "nostr.fetch-upstream")
+        .is_ok_and(|value| value.is_some_and(|value| value == "true"))
+}
+
 #[allow(clippy::too_many_lines)]
 async fn create_relays_request(
     git_repo_path: Option<&Path>,
@@ -936,7 +1357,18 @@ async fn create_relays_request(
             set.insert(trusted_maintainer_coordinate.clone());
         }
         if let Some(repo_ref) = &repo_ref {
-            for c in repo_ref.coordinates() {
+            let mut coordinates = repo_ref.coordinates_including_previous_identifiers();
+            if let Some(upstream) = &repo_ref.upstream {
+                if fetch_upstream_enabled(git_repo_path) {
+                    coordinates.extend(
+                        get_repo_ref_from_cache(git_repo_path, upstream)
+                            .await
+                            .map(|upstream_ref| upstream_ref.coordinates())
+                            .unwrap_or_else(|_| HashSet::from([upstream.clone()])),
+                    );
+                }
+            }
+            for c in coordinates {
                 if !set
                     .iter()
                     .any(|e| e.identifier.eq(&c.identifier) && e.public_key.eq(&c.public_key))
@@ -988,7 +1420,6 @@ async fn create_relays_request(
             {
                 if event_is_patch_set_root(event) || event_is_revision_root(event) {
                     proposals.insert(event.id);
-                    contributors.insert(event.pubkey);
                 }
             }
         }
@@ -1139,6 +1570,7 @@ async fn create_relays_request(
         } else {
             None
         },
+        org: repo_ref.as_ref().and_then(|repo_ref| repo_ref.org.clone()),
         proposals,
         contributors,
         missing_contributor_profiles,
@@ -1148,9 +1580,48 @@ async fn create_relays_request(
     })
 }
 
+/// reject forged or malformed events before they are cached: the signature
+/// must be valid, repo announcements and state need an identifier, and state
+/// must be published by one of the repository's maintainers
+fn check_fetched_event(
+    event: &nostr::Event,
+    request: &FetchRequest,
+    report: &FetchReport,
+) -> Result<()> {
+    event.verify().context("invalid id or signature")?;
+    // the cache keeps the newest replaceable event so a future-dated one would
+    // hide later updates. it's fetched again once its time comes
+    if (event.kind.is_replaceable() || event.kind.is_addressable()) && is_future_dated(event) {
+        bail!("replaceable event dated in the future");
+    }
+    if event.kind.eq(&Kind::GitRepoAnnouncement) || event.kind.eq(&STATE_KIND) {
+        let identifier = event
+            .tags
+            .identifier()
+            .context("repository event without an identifier")?;
+        if event.kind.eq(&STATE_KIND)
+            && !request
+                .repo_coordinates_without_relays
+                .iter()
+                .map(|(c, _)| c)
+                .chain(report.repo_coordinates_without_relays.iter())
+                .any(|c| c.identifier.eq(identifier) && c.public_key.eq(&event.pubkey))
+        {
+            bail!("repository state not published by a maintainer");
+        }
+    } else if event.kind.eq(&Kind::GitPatch) {
+        if event.content.trim().is_empty() {
+            bail!("patch without content");
+        }
+    } else if status_kinds().contains(&event.kind) && event.tags.event_ids().next().is_none() {
+        bail!("status without a proposal");
+    }
+    Ok(())
+}
+
 #[allow(clippy::too_many_lines)]
-async fn process_fetched_events(
-    events: Vec<nostr::Event>,
+async fn process_fetched_event(
+    event: &nostr::Event,
     request: &FetchRequest,
     git_repo_path: Option<&Path>,
     fresh_coordinates: &mut HashSet<Coordinate>,
@@ -1158,135 +1629,160 @@ async fn process_fetched_events(
     fresh_profiles: &mut HashSet<PublicKey>,
     report: &mut FetchReport,
 ) -> Result<()> {
-    for event in &events {
-        if !request.existing_events.contains(&event.id) {
-            if let Some(git_repo_path) = git_repo_path {
-                save_event_in_local_cache(git_repo_path, event).await?;
-            }
-            if event.kind.eq(&Kind::GitRepoAnnouncement) {
-                save_event_in_global_cache(git_repo_path, event).await?;
-                let new_coordinate = !request
-                    .repo_coordinates_without_relays
+    if request.existing_events.contains(&event.id) {
+        return Ok(());
+    }
+    if let Some(git_repo_path) = git_repo_path {
+        if save_event_in_local_cache(git_repo_path, event).await? {
+            run_event_hooks(git_repo_path, event);
+        }
+    }
+    if event.kind.eq(&Kind::GitRepoAnnouncement) {
+        save_event_in_global_cache(git_repo_path, event).await?;
+        let new_coordinate = !request
+            .repo_coordinates_without_relays
+            .iter()
+            .map(|(c, _)| c.clone())
+            .any(|c| {
+                c.identifier.eq(event.tags.identifier().unwrap()) && c.public_key.eq(&event.pubkey)
+            });
+        let update_to_existing = !new_coordinate
+            && request
+                .repo_coordinates_without_relays
+                .iter()
+                .any(|(c, t)| {
+                    c.identifier.eq(event.tags.identifier().unwrap())
+                        && c.public_key.eq(&event.pubkey)
+                        && if let Some(t) = t {
+                            event.created_at.gt(t)
+                        } else {
+                            true
+                        }
+                });
+        if update_to_existing {
+            report.updated_repo_announcements.push((
+                Coordinate {
+                    kind: event.kind,
+                    public_key: event.pubkey,
+                    identifier: event.tags.identifier().unwrap().to_owned(),
+                    relays: vec![],
+                },
+                event.created_at,
+            ));
+        }
+        // if contains new maintainer
+        if let Ok(repo_ref) = &RepoRef::try_from((event.clone(), None)) {
+            for m in &repo_ref.maintainers {
+                if !request
+                    .repo_coordinates_without_relays // prexisting maintainers
                     .iter()
                     .map(|(c, _)| c.clone())
-                    .any(|c| {
-                        c.identifier.eq(event.tags.identifier().unwrap())
-                            && c.public_key.eq(&event.pubkey)
-                    });
-                let update_to_existing = !new_coordinate
-                    && request
-                        .repo_coordinates_without_relays
-                        .iter()
-                        .any(|(c, t)| {
-                            c.identifier.eq(event.tags.identifier().unwrap())
-                                && c.public_key.eq(&event.pubkey)
-                                && if let Some(t) = t {
-                                    event.created_at.gt(t)
-                                } else {
-                                    true
-                                }
-                        });
-                if update_to_existing {
-                    report.updated_repo_announcements.push((
-                        Coordinate {
-                            kind: event.kind,
-                            public_key: event.pubkey,
-                            identifier: event.tags.identifier().unwrap().to_owned(),
-                            relays: vec![],
-                        },
-                        event.created_at,
-                    ));
-                }
-                // if contains new maintainer
-                if let Ok(repo_ref) = &RepoRef::try_from((event.clone(), None)) {
-                    for m in &repo_ref.maintainers {
-                        if !request
-                            .repo_coordinates_without_relays // prexisting maintainers
-                            .iter()
-                            .map(|(c, _)| c.clone())
-                            .collect::<HashSet<Coordinate>>()
-                            .union(&report.repo_coordinates_without_relays) // already added maintainers
-                            .any(|c| c.identifier.eq(&repo_ref.identifier) && m.eq(&c.public_key))
-                        {
-                            let c = Coordinate {
-                                kind: event.kind,
-                                public_key: *m,
-                                identifier: repo_ref.identifier.clone(),
-                                relays: vec![],
-                            };
-                            fresh_coordinates.insert(c.clone());
-                            report.repo_coordinates_without_relays.insert(c);
-
-                            if !request.contributors.contains(m)
-                                && !request
-                                    .profiles_to_fetch_from_user_relays
-                                    .clone()
-                                    .into_keys()
-                                    .collect::<HashSet<PublicKey>>()
-                                    .contains(m)
-                                && !fresh_profiles.contains(m)
-                            {
-                                fresh_profiles.insert(m.to_owned());
-                            }
-                        }
-                    }
-                }
-            } else if event.kind.eq(&STATE_KIND) {
-                let existing_state = if report.updated_state.is_some() {
-                    report.updated_state
-                } else {
-                    request.state
-                };
-                if let Some((timestamp, id)) = existing_state {
-                    if event.created_at.gt(&timestamp)
-                        || (event.created_at.eq(&timestamp) && event.id.gt(&id))
-                    {
-                        report.updated_state = Some((event.created_at, event.id));
-                    }
-                }
-            } else if event_is_patch_set_root(event) {
-                fresh_proposal_roots.insert(event.id);
-                report.proposals.insert(event.id);
-                if !request.contributors.contains(&event.pubkey)
-                    && !fresh_profiles.contains(&event.pubkey)
-                {
-                    fresh_profiles.insert(event.pubkey);
-                }
-            } else if [Kind::RelayList, Kind::Metadata].contains(&event.kind) {
-                if request.missing_contributor_profiles.contains(&event.pubkey) {
-                    report.contributor_profiles.insert(event.pubkey);
-                } else if let Some((_, (metadata_timestamp, relay_list_timestamp))) = request
-                    .profiles_to_fetch_from_user_relays
-                    .get_key_value(&event.pubkey)
+                    .collect::<HashSet<Coordinate>>()
+                    .union(&report.repo_coordinates_without_relays) // already added maintainers
+                    .any(|c| c.identifier.eq(&repo_ref.identifier) && m.eq(&c.public_key))
                 {
-                    if (Kind::Metadata.eq(&event.kind) && event.created_at.gt(metadata_timestamp))
-                        || (Kind::RelayList.eq(&event.kind)
-                            && event.created_at.gt(relay_list_timestamp))
+                    let c = Coordinate {
+                        kind: event.kind,
+                        public_key: *m,
+                        identifier: repo_ref.identifier.clone(),
+                        relays: vec![],
+                    };
+                    fresh_coordinates.insert(c.clone());
+                    report.repo_coordinates_without_relays.insert(c);
+
+                    if !request.contributors.contains(m)
+                        && !request
+                            .profiles_to_fetch_from_user_relays
+                            .clone()
+                            .into_keys()
+                            .collect::<HashSet<PublicKey>>()
+                            .contains(m)
+                        && !fresh_profiles.contains(m)
                     {
-                        report.profile_updates.insert(event.pubkey);
+                        fresh_profiles.insert(m.to_owned());
                     }
                 }
-                save_event_in_global_cache(git_repo_path, event).await?;
             }
         }
-    }
-    for event in &events {
-        if !request.existing_events.contains(&event.id)
-            && !event
-                .tags
-                .event_ids()
-                .any(|id| report.proposals.contains(id))
+    } else if event.kind.eq(&STATE_KIND) {
+        let existing_state = if report.updated_state.is_some() {
+            report.updated_state
+        } else {
+            request.state
+        };
+        if let Some((timestamp, id)) = existing_state {
+            if event.created_at.gt(&timestamp)
+                || (event.created_at.eq(&timestamp) && event.id.gt(&id))
+            {
+                report.updated_state = Some((event.created_at, event.id));
+            }
+        }
+    } else if event_is_patch_set_root(event) {
+        // author profiles are fetched when displayed, see fetch_missing_profiles
+        fresh_proposal_roots.insert(event.id);
+        report.proposals.insert(event.id);
+    } else if [Kind::RelayList, Kind::Metadata].contains(&event.kind) {
+        if request.missing_contributor_profiles.contains(&event.pubkey) {
+            report.contributor_profiles.insert(event.pubkey);
+        } else if let Some((_, (metadata_timestamp, relay_list_timestamp))) = request
+            .profiles_to_fetch_from_user_relays
+            .get_key_value(&event.pubkey)
         {
-            if event.kind.eq(&Kind::GitPatch) && !event_is_patch_set_root(event) {
-                report.commits.insert(event.id);
-            } else if status_kinds().contains(&event.kind) {
-                report.statuses.insert(event.id);
+            if (Kind::Metadata.eq(&event.kind) && event.created_at.gt(metadata_timestamp))
+                || (Kind::RelayList.eq(&event.kind) && event.created_at.gt(relay_list_timestamp))
+            {
+                report.profile_updates.insert(event.pubkey);
             }
         }
+        save_event_in_global_cache(git_repo_path, event).await?;
     }
     Ok(())
 }
 
+/// new patch or status whose proposal may arrive later in the same response
+struct FetchedReply {
+    id: EventId,
+    is_status: bool,
+    references: Vec<EventId>,
+}
+
+impl FetchedReply {
+    fn from_event(event: &nostr::Event, request: &FetchRequest) -> Option<Self> {
+        if request.existing_events.contains(&event.id) {
+            None
+        } else if event.kind.eq(&Kind::GitPatch) && !event_is_patch_set_root(event) {
+            Some(false)
+        } else if status_kinds().contains(&event.kind) {
+            Some(true)
+        } else {
+            None
+        }
+        .map(|is_status| Self {
+            id: event.id,
+            is_status,
+            references: event.tags.event_ids().copied().collect(),
+        })
+    }
+}
+
+/// count patches and statuses as updates unless they belong to a proposal
+/// already counted as new
+fn classify_fetched_replies(replies: Vec<FetchedReply>, report: &mut FetchReport) {
+    for reply in replies {
+        if !reply
+            .references
+            .iter()
+            .any(|id| report.proposals.contains(id))
+        {
+            if reply.is_status {
+                report.statuses.insert(reply.id);
+            } else {
+                report.commits.insert(reply.id);
+            }
+        }
+    }
+}
+
 pub fn consolidate_fetch_reports(reports: Vec<Result<FetchReport>>) -> FetchReport {
     let mut report = FetchReport::default();
     for relay_report in reports.into_iter().flatten() {
@@ -1339,6 +1835,7 @@ pub fn consolidate_fetch_reports(reports: Vec<Result<FetchReport>>) -> FetchRepo
         for c in relay_report.profile_updates {
             report.profile_updates.insert(c);
         }
+        report.timed_out |= relay_report.timed_out;
     }
     report
 }
@@ -1354,8 +1851,14 @@ pub fn get_fetch_filters(
             vec![
                 get_filter_state_events(repo_coordinates),
                 get_filter_repo_events(repo_coordinates),
+                // issues and numbering indexes are fetched for short numbers
                 nostr::Filter::default()
-                    .kinds(vec![Kind::GitPatch, Kind::EventDeletion])
+                    .kinds(vec![
+                        Kind::GitPatch,
+                        Kind::GitIssue,
+                        Kind::BookmarkSet,
+                        Kind::EventDeletion,
+                    ])
                     .custom_tag(
                         SingleLetterTag::lowercase(nostr_sdk::Alphabet::A),
                         repo_coordinates
@@ -1369,9 +1872,14 @@ pub fn get_fetch_filters(
             vec![]
         } else {
             vec![
-                nostr::Filter::default()
-                    .events(proposal_ids.clone())
-                    .kinds([vec![Kind::GitPatch, Kind::EventDeletion], status_kinds()].concat()),
+                // reactions are tallied as 👍/👎 in `ngit list`
+                nostr::Filter::default().events(proposal_ids.clone()).kinds(
+                    [
+                        vec![Kind::GitPatch, Kind::EventDeletion, Kind::Reaction],
+                        status_kinds(),
+                    ]
+                    .concat(),
+                ),
             ]
         },
         if required_profiles.is_empty() {
@@ -1418,6 +1926,98 @@ pub fn get_filter_state_events(repo_coordinates: &HashSet<Coordinate>) -> nostr:
         )
 }
 
+/// skip relays for authors no relay had a profile for within this many seconds
+static MISSING_PROFILE_RETRY_SECS: u64 = 24 * 60 * 60;
+
+fn get_missing_profiles_path(git_repo_path: &Path) -> Result<PathBuf> {
+    Ok(if settings().test_mode {
+        git_repo_path.join(".git/test-missing-profiles")
+    } else {
+        create_dir_all(get_dirs()?.cache_dir())?;
+        get_dirs()?.cache_dir().join("missing-profiles")
+    })
+}
+
+/// authors listed as `<hex pubkey> <unix timestamp>` lines searched for more
+/// recently than `MISSING_PROFILE_RETRY_SECS` before `now`
+fn parse_recently_missing_profiles(contents: &str, now: u64) -> HashMap<PublicKey, u64> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (public_key, timestamp) = line.split_once(' ')?;
+            let timestamp = timestamp.trim().parse::<u64>().ok()?;
+            (now.saturating_sub(timestamp) < MISSING_PROFILE_RETRY_SECS)
+                .then_some((PublicKey::from_hex(public_key).ok()?, timestamp))
+        })
+        .collect()
+}
+
+/// fetch profiles of authors about to be displayed that aren't cached yet.
+/// authors without a profile on any relay are remembered for a day so they
+/// aren't searched for on every run
+pub async fn fetch_missing_profiles(
+    #[cfg(test)] client: &crate::client::MockConnect,
+    #[cfg(not(test))] client: &Client,
+    git_repo_path: &Path,
+    repo_ref: &RepoRef,
+    public_keys: &[PublicKey],
+) -> Result<()> {
+    let missing_profiles_path = get_missing_profiles_path(git_repo_path)?;
+    let now = Timestamp::now().as_u64();
+    let mut recently_missing = parse_recently_missing_profiles(
+        &std::fs::read_to_string(&missing_profiles_path).unwrap_or_default(),
+        now,
+    );
+    let mut to_fetch = HashSet::new();
+    for public_key in public_keys {
+        if !recently_missing.contains_key(public_key)
+            && get_user_ref_from_cache(Some(git_repo_path), public_key)
+                .await
+                .is_err()
+        {
+            to_fetch.insert(*public_key);
+        }
+    }
+    if to_fetch.is_empty() {
+        return Ok(());
+    }
+    let relays = repo_ref
+        .relays
+        .iter()
+        .map(std::string::ToString::to_string)
+        .chain(client.get_fallback_relays().iter().cloned())
+        .collect::<HashSet<String>>();
+    let events = client
+        .get_events(
+            relays.into_iter().collect(),
+            vec![get_filter_contributor_profiles(to_fetch.clone())],
+        )
+        .await?;
+    for event in &events {
+        if to_fetch.contains(&event.pubkey) {
+            save_event_in_global_cache(Some(git_repo_path), event).await?;
+        }
+    }
+    for public_key in to_fetch {
+        if !events.iter().any(|event| event.pubkey.eq(&public_key)) {
+            recently_missing.insert(public_key, now);
+        }
+    }
+    std::fs::write(
+        &missing_profiles_path,
+        recently_missing
+            .iter()
+            .fold(String::new(), |mut contents, (public_key, timestamp)| {
+                let _ = writeln!(contents, "{} {timestamp}", public_key.to_hex());
+                contents
+            }),
+    )
+    .context(format!(
+        "failed to write {}",
+        missing_profiles_path.display()
+    ))
+}
+
 pub fn get_filter_contributor_profiles(contributors: HashSet<PublicKey>) -> nostr::Filter {
     nostr::Filter::default()
         .kinds(vec![Kind::Metadata, Kind::RelayList])
@@ -1435,6 +2035,23 @@ pub struct FetchReport {
     statuses: HashSet<EventId>,
     contributor_profiles: HashSet<PublicKey>,
     profile_updates: HashSet<PublicKey>,
+    /// a relay didn't reach EOSE, even when retried with a longer timeout
+    timed_out: bool,
+    /// maintainers listed only by co-maintainers, with who listed them
+    unconfirmed_maintainers: HashMap<PublicKey, PublicKey>,
+}
+
+impl FetchReport {
+    /// whether a relay timed out so events may be missing
+    pub fn is_incomplete(&self) -> bool {
+        self.timed_out
+    }
+
+    /// maintainers listed only by co-maintainers that aren't trusted until
+    /// confirmed, with who listed them
+    pub fn unconfirmed_maintainers(&self) -> &HashMap<PublicKey, PublicKey> {
+        &self.unconfirmed_maintainers
+    }
 }
 
 impl Display for FetchReport {
@@ -1519,6 +2136,8 @@ pub struct FetchRequest {
     selected_relay: Option<RelayUrl>,
     relay_column_width: usize,
     repo_coordinates_without_relays: Vec<(Coordinate, Option<Timestamp>)>,
+    /// organization maintainer list the repository belongs to
+    org: Option<Coordinate>,
     state: Option<(Timestamp, EventId)>,
     proposals: HashSet<EventId>,
     contributors: HashSet<PublicKey>,
@@ -1528,6 +2147,47 @@ pub struct FetchRequest {
     user_relays_for_profiles: HashSet<RelayUrl>,
 }
 
+/// desktop notifications for new proposals and for patches and statuses on
+/// the logged in user's proposals
+async fn notify_fetch_report(
+    git_repo_path: &Path,
+    repo_ref: &RepoRef,
+    report: &FetchReport,
+    new_proposals: &[nostr::Event],
+) {
+    let user = get_likely_logged_in_user(git_repo_path)
+        .await
+        .unwrap_or_default();
+    let proposals = get_proposals_and_revisions_from_cache(
+        git_repo_path,
+        repo_ref.coordinates_including_previous_identifiers(),
+    )
+    .await
+    .unwrap_or_default();
+    let new_replies = get_events_from_local_cache(
+        git_repo_path,
+        vec![nostr::Filter::default().ids(report.commits.union(&report.statuses).copied())],
+    )
+    .await
+    .unwrap_or_default();
+    let (new_statuses, new_patches): (Vec<nostr::Event>, Vec<nostr::Event>) = new_replies
+        .into_iter()
+        .partition(|e| status_kinds().contains(&e.kind));
+    for (summary, body) in fetch_notifications(
+        &repo_ref.name,
+        user.as_ref(),
+        &proposals,
+        new_proposals,
+        &new_patches,
+        &new_statuses,
+    ) {
+        if let Err(error) = desktop_notify(&summary, &body) {
+            tracing::warn!("failed to show desktop notification: {error:#}");
+            break;
+        }
+    }
+}
+
 pub async fn fetching_with_report(
     git_repo_path: &Path,
     #[cfg(test)] client: &crate::client::MockConnect,
@@ -1535,24 +2195,135 @@ pub async fn fetching_with_report(
     trusted_maintainer_coordinate: &Coordinate,
 ) -> Result<FetchReport> {
     let term = console::Term::stderr();
-    term.write_line("fetching updates...")?;
-    let (relay_reports, progress_reporter) = client
-        .fetch_all(
-            Some(git_repo_path),
-            Some(trusted_maintainer_coordinate),
-            &HashSet::new(),
+    let _ = recent_repos::record_used(git_repo_path);
+    let mut report = if recent_repos::refreshed_by_daemon(git_repo_path) {
+        if !output::quiet() {
+            term.write_line("using cache refreshed by ngit daemon")?;
+        }
+        FetchReport::default()
+    } else {
+        if !output::quiet() {
+            term.write_line("fetching updates...")?;
+        }
+        let (relay_reports, progress_reporter) = client
+            .fetch_all(
+                Some(git_repo_path),
+                Some(trusted_maintainer_coordinate),
+                &HashSet::new(),
+            )
+            .await?;
+        if !relay_reports.iter().any(std::result::Result::is_err) {
+            let _ = progress_reporter.clear();
+        }
+        consolidate_fetch_reports(relay_reports)
+    };
+    if report.to_string().is_empty() {
+        output::info("no updates");
+    } else {
+        output::info(&format!("updates: {report}"));
+    }
+    if let Some(hint) = update_hint(client).await {
+        if !output::quiet() {
+            term.write_line(&theme::dim().apply_to(hint).to_string())?;
+        }
+    }
+    report.unconfirmed_maintainers =
+        get_unconfirmed_maintainers(Some(git_repo_path), trusted_maintainer_coordinate)
+            .await
+            .unwrap_or_default();
+    if let (Ok(git_repo), Ok(repo_ref)) = (
+        Repo::from_path(&git_repo_path.to_path_buf()),
+        get_repo_ref_from_cache(Some(git_repo_path), trusted_maintainer_coordinate).await,
+    ) {
+        let new_proposals = get_events_from_local_cache(git_repo_path, vec![
+            nostr::Filter::default().ids(report.proposals.iter().copied()),
+        ])
+        .await
+        .unwrap_or_default();
+        for warning in get_lineage_warnings(&git_repo, &repo_ref, &new_proposals) {
+            term.write_line(&format!("WARNING: {warning}"))?;
+        }
+        if notifications_enabled(&git_repo) {
+            notify_fetch_report(git_repo_path, &repo_ref, &report, &new_proposals).await;
+        }
+        if let Some(renamed_to) = &repo_ref.renamed_to {
+            term.write_line(&format!(
+                "WARNING: this repository has been renamed to {renamed_to}. update your remote to {}",
+                RepoRef {
+                    identifier: renamed_to.clone(),
+                    renamed_to: None,
+                    nostr_git_url: None,
+                    ..repo_ref.clone()
+                }
+                .to_nostr_git_url(&Some(&git_repo))
+            ))?;
+        }
+    }
+    for public_key in report
+        .repo_coordinates_without_relays
+        .iter()
+        // excludes upstream and pre-rename coordinates
+        .filter(|c| c.identifier == trusted_maintainer_coordinate.identifier)
+        .map(|c| c.public_key)
+        .collect::<HashSet<PublicKey>>()
+    {
+        println!(
+            "new maintainer: {}",
+            get_author_display_name(Some(git_repo_path), &public_key).await
+        );
+    }
+    Ok(report)
+}
+
+/// ask the user what fetching can't decide on its own: whether to trust
+/// maintainers listed only by co-maintainers and which of the maintainers'
+/// conflicting announcements to use. only for interactive commands; until
+/// answered such maintainers aren't trusted and announcements are merged.
+pub async fn resolve_repo_trust(
+    git_repo: &Repo,
+    report: &FetchReport,
+    trusted_maintainer_coordinate: &Coordinate,
+) -> Result<()> {
+    for (maintainer, listed_by) in &report.unconfirmed_maintainers {
+        confirm_maintainer(
+            git_repo,
+            maintainer,
+            listed_by,
+            &trusted_maintainer_coordinate.identifier,
         )
         .await?;
-    if !relay_reports.iter().any(std::result::Result::is_err) {
-        let _ = progress_reporter.clear();
     }
-    let report = consolidate_fetch_reports(relay_reports);
-    if report.to_string().is_empty() {
-        println!("no updates");
+    if let Ok(repo_ref) =
+        get_repo_ref_from_cache(Some(git_repo.get_path()?), trusted_maintainer_coordinate).await
+    {
+        choose_announcement_if_conflicting(git_repo, &repo_ref).await?;
+    }
+    Ok(())
+}
+
+/// ask once whether a maintainer listed only by a co-maintainer should be
+/// trusted and remember the answer
+async fn confirm_maintainer(
+    git_repo: &Repo,
+    maintainer: &PublicKey,
+    listed_by: &PublicKey,
+    identifier: &str,
+) -> Result<()> {
+    let git_repo_path = git_repo.get_path()?;
+    let name = get_author_display_name(Some(git_repo_path), maintainer).await;
+    let listed_by_name = get_author_display_name(Some(git_repo_path), listed_by).await;
+    if Interactor::default().confirm(
+        PromptConfirmParms::default()
+            .with_prompt(format!(
+                "{listed_by_name} lists {name} ({}) as a maintainer of {identifier}. treat {name} as a maintainer?",
+                maintainer.to_bech32()?
+            ))
+            .with_default(false),
+    )? {
+        trust_maintainer(&Some(git_repo), maintainer)
     } else {
-        println!("updates: {report}");
+        reject_maintainer(&Some(git_repo), maintainer)
     }
-    Ok(report)
 }
 
 pub async fn get_proposals_and_revisions_from_cache(
@@ -1702,7 +2473,7 @@ pub async fn send_events(
     let m = if silent {
         MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
     } else {
-        MultiProgress::new()
+        new_progress_reporter()
     };
     let pb_style = ProgressStyle::with_template(if animate {
         " {spinner} {prefix} {bar} {pos}/{len} {msg}"
@@ -1714,19 +2485,13 @@ pub async fn send_events(
     let pb_after_style =
         |symbol| ProgressStyle::with_template(format!(" {symbol} {}", "{prefix} {msg}",).as_str());
     let pb_after_style_succeeded = pb_after_style(if animate {
-        console::style("✔".to_string())
-            .for_stderr()
-            .green()
-            .to_string()
+        theme::success().for_stderr().apply_to("✔").to_string()
     } else {
         "y".to_string()
     })?;
 
     let pb_after_style_failed = pb_after_style(if animate {
-        console::style("✘".to_string())
-            .for_stderr()
-            .red()
-            .to_string()
+        theme::error().for_stderr().apply_to("✘").to_string()
     } else {
         "x".to_string()
     })?;
@@ -1781,14 +2546,14 @@ pub async fn send_events(
                 Err(e) => {
                     pb.set_style(pb_after_style_failed.clone());
                     pb.finish_with_message(
-                        console::style(
-                            e.to_string()
-                                .replace("relay pool error:", "error:")
-                                .replace("event not published: ", "error: "),
-                        )
-                        .for_stderr()
-                        .red()
-                        .to_string(),
+                        theme::error()
+                            .for_stderr()
+                            .apply_to(
+                                e.to_string()
+                                    .replace("relay pool error:", "error:")
+                                    .replace("event not published: ", "error: "),
+                            )
+                            .to_string(),
                     );
                     failed = true;
                     break;
@@ -1811,3 +2576,92 @@ fn remove_trailing_slash(s: &str) -> String {
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_profiles_are_retried_after_a_day() {
+        let recent = nostr::Keys::generate().public_key();
+        let stale = nostr::Keys::generate().public_key();
+        let now = MISSING_PROFILE_RETRY_SECS * 2;
+        let missing = parse_recently_missing_profiles(
+            &format!(
+                "{} {}\n{} 1\nnot a line\n",
+                recent.to_hex(),
+                now - 60,
+                stale.to_hex()
+            ),
+            now,
+        );
+        assert_eq!(missing, HashMap::from([(recent, now - 60)]));
+    }
+
+    #[test]
+    fn fetched_state_must_be_from_a_maintainer() -> Result<()> {
+        let maintainer = nostr::Keys::generate();
+        let request = FetchRequest {
+            repo_coordinates_without_relays: vec![(
+                Coordinate {
+                    kind: Kind::GitRepoAnnouncement,
+                    public_key: maintainer.public_key(),
+                    identifier: "repo".to_string(),
+                    relays: vec![],
+                },
+                None,
+            )],
+            ..Default::default()
+        };
+        let state = |keys: &nostr::Keys| {
+            EventBuilder::new(STATE_KIND, "")
+                .tags([nostr::Tag::identifier("repo")])
+                .sign_with_keys(keys)
+        };
+        let report = FetchReport::default();
+        assert!(check_fetched_event(&state(&maintainer)?, &request, &report).is_ok());
+        assert!(check_fetched_event(&state(&nostr::Keys::generate())?, &request, &report).is_err());
+        let empty_patch = EventBuilder::new(Kind::GitPatch, "").sign_with_keys(&maintainer)?;
+        assert!(check_fetched_event(&empty_patch, &request, &report).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dedup_events_keeps_newest_replaceable_version() -> Result<()> {
+        let keys = nostr::Keys::generate();
+        let state = |created_at: u64| {
+            EventBuilder::new(STATE_KIND, "")
+                .tags([nostr::Tag::identifier("repo")])
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(&keys)
+        };
+        let (older, newer) = (state(1)?, state(2)?);
+        let patch = EventBuilder::new(Kind::GitPatch, "patch").sign_with_keys(&keys)?;
+        assert_eq!(
+            get_dedup_events(vec![
+                Ok(vec![patch.clone(), newer.clone()]),
+                Err(anyhow::anyhow!("relay offline")),
+                Ok(vec![older, patch.clone()]),
+            ]),
+            vec![patch, newer],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn consolidated_report_is_incomplete_if_a_relay_timed_out() {
+        let timed_out = FetchReport {
+            timed_out: true,
+            ..FetchReport::default()
+        };
+        assert!(
+            consolidate_fetch_reports(vec![
+                Ok(FetchReport::default()),
+                Ok(timed_out),
+                Err(anyhow::anyhow!("failed to connect")),
+            ])
+            .is_incomplete()
+        );
+        assert!(!consolidate_fetch_reports(vec![Ok(FetchReport::default())]).is_incomplete());
+    }
+}
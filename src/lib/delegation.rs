@@ -0,0 +1,237 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use nostr::{
+    Event, EventBuilder, Keys, Kind, PublicKey, Tag, Timestamp, UnsignedEvent,
+    nips::nip26::{Conditions, DelegationTag, EventProperties},
+};
+
+#[cfg(feature = "native")]
+use crate::settings::settings;
+
+/// kinds a bot / CI key is delegated by default: patches and statuses
+pub static DEFAULT_DELEGATED_KINDS: [u16; 5] = [1617, 1630, 1631, 1632, 1633];
+
+/// replaceable and addressable events, such as repo state, are looked up by
+/// the key that signed them so a delegation can't attribute them to anyone
+/// else
+fn can_be_delegated(kind: Kind) -> bool {
+    !kind.is_replaceable() && !kind.is_addressable()
+}
+
+/// set on bot / CI machines with `git config --global nostr.delegation
+/// '<tag>'`, or `delegation` in `~/.config/ngit/config.toml`, using the tag
+/// from `ngit account delegate`
+#[cfg(feature = "native")]
+pub fn get_delegation() -> Result<Option<DelegationTag>> {
+    settings()
+        .delegation
+        .as_ref()
+        .map(|json| {
+            DelegationTag::from_json(json.trim())
+                .context("invalid NIP-26 delegation tag in nostr.delegation")
+        })
+        .transpose()
+}
+
+/// build the event including the delegation tag, if one is configured and
+/// the kind can be delegated, so it is attributed to the delegator
+#[cfg(feature = "native")]
+pub fn build_with_delegation(
+    event_builder: EventBuilder,
+    public_key: PublicKey,
+) -> Result<UnsignedEvent> {
+    match get_delegation()? {
+        Some(delegation) if can_be_delegated(event_builder.clone().build(public_key).kind) => {
+            apply_delegation(event_builder, public_key, &delegation)
+        }
+        _ => Ok(event_builder.build(public_key)),
+    }
+}
+
+/// who `event` is attributed to: the delegator of a valid NIP-26 delegation
+/// tag, otherwise the key that signed it
+pub fn event_author(event: &Event) -> PublicKey {
+    if !can_be_delegated(event.kind) {
+        return event.pubkey;
+    }
+    event
+        .tags
+        .iter()
+        .find(|t| {
+            t.as_slice()
+                .first()
+                .is_some_and(|name| name == "delegation")
+        })
+        .and_then(|t| serde_json::to_string(t.as_slice()).ok())
+        .and_then(|json| DelegationTag::from_json(&json).ok())
+        .filter(|delegation| {
+            delegation
+                .validate(
+                    &event.pubkey,
+                    &EventProperties::new(event.kind.as_u16(), event.created_at.as_u64()),
+                )
+                .is_ok()
+        })
+        .map_or(event.pubkey, |delegation| delegation.delegator_pubkey())
+}
+
+/// build the event with `delegation`, erroring if its conditions don't
+/// permit the event
+pub fn apply_delegation(
+    event_builder: EventBuilder,
+    public_key: PublicKey,
+    delegation: &DelegationTag,
+) -> Result<UnsignedEvent> {
+    let tag: Vec<String> = serde_json::from_str(&delegation.as_json())?;
+    let unsigned = event_builder
+        .tags([Tag::parse(&tag).context("failed to create delegation tag")?])
+        .build(public_key);
+    delegation
+        .validate(
+            &public_key,
+            &EventProperties::new(unsigned.kind.as_u16(), unsigned.created_at.as_u64()),
+        )
+        .context(format!(
+            "nostr.delegation doesn't permit this key to sign kind {} events. it may have expired",
+            unsigned.kind.as_u16()
+        ))?;
+    Ok(unsigned)
+}
+
+/// `kind=1617&kind=1630&created_at<1700000000`
+fn conditions_string(kinds: &[u16], until: Timestamp) -> String {
+    kinds
+        .iter()
+        .map(|kind| format!("kind={kind}"))
+        .chain([format!("created_at<{}", until.as_u64())])
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// delegate signing of `kinds` to `delegatee` until `until`
+pub fn create_delegation(
+    delegator: &Keys,
+    delegatee: &PublicKey,
+    kinds: &[u16],
+    until: Timestamp,
+) -> Result<DelegationTag> {
+    if let Some(kind) = kinds
+        .iter()
+        .find(|kind| !can_be_delegated(Kind::from(**kind)))
+    {
+        bail!("kind {kind} is replaceable or addressable so can't be delegated");
+    }
+    let conditions = Conditions::from_str(&conditions_string(kinds, until))
+        .context("invalid delegation conditions")?;
+    DelegationTag::new(delegator, delegatee, conditions).context("failed to sign delegation")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_string_lists_kinds_then_expiry() {
+        assert_eq!(
+            conditions_string(&[1617, 1630], Timestamp::from(1_700_000_000)),
+            "kind=1617&kind=1630&created_at<1700000000"
+        );
+    }
+
+    #[test]
+    fn replaceable_and_addressable_kinds_are_refused() {
+        let bot = Keys::generate().public_key();
+        assert!(
+            create_delegation(
+                &Keys::generate(),
+                &bot,
+                &[1617, 30618],
+                Timestamp::now() + 60
+            )
+            .is_err()
+        );
+    }
+
+    mod apply_delegation {
+        use super::*;
+
+        fn delegation(delegatee: &PublicKey, until: Timestamp) -> Result<DelegationTag> {
+            create_delegation(
+                &Keys::generate(),
+                delegatee,
+                &DEFAULT_DELEGATED_KINDS,
+                until,
+            )
+        }
+
+        #[test]
+        fn adds_delegation_tag_for_permitted_kind() -> Result<()> {
+            let bot = Keys::generate().public_key();
+            let delegation = delegation(&bot, Timestamp::now() + 60)?;
+            let unsigned =
+                apply_delegation(EventBuilder::new(Kind::GitPatch, "patch"), bot, &delegation)?;
+            assert!(unsigned.tags.iter().any(|t| {
+                t.as_slice()[0] == "delegation"
+                    && t.as_slice()[1] == delegation.delegator_pubkey().to_hex()
+            }));
+            Ok(())
+        }
+
+        #[test]
+        fn errors_for_kind_not_delegated() -> Result<()> {
+            let bot = Keys::generate().public_key();
+            let delegation = delegation(&bot, Timestamp::now() + 60)?;
+            assert!(
+                apply_delegation(EventBuilder::new(Kind::TextNote, "hi"), bot, &delegation)
+                    .is_err()
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn errors_when_expired() -> Result<()> {
+            let bot = Keys::generate().public_key();
+            let delegation = delegation(&bot, Timestamp::from(1_000))?;
+            assert!(
+                apply_delegation(EventBuilder::new(Kind::GitPatch, "patch"), bot, &delegation)
+                    .is_err()
+            );
+            Ok(())
+        }
+    }
+
+    mod event_author {
+        use super::*;
+
+        #[test]
+        fn delegated_events_are_attributed_to_the_delegator() -> Result<()> {
+            let delegator = Keys::generate();
+            let bot = Keys::generate();
+            let delegation = create_delegation(
+                &delegator,
+                &bot.public_key(),
+                &DEFAULT_DELEGATED_KINDS,
+                Timestamp::now() + 60,
+            )?;
+            let delegated = apply_delegation(
+                EventBuilder::new(Kind::GitPatch, "patch"),
+                bot.public_key(),
+                &delegation,
+            )?
+            .sign_with_keys(&bot)?;
+            assert_eq!(event_author(&delegated), delegator.public_key());
+
+            // the tag only counts for the delegatee it names
+            let other = Keys::generate();
+            let copied = EventBuilder::new(Kind::GitPatch, "patch")
+                .tags(delegated.tags.iter().cloned())
+                .sign_with_keys(&other)?;
+            assert_eq!(event_author(&copied), other.public_key());
+
+            let undelegated = EventBuilder::new(Kind::GitPatch, "patch").sign_with_keys(&bot)?;
+            assert_eq!(event_author(&undelegated), bot.public_key());
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! resumable downloads of static files over http: bundles, blossom packs and
+//! release binaries. fetches over the git smart http protocol can't be
+//! resumed as the server builds a new pack for every negotiation, so one that
+//! drops partway starts again. objects already received from a bundle or the
+//! pack cache aren't fetched again though.
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::blossom::sha256_hex;
+
+/// directory within `.git` for partially downloaded packfiles and bundles
+pub fn partial_download_dir(git_dir: &Path) -> PathBuf {
+    git_dir.join("ngit-partial")
+}
+
+/// where data received so far from `url` is kept between attempts
+pub fn partial_download_path(partial_dir: &Path, url: &str) -> PathBuf {
+    partial_dir.join(sha256_hex(url.as_bytes()))
+}
+
+/// download `url`, writing data to `partial_dir` as it arrives so that if the
+/// connection drops the next attempt resumes with a http range request. only
+/// for files served as is, not git smart http
+pub async fn download_resumable(url: &str, partial_dir: &Path) -> Result<Vec<u8>> {
+    fs::create_dir_all(partial_dir).context("failed to create directory for partial downloads")?;
+    let path = partial_download_path(partial_dir, url);
+    let existing_len = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = reqwest::Client::new().get(url);
+    if existing_len > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={existing_len}-"));
+    }
+    let mut response = request
+        .send()
+        .await
+        .context(format!("failed to connect to {url}"))?;
+
+    let append = match response.status() {
+        reqwest::StatusCode::PARTIAL_CONTENT => true,
+        // the previous attempt received everything
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE => return take_partial_download(&path),
+        // server doesnt support range requests so start again
+        status if status.is_success() => false,
+        status => bail!("{url} returned status {status}"),
+    };
+    let mut file = if append {
+        OpenOptions::new().append(true).open(&path)
+    } else {
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+    }
+    .context("failed to open partial download")?;
+
+    while let Some(chunk) = response.chunk().await.context(format!(
+        "download from {url} was interrupted. try again to resume"
+    ))? {
+        file.write_all(&chunk)
+            .context("failed to write partial download")?;
+    }
+    drop(file);
+    take_partial_download(&path)
+}
+
+fn take_partial_download(path: &Path) -> Result<Vec<u8>> {
+    let data = fs::read(path).context("failed to read partial download")?;
+    let _ = fs::remove_file(path);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_download_path_differs_by_url() {
+        let dir = Path::new("/tmp/ngit-partial");
+        assert_ne!(
+            partial_download_path(dir, "https://a.example/blob"),
+            partial_download_path(dir, "https://b.example/blob"),
+        );
+        assert_eq!(
+            partial_download_path(dir, "https://a.example/blob"),
+            partial_download_path(dir, "https://a.example/blob"),
+        );
+    }
+}
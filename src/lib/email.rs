@@ -0,0 +1,303 @@
+use std::{io::Write, process::Command};
+
+use anyhow::{Context, Result, bail};
+use nostr::Event;
+
+use crate::{
+    git::{Repo, RepoActions},
+    interrupt,
+};
+
+/// rfc 5322 message id derived from the patch event id
+pub fn message_id(event: &Event) -> String {
+    format!("<{}@nostr>", event.id.to_hex())
+}
+
+/// UTC year, month, day and seconds into the day of a unix timestamp
+pub(crate) fn utc_date(timestamp: u64) -> (u64, u64, u64, u64) {
+    let days = timestamp / 86_400;
+    // civil date from days since 1970-01-01, see Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day, timestamp % 86_400)
+}
+
+/// rfc 2822 date in UTC for a unix timestamp
+fn rfc2822_date(timestamp: u64) -> String {
+    static WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    static MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (year, month, day, seconds) = utc_date(timestamp);
+    format!(
+        "{}, {day:02} {} {year} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[usize::try_from(timestamp / 86_400 % 7).unwrap_or_default()],
+        MONTHS[usize::try_from(month - 1).unwrap_or_default()],
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60,
+    )
+}
+
+/// headers and body of a patch event's `git format-patch` content
+fn split_patch(content: &str) -> (Vec<(String, String)>, &str) {
+    let content = match content.split_once('\n') {
+        Some((first_line, rest)) if first_line.starts_with("From ") => rest,
+        _ => content,
+    };
+    let (header_block, body) = content.split_once("\n\n").unwrap_or((content, ""));
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in header_block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push('\n');
+                value.push_str(line);
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim_start().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+/// the series as emails from `from` to `to` threaded under the first event,
+/// like `git send-email`. patch authors other than `from` are credited with a
+/// `From:` line at the top of the body. when `from` is None each patch is sent
+/// as its author, and the cover letter as the first patch's author
+pub fn render_patch_emails(events: &[Event], from: Option<&str>, to: &[String]) -> Vec<String> {
+    let series_author = events
+        .iter()
+        .find_map(|event| {
+            split_patch(&event.content)
+                .0
+                .into_iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("From"))
+                .map(|(_, value)| value)
+        })
+        .unwrap_or_default();
+    events
+        .iter()
+        .map(|event| {
+            let (headers, body) = split_patch(&event.content);
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| v.clone())
+            };
+            let from = from.map_or_else(
+                || header("From").unwrap_or_else(|| series_author.clone()),
+                str::to_string,
+            );
+            let mut email = vec![
+                format!("From: {from}"),
+                format!("To: {}", to.join(", ")),
+                format!("Subject: {}", header("Subject").unwrap_or_default()),
+                format!(
+                    "Date: {}",
+                    header("Date").unwrap_or_else(|| rfc2822_date(event.created_at.as_u64()))
+                ),
+                format!("Message-Id: {}", message_id(event)),
+            ];
+            if let Some(first) = events.first().filter(|first| first.id.ne(&event.id)) {
+                email.push(format!("In-Reply-To: {}", message_id(first)));
+                email.push(format!("References: {}", message_id(first)));
+            }
+            email.push("MIME-Version: 1.0".to_string());
+            email.push(format!(
+                "Content-Type: {}",
+                header("Content-Type").unwrap_or_else(|| "text/plain; charset=UTF-8".to_string())
+            ));
+            email.push(format!(
+                "Content-Transfer-Encoding: {}",
+                header("Content-Transfer-Encoding").unwrap_or_else(|| "8bit".to_string())
+            ));
+            email.push(String::new());
+            if let Some(author) = header("From").filter(|author| author.ne(&from)) {
+                email.push(format!("From: {author}"));
+                email.push(String::new());
+            }
+            email.push(body.to_string());
+            email.join("\n")
+        })
+        .collect()
+}
+
+/// emails in mboxrd format, as read by patchwork's `parsearchive`
+pub fn to_mbox(emails: &[String]) -> String {
+    emails
+        .iter()
+        .map(|email| {
+            let escaped: Vec<String> = email
+                .lines()
+                .map(|line| {
+                    if line.trim_start_matches('>').starts_with("From ") {
+                        format!(">{line}")
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect();
+            format!(
+                "From ngit Thu Jan  1 00:00:00 1970\n{}\n\n",
+                escaped.join("\n")
+            )
+        })
+        .collect()
+}
+
+/// sender from git config `sendemail.from` or `user.name` and `user.email`
+pub fn get_email_sender(git_repo: &Repo) -> Result<String> {
+    if let Some(from) = git_repo.get_git_config_item("sendemail.from", None)? {
+        return Ok(from);
+    }
+    let email = git_repo
+        .get_git_config_item("user.email", None)?
+        .context("set git config user.email or sendemail.from to send emails")?;
+    Ok(
+        if let Some(name) = git_repo.get_git_config_item("user.name", None)? {
+            format!("{name} <{email}>")
+        } else {
+            email
+        },
+    )
+}
+
+/// hand emails to `git send-email` when `sendemail.smtpserver` is configured,
+/// otherwise pipe each to `sendemail.sendmailcmd` or `sendmail`
+pub fn send_emails(git_repo: &Repo, emails: &[String], to: &[String]) -> Result<()> {
+    if git_repo
+        .get_git_config_item("sendemail.smtpserver", None)?
+        .is_some()
+    {
+        // removed when dropped. only the user can read it
+        let temp_dir = tempfile::Builder::new()
+            .prefix("ngit-send-email-")
+            .tempdir()
+            .context("failed to create a directory for the patch emails")?;
+        let dir = temp_dir.path().to_path_buf();
+        let _remove_if_interrupted = interrupt::on_interrupt({
+            let dir = dir.clone();
+            move || {
+                let _ = std::fs::remove_dir_all(&dir);
+            }
+        });
+        let mut paths = vec![];
+        for (i, email) in emails.iter().enumerate() {
+            let path = dir.join(format!("{:04}.eml", i + 1));
+            std::fs::write(&path, email)?;
+            paths.push(path);
+        }
+        let status = Command::new("git")
+            .current_dir(git_repo.get_path()?)
+            .args([
+                "send-email",
+                "--confirm=never",
+                "--no-thread",
+                "--suppress-cc=all",
+            ])
+            .args(to.iter().map(|to| format!("--to={to}")))
+            .args(&paths)
+            .status()
+            .context("failed to run git send-email");
+        drop(temp_dir);
+        if !status?.success() {
+            bail!("git send-email failed to send the patch emails");
+        }
+        return Ok(());
+    }
+    let sendmail = git_repo
+        .get_git_config_item("sendemail.sendmailcmd", None)?
+        .unwrap_or_else(|| "sendmail".to_string());
+    let mut parts = sendmail.split_whitespace();
+    let program = parts.next().context("sendemail.sendmailcmd is empty")?;
+    for email in emails {
+        let mut child = Command::new(program)
+            .args(parts.clone())
+            .args(["-i", "-t"])
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .context(format!(
+                "failed to run {program}. install sendmail or set git config sendemail.smtpserver"
+            ))?;
+        child
+            .stdin
+            .take()
+            .context("failed to open sendmail stdin")?
+            .write_all(email.as_bytes())?;
+        if !child.wait()?.success() {
+            bail!("{program} failed to send a patch email");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind};
+
+    use super::*;
+
+    fn patch_event(content: &str) -> Event {
+        EventBuilder::new(Kind::GitPatch, content)
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn rfc2822_date_from_timestamp() {
+        assert_eq!(rfc2822_date(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(
+            rfc2822_date(1_700_000_000),
+            "Tue, 14 Nov 2023 22:13:20 +0000"
+        );
+        assert_eq!(rfc2822_date(951_782_400), "Tue, 29 Feb 2000 00:00:00 +0000");
+    }
+
+    #[test]
+    fn renders_threaded_emails_crediting_other_authors() {
+        let cover_letter = patch_event(
+            "From 431b84edc0d2fa118d63faa3c2db9c73d630a5ae Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/1] title\n\ndescription",
+        );
+        let patch = patch_event(
+            "From 431b84edc0d2fa118d63faa3c2db9c73d630a5ae Mon Sep 17 00:00:00 2001\nFrom: Joe Bloggs <joe.bloggs@pm.me>\nDate: Thu, 1 Jan 1970 00:00:00 +0000\nSubject: [PATCH 1/1] add t1.md\n\n---\n t1.md | 1 +\n",
+        );
+        let emails = render_patch_emails(
+            &[cover_letter.clone(), patch.clone()],
+            Some("Alice <alice@example.com>"),
+            &["list@example.com".to_string()],
+        );
+        assert!(emails[0].starts_with(
+            "From: Alice <alice@example.com>\nTo: list@example.com\nSubject: [PATCH 0/1] title\n"
+        ));
+        assert!(!emails[0].contains("In-Reply-To"));
+        assert!(emails[0].ends_with("\n\ndescription"));
+        assert!(emails[1].contains(&format!(
+            "Message-Id: <{}@nostr>\nIn-Reply-To: <{}@nostr>\n",
+            patch.id, cover_letter.id
+        )));
+        assert!(emails[1].contains("Date: Thu, 1 Jan 1970 00:00:00 +0000\n"));
+        assert!(emails[1].contains("\n\nFrom: Joe Bloggs <joe.bloggs@pm.me>\n\n---\n t1.md"));
+    }
+
+    #[test]
+    fn without_sender_emails_are_from_patch_authors() {
+        let cover_letter = patch_event("Subject: [PATCH 0/1] title\n\ndescription");
+        let patch = patch_event(
+            "From: Joe Bloggs <joe.bloggs@pm.me>\nSubject: [PATCH 1/1] add t1.md\n\nFrom the start\n",
+        );
+        let emails = render_patch_emails(&[cover_letter, patch], None, &[]);
+        assert!(emails[0].starts_with("From: Joe Bloggs <joe.bloggs@pm.me>\n"));
+        assert!(emails[1].starts_with("From: Joe Bloggs <joe.bloggs@pm.me>\n"));
+        assert!(!emails[1].contains("\n\nFrom: Joe"));
+        assert!(to_mbox(&emails[1..]).contains("\n>From the start\n"));
+    }
+}
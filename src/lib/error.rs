@@ -0,0 +1,104 @@
+//! error types for callers to branch on. functions still return
+//! `anyhow::Result`, so find these with `downcast_ref` or by walking
+//! `error.chain()`
+
+/// a relay couldn't be reached
+#[derive(Debug, thiserror::Error)]
+pub enum RelayError {
+    #[error("connection timeout")]
+    ConnectionTimeout,
+}
+
+/// the repository or global nostr event cache couldn't be used
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("failed to open or create nostr cache database at {path}")]
+    Open {
+        path: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("failed to execute query on opened nostr cache database at {path}")]
+    Query {
+        path: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("failed to save event in nostr cache database at {path}")]
+    Save {
+        path: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// a git server can't be authenticated with
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error(
+        "no ssh keys found in ~/.ssh and no ssh-agent running. set a key for this remote with `git config nostr.{remote}.ssh-key <path>`"
+    )]
+    NoSshKeys { remote: String },
+}
+
+/// a patch couldn't be applied, even with a 3-way merge
+#[derive(Debug, thiserror::Error)]
+#[error("patch conflicts in: {}", paths.join(", "))]
+pub struct PatchConflicts {
+    pub paths: Vec<String>,
+}
+
+/// a proposal's parent commit isn't in the local repository so its patches
+/// can't be applied where they were created
+#[derive(Debug, thiserror::Error)]
+#[error("failed to find parent commit ({commit_id}). run git pull and try again.")]
+pub struct ParentCommitMissing {
+    pub commit_id: String,
+}
+
+/// a patch's `commit` tag isn't the commit its content produces, so it can't
+/// be trusted for commit-id-based status tracking
+#[derive(Debug, thiserror::Error)]
+#[error("patch {patch_id} claims commit {claimed} but applying it doesn't produce that commit")]
+pub struct CommitIdMismatch {
+    pub patch_id: String,
+    pub claimed: String,
+}
+
+/// whether `error` was caused by an authentication failure, typed or reported
+/// by libgit2
+pub fn is_authentication_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<AuthError>().is_some()
+            || cause.downcast_ref::<git2::Error>().is_some_and(|error| {
+                matches!(
+                    error.code(),
+                    git2::ErrorCode::Auth | git2::ErrorCode::Certificate
+                )
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn finds_authentication_errors_under_context() {
+        let no_keys: anyhow::Result<()> = Err(AuthError::NoSshKeys {
+            remote: "origin".to_string(),
+        }
+        .into());
+        assert!(is_authentication_error(
+            &no_keys.context("failed to fetch").unwrap_err()
+        ));
+        let rejected = anyhow::Error::from(git2::Error::new(
+            git2::ErrorCode::Auth,
+            git2::ErrorClass::Http,
+            "too many redirects or authentication replays",
+        ));
+        assert!(is_authentication_error(&rejected));
+        assert!(!is_authentication_error(
+            &RelayError::ConnectionTimeout.into()
+        ));
+    }
+}
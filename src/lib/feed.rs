@@ -0,0 +1,100 @@
+use crate::email::utc_date;
+
+/// an entry in a repository activity feed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeedEntry {
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub author: String,
+    pub updated: u64,
+    pub summary: String,
+}
+
+fn rfc3339_date(timestamp: u64) -> String {
+    let (year, month, day, seconds) = utc_date(timestamp);
+    format!(
+        "{year}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60,
+    )
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// atom feed of `entries`, which should be most recent first
+pub fn render_atom_feed(id: &str, title: &str, link: &str, entries: &[FeedEntry]) -> String {
+    let mut xml = vec![
+        r#"<?xml version="1.0" encoding="utf-8"?>"#.to_string(),
+        r#"<feed xmlns="http://www.w3.org/2005/Atom">"#.to_string(),
+        format!("  <id>{}</id>", escape_xml(id)),
+        format!("  <title>{}</title>", escape_xml(title)),
+        format!(r#"  <link href="{}"/>"#, escape_xml(link)),
+        format!(
+            "  <updated>{}</updated>",
+            rfc3339_date(entries.iter().map(|e| e.updated).max().unwrap_or_default())
+        ),
+        "  <generator>ngit</generator>".to_string(),
+    ];
+    for entry in entries {
+        xml.push("  <entry>".to_string());
+        xml.push(format!("    <id>{}</id>", escape_xml(&entry.id)));
+        xml.push(format!("    <title>{}</title>", escape_xml(&entry.title)));
+        xml.push(format!(r#"    <link href="{}"/>"#, escape_xml(&entry.link)));
+        xml.push(format!(
+            "    <author><name>{}</name></author>",
+            escape_xml(&entry.author)
+        ));
+        xml.push(format!(
+            "    <updated>{}</updated>",
+            rfc3339_date(entry.updated)
+        ));
+        if !entry.summary.is_empty() {
+            xml.push(format!(
+                "    <summary>{}</summary>",
+                escape_xml(&entry.summary)
+            ));
+        }
+        xml.push("  </entry>".to_string());
+    }
+    xml.push("</feed>".to_string());
+    xml.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc3339_date_from_timestamp() {
+        assert_eq!(rfc3339_date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339_date(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn renders_escaped_entries() {
+        let feed = render_atom_feed(
+            "nostr:naddr1abc",
+            "ngit activity",
+            "https://gitworkshop.dev/repo/naddr1abc",
+            &[FeedEntry {
+                id: "nostr:nevent1abc".to_string(),
+                title: "fix <script> & escaping".to_string(),
+                link: "https://njump.me/nevent1abc".to_string(),
+                author: "alice".to_string(),
+                updated: 1_700_000_000,
+                summary: String::new(),
+            }],
+        );
+        assert!(feed.contains("  <updated>2023-11-14T22:13:20Z</updated>\n"));
+        assert!(feed.contains("<title>fix &lt;script&gt; &amp; escaping</title>"));
+        assert!(!feed.contains("<summary>"));
+        assert!(feed.ends_with("</feed>\n"));
+    }
+}
@@ -0,0 +1,264 @@
+//! an interim C abi for mobile and gui clients. each export takes and returns
+//! a json string so bindings only need `char *`. it is expected to be replaced
+//! by generated uniffi bindings, so isn't covered by semver until then.
+//!
+//! strings returned are owned by the caller and released with
+//! `ngit_string_free`. arguments are only borrowed for the call.
+use std::{
+    collections::HashSet,
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result, bail};
+use nostr::{JsonUtil, nips::nip01::Coordinate};
+use nostr_sdk::{EventId, PublicKey, ToBech32};
+use serde_json::{Value, json};
+
+use crate::{
+    client::get_fetch_filters,
+    git::{Repo, nostr_url::NostrUrlDecoded, str_to_sha1},
+    git_events::{event_to_cover_letter, generate_unsigned_cover_letter_and_patch_events},
+    repo_ref::RepoRef,
+};
+
+/// `{"ok": value}` or `{"error": message}` as a string owned by the caller,
+/// to be released with `ngit_string_free`. panics are returned as errors as
+/// unwinding across the C abi is undefined behaviour
+fn respond(f: impl FnOnce() -> Result<Value>) -> *mut c_char {
+    let response = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => json!({ "ok": value }),
+        Ok(Err(error)) => json!({ "error": format!("{error:#}") }),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| (*message).to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+            json!({ "error": format!("ngit panicked: {message}") })
+        }
+    };
+    CString::new(response.to_string())
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// # Safety
+/// `arg` must be null or a nul-terminated string that outlives the call
+unsafe fn read_arg<'a>(arg: *const c_char) -> Result<&'a str> {
+    if arg.is_null() {
+        bail!("argument is null");
+    }
+    unsafe { CStr::from_ptr(arg) }
+        .to_str()
+        .context("argument is not utf-8")
+}
+
+fn string_array(request: &Value, key: &str) -> Vec<String> {
+    request
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn decode_nostr_url(url: &str) -> Result<Value> {
+    let decoded = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(NostrUrlDecoded::parse_and_resolve(url, &None))?;
+    Ok(json!({
+        "coordinate": decoded.coordinate.to_bech32()?,
+        "identifier": decoded.coordinate.identifier,
+        "public_key": decoded.coordinate.public_key.to_hex(),
+        "relays": decoded
+            .coordinate
+            .relays
+            .iter()
+            .map(|relay| relay.as_str_without_trailing_slash().to_string())
+            .collect::<Vec<_>>(),
+        "protocol": decoded.protocol.map(|protocol| protocol.to_string()),
+        "user": decoded.user,
+        "nip05": decoded.nip05,
+    }))
+}
+
+fn proposal_from_event(event: &str) -> Result<Value> {
+    let event = nostr::Event::from_json(event).context("invalid event json")?;
+    let cover_letter = event_to_cover_letter(&event)?;
+    Ok(json!({
+        "title": cover_letter.title,
+        "description": cover_letter.description,
+        "branch_name": cover_letter.branch_name,
+        "event_id": cover_letter.event_id.map(|id| id.to_hex()),
+        "author": cover_letter.author.map(|author| author.to_hex()),
+    }))
+}
+
+fn fetch_filters(request: &str) -> Result<Value> {
+    let request: Value = serde_json::from_str(request).context("invalid request json")?;
+    let repo_coordinates = string_array(&request, "repo_coordinates")
+        .iter()
+        .map(|coordinate| {
+            Coordinate::parse(coordinate).context(format!("invalid coordinate {coordinate}"))
+        })
+        .collect::<Result<HashSet<_>>>()?;
+    let proposal_ids = string_array(&request, "proposal_ids")
+        .iter()
+        .map(|id| EventId::parse(id).context(format!("invalid event id {id}")))
+        .collect::<Result<HashSet<_>>>()?;
+    let profiles = string_array(&request, "profiles")
+        .iter()
+        .map(|pubkey| PublicKey::parse(pubkey).context(format!("invalid public key {pubkey}")))
+        .collect::<Result<HashSet<_>>>()?;
+    Ok(serde_json::to_value(get_fetch_filters(
+        &repo_coordinates,
+        &proposal_ids,
+        &profiles,
+    ))?)
+}
+
+fn build_patch_events(request: &str) -> Result<Value> {
+    let request: Value = serde_json::from_str(request).context("invalid request json")?;
+    let field = |key: &str| {
+        request
+            .get(key)
+            .and_then(Value::as_str)
+            .context(format!("request is missing \"{key}\""))
+    };
+    let git_repo = Repo::from_path(&PathBuf::from(field("repo_path")?))?;
+    let repo_ref = RepoRef::try_from((
+        nostr::Event::from_json(field("repo_event")?).context("invalid repo_event json")?,
+        None,
+    ))?;
+    let commits = string_array(&request, "commits")
+        .iter()
+        .map(|commit| str_to_sha1(commit))
+        .collect::<Result<Vec<_>>>()?;
+    let cover_letter = match (field("title"), field("description")) {
+        (Ok(title), Ok(description)) => Some((title.to_string(), description.to_string())),
+        _ => None,
+    };
+    let events = generate_unsigned_cover_letter_and_patch_events(
+        cover_letter,
+        &git_repo,
+        &commits,
+        &PublicKey::parse(field("public_key")?)?,
+        &repo_ref,
+        &field("root_proposal_id").ok().map(str::to_string),
+        &[],
+    )?;
+    events
+        .iter()
+        .map(|event| Ok(serde_json::from_str::<Value>(&event.as_json())?))
+        .collect()
+}
+
+/// decode a `nostr://` git url into
+/// `{"coordinate", "identifier", "public_key", "relays", "protocol", "user",
+/// "nip05"}`, resolving nip05 addresses over the network
+///
+/// # Safety
+/// `url` must be a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngit_decode_nostr_url(url: *const c_char) -> *mut c_char {
+    respond(|| unsafe { read_arg(url) }.and_then(decode_nostr_url))
+}
+
+/// `{"title", "description", "branch_name", "event_id", "author"}` of a
+/// proposal root event given as json
+///
+/// # Safety
+/// `event` must be a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngit_proposal_from_event(event: *const c_char) -> *mut c_char {
+    respond(|| unsafe { read_arg(event) }.and_then(proposal_from_event))
+}
+
+/// filters for `{"repo_coordinates": [], "proposal_ids": [], "profiles": []}`
+/// as used by `ngit fetch`
+///
+/// # Safety
+/// `request` must be a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngit_fetch_filters(request: *const c_char) -> *mut c_char {
+    respond(|| unsafe { read_arg(request) }.and_then(fetch_filters))
+}
+
+/// unsigned patch events, with ids set, for `{"repo_path", "repo_event",
+/// "public_key", "commits"}` and optionally `"title"` and `"description"` for a
+/// cover letter and `"root_proposal_id"` for a revision. commits are listed
+/// oldest first. the caller signs and sends them
+///
+/// # Safety
+/// `request` must be a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngit_build_patch_events(request: *const c_char) -> *mut c_char {
+    respond(|| unsafe { read_arg(request) }.and_then(build_patch_events))
+}
+
+/// release a string returned by ngit
+///
+/// # Safety
+/// `string` must be null or returned by an `ngit_` function and not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn ngit_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(f: unsafe extern "C" fn(*const c_char) -> *mut c_char, arg: &str) -> Value {
+        let arg = CString::new(arg).unwrap();
+        unsafe {
+            let response = f(arg.as_ptr());
+            let value = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+            ngit_string_free(response);
+            value
+        }
+    }
+
+    #[test]
+    fn decode_nostr_url_returns_coordinate() {
+        let response = call(
+            ngit_decode_nostr_url,
+            "nostr://npub15qydau2hjma6ngxkl2cyar74wzyjshvl65za5k5rl69264ar2exs5cyejr/relay.damus.io/ngit",
+        );
+        assert_eq!(response["ok"]["identifier"], "ngit");
+        assert_eq!(response["ok"]["relays"][0], "wss://relay.damus.io");
+    }
+
+    #[test]
+    fn errors_are_returned_as_json() {
+        let response = call(ngit_fetch_filters, "not json");
+        assert!(
+            response["error"]
+                .as_str()
+                .unwrap()
+                .contains("invalid request json")
+        );
+        let response = call(ngit_fetch_filters, "{}");
+        assert_eq!(response["ok"], json!([]));
+    }
+
+    #[test]
+    fn panics_are_returned_as_errors() {
+        let response: Value = unsafe {
+            let response = respond(|| panic!("boom"));
+            let value = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+            ngit_string_free(response);
+            value
+        };
+        assert_eq!(response["error"], "ngit panicked: boom");
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result, bail};
+use nostr_sdk::{PublicKey, Tag, TagKind, hashes::sha1::Hash as Sha1Hash};
+use serde_json::Value;
+
+use crate::git::{Repo, oid_to_sha1};
+
+/// tag on proposals imported from another forge with the pull / merge request
+/// url
+pub static IMPORTED_FROM_TAG: &str = "imported-from";
+
+/// tag on imported proposals crediting the pull / merge request author as
+/// `["imported-author", username, display name, profile url]`
+pub static IMPORTED_AUTHOR_TAG: &str = "imported-author";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PullRequestState {
+    Open,
+    Closed,
+    Merged,
+}
+
+/// a GitHub or Forgejo pull request or a GitLab merge request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub state: PullRequestState,
+    pub html_url: String,
+    pub author: String,
+    pub author_name: Option<String>,
+    pub author_url: Option<String>,
+    pub head_ref: String,
+    pub base_ref: String,
+    /// nostr account the author is known to have
+    pub author_npub: Option<PublicKey>,
+}
+
+impl PullRequest {
+    /// cover letter description crediting where the proposal came from
+    pub fn imported_description(&self) -> String {
+        format!(
+            "{}\n\nimported from {} opened by @{}",
+            self.body.trim(),
+            self.html_url,
+            self.author
+        )
+        .trim_start()
+        .to_string()
+    }
+
+    /// `imported-from` and `imported-author` tags for the proposal root, and a
+    /// `p` tag for the author's nostr account if known
+    pub fn attribution_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::custom(
+                TagKind::Custom(std::borrow::Cow::Borrowed(IMPORTED_FROM_TAG)),
+                vec![self.html_url.clone()],
+            ),
+            Tag::custom(
+                TagKind::Custom(std::borrow::Cow::Borrowed(IMPORTED_AUTHOR_TAG)),
+                vec![
+                    self.author.clone(),
+                    self.author_name.clone().unwrap_or_default(),
+                    self.author_url.clone().unwrap_or_default(),
+                ],
+            ),
+        ];
+        if let Some(npub) = self.author_npub {
+            tags.push(Tag::public_key(npub));
+        }
+        tags
+    }
+}
+
+/// instance base url and project path from a project's web url, eg.
+/// `https://gitlab.example.com/group/subgroup/project`
+pub fn split_project_url(url: &str) -> Result<(String, String)> {
+    let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+    let (scheme, rest) = url.split_once("://").context(format!(
+        "invalid project url \"{url}\". expected https://host/owner/name"
+    ))?;
+    match rest.split_once('/') {
+        Some((host, path)) if !host.is_empty() && path.contains('/') => {
+            Ok((format!("{scheme}://{host}"), path.to_string()))
+        }
+        _ => bail!("invalid project url \"{url}\". expected https://host/owner/name"),
+    }
+}
+
+/// send an api request and parse the json response. `forge` names the service
+/// in errors
+pub async fn send_json_request(request: reqwest::RequestBuilder, forge: &str) -> Result<Value> {
+    let response = request
+        .header(reqwest::header::USER_AGENT, "ngit")
+        .send()
+        .await
+        .context(format!("failed to connect to the {forge} api"))?;
+    let url = response.url().path().to_string();
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        let message = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|v| v.get("message").and_then(Value::as_str).map(str::to_string))
+            .unwrap_or(text);
+        bail!("{forge} api responded to {url} with {status}: {message}");
+    }
+    serde_json::from_str(&text).context(format!("failed to parse {forge} api response"))
+}
+
+/// remote callbacks authenticating over https with `token` as the password.
+/// errors if the token is rejected rather than letting git2 retry it forever
+pub fn token_remote_callbacks<'a>(username: &'a str, token: &'a str) -> git2::RemoteCallbacks<'a> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+    let mut attempted = false;
+    callbacks.credentials(move |_, _, _| {
+        if attempted {
+            return Err(git2::Error::from_str("token was rejected"));
+        }
+        attempted = true;
+        git2::Cred::userpass_plaintext(username, token)
+    });
+    callbacks
+}
+
+/// fetch `remote_ref` from `clone_url` into `local_ref`
+pub fn fetch_ref(
+    git_repo: &Repo,
+    clone_url: &str,
+    remote_ref: &str,
+    local_ref: &str,
+    callbacks: git2::RemoteCallbacks<'_>,
+) -> Result<Sha1Hash> {
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+    git_repo
+        .git_repo
+        .remote_anonymous(clone_url)?
+        .fetch(
+            &[format!("+{remote_ref}:{local_ref}")],
+            Some(&mut fetch_options),
+            None,
+        )
+        .context(format!("failed to fetch {remote_ref} from {clone_url}"))?;
+    Ok(oid_to_sha1(
+        &git_repo
+            .git_repo
+            .refname_to_id(local_ref)
+            .context(format!("{remote_ref} wasn't fetched from {clone_url}"))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_project_url_supports_subgroups() -> Result<()> {
+        assert_eq!(
+            split_project_url("https://gitlab.example.com/group/subgroup/project.git")?,
+            (
+                "https://gitlab.example.com".to_string(),
+                "group/subgroup/project".to_string()
+            ),
+        );
+        assert!(split_project_url("https://codeberg.org/project").is_err());
+        assert!(split_project_url("codeberg.org/owner/project").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn attribution_tags_mention_known_nostr_account() {
+        let pull_request = PullRequest {
+            number: 1,
+            title: "title".to_string(),
+            body: String::new(),
+            state: PullRequestState::Open,
+            html_url: "https://codeberg.org/o/r/pulls/1".to_string(),
+            author: "alice".to_string(),
+            author_name: None,
+            author_url: None,
+            head_ref: "feature".to_string(),
+            base_ref: "main".to_string(),
+            author_npub: None,
+        };
+        assert_eq!(pull_request.attribution_tags().len(), 2);
+        let npub = nostr::Keys::generate().public_key();
+        let tags = PullRequest {
+            author_npub: Some(npub),
+            ..pull_request
+        }
+        .attribution_tags();
+        assert_eq!(tags.last(), Some(&Tag::public_key(npub)));
+    }
+}
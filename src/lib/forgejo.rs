@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use serde_json::Value;
+
+use crate::{
+    forge::{
+        PullRequest, PullRequestState, fetch_ref, send_json_request, split_project_url,
+        token_remote_callbacks,
+    },
+    git::Repo,
+};
+
+/// also works with Gitea, which shares the api
+pub struct ForgejoClient {
+    instance: String,
+    repo: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl ForgejoClient {
+    /// `repo_url` eg. `https://codeberg.org/owner/name`
+    pub fn new(repo_url: &str, token: Option<&str>) -> Result<Self> {
+        let (instance, repo) = split_project_url(repo_url)?;
+        Ok(Self {
+            instance,
+            repo,
+            token: token.map(str::to_string),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    pub fn clone_url(&self) -> String {
+        format!("{}/{}.git", self.instance, self.repo)
+    }
+
+    /// open pull requests, most recently created first
+    pub async fn list_open_pull_requests(&self) -> Result<Vec<PullRequest>> {
+        let mut pull_requests = vec![];
+        for page in 1..=10 {
+            let mut request = self.http.get(format!(
+                "{}/api/v1/repos/{}/pulls?state=open&limit=50&page={page}",
+                self.instance, self.repo,
+            ));
+            if let Some(token) = &self.token {
+                request = request.header(reqwest::header::AUTHORIZATION, format!("token {token}"));
+            }
+            let response = send_json_request(request, "Forgejo").await?;
+            let items = response
+                .as_array()
+                .context("Forgejo api didn't return a list of pull requests")?;
+            for item in items {
+                pull_requests.push(pull_request_from_json(item)?);
+            }
+            if items.len() < 50 {
+                break;
+            }
+        }
+        Ok(pull_requests)
+    }
+
+    /// fetch the head of pull request `number` into `refs/ngit-bridge/forgejo/`
+    pub fn fetch_pull_request(&self, git_repo: &Repo, number: u64) -> Result<Sha1Hash> {
+        fetch_ref(
+            git_repo,
+            &self.clone_url(),
+            &format!("refs/pull/{number}/head"),
+            &format!("refs/ngit-bridge/forgejo/pull/{number}"),
+            if let Some(token) = &self.token {
+                token_remote_callbacks("token", token)
+            } else {
+                git2::RemoteCallbacks::new()
+            },
+        )
+        .context(format!(
+            "failed to fetch pull request #{number} from {}",
+            self.repo
+        ))
+    }
+}
+
+pub fn pull_request_from_json(value: &Value) -> Result<PullRequest> {
+    let str_at = |pointer: &str| value.pointer(pointer).and_then(Value::as_str);
+    Ok(PullRequest {
+        number: value
+            .get("number")
+            .and_then(Value::as_u64)
+            .context("pull request is missing a number")?,
+        title: str_at("/title").unwrap_or_default().to_string(),
+        body: str_at("/body").unwrap_or_default().to_string(),
+        state: if value.get("merged").and_then(Value::as_bool) == Some(true) {
+            PullRequestState::Merged
+        } else if str_at("/state") == Some("open") {
+            PullRequestState::Open
+        } else {
+            PullRequestState::Closed
+        },
+        html_url: str_at("/html_url")
+            .context("pull request is missing html_url")?
+            .to_string(),
+        author: str_at("/user/login").unwrap_or("ghost").to_string(),
+        author_name: str_at("/user/full_name")
+            .filter(|name| !name.is_empty())
+            .map(str::to_string),
+        author_url: str_at("/user/html_url").map(str::to_string),
+        head_ref: str_at("/head/ref").unwrap_or_default().to_string(),
+        base_ref: str_at("/base/ref").unwrap_or_default().to_string(),
+        author_npub: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pull_request_from_json_detects_merged() -> Result<()> {
+        let pull_request = pull_request_from_json(&serde_json::json!({
+            "number": 3,
+            "title": "docs",
+            "body": "",
+            "state": "closed",
+            "merged": true,
+            "html_url": "https://codeberg.org/o/r/pulls/3",
+            "user": { "login": "bob", "full_name": "", "html_url": "https://codeberg.org/bob" },
+            "head": { "ref": "docs" },
+            "base": { "ref": "main" },
+        }))?;
+        assert_eq!(pull_request.state, PullRequestState::Merged);
+        assert_eq!(pull_request.author_name, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! fallbacks for patches that libgit2 won't apply as they are, eg. ones
+//! generated on windows or with a different `core.autocrlf`
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use git2::{ApplyOptions, AttrCheckFlags, AttrValue, Diff, Oid, Patch, Repository, Tree};
+
+/// whether `path` in `tree` uses CRLF line endings. new files follow the `eol`
+/// attribute. `None` for binary files, which are left alone
+fn uses_crlf(git_repo: &Repository, tree: &Tree, path: &str) -> Option<bool> {
+    let attr = |name: &str| {
+        AttrValue::from_string(
+            git_repo
+                .get_attr(Path::new(path), name, AttrCheckFlags::FILE_THEN_INDEX)
+                .ok()
+                .flatten(),
+        )
+    };
+    if matches!(attr("binary"), AttrValue::True) || matches!(attr("text"), AttrValue::False) {
+        return None;
+    }
+    if let Ok(entry) = tree.get_path(Path::new(path)) {
+        let blob = git_repo.find_blob(entry.id()).ok()?;
+        if blob.is_binary() {
+            return None;
+        }
+        return Some(blob.content().windows(2).any(|w| w == b"\r\n"));
+    }
+    Some(matches!(attr("eol"), AttrValue::String("crlf")))
+}
+
+/// `content` with the hunk lines of each file using the line endings of the
+/// version in `tree`
+pub fn normalize_line_endings(git_repo: &Repository, tree: &Tree, content: &str) -> String {
+    let mut normalized = String::with_capacity(content.len());
+    // `None` before the first file and for binary files
+    let mut crlf = None;
+    let mut in_hunk = false;
+    for line in content.split_inclusive('\n') {
+        let bare = line.trim_end_matches('\n').trim_end_matches('\r');
+        if let Some(paths) = bare.strip_prefix("diff --git ") {
+            crlf = paths
+                .split_once(" b/")
+                .and_then(|(_, path)| uses_crlf(git_repo, tree, path));
+            in_hunk = false;
+        } else if bare.starts_with("@@") {
+            in_hunk = true;
+        }
+        let Some(crlf) = crlf else {
+            normalized.push_str(line);
+            continue;
+        };
+        normalized.push_str(bare);
+        if line.ends_with('\n') {
+            let is_hunk_line = bare.is_empty() || bare.starts_with([' ', '+', '-']);
+            normalized.push_str(if crlf && in_hunk && is_hunk_line {
+                "\r\n"
+            } else {
+                "\n"
+            });
+        }
+    }
+    normalized
+}
+
+/// the first file, and hunk within it, of `diff` that doesn't apply to `tree`,
+/// eg. `src/main.rs at @@ -10,6 +10,7 @@`
+pub fn describe_apply_failure(git_repo: &Repository, tree: &Tree, diff: &Diff) -> Option<String> {
+    for (index, delta) in diff.deltas().enumerate() {
+        let path = delta.new_file().path().or(delta.old_file().path())?;
+        // apply only this file, and only its first `hunks` hunks if set
+        let applies = |hunks: Option<usize>| {
+            let mut options = ApplyOptions::new();
+            let mut seen = 0;
+            options.delta_callback(|d| {
+                d.is_some_and(|d| d.new_file().path().or(d.old_file().path()) == Some(path))
+            });
+            options.hunk_callback(|_| {
+                seen += 1;
+                !hunks.is_some_and(|hunks| seen > hunks)
+            });
+            git_repo
+                .apply_to_tree(tree, diff, Some(&mut options))
+                .is_ok()
+        };
+        if applies(None) {
+            continue;
+        }
+        let patch = Patch::from_diff(diff, index).ok().flatten();
+        let header = patch.as_ref().and_then(|patch| {
+            let hunk = (1..=patch.num_hunks()).find(|hunks| !applies(Some(*hunks)))?;
+            let (hunk, _) = patch.hunk(hunk - 1).ok()?;
+            Some(String::from_utf8_lossy(hunk.header()).trim().to_string())
+        });
+        return Some(match header {
+            Some(header) => format!("{} at {header}", path.display()),
+            None => path.display().to_string(),
+        });
+    }
+    None
+}
+
+/// apply `content` to `tree` with `git apply --ignore-whitespace` using a
+/// temporary index, so the repository's index and working tree are untouched.
+/// returns the new tree
+pub fn apply_ignoring_whitespace(git_repo: &Repository, tree: &Tree, content: &str) -> Result<Oid> {
+    let index_path = git_repo
+        .path()
+        .join(format!("ngit-apply-index-{}", std::process::id()));
+    let git = |args: &[&str], stdin: Option<&str>| -> Result<String> {
+        let mut child = Command::new("git")
+            .env("GIT_DIR", git_repo.path())
+            .env("GIT_INDEX_FILE", &index_path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run git")?;
+        if let Some(input) = stdin {
+            child
+                .stdin
+                .take()
+                .context("failed to write to git")?
+                .write_all(input.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let result = git(&["read-tree", &tree.id().to_string()], None)
+        .and_then(|_| {
+            git(
+                &["apply", "--cached", "--ignore-whitespace", "-"],
+                Some(content),
+            )
+        })
+        .and_then(|_| git(&["write-tree"], None));
+    let _ = std::fs::remove_file(&index_path);
+    Ok(Oid::from_str(&result?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    #[test]
+    fn crlf_patch_normalized_to_lf_file_and_failing_hunk_named() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        test_repo.populate()?;
+        fs::write(test_repo.dir.join("t3.md"), "one\ntwo\nthree\n")?;
+        test_repo.stage_and_commit("add t3.md")?;
+        let tree = test_repo.git_repo.head()?.peel_to_tree()?;
+
+        // as diffed from a checkout with CRLF line endings
+        let patch = "diff --git a/t3.md b/t3.md\n--- a/t3.md\n+++ b/t3.md\n@@ -1,3 +1,3 @@\n one\r\n-two\r\n+2\r\n three\r\n";
+        let diff = Diff::from_buffer(patch.as_bytes())?;
+        assert!(
+            test_repo
+                .git_repo
+                .apply_to_tree(&tree, &diff, None)
+                .is_err()
+        );
+        assert_eq!(
+            describe_apply_failure(&test_repo.git_repo, &tree, &diff),
+            Some("t3.md at @@ -1,3 +1,3 @@".to_string())
+        );
+
+        let normalized = normalize_line_endings(&test_repo.git_repo, &tree, patch);
+        assert!(!normalized.contains('\r'));
+        let diff = Diff::from_buffer(normalized.as_bytes())?;
+        assert!(test_repo.git_repo.apply_to_tree(&tree, &diff, None).is_ok());
+        Ok(())
+    }
+}
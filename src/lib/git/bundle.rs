@@ -0,0 +1,120 @@
+use std::{path::Path, process::Command};
+
+use anyhow::{Context, Result, bail};
+
+use super::{Repo, RepoActions};
+use crate::{
+    blossom::sha256_hex,
+    download::{download_resumable, partial_download_dir},
+};
+
+/// create a bundle of all branches and tags. uses the git cli as libgit2
+/// doesnt support bundles.
+pub fn create_bundle(git_repo: &Repo, bundle_path: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(git_repo.get_path()?)
+        .args(["bundle", "create"])
+        .arg(bundle_path)
+        .args(["--branches", "--tags"])
+        .output()
+        .context("failed to run git bundle create")?;
+    if !output.status.success() {
+        bail!(
+            "git bundle create failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// fetch objects from a bundle file. its branches and tags are stored under
+/// `refs/nostr-bundle/` so subsequent fetches from git servers only download
+/// what is missing.
+pub fn fetch_from_bundle(git_repo: &Repo, bundle_path: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(git_repo.get_path()?)
+        .args(["fetch", "--quiet", "--no-write-fetch-head"])
+        .arg(bundle_path)
+        .args([
+            "+refs/heads/*:refs/nostr-bundle/heads/*",
+            "+refs/tags/*:refs/nostr-bundle/tags/*",
+        ])
+        .output()
+        .context("failed to run git fetch from bundle")?;
+    if !output.status.success() {
+        bail!(
+            "git fetch from bundle failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// download bundle from http(s) uri or copy from a local path. http downloads
+/// resume from data left in `partial_dir` by an interrupted attempt.
+pub async fn download_bundle(uri: &str, destination: &Path, partial_dir: &Path) -> Result<()> {
+    if uri.starts_with("https://") || uri.starts_with("http://") {
+        let bundle = download_resumable(uri, partial_dir)
+            .await
+            .context(format!("failed to download bundle from {uri}"))?;
+        // blossom urls are the sha256 hash of the blob with an optional extension
+        if let Some(hash) = uri
+            .rsplit('/')
+            .next()
+            .and_then(|s| s.split('.').next())
+            .filter(|s| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            if sha256_hex(&bundle) != hash.to_lowercase() {
+                bail!("bundle downloaded from {uri} does not match its hash");
+            }
+        }
+        std::fs::write(destination, bundle).context("failed to write bundle to disk")?;
+    } else {
+        std::fs::copy(uri.strip_prefix("file://").unwrap_or(uri), destination)
+            .context(format!("failed to copy bundle from {uri}"))?;
+    }
+    Ok(())
+}
+
+/// download a bundle and fetch its objects into `git_repo`
+pub async fn fetch_from_bundle_uri(git_repo: &Repo, uri: &str) -> Result<()> {
+    let bundle_path = git_repo.git_repo.path().join("nostr-bundle.bundle");
+    download_bundle(
+        uri,
+        &bundle_path,
+        &partial_download_dir(git_repo.git_repo.path()),
+    )
+    .await?;
+    let res = fetch_from_bundle(git_repo, &bundle_path);
+    let _ = std::fs::remove_file(&bundle_path);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    #[test]
+    fn fetch_from_bundle_made_with_create_bundle_adds_commits_and_refs() -> Result<()> {
+        let source_repo = GitTestRepo::default();
+        let tip = source_repo.populate()?;
+        let bundle_path = source_repo.dir.join("test.bundle");
+        create_bundle(&Repo::from_path(&source_repo.dir)?, &bundle_path)?;
+
+        let destination_repo = GitTestRepo::default();
+        let destination = Repo::from_path(&destination_repo.dir)?;
+        fetch_from_bundle(&destination, &bundle_path)?;
+
+        assert!(destination.does_commit_exist(&tip.to_string())?);
+        assert_eq!(
+            destination
+                .git_repo
+                .find_reference("refs/nostr-bundle/heads/main")?
+                .target(),
+            Some(tip),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+
+/// username and password for a http(s) git server, sourced via
+/// `git credential` so configured credential helpers are used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitCredential {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl GitCredential {
+    /// ask credential helpers for credentials, falling back to git's own
+    /// prompt if none are configured
+    pub fn fill(url: &str, username: Option<&str>) -> Result<Self> {
+        let mut input = format!("url={url}\n");
+        if let Some(username) = username {
+            input.push_str(&format!("username={username}\n"));
+        }
+        let output = run_git_credential("fill", &input)?;
+        parse_credential_output(url, &output)
+    }
+
+    /// tell credential helpers the credential was accepted so it gets stored
+    pub fn approve(&self) -> Result<()> {
+        run_git_credential("approve", &self.to_input()).map(|_| ())
+    }
+
+    /// tell credential helpers the credential was rejected so it gets erased
+    pub fn reject(&self) -> Result<()> {
+        run_git_credential("reject", &self.to_input()).map(|_| ())
+    }
+
+    fn to_input(&self) -> String {
+        format!(
+            "url={}\nusername={}\npassword={}\n",
+            self.url, self.username, self.password
+        )
+    }
+}
+
+fn run_git_credential(action: &str, input: &str) -> Result<String> {
+    let mut child = Command::new("git")
+        .args(["credential", action])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context(format!("failed to run git credential {action}"))?;
+    child
+        .stdin
+        .take()
+        .context("failed to open stdin of git credential")?
+        // a blank line marks the end of the input
+        .write_all(format!("{input}\n").as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("git credential {action} failed");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn parse_credential_output(url: &str, output: &str) -> Result<GitCredential> {
+    let mut username = None;
+    let mut password = None;
+    for line in output.lines() {
+        if let Some(value) = line.strip_prefix("username=") {
+            username = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("password=") {
+            password = Some(value.to_string());
+        }
+    }
+    Ok(GitCredential {
+        url: url.to_string(),
+        username: username.context("git credential fill returned no username")?,
+        password: password.context("git credential fill returned no password")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_credential_output_extracts_username_and_password() -> Result<()> {
+        assert_eq!(
+            parse_credential_output(
+                "https://example.com/repo.git",
+                "protocol=https\nhost=example.com\nusername=alice\npassword=secret\n",
+            )?,
+            GitCredential {
+                url: "https://example.com/repo.git".to_string(),
+                username: "alice".to_string(),
+                password: "secret".to_string(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_credential_output_errors_without_password() {
+        assert!(parse_credential_output("https://example.com", "username=alice\n").is_err());
+    }
+}
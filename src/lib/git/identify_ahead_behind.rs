@@ -1,13 +1,13 @@
 use anyhow::{Context, Result};
 use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
 
-use super::{Repo, RepoActions};
+use super::RepoActions;
 
 /**
  * returns `(from_branch,to_branch,ahead,behind)`
  */
 pub fn identify_ahead_behind(
-    git_repo: &Repo,
+    git_repo: &impl RepoActions,
     from_branch: &Option<String>,
     to_branch: &Option<String>,
 ) -> Result<(String, String, Vec<Sha1Hash>, Vec<Sha1Hash>)> {
@@ -69,7 +69,20 @@ mod tests {
     use test_utils::git::GitTestRepo;
 
     use super::*;
-    use crate::git::oid_to_sha1;
+    use crate::git::{Repo, memory::MemoryRepo, oid_to_sha1};
+
+    #[test]
+    fn when_from_branch_is_unrelated_return_error() {
+        let git_repo = MemoryRepo::default();
+        git_repo.commit("main", "initial commit");
+        git_repo.commit("orphan", "unrelated commit");
+        assert_eq!(
+            identify_ahead_behind(&git_repo, &None, &None)
+                .unwrap_err()
+                .to_string(),
+            "'orphan' is not branched from 'main'",
+        );
+    }
 
     #[test]
     fn when_from_branch_doesnt_exist_return_error() -> Result<()> {
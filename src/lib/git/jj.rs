@@ -0,0 +1,122 @@
+use std::process::Command;
+
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+
+use super::{Repo, RepoActions, sha1_to_oid};
+
+/// whether a jujutsu repository is colocated with the git repository
+pub fn is_jj_colocated(git_repo: &Repo) -> bool {
+    git_repo
+        .get_path()
+        .is_ok_and(|path| path.join(".jj").is_dir())
+}
+
+fn jj_log(git_repo: &Repo, commit: &Sha1Hash, template: &str) -> Option<String> {
+    let output = Command::new("jj")
+        .current_dir(git_repo.get_path().ok()?)
+        .args([
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "--color=never",
+            "-r",
+            &commit.to_string(),
+            "-T",
+            template,
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let value = stdout.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// jujutsu change id of `commit` and whether it is in the commit's
+/// `change-id` header, which jj writes so the commit id depends on it.
+/// otherwise it is looked up with `jj` in colocated repositories
+pub fn get_change_id(git_repo: &Repo, commit: &Sha1Hash) -> Option<(String, bool)> {
+    let header = git_repo
+        .git_repo
+        .find_commit(sha1_to_oid(commit).ok()?)
+        .ok()?
+        .header_field_bytes("change-id")
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .filter(|id| !id.is_empty());
+    if let Some(change_id) = header {
+        Some((change_id, true))
+    } else if is_jj_colocated(git_repo) {
+        jj_log(git_repo, commit, "change_id").map(|id| (id, false))
+    } else {
+        None
+    }
+}
+
+/// first jujutsu bookmark pointing at `commit`, in colocated repositories
+pub fn get_jj_bookmark(git_repo: &Repo, commit: &Sha1Hash) -> Option<String> {
+    if !is_jj_colocated(git_repo) {
+        return None;
+    }
+    jj_log(
+        git_repo,
+        commit,
+        r#"local_bookmarks.map(|b| b.name()).join("\n")"#,
+    )
+    .and_then(|bookmarks| bookmarks.lines().next().map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+    use crate::git::oid_to_sha1;
+
+    #[test]
+    fn change_id_read_from_commit_header() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let tip = test_repo.populate()?;
+        let git_repo = Repo::from_path(&test_repo.dir)?;
+        assert_eq!(get_change_id(&git_repo, &oid_to_sha1(&tip)), None);
+
+        let commit = test_repo.git_repo.find_commit(tip)?;
+        let buffer = String::from_utf8(
+            test_repo
+                .git_repo
+                .commit_create_buffer(
+                    &commit.author(),
+                    &commit.committer(),
+                    commit.message().unwrap_or_default(),
+                    &commit.tree()?,
+                    &commit
+                        .parents()
+                        .collect::<Vec<_>>()
+                        .iter()
+                        .collect::<Vec<_>>(),
+                )?
+                .to_vec(),
+        )?
+        .replacen(
+            "\n\n",
+            "\nchange-id zyxwvutsrqponmlkzyxwvutsrqponmlk\n\n",
+            1,
+        );
+        let with_change_id = test_repo
+            .git_repo
+            .odb()?
+            .write(git2::ObjectType::Commit, buffer.as_bytes())?;
+        assert_eq!(
+            get_change_id(&git_repo, &oid_to_sha1(&with_change_id)),
+            Some(("zyxwvutsrqponmlkzyxwvutsrqponmlk".to_string(), true))
+        );
+        Ok(())
+    }
+}
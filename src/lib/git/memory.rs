@@ -0,0 +1,409 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use git2::Oid;
+use nostr_sdk::hashes::{Hash, sha1::Hash as Sha1Hash};
+
+use super::RepoActions;
+
+#[derive(Clone)]
+struct MemoryCommit {
+    parents: Vec<Sha1Hash>,
+    message: String,
+    author: Vec<String>,
+}
+
+/// commit graph, branches and config held in memory so logic that only
+/// walks history can be tested without a repository on disk. operations on
+/// trees, the working tree or the object database error
+#[derive(Default)]
+pub struct MemoryRepo {
+    commits: RefCell<HashMap<Sha1Hash, MemoryCommit>>,
+    /// local branches and remote branches as `origin/<name>`
+    branches: RefCell<BTreeMap<String, Sha1Hash>>,
+    checked_out: RefCell<Option<String>>,
+    config: RefCell<HashMap<String, String>>,
+}
+
+impl MemoryRepo {
+    /// add a commit to the tip of `branch`, creating and checking it out
+    pub fn commit(&self, branch: &str, message: &str) -> Sha1Hash {
+        let parents: Vec<Sha1Hash> = self
+            .branches
+            .borrow()
+            .get(branch)
+            .copied()
+            .into_iter()
+            .collect();
+        self.commit_with_parents(branch, message, parents)
+    }
+
+    /// add a merge of `other` into `branch`
+    pub fn merge(&self, branch: &str, other: &str, message: &str) -> Result<Sha1Hash> {
+        let parents = vec![
+            self.get_tip_of_branch(branch)?,
+            self.get_tip_of_branch(other)?,
+        ];
+        Ok(self.commit_with_parents(branch, message, parents))
+    }
+
+    fn commit_with_parents(&self, branch: &str, message: &str, parents: Vec<Sha1Hash>) -> Sha1Hash {
+        let mut commits = self.commits.borrow_mut();
+        let id = Sha1Hash::hash(format!("{parents:?}{message}{}", commits.len()).as_bytes());
+        commits.insert(
+            id,
+            MemoryCommit {
+                parents,
+                message: message.to_string(),
+                author: vec![
+                    "Joe Bloggs".to_string(),
+                    "joe.bloggs@pm.me".to_string(),
+                    "0".to_string(),
+                    "0".to_string(),
+                ],
+            },
+        );
+        self.branches.borrow_mut().insert(branch.to_string(), id);
+        *self.checked_out.borrow_mut() = Some(branch.to_string());
+        id
+    }
+
+    fn find(&self, commit: &Sha1Hash) -> Result<MemoryCommit> {
+        self.commits
+            .borrow()
+            .get(commit)
+            .cloned()
+            .context(format!("could not find commit {commit}"))
+    }
+
+    fn reachable_from(&self, tip: &Sha1Hash) -> Result<HashSet<Sha1Hash>> {
+        let mut reachable = HashSet::new();
+        let mut to_visit = vec![*tip];
+        while let Some(commit) = to_visit.pop() {
+            if reachable.insert(commit) {
+                to_visit.extend(self.find(&commit)?.parents);
+            }
+        }
+        Ok(reachable)
+    }
+
+    /// equivalent to `git rev-list hide..tip`, newest first
+    fn commits_in(&self, tip: &Sha1Hash, hide: &Sha1Hash) -> Result<Vec<Sha1Hash>> {
+        let hidden = self.reachable_from(hide)?;
+        let mut commits = vec![];
+        let mut seen = HashSet::new();
+        let mut to_visit = vec![*tip];
+        while let Some(commit) = to_visit.pop() {
+            if hidden.contains(&commit) || !seen.insert(commit) {
+                continue;
+            }
+            commits.push(commit);
+            to_visit.extend(self.find(&commit)?.parents.into_iter().rev());
+        }
+        Ok(commits)
+    }
+}
+
+impl RepoActions for MemoryRepo {
+    fn get_path(&self) -> Result<&Path> {
+        bail!("an in memory repository has no path")
+    }
+
+    fn get_origin_url(&self) -> Result<String> {
+        self.config
+            .borrow()
+            .get("remote.origin.url")
+            .cloned()
+            .context("failed to find origin url")
+    }
+
+    fn get_remote_name_by_url(&self, url: &str) -> Result<String> {
+        self.config
+            .borrow()
+            .iter()
+            .find_map(|(item, value)| {
+                item.strip_prefix("remote.")?
+                    .strip_suffix(".url")
+                    .filter(|_| value == url)
+                    .map(str::to_string)
+            })
+            .context("could not find remote with matching url")
+    }
+
+    fn get_remote_branch_names(&self) -> Result<Vec<String>> {
+        Ok(self
+            .branches
+            .borrow()
+            .keys()
+            .filter(|name| name.starts_with("origin/"))
+            .cloned()
+            .collect())
+    }
+
+    fn get_local_branch_names(&self) -> Result<Vec<String>> {
+        Ok(self
+            .branches
+            .borrow()
+            .keys()
+            .filter(|name| !name.starts_with("origin/"))
+            .cloned()
+            .collect())
+    }
+
+    fn get_checked_out_branch_name(&self) -> Result<String> {
+        self.checked_out
+            .borrow()
+            .clone()
+            .context("no branch is checked out")
+    }
+
+    fn get_tip_of_branch(&self, branch_name: &str) -> Result<Sha1Hash> {
+        self.branches
+            .borrow()
+            .get(branch_name)
+            .copied()
+            .context(format!(
+                "failed to find local or remote branch {branch_name}"
+            ))
+    }
+
+    fn get_commit_or_tip_of_reference(&self, reference: &str) -> Result<Sha1Hash> {
+        if let Ok(commit) = reference.parse::<Sha1Hash>() {
+            self.find(&commit)?;
+            return Ok(commit);
+        }
+        self.get_tip_of_branch(
+            reference
+                .trim_start_matches("refs/heads/")
+                .trim_start_matches("refs/remotes/"),
+        )
+    }
+
+    fn get_root_commit(&self) -> Result<Sha1Hash> {
+        let mut commit = self.get_head_commit()?;
+        while let Some(parent) = self.find(&commit)?.parents.first() {
+            commit = *parent;
+        }
+        Ok(commit)
+    }
+
+    fn does_commit_exist(&self, commit: &str) -> Result<bool> {
+        Ok(self.commits.borrow().contains_key(&commit.parse()?))
+    }
+
+    fn get_head_commit(&self) -> Result<Sha1Hash> {
+        self.get_tip_of_branch(&self.get_checked_out_branch_name()?)
+    }
+
+    fn get_commit_parent(&self, commit: &Sha1Hash) -> Result<Sha1Hash> {
+        self.find(commit)?
+            .parents
+            .first()
+            .copied()
+            .context(format!("could not find parent of commit {commit}"))
+    }
+
+    fn get_commit_message(&self, commit: &Sha1Hash) -> Result<String> {
+        Ok(self.find(commit)?.message)
+    }
+
+    fn get_commit_message_summary(&self, commit: &Sha1Hash) -> Result<String> {
+        Ok(self
+            .find(commit)?
+            .message
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    fn get_commit_author(&self, commit: &Sha1Hash) -> Result<Vec<String>> {
+        Ok(self.find(commit)?.author)
+    }
+
+    fn get_commit_comitter(&self, commit: &Sha1Hash) -> Result<Vec<String>> {
+        Ok(self.find(commit)?.author)
+    }
+
+    fn get_commit_message_encoding(&self, commit: &Sha1Hash) -> Result<Option<String>> {
+        self.find(commit)?;
+        Ok(None)
+    }
+
+    fn get_commits_ahead_behind(
+        &self,
+        base_commit: &Sha1Hash,
+        latest_commit: &Sha1Hash,
+    ) -> Result<(Vec<Sha1Hash>, Vec<Sha1Hash>)> {
+        if self
+            .reachable_from(base_commit)?
+            .is_disjoint(&self.reachable_from(latest_commit)?)
+        {
+            bail!("{latest_commit} is not an ancestor of {base_commit}");
+        }
+        Ok((
+            self.commits_in(latest_commit, base_commit)?,
+            self.commits_in(base_commit, latest_commit)?,
+        ))
+    }
+
+    fn get_refs(&self, commit: &Sha1Hash) -> Result<Vec<String>> {
+        Ok(self
+            .branches
+            .borrow()
+            .iter()
+            .filter(|(_, tip)| *tip == commit)
+            .map(|(name, _)| name.clone())
+            .collect())
+    }
+
+    fn has_outstanding_changes(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn get_outstanding_changes_paths(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn stash_outstanding_changes(&self, _message: &str) -> Result<Option<Sha1Hash>> {
+        Ok(None)
+    }
+
+    fn pop_stash(&self, _stash: &Sha1Hash) -> Result<()> {
+        bail!("an in memory repository has no stashes")
+    }
+
+    fn make_patch_from_commit(
+        &self,
+        _commit: &Sha1Hash,
+        _series_count: &Option<(u64, u64)>,
+    ) -> Result<String> {
+        bail!("an in memory repository has no trees to make patches from")
+    }
+
+    fn extract_commit_pgp_signature(&self, commit: &Sha1Hash) -> Result<String> {
+        self.find(commit)?;
+        bail!("commit {commit} isn't signed")
+    }
+
+    fn checkout(&self, ref_name: &str) -> Result<Sha1Hash> {
+        let tip = self.get_tip_of_branch(ref_name)?;
+        *self.checked_out.borrow_mut() = Some(ref_name.to_string());
+        Ok(tip)
+    }
+
+    fn create_branch_at_commit(&self, branch_name: &str, commit: &str) -> Result<()> {
+        let commit = self.get_commit_or_tip_of_reference(commit)?;
+        self.branches
+            .borrow_mut()
+            .insert(branch_name.to_string(), commit);
+        Ok(())
+    }
+
+    fn apply_patch_chain(
+        &self,
+        _branch_name: &str,
+        _patch_and_ancestors: Vec<nostr::Event>,
+    ) -> Result<Vec<nostr::Event>> {
+        bail!("an in memory repository can't apply patches")
+    }
+
+    fn apply_patch_chain_onto_main(
+        &self,
+        _branch_name: &str,
+        _patch_and_ancestors: Vec<nostr::Event>,
+    ) -> Result<Vec<nostr::Event>> {
+        bail!("an in memory repository can't apply patches")
+    }
+
+    fn create_commit_from_patch(
+        &self,
+        _patch: &nostr::Event,
+        _parent_commit_id_override: Option<String>,
+    ) -> Result<Oid> {
+        bail!("an in memory repository can't apply patches")
+    }
+
+    fn create_commits_from_patches(
+        &self,
+        _patches: &[nostr::Event],
+        _onto: &Sha1Hash,
+    ) -> Result<Sha1Hash> {
+        bail!("an in memory repository can't apply patches")
+    }
+
+    fn rebase_commits_onto(&self, _commits: &[Sha1Hash], _onto: &Sha1Hash) -> Result<Sha1Hash> {
+        bail!("an in memory repository can't rebase commits")
+    }
+
+    fn parse_starting_commits(&self, starting_commits: &str) -> Result<Vec<Sha1Hash>> {
+        if let Some((base, tip)) = starting_commits.split_once("..") {
+            return self.commits_in(
+                &self.get_commit_or_tip_of_reference(tip)?,
+                &self.get_commit_or_tip_of_reference(base)?,
+            );
+        }
+        Ok(vec![self.get_commit_or_tip_of_reference(starting_commits)?])
+    }
+
+    fn ancestor_of(&self, decendant: &Sha1Hash, ancestor: &Sha1Hash) -> Result<bool> {
+        Ok(decendant != ancestor && self.reachable_from(decendant)?.contains(ancestor))
+    }
+
+    fn create_pack(&self, _tips: &[Sha1Hash], _exclude: &[Sha1Hash]) -> Result<Vec<u8>> {
+        bail!("an in memory repository has no objects to pack")
+    }
+
+    fn index_pack(&self, _pack: &[u8]) -> Result<()> {
+        bail!("an in memory repository has no object database")
+    }
+
+    fn get_git_config_item(&self, item: &str, _global: Option<bool>) -> Result<Option<String>> {
+        Ok(self.config.borrow().get(item).cloned())
+    }
+
+    fn save_git_config_item(&self, item: &str, value: &str, _global: bool) -> Result<()> {
+        self.config
+            .borrow_mut()
+            .insert(item.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove_git_config_item(&self, item: &str, _global: bool) -> Result<bool> {
+        Ok(self.config.borrow_mut().remove(item).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ahead_and_behind_across_a_merge() -> Result<()> {
+        let repo = MemoryRepo::default();
+        let base = repo.commit("main", "initial commit");
+        let behind = repo.commit("main", "on main");
+        repo.create_branch_at_commit("feature", &base.to_string())?;
+        let first = repo.commit("feature", "first");
+        let merge = repo.merge("feature", "main", "merge main")?;
+        let second = repo.commit("feature", "second");
+        let (ahead, behind_commits) = repo.get_commits_ahead_behind(&base, &second)?;
+        assert_eq!(ahead, vec![second, merge, first, behind]);
+        assert!(behind_commits.is_empty());
+        assert!(repo.ancestor_of(&second, &behind)?);
+        assert_eq!(repo.get_root_commit()?, base);
+        Ok(())
+    }
+
+    #[test]
+    fn unrelated_histories_are_not_ancestors() {
+        let repo = MemoryRepo::default();
+        let main = repo.commit("main", "initial commit");
+        let other = repo.commit("other", "unrelated");
+        assert!(repo.get_commits_ahead_behind(&main, &other).is_err());
+    }
+}
@@ -1,49 +1,544 @@
 use std::{
     env::current_dir,
+    io::Write,
     path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Result, bail};
-use git2::{DiffOptions, Oid, Revwalk};
+use git2::{DiffOptions, Oid};
 pub use identify_ahead_behind::identify_ahead_behind;
 use nostr_sdk::{
     Tags,
     hashes::{Hash, sha1::Hash as Sha1Hash},
 };
 
-use crate::git_events::{get_commit_id_from_patch, tag_value};
+pub use crate::error::{CommitIdMismatch, ParentCommitMissing, PatchConflicts};
+use crate::{
+    git_events::{get_commit_id_from_patch, tag_value},
+    interrupt,
+};
+pub mod apply;
+pub mod bundle;
+pub mod credential_helper;
 pub mod identify_ahead_behind;
+pub mod jj;
+#[cfg(any(test, feature = "memory-repo"))]
+pub mod memory;
 pub mod nostr_url;
+pub mod pack_cache;
+pub mod patch_progress;
+pub mod protocol_v2;
 pub mod utils;
 
 pub struct Repo {
     pub git_repo: git2::Repository,
+    ignore_whitespace: bool,
 }
 
 impl Repo {
     pub fn discover() -> Result<Self> {
         Ok(Self {
             git_repo: git2::Repository::discover(current_dir()?)?,
+            ignore_whitespace: false,
         })
     }
     pub fn from_path(path: &PathBuf) -> Result<Self> {
         Ok(Self {
             git_repo: git2::Repository::open(path)?,
+            ignore_whitespace: false,
         })
     }
+
+    /// retry patches that don't apply with `git apply --ignore-whitespace`, as
+    /// git config `nostr.apply-ignore-whitespace true` does
+    pub fn with_ignore_whitespace(mut self, ignore_whitespace: bool) -> Self {
+        self.ignore_whitespace = ignore_whitespace;
+        self
+    }
+
+    /// `-M` / `-C` equivalents set with `nostr.patch-renames` (`true`, `false`,
+    /// `copies` or `copies-harder`, default `true`) and
+    /// `nostr.patch-rename-threshold` (similarity percentage, default 50)
+    fn set_rename_detection_from_config(
+        &self,
+        options: &mut git2::EmailCreateOptions,
+    ) -> Result<()> {
+        let renames = self
+            .get_git_config_item("nostr.patch-renames", None)?
+            .unwrap_or_else(|| "true".to_string());
+        match renames.as_str() {
+            "false" => {
+                options.ignore_renames(true);
+                return Ok(());
+            }
+            "true" => {
+                options.diff_find_options().renames(true);
+            }
+            "copies" => {
+                options.diff_find_options().renames(true).copies(true);
+            }
+            "copies-harder" => {
+                options.diff_options().include_unmodified(true);
+                options
+                    .diff_find_options()
+                    .renames(true)
+                    .copies(true)
+                    .copies_from_unmodified(true)
+                    .remove_unmodified(true);
+            }
+            _ => bail!(
+                "invalid nostr.patch-renames value '{renames}'. expected true, false, copies or copies-harder"
+            ),
+        }
+        if let Some(threshold) = self.get_git_config_item("nostr.patch-rename-threshold", None)? {
+            let threshold: u16 = threshold
+                .trim_end_matches('%')
+                .parse()
+                .ok()
+                .filter(|t| *t <= 100)
+                .context(format!(
+                    "invalid nostr.patch-rename-threshold value '{threshold}'. expected a percentage"
+                ))?;
+            options
+                .diff_find_options()
+                .rename_threshold(threshold)
+                .copy_threshold(threshold);
+        }
+        Ok(())
+    }
+
+    /// returns patches applied. when `onto_main` the patches are applied on
+    /// top of main if the proposal's parent commit is missing
+    fn apply_patches_to_branch(
+        &self,
+        branch_name: &str,
+        patch_and_ancestors: Vec<nostr::Event>,
+        onto_main: bool,
+    ) -> Result<Vec<nostr::Event>> {
+        let branch_tip_result = self.get_tip_of_branch(branch_name);
+
+        // filter out existing ancestors in branch
+        let mut patches_to_apply: Vec<nostr::Event> = patch_and_ancestors
+            .into_iter()
+            .filter(|e| {
+                let commit_id = get_commit_id_from_patch(e).unwrap();
+                if let Ok(branch_tip) = branch_tip_result {
+                    !branch_tip.to_string().eq(&commit_id)
+                        && !self
+                            .ancestor_of(&branch_tip, &str_to_sha1(&commit_id).unwrap())
+                            .unwrap()
+                } else {
+                    true
+                }
+            })
+            .collect();
+
+        let parent_commit_id = tag_value(
+            if let Ok(last_patch) = patches_to_apply.last().context("no patches") {
+                last_patch
+            } else {
+                self.checkout(branch_name)
+                    .context("no patches and so failed to create a proposal branch")?;
+                return Ok(vec![]);
+            },
+            "parent-commit",
+        )?;
+
+        // when the proposal's base isn't available locally and the caller
+        // agreed, rebuild it on top of main, falling back to a 3-way merge for
+        // patches that don't apply
+        let mut rebased_tip = if self.does_commit_exist(&parent_commit_id)? {
+            None
+        } else if !onto_main {
+            return Err(ParentCommitMissing {
+                commit_id: parent_commit_id,
+            }
+            .into());
+        } else {
+            let (_, main_tip) = self.get_main_or_master_branch().context(format!(
+                "failed to find parent commit ({parent_commit_id}) or a main branch to apply it to"
+            ))?;
+            Some(main_tip.to_string())
+        };
+
+        let _restore_if_interrupted = {
+            let path = self.get_path()?.to_path_buf();
+            let previous_checkout = match self.get_checked_out_branch_name() {
+                Ok(name) if name != "HEAD" => Some(name),
+                _ => self.get_head_commit().ok().map(|commit| commit.to_string()),
+            };
+            let branch_name = branch_name.to_string();
+            let previous_tip = branch_tip_result.as_ref().ok().map(ToString::to_string);
+            interrupt::on_interrupt(move || {
+                restore_branch(
+                    &path,
+                    previous_checkout.as_deref(),
+                    &branch_name,
+                    previous_tip.as_deref(),
+                );
+            })
+        };
+
+        // checkout branch
+        self.create_branch_at_commit(
+            branch_name,
+            rebased_tip.as_ref().unwrap_or(&parent_commit_id),
+        )?;
+        self.checkout(branch_name)?;
+
+        // apply commits
+        patches_to_apply.reverse();
+
+        let mut progress = patch_progress::PatchProgress::new(patches_to_apply.len());
+        for patch in &patches_to_apply {
+            if interrupt::is_interrupted() {
+                bail!("interrupted");
+            }
+            progress.start(patch);
+            let commit_id = if let Some(tip) = &rebased_tip {
+                match self.create_commit_from_patch(patch, Some(tip.clone())) {
+                    Ok(oid) => oid.to_string(),
+                    Err(error) => {
+                        if let Some(conflicts) = error.downcast_ref::<PatchConflicts>() {
+                            self.checkout_patch_conflicts(patch, tip)?;
+                            bail!(
+                                "patch {} conflicts with '{branch_name}' in: {}. conflict markers have been left in the working tree. resolve them and commit",
+                                patch.id,
+                                conflicts.paths.join(", ")
+                            );
+                        }
+                        return Err(error.context(format!(
+                            "failed to apply patch {}. earlier patches have been applied to '{branch_name}'",
+                            patch.id
+                        )));
+                    }
+                }
+            } else {
+                // existing commits are checked against the patch rather than recreated
+                self.create_commit_from_patch(patch, None)?.to_string()
+            };
+            self.create_branch_at_commit(branch_name, &commit_id)?;
+            self.checkout(branch_name)?;
+            progress.applied(patch, &commit_id);
+            if rebased_tip.is_some() {
+                rebased_tip = Some(commit_id);
+            }
+        }
+        progress.finish(Some(branch_name));
+        Ok(patches_to_apply)
+    }
+
+    /// record the conflicts of a patch that didn't apply on top of
+    /// `parent_commit_id` in the repository's index and write conflict markers
+    /// to the working tree, as `git am -3` does, so `git status`, `git
+    /// mergetool` and `git add` work on them
+    fn checkout_patch_conflicts(&self, patch: &nostr::Event, parent_commit_id: &str) -> Result<()> {
+        let parent_tree = self
+            .git_repo
+            .find_commit(Oid::from_str(parent_commit_id)?)?
+            .tree()?;
+        let merged = three_way_apply(
+            &self.git_repo,
+            &parent_tree,
+            &git2::Diff::from_buffer(patch.content.as_bytes())?,
+        )?;
+        let mut index = self.git_repo.index()?;
+        index.clear()?;
+        for entry in merged.iter() {
+            // stage 0 entries. conflicted paths are added below at stages 1-3
+            if entry.flags & 0x3000 == 0 {
+                index.add(&entry)?;
+            }
+        }
+        for conflict in merged.conflicts()? {
+            let conflict = conflict?;
+            index.conflict_add(
+                conflict.ancestor.as_ref(),
+                conflict.our.as_ref(),
+                conflict.their.as_ref(),
+            )?;
+        }
+        index.write()?;
+        self.git_repo.checkout_index(
+            Some(&mut index),
+            Some(
+                git2::build::CheckoutBuilder::new()
+                    .force()
+                    .allow_conflicts(true)
+                    .conflict_style_merge(true),
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+/// `git am -3` style fallback: rebuild the tree the patch was created against
+/// from the preimage blobs it references, apply the patch there and merge the
+/// result into `tree`. the returned index may have conflicts.
+fn three_way_apply(
+    git_repo: &git2::Repository,
+    tree: &git2::Tree,
+    diff: &git2::Diff,
+) -> Result<git2::Index> {
+    let odb = git_repo.odb()?;
+    let mut preimage = git2::build::TreeUpdateBuilder::new();
+    for delta in diff.deltas() {
+        let old_file = delta.old_file();
+        if old_file.id().is_zero() {
+            continue;
+        }
+        let path = old_file.path().context("patch is missing a file path")?;
+        let abbreviated = old_file.id().to_string();
+        let blob_id = odb
+            .exists_prefix(
+                old_file.id(),
+                abbreviated.trim_end_matches('0').len().max(7),
+            )
+            .context(format!(
+                "original version of {} referenced in the patch isn't available locally",
+                path.display()
+            ))?;
+        preimage.upsert(path, blob_id, old_file.mode());
+    }
+    let base_tree = git_repo.find_tree(preimage.create_updated(git_repo, tree)?)?;
+    let patched_tree = git_repo.find_tree(
+        git_repo
+            .apply_to_tree(&base_tree, diff, None)
+            .context("patch doesn't apply to the file versions it references")?
+            .write_tree_to(git_repo)?,
+    )?;
+    git_repo
+        .merge_trees(&base_tree, tree, &patched_tree, None)
+        .context("failed to merge patch")
+}
+
+fn diff_paths(diff: &git2::Diff) -> Vec<String> {
+    let mut paths = vec![];
+    for delta in diff.deltas() {
+        for file in [delta.old_file(), delta.new_file()] {
+            if let Some(path) = file.path() {
+                let path = path.to_string_lossy().to_string();
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// files changed by a patch event
+pub fn get_paths_changed_in_patch(patch: &nostr::Event) -> Result<Vec<String>> {
+    Ok(diff_paths(&git2::Diff::from_buffer(
+        patch.content.as_bytes(),
+    )?))
+}
+
+/// `git diff --stat` style summary of the files a patch changes
+pub fn get_diffstat_of_patch(patch: &nostr::Event) -> Result<String> {
+    let stats = git2::Diff::from_buffer(patch.content.as_bytes())?.stats()?;
+    Ok(stats
+        .to_buf(git2::DiffStatsFormat::FULL, 72)?
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn conflicted_paths(index: &git2::Index) -> Result<Vec<String>> {
+    let mut paths = vec![];
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            paths.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+    Ok(paths)
+}
+
+/// whether `commit` is reachable from a local branch, tag or remote-tracking
+/// ref rather than only having been created from a patch
+fn is_reachable_from_local_refs(git_repo: &git2::Repository, commit: Oid) -> Result<bool> {
+    for reference in git_repo.references()? {
+        let reference = reference?;
+        if !reference.name().is_some_and(|name| {
+            ["refs/heads/", "refs/tags/", "refs/remotes/"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        }) {
+            continue;
+        }
+        let Ok(tip) = reference.peel_to_commit() else {
+            continue;
+        };
+        if tip.id() == commit || git_repo.graph_descendant_of(tip.id(), commit)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// whether applying `patch` can reproduce its commit. patches made without
+/// `--binary`, as libgit2 makes them, only say that binary files differ
+fn patch_is_self_contained(patch: &nostr::Event) -> bool {
+    !patch
+        .content
+        .lines()
+        .any(|line| line.starts_with("Binary files ") && line.ends_with(" differ"))
+}
+
+/// errors with `CommitIdMismatch` unless `commit` has the patch's
+/// `parent-commit` as its parent and applying the patch there reproduces its
+/// tree, so a patch can't claim an existing commit its content doesn't match
+fn check_patch_produces_commit(
+    git_repo: &git2::Repository,
+    patch: &nostr::Event,
+    commit: &git2::Commit,
+) -> Result<()> {
+    let mismatch = || CommitIdMismatch {
+        patch_id: patch.id.to_string(),
+        claimed: commit.id().to_string(),
+    };
+    let parent_tree = if commit.parent_count() == 0 {
+        git_repo.find_tree(git_repo.treebuilder(None)?.write()?)?
+    } else {
+        commit.parent(0)?.tree()?
+    };
+    if let Ok(parent_commit_id) = tag_value(patch, "parent-commit") {
+        if !commit
+            .parent_id(0)
+            .is_ok_and(|parent| parent.to_string() == parent_commit_id)
+        {
+            return Err(mismatch().into());
+        }
+    }
+    let tree_id = apply_to_tree_normalizing_line_endings(git_repo, &parent_tree, &patch.content)
+        .map_err(|_| mismatch())?;
+    if tree_id != commit.tree_id() {
+        return Err(mismatch().into());
+    }
+    Ok(())
+}
+
+/// apply `content` to `tree`, retrying with line endings matching the files it
+/// patches. returns the new tree
+fn apply_to_tree_normalizing_line_endings(
+    git_repo: &git2::Repository,
+    tree: &git2::Tree,
+    content: &str,
+) -> std::result::Result<Oid, git2::Error> {
+    let apply = |content: &str| {
+        git_repo
+            .apply_to_tree(tree, &git2::Diff::from_buffer(content.as_bytes())?, None)?
+            .write_tree_to(git_repo)
+    };
+    apply(content).or_else(|error| {
+        let normalized = apply::normalize_line_endings(git_repo, tree, content);
+        if normalized == content {
+            Err(error)
+        } else {
+            apply(&normalized).map_err(|_| error)
+        }
+    })
 }
 
 // pub type CommitId = [u8; 7];
 // pub type Sha1 = [u8; 20];
 
+/// check out `previous_checkout` again and reset `branch_name` to
+/// `previous_tip`, or delete it if it didn't exist. uses the git cli as the
+/// interrupted operation may still hold the repository open
+fn restore_branch(
+    path: &Path,
+    previous_checkout: Option<&str>,
+    branch_name: &str,
+    previous_tip: Option<&str>,
+) {
+    let git = |args: &[&str]| {
+        let _ = std::process::Command::new("git")
+            .current_dir(path)
+            .args(args)
+            .output();
+    };
+    if previous_checkout == Some(branch_name) {
+        if let Some(tip) = previous_tip {
+            git(&["reset", "--quiet", "--keep", tip]);
+        }
+        return;
+    }
+    if let Some(previous_checkout) = previous_checkout {
+        git(&["checkout", "--quiet", previous_checkout]);
+    }
+    if let Some(tip) = previous_tip {
+        git(&["branch", "--force", branch_name, tip]);
+    } else {
+        git(&["branch", "-D", branch_name]);
+    }
+}
+
 pub trait RepoActions {
     fn get_path(&self) -> Result<&Path>;
     fn get_origin_url(&self) -> Result<String>;
+    fn get_remote_name_by_url(&self, url: &str) -> Result<String>;
     fn get_remote_branch_names(&self) -> Result<Vec<String>>;
     fn get_local_branch_names(&self) -> Result<Vec<String>>;
-    fn get_origin_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)>;
-    fn get_local_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)>;
-    fn get_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)>;
+    fn get_origin_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)> {
+        let main_branch_name = {
+            let remote_branches = self
+                .get_remote_branch_names()
+                .context("failed to find any local branches")?;
+            if remote_branches.contains(&"origin/main".to_string()) {
+                "origin/main"
+            } else if remote_branches.contains(&"origin/master".to_string()) {
+                "origin/master"
+            } else {
+                bail!("no main or master branch locally in this git repository to initiate from",)
+            }
+        };
+
+        let tip = self
+            .get_tip_of_branch(main_branch_name)
+            .context(format!(
+                "branch {main_branch_name} was listed as a remote branch but failed to get its tip commit id",
+            ))?;
+
+        Ok((main_branch_name, tip))
+    }
+
+    fn get_local_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)> {
+        let main_branch_name = {
+            let local_branches = self
+                .get_local_branch_names()
+                .context("failed to find any local branches")?;
+            if local_branches.contains(&"main".to_string()) {
+                "main"
+            } else if local_branches.contains(&"master".to_string()) {
+                "master"
+            } else {
+                bail!("no main or master branch locally in this git repository to initiate from",)
+            }
+        };
+
+        let tip = self
+            .get_tip_of_branch(main_branch_name)
+            .context(format!(
+                "branch {main_branch_name} was listed as a local branch but failed to get its tip commit id",
+            ))?;
+
+        Ok((main_branch_name, tip))
+    }
+
+    fn get_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)> {
+        if let Ok(main_tuple) = self
+            .get_origin_main_or_master_branch()
+            .context("the default branches (main or master) do not exist")
+        {
+            Ok(main_tuple)
+        } else {
+            self.get_local_main_or_master_branch()
+                .context("the default branches (main or master) do not exist")
+        }
+    }
+
     fn get_checked_out_branch_name(&self) -> Result<String>;
     fn get_tip_of_branch(&self, branch_name: &str) -> Result<Sha1Hash>;
     fn get_commit_or_tip_of_reference(&self, reference: &str) -> Result<Sha1Hash>;
@@ -61,6 +556,8 @@ pub trait RepoActions {
     /// returns vector ["name", "email", "unixtime", "offset"]
     /// eg ["joe bloggs", "joe@pm.me", "12176","-300"]
     fn get_commit_comitter(&self, commit: &Sha1Hash) -> Result<Vec<String>>;
+    /// value of the commit's `encoding` header, if it has one
+    fn get_commit_message_encoding(&self, commit: &Sha1Hash) -> Result<Option<String>>;
     fn get_commits_ahead_behind(
         &self,
         base_commit: &Sha1Hash,
@@ -69,6 +566,12 @@ pub trait RepoActions {
     fn get_refs(&self, commit: &Sha1Hash) -> Result<Vec<String>>;
     // including (un)staged changes and (un)tracked files
     fn has_outstanding_changes(&self) -> Result<bool>;
+    /// paths of (un)staged changes and untracked files
+    fn get_outstanding_changes_paths(&self) -> Result<Vec<String>>;
+    /// stash (un)staged changes and untracked files. None if there were none
+    fn stash_outstanding_changes(&self, message: &str) -> Result<Option<Sha1Hash>>;
+    /// apply and drop a stash created with `stash_outstanding_changes`
+    fn pop_stash(&self, stash: &Sha1Hash) -> Result<()>;
     fn make_patch_from_commit(
         &self,
         commit: &Sha1Hash,
@@ -82,13 +585,35 @@ pub trait RepoActions {
         branch_name: &str,
         patch_and_ancestors: Vec<nostr::Event>,
     ) -> Result<Vec<nostr::Event>>;
+    /// like `apply_patch_chain` but if the proposal's parent commit isn't
+    /// available locally the patches are applied on top of main instead,
+    /// leaving conflict markers for any that don't apply
+    fn apply_patch_chain_onto_main(
+        &self,
+        branch_name: &str,
+        patch_and_ancestors: Vec<nostr::Event>,
+    ) -> Result<Vec<nostr::Event>>;
     fn create_commit_from_patch(
         &self,
         patch: &nostr::Event,
         parent_commit_id_override: Option<String>,
     ) -> Result<Oid>;
+    /// create commits for `patches` (oldest first) on top of `onto` without
+    /// updating any refs or the working tree. returns the new tip
+    fn create_commits_from_patches(
+        &self,
+        patches: &[nostr::Event],
+        onto: &Sha1Hash,
+    ) -> Result<Sha1Hash>;
+    /// cherry-pick `commits` (oldest first) onto `onto` in memory without
+    /// updating any refs or the working tree. errors with `PatchConflicts`
+    fn rebase_commits_onto(&self, commits: &[Sha1Hash], onto: &Sha1Hash) -> Result<Sha1Hash>;
     fn parse_starting_commits(&self, starting_commits: &str) -> Result<Vec<Sha1Hash>>;
     fn ancestor_of(&self, decendant: &Sha1Hash, ancestor: &Sha1Hash) -> Result<bool>;
+    /// packfile of objects reachable from `tips` but not from `exclude`
+    fn create_pack(&self, tips: &[Sha1Hash], exclude: &[Sha1Hash]) -> Result<Vec<u8>>;
+    /// write packfile into the object database and index it
+    fn index_pack(&self, pack: &[u8]) -> Result<()>;
     fn get_git_config_item(&self, item: &str, global: Option<bool>) -> Result<Option<String>>;
     fn save_git_config_item(&self, item: &str, value: &str, global: bool) -> Result<()>;
     fn remove_git_config_item(&self, item: &str, global: bool) -> Result<bool>;
@@ -112,62 +637,8 @@ impl RepoActions for Repo {
             .to_string())
     }
 
-    fn get_origin_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)> {
-        let main_branch_name = {
-            let remote_branches = self
-                .get_remote_branch_names()
-                .context("failed to find any local branches")?;
-            if remote_branches.contains(&"origin/main".to_string()) {
-                "origin/main"
-            } else if remote_branches.contains(&"origin/master".to_string()) {
-                "origin/master"
-            } else {
-                bail!("no main or master branch locally in this git repository to initiate from",)
-            }
-        };
-
-        let tip = self
-            .get_tip_of_branch(main_branch_name)
-            .context(format!(
-                "branch {main_branch_name} was listed as a remote branch but failed to get its tip commit id",
-            ))?;
-
-        Ok((main_branch_name, tip))
-    }
-
-    fn get_local_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)> {
-        let main_branch_name = {
-            let local_branches = self
-                .get_local_branch_names()
-                .context("failed to find any local branches")?;
-            if local_branches.contains(&"main".to_string()) {
-                "main"
-            } else if local_branches.contains(&"master".to_string()) {
-                "master"
-            } else {
-                bail!("no main or master branch locally in this git repository to initiate from",)
-            }
-        };
-
-        let tip = self
-            .get_tip_of_branch(main_branch_name)
-            .context(format!(
-                "branch {main_branch_name} was listed as a local branch but failed to get its tip commit id",
-            ))?;
-
-        Ok((main_branch_name, tip))
-    }
-
-    fn get_main_or_master_branch(&self) -> Result<(&str, Sha1Hash)> {
-        if let Ok(main_tuple) = self
-            .get_origin_main_or_master_branch()
-            .context("the default branches (main or master) do not exist")
-        {
-            Ok(main_tuple)
-        } else {
-            self.get_local_main_or_master_branch()
-                .context("the default branches (main or master) do not exist")
-        }
+    fn get_remote_name_by_url(&self, url: &str) -> Result<String> {
+        get_remote_name_by_url(&self.git_repo, url)
     }
 
     fn get_local_branch_names(&self) -> Result<Vec<String>> {
@@ -332,6 +803,15 @@ impl RepoActions for Repo {
         Ok(git_sig_to_tag_vec(&sig))
     }
 
+    fn get_commit_message_encoding(&self, commit: &Sha1Hash) -> Result<Option<String>> {
+        Ok(self
+            .git_repo
+            .find_commit(sha1_to_oid(commit)?)
+            .context(format!("could not find commit {commit}"))?
+            .message_encoding()
+            .map(|e| e.to_string()))
+    }
+
     fn get_refs(&self, commit: &Sha1Hash) -> Result<Vec<String>> {
         Ok(self
             .git_repo
@@ -367,6 +847,7 @@ impl RepoActions for Repo {
         if let Some((n, total)) = series_count {
             options.subject_prefix(format!("PATCH {n}/{total}"));
         }
+        self.set_rename_detection_from_config(&mut options)?;
         let patch = git2::Email::from_commit(&c, &mut options)
             .context(format!("failed to create patch from commit {}", &commit))?;
 
@@ -401,64 +882,106 @@ impl RepoActions for Repo {
         Ok(diff.deltas().len().gt(&0))
     }
 
+    fn get_outstanding_changes_paths(&self) -> Result<Vec<String>> {
+        let diff = self.git_repo.diff_tree_to_workdir_with_index(
+            Some(&self.git_repo.head()?.peel_to_tree()?),
+            Some(DiffOptions::new().include_untracked(true)),
+        )?;
+        Ok(diff_paths(&diff))
+    }
+
+    fn stash_outstanding_changes(&self, message: &str) -> Result<Option<Sha1Hash>> {
+        if !self.has_outstanding_changes()? {
+            return Ok(None);
+        }
+        // stashing needs a mutable repository
+        let mut git_repo = git2::Repository::open(self.git_repo.path())?;
+        let signature = git_repo
+            .signature()
+            .or_else(|_| git2::Signature::now("ngit", "ngit@localhost"))?;
+        let oid = git_repo
+            .stash_save(
+                &signature,
+                message,
+                Some(git2::StashFlags::INCLUDE_UNTRACKED),
+            )
+            .context("failed to stash changes")?;
+        Ok(Some(oid_to_sha1(&oid)))
+    }
+
+    fn pop_stash(&self, stash: &Sha1Hash) -> Result<()> {
+        let mut git_repo = git2::Repository::open(self.git_repo.path())?;
+        let stash_oid = sha1_to_oid(stash)?;
+        let mut index = None;
+        git_repo.stash_foreach(|i, _, oid| {
+            if oid.eq(&stash_oid) {
+                index = Some(i);
+                false
+            } else {
+                true
+            }
+        })?;
+        git_repo
+            .stash_pop(
+                index.context(format!("stash {stash} no longer exists"))?,
+                None,
+            )
+            .context("failed to apply stashed changes")?;
+        Ok(())
+    }
+
     fn get_commits_ahead_behind(
         &self,
         base_commit: &Sha1Hash,
         latest_commit: &Sha1Hash,
     ) -> Result<(Vec<Sha1Hash>, Vec<Sha1Hash>)> {
-        let mut ahead: Vec<Sha1Hash> = vec![];
-        let mut behind: Vec<Sha1Hash> = vec![];
-
-        let get_revwalk = |commit: &Sha1Hash| -> Result<Revwalk> {
+        let base_oid = sha1_to_oid(base_commit)?;
+        let latest_oid = sha1_to_oid(latest_commit)?;
+        // both use the commit-graph when available rather than walking each
+        // history
+        let Ok(merge_bases) = self.git_repo.merge_bases(base_oid, latest_oid) else {
+            bail!(format!(
+                "{} is not an ancestor of {}",
+                latest_commit, base_commit
+            ));
+        };
+        let (ahead_count, behind_count) = self
+            .git_repo
+            .graph_ahead_behind(latest_oid, base_oid)
+            .context("failed to count commits ahead and behind")?;
+
+        // equivalent to `git rev-list tip --not <merge bases>`. hiding the
+        // merge bases rather than the other tip stops the walk there instead
+        // of marking the whole shared history as uninteresting
+        let commits_in = |tip: Oid, count: usize| -> Result<Vec<Sha1Hash>> {
+            if count == 0 {
+                return Ok(vec![]);
+            }
             let mut revwalk = self
                 .git_repo
                 .revwalk()
                 .context("revwalk should be created from git repo")?;
+            revwalk.set_sorting(git2::Sort::TOPOLOGICAL)?;
             revwalk
-                .push(sha1_to_oid(commit)?)
+                .push(tip)
                 .context("revwalk should accept commit oid")?;
-            Ok(revwalk)
-        };
-
-        // scan through the base commit ancestory until a common ancestor is found
-        let most_recent_shared_commit = match get_revwalk(base_commit)
-            .context("failed to get revwalk for base_commit")?
-            .find(|base_res| {
-                let base_oid = base_res.as_ref().unwrap();
-
-                if get_revwalk(latest_commit)
-                    .unwrap()
-                    .any(|latest_res| base_oid.eq(latest_res.as_ref().unwrap()))
-                {
-                    true
-                } else {
-                    // add commits not found in latest ancestory to 'behind' vector
-                    behind.push(oid_to_sha1(base_oid));
-                    false
-                }
-            }) {
-            None => {
-                bail!(format!(
-                    "{} is not an ancestor of {}",
-                    latest_commit, base_commit
-                ));
-            }
-            Some(res) => res.context("revwalk failed to reveal commit")?,
+            for merge_base in merge_bases.iter() {
+                revwalk
+                    .hide(*merge_base)
+                    .context("revwalk should accept commit oid")?;
+            }
+            revwalk
+                .take(count)
+                .map(|oid| {
+                    Ok(oid_to_sha1(
+                        &oid.context("revwalk failed to reveal commit")?,
+                    ))
+                })
+                .collect()
         };
 
-        // scan through the latest commits until shared commit is reached
-        get_revwalk(latest_commit)
-            .context("failed to get revwalk for latest_commit")?
-            .any(|latest_res| {
-                let latest_oid = latest_res.as_ref().unwrap();
-                if latest_oid.eq(&most_recent_shared_commit) {
-                    true
-                } else {
-                    // add commits not found in base to 'ahead' vector
-                    ahead.push(oid_to_sha1(latest_oid));
-                    false
-                }
-            });
+        let ahead = commits_in(latest_oid, ahead_count)?;
+        let behind = commits_in(base_oid, behind_count)?;
         Ok((ahead, behind))
     }
 
@@ -504,57 +1027,14 @@ impl RepoActions for Repo {
         branch_name: &str,
         patch_and_ancestors: Vec<nostr::Event>,
     ) -> Result<Vec<nostr::Event>> {
-        let branch_tip_result = self.get_tip_of_branch(branch_name);
-
-        // filter out existing ancestors in branch
-        let mut patches_to_apply: Vec<nostr::Event> = patch_and_ancestors
-            .into_iter()
-            .filter(|e| {
-                let commit_id = get_commit_id_from_patch(e).unwrap();
-                if let Ok(branch_tip) = branch_tip_result {
-                    !branch_tip.to_string().eq(&commit_id)
-                        && !self
-                            .ancestor_of(&branch_tip, &str_to_sha1(&commit_id).unwrap())
-                            .unwrap()
-                } else {
-                    true
-                }
-            })
-            .collect();
-
-        let parent_commit_id = tag_value(
-            if let Ok(last_patch) = patches_to_apply.last().context("no patches") {
-                last_patch
-            } else {
-                self.checkout(branch_name)
-                    .context("no patches and so failed to create a proposal branch")?;
-                return Ok(vec![]);
-            },
-            "parent-commit",
-        )?;
-
-        // check patches can be applied
-        if !self.does_commit_exist(&parent_commit_id)? {
-            bail!("failed to find parent commit ({parent_commit_id}). run git pull and try again.")
-        }
-
-        // checkout branch
-        self.create_branch_at_commit(branch_name, &parent_commit_id)?;
-        self.checkout(branch_name)?;
-
-        // apply commits
-        patches_to_apply.reverse();
-
-        for patch in &patches_to_apply {
-            let commit_id = get_commit_id_from_patch(patch)?;
-            // only create new commits - otherwise make them the tip
-            if !self.does_commit_exist(&commit_id)? {
-                self.create_commit_from_patch(patch, None)?;
-            }
-            self.create_branch_at_commit(branch_name, &commit_id)?;
-            self.checkout(branch_name)?;
-        }
-        Ok(patches_to_apply)
+        self.apply_patches_to_branch(branch_name, patch_and_ancestors, false)
+    }
+    fn apply_patch_chain_onto_main(
+        &self,
+        branch_name: &str,
+        patch_and_ancestors: Vec<nostr::Event>,
+    ) -> Result<Vec<nostr::Event>> {
+        self.apply_patches_to_branch(branch_name, patch_and_ancestors, true)
     }
     fn create_commit_from_patch(
         &self,
@@ -564,7 +1044,16 @@ impl RepoActions for Repo {
         let commit_id = get_commit_id_from_patch(patch);
         if let Ok(commit_id) = &commit_id {
             if self.does_commit_exist(commit_id).unwrap_or(false) {
-                return Ok(Oid::from_str(commit_id)?);
+                let commit = self.git_repo.find_commit(Oid::from_str(commit_id)?)?;
+                if patch_is_self_contained(patch)
+                    && !is_reachable_from_local_refs(&self.git_repo, commit.id())?
+                {
+                    if let Err(error) = check_patch_produces_commit(&self.git_repo, patch, &commit)
+                    {
+                        eprintln!("warning: {error:#}. using the existing commit");
+                    }
+                }
+                return Ok(commit.id());
             }
         }
 
@@ -580,18 +1069,61 @@ impl RepoActions for Repo {
             .context("parrent commit doesnt exist")?;
         let parent_tree = parent_commit.tree()?;
 
-        // let mut apply_opts = git2::ApplyOptions::new();
-        // apply_opts.check(false);
         let mut existing_index = self.git_repo.index()?;
-        let mut index = self.git_repo.apply_to_tree(
+        let diff = git2::Diff::from_buffer(patch.content.as_bytes())?;
+        let tree_id = match apply_to_tree_normalizing_line_endings(
+            &self.git_repo,
             &parent_tree,
-            &git2::Diff::from_buffer(patch.content.as_bytes())?,
-            // Some(&mut apply_opts),
-            None,
-        )?;
-        let tree = self
-            .git_repo
-            .find_tree(index.write_tree_to(&self.git_repo)?)?;
+            &patch.content,
+        ) {
+            Ok(tree_id) => tree_id,
+            Err(error) => {
+                let ignore_whitespace = self.ignore_whitespace
+                    || self
+                        .get_git_config_item("nostr.apply-ignore-whitespace", None)?
+                        .is_some_and(|value| value.eq("true"));
+                match ignore_whitespace
+                    .then(|| {
+                        apply::apply_ignoring_whitespace(
+                            &self.git_repo,
+                            &parent_tree,
+                            &patch.content,
+                        )
+                    })
+                    .and_then(Result::ok)
+                {
+                    Some(tree_id) => tree_id,
+                    None => {
+                        let mut index = three_way_apply(&self.git_repo, &parent_tree, &diff)
+                            .with_context(|| {
+                                format!(
+                                    "patch doesn't apply{}: {error}{}",
+                                    apply::describe_apply_failure(
+                                        &self.git_repo,
+                                        &parent_tree,
+                                        &diff
+                                    )
+                                    .map(|location| format!(" to {location}"))
+                                    .unwrap_or_default(),
+                                    if ignore_whitespace {
+                                        ""
+                                    } else {
+                                        ". if it only differs in whitespace, retry with `git config nostr.apply-ignore-whitespace true`"
+                                    }
+                                )
+                            })?;
+                        if index.has_conflicts() {
+                            return Err(PatchConflicts {
+                                paths: conflicted_paths(&index)?,
+                            }
+                            .into());
+                        }
+                        index.write_tree_to(&self.git_repo)?
+                    }
+                }
+            }
+        };
+        let tree = self.git_repo.find_tree(tree_id)?;
 
         let pgp_sig = if let Ok(pgp_sig) = tag_value(patch, "commit-pgp-sig") {
             if pgp_sig.is_empty() {
@@ -603,18 +1135,12 @@ impl RepoActions for Repo {
             None
         };
 
-        let commit_buff = self.git_repo.commit_create_buffer(
-            &extract_sig_from_patch_tags(&patch.tags, "author")?,
-            &extract_sig_from_patch_tags(&patch.tags, "committer")?,
-            tag_value(patch, "description")?.as_str(),
-            &tree,
-            &[&parent_commit],
-        )?;
+        let commit_buff = commit_buffer_from_patch_tags(&patch.tags, &tree, &parent_commit)?;
 
         let mut applied_oid = self
             .git_repo
             .commit_signed(
-                commit_buff.as_str().unwrap(),
+                &commit_buff,
                 pgp_sig.unwrap_or(String::new()).as_str(),
                 None,
             )
@@ -649,17 +1175,57 @@ impl RepoActions for Repo {
                         .context("failed to amend commit to produce new oid")?;
                 }
                 if !applied_oid.to_string().eq(commit_id) {
-                    bail!(
-                        "when applied the patch commit id ({}) doesn't match the one specified in the event tag ({})",
-                        applied_oid.to_string(),
-                        get_commit_id_from_patch(patch)?,
-                    );
+                    self.git_repo.set_index(&mut existing_index)?;
+                    return Err(anyhow::Error::new(CommitIdMismatch {
+                        patch_id: patch.id.to_string(),
+                        claimed: commit_id.clone(),
+                    })
+                    .context(format!("when applied the patch commit id is {applied_oid}")));
                 }
             }
         }
-        self.git_repo.set_index(&mut existing_index)?;
-        Ok(applied_oid)
+        self.git_repo.set_index(&mut existing_index)?;
+        Ok(applied_oid)
+    }
+    fn create_commits_from_patches(
+        &self,
+        patches: &[nostr::Event],
+        onto: &Sha1Hash,
+    ) -> Result<Sha1Hash> {
+        let mut tip = *onto;
+        for patch in patches {
+            tip = oid_to_sha1(&self.create_commit_from_patch(patch, Some(tip.to_string()))?);
+        }
+        Ok(tip)
+    }
+
+    fn rebase_commits_onto(&self, commits: &[Sha1Hash], onto: &Sha1Hash) -> Result<Sha1Hash> {
+        let mut tip = self.git_repo.find_commit(sha1_to_oid(onto)?)?;
+        for commit in commits {
+            let cherry = self.git_repo.find_commit(sha1_to_oid(commit)?)?;
+            let mut index = self.git_repo.cherrypick_commit(&cherry, &tip, 0, None)?;
+            if index.has_conflicts() {
+                return Err(anyhow::Error::new(PatchConflicts {
+                    paths: conflicted_paths(&index)?,
+                })
+                .context(format!("commit {commit} conflicts")));
+            }
+            let tree = self
+                .git_repo
+                .find_tree(index.write_tree_to(&self.git_repo)?)?;
+            let oid = self.git_repo.commit(
+                None,
+                &cherry.author(),
+                &self.git_repo.signature().unwrap_or(cherry.committer()),
+                cherry.message_raw().unwrap_or_default(),
+                &tree,
+                &[&tip],
+            )?;
+            tip = self.git_repo.find_commit(oid)?;
+        }
+        Ok(oid_to_sha1(&tip.id()))
     }
+
     fn parse_starting_commits(&self, starting_commits: &str) -> Result<Vec<Sha1Hash>> {
         let revspec = self
             .git_repo
@@ -715,6 +1281,52 @@ impl RepoActions for Repo {
         }
     }
 
+    fn create_pack(&self, tips: &[Sha1Hash], exclude: &[Sha1Hash]) -> Result<Vec<u8>> {
+        let mut revwalk = self
+            .git_repo
+            .revwalk()
+            .context("revwalk should be created from git repo")?;
+        for tip in tips {
+            revwalk
+                .push(sha1_to_oid(tip)?)
+                .context("failed to add tip to revwalk")?;
+        }
+        for commit in exclude {
+            let oid = sha1_to_oid(commit)?;
+            // commits from a previous state may not be present locally
+            if self.git_repo.find_commit(oid).is_ok() {
+                revwalk
+                    .hide(oid)
+                    .context("failed to exclude commit from revwalk")?;
+            }
+        }
+        let mut pack_builder = self
+            .git_repo
+            .packbuilder()
+            .context("failed to create packbuilder")?;
+        pack_builder
+            .insert_walk(&mut revwalk)
+            .context("failed to add commits to packbuilder")?;
+        let mut buf = git2::Buf::new();
+        pack_builder
+            .write_buf(&mut buf)
+            .context("failed to write packfile")?;
+        Ok(buf.to_vec())
+    }
+
+    fn index_pack(&self, pack: &[u8]) -> Result<()> {
+        let odb = self
+            .git_repo
+            .odb()
+            .context("failed to open git object database")?;
+        let mut pack_writer = odb.packwriter().context("failed to create packwriter")?;
+        pack_writer
+            .write_all(pack)
+            .context("failed to write packfile to object database")?;
+        pack_writer.commit().context("failed to index packfile")?;
+        Ok(())
+    }
+
     /// setting global to None will suppliment local config with global items
     /// not in local
     fn get_git_config_item(&self, item: &str, global: Option<bool>) -> Result<Option<String>> {
@@ -806,6 +1418,26 @@ fn oid_to_u8_20_bytes(oid: &Oid) -> [u8; 20] {
     ]
 }
 
+pub fn get_remote_name_by_url(git_repo: &git2::Repository, url: &str) -> Result<String> {
+    let remotes = git_repo.remotes()?;
+    Ok(remotes
+        .iter()
+        .find(|r| {
+            if let Some(name) = r {
+                if let Some(remote_url) = git_repo.find_remote(name).unwrap().url() {
+                    url == remote_url
+                } else {
+                    false
+                }
+            } else {
+                false
+            }
+        })
+        .context("could not find remote with matching url")?
+        .context("remote with matching url must be named")?
+        .to_string())
+}
+
 pub fn oid_to_shorthand_string(oid: Oid) -> Result<String> {
     let binding = oid.to_string();
     let b = binding.as_bytes();
@@ -843,10 +1475,73 @@ fn git_sig_to_tag_vec(sig: &git2::Signature) -> Vec<String> {
         sig.name().unwrap_or("").to_string(),
         sig.email().unwrap_or("").to_string(),
         format!("{}", sig.when().seconds()),
-        format!("{}", sig.when().offset_minutes()),
+        // git records `-0000` for an unknown timezone
+        if sig.when().offset_minutes() == 0 && sig.when().sign() == '-' {
+            "-0".to_string()
+        } else {
+            format!("{}", sig.when().offset_minutes())
+        },
     ]
 }
 
+/// raw `author` or `committer` header value, eg. `joe <joe@pm.me> 12176 -0500`
+fn sig_header_from_patch_tags(tags: &Tags, tag_name: &str) -> Result<String> {
+    // validates the tag
+    let sig = extract_sig_from_patch_tags(tags, tag_name)?;
+    let offset = tags
+        .iter()
+        .find(|t| !t.as_slice().is_empty() && t.as_slice()[0].eq(tag_name))
+        .map(|t| t.as_slice()[4].clone())
+        .unwrap_or_default();
+    let minutes = sig.when().offset_minutes().abs();
+    Ok(format!(
+        "{} <{}> {} {}{:02}{:02}",
+        sig.name().unwrap_or(""),
+        sig.email().unwrap_or(""),
+        sig.when().seconds(),
+        if offset.starts_with('-') { '-' } else { '+' },
+        minutes / 60,
+        minutes % 60,
+    ))
+}
+
+/// unsigned commit object, written the same way git writes it, so the commit
+/// id matches the contributor's original
+fn commit_buffer_from_patch_tags(
+    tags: &Tags,
+    tree: &git2::Tree,
+    parent: &git2::Commit,
+) -> Result<String> {
+    let mut buff = format!(
+        "tree {}\nparent {}\nauthor {}\ncommitter {}\n",
+        tree.id(),
+        parent.id(),
+        sig_header_from_patch_tags(tags, "author")?,
+        sig_header_from_patch_tags(tags, "committer")?,
+    );
+    if let Some(encoding) = tags
+        .iter()
+        .find(|t| t.as_slice().len() > 1 && t.as_slice()[0].eq("commit-encoding"))
+    {
+        buff.push_str(&format!("encoding {}\n", encoding.as_slice()[1]));
+    }
+    if let Some(change_id) = tags
+        .iter()
+        .find(|t| t.as_slice().len() > 1 && t.as_slice()[0].eq("commit-change-id"))
+    {
+        buff.push_str(&format!("change-id {}\n", change_id.as_slice()[1]));
+    }
+    buff.push('\n');
+    buff.push_str(
+        &tags
+            .iter()
+            .find(|t| t.as_slice().len() > 1 && t.as_slice()[0].eq("description"))
+            .context("tag 'description' not present in patch")?
+            .as_slice()[1],
+    );
+    Ok(buff)
+}
+
 fn extract_sig_from_patch_tags<'a>(tags: &'a Tags, tag_name: &str) -> Result<git2::Signature<'a>> {
     let v = tags
         .iter()
@@ -1241,6 +1936,89 @@ mod tests {
         }
     }
 
+    mod rebase_commits_onto {
+        use super::*;
+
+        #[test]
+        fn rebases_commits_without_touching_refs() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            test_repo.create_branch("feature")?;
+            test_repo.checkout("feature")?;
+            fs::write(test_repo.dir.join("f1.md"), "some content")?;
+            let feature = test_repo.stage_and_commit("add f1.md")?;
+            test_repo.checkout("main")?;
+            fs::write(test_repo.dir.join("m1.md"), "some content")?;
+            let main_tip = test_repo.stage_and_commit("add m1.md")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            let rebased =
+                git_repo.rebase_commits_onto(&[oid_to_sha1(&feature)], &oid_to_sha1(&main_tip))?;
+            let rebased = test_repo.git_repo.find_commit(sha1_to_oid(&rebased)?)?;
+            assert_eq!(rebased.parent_id(0)?, main_tip);
+            assert!(rebased.tree()?.get_name("f1.md").is_some());
+            assert_eq!(
+                git_repo.get_tip_of_branch("feature")?,
+                oid_to_sha1(&feature)
+            );
+            assert_eq!(git_repo.get_tip_of_branch("main")?, oid_to_sha1(&main_tip));
+            Ok(())
+        }
+
+        #[test]
+        fn errors_with_conflicting_paths() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            test_repo.create_branch("feature")?;
+            test_repo.checkout("feature")?;
+            fs::write(test_repo.dir.join("t2.md"), "feature content")?;
+            let feature = test_repo.stage_and_commit("update t2.md on feature")?;
+            test_repo.checkout("main")?;
+            fs::write(test_repo.dir.join("t2.md"), "main content")?;
+            let main_tip = test_repo.stage_and_commit("update t2.md on main")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            let error = git_repo
+                .rebase_commits_onto(&[oid_to_sha1(&feature)], &oid_to_sha1(&main_tip))
+                .unwrap_err();
+            assert_eq!(
+                error.downcast_ref::<PatchConflicts>().unwrap().paths,
+                vec!["t2.md".to_string()]
+            );
+            Ok(())
+        }
+    }
+
+    mod stash_outstanding_changes {
+        use super::*;
+
+        #[test]
+        fn returns_none_when_clean() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            assert_eq!(git_repo.stash_outstanding_changes("test")?, None);
+            Ok(())
+        }
+
+        #[test]
+        fn stashes_and_pops_changes_and_untracked_files() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            fs::write(test_repo.dir.join("t1.md"), "changed")?;
+            fs::write(test_repo.dir.join("untracked.md"), "new")?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+
+            let stash = git_repo.stash_outstanding_changes("test")?.unwrap();
+            assert!(!git_repo.has_outstanding_changes()?);
+
+            git_repo.pop_stash(&stash)?;
+            assert_eq!(fs::read_to_string(test_repo.dir.join("t1.md"))?, "changed");
+            assert!(test_repo.dir.join("untracked.md").exists());
+            Ok(())
+        }
+    }
+
     mod make_patch_from_commit {
         use super::*;
         #[test]
@@ -1314,6 +2092,81 @@ mod tests {
             );
             Ok(())
         }
+
+        mod rename_detection {
+            use super::*;
+
+            fn rename_t1_and_copy_t2(test_repo: &GitTestRepo) -> Result<Oid> {
+                test_repo.populate()?;
+                fs::rename(
+                    test_repo.dir.join("t1.md"),
+                    test_repo.dir.join("t1-moved.md"),
+                )?;
+                fs::copy(test_repo.dir.join("t2.md"), test_repo.dir.join("t3.md"))?;
+                test_repo.stage_and_commit("move t1.md and copy t2.md")
+            }
+
+            #[test]
+            fn renames_detected_by_default() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let oid = rename_t1_and_copy_t2(&test_repo)?;
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                let patch = git_repo.make_patch_from_commit(&oid_to_sha1(&oid), &None)?;
+                assert!(patch.contains("rename from t1.md\nrename to t1-moved.md"));
+                assert!(!patch.contains("copy from"));
+                Ok(())
+            }
+
+            #[test]
+            fn renames_can_be_disabled() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let oid = rename_t1_and_copy_t2(&test_repo)?;
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                git_repo.save_git_config_item("nostr.patch-renames", "false", false)?;
+                let patch = git_repo.make_patch_from_commit(&oid_to_sha1(&oid), &None)?;
+                assert!(!patch.contains("rename from"));
+                assert!(patch.contains("deleted file mode"));
+                Ok(())
+            }
+
+            #[test]
+            fn copies_from_unmodified_files_detected_with_copies_harder() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let oid = rename_t1_and_copy_t2(&test_repo)?;
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                git_repo.save_git_config_item("nostr.patch-renames", "copies-harder", false)?;
+                let patch = git_repo.make_patch_from_commit(&oid_to_sha1(&oid), &None)?;
+                assert!(patch.contains("rename from t1.md"));
+                assert!(patch.contains("copy from t2.md\ncopy to t3.md"));
+                Ok(())
+            }
+        }
+    }
+
+    mod get_diffstat_of_patch {
+        use nostr::{EventBuilder, Keys, Kind};
+
+        use super::*;
+
+        #[test]
+        fn lists_files_and_totals() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let oid = test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let patch = EventBuilder::new(
+                Kind::GitPatch,
+                git_repo.make_patch_from_commit(&oid_to_sha1(&oid), &None)?,
+            )
+            .sign_with_keys(&Keys::generate())?;
+
+            let diffstat = get_diffstat_of_patch(&patch)?;
+            assert!(diffstat.contains("t2.md | 1 +"), "{diffstat}");
+            assert!(
+                diffstat.contains("1 file changed, 1 insertion(+)"),
+                "{diffstat}"
+            );
+            Ok(())
+        }
     }
 
     mod get_main_or_master_branch {
@@ -1526,6 +2379,44 @@ mod tests {
                 Ok(())
             }
 
+            #[test]
+            fn counts_ahead_and_behind_across_deep_history() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let mut tip = test_repo.populate()?;
+                let tree = test_repo.git_repo.find_commit(tip)?.tree()?;
+                let sig = test_utils::git::joe_signature();
+                let commit_on = |parent: Oid, message: &str| -> Result<Oid> {
+                    Ok(test_repo.git_repo.commit(
+                        None,
+                        &sig,
+                        &sig,
+                        message,
+                        &tree,
+                        &[&test_repo.git_repo.find_commit(parent)?],
+                    )?)
+                };
+                let mut branch_point = tip;
+                for i in 0..10_000 {
+                    tip = commit_on(tip, &format!("commit {i}"))?;
+                    if i == 10 {
+                        branch_point = tip;
+                    }
+                }
+                let feature_1 = commit_on(branch_point, "feature 1")?;
+                let feature_2 = commit_on(feature_1, "feature 2")?;
+
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                let (ahead, behind) = git_repo
+                    .get_commits_ahead_behind(&oid_to_sha1(&tip), &oid_to_sha1(&feature_2))?;
+                assert_eq!(
+                    ahead,
+                    vec![oid_to_sha1(&feature_2), oid_to_sha1(&feature_1)]
+                );
+                assert_eq!(behind.len(), 10_000 - 11);
+                assert_eq!(behind[0], oid_to_sha1(&tip));
+                Ok(())
+            }
+
             #[test]
             fn when_2_commit_ahead_and_2_commits_behind() -> Result<()> {
                 let test_repo = GitTestRepo::default();
@@ -1737,6 +2628,115 @@ mod tests {
             Ok(())
         }
 
+        mod patch_with_spoofed_commit_id {
+            use test_utils::TEST_KEY_1_KEYS;
+
+            use super::*;
+
+            fn with_commit_tag(patch: &nostr::Event, commit_id: &str) -> Result<nostr::Event> {
+                let tags = patch.tags.iter().map(|t| {
+                    if t.as_slice()[0].eq("commit") {
+                        nostr::Tag::parse(["commit", commit_id]).unwrap()
+                    } else {
+                        t.clone()
+                    }
+                });
+                Ok(nostr::EventBuilder::new(patch.kind, patch.content.clone())
+                    .tags(tags)
+                    .sign_with_keys(&TEST_KEY_1_KEYS)?)
+            }
+
+            async fn x1_patch_claiming(
+                test_repo: &GitTestRepo,
+                commit_id: impl Fn(&GitTestRepo) -> String,
+            ) -> Result<Result<Oid>> {
+                let source_repo = GitTestRepo::default();
+                source_repo.populate()?;
+                fs::write(source_repo.dir.join("x1.md"), "some content")?;
+                source_repo.stage_and_commit("add x1.md")?;
+                let patch = with_commit_tag(
+                    &generate_patch_from_head_commit(&source_repo).await?,
+                    &commit_id(&source_repo),
+                )?;
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                Ok(git_repo.create_commit_from_patch(&patch, None))
+            }
+
+            /// commit `file` on a branch that is then deleted, so the commit
+            /// exists but isn't reachable from any ref
+            fn commit_unreachable(
+                test_repo: &GitTestRepo,
+                file: &str,
+                content: &[u8],
+                message: &str,
+            ) -> Result<Oid> {
+                test_repo.create_branch("unreachable")?;
+                test_repo.checkout("unreachable")?;
+                fs::write(test_repo.dir.join(file), content)?;
+                let oid = test_repo.stage_and_commit(message)?;
+                test_repo.checkout("main")?;
+                test_repo
+                    .git_repo
+                    .find_branch("unreachable", git2::BranchType::Local)?
+                    .delete()?;
+                Ok(oid)
+            }
+
+            #[tokio::test]
+            async fn claiming_a_commit_reachable_from_a_branch_uses_it() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                let main_tip = test_repo.populate()?;
+                let applied = x1_patch_claiming(&test_repo, |_| main_tip.to_string()).await??;
+                assert_eq!(applied, main_tip);
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn claiming_an_unreachable_commit_it_doesnt_produce_warns_and_uses_it()
+            -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                test_repo.populate()?;
+                let unreachable = commit_unreachable(&test_repo, "y1.md", b"other", "add y1.md")?;
+                let applied = x1_patch_claiming(&test_repo, |_| unreachable.to_string()).await??;
+                assert_eq!(applied, unreachable);
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn claiming_a_different_new_commit_errors() -> Result<()> {
+                let test_repo = GitTestRepo::default();
+                test_repo.populate()?;
+                let error = x1_patch_claiming(&test_repo, |_| {
+                    "0123456789abcdef0123456789abcdef01234567".to_string()
+                })
+                .await?
+                .unwrap_err();
+                assert!(error.chain().any(|cause| cause.is::<CommitIdMismatch>()));
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn binary_patch_for_an_unreachable_commit_isnt_verified() -> Result<()> {
+                let binary = [0u8, 159, 146, 150, 0, 255];
+                let source_repo = GitTestRepo::default();
+                source_repo.populate()?;
+                fs::write(source_repo.dir.join("image.png"), binary)?;
+                let commit = source_repo.stage_and_commit("add image.png")?;
+                let patch = generate_patch_from_head_commit(&source_repo).await?;
+                assert!(!patch_is_self_contained(&patch));
+
+                let test_repo = GitTestRepo::default();
+                test_repo.populate()?;
+                assert_eq!(
+                    commit_unreachable(&test_repo, "image.png", &binary, "add image.png")?,
+                    commit,
+                );
+                let git_repo = Repo::from_path(&test_repo.dir)?;
+                assert_eq!(git_repo.create_commit_from_patch(&patch, None)?, commit);
+                Ok(())
+            }
+        }
+
         mod patch_created_as_commit_with_matching_id {
             use test_utils::git::joe_signature;
 
@@ -1821,6 +2821,51 @@ mod tests {
 
             // TODO: pgp signature
 
+            /// rewrite the head commit object with `f`, eg. to add headers that
+            /// git2 can't create
+            fn rewrite_head_commit(
+                test_repo: &GitTestRepo,
+                f: impl Fn(String) -> String,
+            ) -> Result<()> {
+                let head = test_repo.git_repo.head()?.peel_to_commit()?;
+                let odb = test_repo.git_repo.odb()?;
+                let raw = String::from_utf8(odb.read(head.id())?.data().to_vec())?;
+                let oid = odb.write(git2::ObjectType::Commit, f(raw).as_bytes())?;
+                test_repo
+                    .git_repo
+                    .head()?
+                    .set_target(oid, "rewrite head commit")?;
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn unknown_timezone_offset() -> Result<()> {
+                let source_repo = GitTestRepo::default();
+                source_repo.populate()?;
+                fs::write(source_repo.dir.join("x1.md"), "some content")?;
+                source_repo.stage_and_commit("add x1.md")?;
+                rewrite_head_commit(&source_repo, |raw| raw.replace(" +0000\n", " -0000\n"))?;
+
+                test_patch_applies_to_repository(
+                    generate_patch_from_head_commit(&source_repo).await?,
+                )
+            }
+
+            #[tokio::test]
+            async fn commit_message_encoding() -> Result<()> {
+                let source_repo = GitTestRepo::default();
+                source_repo.populate()?;
+                fs::write(source_repo.dir.join("x1.md"), "some content")?;
+                source_repo.stage_and_commit("add x1.md")?;
+                rewrite_head_commit(&source_repo, |raw| {
+                    raw.replacen("\n\n", "\nencoding ISO-8859-1\n\n", 1)
+                })?;
+
+                test_patch_applies_to_repository(
+                    generate_patch_from_head_commit(&source_repo).await?,
+                )
+            }
+
             #[tokio::test]
             async fn unique_author_and_commiter_details() -> Result<()> {
                 let source_repo = GitTestRepo::default();
@@ -2055,7 +3100,85 @@ mod tests {
                 }
             }
 
-            // TODO when_proposal_root_is_tip_ahead_of_main_and_doesnt_exist
+            mod when_branch_root_doesnt_exist {
+                use super::*;
+
+                #[tokio::test]
+                async fn errors_asking_to_pull() -> Result<()> {
+                    let (_, _, patch_events) = generate_test_repo_and_events().await?;
+                    let test_repo = GitTestRepo::default();
+                    test_repo.populate_minus_1()?;
+                    std::fs::write(test_repo.dir.join("m3.md"), "some content")?;
+                    test_repo.stage_and_commit("add m3.md")?;
+                    let git_repo = Repo::from_path(&test_repo.dir)?;
+                    let error = git_repo
+                        .apply_patch_chain(BRANCH_NAME, patch_events)
+                        .unwrap_err();
+                    assert!(error.downcast_ref::<ParentCommitMissing>().is_some());
+                    assert!(git_repo.get_tip_of_branch(BRANCH_NAME).is_err());
+                    Ok(())
+                }
+
+                #[tokio::test]
+                async fn patches_get_applied_on_top_of_main_when_asked() -> Result<()> {
+                    let (_, _, patch_events) = generate_test_repo_and_events().await?;
+                    let test_repo = GitTestRepo::default();
+                    test_repo.populate_minus_1()?;
+                    std::fs::write(test_repo.dir.join("m3.md"), "some content")?;
+                    let main_tip = test_repo.stage_and_commit("add m3.md")?;
+                    let git_repo = Repo::from_path(&test_repo.dir)?;
+                    let res = git_repo.apply_patch_chain_onto_main(BRANCH_NAME, patch_events)?;
+                    assert_eq!(res.len(), 3);
+                    assert_eq!(
+                        git_repo.get_checked_out_branch_name()?,
+                        BRANCH_NAME.to_string(),
+                    );
+                    assert!(test_repo.dir.join("f3.md").exists());
+                    assert!(test_repo.dir.join("m3.md").exists());
+                    let tip = test_repo.git_repo.head()?.peel_to_commit()?;
+                    assert_eq!(tip.parent(0)?.parent(0)?.parent_id(0)?, main_tip);
+                    Ok(())
+                }
+
+                #[tokio::test]
+                async fn conflicting_patch_leaves_markers_and_reports_files() -> Result<()> {
+                    let original_repo = GitTestRepo::default();
+                    original_repo.populate()?;
+                    std::fs::write(original_repo.dir.join("t2.md"), "proposed content")?;
+                    let oid = original_repo.stage_and_commit("update t2.md")?;
+                    let patch_events = generate_cover_letter_and_patch_events(
+                        None,
+                        &Repo::from_path(&original_repo.dir)?,
+                        &[oid_to_sha1(&oid)],
+                        &TEST_KEY_1_SIGNER,
+                        &RepoRef::try_from((generate_repo_ref_event(), None)).unwrap(),
+                        &None,
+                        &[],
+                    )
+                    .await?;
+
+                    let test_repo = GitTestRepo::default();
+                    test_repo.populate_minus_1()?;
+                    std::fs::write(test_repo.dir.join("t2.md"), "some content1")?;
+                    test_repo.stage_and_commit("add t2.md on a different history")?;
+                    std::fs::write(test_repo.dir.join("t2.md"), "local content")?;
+                    test_repo.stage_and_commit("update t2.md locally")?;
+                    let git_repo = Repo::from_path(&test_repo.dir)?;
+
+                    let error = git_repo
+                        .apply_patch_chain_onto_main(BRANCH_NAME, patch_events)
+                        .unwrap_err();
+                    assert!(format!("{error:?}").contains("t2.md"));
+                    assert!(
+                        std::fs::read_to_string(test_repo.dir.join("t2.md"))?.contains("<<<<<<<")
+                    );
+                    // recorded in the real index so `git status` shows the conflict
+                    let index = test_repo.git_repo.index()?;
+                    assert!(index.has_conflicts());
+                    assert_eq!(conflicted_paths(&index)?, vec!["t2.md".to_string()]);
+                    Ok(())
+                }
+            }
         }
 
         mod when_branch_and_first_commits_exists {
@@ -2376,4 +3499,42 @@ mod tests {
             Ok(())
         }
     }
+
+    mod create_pack {
+        use super::*;
+
+        #[test]
+        fn indexed_pack_makes_commits_available_in_other_repo() -> Result<()> {
+            let source_repo = GitTestRepo::default();
+            let tip = source_repo.populate()?;
+            let destination_repo = GitTestRepo::default();
+
+            let git_repo = Repo::from_path(&source_repo.dir)?;
+            let pack = git_repo.create_pack(&[oid_to_sha1(&tip)], &[])?;
+
+            let destination = Repo::from_path(&destination_repo.dir)?;
+            assert!(!destination.does_commit_exist(&tip.to_string())?);
+            destination.index_pack(&pack)?;
+            assert!(destination.does_commit_exist(&tip.to_string())?);
+            Ok(())
+        }
+
+        #[test]
+        fn excluded_commits_are_not_packed() -> Result<()> {
+            let source_repo = GitTestRepo::default();
+            let excluded = source_repo.populate()?;
+            std::fs::write(source_repo.dir.join("t3.md"), "some content")?;
+            let tip = source_repo.stage_and_commit("add t3.md")?;
+            let destination_repo = GitTestRepo::default();
+
+            let git_repo = Repo::from_path(&source_repo.dir)?;
+            let pack = git_repo.create_pack(&[oid_to_sha1(&tip)], &[oid_to_sha1(&excluded)])?;
+
+            let destination = Repo::from_path(&destination_repo.dir)?;
+            destination.index_pack(&pack)?;
+            assert!(destination.does_commit_exist(&tip.to_string())?);
+            assert!(!destination.does_commit_exist(&excluded.to_string())?);
+            Ok(())
+        }
+    }
 }
@@ -3,7 +3,7 @@ use std::{collections::HashMap, str::FromStr};
 
 use anyhow::{Context, Error, Result, anyhow, bail};
 use nostr::nips::{nip01::Coordinate, nip05};
-use nostr_sdk::{PublicKey, RelayUrl, ToBech32, Url};
+use nostr_sdk::{PublicKey, RelayUrl, Timestamp, ToBech32, Url};
 
 use super::{Repo, get_git_config_item, save_git_config_item};
 
@@ -106,10 +106,12 @@ impl NostrUrlDecoded {
         if !url.starts_with("nostr://") {
             bail!("nostr git url must start with nostr://");
         }
-        // process get url parameters if present
-        for (name, value) in Url::parse(url)?.query_pairs() {
+        // process get url parameters if present. parsed manually because
+        // coordinates such as `30617:<pubkey>:<identifier>` aren't valid hosts
+        let query = url.split_once('?').map_or("", |(_, query)| query);
+        for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
             if name.contains("relay") {
-                let mut decoded = urlencoding::decode(&value)
+                let mut decoded = urlencoding::decode(value)
                     .context("could not parse relays in nostr git url")?
                     .to_string();
                 if !decoded.starts_with("ws://") && !decoded.starts_with("wss://") {
@@ -119,7 +121,7 @@ impl NostrUrlDecoded {
                     RelayUrl::parse(&decoded).context("could not parse relays in nostr git url")?;
                 relays.push(url);
             } else if name == "protocol" {
-                protocol = match value.as_ref() {
+                protocol = match value {
                     "ssh" => Some(ServerProtocol::Ssh),
                     "https" => Some(ServerProtocol::Https),
                     "http" => Some(ServerProtocol::Http),
@@ -127,7 +129,7 @@ impl NostrUrlDecoded {
                     _ => None,
                 };
             } else if name == "user" {
-                user = Some(value.to_string());
+                user = Some(urlencoding::decode(value)?.to_string());
             }
         }
 
@@ -162,13 +164,23 @@ impl NostrUrlDecoded {
         }
         // extract naddr npub/<optional-relays>/identifer
         let part = parts.first().context(INCORRECT_NOSTR_URL_FORMAT_ERROR)?;
-        // naddr used
-        let coordinate = if let Ok(coordinate) = Coordinate::parse(part) {
-            if coordinate.kind.eq(&nostr_sdk::Kind::GitRepoAnnouncement) {
-                coordinate
-            } else {
+        if part.trim_start_matches("nostr:").starts_with("nevent1") {
+            bail!(
+                "nevent points to a specific event rather than a repository. use the repository naddr instead"
+            );
+        }
+        // naddr or <kind>:<pubkey>:<identifier> coordinate used
+        let coordinate = if let Some(mut coordinate) = parse_coordinate(part) {
+            if !coordinate.kind.eq(&nostr_sdk::Kind::GitRepoAnnouncement) {
                 bail!("naddr doesnt point to a git repository announcement");
             }
+            // relay hints from url parameters
+            for relay in relays {
+                if !coordinate.relays.contains(&relay) {
+                    coordinate.relays.push(relay);
+                }
+            }
+            coordinate
         // <npub|nip05_address>/<optional-relays>/identifer used
         } else {
             let npub_or_nip05 = part.to_owned();
@@ -191,35 +203,14 @@ impl NostrUrlDecoded {
             let public_key = match PublicKey::parse(npub_or_nip05) {
                 Ok(public_key) => public_key,
                 Err(_) => {
-                    nip05 = Some(npub_or_nip05.to_string());
-                    if let Ok(public_key) =
-                        resolve_nip05_from_git_config_cache(npub_or_nip05, git_repo)
-                    {
-                        public_key
-                    } else {
-                        let term = console::Term::stderr();
-                        let domain = {
-                            let s = npub_or_nip05.split('@').collect::<Vec<&str>>();
-                            if s.len() == 2 { s[1] } else { s[0] }
-                        };
-                        term.write_line(&format!("fetching pubic key info from {domain}..."))?;
-                        let res = nip05::profile(npub_or_nip05, None).await.context(format!(
-                            "failed to get nostr public key for {npub_or_nip05} from {domain}"
-                        ))?;
-                        term.clear_last_lines(1)?;
-                        nip05 = Some(npub_or_nip05.to_string());
-                        let _ = save_nip05_to_git_config_cache(
-                            npub_or_nip05,
-                            &res.public_key,
-                            git_repo,
-                        );
-                        if relays.is_empty() {
-                            for r in res.relays {
-                                relays.push(r);
-                            }
-                        }
-                        res.public_key
+                    // nip05 names are case insensitive
+                    let address = npub_or_nip05.to_lowercase();
+                    let (public_key, nip05_relays) = resolve_nip05(&address, git_repo).await?;
+                    if relays.is_empty() {
+                        relays = nip05_relays;
                     }
+                    nip05 = Some(address);
+                    public_key
                 }
             };
             Coordinate {
@@ -240,11 +231,67 @@ impl NostrUrlDecoded {
     }
 }
 
-fn resolve_nip05_from_git_config_cache(nip05: &str, git_repo: &Option<&Repo>) -> Result<PublicKey> {
-    if let Some(public_key) = load_nip_cache(git_repo)?.get(nip05) {
-        Ok(*public_key)
-    } else {
-        bail!("nip05 not stored in local git config cache")
+/// parse naddr, optionally prefixed with `nostr:` as in links copied from
+/// nostr clients, or a raw `<kind>:<pubkey>:<identifier>` coordinate
+fn parse_coordinate(s: &str) -> Option<Coordinate> {
+    let s = s.strip_prefix("nostr:").unwrap_or(s);
+    if let Some((kind, rest)) = s.split_once(':') {
+        let (public_key, identifier) = rest.split_once(':')?;
+        return Some(Coordinate {
+            kind: nostr_sdk::Kind::from(kind.parse::<u16>().ok()?),
+            public_key: PublicKey::parse(public_key).ok()?,
+            identifier: urlencoding::decode(identifier).ok()?.to_string(),
+            relays: vec![],
+        });
+    }
+    Coordinate::parse(s).ok()
+}
+
+/// how long a nip05 lookup is trusted before it is checked again
+static NIP05_CACHE_TTL_SECS: u64 = 60 * 60 * 24 * 7;
+
+/// resolve nip05 address using the git config cache, rechecking entries older
+/// than `NIP05_CACHE_TTL_SECS`. if the address now points to a different
+/// public key it errors rather than silently switching repositories.
+async fn resolve_nip05(
+    address: &str,
+    git_repo: &Option<&Repo>,
+) -> Result<(PublicKey, Vec<RelayUrl>)> {
+    let cached = load_nip_cache(git_repo)?.get(address).copied();
+    if let Some((public_key, verified_at)) = cached {
+        if Timestamp::now().as_u64().saturating_sub(verified_at) < NIP05_CACHE_TTL_SECS {
+            return Ok((public_key, vec![]));
+        }
+    }
+    let domain = address
+        .split_once('@')
+        .map_or(address, |(_, domain)| domain);
+    let term = console::Term::stderr();
+    term.write_line(&format!("fetching pubic key info from {domain}..."))?;
+    let res = nip05::profile(address, None).await;
+    term.clear_last_lines(1)?;
+    match res {
+        Ok(profile) => {
+            if let Some((cached_public_key, _)) = cached {
+                if cached_public_key != profile.public_key {
+                    bail!(
+                        "{address} now points to a different nostr public key than when it was last used. if this is expected, remove it from `nostr.nip05` in git config"
+                    );
+                }
+            }
+            let _ = save_nip05_to_git_config_cache(address, &profile.public_key, git_repo);
+            Ok((profile.public_key, profile.relays))
+        }
+        Err(error) => {
+            // stale cache is better than nothing if the domain is unreachable
+            if let Some((public_key, _)) = cached {
+                Ok((public_key, vec![]))
+            } else {
+                Err(error).context(format!(
+                    "failed to get nostr public key for {address} from {domain}"
+                ))
+            }
+        }
     }
 }
 
@@ -254,7 +301,7 @@ pub fn use_nip05_git_config_cache_to_find_nip05_from_public_key(
 ) -> Result<Option<String>> {
     let h = load_nip_cache(git_repo)?;
     Ok(h.iter()
-        .find_map(|(k, v)| if *v == *public_key { Some(k) } else { None })
+        .find_map(|(k, (v, _))| if *v == *public_key { Some(k) } else { None })
         .cloned())
 }
 
@@ -264,11 +311,16 @@ pub fn save_nip05_to_git_config_cache(
     git_repo: &Option<&Repo>,
 ) -> Result<()> {
     let mut h = load_nip_cache(git_repo)?;
-    h.insert(nip05.to_string(), *public_key);
+    h.insert(
+        nip05.to_lowercase(),
+        (*public_key, Timestamp::now().as_u64()),
+    );
 
     let s = h
         .into_iter()
-        .map(|(nip05, public_key)| format!("{nip05}:{}", public_key.to_hex()))
+        .map(|(nip05, (public_key, verified_at))| {
+            format!("{nip05}:{}:{verified_at}", public_key.to_hex())
+        })
         .collect::<Vec<String>>()
         .join(",");
 
@@ -276,19 +328,29 @@ pub fn save_nip05_to_git_config_cache(
         .context("could not save nip05 cache in git config")
 }
 
-fn load_nip_cache(git_repo: &Option<&Repo>) -> Result<HashMap<String, PublicKey>> {
-    let mut h = HashMap::new();
+/// nip05 address to public key and unix time it was last verified. entries
+/// saved by earlier versions have no timestamp so are treated as stale.
+fn load_nip_cache(git_repo: &Option<&Repo>) -> Result<HashMap<String, (PublicKey, u64)>> {
     let stored_value = get_git_config_item(git_repo, "nostr.nip05")?
         .context("no nip05s in local git config cache so retun empty cache")
         .unwrap_or_default();
+    Ok(parse_nip05_cache(&stored_value))
+}
+
+fn parse_nip05_cache(stored_value: &str) -> HashMap<String, (PublicKey, u64)> {
+    let mut h = HashMap::new();
     for pair in stored_value.split(',') {
-        if let Some((cached_nip05, pubkey)) = pair.split_once(':') {
+        if let Some((cached_nip05, value)) = pair.split_once(':') {
+            let (pubkey, verified_at) = value.split_once(':').unwrap_or((value, "0"));
             if let Ok(public_key) = PublicKey::parse(pubkey) {
-                h.insert(cached_nip05.to_string(), public_key);
+                h.insert(
+                    cached_nip05.to_string(),
+                    (public_key, verified_at.parse().unwrap_or(0)),
+                );
             }
         }
     }
-    Ok(h)
+    h
 }
 
 #[derive(Debug, PartialEq, Default)]
@@ -461,6 +523,9 @@ impl CloneUrl {
     pub fn protocol(&self) -> ServerProtocol {
         self.protocol.clone()
     }
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
 
     pub fn short_name(&self) -> String {
         let domain = self.domain();
@@ -1055,6 +1120,34 @@ mod tests {
         }
     }
 
+    mod parse_nip05_cache {
+        use super::*;
+
+        fn public_key() -> PublicKey {
+            PublicKey::parse("npub15qydau2hjma6ngxkl2cyar74wzyjshvl65za5k5rl69264ar2exs5cyejr")
+                .unwrap()
+        }
+
+        #[test]
+        fn reads_verified_at() {
+            let cache = parse_nip05_cache(&format!(
+                "dan@gitworkshop.dev:{}:1700000000",
+                public_key().to_hex()
+            ));
+            assert_eq!(
+                cache.get("dan@gitworkshop.dev"),
+                Some(&(public_key(), 1_700_000_000))
+            );
+        }
+
+        #[test]
+        fn entries_without_verified_at_are_stale() {
+            let cache =
+                parse_nip05_cache(&format!("dan@gitworkshop.dev:{}", public_key().to_hex()));
+            assert_eq!(cache.get("dan@gitworkshop.dev"), Some(&(public_key(), 0)));
+        }
+    }
+
     mod nostr_url_decoded_paramemters_from_str {
         use super::*;
 
@@ -1099,6 +1192,57 @@ mod tests {
             Ok(())
         }
 
+        #[tokio::test]
+        async fn from_naddr_with_nostr_prefix_and_relay_parameter() -> Result<()> {
+            let url = "nostr://nostr:naddr1qqzxuemfwsqs6amnwvaz7tmwdaejumr0dspzpgqgmmc409hm4xsdd74sf68a2uyf9pwel4g9mfdg8l5244t6x4jdqvzqqqrhnym0k2qj?relay=relay.damus.io".to_string();
+            let mut coordinate = get_model_coordinate(true);
+            coordinate
+                .relays
+                .push(RelayUrl::parse("wss://relay.damus.io").unwrap());
+            assert_eq!(
+                NostrUrlDecoded::parse_and_resolve(&url, &None).await?,
+                NostrUrlDecoded {
+                    original_string: url.clone(),
+                    coordinate,
+                    protocol: None,
+                    user: None,
+                    nip05: None,
+                },
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn from_coordinate_string() -> Result<()> {
+            let url = format!(
+                "nostr://30617:{}:ngit",
+                get_model_coordinate(false).public_key.to_hex()
+            );
+            assert_eq!(
+                NostrUrlDecoded::parse_and_resolve(&url, &None).await?,
+                NostrUrlDecoded {
+                    original_string: url.clone(),
+                    coordinate: get_model_coordinate(false),
+                    protocol: None,
+                    user: None,
+                    nip05: None,
+                },
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn from_nevent_errors() {
+            assert!(
+                NostrUrlDecoded::parse_and_resolve(
+                    "nostr://nevent1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p",
+                    &None
+                )
+                .await
+                .is_err()
+            );
+        }
+
         mod from_npub_slash_identifier {
             use super::*;
 
@@ -0,0 +1,285 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, bail};
+
+use super::{Repo, RepoActions};
+use crate::{blossom::sha256_hex, get_dirs, settings::settings};
+
+/// temporary refs used to name the tips in a cached bundle
+static CACHE_REF_PREFIX: &str = "refs/nostr-pack-cache/";
+
+/// bundles older than this are removed
+static MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+/// bundles of previously fetched data, shared by every working copy so
+/// cloning the same repository again doesn't redownload it
+pub fn pack_cache_dir(git_repo_path: &Path) -> Result<PathBuf> {
+    if settings().test_mode {
+        Ok(git_repo_path.join(".git/test-pack-cache"))
+    } else {
+        Ok(get_dirs()?.cache_dir().join("packs"))
+    }
+}
+
+/// cached bundle for fetching `oids` from `git_server_url`
+pub fn pack_cache_path(cache_dir: &Path, git_server_url: &str, oids: &[String]) -> PathBuf {
+    let mut oids = oids.to_vec();
+    oids.sort();
+    oids.dedup();
+    cache_dir.join(format!(
+        "{}.bundle",
+        sha256_hex(format!("{git_server_url} {}", oids.join(" ")).as_bytes())
+    ))
+}
+
+/// fetch objects from a bundle in `cache_dir` cached after fetching `oids`
+/// from `git_server_url`. returns false if there isn't one this repository
+/// has the history for.
+pub fn fetch_from_pack_cache(
+    git_repo: &Repo,
+    cache_dir: &Path,
+    git_server_url: &str,
+    oids: &[String],
+) -> Result<bool> {
+    let bundle_path = pack_cache_path(cache_dir, git_server_url, oids);
+    if !bundle_path.exists()
+        || run_git(
+            git_repo,
+            &[
+                "bundle",
+                "verify",
+                "--quiet",
+                &bundle_path.to_string_lossy(),
+            ],
+        )
+        .is_err()
+    {
+        return Ok(false);
+    }
+    let res = run_git(
+        git_repo,
+        &[
+            "fetch",
+            "--quiet",
+            "--no-write-fetch-head",
+            &bundle_path.to_string_lossy(),
+            &format!("+{CACHE_REF_PREFIX}*:{CACHE_REF_PREFIX}*"),
+        ],
+    );
+    delete_cache_refs(git_repo)?;
+    if let Err(error) = res {
+        // remove corrupt bundle so it gets replaced
+        let _ = std::fs::remove_file(&bundle_path);
+        return Err(error);
+    }
+    Ok(true)
+}
+
+/// store the objects fetched for `oids` from `git_server_url` in `cache_dir`.
+/// history already reachable from the repository's branches, tags and
+/// remotes is left out, so only a first clone bundles everything
+pub fn save_to_pack_cache(
+    git_repo: &Repo,
+    cache_dir: &Path,
+    git_server_url: &str,
+    oids: &[String],
+) -> Result<()> {
+    std::fs::create_dir_all(cache_dir).context("failed to create pack cache directory")?;
+    prune_pack_cache(cache_dir);
+    let bundle_path = pack_cache_path(cache_dir, git_server_url, oids);
+    if bundle_path.exists() {
+        return Ok(());
+    }
+    // git bundle needs named refs rather than oids
+    let mut ref_names = vec![];
+    for oid in oids {
+        let ref_name = format!("{CACHE_REF_PREFIX}{oid}");
+        let object = git_repo
+            .git_repo
+            .revparse_single(oid)
+            .context(format!("failed to find {oid}"))?;
+        git_repo
+            .git_repo
+            .reference(&ref_name, object.id(), true, "ngit pack cache")?;
+        ref_names.push(ref_name);
+    }
+    // write to a temporary file so a partial bundle is never used
+    let tmp_path = bundle_path.with_extension("tmp");
+    let mut args = vec![
+        "bundle".to_string(),
+        "create".to_string(),
+        "--quiet".to_string(),
+        tmp_path.to_string_lossy().to_string(),
+    ];
+    args.extend(ref_names);
+    args.extend(["--not", "--branches", "--tags", "--remotes"].map(String::from));
+    let res = run_git(
+        git_repo,
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+    );
+    delete_cache_refs(git_repo)?;
+    res?;
+    std::fs::rename(&tmp_path, &bundle_path).context("failed to move bundle into pack cache")
+}
+
+fn prune_pack_cache(cache_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(cache_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .is_ok_and(|age| age > MAX_AGE)
+            })
+        {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn delete_cache_refs(git_repo: &Repo) -> Result<()> {
+    for reference in git_repo
+        .git_repo
+        .references_glob(&format!("{CACHE_REF_PREFIX}*"))?
+    {
+        reference?.delete()?;
+    }
+    Ok(())
+}
+
+fn run_git(git_repo: &Repo, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(git_repo.get_path()?)
+        .args(args)
+        .output()
+        .context(format!("failed to run git {}", args[0]))?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    #[test]
+    fn pack_cache_path_ignores_oid_order() {
+        let dir = Path::new("/tmp/packs");
+        assert_eq!(
+            pack_cache_path(
+                dir,
+                "https://example.com/repo.git",
+                &["a".to_string(), "b".to_string()]
+            ),
+            pack_cache_path(
+                dir,
+                "https://example.com/repo.git",
+                &["b".to_string(), "a".to_string()]
+            ),
+        );
+        assert_ne!(
+            pack_cache_path(dir, "https://example.com/repo.git", &["a".to_string()]),
+            pack_cache_path(dir, "https://example.org/repo.git", &["a".to_string()]),
+        );
+    }
+
+    /// fetch `origin`'s main branch into `git_repo` without updating any of
+    /// its refs, as the remote helper does before git updates them
+    fn fetch_without_refs(git_repo: &GitTestRepo, origin: &GitTestRepo) -> Result<()> {
+        let status = Command::new("git")
+            .current_dir(&git_repo.dir)
+            .args(["fetch", "--quiet", "--no-write-fetch-head"])
+            .arg(&origin.dir)
+            .arg("main")
+            .status()?;
+        assert!(status.success());
+        Ok(())
+    }
+
+    #[test]
+    fn saved_pack_can_be_fetched_into_another_repo() -> Result<()> {
+        let origin = GitTestRepo::default();
+        let tip = origin.populate()?;
+        let cache_dir = origin.dir.join("packs");
+        let oids = vec![tip.to_string()];
+        let server_url = "https://example.com/repo.git";
+
+        let first_clone = GitTestRepo::default();
+        fetch_without_refs(&first_clone, &origin)?;
+        save_to_pack_cache(
+            &Repo::from_path(&first_clone.dir)?,
+            &cache_dir,
+            server_url,
+            &oids,
+        )?;
+
+        let destination_repo = GitTestRepo::default();
+        let destination = Repo::from_path(&destination_repo.dir)?;
+        assert!(fetch_from_pack_cache(
+            &destination,
+            &cache_dir,
+            server_url,
+            &oids
+        )?);
+        assert!(destination.does_commit_exist(&tip.to_string())?);
+        assert!(
+            destination
+                .git_repo
+                .references_glob(&format!("{CACHE_REF_PREFIX}*"))?
+                .next()
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn saved_pack_leaves_out_history_the_repo_had() -> Result<()> {
+        let origin = GitTestRepo::default();
+        origin.populate()?;
+        let existing = GitTestRepo::clone_repo(&origin)?;
+        std::fs::write(origin.dir.join("t3.md"), "some content")?;
+        let tip = origin.stage_and_commit("add t3.md")?;
+        let cache_dir = origin.dir.join("packs");
+        let oids = vec![tip.to_string()];
+        let server_url = "https://example.com/repo.git";
+
+        fetch_without_refs(&existing, &origin)?;
+        save_to_pack_cache(
+            &Repo::from_path(&existing.dir)?,
+            &cache_dir,
+            server_url,
+            &oids,
+        )?;
+
+        // lacks the history the bundle builds on
+        let empty_repo = GitTestRepo::default();
+        let empty = Repo::from_path(&empty_repo.dir)?;
+        assert!(!fetch_from_pack_cache(
+            &empty, &cache_dir, server_url, &oids
+        )?);
+        assert!(pack_cache_path(&cache_dir, server_url, &oids).exists());
+
+        let other_clone = GitTestRepo::clone_repo(&existing)?;
+        let other = Repo::from_path(&other_clone.dir)?;
+        assert!(fetch_from_pack_cache(
+            &other, &cache_dir, server_url, &oids
+        )?);
+        assert!(other.does_commit_exist(&tip.to_string())?);
+        Ok(())
+    }
+}
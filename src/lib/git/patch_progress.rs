@@ -0,0 +1,76 @@
+//! a progress bar while patches are turned into commits, followed by a summary
+//! of the commits. both go to stderr, which stays clear of the remote helper
+//! protocol on stdout
+
+use std::time::Duration;
+
+use crate::{
+    git_events::commit_msg_from_patch_oneliner,
+    output,
+    progress::{ProgressBar, ProgressDrawTarget, ProgressStyle},
+    settings::settings,
+    theme,
+};
+
+pub struct PatchProgress {
+    pb: ProgressBar,
+    applied: Vec<(String, String)>,
+}
+
+impl PatchProgress {
+    pub fn new(patch_count: usize) -> Self {
+        let pb = if output::quiet() || settings().test_mode {
+            ProgressBar::with_draw_target(Some(patch_count as u64), ProgressDrawTarget::hidden())
+        } else {
+            ProgressBar::new(patch_count as u64)
+        };
+        if let Ok(style) =
+            ProgressStyle::with_template(" {spinner} applying {bar:20} {pos}/{len} {wide_msg}")
+        {
+            pb.set_style(style.progress_chars("##-"));
+        }
+        pb.enable_steady_tick(Duration::from_millis(300));
+        Self {
+            pb,
+            applied: vec![],
+        }
+    }
+
+    /// show the subject of the patch about to be applied
+    pub fn start(&self, patch: &nostr::Event) {
+        self.pb.set_message(subject(patch));
+    }
+
+    pub fn applied(&mut self, patch: &nostr::Event, commit_id: &str) {
+        self.applied.push((commit_id.to_string(), subject(patch)));
+        self.pb.inc(1);
+    }
+
+    /// clear the bar, listing the commits when given the branch they are on
+    pub fn finish(self, branch_name: Option<&str>) {
+        self.pb.finish_and_clear();
+        let Some(branch_name) = branch_name else {
+            return;
+        };
+        if self.applied.is_empty() || output::quiet() || settings().test_mode {
+            return;
+        }
+        eprintln!(
+            "applied {} patch{} to '{branch_name}':",
+            self.applied.len(),
+            if self.applied.len() == 1 { "" } else { "es" },
+        );
+        for (commit_id, subject) in &self.applied {
+            eprintln!(
+                "  {} {subject}",
+                theme::dim()
+                    .for_stderr()
+                    .apply_to(&commit_id[..commit_id.len().min(7)])
+            );
+        }
+    }
+}
+
+fn subject(patch: &nostr::Event) -> String {
+    commit_msg_from_patch_oneliner(patch).unwrap_or_else(|_| patch.id.to_string())
+}
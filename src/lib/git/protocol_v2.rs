@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+
+/// ref prefixes needed by the remote helper. filtering on these stops servers
+/// from sending refs such as `refs/pull/*` which can number in the hundreds of
+/// thousands for large repositories.
+pub static LIST_REF_PREFIXES: [&str; 3] = ["HEAD", "refs/heads/", "refs/tags/"];
+
+pub fn encode_pkt_line(line: &str) -> String {
+    format!("{:04x}{line}", line.len() + 4)
+}
+
+/// split a pkt-line stream into data lines, stopping at the first flush-pkt
+pub fn decode_pkt_lines(data: &[u8]) -> Result<Vec<String>> {
+    let mut lines = vec![];
+    let mut position = 0;
+    while position + 4 <= data.len() {
+        let len = usize::from_str_radix(
+            std::str::from_utf8(&data[position..position + 4])
+                .context("invalid pkt-line length")?,
+            16,
+        )
+        .context("invalid pkt-line length")?;
+        match len {
+            0 => return Ok(lines),
+            // delim-pkt and response-end-pkt
+            1 | 2 => position += 4,
+            3 => bail!("invalid pkt-line length"),
+            _ => {
+                let line = data
+                    .get(position + 4..position + len)
+                    .context("truncated pkt-line")?;
+                lines.push(
+                    String::from_utf8_lossy(line)
+                        .trim_end_matches('\n')
+                        .to_string(),
+                );
+                position += len;
+            }
+        }
+    }
+    Ok(lines)
+}
+
+/// parse ls-refs output into the same format as the ref list returned by
+/// libgit2, with symbolic refs as 'ref: <target>'
+pub fn parse_ls_refs_lines(lines: &[String]) -> Result<HashMap<String, String>> {
+    let mut state = HashMap::new();
+    for line in lines {
+        let mut parts = line.split(' ');
+        let (Some(oid), Some(name)) = (parts.next(), parts.next()) else {
+            bail!("unexpected ls-refs line: {line}");
+        };
+        if let Some(target) = parts.find_map(|a| a.strip_prefix("symref-target:")) {
+            state.insert(name.to_string(), format!("ref: {target}"));
+        } else {
+            state.insert(name.to_string(), oid.to_string());
+        }
+    }
+    Ok(state)
+}
+
+fn is_v2_advertisement(lines: &[String]) -> bool {
+    lines.iter().any(|l| l == "version 2") && lines.iter().any(|l| l.starts_with("ls-refs"))
+}
+
+/// list refs over smart http using protocol v2 ls-refs, which lets the server
+/// filter refs by `ref_prefixes`. fails if the server doesnt support v2 so the
+/// caller can fall back to a full ref advertisement.
+pub async fn ls_refs_over_http(
+    url: &str,
+    ref_prefixes: &[&str],
+) -> Result<HashMap<String, String>> {
+    let url = url.trim_end_matches('/');
+    let client = reqwest::Client::new();
+
+    let advertisement = client
+        .get(format!("{url}/info/refs?service=git-upload-pack"))
+        .header("Git-Protocol", "version=2")
+        .send()
+        .await
+        .context(format!("failed to connect to {url}"))?;
+    if !advertisement.status().is_success() {
+        bail!("{url} returned status {}", advertisement.status());
+    }
+    let advertisement = advertisement.bytes().await?;
+    // smart http servers prefix the capabilities with '# service=git-upload-pack'
+    // followed by a flush-pkt
+    let mut lines = decode_pkt_lines(&advertisement)?;
+    if lines.first().is_some_and(|l| l.starts_with("# service=")) {
+        let service_len = usize::from_str_radix(
+            std::str::from_utf8(advertisement.get(..4).context("truncated pkt-line")?)?,
+            16,
+        )?;
+        lines = decode_pkt_lines(
+            advertisement
+                .get(service_len + 4..)
+                .context("truncated pkt-line")?,
+        )?;
+    }
+    if !is_v2_advertisement(&lines) {
+        bail!("{url} doesnt support git protocol v2");
+    }
+
+    let mut body = encode_pkt_line("command=ls-refs\n");
+    body.push_str(&encode_pkt_line(&format!(
+        "agent=ngit/{}\n",
+        env!("CARGO_PKG_VERSION")
+    )));
+    body.push_str("0001");
+    body.push_str(&encode_pkt_line("symrefs\n"));
+    for prefix in ref_prefixes {
+        body.push_str(&encode_pkt_line(&format!("ref-prefix {prefix}\n")));
+    }
+    body.push_str("0000");
+
+    let response = client
+        .post(format!("{url}/git-upload-pack"))
+        .header("Git-Protocol", "version=2")
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            "application/x-git-upload-pack-request",
+        )
+        .header(
+            reqwest::header::ACCEPT,
+            "application/x-git-upload-pack-result",
+        )
+        .body(body)
+        .send()
+        .await
+        .context(format!("failed to connect to {url}"))?;
+    if !response.status().is_success() {
+        bail!("{url} returned status {} for ls-refs", response.status());
+    }
+    parse_ls_refs_lines(&decode_pkt_lines(&response.bytes().await?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_pkt_line_includes_length_prefix() {
+        assert_eq!(
+            encode_pkt_line("command=ls-refs\n"),
+            "0014command=ls-refs\n"
+        );
+    }
+
+    #[test]
+    fn decode_pkt_lines_skips_delim_and_stops_at_flush() -> Result<()> {
+        assert_eq!(
+            decode_pkt_lines(b"000eversion 2\n0001000bls-refs0000000aignored")?,
+            vec!["version 2", "ls-refs"],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_ls_refs_lines_handles_symrefs() -> Result<()> {
+        let state = parse_ls_refs_lines(&[
+            "431b84edc0d2fa118d63faa3c2db9c73d630a5ae HEAD symref-target:refs/heads/main"
+                .to_string(),
+            "431b84edc0d2fa118d63faa3c2db9c73d630a5ae refs/heads/main".to_string(),
+        ])?;
+        assert_eq!(state.get("HEAD"), Some(&"ref: refs/heads/main".to_string()));
+        assert_eq!(
+            state.get("refs/heads/main"),
+            Some(&"431b84edc0d2fa118d63faa3c2db9c73d630a5ae".to_string())
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use directories::UserDirs;
 
@@ -23,3 +23,13 @@ pub fn check_ssh_keys() -> bool {
     }
     false // No keys found
 }
+
+/// expand a leading `~/` to the user's home directory
+pub fn expand_home_dir(path: &str) -> PathBuf {
+    if let Some(stripped) = path.strip_prefix("~/") {
+        if let Some(user_dirs) = UserDirs::new() {
+            return user_dirs.home_dir().join(stripped);
+        }
+    }
+    PathBuf::from(path)
+}
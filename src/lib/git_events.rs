@@ -1,19 +1,39 @@
-use std::{str::FromStr, sync::Arc};
+#[cfg(feature = "native")]
+use std::sync::Arc;
+use std::{collections::HashMap, str::FromStr};
 
+#[cfg(feature = "native")]
+use anyhow::anyhow;
 use anyhow::{Context, Result, bail};
-use nostr::nips::{nip01::Coordinate, nip10::Marker, nip19::Nip19};
+use nostr::nips::nip19::Nip19;
+#[cfg(feature = "native")]
+use nostr::nips::{nip01::Coordinate, nip10::Marker};
 use nostr_sdk::{
-    Event, EventBuilder, EventId, FromBech32, Kind, NostrSigner, PublicKey, RelayUrl, Tag, TagKind,
-    TagStandard, hashes::sha1::Hash as Sha1Hash,
+    Event, EventId, FromBech32, Kind, PublicKey, RelayUrl, Timestamp,
+    hashes::sha1::Hash as Sha1Hash,
 };
+#[cfg(feature = "native")]
+use nostr_sdk::{EventBuilder, NostrSigner, Tag, TagKind, TagStandard, ToBech32, UnsignedEvent};
 
+use crate::delegation::event_author;
+#[cfg(feature = "native")]
 use crate::{
     cli_interactor::{Interactor, InteractorPrompt, PromptInputParms},
-    client::sign_event,
-    git::{Repo, RepoActions},
+    client::{sign_event, sign_events},
+    delegation::build_with_delegation,
+    git::{
+        Repo, RepoActions, get_git_config_item,
+        jj::{get_change_id, get_jj_bookmark, is_jj_colocated},
+    },
     repo_ref::RepoRef,
+    settings::settings,
+    short_numbers::ShortNumbers,
 };
 
+/// how far into the future an event's `created_at` can be, by default,
+/// before it is treated as clock-skewed
+pub static DEFAULT_FUTURE_TOLERANCE_SECS: u64 = 15 * 60;
+
 pub fn tag_value(event: &Event, tag_name: &str) -> Result<String> {
     Ok(event
         .tags
@@ -36,6 +56,23 @@ pub fn get_commit_id_from_patch(event: &Event) -> Result<String> {
     }
 }
 
+/// commit ids in a proposal's `r` tags. one should be the earliest unique
+/// commit of the repository it was created against, though not necessarily
+/// the first as others reference the patch commit
+pub fn get_proposal_commit_references(event: &Event) -> Vec<String> {
+    event
+        .tags
+        .iter()
+        .filter(|t| {
+            t.as_slice().len() > 1
+                && t.as_slice()[0].eq("r")
+                && t.as_slice()[1].len() == 40
+                && Sha1Hash::from_str(&t.as_slice()[1]).is_ok()
+        })
+        .map(|t| t.as_slice()[1].clone())
+        .collect()
+}
+
 pub fn get_event_root(event: &nostr::Event) -> Result<EventId> {
     Ok(EventId::parse(
         event
@@ -49,6 +86,48 @@ pub fn get_event_root(event: &nostr::Event) -> Result<EventId> {
     )?)
 }
 
+/// whether `event` is dated further ahead than `future-tolerance-secs`, eg.
+/// by a skewed clock, and so shouldn't win latest-event comparisons
+pub fn is_future_dated(event: &Event) -> bool {
+    event.created_at.as_u64()
+        > Timestamp::now()
+            .as_u64()
+            .saturating_add(future_tolerance_secs())
+}
+
+#[cfg(feature = "native")]
+fn future_tolerance_secs() -> u64 {
+    settings().future_tolerance_secs
+}
+
+/// without settings to load it from
+#[cfg(not(feature = "native"))]
+fn future_tolerance_secs() -> u64 {
+    DEFAULT_FUTURE_TOLERANCE_SECS
+}
+
+/// newest first with future-dated events last
+pub fn sort_newest_first(events: &mut [Event]) {
+    events.sort_by_key(|e| (is_future_dated(e), std::cmp::Reverse(e.created_at)));
+}
+
+/// `events` without future-dated ones, unless that would leave none
+pub fn without_future_dated(events: Vec<Event>) -> Vec<Event> {
+    if events.iter().all(is_future_dated) {
+        return events;
+    }
+    events
+        .into_iter()
+        .filter(|e| {
+            let future_dated = is_future_dated(e);
+            if future_dated {
+                tracing::warn!(id = %e.id, created_at = %e.created_at, "ignored future-dated event");
+            }
+            !future_dated
+        })
+        .collect()
+}
+
 pub fn status_kinds() -> Vec<Kind> {
     vec![
         Kind::GitStatusOpen,
@@ -58,6 +137,103 @@ pub fn status_kinds() -> Vec<Kind> {
     ]
 }
 
+/// maintainers can set any status. the proposal author can close, reopen or
+/// mark it as draft but not claim it was applied. statuses and proposals
+/// signed under a valid delegation count as from the delegator
+pub fn status_is_authorized(status: &Event, proposal: &Event, maintainers: &[PublicKey]) -> bool {
+    let status_author = event_author(status);
+    maintainers.contains(&status_author)
+        || (status_author.eq(&event_author(proposal)) && !status.kind.eq(&Kind::GitStatusApplied))
+}
+
+/// the kind of the latest authorized status of `proposal` in `statuses`,
+/// which are newest first, or open if there isn't one. also returns the
+/// statuses anyone else published as third-party claims
+pub fn get_proposal_status<'a>(
+    proposal: &Event,
+    statuses: &'a [Event],
+    maintainers: &[PublicKey],
+) -> (Kind, Vec<&'a Event>) {
+    let (authorized, third_party): (Vec<&Event>, Vec<&Event>) = statuses
+        .iter()
+        .filter(|s| {
+            status_kinds().contains(&s.kind) && s.tags.event_ids().any(|id| id.eq(&proposal.id))
+        })
+        .partition(|s| status_is_authorized(s, proposal, maintainers));
+    (
+        authorized.first().map_or(Kind::GitStatusOpen, |s| s.kind),
+        third_party,
+    )
+}
+
+/// 👍 and 👎 counts from NIP-25 reactions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReactionTally {
+    pub likes: usize,
+    pub dislikes: usize,
+}
+
+impl std::fmt::Display for ReactionTally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut counts = vec![];
+        if self.likes > 0 {
+            counts.push(format!("👍 {}", self.likes));
+        }
+        if self.dislikes > 0 {
+            counts.push(format!("👎 {}", self.dislikes));
+        }
+        write!(f, "{}", counts.join(" "))
+    }
+}
+
+/// tallies of `reactions` by the event each reacts to, its last `e` tag.
+/// `+` (or empty) and 👍 are likes, `-` and 👎 dislikes and other emoji are
+/// ignored. only each author's latest like or dislike of an event counts
+pub fn get_reaction_tallies(reactions: &[Event]) -> HashMap<EventId, ReactionTally> {
+    let mut reactions: Vec<(&Event, bool)> = reactions
+        .iter()
+        .filter(|e| e.kind == Kind::Reaction)
+        .filter_map(|e| match e.content.trim() {
+            "+" | "" | "👍" => Some((e, true)),
+            "-" | "👎" => Some((e, false)),
+            _ => None,
+        })
+        .collect();
+    reactions.sort_by_key(|(e, _)| e.created_at);
+    let mut latest: HashMap<(EventId, PublicKey), bool> = HashMap::new();
+    for (reaction, like) in reactions {
+        if let Some(target) = reaction.tags.event_ids().last() {
+            latest.insert((*target, reaction.pubkey), like);
+        }
+    }
+    let mut tallies: HashMap<EventId, ReactionTally> = HashMap::new();
+    for ((target, _), like) in latest {
+        let tally = tallies.entry(target).or_default();
+        if like {
+            tally.likes += 1;
+        } else {
+            tally.dislikes += 1;
+        }
+    }
+    tallies
+}
+
+/// id, kind and every tag of an event, for `--dry-run` to report what would
+/// have been published
+pub fn format_event_for_dry_run(event: &Event) -> Vec<String> {
+    let mut lines = vec![
+        format!("id: {}", event.id),
+        format!("kind: {}", event.kind.as_u16()),
+    ];
+    if !event.tags.is_empty() {
+        lines.push("tags:".to_string());
+        for tag in event.tags.iter() {
+            lines.push(format!("  {}", tag.as_slice().join(" ")));
+        }
+    }
+    lines
+}
+
 pub fn event_is_patch_set_root(event: &Event) -> bool {
     event.kind.eq(&Kind::GitPatch)
         && event
@@ -82,8 +258,8 @@ pub fn patch_supports_commit_ids(event: &Event) -> bool {
             .any(|t| !t.as_slice().is_empty() && t.as_slice()[0].eq("commit-pgp-sig"))
 }
 
+#[cfg(feature = "native")]
 #[allow(clippy::too_many_arguments)]
-#[allow(clippy::too_many_lines)]
 pub async fn generate_patch_event(
     git_repo: &Repo,
     root_commit: &Sha1Hash,
@@ -97,152 +273,262 @@ pub async fn generate_patch_event(
     root_proposal_id: &Option<String>,
     mentions: &[nostr::Tag],
 ) -> Result<nostr::Event> {
+    sign_event(
+        generate_patch_event_builder(
+            git_repo,
+            make_patch(git_repo, commit, &series_count)?,
+            root_commit,
+            commit,
+            thread_event_id,
+            repo_ref,
+            parent_patch_event_id,
+            branch_name,
+            root_proposal_id,
+            mentions,
+        )?,
+        signer,
+    )
+    .await
+    .context("failed to sign event")
+}
+
+#[cfg(feature = "native")]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+fn generate_patch_event_builder(
+    git_repo: &Repo,
+    patch: String,
+    root_commit: &Sha1Hash,
+    commit: &Sha1Hash,
+    thread_event_id: Option<nostr::EventId>,
+    repo_ref: &RepoRef,
+    parent_patch_event_id: Option<nostr::EventId>,
+    branch_name: Option<String>,
+    root_proposal_id: &Option<String>,
+    mentions: &[nostr::Tag],
+) -> Result<EventBuilder> {
     let commit_parent = git_repo
         .get_commit_parent(commit)
         .context("failed to get parent commit")?;
     let relay_hint = repo_ref.relays.first().cloned();
 
-    sign_event(
-        EventBuilder::new(
-            nostr::event::Kind::GitPatch,
-            git_repo
-                .make_patch_from_commit(commit, &series_count)
-                .context(format!("failed to make patch for commit {commit}"))?,
-        )
-        .tags(
-            [
-                repo_ref
-                    .maintainers
-                    .iter()
-                    .map(|m| {
-                        Tag::coordinate(Coordinate {
-                            kind: nostr::Kind::GitRepoAnnouncement,
-                            public_key: *m,
-                            identifier: repo_ref.identifier.to_string(),
-                            relays: repo_ref.relays.clone(),
-                        })
+    Ok(EventBuilder::new(nostr::event::Kind::GitPatch, patch).tags(
+        [
+            repo_ref
+                .maintainers
+                .iter()
+                .map(|m| {
+                    Tag::coordinate(Coordinate {
+                        kind: nostr::Kind::GitRepoAnnouncement,
+                        public_key: *m,
+                        identifier: repo_ref.identifier.to_string(),
+                        relays: repo_ref.relays.clone(),
                     })
-                    .collect::<Vec<Tag>>(),
+                })
+                .collect::<Vec<Tag>>(),
+            vec![
+                Tag::from_standardized(TagStandard::Reference(root_commit.to_string())),
+                // commit id reference is a trade-off. its now
+                // unclear which one is the root commit id but it
+                // enables easier location of code comments againt
+                // code that makes it into the main branch, assuming
+                // the commit id is correct
+                Tag::from_standardized(TagStandard::Reference(commit.to_string())),
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
+                    vec![format!(
+                        "git patch: {}",
+                        git_repo
+                            .get_commit_message_summary(commit)
+                            .unwrap_or_default()
+                    )],
+                ),
+            ],
+            if let Some(thread_event_id) = thread_event_id {
+                vec![Tag::from_standardized(nostr_sdk::TagStandard::Event {
+                    event_id: thread_event_id,
+                    relay_url: relay_hint.clone(),
+                    marker: Some(Marker::Root),
+                    public_key: None,
+                    uppercase: false,
+                })]
+            } else if let Some(event_ref) = root_proposal_id.clone() {
                 vec![
-                    Tag::from_standardized(TagStandard::Reference(root_commit.to_string())),
-                    // commit id reference is a trade-off. its now
-                    // unclear which one is the root commit id but it
-                    // enables easier location of code comments againt
-                    // code that makes it into the main branch, assuming
-                    // the commit id is correct
-                    Tag::from_standardized(TagStandard::Reference(commit.to_string())),
-                    Tag::custom(TagKind::Custom(std::borrow::Cow::Borrowed("alt")), vec![
-                        format!(
-                            "git patch: {}",
-                            git_repo
-                                .get_commit_message_summary(commit)
-                                .unwrap_or_default()
-                        ),
-                    ]),
-                ],
-                if let Some(thread_event_id) = thread_event_id {
-                    vec![Tag::from_standardized(nostr_sdk::TagStandard::Event {
-                        event_id: thread_event_id,
-                        relay_url: relay_hint.clone(),
-                        marker: Some(Marker::Root),
-                        public_key: None,
-                        uppercase: false,
-                    })]
-                } else if let Some(event_ref) = root_proposal_id.clone() {
-                    vec![
-                        Tag::hashtag("root"),
-                        Tag::hashtag("revision-root"),
-                        // TODO check if id is for a root proposal (perhaps its for an issue?)
-                        event_tag_from_nip19_or_hex(
-                            &event_ref,
-                            "proposal",
-                            Marker::Reply,
-                            false,
-                            false,
-                        )?,
-                    ]
-                } else {
-                    vec![Tag::hashtag("root")]
-                },
-                mentions.to_vec(),
-                if let Some(id) = parent_patch_event_id {
-                    vec![Tag::from_standardized(nostr_sdk::TagStandard::Event {
-                        event_id: id,
-                        relay_url: relay_hint.clone(),
-                        marker: Some(Marker::Reply),
-                        public_key: None,
-                        uppercase: false,
-                    })]
+                    Tag::hashtag("root"),
+                    Tag::hashtag("revision-root"),
+                    // TODO check if id is for a root proposal (perhaps its for an issue?)
+                    event_tag_from_nip19_or_hex(
+                        &event_ref,
+                        "proposal",
+                        Marker::Reply,
+                        false,
+                        false,
+                    )?,
+                ]
+            } else {
+                vec![Tag::hashtag("root")]
+            },
+            mentions.to_vec(),
+            if let Some(id) = parent_patch_event_id {
+                vec![Tag::from_standardized(nostr_sdk::TagStandard::Event {
+                    event_id: id,
+                    relay_url: relay_hint.clone(),
+                    marker: Some(Marker::Reply),
+                    public_key: None,
+                    uppercase: false,
+                })]
+            } else {
+                vec![]
+            },
+            // see comment on branch names in cover letter event creation
+            if let Some(branch_name) = branch_name {
+                if thread_event_id.is_none() {
+                    vec![Tag::custom(
+                        TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
+                        vec![branch_name.chars().take(60).collect::<String>()],
+                    )]
                 } else {
                     vec![]
-                },
-                // see comment on branch names in cover letter event creation
-                if let Some(branch_name) = branch_name {
-                    if thread_event_id.is_none() {
+                }
+            } else {
+                vec![]
+            },
+            // whilst it is in nip34 draft to tag the maintainers
+            // I'm not sure it is a good idea because if they are
+            // interested in all patches then their specialised
+            // client should subscribe to patches tagged with the
+            // repo reference. maintainers of large repos will not
+            // be interested in every patch.
+            repo_ref
+                .maintainers
+                .iter()
+                .map(|pk| Tag::public_key(*pk))
+                .collect(),
+            vec![
+                // a fallback is now in place to extract this from the patch
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("commit")),
+                    vec![commit.to_string()],
+                ),
+                // this is required as patches cannot be relied upon to include the 'base
+                // commit'
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("parent-commit")),
+                    vec![commit_parent.to_string()],
+                ),
+                // this is required to ensure the commit id matches
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("commit-pgp-sig")),
+                    vec![
+                        git_repo
+                            .extract_commit_pgp_signature(commit)
+                            .unwrap_or_default(),
+                    ],
+                ),
+                // removing description tag will not cause anything to break
+                Tag::from_standardized(nostr_sdk::TagStandard::Description(
+                    git_repo.get_commit_message(commit)?.to_string(),
+                )),
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("author")),
+                    git_repo.get_commit_author(commit)?,
+                ),
+                // this is required to ensure the commit id matches
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("committer")),
+                    git_repo.get_commit_comitter(commit)?,
+                ),
+            ],
+            // this is required to ensure the commit id matches
+            if let Some(encoding) = git_repo.get_commit_message_encoding(commit)? {
+                vec![Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("commit-encoding")),
+                    vec![encoding],
+                )]
+            } else {
+                vec![]
+            },
+            // lets rebased jj changes be recognised as revisions of a proposal
+            if let Some((change_id, in_header)) = get_change_id(git_repo, commit) {
+                [
+                    vec![Tag::custom(
+                        TagKind::Custom(std::borrow::Cow::Borrowed("change-id")),
+                        vec![change_id.clone()],
+                    )],
+                    // this is required to ensure the commit id matches
+                    if in_header {
                         vec![Tag::custom(
-                            TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
-                            vec![branch_name.chars().take(60).collect::<String>()],
+                            TagKind::Custom(std::borrow::Cow::Borrowed("commit-change-id")),
+                            vec![change_id],
                         )]
                     } else {
                         vec![]
-                    }
-                } else {
-                    vec![]
-                },
-                // whilst it is in nip34 draft to tag the maintainers
-                // I'm not sure it is a good idea because if they are
-                // interested in all patches then their specialised
-                // client should subscribe to patches tagged with the
-                // repo reference. maintainers of large repos will not
-                // be interested in every patch.
-                repo_ref
-                    .maintainers
-                    .iter()
-                    .map(|pk| Tag::public_key(*pk))
-                    .collect(),
-                vec![
-                    // a fallback is now in place to extract this from the patch
-                    Tag::custom(TagKind::Custom(std::borrow::Cow::Borrowed("commit")), vec![
-                        commit.to_string(),
-                    ]),
-                    // this is required as patches cannot be relied upon to include the 'base
-                    // commit'
-                    Tag::custom(
-                        TagKind::Custom(std::borrow::Cow::Borrowed("parent-commit")),
-                        vec![commit_parent.to_string()],
-                    ),
-                    // this is required to ensure the commit id matches
-                    Tag::custom(
-                        TagKind::Custom(std::borrow::Cow::Borrowed("commit-pgp-sig")),
-                        vec![
-                            git_repo
-                                .extract_commit_pgp_signature(commit)
-                                .unwrap_or_default(),
-                        ],
-                    ),
-                    // removing description tag will not cause anything to break
-                    Tag::from_standardized(nostr_sdk::TagStandard::Description(
-                        git_repo.get_commit_message(commit)?.to_string(),
-                    )),
-                    Tag::custom(
-                        TagKind::Custom(std::borrow::Cow::Borrowed("author")),
-                        git_repo.get_commit_author(commit)?,
-                    ),
-                    // this is required to ensure the commit id matches
-                    Tag::custom(
-                        TagKind::Custom(std::borrow::Cow::Borrowed("committer")),
-                        git_repo.get_commit_comitter(commit)?,
-                    ),
-                ],
-            ]
-            .concat(),
-        ),
-        signer,
-    )
-    .await
-    .context("failed to sign event")
+                    },
+                ]
+                .concat()
+            } else {
+                vec![]
+            },
+        ]
+        .concat(),
+    ))
+}
+
+#[cfg(feature = "native")]
+fn make_patch(
+    git_repo: &Repo,
+    commit: &Sha1Hash,
+    series_count: &Option<(u64, u64)>,
+) -> Result<String> {
+    git_repo
+        .make_patch_from_commit(commit, series_count)
+        .context(format!("failed to make patch for commit {commit}"))
+}
+
+/// patches for each commit and its series position. diffs dominate the time
+/// taken to build a long series so they are split across threads, each with
+/// its own handle on the repository
+#[cfg(feature = "native")]
+fn make_patches(
+    git_repo: &Repo,
+    commits: &[(Sha1Hash, Option<(u64, u64)>)],
+) -> Result<Vec<String>> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, std::num::NonZeroUsize::get)
+        .min(commits.len());
+    if threads <= 1 {
+        return commits
+            .iter()
+            .map(|(commit, series_count)| make_patch(git_repo, commit, series_count))
+            .collect();
+    }
+    let git_dir = git_repo.git_repo.path();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = commits
+            .chunks(commits.len().div_ceil(threads))
+            .map(|chunk| {
+                scope.spawn(move || -> Result<Vec<String>> {
+                    let git_repo = Repo::from_path(&git_dir.to_path_buf())?;
+                    chunk
+                        .iter()
+                        .map(|(commit, series_count)| make_patch(&git_repo, commit, series_count))
+                        .collect()
+                })
+            })
+            .collect();
+        let mut patches = vec![];
+        for handle in handles {
+            patches.extend(
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("failed to make patches"))??,
+            );
+        }
+        Ok(patches)
+    })
 }
 
+#[cfg(feature = "native")]
 pub fn event_tag_from_nip19_or_hex(
     reference: &str,
     reference_name: &str,
@@ -312,7 +598,36 @@ pub fn event_tag_from_nip19_or_hex(
     }
 }
 
-#[allow(clippy::too_many_lines)]
+/// event id and relay hints from a nevent, note or hex id, optionally
+/// prefixed with `nostr:` or within a link eg. from njump.me or gitworkshop.dev
+pub fn event_id_from_user_input(input: &str) -> Result<(EventId, Vec<RelayUrl>)> {
+    let input = input.trim();
+    let without_query = input.split(['?', '#']).next().unwrap_or(input);
+    for segment in without_query.rsplit('/') {
+        let segment = segment.trim_start_matches("nostr:");
+        match Nip19::from_bech32(segment) {
+            Ok(Nip19::Event(n)) => {
+                return Ok((
+                    n.event_id,
+                    n.relays
+                        .iter()
+                        .filter_map(|r| RelayUrl::parse(r).ok())
+                        .collect(),
+                ));
+            }
+            Ok(Nip19::EventId(id)) => return Ok((id, vec![])),
+            _ => {}
+        }
+        if segment.len() == 64 {
+            if let Ok(id) = EventId::from_hex(segment) {
+                return Ok((id, vec![]));
+            }
+        }
+    }
+    bail!("not a nevent, note, event id or a link containing one")
+}
+
+#[cfg(feature = "native")]
 pub async fn generate_cover_letter_and_patch_events(
     cover_letter_title_description: Option<(String, String)>,
     git_repo: &Repo,
@@ -322,14 +637,41 @@ pub async fn generate_cover_letter_and_patch_events(
     root_proposal_id: &Option<String>,
     mentions: &[nostr::Tag],
 ) -> Result<Vec<nostr::Event>> {
+    // ids are known before signing so the whole series can be sent to the
+    // signer at once
+    let events = generate_unsigned_cover_letter_and_patch_events(
+        cover_letter_title_description,
+        git_repo,
+        commits,
+        &signer.get_public_key().await?,
+        repo_ref,
+        root_proposal_id,
+        mentions,
+    )?;
+    sign_events(events, signer).await
+}
+
+/// cover letter and patch events for `commits` with ids set, ready to be
+/// signed by `public_key`
+#[cfg(feature = "native")]
+#[allow(clippy::too_many_lines)]
+pub fn generate_unsigned_cover_letter_and_patch_events(
+    cover_letter_title_description: Option<(String, String)>,
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    public_key: &PublicKey,
+    repo_ref: &RepoRef,
+    root_proposal_id: &Option<String>,
+    mentions: &[nostr::Tag],
+) -> Result<Vec<UnsignedEvent>> {
     let root_commit = git_repo
         .get_root_commit()
         .context("failed to get root commit of the repository")?;
 
-    let mut events = vec![];
+    let mut events: Vec<UnsignedEvent> = vec![];
 
     if let Some((title, description)) = cover_letter_title_description {
-        events.push(sign_event(EventBuilder::new(
+        events.push(build_with_id(EventBuilder::new(
         nostr::event::Kind::GitPatch,
         format!(
             "From {} Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/{}] {title}\n\n{description}",
@@ -369,24 +711,13 @@ pub async fn generate_cover_letter_and_patch_events(
             // eventually a prefix will be needed of the event id to stop 2 proposals with the same name colliding
             // a change like this, or the removal of this tag will require the actual branch name to be tracked
             // so pulling and pushing still work
-            if let Ok(branch_name) = git_repo.get_checked_out_branch_name() {
-                if !branch_name.eq("main")
-                    && !branch_name.eq("master")
-                    && !branch_name.eq("origin/main")
-                    && !branch_name.eq("origin/master")
-                {
-                    vec![
-                        Tag::custom(
-                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
-                            vec![if let Some(branch_name) = branch_name.strip_prefix("pr/") {
-                                branch_name.to_string()
-                            } else {
-                                branch_name
-                            }.chars().take(60).collect::<String>()],
-                        ),
-                    ]
-                }
-                else { vec![] }
+            if let Some(branch_name) = get_proposal_branch_name(git_repo, commits.last()) {
+                vec![
+                    Tag::custom(
+                        nostr::TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
+                        vec![branch_name],
+                    ),
+                ]
             } else {
                 vec![]
             },
@@ -395,79 +726,255 @@ pub async fn generate_cover_letter_and_patch_events(
                 .map(|pk| Tag::public_key(*pk))
                 .collect(),
         ].concat(),
-    ), signer).await
-    .context("failed to create cover-letter event")?);
+    ), public_key)?);
     }
 
-    for (i, commit) in commits.iter().enumerate() {
-        events.push(
-            generate_patch_event(
+    let has_cover_letter = !events.is_empty();
+    let patches = make_patches(
+        git_repo,
+        &commits
+            .iter()
+            .enumerate()
+            .map(|(i, commit)| {
+                Ok((
+                    *commit,
+                    if !has_cover_letter && commits.len().eq(&1) {
+                        None
+                    } else {
+                        Some(((i + 1).try_into()?, commits.len().try_into()?))
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?,
+    )?;
+    for (commit, patch) in commits.iter().zip(patches) {
+        events.push(build_with_id(
+            generate_patch_event_builder(
                 git_repo,
+                patch,
                 &root_commit,
                 commit,
-                events.first().map(|event| event.id),
-                signer,
+                events.first().and_then(|event| event.id),
                 repo_ref,
-                events.last().map(|e| e.id),
-                if events.is_empty() && commits.len().eq(&1) {
-                    None
-                } else {
-                    Some(((i + 1).try_into()?, commits.len().try_into()?))
-                },
+                events.last().and_then(|e| e.id),
                 if events.is_empty() {
-                    if let Ok(branch_name) = git_repo.get_checked_out_branch_name() {
-                        if !branch_name.eq("main")
-                            && !branch_name.eq("master")
-                            && !branch_name.eq("origin/main")
-                            && !branch_name.eq("origin/master")
-                        {
-                            Some(
-                                if let Some(branch_name) = branch_name.strip_prefix("pr/") {
-                                    branch_name.to_string()
-                                } else {
-                                    branch_name
-                                }
-                                .chars()
-                                .take(60)
-                                .collect::<String>(),
-                            )
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                    get_proposal_branch_name(git_repo, commits.last())
                 } else {
                     None
                 },
                 root_proposal_id,
                 if events.is_empty() { mentions } else { &[] },
             )
-            .await
             .context("failed to generate patch event")?,
-        );
+            public_key,
+        )?);
     }
     Ok(events)
 }
 
+/// patches for `commits` continuing a proposal's series after
+/// `parent_patch_event_id`, numbered from `series_start` of `series_total`
+#[cfg(feature = "native")]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_patch_events_continuing_proposal(
+    git_repo: &Repo,
+    commits: &[Sha1Hash],
+    thread_event_id: nostr::EventId,
+    parent_patch_event_id: nostr::EventId,
+    series_start: u64,
+    series_total: u64,
+    signer: &Arc<dyn NostrSigner>,
+    repo_ref: &RepoRef,
+) -> Result<Vec<nostr::Event>> {
+    let root_commit = git_repo
+        .get_root_commit()
+        .context("failed to get root commit of the repository")?;
+    let public_key = signer.get_public_key().await?;
+    let patches = make_patches(
+        git_repo,
+        &commits
+            .iter()
+            .zip(series_start..)
+            .map(|(commit, i)| (*commit, Some((i, series_total))))
+            .collect::<Vec<_>>(),
+    )?;
+    let mut events: Vec<UnsignedEvent> = vec![];
+    for (commit, patch) in commits.iter().zip(patches) {
+        events.push(build_with_id(
+            generate_patch_event_builder(
+                git_repo,
+                patch,
+                &root_commit,
+                commit,
+                Some(thread_event_id),
+                repo_ref,
+                events
+                    .last()
+                    .and_then(|e| e.id)
+                    .or(Some(parent_patch_event_id)),
+                None,
+                &None,
+                &[],
+            )
+            .context("failed to make patch event from commit")?,
+            &public_key,
+        )?);
+    }
+    sign_events(events, signer).await
+}
+
+#[cfg(feature = "native")]
+fn build_with_id(event_builder: EventBuilder, public_key: &PublicKey) -> Result<UnsignedEvent> {
+    let mut unsigned = build_with_delegation(event_builder, *public_key)?;
+    unsigned.ensure_id();
+    Ok(unsigned)
+}
+
 pub struct CoverLetter {
     pub title: String,
     pub description: String,
     pub branch_name: String,
     pub event_id: Option<nostr::EventId>,
+    pub author: Option<PublicKey>,
+}
+
+pub static DEFAULT_BRANCH_NAME_TEMPLATE: &str = "pr/{slug}({id})";
+
+/// `nostr.branch-name-template` from local or global git config. supports
+/// `{slug}`, `{id}`, `{number}` and `{author}`
+#[cfg(feature = "native")]
+pub fn get_branch_name_template(git_repo: &Option<&Repo>) -> String {
+    get_git_config_item(git_repo, "nostr.branch-name-template")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| DEFAULT_BRANCH_NAME_TEMPLATE.to_string())
+}
+
+/// the slug part of `branch_name` if it fits `template`
+#[cfg(feature = "native")]
+fn match_branch_name_template(template: &str, branch_name: &str) -> Option<String> {
+    let mut slug = None;
+    let mut rest = branch_name;
+    let mut template_rest = template;
+    while !template_rest.is_empty() {
+        if let Some(after_open) = template_rest.strip_prefix('{') {
+            let (placeholder, after) = after_open.split_once('}')?;
+            template_rest = after;
+            // placeholders match up to the next literal text in the template
+            let literal_end = template_rest.find('{').unwrap_or(template_rest.len());
+            let next_literal = &template_rest[..literal_end];
+            let value_len = if next_literal.is_empty() {
+                if template_rest.is_empty() {
+                    rest.len()
+                } else {
+                    return None;
+                }
+            } else {
+                rest.find(next_literal)?
+            };
+            if placeholder == "slug" {
+                slug = Some(rest[..value_len].to_string());
+            }
+            rest = &rest[value_len..];
+        } else {
+            let literal_end = template_rest.find('{').unwrap_or(template_rest.len());
+            rest = rest.strip_prefix(&template_rest[..literal_end])?;
+            template_rest = &template_rest[literal_end..];
+        }
+    }
+    if rest.is_empty() {
+        slug.filter(|s| !s.is_empty())
+    } else {
+        None
+    }
 }
 
+/// slug of the checked out branch for the proposal's branch-name tag. in jj
+/// colocated repositories, where HEAD is detached, the bookmark on `tip`
+#[cfg(feature = "native")]
+fn get_proposal_branch_name(git_repo: &Repo, tip: Option<&Sha1Hash>) -> Option<String> {
+    let branch_name = git_repo
+        .get_checked_out_branch_name()
+        .ok()
+        .filter(|b| b.ne("HEAD") || !is_jj_colocated(git_repo))
+        .or_else(|| tip.and_then(|tip| get_jj_bookmark(git_repo, tip)))?;
+    if ["main", "master", "origin/main", "origin/master"].contains(&branch_name.as_str()) {
+        None
+    } else {
+        Some(branch_name_to_slug(git_repo, branch_name))
+    }
+}
+
+/// the part of a branch name published in a proposal's branch-name tag
+#[cfg(feature = "native")]
+fn branch_name_to_slug(git_repo: &Repo, branch_name: String) -> String {
+    let template = get_branch_name_template(&Some(git_repo));
+    if template != DEFAULT_BRANCH_NAME_TEMPLATE {
+        if let Some(slug) = match_branch_name_template(&template, &branch_name) {
+            return slug.chars().take(60).collect();
+        }
+    }
+    if let Some(branch_name) = branch_name.strip_prefix("pr/") {
+        branch_name.to_string()
+    } else {
+        branch_name
+    }
+    .chars()
+    .take(60)
+    .collect::<String>()
+}
+
+#[cfg(feature = "native")]
 impl CoverLetter {
+    /// local branch name using `nostr.branch-name-template`
     pub fn get_branch_name(&self) -> Result<String> {
-        Ok(format!(
-            "pr/{}({})",
-            self.branch_name,
-            &self
-                .event_id
-                .context("proposal root event_id must be know to get it's branch name")?
-                .to_hex()
-                .as_str()[..8],
-        ))
+        let git_repo = Repo::discover().ok();
+        let template = get_branch_name_template(&git_repo.as_ref());
+        let number = match (&git_repo, self.event_id) {
+            (Some(git_repo), Some(event_id)) if template.contains("{number}") => {
+                ShortNumbers::load(git_repo.get_path()?).number_of(&event_id)
+            }
+            _ => None,
+        };
+        self.get_branch_name_from_template(&template, number)
+    }
+
+    /// `number` is the proposal's short number, needed if `template` uses
+    /// `{number}`
+    pub fn get_branch_name_from_template(
+        &self,
+        template: &str,
+        number: Option<usize>,
+    ) -> Result<String> {
+        let event_id = self
+            .event_id
+            .context("proposal root event_id must be know to get it's branch name")?;
+        let id = &event_id.to_hex()[..8];
+        let number = if template.contains("{number}") {
+            number
+                .context(format!(
+                    "proposal {id} has no short number yet so nostr.branch-name-template \"{template}\" can't be used. run `ngit numbers` to assign one"
+                ))?
+                .to_string()
+        } else {
+            String::new()
+        };
+        let author = if let Some(author) = self.author {
+            author.to_bech32()?.chars().take(12).collect()
+        } else {
+            "unknown".to_string()
+        };
+        let branch_name = template
+            .replace("{slug}", &self.branch_name)
+            .replace("{id}", id)
+            .replace("{number}", &number)
+            .replace("{author}", &author);
+        if !git2::Branch::name_is_valid(&branch_name)? {
+            bail!(
+                "nostr.branch-name-template \"{template}\" produced an invalid branch name \"{branch_name}\""
+            );
+        }
+        Ok(branch_name)
     }
 }
 pub fn event_is_cover_letter(event: &nostr::Event) -> bool {
@@ -550,6 +1057,7 @@ pub fn event_to_cover_letter(event: &nostr::Event) -> Result<CoverLetter> {
         .take(60)
         .collect(),
         event_id: Some(event.id),
+        author: Some(event.pubkey),
     })
 }
 
@@ -596,6 +1104,161 @@ pub fn get_most_recent_patch_with_ancestors(
     Ok(res)
 }
 
+/// the proposal or revision root that starts the chain `patch` belongs to
+fn get_patch_chain_root(patch: &Event) -> Option<EventId> {
+    if event_is_patch_set_root(patch) {
+        Some(patch.id)
+    } else {
+        get_event_root(patch).ok()
+    }
+}
+
+/// proposals and revision roots in `patches` ordered by their most recently
+/// added patch, newest first
+pub fn get_revision_roots(patches: &[Event]) -> Vec<&Event> {
+    let latest_in_chain = |root: &Event| {
+        patches
+            .iter()
+            .filter(|p| get_patch_chain_root(p).is_some_and(|id| id.eq(&root.id)))
+            .map(|p| p.created_at)
+            .max()
+            .unwrap_or(root.created_at)
+    };
+    let mut roots: Vec<&Event> = patches
+        .iter()
+        .filter(|p| event_is_patch_set_root(p))
+        .collect();
+    roots.sort_by_key(|r| (latest_in_chain(r), r.created_at));
+    roots.reverse();
+    roots
+}
+
+/// revision roots, newest first, whose chains were still being added to after
+/// the latest revision was published, eg. when revisions were pushed from
+/// two machines. empty unless there is more than one.
+pub fn get_concurrent_revision_roots(patches: &[Event]) -> Vec<&Event> {
+    let roots = get_revision_roots(patches);
+    let Some(latest) = roots.first() else {
+        return vec![];
+    };
+    let concurrent: Vec<&Event> = roots
+        .iter()
+        .filter(|r| {
+            r.id.eq(&latest.id)
+                || patches.iter().any(|p| {
+                    get_patch_chain_root(p).is_some_and(|id| id.eq(&r.id))
+                        && p.created_at.ge(&latest.created_at)
+                })
+        })
+        .copied()
+        .collect();
+    if concurrent.len() > 1 {
+        concurrent
+    } else {
+        vec![]
+    }
+}
+
+/// patches in the chain started by proposal or revision root `root_id`
+pub fn get_patches_of_revision(patches: &[Event], root_id: &EventId) -> Vec<Event> {
+    patches
+        .iter()
+        .filter(|p| get_patch_chain_root(p).is_some_and(|id| id.eq(root_id)))
+        .cloned()
+        .collect()
+}
+
+/// proposals sent more than once by the same author, eg. from two clients,
+/// keyed by the earliest root with the later roots, oldest first. roots match
+/// on their commit id, or for cover letters the commit ids of their patches
+/// by the same author in `patches`
+pub fn get_duplicate_proposals(
+    proposals: &[Event],
+    patches: &[Event],
+) -> HashMap<EventId, Vec<Event>> {
+    let mut groups: HashMap<(PublicKey, String), Vec<&Event>> = HashMap::new();
+    for proposal in proposals {
+        let key = if event_is_cover_letter(proposal) {
+            let mut commit_ids: Vec<String> = patches
+                .iter()
+                .filter(|p| {
+                    p.pubkey.eq(&proposal.pubkey)
+                        && !p.id.eq(&proposal.id)
+                        && get_patch_chain_root(p).is_some_and(|id| id.eq(&proposal.id))
+                })
+                .filter_map(|p| get_commit_id_from_patch(p).ok())
+                .collect();
+            commit_ids.sort();
+            commit_ids.dedup();
+            if commit_ids.is_empty() {
+                None
+            } else {
+                Some(format!("commits {}", commit_ids.join(",")))
+            }
+        } else {
+            get_commit_id_from_patch(proposal)
+                .ok()
+                .map(|commit_id| format!("commit {commit_id}"))
+        };
+        if let Some(key) = key {
+            groups
+                .entry((proposal.pubkey, key))
+                .or_default()
+                .push(proposal);
+        }
+    }
+    groups
+        .into_values()
+        .filter(|roots| roots.len() > 1)
+        .map(|mut roots| {
+            roots.sort_by_key(|e| (e.created_at, e.id));
+            let original = roots.remove(0);
+            (original.id, roots.into_iter().cloned().collect())
+        })
+        .collect()
+}
+
+/// NIP-09 deletion request for `events`, tagged with the repository so it is
+/// fetched alongside its proposals
+#[cfg(feature = "native")]
+pub async fn generate_deletion_event(
+    events: &[Event],
+    reason: &str,
+    repo_ref: &RepoRef,
+    signer: &Arc<dyn NostrSigner>,
+) -> Result<Event> {
+    let mut kinds: Vec<u16> = events.iter().map(|e| e.kind.as_u16()).collect();
+    kinds.sort_unstable();
+    kinds.dedup();
+    sign_event(
+        EventBuilder::new(Kind::EventDeletion, reason).tags(
+            [
+                events
+                    .iter()
+                    .map(|e| Tag::event(e.id))
+                    .collect::<Vec<Tag>>(),
+                kinds
+                    .iter()
+                    .map(|kind| {
+                        Tag::custom(
+                            TagKind::Custom(std::borrow::Cow::Borrowed("k")),
+                            vec![kind.to_string()],
+                        )
+                    })
+                    .collect(),
+                repo_ref
+                    .coordinates()
+                    .into_iter()
+                    .map(Tag::coordinate)
+                    .collect(),
+            ]
+            .concat(),
+        ),
+        signer,
+    )
+    .await
+}
+
 fn get_event_parent_id(event: &nostr::Event) -> Result<String> {
     Ok(if let Some(reply_tag) = event
         .tags
@@ -614,6 +1277,7 @@ fn get_event_parent_id(event: &nostr::Event) -> Result<String> {
         .clone())
 }
 
+#[cfg(feature = "native")]
 pub fn is_event_proposal_root_for_branch(
     e: &Event,
     branch_name_or_refstr: &str,
@@ -632,6 +1296,482 @@ pub fn is_event_proposal_root_for_branch(
 mod tests {
     use super::*;
 
+    #[cfg(feature = "native")]
+    mod make_patches {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+        use crate::git::oid_to_sha1;
+
+        #[test]
+        fn matches_patches_made_one_at_a_time() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let mut commits = vec![];
+            for i in 0..5 {
+                std::fs::write(test_repo.dir.join(format!("t{i}.md")), "some content")?;
+                commits.push(oid_to_sha1(
+                    &test_repo.stage_and_commit(&format!("add t{i}.md"))?,
+                ));
+            }
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let commits: Vec<_> = commits
+                .into_iter()
+                .zip(1..)
+                .map(|(commit, i)| (commit, Some((i, 5))))
+                .collect();
+            assert_eq!(
+                make_patches(&git_repo, &commits)?,
+                commits
+                    .iter()
+                    .map(|(commit, series_count)| make_patch(&git_repo, commit, series_count))
+                    .collect::<Result<Vec<_>>>()?,
+            );
+            Ok(())
+        }
+    }
+
+    mod event_id_from_user_input {
+        use super::*;
+
+        static HEX: &str = "431b84edc0d2fa118d63faa3c2db9c73d630a5ae431b84edc0d2fa118d63faa3";
+
+        #[test]
+        fn accepts_hex_note_and_nevent_with_prefix_or_in_links() -> Result<()> {
+            let id = EventId::from_hex(HEX)?;
+            let note = id.to_bech32()?;
+            let nevent =
+                nostr::nips::nip19::Nip19Event::new(id, ["wss://relay.example.com"]).to_bech32()?;
+            for input in [
+                HEX.to_string(),
+                note.clone(),
+                format!("nostr:{nevent}"),
+                format!("https://njump.me/{nevent}"),
+                format!("https://gitworkshop.dev/npub1xyz/ngit/proposals/{note}?tab=files"),
+            ] {
+                assert_eq!(event_id_from_user_input(&input)?.0, id, "{input}");
+            }
+            assert_eq!(
+                event_id_from_user_input(&nevent)?.1,
+                vec![RelayUrl::parse("wss://relay.example.com")?],
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn rejects_other_input() {
+            assert!(event_id_from_user_input("https://gitworkshop.dev/repos").is_err());
+            assert!(event_id_from_user_input("not-an-id").is_err());
+        }
+    }
+
+    mod concurrent_revisions {
+        use nostr::Timestamp;
+
+        use super::*;
+
+        fn patch(created_at: u64, root: Option<&Event>, revision_of: Option<&Event>) -> Event {
+            let tags = if let Some(root) = root {
+                vec![Tag::parse(["e", &root.id.to_hex(), "", "root"]).unwrap()]
+            } else if let Some(proposal) = revision_of {
+                vec![
+                    Tag::hashtag("root"),
+                    Tag::hashtag("revision-root"),
+                    Tag::parse(["e", &proposal.id.to_hex(), "", "reply"]).unwrap(),
+                ]
+            } else {
+                vec![Tag::hashtag("root")]
+            };
+            EventBuilder::new(Kind::GitPatch, created_at.to_string())
+                .tags(tags)
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(&nostr::Keys::generate())
+                .unwrap()
+        }
+
+        #[test]
+        fn sequential_revisions_arent_concurrent() {
+            let proposal = patch(10, None, None);
+            let revision = patch(20, None, Some(&proposal));
+            let patches = vec![
+                proposal.clone(),
+                patch(11, Some(&proposal), None),
+                revision.clone(),
+                patch(21, Some(&revision), None),
+            ];
+            assert!(get_concurrent_revision_roots(&patches).is_empty());
+            assert_eq!(get_revision_roots(&patches)[0].id, revision.id);
+        }
+
+        #[test]
+        fn chain_extended_after_latest_revision_is_concurrent() {
+            let proposal = patch(10, None, None);
+            let revision_a = patch(20, None, Some(&proposal));
+            let revision_b = patch(21, None, Some(&proposal));
+            let patches = vec![
+                proposal.clone(),
+                revision_a.clone(),
+                patch(22, Some(&revision_a), None),
+                revision_b.clone(),
+            ];
+            assert_eq!(
+                get_concurrent_revision_roots(&patches)
+                    .iter()
+                    .map(|e| e.id)
+                    .collect::<Vec<EventId>>(),
+                vec![revision_a.id, revision_b.id],
+            );
+            assert_eq!(
+                get_patches_of_revision(&patches, &revision_b.id),
+                vec![revision_b],
+            );
+        }
+    }
+
+    mod proposal_status {
+        use super::*;
+
+        fn status(keys: &nostr::Keys, kind: Kind, proposal: &Event, created_at: u64) -> Event {
+            EventBuilder::new(kind, "")
+                .tags([Tag::event(proposal.id)])
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(keys)
+                .unwrap()
+        }
+
+        #[test]
+        fn only_maintainers_and_author_change_status() {
+            let maintainer = nostr::Keys::generate();
+            let author = nostr::Keys::generate();
+            let stranger = nostr::Keys::generate();
+            let proposal = EventBuilder::new(Kind::GitPatch, "patch")
+                .sign_with_keys(&author)
+                .unwrap();
+            let maintainers = vec![maintainer.public_key()];
+
+            let mut statuses = vec![
+                status(&stranger, Kind::GitStatusApplied, &proposal, 30),
+                status(&author, Kind::GitStatusApplied, &proposal, 20),
+                status(&author, Kind::GitStatusDraft, &proposal, 10),
+            ];
+            let (kind, third_party) = get_proposal_status(&proposal, &statuses, &maintainers);
+            assert_eq!(kind, Kind::GitStatusDraft);
+            assert_eq!(third_party.len(), 2);
+
+            statuses.insert(
+                0,
+                status(&maintainer, Kind::GitStatusApplied, &proposal, 40),
+            );
+            assert_eq!(
+                get_proposal_status(&proposal, &statuses, &maintainers).0,
+                Kind::GitStatusApplied
+            );
+        }
+
+        #[test]
+        fn statuses_from_a_delegated_key_count_as_the_maintainer() -> Result<()> {
+            let maintainer = nostr::Keys::generate();
+            let bot = nostr::Keys::generate();
+            let proposal = EventBuilder::new(Kind::GitPatch, "patch")
+                .sign_with_keys(&nostr::Keys::generate())
+                .unwrap();
+            let delegation = crate::delegation::create_delegation(
+                &maintainer,
+                &bot.public_key(),
+                &crate::delegation::DEFAULT_DELEGATED_KINDS,
+                Timestamp::now() + 60,
+            )?;
+            let tag: Vec<String> = serde_json::from_str(&delegation.as_json())?;
+            let statuses = vec![
+                EventBuilder::new(Kind::GitStatusApplied, "")
+                    .tags([Tag::event(proposal.id), Tag::parse(&tag)?])
+                    .sign_with_keys(&bot)?,
+            ];
+            assert_eq!(
+                get_proposal_status(&proposal, &statuses, &[maintainer.public_key()]).0,
+                Kind::GitStatusApplied
+            );
+            Ok(())
+        }
+    }
+
+    mod reaction_tallies {
+        use super::*;
+
+        fn reaction(keys: &nostr::Keys, content: &str, tags: Vec<Tag>, created_at: u64) -> Event {
+            EventBuilder::new(Kind::Reaction, content)
+                .tags(tags)
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(keys)
+                .unwrap()
+        }
+
+        #[test]
+        fn latest_reaction_per_author_counts_towards_last_e_tag() {
+            let proposal = EventBuilder::new(Kind::GitPatch, "patch")
+                .sign_with_keys(&nostr::Keys::generate())
+                .unwrap();
+            let patch = EventBuilder::new(Kind::GitPatch, "patch")
+                .sign_with_keys(&nostr::Keys::generate())
+                .unwrap();
+            let fan = nostr::Keys::generate();
+            let changed_mind = nostr::Keys::generate();
+            let critic = nostr::Keys::generate();
+            let on_proposal = vec![Tag::event(proposal.id)];
+            let tallies = get_reaction_tallies(&[
+                reaction(&fan, "+", on_proposal.clone(), 10),
+                reaction(&fan, "👍", on_proposal.clone(), 20),
+                reaction(&changed_mind, "-", on_proposal.clone(), 10),
+                reaction(&changed_mind, "+", on_proposal.clone(), 20),
+                reaction(&critic, "👎", on_proposal.clone(), 10),
+                reaction(&critic, "🚀", on_proposal, 30),
+                reaction(
+                    &critic,
+                    "+",
+                    vec![Tag::event(proposal.id), Tag::event(patch.id)],
+                    10,
+                ),
+            ]);
+            assert_eq!(
+                tallies.get(&proposal.id),
+                Some(&ReactionTally {
+                    likes: 2,
+                    dislikes: 1
+                })
+            );
+            assert_eq!(tallies.get(&proposal.id).unwrap().to_string(), "👍 2 👎 1");
+            assert_eq!(
+                tallies.get(&patch.id),
+                Some(&ReactionTally {
+                    likes: 1,
+                    dislikes: 0
+                })
+            );
+        }
+    }
+
+    mod future_dated {
+        use super::*;
+
+        fn status(created_at: Timestamp) -> Event {
+            EventBuilder::new(Kind::GitStatusOpen, "")
+                .custom_created_at(created_at)
+                .sign_with_keys(&nostr::Keys::generate())
+                .unwrap()
+        }
+
+        #[test]
+        fn future_dated_events_lose_latest_comparisons() {
+            let now = Timestamp::now().as_u64();
+            let older = status(Timestamp::from(now - 60));
+            let future = status(Timestamp::from(now + 24 * 60 * 60));
+            let mut events = vec![
+                future.clone(),
+                status(Timestamp::from(now - 120)),
+                older.clone(),
+            ];
+            sort_newest_first(&mut events);
+            assert_eq!(events.first(), Some(&older));
+            assert_eq!(events.last(), Some(&future));
+            assert!(!without_future_dated(events).contains(&future));
+            assert_eq!(without_future_dated(vec![future.clone()]), vec![future]);
+        }
+    }
+
+    mod format_event_for_dry_run {
+        use super::*;
+
+        #[test]
+        fn lists_id_kind_and_tags() -> Result<()> {
+            let event = EventBuilder::new(Kind::GitStatusApplied, "")
+                .tags([Tag::hashtag("ngit")])
+                .sign_with_keys(&nostr::Keys::generate())?;
+            assert_eq!(
+                format_event_for_dry_run(&event),
+                vec![
+                    format!("id: {}", event.id),
+                    "kind: 1631".to_string(),
+                    "tags:".to_string(),
+                    "  t ngit".to_string(),
+                ]
+            );
+            Ok(())
+        }
+    }
+
+    mod duplicate_proposals {
+        use nostr::Timestamp;
+
+        use super::*;
+
+        fn root_patch(keys: &nostr::Keys, created_at: u64, commit: &str) -> Event {
+            EventBuilder::new(Kind::GitPatch, "patch")
+                .tags([
+                    Tag::hashtag("root"),
+                    Tag::parse(["commit", commit]).unwrap(),
+                ])
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(keys)
+                .unwrap()
+        }
+
+        fn cover_letter(keys: &nostr::Keys, created_at: u64, branch_name: &str) -> Event {
+            EventBuilder::new(
+                Kind::GitPatch,
+                "From ea897e987ea9a7a98e7a987e97987ea98e7a3334 Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/1] add feature\n\n",
+            )
+            .tags([
+                Tag::hashtag("root"),
+                Tag::hashtag("cover-letter"),
+                Tag::custom(
+                    TagKind::Custom(std::borrow::Cow::Borrowed("branch-name")),
+                    vec![branch_name.to_string()],
+                ),
+            ])
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+        }
+
+        fn patch_of(keys: &nostr::Keys, root: &Event, commit: &str) -> Event {
+            EventBuilder::new(Kind::GitPatch, "patch")
+                .tags([
+                    Tag::from_standardized(nostr_sdk::TagStandard::Event {
+                        event_id: root.id,
+                        relay_url: None,
+                        marker: Some(Marker::Root),
+                        public_key: None,
+                        uppercase: false,
+                    }),
+                    Tag::parse(["commit", commit]).unwrap(),
+                ])
+                .sign_with_keys(keys)
+                .unwrap()
+        }
+
+        #[test]
+        fn cover_letters_match_on_their_commits_not_branch_name() {
+            let keys = nostr::Keys::generate();
+            let original = cover_letter(&keys, 10, "add-feature");
+            let resent = cover_letter(&keys, 20, "add-feature-2");
+            let different = cover_letter(&keys, 30, "add-feature");
+            let patches = vec![
+                patch_of(&keys, &original, &"a".repeat(40)),
+                patch_of(&keys, &resent, &"a".repeat(40)),
+                patch_of(&keys, &different, &"b".repeat(40)),
+            ];
+            assert_eq!(
+                get_duplicate_proposals(&[original.clone(), resent.clone(), different], &patches),
+                HashMap::from([(original.id, vec![resent])]),
+            );
+        }
+
+        #[test]
+        fn same_author_and_commit_are_duplicates_of_the_earliest() {
+            let keys = nostr::Keys::generate();
+            let original = root_patch(&keys, 10, &"a".repeat(40));
+            let resent = root_patch(&keys, 20, &"a".repeat(40));
+            let proposals = vec![
+                resent.clone(),
+                root_patch(&keys, 15, &"b".repeat(40)),
+                original.clone(),
+                root_patch(&nostr::Keys::generate(), 30, &"a".repeat(40)),
+            ];
+            assert_eq!(
+                get_duplicate_proposals(&proposals, &[]),
+                HashMap::from([(original.id, vec![resent])]),
+            );
+        }
+    }
+
+    #[cfg(feature = "native")]
+    mod branch_name_template {
+        use super::*;
+
+        fn cover_letter() -> CoverLetter {
+            CoverLetter {
+                title: "add feature".to_string(),
+                description: String::new(),
+                branch_name: "add-feature".to_string(),
+                event_id: Some(
+                    EventId::from_hex(
+                        "431b84edc0d2fa118d63faa3c2db9c73d630a5ae431b84edc0d2fa118d63faa3",
+                    )
+                    .unwrap(),
+                ),
+                author: None,
+            }
+        }
+
+        #[test]
+        fn default_template_matches_previous_naming() -> Result<()> {
+            assert_eq!(
+                cover_letter().get_branch_name_from_template(DEFAULT_BRANCH_NAME_TEMPLATE, None)?,
+                "pr/add-feature(431b84ed)",
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn custom_template() -> Result<()> {
+            assert_eq!(
+                cover_letter().get_branch_name_from_template("proposals/{id}-{slug}", None)?,
+                "proposals/431b84ed-add-feature",
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn number_template() -> Result<()> {
+            assert_eq!(
+                cover_letter().get_branch_name_from_template("pr/{number}-{slug}", Some(42))?,
+                "pr/42-add-feature",
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn number_template_errors_without_a_number() {
+            assert!(
+                cover_letter()
+                    .get_branch_name_from_template("pr/{number}-{slug}", None)
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn invalid_branch_names_error() {
+            assert!(
+                cover_letter()
+                    .get_branch_name_from_template("{slug}..{id}", None)
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn slug_extracted_from_branch_name_fitting_template() {
+            assert_eq!(
+                match_branch_name_template("{author}/{slug}", "npub1abc/add-feature"),
+                Some("add-feature".to_string()),
+            );
+            assert_eq!(
+                match_branch_name_template(
+                    "proposals/{id}-{slug}",
+                    "proposals/431b84ed-add-feature"
+                ),
+                Some("add-feature".to_string()),
+            );
+            assert_eq!(
+                match_branch_name_template("pr/{number}-{slug}", "pr/42-add-feature"),
+                Some("add-feature".to_string()),
+            );
+            assert_eq!(
+                match_branch_name_template("proposals/{id}-{slug}", "feature/add-feature"),
+                None,
+            );
+        }
+    }
+
     mod event_to_cover_letter {
         use super::*;
 
@@ -0,0 +1,442 @@
+use anyhow::{Context, Result, bail};
+use nostr::EventId;
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use serde_json::Value;
+
+use crate::{
+    forge::{PullRequest, PullRequestState, fetch_ref, send_json_request, token_remote_callbacks},
+    git::{Repo, sha1_to_oid},
+};
+
+static GITHUB_API_URL: &str = "https://api.github.com";
+
+/// hidden line in the body of pull requests exported from nostr proposals
+static PROPOSAL_MARKER_PREFIX: &str = "<!-- nostr-proposal: ";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitHubRepo {
+    pub owner: String,
+    pub name: String,
+}
+
+impl GitHubRepo {
+    /// from `owner/name`, an https url or an ssh url
+    pub fn parse(s: &str) -> Result<Self> {
+        let path = s
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("ssh://")
+            .trim_start_matches("git@")
+            .trim_start_matches("github.com")
+            .trim_start_matches([':', '/'])
+            .trim_end_matches('/')
+            .trim_end_matches(".git");
+        match path.split('/').collect::<Vec<&str>>().as_slice() {
+            [owner, name] if !owner.is_empty() && !name.is_empty() => Ok(Self {
+                owner: (*owner).to_string(),
+                name: (*name).to_string(),
+            }),
+            _ => bail!("invalid GitHub repository \"{s}\". expected owner/name"),
+        }
+    }
+
+    pub fn clone_url(&self) -> String {
+        format!("https://github.com/{}/{}.git", self.owner, self.name)
+    }
+}
+
+impl std::fmt::Display for GitHubRepo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)
+    }
+}
+
+pub fn pull_request_from_json(value: &Value) -> Result<PullRequest> {
+    let str_at = |pointer: &str| value.pointer(pointer).and_then(Value::as_str);
+    Ok(PullRequest {
+        number: value
+            .get("number")
+            .and_then(Value::as_u64)
+            .context("pull request is missing a number")?,
+        title: str_at("/title").unwrap_or_default().to_string(),
+        body: str_at("/body").unwrap_or_default().to_string(),
+        state: if value.get("merged_at").is_some_and(|v| !v.is_null()) {
+            PullRequestState::Merged
+        } else if str_at("/state") == Some("open") {
+            PullRequestState::Open
+        } else {
+            PullRequestState::Closed
+        },
+        html_url: str_at("/html_url")
+            .context("pull request is missing html_url")?
+            .to_string(),
+        author: str_at("/user/login").unwrap_or("ghost").to_string(),
+        author_name: None,
+        author_url: str_at("/user/html_url").map(str::to_string),
+        head_ref: str_at("/head/ref").unwrap_or_default().to_string(),
+        base_ref: str_at("/base/ref").unwrap_or_default().to_string(),
+        author_npub: None,
+    })
+}
+
+/// details of a repository listed on GitHub, for prefilling `ngit init`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GitHubRepoMetadata {
+    pub name: String,
+    pub description: Option<String>,
+    pub topics: Vec<String>,
+    pub homepage: Option<String>,
+    /// SPDX identifier eg. MIT
+    pub license: Option<String>,
+    pub language: Option<String>,
+}
+
+pub fn repo_metadata_from_json(value: &Value) -> GitHubRepoMetadata {
+    let non_empty = |pointer: &str| {
+        value
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    GitHubRepoMetadata {
+        name: non_empty("/name").unwrap_or_default(),
+        description: non_empty("/description"),
+        topics: value
+            .get("topics")
+            .and_then(Value::as_array)
+            .map(|topics| {
+                topics
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        homepage: non_empty("/homepage"),
+        // GitHub reports licenses it can't identify as NOASSERTION
+        license: non_empty("/license/spdx_id").filter(|id| id != "NOASSERTION"),
+        language: non_empty("/language"),
+    }
+}
+
+/// line to include in the body of a pull request exported from a proposal
+pub fn proposal_marker(proposal_id: &EventId) -> String {
+    format!("{PROPOSAL_MARKER_PREFIX}{} -->", proposal_id.to_hex())
+}
+
+/// id of the proposal a pull request was exported from
+pub fn proposal_id_from_pr_body(body: &str) -> Option<EventId> {
+    let (_, rest) = body.split_once(PROPOSAL_MARKER_PREFIX)?;
+    let (id, _) = rest.split_once(" -->")?;
+    EventId::from_hex(id.trim()).ok()
+}
+
+/// id of the proposal `pull_request` was exported from, if the bridge opened
+/// it as `bridge_login`. anyone can copy the marker into their own pull
+/// request so it isn't trusted on others
+pub fn bridged_proposal_id(pull_request: &PullRequest, bridge_login: &str) -> Option<EventId> {
+    if pull_request.author.eq_ignore_ascii_case(bridge_login) {
+        proposal_id_from_pr_body(&pull_request.body)
+    } else {
+        None
+    }
+}
+
+pub struct GitHubClient {
+    token: String,
+    http: reqwest::Client,
+}
+
+impl GitHubClient {
+    /// an empty `token` makes unauthenticated requests, which only see public
+    /// repositories and are rate limited
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value> {
+        let mut request = self
+            .http
+            .request(method, format!("{GITHUB_API_URL}{path}"))
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+        if !self.token.is_empty() {
+            request = request.header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.token),
+            );
+        }
+        if let Some(body) = body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string());
+        }
+        send_json_request(request, "GitHub").await
+    }
+
+    /// login of the account the token belongs to
+    pub async fn authenticated_user(&self) -> Result<String> {
+        Ok(self
+            .request(reqwest::Method::GET, "/user", None)
+            .await?
+            .get("login")
+            .and_then(Value::as_str)
+            .context("GitHub api didn't return the login of the token's user")?
+            .to_string())
+    }
+
+    pub async fn get_repo_metadata(&self, repo: &GitHubRepo) -> Result<GitHubRepoMetadata> {
+        Ok(repo_metadata_from_json(
+            &self
+                .request(reqwest::Method::GET, &format!("/repos/{repo}"), None)
+                .await?,
+        ))
+    }
+
+    /// all pull requests, open and closed, most recently created first
+    pub async fn list_pull_requests(&self, repo: &GitHubRepo) -> Result<Vec<PullRequest>> {
+        let mut pull_requests = vec![];
+        for page in 1..=10 {
+            let response = self
+                .request(
+                    reqwest::Method::GET,
+                    &format!("/repos/{repo}/pulls?state=all&per_page=100&page={page}"),
+                    None,
+                )
+                .await?;
+            let items = response
+                .as_array()
+                .context("GitHub api didn't return a list of pull requests")?;
+            for item in items {
+                pull_requests.push(pull_request_from_json(item)?);
+            }
+            if items.len() < 100 {
+                break;
+            }
+        }
+        Ok(pull_requests)
+    }
+
+    pub async fn create_pull_request(
+        &self,
+        repo: &GitHubRepo,
+        title: &str,
+        body: &str,
+        head: &str,
+        base: &str,
+    ) -> Result<PullRequest> {
+        pull_request_from_json(
+            &self
+                .request(
+                    reqwest::Method::POST,
+                    &format!("/repos/{repo}/pulls"),
+                    Some(serde_json::json!({
+                        "title": title,
+                        "body": body,
+                        "head": head,
+                        "base": base,
+                    })),
+                )
+                .await?,
+        )
+    }
+
+    pub async fn close_pull_request(
+        &self,
+        repo: &GitHubRepo,
+        number: u64,
+        comment: &str,
+    ) -> Result<()> {
+        self.request(
+            reqwest::Method::POST,
+            &format!("/repos/{repo}/issues/{number}/comments"),
+            Some(serde_json::json!({ "body": comment })),
+        )
+        .await?;
+        self.request(
+            reqwest::Method::PATCH,
+            &format!("/repos/{repo}/pulls/{number}"),
+            Some(serde_json::json!({ "state": "closed" })),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// fetch the head of pull request `number` into `refs/ngit-bridge/github/`
+pub fn fetch_pull_request(
+    git_repo: &Repo,
+    repo: &GitHubRepo,
+    token: &str,
+    number: u64,
+) -> Result<Sha1Hash> {
+    fetch_ref(
+        git_repo,
+        &repo.clone_url(),
+        &format!("refs/pull/{number}/head"),
+        &format!("refs/ngit-bridge/github/pull/{number}"),
+        token_remote_callbacks("x-access-token", token),
+    )
+    .context(format!(
+        "failed to fetch pull request #{number} from {repo}"
+    ))
+}
+
+/// force push `commit` to `branch` on GitHub
+pub fn push_branch(
+    git_repo: &Repo,
+    repo: &GitHubRepo,
+    token: &str,
+    commit: &Sha1Hash,
+    branch: &str,
+) -> Result<()> {
+    let local_ref = format!("refs/ngit-bridge/github/export/{branch}");
+    git_repo.git_repo.reference(
+        &local_ref,
+        sha1_to_oid(commit)?,
+        true,
+        "ngit bridge github export",
+    )?;
+    let mut push_options = git2::PushOptions::new();
+    push_options.remote_callbacks(token_remote_callbacks("x-access-token", token));
+    git_repo
+        .git_repo
+        .remote_anonymous(&repo.clone_url())?
+        .push(
+            &[format!("+{local_ref}:refs/heads/{branch}")],
+            Some(&mut push_options),
+        )
+        .context(format!("failed to push {branch} to {repo}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repo_from_shorthand_and_urls() -> Result<()> {
+        let expected = GitHubRepo {
+            owner: "nostr-protocol".to_string(),
+            name: "nips".to_string(),
+        };
+        for input in [
+            "nostr-protocol/nips",
+            "https://github.com/nostr-protocol/nips",
+            "https://github.com/nostr-protocol/nips.git",
+            "git@github.com:nostr-protocol/nips.git",
+        ] {
+            assert_eq!(GitHubRepo::parse(input)?, expected, "{input}");
+        }
+        assert!(GitHubRepo::parse("nips").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn pull_request_state_from_json() -> Result<()> {
+        let json = |state: &str, merged_at: Value| {
+            serde_json::json!({
+                "number": 7,
+                "title": "add feature",
+                "body": null,
+                "state": state,
+                "merged_at": merged_at,
+                "html_url": "https://github.com/o/r/pull/7",
+                "user": { "login": "alice" },
+                "head": { "ref": "feature" },
+                "base": { "ref": "main" },
+            })
+        };
+        let open = pull_request_from_json(&json("open", Value::Null))?;
+        assert_eq!(open.state, PullRequestState::Open);
+        assert_eq!(open.body, "");
+        assert_eq!(open.author, "alice");
+        assert_eq!(
+            pull_request_from_json(&json("closed", Value::Null))?.state,
+            PullRequestState::Closed,
+        );
+        assert_eq!(
+            pull_request_from_json(&json("closed", "2024-01-01T00:00:00Z".into()))?.state,
+            PullRequestState::Merged,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn repo_metadata_from_json_skips_blanks_and_unknown_licenses() {
+        let metadata = repo_metadata_from_json(&serde_json::json!({
+            "name": "nips",
+            "description": "Nostr Implementation Possibilities",
+            "topics": ["nostr", "protocol"],
+            "homepage": "",
+            "license": { "spdx_id": "NOASSERTION" },
+            "language": null,
+        }));
+        assert_eq!(
+            metadata,
+            GitHubRepoMetadata {
+                name: "nips".to_string(),
+                description: Some("Nostr Implementation Possibilities".to_string()),
+                topics: vec!["nostr".to_string(), "protocol".to_string()],
+                homepage: None,
+                license: None,
+                language: None,
+            }
+        );
+        assert_eq!(
+            repo_metadata_from_json(&serde_json::json!({ "license": { "spdx_id": "MIT" } }))
+                .license
+                .as_deref(),
+            Some("MIT")
+        );
+    }
+
+    #[test]
+    fn proposal_marker_round_trips() {
+        let id = EventId::all_zeros();
+        let body = format!("description\n\n{}", proposal_marker(&id));
+        assert_eq!(proposal_id_from_pr_body(&body), Some(id));
+        assert_eq!(proposal_id_from_pr_body("description"), None);
+    }
+
+    #[test]
+    fn proposal_marker_only_trusted_on_pull_requests_opened_by_the_bridge() -> Result<()> {
+        let id = EventId::all_zeros();
+        let pull_request = |login: &str| {
+            pull_request_from_json(&serde_json::json!({
+                "number": 7,
+                "title": "add feature",
+                "body": format!("description\n\n{}", proposal_marker(&id)),
+                "state": "closed",
+                "merged_at": null,
+                "html_url": "https://github.com/o/r/pull/7",
+                "user": { "login": login },
+                "head": { "ref": "feature" },
+                "base": { "ref": "main" },
+            }))
+        };
+        assert_eq!(
+            bridged_proposal_id(&pull_request("ngit-bot")?, "ngit-bot"),
+            Some(id)
+        );
+        assert_eq!(
+            bridged_proposal_id(&pull_request("NGIT-bot")?, "ngit-bot"),
+            Some(id)
+        );
+        assert_eq!(
+            bridged_proposal_id(&pull_request("mallory")?, "ngit-bot"),
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use serde_json::Value;
+
+use crate::{
+    forge::{
+        PullRequest, PullRequestState, fetch_ref, send_json_request, split_project_url,
+        token_remote_callbacks,
+    },
+    git::Repo,
+};
+
+pub struct GitLabClient {
+    instance: String,
+    project: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl GitLabClient {
+    /// `project_url` eg. `https://gitlab.example.com/group/project`
+    pub fn new(project_url: &str, token: Option<&str>) -> Result<Self> {
+        let (instance, project) = split_project_url(project_url)?;
+        Ok(Self {
+            instance,
+            project,
+            token: token.map(str::to_string),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    pub fn clone_url(&self) -> String {
+        format!("{}/{}.git", self.instance, self.project)
+    }
+
+    /// open merge requests, most recently created first
+    pub async fn list_open_merge_requests(&self) -> Result<Vec<PullRequest>> {
+        let mut merge_requests = vec![];
+        for page in 1..=10 {
+            let mut request = self.http.get(format!(
+                "{}/api/v4/projects/{}/merge_requests?state=opened&per_page=100&page={page}",
+                self.instance,
+                urlencoding::encode(&self.project),
+            ));
+            if let Some(token) = &self.token {
+                request = request.header("PRIVATE-TOKEN", token);
+            }
+            let response = send_json_request(request, "GitLab").await?;
+            let items = response
+                .as_array()
+                .context("GitLab api didn't return a list of merge requests")?;
+            for item in items {
+                merge_requests.push(merge_request_from_json(item)?);
+            }
+            if items.len() < 100 {
+                break;
+            }
+        }
+        Ok(merge_requests)
+    }
+
+    /// fetch the head of merge request `iid` into `refs/ngit-bridge/gitlab/`
+    pub fn fetch_merge_request(&self, git_repo: &Repo, iid: u64) -> Result<Sha1Hash> {
+        fetch_ref(
+            git_repo,
+            &self.clone_url(),
+            &format!("refs/merge-requests/{iid}/head"),
+            &format!("refs/ngit-bridge/gitlab/merge-requests/{iid}"),
+            if let Some(token) = &self.token {
+                token_remote_callbacks("oauth2", token)
+            } else {
+                git2::RemoteCallbacks::new()
+            },
+        )
+        .context(format!(
+            "failed to fetch merge request !{iid} from {}",
+            self.project
+        ))
+    }
+}
+
+pub fn merge_request_from_json(value: &Value) -> Result<PullRequest> {
+    let str_at = |pointer: &str| value.pointer(pointer).and_then(Value::as_str);
+    Ok(PullRequest {
+        number: value
+            .get("iid")
+            .and_then(Value::as_u64)
+            .context("merge request is missing an iid")?,
+        title: str_at("/title").unwrap_or_default().to_string(),
+        body: str_at("/description").unwrap_or_default().to_string(),
+        state: match str_at("/state") {
+            Some("opened") => PullRequestState::Open,
+            Some("merged") => PullRequestState::Merged,
+            _ => PullRequestState::Closed,
+        },
+        html_url: str_at("/web_url")
+            .context("merge request is missing web_url")?
+            .to_string(),
+        author: str_at("/author/username").unwrap_or("ghost").to_string(),
+        author_name: str_at("/author/name").map(str::to_string),
+        author_url: str_at("/author/web_url").map(str::to_string),
+        head_ref: str_at("/source_branch").unwrap_or_default().to_string(),
+        base_ref: str_at("/target_branch").unwrap_or_default().to_string(),
+        author_npub: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_request_from_json_credits_author() -> Result<()> {
+        let merge_request = merge_request_from_json(&serde_json::json!({
+            "iid": 12,
+            "title": "fix build",
+            "description": "details",
+            "state": "opened",
+            "web_url": "https://gitlab.example.com/g/p/-/merge_requests/12",
+            "author": {
+                "username": "alice",
+                "name": "Alice",
+                "web_url": "https://gitlab.example.com/alice",
+            },
+            "source_branch": "fix-build",
+            "target_branch": "main",
+        }))?;
+        assert_eq!(merge_request.number, 12);
+        assert_eq!(merge_request.state, PullRequestState::Open);
+        assert_eq!(merge_request.author_name, Some("Alice".to_string()));
+        assert_eq!(
+            merge_request.attribution_tags()[1].as_slice(),
+            [
+                "imported-author",
+                "alice",
+                "Alice",
+                "https://gitlab.example.com/alice"
+            ],
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use nostr::{Event, JsonUtil, Kind};
+
+use crate::{
+    git::{Repo, RepoActions},
+    git_events::{event_is_patch_set_root, status_kinds},
+};
+
+/// hook run when `event` is fetched: `proposal`, `patch`, `status` or `issue`
+pub fn hook_name(event: &Event) -> Option<&'static str> {
+    if event_is_patch_set_root(event) {
+        Some("proposal")
+    } else if event.kind.eq(&Kind::GitPatch) {
+        Some("patch")
+    } else if status_kinds().contains(&event.kind) {
+        Some("status")
+    } else if event.kind.eq(&Kind::GitIssue) {
+        Some("issue")
+    } else {
+        None
+    }
+}
+
+/// shell command from git config `nostr.hooks.<name>` and the executable
+/// `.git/hooks/ngit-<name>`
+fn hook_commands(git_repo: &Repo, name: &str) -> Result<Vec<String>> {
+    let mut commands = vec![];
+    if let Some(command) = git_repo.get_git_config_item(&format!("nostr.hooks.{name}"), None)? {
+        commands.push(command);
+    }
+    let path = git_repo
+        .git_repo
+        .path()
+        .join("hooks")
+        .join(format!("ngit-{name}"));
+    if path.is_file() {
+        commands.push(format!(
+            "'{}'",
+            path.to_string_lossy().replace('\'', "'\\''")
+        ));
+    }
+    Ok(commands)
+}
+
+fn run_hook(git_repo_path: &Path, name: &str, command: &str, event: &Event) -> Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .current_dir(git_repo_path)
+        .env("NGIT_HOOK", name)
+        .env("NGIT_EVENT_ID", event.id.to_hex())
+        .env("NGIT_EVENT_KIND", event.kind.as_u16().to_string())
+        .stdin(Stdio::piped())
+        // stdout may be the remote helper's channel to git
+        .stdout(Stdio::from(std::io::stderr()))
+        .spawn()
+        .context(format!("failed to run {name} hook"))?;
+    let written = child
+        .stdin
+        .take()
+        .context("failed to open hook stdin")?
+        .write_all(event.as_json().as_bytes());
+    // wait even if the write failed so the hook isn't left as a zombie
+    if !child.wait()?.success() {
+        bail!("{name} hook `{command}` failed");
+    }
+    match written {
+        // hooks don't have to read the event
+        Err(error) if error.kind() != std::io::ErrorKind::BrokenPipe => {
+            Err(error).context(format!("failed to pass event to {name} hook"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// pass a newly fetched event as json on stdin to the user's hooks for its
+/// kind. hook failures are reported but don't interrupt fetching
+pub fn run_event_hooks(git_repo_path: &Path, event: &Event) {
+    let Some(name) = hook_name(event) else {
+        return;
+    };
+    let Ok(git_repo) = Repo::from_path(&git_repo_path.to_path_buf()) else {
+        return;
+    };
+    match hook_commands(&git_repo, name) {
+        Ok(commands) => {
+            for command in commands {
+                if let Err(error) = run_hook(git_repo_path, name, &command, event) {
+                    eprintln!("warning: {error:#}");
+                }
+            }
+        }
+        Err(error) => eprintln!("warning: failed to read {name} hooks: {error:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    fn event(kind: Kind, tags: Vec<Tag>) -> Event {
+        EventBuilder::new(kind, "")
+            .tags(tags)
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn hook_name_by_event_kind() {
+        assert_eq!(
+            hook_name(&event(Kind::GitPatch, vec![Tag::hashtag("root")])),
+            Some("proposal")
+        );
+        assert_eq!(hook_name(&event(Kind::GitPatch, vec![])), Some("patch"));
+        assert_eq!(
+            hook_name(&event(Kind::GitStatusApplied, vec![])),
+            Some("status")
+        );
+        assert_eq!(hook_name(&event(Kind::GitIssue, vec![])), Some("issue"));
+        assert_eq!(hook_name(&event(Kind::TextNote, vec![])), None);
+    }
+
+    #[test]
+    fn configured_hook_receives_event_json() -> Result<()> {
+        let test_repo = GitTestRepo::default();
+        let git_repo = Repo::from_path(&test_repo.dir)?;
+        git_repo.save_git_config_item("nostr.hooks.status", "cat > status.json", false)?;
+        let status = event(Kind::GitStatusClosed, vec![]);
+        run_event_hooks(&test_repo.dir, &status);
+        assert_eq!(
+            std::fs::read_to_string(test_repo.dir.join("status.json"))?,
+            status.as_json()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hooks_ignoring_the_event_succeed_and_failures_are_reported() {
+        let test_repo = GitTestRepo::default();
+        let status = event(Kind::GitStatusClosed, vec![]);
+        assert!(run_hook(&test_repo.dir, "status", "true", &status).is_ok());
+        assert!(run_hook(&test_repo.dir, "status", "exit 1", &status).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+type Cleanup = Box<dyn FnOnce() + Send>;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static NEXT_CLEANUP_ID: AtomicU64 = AtomicU64::new(0);
+
+static CLEANUPS: Mutex<Vec<(u64, Cleanup)>> = Mutex::new(Vec::new());
+
+/// whether ctrl-c has been pressed. loops should stop starting new work
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// runs its cleanup if ctrl-c is pressed before it is dropped
+#[must_use = "the cleanup is unregistered when the guard is dropped"]
+pub struct CleanupGuard(u64);
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        if let Ok(mut cleanups) = CLEANUPS.lock() {
+            cleanups.retain(|(id, _)| *id != self.0);
+        }
+    }
+}
+
+/// undo partial work with `cleanup` if interrupted while the guard is held
+pub fn on_interrupt(cleanup: impl FnOnce() + Send + 'static) -> CleanupGuard {
+    let id = NEXT_CLEANUP_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut cleanups) = CLEANUPS.lock() {
+        cleanups.push((id, Box::new(cleanup)));
+    }
+    CleanupGuard(id)
+}
+
+/// mark as interrupted and run registered cleanups, most recent first
+fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+    let cleanups = CLEANUPS
+        .lock()
+        .map(|mut cleanups| std::mem::take(&mut *cleanups))
+        .unwrap_or_default();
+    for (_, cleanup) in cleanups.into_iter().rev() {
+        cleanup();
+    }
+}
+
+/// on ctrl-c run cleanups, restore the cursor hidden by prompts and progress
+/// bars and exit with 130. prompts block the thread they run on so this
+/// listens on a separate task
+pub fn exit_on_ctrl_c() {
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            interrupt();
+            let term = console::Term::stderr();
+            let _ = term.show_cursor();
+            let _ = term.write_line("");
+            std::process::exit(130);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registered(guard: &CleanupGuard) -> bool {
+        CLEANUPS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(id, _)| *id == guard.0)
+    }
+
+    #[test]
+    fn dropping_guard_unregisters_cleanup() {
+        let first = on_interrupt(|| {});
+        let first_id = first.0;
+        let second = on_interrupt(|| {});
+        assert!(registered(&first));
+        drop(first);
+        assert!(registered(&second));
+        assert!(
+            !CLEANUPS
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|(id, _)| *id == first_id)
+        );
+    }
+}
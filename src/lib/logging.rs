@@ -0,0 +1,96 @@
+use anyhow::{Context, Result, anyhow};
+use tracing_subscriber::EnvFilter;
+
+/// a level such as `debug`, applied to every crate, or `EnvFilter` directives
+/// such as `ngit=debug,nostr_relay_pool=warn`
+pub fn parse_log_filter(filter: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(filter.trim()).context(format!(
+        "invalid log level \"{filter}\". expected off, error, warn, info, debug, trace or directives such as ngit=debug"
+    ))
+}
+
+/// log to stderr at `--log-level` or, when not given, `log-level` from ngit
+/// settings or `NGIT_LOG`. nothing is logged when neither is set
+pub fn init(log_level: Option<&str>) -> Result<()> {
+    let Some(level) = log_level
+        .map(str::to_string)
+        .or_else(|| crate::settings::settings().log_level.clone())
+    else {
+        return Ok(());
+    };
+    let filter = parse_log_filter(&level)?;
+    if level.trim().eq_ignore_ascii_case("off") {
+        return Ok(());
+    }
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .try_init()
+        .map_err(|error| anyhow!(error))
+        .context("failed to set up logging")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn logged_with(filter: &str) -> Result<String> {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(parse_log_filter(filter)?)
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .without_time()
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::debug_span!("fetch", relay = "wss://relay.example").entered();
+            tracing::debug!(events = 3, "fetched");
+            tracing::trace!("not logged");
+        });
+        Ok(String::from_utf8(buffer.0.lock().unwrap().clone())?)
+    }
+
+    #[test]
+    fn parses_levels_and_directives() {
+        assert!(parse_log_filter("debug").is_ok());
+        assert!(parse_log_filter(" ngit=debug,nostr_relay_pool=warn ").is_ok());
+        assert!(parse_log_filter("ngit=loud").is_err());
+    }
+
+    #[test]
+    fn logs_events_at_or_above_level_within_spans() -> Result<()> {
+        let output = logged_with("debug")?;
+        assert!(output.contains("DEBUG"));
+        assert!(output.contains("fetch{relay=\"wss://relay.example\"}"));
+        assert!(output.contains("fetched events=3"));
+        assert!(!output.contains("not logged"));
+        Ok(())
+    }
+
+    #[test]
+    fn directives_filter_by_target() -> Result<()> {
+        assert!(logged_with("nostr_relay_pool=debug")?.is_empty());
+        assert!(logged_with("ngit=debug")?.contains("fetched events=3"));
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+use std::{collections::HashMap, future::Future, path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use nostr::{
+    Event, PublicKey, Timestamp, UnsignedEvent,
+    nips::nip46::NostrConnectURI,
+    signer::{SignerBackend, SignerError},
+};
+use nostr_connect::client::NostrConnect;
+use nostr_sdk::{Keys, NostrSigner, RelayUrl};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::get_dirs;
+
+/// how long to wait for the user to approve a request in their signer
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// delay before the first reconnection attempt, doubled for each further one
+static INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+static MAX_ATTEMPTS: u32 = 4;
+
+/// a remote signer connection remembered between runs so the user public key
+/// and relays don't need to be negotiated each time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BunkerSession {
+    pub bunker_uri: String,
+    pub user_public_key: String,
+    pub relays: Vec<String>,
+    pub last_connected: u64,
+    /// NIP-46 permissions requested at login, eg `sign_event:1617`. empty if
+    /// none were requested up front
+    #[serde(default)]
+    pub perms: Vec<String>,
+}
+
+fn sessions_path() -> Result<PathBuf> {
+    Ok(get_dirs()?.cache_dir().join("bunker-sessions.json"))
+}
+
+/// sessions keyed by app key public key
+fn load_sessions() -> HashMap<String, BunkerSession> {
+    sessions_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_sessions(sessions: &HashMap<String, BunkerSession>) -> Result<()> {
+    let path = sessions_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("failed to create cache directory")?;
+    }
+    std::fs::write(path, serde_json::to_string(sessions)?)
+        .context("failed to save remote signer session")
+}
+
+pub fn load_bunker_session(app_public_key: &PublicKey) -> Option<BunkerSession> {
+    load_sessions().remove(&app_public_key.to_hex())
+}
+
+pub fn save_bunker_session(app_public_key: &PublicKey, session: BunkerSession) -> Result<()> {
+    let mut sessions = load_sessions();
+    sessions.insert(app_public_key.to_hex(), session);
+    save_sessions(&sessions)
+}
+
+pub fn remove_bunker_session(app_public_key: &PublicKey) -> Result<()> {
+    let mut sessions = load_sessions();
+    if sessions.remove(&app_public_key.to_hex()).is_some() {
+        save_sessions(&sessions)?;
+    }
+    Ok(())
+}
+
+/// the remote signer no longer accepts requests from this app
+fn is_access_revoked(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["unauthorized", "not authorized", "revoked"]
+        .iter()
+        .any(|reason| error.contains(reason))
+}
+
+/// the user declined this one request in their signer
+fn is_declined(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["denied", "rejected"]
+        .iter()
+        .any(|reason| error.contains(reason))
+}
+
+/// a timeout means the user didn't approve in time so retrying won't help
+fn is_retryable(error: &str) -> bool {
+    !is_access_revoked(error) && !is_declined(error) && !error.to_lowercase().contains("timeout")
+}
+
+/// the session saved for `uri`'s signer, if it was saved for the same one
+fn session_for_uri(app_public_key: &PublicKey, uri: &NostrConnectURI) -> Option<BunkerSession> {
+    let session = load_bunker_session(app_public_key)?;
+    match (NostrConnectURI::parse(&session.bunker_uri).ok()?, uri) {
+        (
+            NostrConnectURI::Bunker {
+                remote_signer_public_key: saved,
+                ..
+            },
+            NostrConnectURI::Bunker {
+                remote_signer_public_key,
+                ..
+            },
+        ) if saved != *remote_signer_public_key => None,
+        _ => Some(session),
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF * 2u32.pow(attempt)
+}
+
+/// a NIP-46 signer that only connects when first used and reconnects with
+/// exponential backoff when relays drop the connection
+#[derive(Debug)]
+pub struct ReconnectingNostrConnect {
+    uri: NostrConnectURI,
+    app_keys: Keys,
+    user_public_key: OnceCell<PublicKey>,
+    session: Option<BunkerSession>,
+    connection: Mutex<Option<Arc<NostrConnect>>>,
+}
+
+impl ReconnectingNostrConnect {
+    pub fn new(uri: NostrConnectURI, app_keys: Keys, user_public_key: Option<PublicKey>) -> Self {
+        let session = session_for_uri(&app_keys.public_key(), &uri);
+        let user_public_key = user_public_key.or_else(|| {
+            session
+                .as_ref()
+                .and_then(|session| PublicKey::parse(&session.user_public_key).ok())
+        });
+        Self {
+            uri,
+            app_keys,
+            user_public_key: OnceCell::new_with(user_public_key),
+            session,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// `uri` with the relays the signer last answered on, which are the most
+    /// recent login's when another config still has an older bunker url
+    fn connection_uri(&self) -> NostrConnectURI {
+        match (&self.uri, &self.session) {
+            (
+                NostrConnectURI::Bunker {
+                    remote_signer_public_key,
+                    secret,
+                    ..
+                },
+                Some(session),
+            ) if !session.relays.is_empty() => NostrConnectURI::Bunker {
+                remote_signer_public_key: *remote_signer_public_key,
+                relays: session
+                    .relays
+                    .iter()
+                    .filter_map(|relay| RelayUrl::parse(relay).ok())
+                    .collect(),
+                secret: secret.clone(),
+            },
+            _ => self.uri.clone(),
+        }
+    }
+
+    /// the user public key if known without contacting the signer
+    pub fn known_user_public_key(&self) -> Option<PublicKey> {
+        self.user_public_key.get().copied()
+    }
+
+    async fn connection(&self) -> Result<Arc<NostrConnect>, SignerError> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+        let signer = NostrConnect::new(
+            self.connection_uri(),
+            self.app_keys.clone(),
+            REQUEST_TIMEOUT,
+            None,
+        )
+        .map_err(SignerError::backend)?;
+        if let Some(public_key) = self.user_public_key.get() {
+            signer
+                .non_secure_set_user_public_key(*public_key)
+                .map_err(SignerError::backend)?;
+        }
+        let signer = Arc::new(signer);
+        *connection = Some(signer.clone());
+        Ok(signer)
+    }
+
+    async fn with_reconnect<T, F, Fut>(&self, request: F) -> Result<T, SignerError>
+    where
+        F: Fn(Arc<NostrConnect>) -> Fut,
+        Fut: Future<Output = Result<T, SignerError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let error = match request(self.connection().await?).await {
+                Ok(res) => {
+                    self.remember_session().await;
+                    return Ok(res);
+                }
+                Err(error) => error,
+            };
+            let message = error.to_string();
+            if is_access_revoked(&message) {
+                let _ = remove_bunker_session(&self.app_keys.public_key());
+                return Err(SignerError::backend(std::io::Error::other(format!(
+                    "remote signer refused access: {message}. reauthorize with `ngit account login`"
+                ))));
+            }
+            if is_declined(&message) {
+                return Err(SignerError::backend(std::io::Error::other(format!(
+                    "remote signer declined the request: {message}"
+                ))));
+            }
+            attempt += 1;
+            if !is_retryable(&message) || attempt >= MAX_ATTEMPTS {
+                return Err(error);
+            }
+            // start a fresh connection in case the relay connection is stuck
+            *self.connection.lock().await = None;
+            tokio::time::sleep(backoff(attempt - 1)).await;
+        }
+    }
+
+    async fn remember_session(&self) {
+        if let Some(user_public_key) = self.user_public_key.get() {
+            let uri = self.connection_uri();
+            let _ = save_bunker_session(
+                &self.app_keys.public_key(),
+                BunkerSession {
+                    bunker_uri: uri.to_string(),
+                    user_public_key: user_public_key.to_hex(),
+                    relays: uri.relays().iter().map(|r| r.to_string()).collect(),
+                    last_connected: Timestamp::now().as_u64(),
+                    perms: self
+                        .session
+                        .as_ref()
+                        .map(|session| session.perms.clone())
+                        .unwrap_or_default(),
+                },
+            );
+        }
+    }
+
+    /// a hint for signing `kind` when it wasn't among the permissions
+    /// requested at login, so the signer asks each time
+    fn missing_perm_hint(&self, kind: u16) -> Option<String> {
+        let perms = &self.session.as_ref()?.perms;
+        if perms.is_empty() || perms.contains(&format!("sign_event:{kind}")) {
+            None
+        } else {
+            Some(format!(
+                "kind {kind} wasn't among the permissions granted at login. log in again with `ngit account login` to grant it"
+            ))
+        }
+    }
+}
+
+#[async_trait]
+impl NostrSigner for ReconnectingNostrConnect {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::NostrConnect
+    }
+
+    async fn get_public_key(&self) -> Result<PublicKey, SignerError> {
+        if let Some(public_key) = self.known_user_public_key() {
+            return Ok(public_key);
+        }
+        let public_key = self
+            .with_reconnect(|signer| async move { signer.get_public_key().await })
+            .await?;
+        let _ = self.user_public_key.set(public_key);
+        self.remember_session().await;
+        Ok(public_key)
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        let kind = unsigned.kind.as_u16();
+        self.with_reconnect(|signer| {
+            let unsigned = unsigned.clone();
+            async move { signer.sign_event(unsigned).await }
+        })
+        .await
+        .map_err(|error| match self.missing_perm_hint(kind) {
+            Some(hint) if is_declined(&error.to_string()) => {
+                SignerError::backend(std::io::Error::other(format!("{error}. {hint}")))
+            }
+            _ => error,
+        })
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self.with_reconnect(|signer| async move { signer.nip04_encrypt(public_key, content).await })
+            .await
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: &PublicKey,
+        encrypted_content: &str,
+    ) -> Result<String, SignerError> {
+        self.with_reconnect(|signer| async move {
+            signer.nip04_decrypt(public_key, encrypted_content).await
+        })
+        .await
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self.with_reconnect(|signer| async move { signer.nip44_encrypt(public_key, content).await })
+            .await
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: &PublicKey,
+        payload: &str,
+    ) -> Result<String, SignerError> {
+        self.with_reconnect(|signer| async move { signer.nip44_decrypt(public_key, payload).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_revoked_errors_are_not_retried() {
+        assert!(is_access_revoked("Unauthorized: app not allowed"));
+        assert!(!is_retryable("request rejected by user"));
+        // declining one request doesn't end the session
+        assert!(!is_access_revoked("request rejected by user"));
+        assert!(!is_access_revoked("permission denied"));
+        assert!(!is_retryable("timeout"));
+        assert!(is_retryable("relay connection closed"));
+    }
+
+    #[test]
+    fn connects_on_the_relays_the_signer_last_answered_on() -> Result<()> {
+        let uri = NostrConnectURI::parse(&format!(
+            "bunker://{}?relay=wss://old.example.com",
+            Keys::generate().public_key()
+        ))?;
+        let signer = ReconnectingNostrConnect {
+            uri: uri.clone(),
+            app_keys: Keys::generate(),
+            user_public_key: OnceCell::new(),
+            session: Some(BunkerSession {
+                bunker_uri: uri.to_string(),
+                user_public_key: Keys::generate().public_key().to_hex(),
+                relays: vec!["wss://new.example.com".to_string()],
+                last_connected: 0,
+                perms: vec!["sign_event:1617".to_string()],
+            }),
+            connection: Mutex::new(None),
+        };
+        assert_eq!(
+            signer.connection_uri().relays().first(),
+            Some(&RelayUrl::parse("wss://new.example.com")?)
+        );
+        assert!(signer.missing_perm_hint(1617).is_none());
+        assert!(signer.missing_perm_hint(5).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn backoff_doubles_each_attempt() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(2), Duration::from_secs(4));
+    }
+}
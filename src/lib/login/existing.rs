@@ -1,14 +1,18 @@
-use std::{str::FromStr, sync::Arc, time::Duration};
+use std::{str::FromStr, sync::Arc};
 
 use anyhow::{Context, Result, bail};
 use nostr::nips::nip46::NostrConnectURI;
-use nostr_connect::client::NostrConnect;
 use nostr_sdk::{NostrSigner, PublicKey};
 
 use super::{
     SignerInfo, SignerInfoSource,
+    bunker_session::ReconnectingNostrConnect,
+    hardware_signer::HardwareSigner,
     key_encryption::decrypt_key,
+    nip07_bridge::Nip07BridgeSigner,
+    os_keyring::resolve_nsec,
     print_logged_in_as,
+    session_lock::{get_unlocked_key, save_unlocked_key},
     user::{UserRef, get_user_details},
 };
 #[cfg(not(test))]
@@ -72,7 +76,7 @@ pub fn get_signer_info(
     Ok(match source {
         None => {
             let mut result = None;
-            for source in if std::env::var("NGITTEST").is_ok() {
+            for source in if crate::settings::settings().test_mode {
                 vec![
                     SignerInfoSource::CommandLineArguments,
                     SignerInfoSource::GitLocal,
@@ -103,57 +107,35 @@ pub fn get_signer_info(
         Some(SignerInfoSource::GitLocal) => {
             let git_repo =
                 git_repo.context("failed to get local git config as no git_repo supplied")?;
-            if let Ok(nsec) = get_git_config_item(&Some(git_repo), "nostr.nsec")
+            if let Some(signer_info) =
+                get_signer_info_from_git_config(&Some(git_repo), "nostr.", password)
+                    .context("failed get local git config")?
+            {
+                (signer_info, SignerInfoSource::GitLocal)
+            } else if let Some(identity) = get_git_config_item(&Some(git_repo), "nostr.identity")
                 .context("failed get local git config")?
-                .context("git local config item nostr.nsec doesn't exist")
             {
                 (
-                    SignerInfo::Nsec {
-                        nsec: nsec.to_string(),
-                        password: password.clone(),
-                        npub: get_git_config_item(&Some(git_repo), "nostr.npub")
-                            .context("failed get local git config")?,
-                    },
+                    get_signer_info_from_git_config(
+                        &None,
+                        &identity_config_prefix(&identity),
+                        password,
+                    )
+                    .context("failed to get global git config")?
+                    .context(format!(
+                        "identity \"{identity}\" selected in local git config nostr.identity doesn't exist. create it with `ngit account login --identity {identity}`"
+                    ))?,
                     SignerInfoSource::GitLocal,
                 )
-            } else if let Ok(bunker_uri) = get_git_config_item(&Some(git_repo), "nostr.bunker-uri")
-                .context("failed get local git config")?
-                .context("git local config item nostr.bunker-uri doesn't exist")
-            {
-                (SignerInfo::Bunker {
-                    bunker_uri, bunker_app_key: get_git_config_item(&Some(git_repo), "nostr.bunker-app-key")
-                    .context("failed get local git config")?
-                    .context("git local config item nostr.bunker-uri exists but nostr.bunker-app-key doesn't")?,
-                    npub: get_git_config_item(&Some(git_repo), "nostr.npub")
-                        .context("failed get local git config")?,
-                }, SignerInfoSource::GitLocal)
             } else {
                 bail!("no signer info in local git config")
             }
         }
         Some(SignerInfoSource::GitGlobal) => {
-            if let Some(nsec) = get_git_config_item(&None, "nostr.nsec")
-                .context("failed to get global git config")?
-            {
-                (
-                    SignerInfo::Nsec {
-                        nsec: nsec.to_string(),
-                        password: password.clone(),
-                        npub: get_git_config_item(&None, "nostr.npub")
-                            .context("failed to get global git config")?,
-                    },
-                    SignerInfoSource::GitGlobal,
-                )
-            } else if let Some(bunker_uri) = get_git_config_item(&None, "nostr.bunker-uri")
+            if let Some(signer_info) = get_signer_info_from_git_config(&None, "nostr.", password)
                 .context("failed to get global git config")?
             {
-                (SignerInfo::Bunker {
-                    bunker_uri, bunker_app_key: get_git_config_item(&None, "nostr.bunker-app-key")
-                    .context("failed get local git config")?
-                    .context("git global config item nostr.bunker-uri exists but nostr.bunker-app-key doesn't")?,
-                    npub: get_git_config_item(&None, "nostr.npub")
-                        .context("failed get global git config")?,
-                }, SignerInfoSource::GitGlobal)
+                (signer_info, SignerInfoSource::GitGlobal)
             } else {
                 bail!("no signer info in global git config")
             }
@@ -161,35 +143,93 @@ pub fn get_signer_info(
     })
 }
 
+/// prefix of git config items for a named identity stored in global git
+/// config, eg. `nostr.identity.work.npub`
+pub fn identity_config_prefix(identity: &str) -> String {
+    format!("nostr.identity.{identity}.")
+}
+
+/// signer info from git config items starting with `prefix` eg. `nostr.nsec`
+fn get_signer_info_from_git_config(
+    git_repo: &Option<&Repo>,
+    prefix: &str,
+    password: &Option<String>,
+) -> Result<Option<SignerInfo>> {
+    let get = |item: &str| get_git_config_item(git_repo, &format!("{prefix}{item}"));
+    let npub = get("npub")?;
+    Ok(if let Some(nsec) = get("nsec")? {
+        Some(SignerInfo::Nsec {
+            nsec,
+            password: password.clone(),
+            npub,
+        })
+    } else if let Some(bunker_uri) = get("bunker-uri")? {
+        Some(SignerInfo::Bunker {
+            bunker_uri,
+            bunker_app_key: get("bunker-app-key")?.context(format!(
+                "git config item {prefix}bunker-uri exists but {prefix}bunker-app-key doesn't"
+            ))?,
+            npub,
+        })
+    } else if get("nip07-bridge")?.is_some_and(|v| v == "true") {
+        Some(SignerInfo::BrowserExtension { npub })
+    } else if let Some(device) = get("hardware-signer")? {
+        Some(SignerInfo::Hardware { device, npub })
+    } else if git_repo.is_none() && prefix == "nostr." {
+        crate::settings::settings()
+            .hardware_signer
+            .clone()
+            .map(|device| SignerInfo::Hardware { device, npub })
+    } else {
+        None
+    })
+}
+
+/// `allow_prompts`: if false, fail rather than ask for a password or wait for
+/// a remote signer, browser extension or device to share the public key
 async fn get_signer(
     signer_info: &SignerInfo,
-    prompt_for_ncryptsec_password: bool,
+    allow_prompts: bool,
 ) -> Result<(Arc<dyn NostrSigner>, PublicKey)> {
+    let known_public_key = |npub: &Option<String>| -> Result<Option<PublicKey>> {
+        let public_key = npub.clone().and_then(|npub| PublicKey::parse(npub).ok());
+        if public_key.is_none() && !allow_prompts {
+            bail!("failed to login without prompts as nostr.npub isn't set");
+        }
+        Ok(public_key)
+    };
     match signer_info {
         SignerInfo::Nsec {
             nsec,
             password,
-            npub: _,
+            npub,
         } => {
+            let nsec = &resolve_nsec(nsec, npub)?;
             let keys = if nsec.contains("ncryptsec") {
-                // TODO get user details from npub
-                // TODO add retry loop
-                // TODO in retry loop give option to login again
-                let password = if let Some(password) = password {
-                    password.clone()
+                if let Some(keys) = npub.as_deref().and_then(get_unlocked_key) {
+                    keys
                 } else {
-                    if !prompt_for_ncryptsec_password {
-                        bail!(
-                            "failed to login without prompts a nsec is encrypted with a password"
-                        );
-                    }
-                    Interactor::default()
-                        .password(PromptPasswordParms::default().with_prompt("password"))
-                        .context("failed to get password input from interactor.password")?
-                };
-                decrypt_key(nsec, password.clone().as_str())
-                    .context("failed to decrypt key with provided password")
-                    .context("failed to decrypt ncryptsec supplied as nsec with password")?
+                    // TODO get user details from npub
+                    // TODO add retry loop
+                    // TODO in retry loop give option to login again
+                    let password = if let Some(password) = password {
+                        password.clone()
+                    } else {
+                        if !allow_prompts {
+                            bail!(
+                                "failed to login without prompts a nsec is encrypted with a password"
+                            );
+                        }
+                        Interactor::default()
+                            .password(PromptPasswordParms::default().with_prompt("password"))
+                            .context("failed to get password input from interactor.password")?
+                    };
+                    let keys = decrypt_key(nsec, password.clone().as_str())
+                        .context("failed to decrypt key with provided password")
+                        .context("failed to decrypt ncryptsec supplied as nsec with password")?;
+                    let _ = save_unlocked_key(&keys);
+                    keys
+                }
             } else {
                 nostr::Keys::from_str(nsec).context("invalid nsec parameter")?
             };
@@ -202,16 +242,16 @@ async fn get_signer(
             npub,
         } => {
             let uri = NostrConnectURI::parse(bunker_uri)?;
-            let s = NostrConnect::new(
+            let s = ReconnectingNostrConnect::new(
                 uri,
                 nostr::Keys::from_str(bunker_app_key).context("invalid app key")?,
-                Duration::from_secs(10 * 60),
-                None,
-            )?;
-            if let Some(public_key) = npub.clone().and_then(|npub| PublicKey::parse(npub).ok()) {
-                s.non_secure_set_user_public_key(public_key)?;
+                npub.clone().and_then(|npub| PublicKey::parse(npub).ok()),
+            );
+            if let Some(public_key) = s.known_user_public_key() {
                 let signer: Arc<dyn NostrSigner> = Arc::new(s);
                 Ok((signer, public_key))
+            } else if !allow_prompts {
+                bail!("failed to login without prompts as the remote signer public key isn't known");
             } else {
                 let signer: Arc<dyn NostrSigner> = Arc::new(s);
                 let term = console::Term::stderr();
@@ -221,5 +261,65 @@ async fn get_signer(
                 Ok((signer, public_key))
             }
         }
+        SignerInfo::BrowserExtension { npub } => {
+            let signer = Nip07BridgeSigner::new(known_public_key(npub)?);
+            let public_key = signer
+                .get_public_key()
+                .await
+                .context("failed to get public key from browser extension")?;
+            let signer: Arc<dyn NostrSigner> = Arc::new(signer);
+            Ok((signer, public_key))
+        }
+        SignerInfo::Hardware { device, npub } => {
+            let signer = HardwareSigner::new(device.into(), known_public_key(npub)?);
+            let public_key = signer
+                .get_public_key()
+                .await
+                .context("failed to get public key from hardware signer")?;
+            let signer: Arc<dyn NostrSigner> = Arc::new(signer);
+            Ok((signer, public_key))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::git::GitTestRepo;
+
+    use super::*;
+
+    mod get_signer_info_from_git_config {
+        use super::*;
+
+        #[test]
+        fn reads_items_under_prefix() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let prefix = identity_config_prefix("work");
+            git_repo.save_git_config_item(&format!("{prefix}nsec"), "nsec123", false)?;
+            git_repo.save_git_config_item(&format!("{prefix}npub"), "npub123", false)?;
+
+            assert!(
+                get_signer_info_from_git_config(&Some(&git_repo), "nostr.", &None)?.is_none()
+            );
+            let Some(SignerInfo::Nsec { nsec, npub, .. }) =
+                get_signer_info_from_git_config(&Some(&git_repo), &prefix, &None)?
+            else {
+                panic!("expected nsec signer info");
+            };
+            assert_eq!(nsec, "nsec123");
+            assert_eq!(npub, Some("npub123".to_string()));
+            Ok(())
+        }
+
+        #[test]
+        fn errors_when_bunker_app_key_missing() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            git_repo.save_git_config_item("nostr.bunker-uri", "bunker://abc", false)?;
+
+            assert!(get_signer_info_from_git_config(&Some(&git_repo), "nostr.", &None).is_err());
+            Ok(())
+        }
     }
 }
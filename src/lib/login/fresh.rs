@@ -2,17 +2,21 @@ use std::{str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result, bail};
 use console::Style;
-use dialoguer::theme::{ColorfulTheme, Theme};
-use nostr::nips::{nip05, nip46::NostrConnectURI};
+use nostr::nips::{nip05, nip44, nip46::NostrConnectURI};
 use nostr_connect::client::NostrConnect;
-use nostr_sdk::{EventBuilder, Keys, Metadata, NostrSigner, PublicKey, RelayUrl, ToBech32};
+use nostr_sdk::{
+    EventBuilder, Keys, Kind, Metadata, NostrSigner, PublicKey, RelayUrl, Tag, Timestamp, ToBech32,
+};
 use qrcode::QrCode;
 use tokio::{signal, sync::Mutex};
 
 use super::{
     SignerInfo, SignerInfoSource,
-    existing::load_existing_login,
+    bunker_session::{BunkerSession, save_bunker_session},
+    existing::{identity_config_prefix, load_existing_login},
     key_encryption::decrypt_key,
+    nip07_bridge::Nip07BridgeSigner,
+    os_keyring::{KEYRING_NSEC_PLACEHOLDER, keyring_enabled, save_nsec_to_keyring},
     print_logged_in_as,
     user::{UserRef, get_user_details},
 };
@@ -23,7 +27,7 @@ use crate::client::MockConnect;
 use crate::{
     cli_interactor::{
         Interactor, InteractorPrompt, Printer, PromptChoiceParms, PromptConfirmParms,
-        PromptInputParms, PromptPasswordParms,
+        PromptInputParms, PromptPasswordParms, format_prompt_error, format_prompt_selection,
     },
     client::{Connect, send_events},
     git::{Repo, RepoActions, remove_git_config_item, save_git_config_item},
@@ -35,6 +39,7 @@ pub async fn fresh_login_or_signup(
     #[cfg(not(test))] client: Option<&Client>,
     signer_info: Option<SignerInfo>,
     save_local: bool,
+    identity: Option<&str>,
 ) -> Result<(Arc<dyn NostrSigner>, UserRef, SignerInfoSource)> {
     let (signer, public_key, signer_info, source) = loop {
         if let Some(signer_info) = signer_info {
@@ -58,6 +63,7 @@ pub async fn fresh_login_or_signup(
                 .with_choices(vec![
                     "secret key (nsec / ncryptsec)".to_string(),
                     "nostr connect (remote signer)".to_string(),
+                    "browser extension (NIP-07)".to_string(),
                     "create account".to_string(),
                     "help".to_string(),
                 ])
@@ -79,7 +85,14 @@ pub async fn fresh_login_or_signup(
                     continue;
                 }
             },
-            2 => match signup(client).await {
+            2 => match get_fresh_nip07_signer().await {
+                Ok(res) => break res,
+                Err(e) => {
+                    eprintln!("error getting signer from browser extension: {e}");
+                    continue;
+                }
+            },
+            3 => match signup(client).await {
                 Ok(Some(res)) => break res,
                 Ok(None) => continue,
                 Err(e) => {
@@ -93,7 +106,13 @@ pub async fn fresh_login_or_signup(
             }
         }
     };
-    let _ = save_to_git_config(git_repo, &signer_info, !save_local).await;
+    if let Some(identity) = identity {
+        if let Err(error) = save_identity_to_git_config(git_repo, identity, &signer_info) {
+            eprintln!("Error: {:?}", error);
+        }
+    } else {
+        let _ = save_to_git_config(git_repo, &signer_info, !save_local).await;
+    }
     let user_ref = get_user_details(
         &public_key,
         client,
@@ -211,29 +230,21 @@ pub async fn get_fresh_nsec_signer() -> Result<
 }
 
 fn show_prompt_success(label: &str, value: &str) {
-    eprintln!("{}", {
-        let mut s = String::new();
-        let _ = ColorfulTheme::default().format_input_prompt_selection(&mut s, label, value);
-        s
-    });
+    eprintln!("{}", format_prompt_selection(label, value));
 }
 
 fn show_prompt_error(label: &str, value: &str) {
-    eprintln!("{}", {
-        let mut s = String::new();
-        let _ = ColorfulTheme::default().format_error(
-            &mut s,
-            &format!(
-                "{label}: \"{}\"",
-                if value.is_empty() {
-                    "empty".to_string()
-                } else {
-                    shorten_string(value)
-                }
-            ),
-        );
-        s
-    });
+    eprintln!(
+        "{}",
+        format_prompt_error(&format!(
+            "{label}: \"{}\"",
+            if value.is_empty() {
+                "empty".to_string()
+            } else {
+                shorten_string(value)
+            }
+        ))
+    );
 }
 
 fn shorten_string(s: &str) -> String {
@@ -255,9 +266,7 @@ pub async fn get_fresh_nip46_signer(
         SignerInfoSource,
     )>,
 > {
-    let (app_key, nostr_connect_url) = generate_nostr_connect_app(client)?;
-    let printer = Arc::new(Mutex::new(Printer::default()));
-    let signer_choice = Interactor::default().choice(
+    let method = match Interactor::default().choice(
         PromptChoiceParms::default()
             .with_prompt("login to nostr with remote signer")
             .with_default(0)
@@ -269,10 +278,40 @@ pub async fn get_fresh_nip46_signer(
                 "back".to_string(),
             ])
             .dont_report(),
-    )?;
-    let url = match signer_choice {
-        0 | 1 => nostr_connect_url,
-        2 => {
+    )? {
+        0 => RemoteSignerMethod::QrCode,
+        1 => RemoteSignerMethod::NostrConnectUrl,
+        2 => RemoteSignerMethod::Nip05,
+        3 => RemoteSignerMethod::BunkerUrl,
+        _ => return Ok(None),
+    };
+    get_fresh_nip46_signer_with_method(client, &method)
+        .await
+        .map(Some)
+}
+
+pub enum RemoteSignerMethod {
+    QrCode,
+    NostrConnectUrl,
+    Nip05,
+    BunkerUrl,
+}
+
+pub async fn get_fresh_nip46_signer_with_method(
+    #[cfg(test)] client: Option<&MockConnect>,
+    #[cfg(not(test))] client: Option<&Client>,
+    method: &RemoteSignerMethod,
+) -> Result<(
+    Arc<dyn NostrSigner>,
+    PublicKey,
+    SignerInfo,
+    SignerInfoSource,
+)> {
+    let (app_key, nostr_connect_url) = generate_nostr_connect_app(client)?;
+    let printer = Arc::new(Mutex::new(Printer::default()));
+    let url = match method {
+        RemoteSignerMethod::QrCode | RemoteSignerMethod::NostrConnectUrl => nostr_connect_url,
+        RemoteSignerMethod::Nip05 => {
             let mut error = None;
             loop {
                 let input = Interactor::default()
@@ -290,7 +329,7 @@ pub async fn get_fresh_nip46_signer(
                 }
             }
         }
-        3 => {
+        RemoteSignerMethod::BunkerUrl => {
             let mut error = None;
             loop {
                 let input = Interactor::default()
@@ -308,30 +347,30 @@ pub async fn get_fresh_nip46_signer(
                 }
             }
         }
-        _ => return Ok(None),
     };
 
     {
         let printer_clone = Arc::clone(&printer);
         let mut printer_locked = printer_clone.lock().await;
-        match signer_choice {
-            0 => {
+        match method {
+            RemoteSignerMethod::QrCode => {
                 printer_locked
                     .println("login to nostr with remote signer via nostr connect".to_string());
                 printer_locked.println("scan QR code in signer app (eg Amber):".to_string());
-                printer_locked.printlns(generate_qr(&url.to_string())?);
+                printer_locked.printlns(generate_qr(&nostr_connect_url_with_perms(&url))?);
                 printer_locked.println(
                     "scan QR code in signer app or use ctrl + c to go back to login menu..."
                         .to_string(),
                 );
             }
-            1 => {
+            RemoteSignerMethod::NostrConnectUrl => {
                 printer_locked
                     .println("login to nostr with remote signer via nostr connect".to_string());
                 printer_locked.println("".to_string());
+                let url = nostr_connect_url_with_perms(&url);
                 printer_locked.println_with_custom_formatting(
-                    format!("{}", Style::new().bold().apply_to(url.to_string()),),
-                    url.to_string(),
+                    format!("{}", Style::new().bold().apply_to(&url),),
+                    url,
                 );
                 printer_locked.println("".to_string());
                 printer_locked.println(
@@ -350,17 +389,63 @@ pub async fn get_fresh_nip46_signer(
 
     let (signer, user_public_key, bunker_url) =
         listen_for_remote_signer(&app_key, &url, printer).await?;
+    let perms = match method {
+        RemoteSignerMethod::QrCode | RemoteSignerMethod::NostrConnectUrl => requested_perms(),
+        _ => match client {
+            Some(client)
+                if request_perms_from_bunker(client, &app_key, &url)
+                    .await
+                    .is_ok() =>
+            {
+                requested_perms()
+            }
+            _ => vec![],
+        },
+    };
+    // so the next run doesn't need to renegotiate the connection
+    let _ = save_bunker_session(
+        &app_key.public_key(),
+        BunkerSession {
+            bunker_uri: bunker_url.to_string(),
+            user_public_key: user_public_key.to_hex(),
+            relays: bunker_url.relays().iter().map(|r| r.to_string()).collect(),
+            last_connected: Timestamp::now().as_u64(),
+            perms,
+        },
+    );
     let signer_info = SignerInfo::Bunker {
         bunker_uri: bunker_url.to_string(),
         bunker_app_key: app_key.secret_key().to_secret_hex(),
         npub: Some(user_public_key.to_bech32()?),
     };
-    Ok(Some((
+    Ok((
         signer,
         user_public_key,
         signer_info,
         SignerInfoSource::GitGlobal,
-    )))
+    ))
+}
+
+pub async fn get_fresh_nip07_signer() -> Result<(
+    Arc<dyn NostrSigner>,
+    PublicKey,
+    SignerInfo,
+    SignerInfoSource,
+)> {
+    let signer = Nip07BridgeSigner::new(None);
+    let public_key = signer
+        .get_public_key()
+        .await
+        .context("failed to get public key from browser extension")?;
+    let signer_info = SignerInfo::BrowserExtension {
+        npub: Some(public_key.to_bech32()?),
+    };
+    Ok((
+        Arc::new(signer),
+        public_key,
+        signer_info,
+        SignerInfoSource::GitGlobal,
+    ))
 }
 
 pub fn generate_nostr_connect_app(
@@ -381,6 +466,75 @@ pub fn generate_nostr_connect_app(
     Ok((app_key, nostr_connect_url))
 }
 
+/// kinds ngit signs, requested up front so signers can approve them all at
+/// once instead of prompting for each event in a patch series
+static REQUESTED_SIGN_EVENT_KINDS: [u16; 11] = [
+    5, 1617, 1621, 1630, 1631, 1632, 1633, 22242, 24242, 30617, 30618,
+];
+
+/// NIP-46 `perms` for the kinds ngit signs
+fn requested_perms() -> Vec<String> {
+    REQUESTED_SIGN_EVENT_KINDS
+        .iter()
+        .map(|kind| format!("sign_event:{kind}"))
+        .collect()
+}
+
+/// nostrconnect:// url with NIP-46 `perms` for the kinds ngit signs
+fn nostr_connect_url_with_perms(url: &NostrConnectURI) -> String {
+    format!("{url}&perms={}", requested_perms().join(","))
+}
+
+/// ask a bunker for the `perms` a nostrconnect:// url would have requested.
+/// NIP-46 only takes them with `connect`, so another one is sent after the
+/// handshake. signers that don't accept it ignore it and prompt per kind
+async fn request_perms_from_bunker(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+    app_key: &Keys,
+    bunker_url: &NostrConnectURI,
+) -> Result<()> {
+    let NostrConnectURI::Bunker {
+        remote_signer_public_key,
+        relays,
+        secret,
+    } = bunker_url
+    else {
+        bail!("not a bunker url");
+    };
+    let request = serde_json::json!({
+        "id": format!("ngit-perms-{}", Timestamp::now().as_u64()),
+        "method": "connect",
+        "params": [
+            remote_signer_public_key.to_hex(),
+            secret.clone().unwrap_or_default(),
+            requested_perms().join(","),
+        ],
+    });
+    let event = EventBuilder::new(
+        Kind::NostrConnect,
+        nip44::encrypt(
+            app_key.secret_key(),
+            remote_signer_public_key,
+            request.to_string(),
+            nip44::Version::V2,
+        )?,
+    )
+    .tags([Tag::public_key(*remote_signer_public_key)])
+    .sign_with_keys(app_key)?;
+    let mut sent = false;
+    for relay in relays {
+        sent |= client
+            .send_event_to(None, relay.as_str(), event.clone())
+            .await
+            .is_ok();
+    }
+    if !sent {
+        bail!("failed to send permissions request to the bunker's relays");
+    }
+    Ok(())
+}
+
 pub async fn fetch_nip46_uri_from_nip05(nip05: &str) -> Result<NostrConnectURI> {
     let term = console::Term::stderr();
     term.write_line("contacting login service provider...")?;
@@ -493,7 +647,7 @@ async fn save_to_git_config(
     signer_info: &SignerInfo,
     global: bool,
 ) -> Result<()> {
-    let global = if std::env::var("NGITTEST").is_ok() {
+    let global = if crate::settings::settings().test_mode {
         false
     } else {
         global
@@ -527,6 +681,14 @@ async fn save_to_git_config(
                 eprintln!("nostr.bunker-uri: {bunker_uri}");
                 eprintln!("nostr.bunker-app-key: {bunker_app_key}");
             }
+            SignerInfo::BrowserExtension { npub: _ } => {
+                eprintln!("consider manually setting git config nostr.nip07-bridge to: true");
+            }
+            SignerInfo::Hardware { device, npub: _ } => {
+                eprintln!(
+                    "consider manually setting git config nostr.hardware-signer to: {device}"
+                );
+            }
         }
         if global {
             loop {
@@ -616,6 +778,8 @@ fn get_pubkey_from_signer_info(signer_info: &SignerInfo) -> Result<PublicKey> {
             password: _,
             npub,
         } => npub,
+        SignerInfo::BrowserExtension { npub } => npub,
+        SignerInfo::Hardware { device: _, npub } => npub,
     };
     if let Some(npub) = npub {
         PublicKey::parse(npub).context("format of npub string in signer_info is invalid")
@@ -624,6 +788,16 @@ fn get_pubkey_from_signer_info(signer_info: &SignerInfo) -> Result<PublicKey> {
     }
 }
 
+static LOCAL_LOGIN_ITEMS: [&str; 7] = [
+    "nostr.npub",
+    "nostr.nsec",
+    "nostr.bunker-uri",
+    "nostr.bunker-app-key",
+    "nostr.nip07-bridge",
+    "nostr.hardware-signer",
+    "nostr.identity",
+];
+
 fn silently_save_to_git_config(
     git_repo: &Option<&Repo>,
     signer_info: &SignerInfo,
@@ -632,10 +806,9 @@ fn silently_save_to_git_config(
     if global {
         // remove local login otherwise it will override global next time ngit is called
         if let Some(git_repo) = git_repo {
-            git_repo.remove_git_config_item("nostr.npub", false)?;
-            git_repo.remove_git_config_item("nostr.nsec", false)?;
-            git_repo.remove_git_config_item("nostr.bunker-uri", false)?;
-            git_repo.remove_git_config_item("nostr.bunker-app-key", false)?;
+            for item in LOCAL_LOGIN_ITEMS {
+                git_repo.remove_git_config_item(item, false)?;
+            }
         }
     }
 
@@ -647,6 +820,23 @@ fn silently_save_to_git_config(
         git_repo
     };
 
+    save_signer_info_to_git_config(git_repo, "nostr.", signer_info)
+}
+
+/// save under `prefix` and remove items for other signer types
+fn save_signer_info_to_git_config(
+    git_repo: &Option<&Repo>,
+    prefix: &str,
+    signer_info: &SignerInfo,
+) -> Result<()> {
+    let save =
+        |item: &str, value: &str| save_git_config_item(git_repo, &format!("{prefix}{item}"), value);
+    let remove = |items: &[&str]| -> Result<()> {
+        for item in items {
+            remove_git_config_item(git_repo, &format!("{prefix}{item}"))?;
+        }
+        Ok(())
+    };
     let npub_to_save;
     match signer_info {
         SignerInfo::Nsec {
@@ -655,9 +845,25 @@ fn silently_save_to_git_config(
             npub,
         } => {
             npub_to_save = npub;
-            save_git_config_item(git_repo, "nostr.nsec", nsec)?;
-            remove_git_config_item(git_repo, "nostr.bunker-uri")?;
-            remove_git_config_item(git_repo, "nostr.bunker-app-key")?;
+            // fall back to git config on headless systems without a keyring
+            let in_keyring = keyring_enabled()
+                && npub
+                    .as_ref()
+                    .is_some_and(|npub| save_nsec_to_keyring(npub, nsec).is_ok());
+            save(
+                "nsec",
+                if in_keyring {
+                    KEYRING_NSEC_PLACEHOLDER
+                } else {
+                    nsec
+                },
+            )?;
+            remove(&[
+                "bunker-uri",
+                "bunker-app-key",
+                "nip07-bridge",
+                "hardware-signer",
+            ])?;
         }
         SignerInfo::Bunker {
             bunker_uri,
@@ -665,16 +871,53 @@ fn silently_save_to_git_config(
             npub,
         } => {
             npub_to_save = npub;
-            save_git_config_item(git_repo, "nostr.bunker-uri", bunker_uri)?;
-            save_git_config_item(git_repo, "nostr.bunker-app-key", bunker_app_key)?;
-            remove_git_config_item(git_repo, "nostr.nsec")?;
+            save("bunker-uri", bunker_uri)?;
+            save("bunker-app-key", bunker_app_key)?;
+            remove(&["nsec", "nip07-bridge", "hardware-signer"])?;
+        }
+        SignerInfo::BrowserExtension { npub } => {
+            npub_to_save = npub;
+            save("nip07-bridge", "true")?;
+            remove(&["nsec", "bunker-uri", "bunker-app-key", "hardware-signer"])?;
+        }
+        SignerInfo::Hardware { device, npub } => {
+            npub_to_save = npub;
+            save("hardware-signer", device)?;
+            remove(&["nsec", "bunker-uri", "bunker-app-key", "nip07-bridge"])?;
         }
     }
     if let Some(npub) = npub_to_save {
-        save_git_config_item(git_repo, "nostr.npub", npub)?;
+        save("npub", npub)?;
+    } else {
+        remove(&["npub"])?;
+    }
+    Ok(())
+}
+
+/// save as a named identity in global git config and select it for the local
+/// repository
+fn save_identity_to_git_config(
+    git_repo: &Option<&Repo>,
+    identity: &str,
+    signer_info: &SignerInfo,
+) -> Result<()> {
+    let prefix = identity_config_prefix(identity);
+    save_signer_info_to_git_config(&None, &prefix, signer_info).context(format!(
+        "failed to save identity \"{identity}\" to global git config"
+    ))?;
+    if let Some(git_repo) = git_repo {
+        // a local login would take priority over the selected identity
+        for item in LOCAL_LOGIN_ITEMS {
+            git_repo.remove_git_config_item(item, false)?;
+        }
+        git_repo.save_git_config_item("nostr.identity", identity, false)?;
+        eprintln!(
+            "saved login details as identity \"{identity}\" and selected it for this repository"
+        );
     } else {
-        remove_git_config_item(git_repo, "nostr.npub")?;
+        eprintln!("saved login details as identity \"{identity}\"");
     }
+    eprintln!("select it in another repository with `git config nostr.identity {identity}`");
     Ok(())
 }
 
@@ -0,0 +1,209 @@
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use nostr::{
+    Event, PublicKey, UnsignedEvent,
+    secp256k1::schnorr::Signature,
+    signer::{SignerBackend, SignerError},
+};
+use nostr_sdk::NostrSigner;
+use tokio::sync::{Mutex, OnceCell};
+
+/// how long to wait for the user to approve a request on the device
+static DEVICE_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+/// used by the LNbits Nostr Signing Device. ignored by USB CDC devices
+static DEVICE_BAUD_RATE: u32 = 9600;
+
+/// signs with a serial / USB nostr signing device (eg. the LNbits Nostr
+/// Signing Device) using its line based protocol so the secret key stays on
+/// the device. each event id is approved on-device.
+#[derive(Debug)]
+pub struct HardwareSigner {
+    device: PathBuf,
+    public_key: OnceCell<PublicKey>,
+    // one command at a time so responses aren't mixed up
+    lock: Mutex<()>,
+}
+
+impl HardwareSigner {
+    pub fn new(device: PathBuf, public_key: Option<PublicKey>) -> Self {
+        Self {
+            device,
+            public_key: OnceCell::new_with(public_key),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn command(&self, command: &str, arg: Option<String>) -> Result<String> {
+        let _guard = self.lock.lock().await;
+        let device = self.device.clone();
+        let command = command.to_string();
+        let line = match &arg {
+            Some(arg) => format!("{command} {arg}\n"),
+            None => format!("{command}\n"),
+        };
+        // the port's read timeout ends the blocking call, so the lock is held
+        // until the device is finished with
+        tokio::task::spawn_blocking(move || send_device_command(&device, &command, &line))
+            .await
+            .context("hardware signer task failed")?
+    }
+}
+
+fn send_device_command(device: &Path, command: &str, line: &str) -> Result<String> {
+    let deadline = Instant::now() + DEVICE_TIMEOUT;
+    let mut port = serialport::new(device.to_string_lossy(), DEVICE_BAUD_RATE)
+        .timeout(DEVICE_TIMEOUT)
+        .open()
+        .context(format!(
+            "failed to open hardware signer at {}",
+            device.display()
+        ))?;
+    port.write_all(line.as_bytes())
+        .context("failed to write to hardware signer")?;
+    port.flush()?;
+    let mut reader = BufReader::new(port);
+    loop {
+        // log lines don't extend the time the user has to approve
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(device_timeout_error());
+        }
+        reader.get_mut().set_timeout(remaining)?;
+        let mut response = String::new();
+        match reader.read_line(&mut response) {
+            Ok(0) => bail!("hardware signer disconnected"),
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::TimedOut => return Err(device_timeout_error()),
+            Err(error) => return Err(error).context("failed to read from hardware signer"),
+        }
+        if let Some(value) = parse_device_response(command, &response) {
+            return value;
+        }
+    }
+}
+
+fn device_timeout_error() -> anyhow::Error {
+    anyhow!("timed out waiting for hardware signer. was the request approved on the device?")
+}
+
+/// devices also print log lines so only lines prefixed with `command` are a
+/// response to it
+fn parse_device_response(command: &str, line: &str) -> Option<Result<String>> {
+    let value = line.trim().strip_prefix(command)?;
+    if !value.is_empty() && !value.starts_with(' ') {
+        return None;
+    }
+    let value = value.trim();
+    Some(if value.is_empty() {
+        Err(anyhow!("hardware signer rejected {command}"))
+    } else if let Some(error) = value.strip_prefix("error") {
+        Err(anyhow!("hardware signer {command} failed:{error}"))
+    } else {
+        Ok(value.to_string())
+    })
+}
+
+fn to_signer_error(error: anyhow::Error) -> SignerError {
+    SignerError::backend(std::io::Error::other(format!("{error:#}")))
+}
+
+fn unsupported(method: &str) -> SignerError {
+    to_signer_error(anyhow!("hardware signer doesn't support {method}"))
+}
+
+#[async_trait]
+impl NostrSigner for HardwareSigner {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::Custom("hardware signer".into())
+    }
+
+    async fn get_public_key(&self) -> Result<PublicKey, SignerError> {
+        self.public_key
+            .get_or_try_init(|| async {
+                self.command("/public-key", None)
+                    .await
+                    .and_then(|hex| {
+                        PublicKey::from_hex(&hex)
+                            .context("hardware signer returned invalid public key")
+                    })
+                    .map_err(to_signer_error)
+            })
+            .await
+            .copied()
+    }
+
+    async fn sign_event(&self, mut unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        unsigned.ensure_id();
+        let id = unsigned
+            .id
+            .context("failed to compute event id")
+            .map_err(to_signer_error)?;
+        let sig = self
+            .command("/sign-message", Some(id.to_hex()))
+            .await
+            .and_then(|sig| {
+                Signature::from_str(&sig).context("hardware signer returned invalid signature")
+            })
+            .map_err(to_signer_error)?;
+        // fails if the device holds a different key
+        unsigned
+            .add_signature(sig)
+            .context("hardware signer signature is invalid for this event")
+            .map_err(to_signer_error)
+    }
+
+    async fn nip04_encrypt(&self, _: &PublicKey, _: &str) -> Result<String, SignerError> {
+        Err(unsupported("nip04 encryption"))
+    }
+
+    async fn nip04_decrypt(&self, _: &PublicKey, _: &str) -> Result<String, SignerError> {
+        Err(unsupported("nip04 decryption"))
+    }
+
+    async fn nip44_encrypt(&self, _: &PublicKey, _: &str) -> Result<String, SignerError> {
+        Err(unsupported("nip44 encryption"))
+    }
+
+    async fn nip44_decrypt(&self, _: &PublicKey, _: &str) -> Result<String, SignerError> {
+        Err(unsupported("nip44 decryption"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_device_response_ignores_log_lines() {
+        assert!(parse_device_response("/sign-message", "waiting for button press\r\n").is_none());
+        assert!(parse_device_response("/public-key", "/public-keys abc\n").is_none());
+        assert_eq!(
+            parse_device_response("/public-key", "/public-key abc123\r\n")
+                .unwrap()
+                .unwrap(),
+            "abc123"
+        );
+    }
+
+    #[test]
+    fn parse_device_response_errors_when_rejected() {
+        assert!(
+            parse_device_response("/sign-message", "/sign-message\n")
+                .unwrap()
+                .is_err()
+        );
+        assert!(
+            parse_device_response("/sign-message", "/sign-message error cancelled\n")
+                .unwrap()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use nostr::{Keys, nips::nip06::FromMnemonic};
+
+/// derive keys from a BIP-39 seed phrase using the NIP-06 derivation path
+/// `m/44'/1237'/<account>'/0/0`
+pub fn keys_from_mnemonic(mnemonic: &str, account: u32) -> Result<Keys> {
+    let mnemonic = mnemonic.split_whitespace().collect::<Vec<&str>>().join(" ");
+    Keys::from_mnemonic_with_account(mnemonic, None, Some(account)).context("invalid seed phrase")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vector from NIP-06
+    static MNEMONIC: &str =
+        "leader monkey parrot ring guide accident before fence cannon height naive bean";
+
+    #[test]
+    fn keys_from_mnemonic_matches_nip06_test_vector() -> Result<()> {
+        assert_eq!(
+            keys_from_mnemonic(MNEMONIC, 0)?
+                .secret_key()
+                .to_secret_hex(),
+            "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keys_from_mnemonic_ignores_extra_whitespace_and_differs_by_account() -> Result<()> {
+        let keys = keys_from_mnemonic(&format!("  {}\n", MNEMONIC.replace(' ', "  ")), 0)?;
+        assert_eq!(
+            keys.public_key(),
+            keys_from_mnemonic(MNEMONIC, 0)?.public_key()
+        );
+        assert_ne!(
+            keys.public_key(),
+            keys_from_mnemonic(MNEMONIC, 1)?.public_key()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keys_from_mnemonic_rejects_invalid_phrase() {
+        assert!(keys_from_mnemonic("not a valid seed phrase", 0).is_err());
+    }
+}
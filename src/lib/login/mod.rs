@@ -12,10 +12,17 @@ use crate::client::MockConnect;
 use crate::git::{Repo, RepoActions};
 
 pub mod existing;
-mod key_encryption;
+pub mod hardware_signer;
+pub mod key_encryption;
+pub mod mnemonic;
+pub mod nip05;
+pub mod nip07_bridge;
+pub mod os_keyring;
+pub mod session_lock;
 use existing::load_existing_login;
 pub mod user;
 use user::UserRef;
+pub mod bunker_session;
 pub mod fresh;
 
 pub async fn login_or_signup(
@@ -40,7 +47,7 @@ pub async fn login_or_signup(
     if res.is_ok() {
         res
     } else {
-        fresh_login_or_signup(git_repo, client, None, false).await
+        fresh_login_or_signup(git_repo, client, None, false, None).await
     }
 }
 
@@ -56,6 +63,13 @@ pub enum SignerInfo {
         bunker_app_key: String,
         npub: Option<String>,
     },
+    BrowserExtension {
+        npub: Option<String>,
+    },
+    Hardware {
+        device: String,
+        npub: Option<String>,
+    },
 }
 
 #[derive(PartialEq, Clone)]
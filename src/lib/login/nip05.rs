@@ -0,0 +1,119 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use nostr::{PublicKey, Timestamp, nips::nip05};
+use serde::{Deserialize, Serialize};
+
+use crate::get_dirs;
+
+/// re-verify cached results after this long
+static VERIFICATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+static VERIFICATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct Nip05Verification {
+    nip05: String,
+    verified: bool,
+    checked_at: u64,
+}
+
+fn verifications_path() -> Result<PathBuf> {
+    Ok(get_dirs()?.cache_dir().join("nip05-verifications.json"))
+}
+
+/// verifications keyed by public key
+fn load_verifications() -> HashMap<String, Nip05Verification> {
+    verifications_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_verification(public_key: &PublicKey, verification: Nip05Verification) -> Result<()> {
+    let path = verifications_path()?;
+    let mut verifications = load_verifications();
+    verifications.insert(public_key.to_hex(), verification);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("failed to create cache directory")?;
+    }
+    // a verification finishing in the background as ngit exits mustn't leave
+    // a partly written file
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_string(&verifications)?)
+        .context("failed to save nip05 verification")?;
+    std::fs::rename(tmp_path, path).context("failed to save nip05 verification")
+}
+
+fn is_fresh(verification: &Nip05Verification, nip05: &str, now: u64) -> bool {
+    verification.nip05 == nip05
+        && now.saturating_sub(verification.checked_at) < VERIFICATION_TTL.as_secs()
+}
+
+/// whether `nip05` resolves to `public_key`, using the global cache when
+/// checked in the last day. relays aren't contacted in tests.
+pub async fn is_nip05_verified(public_key: &PublicKey, nip05: &str) -> bool {
+    let now = Timestamp::now().as_u64();
+    if let Some(verification) = load_verifications().get(&public_key.to_hex()) {
+        if is_fresh(verification, nip05, now) {
+            return verification.verified;
+        }
+    }
+    if crate::settings::settings().test_mode {
+        return false;
+    }
+    let Ok(Ok(verified)) =
+        tokio::time::timeout(VERIFICATION_TIMEOUT, nip05::verify(public_key, nip05, None)).await
+    else {
+        // don't cache timeouts or network errors so it is retried next time
+        return false;
+    };
+    let _ = save_verification(public_key, Nip05Verification {
+        nip05: nip05.to_string(),
+        verified,
+        checked_at: now,
+    });
+    verified
+}
+
+/// the last verification of `nip05` without waiting on the network. one that
+/// is missing or more than a day old is checked in the background so it is
+/// up to date next time
+pub fn cached_nip05_verification(public_key: &PublicKey, nip05: &str) -> bool {
+    let cached = load_verifications().remove(&public_key.to_hex());
+    if let Some(verification) = &cached {
+        if is_fresh(verification, nip05, Timestamp::now().as_u64()) {
+            return verification.verified;
+        }
+    }
+    if !crate::settings::settings().test_mode {
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let public_key = *public_key;
+            let nip05 = nip05.to_string();
+            runtime.spawn(async move { is_nip05_verified(&public_key, &nip05).await });
+        }
+    }
+    cached.is_some_and(|verification| verification.nip05 == nip05 && verification.verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_fresh_requires_same_nip05_within_ttl() {
+        let verification = Nip05Verification {
+            nip05: "alice@example.com".to_string(),
+            verified: true,
+            checked_at: 1_000,
+        };
+        assert!(is_fresh(&verification, "alice@example.com", 1_000 + 60));
+        assert!(!is_fresh(&verification, "bob@example.com", 1_000 + 60));
+        assert!(!is_fresh(
+            &verification,
+            "alice@example.com",
+            1_000 + VERIFICATION_TTL.as_secs()
+        ));
+    }
+}
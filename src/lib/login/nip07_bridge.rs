@@ -0,0 +1,434 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use nostr::{
+    Event, JsonUtil, Keys, PublicKey, UnsignedEvent,
+    signer::{SignerBackend, SignerError},
+};
+use nostr_sdk::NostrSigner;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{Mutex, OnceCell, oneshot},
+};
+
+/// how long to wait for the user to approve a request in the browser
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// a request taken by a page which hasn't responded after this long is
+/// offered again, eg. when the page was reloaded mid-approval. a page only
+/// asks for another request after responding to the last
+static IN_FLIGHT_GRACE: Duration = Duration::from_secs(10);
+
+static MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// signed events and decrypted messages are well within this
+static MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// polls for requests and passes them to the extension via `window.nostr`
+static BRIDGE_PAGE: &str = r#"<!doctype html>
+<html>
+<head><title>ngit signer</title></head>
+<body>
+<h3>ngit browser extension signer</h3>
+<p id="status">waiting for signing requests from ngit...</p>
+<script>
+const setStatus = (s) => { document.getElementById('status').textContent = s; };
+async function handle(request) {
+  const [a, b] = request.params;
+  switch (request.method) {
+    case 'getPublicKey': return await window.nostr.getPublicKey();
+    case 'signEvent': return await window.nostr.signEvent(a);
+    case 'nip04.encrypt': return await window.nostr.nip04.encrypt(a, b);
+    case 'nip04.decrypt': return await window.nostr.nip04.decrypt(a, b);
+    case 'nip44.encrypt': return await window.nostr.nip44.encrypt(a, b);
+    case 'nip44.decrypt': return await window.nostr.nip44.decrypt(a, b);
+    default: throw new Error('unsupported method ' + request.method);
+  }
+}
+async function poll() {
+  while (true) {
+    let res;
+    try {
+      res = await fetch('request');
+    } catch (e) {
+      setStatus('ngit has finished. you can close this tab');
+      return;
+    }
+    if (res.status !== 200) {
+      await new Promise((r) => setTimeout(r, 500));
+      continue;
+    }
+    const request = await res.json();
+    setStatus('approve ' + request.method + ' request in your extension...');
+    const response = { id: request.id };
+    try {
+      if (!window.nostr) throw new Error('no nostr browser extension found');
+      response.result = await handle(request);
+    } catch (e) {
+      response.error = String(e);
+    }
+    await fetch('response', { method: 'POST', body: JSON.stringify(response) });
+    setStatus('waiting for signing requests from ngit...');
+  }
+}
+poll();
+</script>
+</body>
+</html>
+"#;
+
+#[derive(Debug, Default)]
+struct Bridge {
+    pending: VecDeque<Value>,
+    /// taken by a page and not yet responded to, with when
+    in_flight: HashMap<u64, (Value, Instant)>,
+    waiting: HashMap<u64, oneshot::Sender<Result<Value, String>>>,
+    next_id: u64,
+}
+
+impl Bridge {
+    /// next request for a page to handle, requeuing any taken more than
+    /// [`IN_FLIGHT_GRACE`] ago without a response
+    fn take_request(&mut self, now: Instant) -> Option<Value> {
+        let mut stale: Vec<u64> = self
+            .in_flight
+            .iter()
+            .filter(|(_, (_, taken))| now.duration_since(*taken) >= IN_FLIGHT_GRACE)
+            .map(|(id, _)| *id)
+            .collect();
+        stale.sort_unstable();
+        for id in stale.into_iter().rev() {
+            if let Some((request, _)) = self.in_flight.remove(&id) {
+                self.pending.push_front(request);
+            }
+        }
+        let request = self.pending.pop_front()?;
+        if let Some(id) = request["id"].as_u64() {
+            self.in_flight.insert(id, (request.clone(), now));
+        }
+        Some(request)
+    }
+
+    fn take_waiting(&mut self, id: u64) -> Option<oneshot::Sender<Result<Value, String>>> {
+        self.in_flight.remove(&id);
+        self.waiting.remove(&id)
+    }
+}
+
+/// signs using a NIP-07 browser extension (eg. nos2x or Alby) through a page
+/// served on localhost so the secret key never leaves the extension. the
+/// server is only started when the first request is made.
+#[derive(Debug)]
+pub struct Nip07BridgeSigner {
+    public_key: OnceCell<PublicKey>,
+    bridge: Arc<Mutex<Bridge>>,
+    server: OnceCell<()>,
+}
+
+impl Nip07BridgeSigner {
+    pub fn new(public_key: Option<PublicKey>) -> Self {
+        Self {
+            public_key: OnceCell::new_with(public_key),
+            bridge: Arc::new(Mutex::new(Bridge::default())),
+            server: OnceCell::new(),
+        }
+    }
+
+    async fn start_server(&self) -> Result<()> {
+        self.server
+            .get_or_try_init(|| async {
+                let listener = TcpListener::bind("127.0.0.1:0")
+                    .await
+                    .context("failed to start browser extension signer")?;
+                // stops other sites from posting responses
+                let token = Keys::generate().public_key().to_hex();
+                eprintln!(
+                    "open http://{}/{token}/ in a browser with a nostr extension to approve signing requests",
+                    listener.local_addr()?
+                );
+                let bridge = self.bridge.clone();
+                tokio::spawn(async move {
+                    while let Ok((stream, _)) = listener.accept().await {
+                        let bridge = bridge.clone();
+                        let token = token.clone();
+                        tokio::spawn(async move {
+                            let _ = handle_connection(stream, &token, &bridge).await;
+                        });
+                    }
+                });
+                Ok::<(), anyhow::Error>(())
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        self.start_server().await?;
+        let (sender, receiver) = oneshot::channel();
+        let id = {
+            let mut bridge = self.bridge.lock().await;
+            let id = bridge.next_id;
+            bridge.next_id += 1;
+            bridge
+                .pending
+                .push_back(json!({ "id": id, "method": method, "params": params }));
+            bridge.waiting.insert(id, sender);
+            id
+        };
+        match tokio::time::timeout(REQUEST_TIMEOUT, receiver).await {
+            Ok(Ok(Ok(value))) => Ok(value),
+            Ok(Ok(Err(error))) => bail!("browser extension failed to {method}: {error}"),
+            Ok(Err(_)) => bail!("browser extension signer stopped"),
+            Err(_) => {
+                let mut bridge = self.bridge.lock().await;
+                bridge.pending.retain(|r| r["id"].as_u64() != Some(id));
+                bridge.take_waiting(id);
+                bail!("timed out waiting for browser extension to {method}")
+            }
+        }
+    }
+
+    async fn request_string(&self, method: &str, params: Value) -> Result<String, SignerError> {
+        self.request(method, params)
+            .await
+            .and_then(|value| {
+                value.as_str().map(String::from).context(format!(
+                    "browser extension returned invalid {method} response"
+                ))
+            })
+            .map_err(to_signer_error)
+    }
+}
+
+fn to_signer_error(error: anyhow::Error) -> SignerError {
+    SignerError::backend(std::io::Error::other(format!("{error:#}")))
+}
+
+#[async_trait]
+impl NostrSigner for Nip07BridgeSigner {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::BrowserExtension
+    }
+
+    async fn get_public_key(&self) -> Result<PublicKey, SignerError> {
+        self.public_key
+            .get_or_try_init(|| async {
+                let public_key = self.request_string("getPublicKey", json!([])).await?;
+                PublicKey::parse(&public_key)
+                    .context("browser extension returned invalid public key")
+                    .map_err(to_signer_error)
+            })
+            .await
+            .copied()
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        let unsigned_json: Value = serde_json::from_str(&unsigned.as_json())
+            .context("failed to serialize unsigned event")
+            .map_err(to_signer_error)?;
+        let signed = self
+            .request("signEvent", json!([unsigned_json]))
+            .await
+            .map_err(to_signer_error)?;
+        let event = Event::from_json(signed.to_string())
+            .context("browser extension returned invalid event")
+            .map_err(to_signer_error)?;
+        if event.pubkey != unsigned.pubkey || event.verify().is_err() {
+            return Err(to_signer_error(anyhow::anyhow!(
+                "browser extension signed event with a different key or invalid signature"
+            )));
+        }
+        Ok(event)
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self.request_string("nip04.encrypt", json!([public_key.to_hex(), content]))
+            .await
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: &PublicKey,
+        encrypted_content: &str,
+    ) -> Result<String, SignerError> {
+        self.request_string(
+            "nip04.decrypt",
+            json!([public_key.to_hex(), encrypted_content]),
+        )
+        .await
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self.request_string("nip44.encrypt", json!([public_key.to_hex(), content]))
+            .await
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: &PublicKey,
+        payload: &str,
+    ) -> Result<String, SignerError> {
+        self.request_string("nip44.decrypt", json!([public_key.to_hex(), payload]))
+            .await
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    token: &str,
+    bridge: &Mutex<Bridge>,
+) -> Result<()> {
+    let (method, path, body) = read_http_request(&mut stream).await?;
+    let (status, content_type, body) =
+        match (method.as_str(), path.strip_prefix(&format!("/{token}/"))) {
+            ("GET", Some("")) => ("200 OK", "text/html", BRIDGE_PAGE.to_string()),
+            ("GET", Some("request")) => match bridge.lock().await.take_request(Instant::now()) {
+                Some(request) => ("200 OK", "application/json", request.to_string()),
+                None => ("204 No Content", "application/json", String::new()),
+            },
+            ("POST", Some("response")) => {
+                let response: Value =
+                    serde_json::from_slice(&body).context("invalid response from browser")?;
+                let id = response["id"].as_u64().context("response missing id")?;
+                if let Some(sender) = bridge.lock().await.take_waiting(id) {
+                    let _ = sender.send(match response.get("error").filter(|e| !e.is_null()) {
+                        Some(error) => Err(error.as_str().unwrap_or_default().to_string()),
+                        None => Ok(response["result"].clone()),
+                    });
+                }
+                ("200 OK", "application/json", "{}".to_string())
+            }
+            _ => ("404 Not Found", "text/plain", String::new()),
+        };
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    Ok(())
+}
+
+async fn read_http_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>)> {
+    let mut data = vec![];
+    let mut buf = [0; 4096];
+    let head_end = loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            bail!("connection closed before request was received");
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(position) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break position + 4;
+        }
+        if data.len() > MAX_HEAD_LEN {
+            bail!("request headers too large");
+        }
+    };
+    let (method, path, content_length) =
+        parse_request_head(&String::from_utf8_lossy(&data[..head_end]))
+            .context("invalid http request")?;
+    if content_length > MAX_BODY_LEN {
+        bail!("request body too large");
+    }
+    while data.len() < head_end + content_length {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            bail!("connection closed before request body was received");
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    Ok((
+        method,
+        path,
+        data[head_end..head_end + content_length].to_vec(),
+    ))
+}
+
+/// method, path and content length from the request line and headers
+fn parse_request_head(head: &str) -> Option<(String, String, usize)> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    Some((method, path, content_length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_http_request_rejects_large_bodies() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let mut client = TcpStream::connect(listener.local_addr()?).await?;
+        let (mut server, _) = listener.accept().await?;
+        client
+            .write_all(
+                format!(
+                    "POST /abc/response HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                    MAX_BODY_LEN + 1
+                )
+                .as_bytes(),
+            )
+            .await?;
+        assert!(read_http_request(&mut server).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn requests_taken_without_a_response_are_offered_again() {
+        let mut bridge = Bridge::default();
+        bridge.pending.push_back(json!({ "id": 0 }));
+        bridge.pending.push_back(json!({ "id": 1 }));
+        let now = Instant::now();
+        assert_eq!(bridge.take_request(now), Some(json!({ "id": 0 })));
+        // another page asking straight away gets the next one
+        assert_eq!(bridge.take_request(now), Some(json!({ "id": 1 })));
+        assert_eq!(bridge.take_request(now), None);
+
+        bridge.take_waiting(1);
+        assert_eq!(
+            bridge.take_request(now + IN_FLIGHT_GRACE),
+            Some(json!({ "id": 0 }))
+        );
+        assert_eq!(bridge.take_request(now + IN_FLIGHT_GRACE), None);
+    }
+
+    #[test]
+    fn parse_request_head_extracts_method_path_and_content_length() {
+        assert_eq!(
+            parse_request_head(
+                "POST /abc/response HTTP/1.1\r\nHost: 127.0.0.1\r\ncontent-length: 42\r\n\r\n"
+            ),
+            Some(("POST".to_string(), "/abc/response".to_string(), 42))
+        );
+        assert_eq!(
+            parse_request_head("GET /abc/ HTTP/1.1\r\n\r\n"),
+            Some(("GET".to_string(), "/abc/".to_string(), 0))
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+
+use crate::git::{Repo, get_git_config_item};
+
+/// saved as nostr.nsec when the secret key is held in the os keyring
+pub static KEYRING_NSEC_PLACEHOLDER: &str = "keyring";
+
+static KEYRING_SERVICE: &str = "ngit";
+
+/// the os keyring isn't used in tests or if disabled via
+/// `git config --global nostr.keyring false`
+pub fn keyring_enabled() -> bool {
+    !crate::settings::settings().test_mode
+        && !get_git_config_item(&None, "nostr.keyring")
+            .is_ok_and(|value| value.is_some_and(|value| value == "false"))
+}
+
+fn entry(npub: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, npub).context("failed to access os keyring")
+}
+
+/// store nsec or ncryptsec in the macOS Keychain, Secret Service or Windows
+/// Credential Manager
+pub fn save_nsec_to_keyring(npub: &str, nsec: &str) -> Result<()> {
+    entry(npub)?
+        .set_password(nsec)
+        .context("failed to save secret key to os keyring")
+}
+
+/// the nsec or ncryptsec itself, fetching it from the keyring if nostr.nsec
+/// is the placeholder
+pub fn resolve_nsec(nsec: &str, npub: &Option<String>) -> Result<String> {
+    if nsec == KEYRING_NSEC_PLACEHOLDER {
+        get_nsec_from_keyring(
+            npub.as_ref()
+                .context("nostr.npub is required to get secret key from os keyring")?,
+        )
+    } else {
+        Ok(nsec.to_string())
+    }
+}
+
+pub fn get_nsec_from_keyring(npub: &str) -> Result<String> {
+    entry(npub)?.get_password().context(format!(
+        "failed to get secret key for {npub} from os keyring"
+    ))
+}
+
+/// remove the secret key from the keyring unless another login, local or
+/// global, still refers to it
+pub fn delete_nsec_from_keyring_if_unused(git_repo: &Option<&Repo>, npub: &str) -> Result<()> {
+    let still_used = |git_repo: &Option<&Repo>| {
+        get_git_config_item(git_repo, "nostr.nsec")
+            .is_ok_and(|nsec| nsec.is_some_and(|nsec| nsec == KEYRING_NSEC_PLACEHOLDER))
+            && get_git_config_item(git_repo, "nostr.npub")
+                .is_ok_and(|n| n.is_some_and(|n| n == npub))
+    };
+    if still_used(&None) || (git_repo.is_some() && still_used(git_repo)) || used_by_identity(npub) {
+        return Ok(());
+    }
+    match entry(npub)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(error) => Err(error).context("failed to remove secret key from os keyring"),
+    }
+}
+
+/// whether a named identity in global git config logs in as `npub`
+fn used_by_identity(npub: &str) -> bool {
+    let Ok(config) = git2::Config::open_default().and_then(|mut c| c.open_global()) else {
+        return false;
+    };
+    let Ok(mut entries) = config.entries(Some(r"^nostr\.identity\..*\.npub$")) else {
+        return false;
+    };
+    while let Some(Ok(entry)) = entries.next() {
+        if entry.value() == Some(npub) {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,311 @@
+//! decrypted ncryptsecs are held in memory by a short-lived agent process,
+//! `ngit account unlock-agent`, which exits once the last key expires. like
+//! `git credential-cache` it listens on a unix socket only the user can reach.
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use nostr::Keys;
+use nostr_sdk::ToBech32;
+
+use crate::{get_dirs, settings::settings};
+
+/// how long the agent waits for its first key, and a client waits for a
+/// newly started agent
+static AGENT_STARTUP_GRACE: Duration = Duration::from_secs(5);
+
+/// how often the agent checks for expired keys between requests
+static AGENT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// how long either side waits for the other to read or reply
+static AGENT_IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// an unlocked key held by the agent
+struct UnlockedKey {
+    keys: Keys,
+    expires_at: Instant,
+}
+
+/// how long a decrypted ncryptsec stays unlocked after the password is
+/// entered, set with `git config --global nostr.unlock-timeout 15` or
+/// `unlock-timeout` in `~/.config/ngit/config.toml`. disabled by default, in
+/// tests and where unix sockets aren't available.
+pub fn unlock_timeout_secs() -> u64 {
+    if !cfg!(unix) || settings().test_mode {
+        return 0;
+    }
+    settings().unlock_timeout_mins * 60
+}
+
+/// in a directory only the user can access
+fn agent_socket_path() -> Result<PathBuf> {
+    let dirs = get_dirs()?;
+    let dir = dirs
+        .runtime_dir()
+        .unwrap_or(dirs.cache_dir())
+        .join("unlock-agent");
+    std::fs::create_dir_all(&dir).context("failed to create unlock agent directory")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .context("failed to restrict unlock agent directory")?;
+    }
+    Ok(dir.join("socket"))
+}
+
+/// send a one line request to the agent and return its one line reply
+#[cfg(unix)]
+fn agent_request(request: &str) -> Result<String> {
+    let mut stream = std::os::unix::net::UnixStream::connect(agent_socket_path()?)
+        .context("unlock agent isn't running")?;
+    stream.set_read_timeout(Some(AGENT_IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(AGENT_IO_TIMEOUT))?;
+    stream.write_all(format!("{request}\n").as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .context("unlock agent didn't reply")?;
+    Ok(reply.trim_end().to_string())
+}
+
+#[cfg(not(unix))]
+fn agent_request(_request: &str) -> Result<String> {
+    bail!("the unlock agent needs unix sockets")
+}
+
+/// `ngit`, also when a key is unlocked by git-remote-nostr
+fn agent_exe() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(format!("ngit{}", std::env::consts::EXE_SUFFIX)))
+        .filter(|ngit| ngit.exists())
+        .unwrap_or_else(|| PathBuf::from("ngit"))
+}
+
+fn start_agent() -> Result<()> {
+    let mut command = std::process::Command::new(agent_exe());
+    command
+        .args(["account", "unlock-agent"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    // outlive the command that started it, and its ctrl-c
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    command.spawn().context("failed to start unlock agent")?;
+    let started = Instant::now();
+    while agent_request("ping").is_err() {
+        if started.elapsed() > AGENT_STARTUP_GRACE {
+            bail!("unlock agent didn't start");
+        }
+        std::thread::sleep(AGENT_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// keys decrypted within the unlock timeout so the password isn't asked for
+/// again
+pub fn get_unlocked_key(npub: &str) -> Option<Keys> {
+    if unlock_timeout_secs() == 0 {
+        return None;
+    }
+    let unlocked = Keys::from_str(&agent_request(&format!("get {npub}")).ok()?).ok()?;
+    if unlocked.public_key().to_bech32().ok()? == npub {
+        Some(unlocked)
+    } else {
+        None
+    }
+}
+
+/// keep `keys` unlocked in the agent until the unlock timeout passes, if
+/// enabled
+pub fn save_unlocked_key(keys: &Keys) -> Result<()> {
+    let timeout = unlock_timeout_secs();
+    if timeout == 0 {
+        return Ok(());
+    }
+    if agent_request("ping").is_err() {
+        start_agent()?;
+    }
+    let request = format!("unlock {} {timeout}", keys.secret_key().to_bech32()?);
+    if agent_request(&request)? == "ok" {
+        Ok(())
+    } else {
+        bail!("unlock agent refused the key")
+    }
+}
+
+/// stop the agent so the password is required again
+pub fn lock_keys() -> Result<()> {
+    if !agent_socket_path()?.exists() {
+        return Ok(());
+    }
+    match agent_request("lock") {
+        Ok(_) => Ok(()),
+        // a stale socket left by an agent that didn't exit cleanly
+        Err(_) => std::fs::remove_file(agent_socket_path()?)
+            .context("failed to remove stale unlock agent socket"),
+    }
+}
+
+/// removes expired keys from `keys`, returning the npubs removed
+fn remove_expired(keys: &mut HashMap<String, UnlockedKey>, now: Instant) -> Vec<String> {
+    let expired: Vec<String> = keys
+        .iter()
+        .filter(|(_, key)| key.expires_at <= now)
+        .map(|(npub, _)| npub.clone())
+        .collect();
+    for npub in &expired {
+        keys.remove(npub);
+    }
+    expired
+}
+
+/// reply to one request line and whether the agent should keep running
+fn handle_agent_request(
+    request: &str,
+    keys: &mut HashMap<String, UnlockedKey>,
+    now: Instant,
+) -> Result<(String, bool)> {
+    let mut words = request.split_whitespace();
+    Ok(match (words.next(), words.next(), words.next()) {
+        (Some("ping"), None, None) => ("ok".to_string(), true),
+        (Some("get"), Some(npub), None) => (
+            keys.get(npub)
+                .map(|key| key.keys.secret_key().to_bech32())
+                .transpose()?
+                .unwrap_or_default(),
+            true,
+        ),
+        (Some("unlock"), Some(nsec), Some(secs)) => {
+            let unlocked = Keys::from_str(nsec).context("invalid nsec")?;
+            let secs = secs.parse::<u64>().context("invalid timeout")?;
+            keys.insert(
+                unlocked.public_key().to_bech32()?,
+                UnlockedKey {
+                    keys: unlocked,
+                    expires_at: now + Duration::from_secs(secs),
+                },
+            );
+            ("ok".to_string(), true)
+        }
+        (Some("lock"), None, None) => {
+            keys.clear();
+            ("ok".to_string(), false)
+        }
+        _ => ("unknown request".to_string(), true),
+    })
+}
+
+/// serve unlocked keys from memory until the last one expires or `ngit
+/// account logout --lock` is run, then exit
+#[cfg(unix)]
+pub fn run_agent() -> Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    let path = agent_socket_path()?;
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).context("failed to start unlock agent")?;
+    listener.set_nonblocking(true)?;
+    let started = Instant::now();
+    let mut keys = HashMap::new();
+    loop {
+        remove_expired(&mut keys, Instant::now());
+        if keys.is_empty() && started.elapsed() > AGENT_STARTUP_GRACE {
+            break;
+        }
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(AGENT_POLL_INTERVAL);
+                continue;
+            }
+            Err(error) => return Err(error).context("unlock agent failed to accept request"),
+        };
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(AGENT_IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(AGENT_IO_TIMEOUT))?;
+        let mut request = String::new();
+        if BufReader::new(&stream).read_line(&mut request).is_err() {
+            continue;
+        }
+        let (reply, keep_running) = handle_agent_request(&request, &mut keys, Instant::now())
+            .unwrap_or_else(|error| (format!("{error:#}"), true));
+        let _ = (&stream).write_all(format!("{reply}\n").as_bytes());
+        if !keep_running {
+            break;
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run_agent() -> Result<()> {
+    bail!("the unlock agent needs unix sockets")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked_key(expires_at: Instant) -> UnlockedKey {
+        UnlockedKey {
+            keys: Keys::generate(),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn remove_expired_only_removes_expired_keys() {
+        let now = Instant::now();
+        let mut keys = HashMap::from([
+            ("npub1a".to_string(), unlocked_key(now)),
+            (
+                "npub1b".to_string(),
+                unlocked_key(now + Duration::from_secs(60)),
+            ),
+        ]);
+        assert_eq!(remove_expired(&mut keys, now), vec!["npub1a"]);
+        assert_eq!(keys.keys().collect::<Vec<&String>>(), vec!["npub1b"]);
+        assert!(remove_expired(&mut keys, now).is_empty());
+    }
+
+    #[test]
+    fn agent_serves_unlocked_keys_until_they_expire_or_are_locked() -> Result<()> {
+        let unlocked = Keys::generate();
+        let npub = unlocked.public_key().to_bech32()?;
+        let nsec = unlocked.secret_key().to_bech32()?;
+        let now = Instant::now();
+        let mut keys = HashMap::new();
+
+        assert_eq!(
+            handle_agent_request(&format!("get {npub}"), &mut keys, now)?,
+            (String::new(), true)
+        );
+        assert_eq!(
+            handle_agent_request(&format!("unlock {nsec} 60"), &mut keys, now)?,
+            ("ok".to_string(), true)
+        );
+        assert_eq!(
+            handle_agent_request(&format!("get {npub}\n"), &mut keys, now)?,
+            (nsec, true)
+        );
+        remove_expired(&mut keys, now + Duration::from_secs(60));
+        assert_eq!(
+            handle_agent_request(&format!("get {npub}"), &mut keys, now)?,
+            (String::new(), true)
+        );
+        assert_eq!(
+            handle_agent_request("lock", &mut keys, now)?,
+            ("ok".to_string(), false)
+        );
+        Ok(())
+    }
+}
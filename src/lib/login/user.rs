@@ -5,6 +5,7 @@ use nostr::PublicKey;
 use nostr_sdk::{Alphabet, JsonUtil, Kind, SingleLetterTag, Timestamp, ToBech32};
 use serde::{self, Deserialize, Serialize};
 
+use super::nip05::cached_nip05_verification;
 #[cfg(not(test))]
 use crate::client::Client;
 #[cfg(test)]
@@ -131,6 +132,43 @@ pub async fn get_user_ref_from_cache(
     })
 }
 
+/// name from the cached profile, with the nip05 address when it is verified eg.
+/// `alice (alice@example.com ✓)`. a shortened npub if there is no profile.
+/// only cached verifications are used so listing events never waits on nip05
+/// servers
+pub async fn get_author_display_name(
+    git_repo_path: Option<&Path>,
+    public_key: &PublicKey,
+) -> String {
+    let Ok(user_ref) = get_user_ref_from_cache(git_repo_path, public_key).await else {
+        return format_author_display_name(public_key, None, false);
+    };
+    let nip05_verified = if let Some(nip05) = &user_ref.metadata.nip05 {
+        cached_nip05_verification(public_key, nip05)
+    } else {
+        false
+    };
+    format_author_display_name(public_key, Some(&user_ref.metadata), nip05_verified)
+}
+
+fn format_author_display_name(
+    public_key: &PublicKey,
+    metadata: Option<&UserMetadata>,
+    nip05_verified: bool,
+) -> String {
+    let npub = public_key
+        .to_bech32()
+        .unwrap_or_else(|_| public_key.to_hex());
+    let name = match metadata {
+        Some(metadata) if metadata.name != npub => metadata.name.clone(),
+        _ => format!("{}...{}", &npub[..12], &npub[npub.len() - 4..]),
+    };
+    match metadata.and_then(|m| m.nip05.as_ref()) {
+        Some(nip05) if nip05_verified => format!("{name} ({nip05} ✓)"),
+        _ => name,
+    }
+}
+
 pub fn extract_user_metadata(
     public_key: &nostr::PublicKey,
     events: &[nostr::Event],
@@ -215,3 +253,52 @@ pub fn extract_user_relays(public_key: &nostr::PublicKey, events: &[nostr::Event
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nostr::Keys;
+
+    use super::*;
+
+    mod format_author_display_name {
+        use super::*;
+
+        fn metadata(name: &str, nip05: Option<&str>) -> UserMetadata {
+            UserMetadata {
+                name: name.to_string(),
+                created_at: Timestamp::from(0),
+                nip05: nip05.map(String::from),
+            }
+        }
+
+        #[test]
+        fn shows_nip05_only_when_verified() {
+            let public_key = Keys::generate().public_key();
+            let metadata = metadata("alice", Some("alice@example.com"));
+            assert_eq!(
+                format_author_display_name(&public_key, Some(&metadata), true),
+                "alice (alice@example.com ✓)"
+            );
+            assert_eq!(
+                format_author_display_name(&public_key, Some(&metadata), false),
+                "alice"
+            );
+        }
+
+        #[test]
+        fn shortens_npub_when_no_name() -> Result<()> {
+            let public_key = Keys::generate().public_key();
+            let npub = public_key.to_bech32()?;
+            let expected = format!("{}...{}", &npub[..12], &npub[npub.len() - 4..]);
+            assert_eq!(
+                format_author_display_name(&public_key, None, false),
+                expected
+            );
+            assert_eq!(
+                format_author_display_name(&public_key, Some(&metadata(&npub, None)), false),
+                expected
+            );
+            Ok(())
+        }
+    }
+}
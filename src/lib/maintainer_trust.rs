@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use nostr::PublicKey;
+
+use crate::git::{Repo, get_git_config_item, save_git_config_item};
+
+/// whether a maintainer listed in an announcement should be treated as one
+#[derive(Debug, PartialEq, Eq)]
+pub enum MaintainerTrust {
+    Trusted,
+    Rejected,
+    /// only listed by a co-maintainer and not yet confirmed by the user
+    Unconfirmed,
+}
+
+/// maintainers listed by the trusted maintainer are trusted as they vouch for
+/// them. those only listed by co-maintainers must be in the
+/// `nostr.trusted-maintainers` allow-list, unless `nostr.maintainer-trust` or
+/// `maintainer-trust` in `~/.config/ngit/config.toml` is `open`, so a single
+/// compromised co-maintainer can't add others.
+pub fn get_maintainer_trust(
+    git_repo: &Option<&Repo>,
+    maintainer: &PublicKey,
+    listed_by: &PublicKey,
+    trusted_maintainer: &PublicKey,
+) -> MaintainerTrust {
+    evaluate_maintainer_trust(
+        maintainer,
+        listed_by,
+        trusted_maintainer,
+        &get_public_keys_from_git_config(git_repo, "nostr.trusted-maintainers"),
+        &get_public_keys_from_git_config(git_repo, "nostr.rejected-maintainers"),
+        crate::settings::settings().open_maintainer_trust,
+    )
+}
+
+fn evaluate_maintainer_trust(
+    maintainer: &PublicKey,
+    listed_by: &PublicKey,
+    trusted_maintainer: &PublicKey,
+    allowed: &HashSet<PublicKey>,
+    rejected: &HashSet<PublicKey>,
+    open: bool,
+) -> MaintainerTrust {
+    if maintainer == trusted_maintainer || maintainer == listed_by {
+        MaintainerTrust::Trusted
+    } else if rejected.contains(maintainer) {
+        MaintainerTrust::Rejected
+    } else if listed_by == trusted_maintainer || allowed.contains(maintainer) || open {
+        MaintainerTrust::Trusted
+    } else {
+        MaintainerTrust::Unconfirmed
+    }
+}
+
+fn get_public_keys_from_git_config(git_repo: &Option<&Repo>, item: &str) -> HashSet<PublicKey> {
+    parse_public_keys(
+        &get_git_config_item(git_repo, item)
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+    )
+}
+
+fn parse_public_keys(s: &str) -> HashSet<PublicKey> {
+    s.split(',')
+        .filter_map(|pk| PublicKey::parse(pk.trim()).ok())
+        .collect()
+}
+
+fn add_public_key_to_git_config(
+    git_repo: &Option<&Repo>,
+    item: &str,
+    public_key: &PublicKey,
+) -> Result<()> {
+    let mut public_keys = get_public_keys_from_git_config(git_repo, item)
+        .iter()
+        .map(PublicKey::to_hex)
+        .collect::<Vec<String>>();
+    public_keys.push(public_key.to_hex());
+    save_git_config_item(git_repo, item, &public_keys.join(","))
+}
+
+pub fn trust_maintainer(git_repo: &Option<&Repo>, public_key: &PublicKey) -> Result<()> {
+    add_public_key_to_git_config(git_repo, "nostr.trusted-maintainers", public_key)
+}
+
+pub fn reject_maintainer(git_repo: &Option<&Repo>, public_key: &PublicKey) -> Result<()> {
+    add_public_key_to_git_config(git_repo, "nostr.rejected-maintainers", public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::Keys;
+
+    use super::*;
+
+    #[test]
+    fn parse_public_keys_ignores_invalid_entries() {
+        let public_key = Keys::generate().public_key();
+        assert_eq!(
+            parse_public_keys(&format!("{}, invalid,", public_key.to_hex())),
+            HashSet::from([public_key])
+        );
+    }
+
+    mod evaluate_maintainer_trust {
+        use super::*;
+
+        #[test]
+        fn maintainers_listed_by_trusted_maintainer_are_trusted() {
+            let trusted = Keys::generate().public_key();
+            let maintainer = Keys::generate().public_key();
+            assert_eq!(
+                evaluate_maintainer_trust(
+                    &maintainer,
+                    &trusted,
+                    &trusted,
+                    &HashSet::new(),
+                    &HashSet::new(),
+                    false
+                ),
+                MaintainerTrust::Trusted
+            );
+        }
+
+        #[test]
+        fn maintainers_only_listed_by_co_maintainer_need_confirmation() {
+            let trusted = Keys::generate().public_key();
+            let co_maintainer = Keys::generate().public_key();
+            let maintainer = Keys::generate().public_key();
+            let evaluate = |allowed: &HashSet<PublicKey>, rejected: &HashSet<PublicKey>, open| {
+                evaluate_maintainer_trust(
+                    &maintainer,
+                    &co_maintainer,
+                    &trusted,
+                    allowed,
+                    rejected,
+                    open,
+                )
+            };
+            let none = HashSet::new();
+            let listed = HashSet::from([maintainer]);
+            assert_eq!(evaluate(&none, &none, false), MaintainerTrust::Unconfirmed);
+            assert_eq!(evaluate(&listed, &none, false), MaintainerTrust::Trusted);
+            assert_eq!(evaluate(&none, &listed, false), MaintainerTrust::Rejected);
+            assert_eq!(evaluate(&none, &none, true), MaintainerTrust::Trusted);
+            assert_eq!(evaluate(&none, &listed, true), MaintainerTrust::Rejected);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! proposals published by older ngit versions or other git-nostr tools, such
+//! as gitstr, republished as current NIP-34 proposals so switching tools
+//! doesn't lose them. recognised are patches that reference the repository
+//! only by its root commit (`r` tag) rather than its announcement (`a` tag),
+//! and series without a `t root` patch whose patches reply to one another
+//! with unmarked `e` tags.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use nostr::{
+    Event, EventBuilder, EventId, Filter, Kind, PublicKey, SingleLetterTag, Tag, TagKind,
+    TagStandard, ToBech32, UnsignedEvent,
+    nips::{nip01::Coordinate, nip10::Marker},
+};
+use nostr_sdk::Alphabet;
+
+use crate::{
+    delegation::build_with_delegation,
+    forge::IMPORTED_FROM_TAG,
+    git_events::{commit_msg_from_patch_oneliner, event_is_cover_letter, event_is_patch_set_root},
+    repo_ref::RepoRef,
+};
+
+/// tags describing the commit, kept as they were so the commit id still
+/// matches when the patch is applied
+static COMMIT_TAGS: [&str; 6] = [
+    "commit",
+    "parent-commit",
+    "commit-pgp-sig",
+    "description",
+    "author",
+    "committer",
+];
+
+/// patches referencing the repository by announcement or by root commit
+pub fn get_filter_patches_for_migration(
+    repo_coordinates: &HashSet<Coordinate>,
+    root_commit: &str,
+) -> Vec<Filter> {
+    vec![
+        Filter::default().kind(Kind::GitPatch).custom_tag(
+            SingleLetterTag::lowercase(Alphabet::A),
+            repo_coordinates
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>(),
+        ),
+        Filter::default()
+            .kind(Kind::GitPatch)
+            .custom_tag(SingleLetterTag::lowercase(Alphabet::R), [root_commit]),
+    ]
+}
+
+fn has_marked_root(event: &Event) -> bool {
+    event_is_patch_set_root(event)
+        || event.tags.iter().any(|t| {
+            t.as_slice().first().is_some_and(|name| name == "e")
+                && t.as_slice().get(3).is_some_and(|marker| marker == "root")
+        })
+}
+
+/// a patch current proposal lookups miss, because it doesn't reference any of
+/// `repo_coordinates` or isn't threaded with `t root` and NIP-10 markers.
+/// patches for other repositories, such as forks sharing the root commit,
+/// aren't legacy
+pub fn is_legacy_patch(event: &Event, repo_coordinates: &HashSet<Coordinate>) -> bool {
+    let coordinates: Vec<String> = repo_coordinates
+        .iter()
+        .map(std::string::ToString::to_string)
+        .collect();
+    let repo_references: Vec<&String> = event
+        .tags
+        .iter()
+        .filter_map(|t| match t.as_slice() {
+            [name, coordinate, ..]
+                if name == "a"
+                    && coordinate
+                        .starts_with(&format!("{}:", Kind::GitRepoAnnouncement.as_u16())) =>
+            {
+                Some(coordinate)
+            }
+            _ => None,
+        })
+        .collect();
+    if event.kind != Kind::GitPatch {
+        return false;
+    }
+    if repo_references.is_empty() {
+        return true;
+    }
+    repo_references.iter().any(|c| coordinates.contains(*c)) && !has_marked_root(event)
+}
+
+/// `legacy_patches` grouped into series, each oldest first. a patch joins the
+/// series of an earlier patch by the same author that it references
+pub fn legacy_series(legacy_patches: &[Event]) -> Vec<Vec<Event>> {
+    let mut patches: Vec<&Event> = legacy_patches.iter().collect();
+    patches.sort_by_key(|e| e.created_at);
+    let mut series: Vec<Vec<Event>> = vec![];
+    let mut series_of: HashMap<EventId, usize> = HashMap::new();
+    for patch in patches {
+        let index = if let Some(index) = patch
+            .tags
+            .event_ids()
+            .filter_map(|id| series_of.get(id).copied())
+            .find(|index| series[*index][0].pubkey == patch.pubkey)
+        {
+            series[index].push(patch.clone());
+            index
+        } else {
+            series.push(vec![patch.clone()]);
+            series.len() - 1
+        };
+        series_of.insert(patch.id, index);
+    }
+    series
+}
+
+/// `imported-from` value linking a migrated patch to the one it replaces
+pub fn migrated_from(legacy_patch: &Event) -> Result<String> {
+    Ok(format!("nostr:{}", legacy_patch.id.to_bech32()?))
+}
+
+/// `imported-from` values of `events`, to skip series already migrated
+pub fn get_migrated_from(events: &[Event]) -> HashSet<String> {
+    events
+        .iter()
+        .flat_map(|e| e.tags.iter())
+        .filter_map(|t| match t.as_slice() {
+            [name, value, ..] if name == IMPORTED_FROM_TAG => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// a proposal for `public_key` to sign with the content of each patch in
+/// `series`, threaded like `ngit send` threads them. each patch links to the
+/// one it replaces and mentions its author
+pub fn migrated_series(
+    series: &[Event],
+    repo_ref: &RepoRef,
+    root_commit: &str,
+    public_key: &PublicKey,
+) -> Result<Vec<UnsignedEvent>> {
+    let relay_hint = repo_ref.relays.first().cloned();
+    let mut events: Vec<UnsignedEvent> = vec![];
+    for (i, legacy_patch) in series.iter().enumerate() {
+        let mut tags: Vec<Tag> = repo_ref
+            .maintainers
+            .iter()
+            .map(|m| {
+                Tag::coordinate(Coordinate {
+                    kind: Kind::GitRepoAnnouncement,
+                    public_key: *m,
+                    identifier: repo_ref.identifier.to_string(),
+                    relays: repo_ref.relays.clone(),
+                })
+            })
+            .collect();
+        tags.push(Tag::from_standardized(TagStandard::Reference(
+            root_commit.to_string(),
+        )));
+        tags.push(Tag::custom(
+            TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
+            vec![format!(
+                "git patch: {}",
+                commit_msg_from_patch_oneliner(legacy_patch).unwrap_or_default()
+            )],
+        ));
+        if let (Some(root), Some(parent)) = (events.first(), events.last()) {
+            for (id, marker) in [(root.id, Marker::Root), (parent.id, Marker::Reply)] {
+                if let Some(event_id) = id {
+                    tags.push(Tag::from_standardized(TagStandard::Event {
+                        event_id,
+                        relay_url: relay_hint.clone(),
+                        marker: Some(marker),
+                        public_key: None,
+                        uppercase: false,
+                    }));
+                }
+            }
+        } else {
+            tags.push(Tag::hashtag("root"));
+            if event_is_cover_letter(legacy_patch) || legacy_patch.content.contains("[PATCH 0/") {
+                tags.push(Tag::hashtag("cover-letter"));
+            }
+        }
+        for tag in legacy_patch.tags.iter() {
+            let copied = match tag.as_slice() {
+                [name, value, ..] if name == "r" => value != root_commit,
+                [name, ..] if name == "branch-name" => i == 0,
+                [name, ..] => COMMIT_TAGS.contains(&name.as_str()),
+                _ => false,
+            };
+            if copied {
+                tags.push(tag.clone());
+            }
+        }
+        tags.extend(repo_ref.maintainers.iter().map(|pk| Tag::public_key(*pk)));
+        if legacy_patch.pubkey != *public_key
+            && !repo_ref.maintainers.contains(&legacy_patch.pubkey)
+        {
+            tags.push(Tag::public_key(legacy_patch.pubkey));
+        }
+        tags.push(Tag::custom(
+            TagKind::Custom(std::borrow::Cow::Borrowed(IMPORTED_FROM_TAG)),
+            vec![migrated_from(legacy_patch)?],
+        ));
+        let mut unsigned = build_with_delegation(
+            EventBuilder::new(Kind::GitPatch, legacy_patch.content.clone()).tags(tags),
+            *public_key,
+        )?;
+        unsigned.ensure_id();
+        events.push(unsigned);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::Keys;
+    use test_utils::{TEST_KEY_1_KEYS, generate_repo_ref_event};
+
+    use super::*;
+
+    fn repo_ref() -> Result<RepoRef> {
+        RepoRef::try_from((generate_repo_ref_event(), None))
+    }
+
+    fn patch(keys: &Keys, subject: &str, tags: Vec<Tag>) -> Result<Event> {
+        Ok(EventBuilder::new(
+            Kind::GitPatch,
+            format!(
+                "From 431b84edc0d2fa118d63faa3c2db9c73d630a5ae Mon Sep 17 00:00:00 2001\nSubject: [PATCH] {subject}\n\n---\n"
+            ),
+        )
+        .tags(tags)
+        .sign_with_keys(keys)?)
+    }
+
+    #[test]
+    fn patches_without_repo_or_thread_markers_are_legacy() -> Result<()> {
+        let repo_ref = repo_ref()?;
+        let coordinate = Tag::coordinate(repo_ref.coordinate_with_hint());
+        let current = patch(
+            &TEST_KEY_1_KEYS,
+            "current",
+            vec![coordinate.clone(), Tag::hashtag("root")],
+        )?;
+        let root_commit_only = patch(
+            &TEST_KEY_1_KEYS,
+            "older ngit",
+            vec![
+                Tag::from_standardized(TagStandard::Reference(repo_ref.root_commit.clone())),
+                Tag::hashtag("root"),
+            ],
+        )?;
+        let unthreaded = patch(&TEST_KEY_1_KEYS, "gitstr", vec![coordinate])?;
+        let fork = patch(
+            &TEST_KEY_1_KEYS,
+            "fork",
+            vec![Tag::coordinate(Coordinate {
+                identifier: "fork".to_string(),
+                ..repo_ref.coordinate_with_hint()
+            })],
+        )?;
+        assert!(!is_legacy_patch(&current, &repo_ref.coordinates()));
+        assert!(!is_legacy_patch(&fork, &repo_ref.coordinates()));
+        assert!(is_legacy_patch(&root_commit_only, &repo_ref.coordinates()));
+        assert!(is_legacy_patch(&unthreaded, &repo_ref.coordinates()));
+        Ok(())
+    }
+
+    #[test]
+    fn series_follow_references_from_the_same_author() -> Result<()> {
+        let author = Keys::generate();
+        let other = Keys::generate();
+        let first = patch(&author, "first", vec![])?;
+        let second = patch(&author, "second", vec![Tag::event(first.id)])?;
+        let reply_by_other = patch(&other, "unrelated", vec![Tag::event(first.id)])?;
+        let series = legacy_series(&[second.clone(), reply_by_other.clone(), first.clone()]);
+        assert_eq!(series.len(), 2);
+        assert!(series.contains(&vec![first, second]));
+        assert!(series.contains(&vec![reply_by_other]));
+        Ok(())
+    }
+
+    #[test]
+    fn migrated_series_is_threaded_and_links_to_the_original() -> Result<()> {
+        let repo_ref = repo_ref()?;
+        let author = Keys::generate();
+        let first = patch(
+            &author,
+            "first",
+            vec![Tag::custom(
+                TagKind::Custom(std::borrow::Cow::Borrowed("commit")),
+                vec!["431b84edc0d2fa118d63faa3c2db9c73d630a5ae".to_string()],
+            )],
+        )?;
+        let second = patch(&author, "second", vec![Tag::event(first.id)])?;
+        let migrated = migrated_series(
+            &[first.clone(), second],
+            &repo_ref,
+            &repo_ref.root_commit,
+            &TEST_KEY_1_KEYS.public_key(),
+        )?
+        .into_iter()
+        .map(|unsigned| unsigned.sign_with_keys(&TEST_KEY_1_KEYS))
+        .collect::<Result<Vec<Event>, _>>()?;
+
+        assert!(event_is_patch_set_root(&migrated[0]));
+        assert!(!is_legacy_patch(&migrated[0], &repo_ref.coordinates()));
+        assert!(!is_legacy_patch(&migrated[1], &repo_ref.coordinates()));
+        assert_eq!(migrated[0].content, first.content);
+        assert!(migrated[0].tags.iter().any(|t| t.as_slice()[0] == "commit"));
+        assert!(
+            migrated[0]
+                .tags
+                .public_keys()
+                .any(|pk| *pk == author.public_key())
+        );
+        assert!(migrated[1].tags.event_ids().any(|id| *id == migrated[0].id));
+        assert!(get_migrated_from(&migrated).contains(&migrated_from(&first)?));
+        Ok(())
+    }
+}
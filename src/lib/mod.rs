@@ -1,14 +1,127 @@
+//! nostr git primitives used by the `ngit` and `git-remote-nostr` binaries.
+//!
+//! the stable api, following semver from 1.0, is:
+//! - [`client`] fetching from relays, the local cache and publishing events
+//! - [`git_events`] building and reading nip34 patch, proposal and status
+//!   events
+//! - [`repo_ref`] repository announcements, maintainers and relays
+//! - [`git::nostr_url`] parsing and resolving `nostr://` clone urls
+//!
+//! other modules support the cli and may change in any release, including
+//! [`ffi`], an interim C abi for mobile and gui clients. see `examples/` for
+//! usage.
+//!
+//! everything touching git, the filesystem or the os keyring is behind the
+//! default `native` feature. without it only [`git_events`] and
+//! [`git::nostr_url`] are built, eg. for wasm32 clients. prompts and progress
+//! bars need the default `cli` feature too. with `native` alone, as for a gui
+//! or bot, nothing is drawn and prompts take their defaults, as with
+//! `--non-interactive`.
+#[cfg(feature = "native")]
+pub mod blossom;
+#[doc(hidden)]
+#[cfg(feature = "native")]
 pub mod cli_interactor;
+#[cfg(feature = "native")]
 pub mod client;
+pub mod delegation;
+#[cfg(feature = "native")]
+pub mod download;
+#[cfg(feature = "native")]
+pub mod email;
+#[cfg(feature = "native")]
+pub mod error;
+#[cfg(feature = "native")]
+pub mod feed;
+#[cfg(feature = "native")]
+pub mod ffi;
+#[cfg(feature = "native")]
+pub mod forge;
+#[cfg(feature = "native")]
+pub mod forgejo;
+#[cfg(feature = "native")]
 pub mod git;
+#[cfg(not(feature = "native"))]
+pub mod git {
+    //! without the `native` feature only [`nostr_url`] is available and nip05
+    //! lookups aren't cached
+    pub mod nostr_url;
+
+    /// there is no git repository to read config from without `native`
+    pub enum Repo {}
+
+    fn get_git_config_item(
+        _git_repo: &Option<&Repo>,
+        _item: &str,
+    ) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn save_git_config_item(
+        _git_repo: &Option<&Repo>,
+        _item: &str,
+        _value: &str,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
 pub mod git_events;
+#[cfg(feature = "native")]
+pub mod github;
+#[cfg(feature = "native")]
+pub mod gitlab;
+#[cfg(feature = "native")]
+pub mod hooks;
+#[cfg(feature = "native")]
+pub mod interrupt;
+#[cfg(feature = "native")]
+pub mod logging;
+#[cfg(feature = "native")]
 pub mod login;
+#[cfg(feature = "native")]
+pub mod maintainer_trust;
+#[cfg(feature = "native")]
+pub mod migrate;
+#[cfg(feature = "native")]
+pub mod notifications;
+#[cfg(feature = "native")]
+pub mod org;
+#[doc(hidden)]
+#[cfg(feature = "native")]
+pub mod output;
+#[cfg(feature = "native")]
+pub mod patchwork;
+#[doc(hidden)]
+#[cfg(feature = "native")]
+pub mod progress;
+#[cfg(feature = "native")]
+pub mod radicle;
+#[cfg(feature = "native")]
+pub mod recent_repos;
+#[cfg(feature = "native")]
 pub mod repo_ref;
+#[cfg(feature = "native")]
 pub mod repo_state;
+#[cfg(feature = "native")]
+pub mod self_update;
+#[cfg(feature = "native")]
+pub mod settings;
+#[cfg(feature = "native")]
+pub mod short_numbers;
+#[cfg(feature = "native")]
+pub mod signing_policy;
+#[doc(hidden)]
+#[cfg(feature = "native")]
+pub mod theme;
+#[cfg(feature = "native")]
+pub mod trailers;
 
+#[cfg(feature = "native")]
 use anyhow::{Result, anyhow};
+#[cfg(feature = "native")]
 use directories::ProjectDirs;
 
+#[cfg(feature = "native")]
 pub fn get_dirs() -> Result<ProjectDirs> {
     ProjectDirs::from("", "", "ngit").ok_or(anyhow!(
         "should find operating system home directories with rust-directories crate"
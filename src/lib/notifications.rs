@@ -0,0 +1,193 @@
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use nostr::{Event, Kind, PublicKey};
+
+use crate::{
+    git::{Repo, RepoActions},
+    git_events::{event_to_cover_letter, is_future_dated},
+};
+
+/// opted in with git config `nostr.notifications true` or `notifications = true`
+/// in ngit settings
+pub fn notifications_enabled(git_repo: &Repo) -> bool {
+    git_repo
+        .get_git_config_item("nostr.notifications", None)
+        .ok()
+        .flatten()
+        .map_or(crate::settings::settings().notifications, |v| v.eq("true"))
+}
+
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// raise a native notification with `notify-send` or on macOS `osascript`
+pub fn desktop_notify(summary: &str, body: &str) -> Result<()> {
+    let status = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .args([
+                "-e",
+                &format!(
+                    "display notification {} with title {}",
+                    applescript_string(body),
+                    applescript_string(summary)
+                ),
+            ])
+            .status()
+            .context("failed to run osascript")?
+    } else if cfg!(windows) {
+        bail!("desktop notifications aren't supported on windows");
+    } else {
+        Command::new("notify-send")
+            .args(["--app-name=ngit", summary, body])
+            .status()
+            .context("failed to run notify-send. is libnotify installed?")?
+    };
+    if !status.success() {
+        bail!("failed to show desktop notification");
+    }
+    Ok(())
+}
+
+fn title(proposal: &Event) -> String {
+    event_to_cover_letter(proposal).map_or_else(|_| "untitled".to_string(), |c| c.title)
+}
+
+/// summary and body of notifications for newly fetched events. patches and
+/// statuses only notify `user` when they are on the user's proposals and from
+/// someone else
+pub fn fetch_notifications(
+    repo_name: &str,
+    user: Option<&PublicKey>,
+    proposals: &[Event],
+    new_proposals: &[Event],
+    new_patches: &[Event],
+    new_statuses: &[Event],
+) -> Vec<(String, String)> {
+    let mut notifications = vec![];
+    let new_proposals: Vec<&Event> = new_proposals
+        .iter()
+        .filter(|p| !user.is_some_and(|user| p.pubkey.eq(user)))
+        .collect();
+    match new_proposals.as_slice() {
+        [] => {}
+        [proposal] => notifications.push((format!("new proposal in {repo_name}"), title(proposal))),
+        proposals => notifications.push((
+            format!("{} new proposals in {repo_name}", proposals.len()),
+            proposals
+                .iter()
+                .map(|p| title(p))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        )),
+    }
+    let Some(user) = user else {
+        return notifications;
+    };
+    for proposal in proposals.iter().filter(|p| p.pubkey.eq(user)) {
+        let on_proposal =
+            |e: &&Event| e.pubkey.ne(user) && e.tags.event_ids().any(|id| id.eq(&proposal.id));
+        let patches = new_patches.iter().filter(on_proposal).count();
+        if patches > 0 {
+            notifications.push((
+                format!(
+                    "new patch{} on your proposal",
+                    if patches == 1 { "" } else { "es" }
+                ),
+                title(proposal),
+            ));
+        }
+        if let Some(status) = new_statuses
+            .iter()
+            .filter(on_proposal)
+            .max_by_key(|s| (!is_future_dated(s), s.created_at))
+        {
+            notifications.push((
+                format!(
+                    "your proposal was {}",
+                    match status.kind {
+                        Kind::GitStatusApplied => "applied",
+                        Kind::GitStatusClosed => "closed",
+                        Kind::GitStatusDraft => "marked as draft",
+                        _ => "reopened",
+                    }
+                ),
+                title(proposal),
+            ));
+        }
+    }
+    notifications
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+
+    use super::*;
+
+    fn proposal(keys: &Keys) -> Event {
+        EventBuilder::new(
+            Kind::GitPatch,
+            "From ab1 Mon Sep 17 00:00:00 2001\nSubject: [PATCH 0/1] add feature\n\ndescription",
+        )
+        .tags([Tag::hashtag("root"), Tag::hashtag("cover-letter")])
+        .sign_with_keys(keys)
+        .unwrap()
+    }
+
+    fn reply(kind: Kind, to: &Event, keys: &Keys) -> Event {
+        EventBuilder::new(kind, "")
+            .tags([Tag::event(to.id)])
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn applescript_string_is_escaped() {
+        assert_eq!(
+            applescript_string("say \"hi\" \\"),
+            "\"say \\\"hi\\\" \\\\\""
+        );
+    }
+
+    #[test]
+    fn notifies_of_activity_on_users_proposals() {
+        let user = Keys::generate();
+        let maintainer = Keys::generate();
+        let own = proposal(&user);
+        let other = proposal(&maintainer);
+        let proposals = vec![own.clone(), other.clone()];
+        let notifications = fetch_notifications(
+            "ngit",
+            Some(&user.public_key()),
+            &proposals,
+            &proposals,
+            &[
+                reply(Kind::GitPatch, &own, &maintainer),
+                reply(Kind::GitPatch, &other, &maintainer),
+            ],
+            &[
+                reply(Kind::GitStatusApplied, &own, &maintainer),
+                reply(Kind::GitStatusClosed, &own, &user),
+            ],
+        );
+        assert_eq!(
+            notifications,
+            vec![
+                (
+                    "new proposal in ngit".to_string(),
+                    "add feature".to_string()
+                ),
+                (
+                    "new patch on your proposal".to_string(),
+                    "add feature".to_string()
+                ),
+                (
+                    "your proposal was applied".to_string(),
+                    "add feature".to_string()
+                ),
+            ]
+        );
+    }
+}
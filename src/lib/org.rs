@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use nostr::{
+    Event, Filter, Kind, PublicKey, SingleLetterTag, Tag, TagStandard, nips::nip01::Coordinate,
+};
+use nostr_sdk::NostrSigner;
+
+#[cfg(not(test))]
+use crate::client::Client;
+#[cfg(test)]
+use crate::client::MockConnect;
+use crate::{
+    client::{Connect, sign_event},
+    repo_ref::RepoRef,
+};
+
+/// marks a NIP-51 follow set as a list of an organization's maintainers
+static ORG_HASHTAG: &str = "ngit-org";
+
+/// a group of maintainers under a shared identifier, published as a NIP-51
+/// follow set so repositories can be anchored to the group rather than one
+/// person's npub
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Org {
+    pub identifier: String,
+    pub name: String,
+    pub public_key: PublicKey,
+    pub members: Vec<PublicKey>,
+}
+
+impl TryFrom<&Event> for Org {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &Event) -> Result<Self> {
+        if !event.kind.eq(&Kind::FollowSet)
+            || !event
+                .tags
+                .iter()
+                .any(|t| matches!(t.as_slice(), [k, v, ..] if k == "t" && v == ORG_HASHTAG))
+        {
+            bail!("event is not an organization maintainer list");
+        }
+        let identifier = event
+            .tags
+            .identifier()
+            .context("organization event is missing an identifier")?
+            .to_string();
+        let mut org = Org {
+            name: identifier.clone(),
+            identifier,
+            public_key: event.pubkey,
+            members: vec![event.pubkey],
+        };
+        for tag in event.tags.iter() {
+            match tag.as_slice() {
+                [t, name, ..] if t == "title" => org.name = name.clone(),
+                [t, pk, ..] if t == "p" => {
+                    if let Ok(pk) = PublicKey::parse(pk) {
+                        if !org.members.contains(&pk) {
+                            org.members.push(pk);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(org)
+    }
+}
+
+impl Org {
+    pub async fn to_event(&self, signer: &Arc<dyn NostrSigner>) -> Result<Event> {
+        sign_event(
+            nostr_sdk::EventBuilder::new(Kind::FollowSet, "").tags(
+                [
+                    vec![
+                        Tag::identifier(self.identifier.clone()),
+                        Tag::from_standardized(TagStandard::Title(self.name.clone())),
+                        Tag::hashtag(ORG_HASHTAG),
+                        Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
+                            vec![format!("git organization: {}", self.name)],
+                        ),
+                    ],
+                    self.members.iter().map(|pk| Tag::public_key(*pk)).collect(),
+                ]
+                .concat(),
+            ),
+            signer,
+        )
+        .await
+        .context("failed to create organization event")
+    }
+
+    pub fn coordinate(&self) -> Coordinate {
+        Coordinate {
+            kind: Kind::FollowSet,
+            public_key: self.public_key,
+            identifier: self.identifier.clone(),
+            relays: vec![],
+        }
+    }
+}
+
+pub fn get_filter_org(coordinate: &Coordinate) -> Filter {
+    Filter::default()
+        .kind(Kind::FollowSet)
+        .author(coordinate.public_key)
+        .identifier(coordinate.identifier.clone())
+}
+
+/// repository announcements tagged with the organization
+pub fn get_filter_org_repos(coordinate: &Coordinate) -> Filter {
+    Filter::default()
+        .kind(Kind::GitRepoAnnouncement)
+        .custom_tag(
+            SingleLetterTag::lowercase(nostr_sdk::Alphabet::A),
+            [Coordinate {
+                relays: vec![],
+                ..coordinate.clone()
+            }
+            .to_string()],
+        )
+}
+
+fn relays_for(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+    coordinate: &Coordinate,
+) -> Vec<String> {
+    [
+        client.get_fallback_relays().clone(),
+        coordinate.relays.iter().map(|r| r.to_string()).collect(),
+    ]
+    .concat()
+}
+
+/// most recent maintainer list for the organization at `coordinate`
+pub async fn fetch_org(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+    coordinate: &Coordinate,
+) -> Result<Org> {
+    let mut events = client
+        .get_events(
+            relays_for(client, coordinate),
+            vec![get_filter_org(coordinate)],
+        )
+        .await?;
+    events.sort_by_key(|e| e.created_at);
+    Org::try_from(events.last().context("organization not found on relays")?)
+}
+
+/// repositories announced by members of the organization that reference it
+pub async fn fetch_org_repos(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+    org: &Org,
+    coordinate: &Coordinate,
+) -> Result<Vec<RepoRef>> {
+    let mut events = client
+        .get_events(
+            relays_for(client, coordinate),
+            vec![get_filter_org_repos(coordinate).authors(org.members.clone())],
+        )
+        .await?;
+    events.sort_by_key(|e| e.created_at);
+    events.reverse();
+    let mut repos: Vec<RepoRef> = vec![];
+    for event in events {
+        if let Ok(repo_ref) = RepoRef::try_from((event, None)) {
+            if !repos.iter().any(|r| r.identifier.eq(&repo_ref.identifier)) {
+                repos.push(repo_ref);
+            }
+        }
+    }
+    repos.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(repos)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trips_members_and_includes_author() -> Result<()> {
+        let org = Org {
+            identifier: "example-org".to_string(),
+            name: "Example Org".to_string(),
+            public_key: TEST_KEY_1_KEYS.public_key(),
+            members: vec![TEST_KEY_1_KEYS.public_key(), TEST_KEY_2_KEYS.public_key()],
+        };
+        let event = org.to_event(&TEST_KEY_1_SIGNER).await?;
+        assert_eq!(Org::try_from(&event)?, org);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_org_finds_list_among_other_events() -> Result<()> {
+        let org = Org {
+            identifier: "example-org".to_string(),
+            name: "Example Org".to_string(),
+            public_key: TEST_KEY_1_KEYS.public_key(),
+            members: vec![TEST_KEY_1_KEYS.public_key(), TEST_KEY_2_KEYS.public_key()],
+        };
+        let client = MockConnect {
+            events: vec![
+                sign_event(
+                    nostr_sdk::EventBuilder::new(Kind::FollowSet, "")
+                        .tags([Tag::identifier("friends".to_string())]),
+                    &TEST_KEY_1_SIGNER,
+                )
+                .await?,
+                org.to_event(&TEST_KEY_1_SIGNER).await?,
+            ],
+            ..MockConnect::default()
+        };
+        assert_eq!(fetch_org(&client, &org.coordinate()).await?, org);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_follow_sets_without_org_hashtag() -> Result<()> {
+        let event = sign_event(
+            nostr_sdk::EventBuilder::new(Kind::FollowSet, "")
+                .tags([Tag::identifier("friends".to_string())]),
+            &TEST_KEY_1_SIGNER,
+        )
+        .await?;
+        assert!(Org::try_from(&event).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! how much ngit prints: `-q`, `-v` and `--json` output for editors and bots.
+//! with `--json` the result of a command is written to stdout as one json
+//! object and everything else, including progress and messages printed by the
+//! library, is moved to stderr
+
+use std::{
+    fs::File,
+    io::Write,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicI8, Ordering},
+    },
+};
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// -1 with `-q`, 1 with `-v` and 2 with `-vv`
+static VERBOSITY: AtomicI8 = AtomicI8::new(0);
+
+/// the original stdout once stdout has been pointed at stderr
+static RESULTS: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// switch to json output. on unix stdout is redirected to stderr so only
+/// [`emit`] reaches the original stdout. elsewhere messages printed with
+/// `println!` are left on stdout
+pub fn enable_json() -> Result<()> {
+    JSON.store(true, Ordering::Relaxed);
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+
+        std::io::stdout().flush()?;
+        let results = std::io::stdout()
+            .as_fd()
+            .try_clone_to_owned()
+            .context("failed to duplicate stdout")?;
+        // SAFETY: both are descriptors of this process that stay open
+        if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+            return Err(std::io::Error::last_os_error()).context("failed to redirect stdout");
+        }
+        let _ = RESULTS.set(Mutex::new(File::from(results)));
+    }
+    Ok(())
+}
+
+pub fn set_verbosity(verbosity: i8) {
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+/// hide progress bars and informational lines, leaving errors and results
+pub fn quiet() -> bool {
+    VERBOSITY.load(Ordering::Relaxed) < 0
+}
+
+/// log level implied by `-v` and `-vv` when none is set explicitly
+pub fn verbose_log_level() -> Option<&'static str> {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        ..=0 => None,
+        1 => Some("debug"),
+        _ => Some("trace"),
+    }
+}
+
+/// print an informational line to stdout unless `-q` was given
+pub fn info(line: &str) {
+    if !quiet() {
+        println!("{line}");
+    }
+}
+
+/// whether `--json` was given
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// write `value` as a line of json to stdout
+pub fn emit(value: &Value) -> Result<()> {
+    if let Some(results) = RESULTS.get() {
+        let mut results = results
+            .lock()
+            .map_err(|_| anyhow::anyhow!("stdout lock poisoned"))?;
+        writeln!(results, "{value}")?;
+        results.flush()?;
+    } else {
+        println!("{value}");
+    }
+    Ok(())
+}
+
+/// `{"error": message}`, with the context chain joined as anyhow's `{:#}` does
+pub fn error_value(error: &anyhow::Error) -> Value {
+    json!({ "error": format!("{error:#}") })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_sets_quiet_and_log_level() {
+        set_verbosity(-1);
+        assert!(quiet());
+        assert_eq!(verbose_log_level(), None);
+        set_verbosity(1);
+        assert!(!quiet());
+        assert_eq!(verbose_log_level(), Some("debug"));
+        set_verbosity(2);
+        assert_eq!(verbose_log_level(), Some("trace"));
+        set_verbosity(0);
+    }
+
+    #[test]
+    fn error_includes_context() {
+        let error = anyhow::anyhow!("relay refused event").context("failed to send patches");
+        assert_eq!(
+            error_value(&error),
+            json!({ "error": "failed to send patches: relay refused event" })
+        );
+    }
+}
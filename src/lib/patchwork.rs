@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use nostr::Kind;
+use serde_json::Value;
+
+use crate::forge::send_json_request;
+
+/// patchwork state matching a proposal status
+pub fn patchwork_state(status: Kind) -> &'static str {
+    match status {
+        Kind::GitStatusApplied => "accepted",
+        Kind::GitStatusClosed => "rejected",
+        Kind::GitStatusDraft => "rfc",
+        _ => "new",
+    }
+}
+
+/// a patch as tracked by patchwork
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchworkPatch {
+    pub id: u64,
+    pub state: String,
+}
+
+pub struct PatchworkClient {
+    url: String,
+    project: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl PatchworkClient {
+    /// `url` of the instance eg. `https://patchwork.kernel.org` and the
+    /// project's linkname
+    pub fn new(url: &str, project: &str, token: Option<&str>) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            project: project.to_string(),
+            token: token.map(str::to_string),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(token) = &self.token {
+            request.header(reqwest::header::AUTHORIZATION, format!("Token {token}"))
+        } else {
+            request
+        }
+    }
+
+    /// the patch patchwork parsed from the email with `message_id`, if it has
+    /// received it
+    pub async fn find_patch(&self, message_id: &str) -> Result<Option<PatchworkPatch>> {
+        let response = send_json_request(
+            self.authorize(self.http.get(format!(
+                "{}/api/1.2/patches/?project={}&msgid={}",
+                self.url,
+                urlencoding::encode(&self.project),
+                urlencoding::encode(message_id.trim_start_matches('<').trim_end_matches('>')),
+            ))),
+            "Patchwork",
+        )
+        .await?;
+        Ok(response
+            .as_array()
+            .context("Patchwork api didn't return a list of patches")?
+            .first()
+            .and_then(patch_from_json))
+    }
+
+    /// requires a token with maintainer rights on the project
+    pub async fn set_patch_state(&self, id: u64, state: &str) -> Result<()> {
+        send_json_request(
+            self.authorize(
+                self.http
+                    .patch(format!("{}/api/1.2/patches/{id}/", self.url))
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(serde_json::json!({ "state": state }).to_string()),
+            ),
+            "Patchwork",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+fn patch_from_json(value: &Value) -> Option<PatchworkPatch> {
+    Some(PatchworkPatch {
+        id: value.get("id").and_then(Value::as_u64)?,
+        state: value
+            .get("state")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_map_to_default_patchwork_states() {
+        assert_eq!(patchwork_state(Kind::GitStatusOpen), "new");
+        assert_eq!(patchwork_state(Kind::GitStatusApplied), "accepted");
+        assert_eq!(patchwork_state(Kind::GitStatusClosed), "rejected");
+        assert_eq!(patchwork_state(Kind::GitStatusDraft), "rfc");
+    }
+
+    #[test]
+    fn patch_from_json_reads_id_and_state() {
+        assert_eq!(
+            patch_from_json(&serde_json::json!({ "id": 42, "state": "under-review" })),
+            Some(PatchworkPatch {
+                id: 42,
+                state: "under-review".to_string()
+            }),
+        );
+        assert_eq!(patch_from_json(&serde_json::json!({})), None);
+    }
+}
@@ -0,0 +1,111 @@
+//! relay and patch progress bars. they are drawn by indicatif with the `cli`
+//! feature. without it these stand-ins keep the same api and draw nothing
+
+#[cfg(feature = "cli")]
+pub use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+
+#[cfg(not(feature = "cli"))]
+pub use self::hidden::*;
+
+#[cfg(not(feature = "cli"))]
+mod hidden {
+    use std::{borrow::Cow, convert::Infallible, fmt::Write, time::Duration};
+
+    pub struct ProgressDrawTarget;
+
+    impl ProgressDrawTarget {
+        pub fn hidden() -> Self {
+            Self
+        }
+    }
+
+    /// passed to keys added with [`ProgressStyle::with_key`], which are never
+    /// drawn
+    pub struct ProgressState;
+
+    impl ProgressState {
+        pub fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct ProgressStyle;
+
+    impl ProgressStyle {
+        pub fn with_template(_template: &str) -> Result<Self, Infallible> {
+            Ok(Self)
+        }
+
+        #[must_use]
+        pub fn with_key<F>(self, _key: &'static str, _f: F) -> Self
+        where
+            F: Fn(&ProgressState, &mut dyn Write) + Send + Sync + 'static,
+        {
+            self
+        }
+
+        #[must_use]
+        pub fn progress_chars(self, _chars: &str) -> Self {
+            self
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct ProgressBar;
+
+    impl ProgressBar {
+        pub fn new(_len: u64) -> Self {
+            Self
+        }
+
+        pub fn with_draw_target(_len: Option<u64>, _target: ProgressDrawTarget) -> Self {
+            Self
+        }
+
+        #[must_use]
+        pub fn with_prefix(self, _prefix: impl Into<Cow<'static, str>>) -> Self {
+            self
+        }
+
+        #[must_use]
+        pub fn with_style(self, _style: ProgressStyle) -> Self {
+            self
+        }
+
+        pub fn set_style(&self, _style: ProgressStyle) {}
+
+        pub fn set_prefix(&self, _prefix: impl Into<Cow<'static, str>>) {}
+
+        pub fn set_message(&self, _message: impl Into<Cow<'static, str>>) {}
+
+        pub fn enable_steady_tick(&self, _interval: Duration) {}
+
+        pub fn inc(&self, _delta: u64) {}
+
+        pub fn finish_with_message(&self, _message: impl Into<Cow<'static, str>>) {}
+
+        pub fn finish_and_clear(&self) {}
+    }
+
+    #[derive(Clone, Default)]
+    pub struct MultiProgress;
+
+    impl MultiProgress {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn with_draw_target(_target: ProgressDrawTarget) -> Self {
+            Self
+        }
+
+        pub fn add(&self, pb: ProgressBar) -> ProgressBar {
+            pb
+        }
+
+        pub fn clear(&self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
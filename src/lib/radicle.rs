@@ -0,0 +1,231 @@
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use nostr::{FromBech32, PublicKey};
+use nostr_sdk::hashes::sha1::Hash as Sha1Hash;
+use serde_json::Value;
+
+use crate::{
+    forge::{PullRequest, PullRequestState, fetch_ref, send_json_request},
+    git::{Repo, RepoActions, oid_to_sha1},
+};
+
+pub static DEFAULT_RADICLE_SEED: &str = "https://seed.radicle.xyz";
+
+/// a Radicle patch and the commit of its latest revision
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RadiclePatch {
+    pub id: String,
+    pub oid: String,
+    pub pull_request: PullRequest,
+}
+
+pub struct RadicleClient {
+    seed: String,
+    rid: String,
+    http: reqwest::Client,
+}
+
+impl RadicleClient {
+    /// `rid` eg. `rad:z3gqcJUoA1n9HaHKufZs5FCSGazv5` on the `seed` node's
+    /// http api
+    pub fn new(seed: &str, rid: &str) -> Result<Self> {
+        if !rid.starts_with("rad:z") {
+            bail!("invalid Radicle repository id \"{rid}\". expected rad:z...");
+        }
+        Ok(Self {
+            seed: seed.trim_end_matches('/').to_string(),
+            rid: rid.to_string(),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    pub fn clone_url(&self) -> String {
+        format!("{}/{}.git", self.seed, self.rid.trim_start_matches("rad:"))
+    }
+
+    fn web_url(&self) -> String {
+        format!(
+            "https://app.radicle.xyz/nodes/{}/{}",
+            self.seed
+                .trim_start_matches("https://")
+                .trim_start_matches("http://"),
+            self.rid,
+        )
+    }
+
+    /// patches in `state`: `open`, `draft`, `archived` or `merged`
+    pub async fn list_patches(&self, state: &str) -> Result<Vec<RadiclePatch>> {
+        let mut patches = vec![];
+        for page in 0..10 {
+            let response = send_json_request(
+                self.http.get(format!(
+                    "{}/api/v1/repos/{}/patches?state={state}&page={page}&perPage=100",
+                    self.seed, self.rid,
+                )),
+                "Radicle",
+            )
+            .await?;
+            let items = response
+                .as_array()
+                .context("Radicle api didn't return a list of patches")?;
+            for item in items {
+                patches.push(patch_from_json(item, &self.web_url())?);
+            }
+            if items.len() < 100 {
+                break;
+            }
+        }
+        Ok(patches)
+    }
+
+    /// the latest revision of `patch`, fetched from the seed unless it is
+    /// already in the repository eg. from `git fetch rad`
+    pub fn fetch_patch(&self, git_repo: &Repo, patch: &RadiclePatch) -> Result<Sha1Hash> {
+        if git_repo.does_commit_exist(&patch.oid)? {
+            return Ok(oid_to_sha1(&git2::Oid::from_str(&patch.oid)?));
+        }
+        fetch_ref(
+            git_repo,
+            &self.clone_url(),
+            &patch.oid,
+            &format!("refs/ngit-bridge/radicle/patches/{}", patch.id),
+            git2::RemoteCallbacks::new(),
+        )
+        .context(format!(
+            "failed to fetch Radicle patch {}. try `git fetch rad` first",
+            &patch.id
+        ))
+    }
+}
+
+pub fn patch_from_json(value: &Value, web_url: &str) -> Result<RadiclePatch> {
+    let str_at = |pointer: &str| value.pointer(pointer).and_then(Value::as_str);
+    let id = str_at("/id").context("patch is missing an id")?;
+    let revisions = value
+        .get("revisions")
+        .and_then(Value::as_array)
+        .context("patch is missing revisions")?;
+    let latest = revisions.last().context("patch has no revisions")?;
+    let author_did = str_at("/author/id").unwrap_or_default();
+    Ok(RadiclePatch {
+        id: id.to_string(),
+        oid: latest
+            .get("oid")
+            .and_then(Value::as_str)
+            .context("patch revision is missing an oid")?
+            .to_string(),
+        pull_request: PullRequest {
+            number: 0,
+            title: str_at("/title").unwrap_or_default().to_string(),
+            body: revisions
+                .first()
+                .and_then(|r| r.get("description"))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            state: match str_at("/state/status") {
+                Some("open" | "draft") => PullRequestState::Open,
+                Some("merged") => PullRequestState::Merged,
+                _ => PullRequestState::Closed,
+            },
+            html_url: format!("{web_url}/patches/{id}"),
+            author: str_at("/author/alias").unwrap_or(author_did).to_string(),
+            author_name: Some(author_did.to_string()).filter(|did| !did.is_empty()),
+            author_url: None,
+            head_ref: format!("patches/{}", id.chars().take(7).collect::<String>()),
+            base_ref: latest
+                .get("base")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            author_npub: None,
+        },
+    })
+}
+
+/// Radicle DIDs mapped to nostr accounts with git config
+/// `radicle-identity.<did>.npub`
+pub fn get_radicle_identities(git_repo: &Repo) -> Result<Vec<(String, PublicKey)>> {
+    let config = git_repo.git_repo.config()?;
+    let mut identities = vec![];
+    let mut entries = config.entries(Some(r"radicle-identity\..*\.npub"))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let (Some(name), Some(value)) = (entry.name(), entry.value()) else {
+            continue;
+        };
+        let did = name
+            .trim_start_matches("radicle-identity.")
+            .trim_end_matches(".npub");
+        identities.push((
+            did.to_string(),
+            PublicKey::from_bech32(value).context(format!("invalid npub for {did}"))?,
+        ));
+    }
+    Ok(identities)
+}
+
+/// open a Radicle patch for `tip` by pushing to `remote`'s magic
+/// `refs/patches` ref with `git-remote-rad`
+pub fn open_patch(
+    git_repo: &Repo,
+    remote: &str,
+    tip: &Sha1Hash,
+    title: &str,
+    description: &str,
+) -> Result<()> {
+    let status = Command::new("git")
+        .current_dir(git_repo.get_path()?)
+        .args([
+            "push",
+            "-o",
+            &format!("patch.message={title}"),
+            "-o",
+            &format!("patch.message={description}"),
+            remote,
+            &format!("{tip}:refs/patches"),
+        ])
+        .status()
+        .context("failed to run git push")?;
+    if !status.success() {
+        bail!("failed to open Radicle patch. is the {remote} remote set up with `rad init`?");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_from_json_uses_latest_revision() -> Result<()> {
+        let patch = patch_from_json(
+            &serde_json::json!({
+                "id": "a5e8b5c5e1c0b8b0a4c3b2e1d0c9b8a7f6e5d4c3",
+                "author": { "id": "did:key:z6MkA", "alias": "alice" },
+                "title": "add feature",
+                "state": { "status": "open" },
+                "revisions": [
+                    { "id": "r1", "description": "details", "base": "b1", "oid": "o1" },
+                    { "id": "r2", "description": "rebased", "base": "b2", "oid": "o2" },
+                ],
+            }),
+            "https://app.radicle.xyz/nodes/seed.radicle.xyz/rad:z1",
+        )?;
+        assert_eq!(patch.oid, "o2");
+        assert_eq!(patch.pull_request.body, "details");
+        assert_eq!(patch.pull_request.author, "alice");
+        assert_eq!(
+            patch.pull_request.author_name,
+            Some("did:key:z6MkA".to_string())
+        );
+        assert_eq!(patch.pull_request.state, PullRequestState::Open);
+        assert_eq!(patch.pull_request.head_ref, "patches/a5e8b5c");
+        assert_eq!(
+            patch.pull_request.html_url,
+            "https://app.radicle.xyz/nodes/seed.radicle.xyz/rad:z1/patches/a5e8b5c5e1c0b8b0a4c3b2e1d0c9b8a7f6e5d4c3"
+        );
+        Ok(())
+    }
+}
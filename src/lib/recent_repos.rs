@@ -0,0 +1,145 @@
+use std::{
+    fmt::Write as _,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use nostr_sdk::Timestamp;
+
+use crate::{get_dirs, settings::settings};
+
+/// caches refreshed by `ngit daemon` this recently are used without fetching
+pub static DAEMON_FRESH_SECS: u64 = 90;
+
+/// a repository fetched by an ngit command or the remote helper
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecentRepo {
+    pub path: PathBuf,
+    pub last_used: u64,
+    /// when `ngit daemon` last fetched it. 0 if never
+    pub last_refreshed: u64,
+}
+
+fn get_recent_repos_path() -> Result<PathBuf> {
+    let cache_dir = get_dirs()?.cache_dir().to_path_buf();
+    create_dir_all(&cache_dir).context(format!(
+        "failed to create cache directory in: {}",
+        cache_dir.display()
+    ))?;
+    Ok(cache_dir.join("recent-repos"))
+}
+
+/// lines of `<last used> <last refreshed> <path>`
+fn parse_recent_repos(contents: &str) -> Vec<RecentRepo> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            Some(RecentRepo {
+                last_used: parts.next()?.parse().ok()?,
+                last_refreshed: parts.next()?.parse().ok()?,
+                path: PathBuf::from(parts.next()?),
+            })
+        })
+        .collect()
+}
+
+fn format_recent_repos(repos: &[RecentRepo]) -> String {
+    repos.iter().fold(String::new(), |mut contents, repo| {
+        let _ = writeln!(
+            contents,
+            "{} {} {}",
+            repo.last_used,
+            repo.last_refreshed,
+            repo.path.display()
+        );
+        contents
+    })
+}
+
+/// repositories used within `max_age_secs`, most recently used first
+pub fn get_recent_repos(max_age_secs: u64) -> Result<Vec<RecentRepo>> {
+    let now = Timestamp::now().as_u64();
+    let mut repos: Vec<RecentRepo> =
+        parse_recent_repos(&std::fs::read_to_string(get_recent_repos_path()?).unwrap_or_default())
+            .into_iter()
+            .filter(|repo| now.saturating_sub(repo.last_used) < max_age_secs)
+            .collect();
+    repos.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    Ok(repos)
+}
+
+fn update_recent_repo(git_repo_path: &Path, update: impl FnOnce(&mut RecentRepo)) -> Result<()> {
+    if settings().test_mode {
+        return Ok(());
+    }
+    let path = get_recent_repos_path()?;
+    let mut repos = parse_recent_repos(&std::fs::read_to_string(&path).unwrap_or_default());
+    if let Some(repo) = repos.iter_mut().find(|repo| repo.path == git_repo_path) {
+        update(repo);
+    } else {
+        let mut repo = RecentRepo {
+            path: git_repo_path.to_path_buf(),
+            last_used: 0,
+            last_refreshed: 0,
+        };
+        update(&mut repo);
+        repos.push(repo);
+    }
+    std::fs::write(&path, format_recent_repos(&repos))
+        .context(format!("failed to write {}", path.display()))
+}
+
+/// remember the repository so `ngit daemon` keeps its cache warm
+pub fn record_used(git_repo_path: &Path) -> Result<()> {
+    let now = Timestamp::now().as_u64();
+    update_recent_repo(git_repo_path, |repo| repo.last_used = now)
+}
+
+pub fn record_refreshed(git_repo_path: &Path) -> Result<()> {
+    let now = Timestamp::now().as_u64();
+    update_recent_repo(git_repo_path, |repo| repo.last_refreshed = now)
+}
+
+/// whether `ngit daemon` fetched the repository within `DAEMON_FRESH_SECS`
+pub fn refreshed_by_daemon(git_repo_path: &Path) -> bool {
+    if settings().test_mode {
+        return false;
+    }
+    let now = Timestamp::now().as_u64();
+    get_recent_repos_path()
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .is_some_and(|contents| {
+            parse_recent_repos(&contents).iter().any(|repo| {
+                repo.path == git_repo_path
+                    && now.saturating_sub(repo.last_refreshed) < DAEMON_FRESH_SECS
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_repos_round_trip_with_spaces_in_path() {
+        let repos = vec![
+            RecentRepo {
+                path: PathBuf::from("/home/alice/my repo"),
+                last_used: 1_700_000_000,
+                last_refreshed: 0,
+            },
+            RecentRepo {
+                path: PathBuf::from("/srv/ngit"),
+                last_used: 1_700_000_100,
+                last_refreshed: 1_700_000_200,
+            },
+        ];
+        assert_eq!(
+            parse_recent_repos(&format!("{}garbage\n", format_recent_repos(&repos))),
+            repos
+        );
+    }
+}
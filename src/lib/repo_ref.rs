@@ -7,8 +7,10 @@ use std::{
 };
 
 use anyhow::{Context, Result, bail};
-use console::Style;
-use nostr::{FromBech32, PublicKey, Tag, TagStandard, ToBech32, nips::nip01::Coordinate};
+use nostr::{
+    EventId, FromBech32, PublicKey, Tag, TagStandard, ToBech32,
+    nips::{nip01::Coordinate, nip19::Nip19Event},
+};
 use nostr_sdk::{Kind, NostrSigner, RelayUrl, Timestamp};
 use serde::{Deserialize, Serialize};
 
@@ -18,12 +20,21 @@ use crate::{
     cli_interactor::{
         Interactor, InteractorPrompt, PromptChoiceParms, PromptConfirmParms, PromptInputParms,
     },
-    client::{Connect, consolidate_fetch_reports, get_repo_ref_from_cache, sign_event},
+    client::{
+        Connect, consolidate_fetch_reports, get_event_from_global_cache,
+        get_events_from_local_cache, get_repo_ref_from_cache, sign_event,
+    },
     git::{
-        Repo, RepoActions,
+        Repo, RepoActions, get_git_config_item,
         nostr_url::{NostrUrlDecoded, use_nip05_git_config_cache_to_find_nip05_from_public_key},
+        save_git_config_item,
     },
-    login::user::get_user_details,
+    git_events::get_proposal_commit_references,
+    login::{
+        get_likely_logged_in_user,
+        user::{get_author_display_name, get_user_details},
+    },
+    theme,
 };
 
 #[derive(Clone)]
@@ -33,7 +44,28 @@ pub struct RepoRef {
     pub identifier: String,
     pub root_commit: String,
     pub git_server: Vec<String>,
+    /// blossom servers hosting packfiles referenced in state events
+    pub blossoms: Vec<String>,
+    /// git bundle uris to seed initial clones from
+    pub bundles: Vec<String>,
     pub web: Vec<String>,
+    /// SPDX license identifier eg. `MIT`
+    pub license: Option<String>,
+    /// primary programming language
+    pub language: Option<String>,
+    /// topics, published as hashtags
+    pub hashtags: Vec<String>,
+    pub homepage: Option<String>,
+    /// how to reach the maintainers eg. an email address or nip05
+    pub contact: Option<String>,
+    /// identifiers the repository was published under before being renamed
+    pub previous_identifiers: Vec<String>,
+    /// set on the announcement left at an old identifier after a rename
+    pub renamed_to: Option<String>,
+    /// the repository this is a fork of
+    pub upstream: Option<Coordinate>,
+    /// organization maintainer list the repository belongs to
+    pub org: Option<Coordinate>,
     pub relays: Vec<RelayUrl>,
     pub maintainers: Vec<PublicKey>,
     pub trusted_maintainer: PublicKey,
@@ -56,7 +88,18 @@ impl TryFrom<(nostr::Event, Option<PublicKey>)> for RepoRef {
             identifier: String::new(),
             root_commit: String::new(),
             git_server: Vec::new(),
+            blossoms: Vec::new(),
+            bundles: Vec::new(),
             web: Vec::new(),
+            license: None,
+            language: None,
+            hashtags: Vec::new(),
+            homepage: None,
+            contact: None,
+            previous_identifiers: vec![],
+            renamed_to: None,
+            upstream: None,
+            org: None,
             relays: Vec::new(),
             maintainers: Vec::new(),
             trusted_maintainer: trusted_maintainer.unwrap_or(event.pubkey),
@@ -75,6 +118,31 @@ impl TryFrom<(nostr::Event, Option<PublicKey>)> for RepoRef {
                 [t, web @ ..] if t == "web" => {
                     r.web = web.to_vec();
                 }
+                [t, blossoms @ ..] if t == "blossoms" => {
+                    r.blossoms = blossoms.to_vec();
+                }
+                [t, bundles @ ..] if t == "bundles" => {
+                    r.bundles = bundles.to_vec();
+                }
+                [t, license, ..] if t == "license" => r.license = Some(license.clone()),
+                [t, language, ..] if t == "language" => r.language = Some(language.clone()),
+                [t, hashtag, ..] if t == "t" => r.hashtags.push(hashtag.clone()),
+                [t, homepage, ..] if t == "homepage" => r.homepage = Some(homepage.clone()),
+                [t, contact, ..] if t == "contact" => r.contact = Some(contact.clone()),
+                [t, previous @ ..] if t == "renamed-from" => {
+                    r.previous_identifiers = previous.to_vec();
+                }
+                [t, renamed_to, ..] if t == "renamed-to" => r.renamed_to = Some(renamed_to.clone()),
+                [t, upstream, ..] if t == "upstream" => {
+                    r.upstream = Coordinate::parse(upstream).ok();
+                }
+                [t, org, ..] if t == "a" => {
+                    if let Ok(org) = Coordinate::parse(org) {
+                        if org.kind.eq(&Kind::FollowSet) {
+                            r.org = Some(org);
+                        }
+                    }
+                }
                 [t, commit_id]
                     if t == "r"
                         && commit_id.len() == 40
@@ -185,7 +253,77 @@ impl RepoRef {
                             vec![format!("git repository: {}", self.name.clone())],
                         ),
                     ],
-                    // code languages and hashtags
+                    if self.blossoms.is_empty() {
+                        vec![]
+                    } else {
+                        vec![Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("blossoms")),
+                            self.blossoms.clone(),
+                        )]
+                    },
+                    if self.bundles.is_empty() {
+                        vec![]
+                    } else {
+                        vec![Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("bundles")),
+                            self.bundles.clone(),
+                        )]
+                    },
+                    if self.previous_identifiers.is_empty() {
+                        vec![]
+                    } else {
+                        vec![Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("renamed-from")),
+                            self.previous_identifiers.clone(),
+                        )]
+                    },
+                    if let Some(renamed_to) = &self.renamed_to {
+                        vec![Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("renamed-to")),
+                            vec![renamed_to.clone()],
+                        )]
+                    } else {
+                        vec![]
+                    },
+                    if let Some(org) = &self.org {
+                        vec![Tag::coordinate(Coordinate {
+                            relays: vec![],
+                            ..org.clone()
+                        })]
+                    } else {
+                        vec![]
+                    },
+                    if let Some(upstream) = &self.upstream {
+                        vec![Tag::custom(
+                            nostr::TagKind::Custom(std::borrow::Cow::Borrowed("upstream")),
+                            vec![
+                                Coordinate {
+                                    relays: vec![],
+                                    ..upstream.clone()
+                                }
+                                .to_string(),
+                            ],
+                        )]
+                    } else {
+                        vec![]
+                    },
+                    [
+                        ("license", &self.license),
+                        ("language", &self.language),
+                        ("homepage", &self.homepage),
+                        ("contact", &self.contact),
+                    ]
+                    .into_iter()
+                    .filter_map(|(name, value)| {
+                        value.as_ref().map(|value| {
+                            Tag::custom(
+                                nostr::TagKind::Custom(std::borrow::Cow::Borrowed(name)),
+                                vec![value.clone()],
+                            )
+                        })
+                    })
+                    .collect(),
+                    self.hashtags.iter().map(Tag::hashtag).collect(),
                 ]
                 .concat(),
             ),
@@ -194,6 +332,36 @@ impl RepoRef {
         .await
         .context("failed to create repository reference event")
     }
+    /// license, language, topics, homepage and contact on one line for
+    /// headers. `None` if none are set.
+    pub fn metadata_summary(&self) -> Option<String> {
+        let items: Vec<String> = [
+            self.license.clone(),
+            self.language.clone(),
+            if self.hashtags.is_empty() {
+                None
+            } else {
+                Some(
+                    self.hashtags
+                        .iter()
+                        .map(|t| format!("#{t}"))
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                )
+            },
+            self.homepage.clone(),
+            self.contact.as_ref().map(|c| format!("contact: {c}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if items.is_empty() {
+            None
+        } else {
+            Some(items.join(" · "))
+        }
+    }
+
     /// coordinates without relay hints
     pub fn coordinates(&self) -> HashSet<Coordinate> {
         let mut res = HashSet::new();
@@ -215,6 +383,34 @@ impl RepoRef {
         res
     }
 
+    /// coordinates without relay hints, including those under identifiers the
+    /// repository was known by before a rename so older proposals are found
+    pub fn coordinates_including_previous_identifiers(&self) -> HashSet<Coordinate> {
+        let mut res = self.coordinates();
+        for identifier in &self.previous_identifiers {
+            for c in self.coordinates() {
+                res.insert(Coordinate {
+                    identifier: identifier.clone(),
+                    ..c
+                });
+            }
+        }
+        res
+    }
+
+    /// the announcement to publish at the old identifier after a rename,
+    /// pointing to `new_identifier`
+    pub fn to_rename_tombstone(&self, new_identifier: &str) -> RepoRef {
+        RepoRef {
+            description: format!("renamed to {new_identifier}"),
+            previous_identifiers: vec![],
+            renamed_to: Some(new_identifier.to_string()),
+            events: HashMap::new(),
+            nostr_git_url: None,
+            ..self.clone()
+        }
+    }
+
     /// coordinates without relay hints
     pub fn coordinate_with_hint(&self) -> Coordinate {
         Coordinate {
@@ -229,6 +425,49 @@ impl RepoRef {
         }
     }
 
+    /// nevent for `event_id` with the repo's first relay as a hint
+    pub fn nevent(&self, event_id: &EventId) -> Result<String> {
+        Nip19Event::new(
+            *event_id,
+            self.relays.iter().take(1).map(ToString::to_string),
+        )
+        .to_bech32()
+        .context("failed to encode nevent")
+    }
+
+    /// repo page on `viewer_url` and its `nostr:` uri
+    pub fn links(&self, viewer_url: &str) -> Result<(String, String)> {
+        let naddr = self.coordinate_with_hint().to_bech32()?;
+        Ok((
+            format!("{}/repo/{naddr}", viewer_url.trim_end_matches('/')),
+            format!("nostr:{naddr}"),
+        ))
+    }
+
+    /// page for a proposal or issue on `viewer_url`, on njump.me and its
+    /// `nostr:` uri
+    pub fn event_links(
+        &self,
+        viewer_url: &str,
+        event_id: &EventId,
+        kind: Kind,
+    ) -> Result<(String, String, String)> {
+        let nevent = self.nevent(event_id)?;
+        let (repo_link, _) = self.links(viewer_url)?;
+        Ok((
+            format!(
+                "{repo_link}/{}/{nevent}",
+                if kind.eq(&Kind::GitIssue) {
+                    "issue"
+                } else {
+                    "proposal"
+                }
+            ),
+            format!("https://njump.me/{nevent}"),
+            format!("nostr:{nevent}"),
+        ))
+    }
+
     /// coordinates without relay hints
     pub fn coordinates_with_timestamps(&self) -> Vec<(Coordinate, Option<Timestamp>)> {
         self.coordinates()
@@ -260,6 +499,170 @@ impl RepoRef {
     }
 }
 
+/// maintainer whose announcement is used for name, relays, git servers etc.
+/// chosen when maintainers' announcements conflict
+pub fn get_announcement_source(git_repo: &Option<&Repo>) -> Option<PublicKey> {
+    get_git_config_item(git_repo, "nostr.announcement-source")
+        .ok()
+        .flatten()
+        .and_then(|pk| PublicKey::parse(pk).ok())
+}
+
+/// fields that differ between maintainers' announcements, with each
+/// maintainer's value
+pub fn get_announcement_differences(
+    repo_ref: &RepoRef,
+) -> Vec<(&'static str, Vec<(PublicKey, String)>)> {
+    let mut announcements: Vec<(PublicKey, RepoRef)> = repo_ref
+        .events
+        .values()
+        .filter_map(|e| Some((e.pubkey, RepoRef::try_from((e.clone(), None)).ok()?)))
+        .collect();
+    announcements.sort_by_key(|(pk, _)| *pk);
+    let fields: [(&'static str, fn(&RepoRef) -> String); 6] = [
+        ("name", |r| r.name.clone()),
+        ("description", |r| r.description.clone()),
+        ("git servers", |r| r.git_server.join(" ")),
+        ("relays", |r| {
+            r.relays
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(" ")
+        }),
+        ("maintainers", |r| {
+            let mut maintainers = r
+                .maintainers
+                .iter()
+                .filter_map(|m| m.to_bech32().ok())
+                .collect::<Vec<String>>();
+            maintainers.sort();
+            maintainers.join(" ")
+        }),
+        ("web", |r| r.web.join(" ")),
+    ];
+    fields
+        .iter()
+        .filter_map(|(field, value)| {
+            let values: Vec<(PublicKey, String)> = announcements
+                .iter()
+                .map(|(pk, r)| (*pk, value(r)))
+                .collect();
+            if values.iter().all(|(_, v)| v == &values[0].1) {
+                None
+            } else {
+                Some((*field, values))
+            }
+        })
+        .collect()
+}
+
+/// when maintainers' announcements conflict and the user hasn't chosen one,
+/// show the differences and persist which to use
+pub async fn choose_announcement_if_conflicting(git_repo: &Repo, repo_ref: &RepoRef) -> Result<()> {
+    if get_announcement_source(&Some(git_repo)).is_some() {
+        return Ok(());
+    }
+    let differences = get_announcement_differences(repo_ref);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let git_repo_path = git_repo.get_path()?;
+    let mut public_keys: Vec<PublicKey> = repo_ref.events.values().map(|e| e.pubkey).collect();
+    public_keys.sort();
+    let mut names = HashMap::new();
+    for pk in &public_keys {
+        names.insert(*pk, get_author_display_name(Some(git_repo_path), pk).await);
+    }
+    println!(
+        "maintainers' announcements of {} differ:",
+        repo_ref.identifier
+    );
+    for (field, values) in &differences {
+        println!("  {field}:");
+        for (pk, value) in values {
+            println!("    {}: {value}", names[pk]);
+        }
+    }
+    let selected = Interactor::default().choice(
+        PromptChoiceParms::default()
+            .with_prompt("which maintainer's announcement should be used?")
+            .with_default(
+                public_keys
+                    .iter()
+                    .position(|pk| pk.eq(&repo_ref.trusted_maintainer))
+                    .unwrap_or(0),
+            )
+            .with_choices(public_keys.iter().map(|pk| names[pk].clone()).collect()),
+    )?;
+    save_git_config_item(
+        &Some(git_repo),
+        "nostr.announcement-source",
+        &public_keys[selected].to_hex(),
+    )
+}
+
+/// warnings for when the announced earliest unique commit, or the root commit
+/// a proposal was created against, isn't in local history. patches applied to
+/// a different lineage won't make sense.
+pub fn get_lineage_warnings(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    proposals: &[nostr::Event],
+) -> Vec<String> {
+    // nothing to compare against in an empty repository
+    if git_repo.get_head_commit().is_err() {
+        return vec![];
+    }
+    let exists = |commit: &str| git_repo.does_commit_exist(commit).unwrap_or(false);
+    let mut warnings = vec![];
+    if !repo_ref.root_commit.is_empty() && !exists(&repo_ref.root_commit) {
+        warnings.push(format!(
+            "the earliest unique commit {} in the \"{}\" announcement isn't in your local history. this may be a different repository",
+            repo_ref.root_commit, repo_ref.name
+        ));
+    }
+    warnings.extend(
+        proposals
+            .iter()
+            .filter_map(|proposal| get_proposal_lineage_warning(git_repo, repo_ref, proposal)),
+    );
+    warnings
+}
+
+/// warning for a proposal whose `r` tags don't include the announced earliest
+/// unique commit or the local root commit, so it may have been created
+/// against a different repository. none if it has no commit `r` tags
+pub fn get_proposal_lineage_warning(
+    git_repo: &Repo,
+    repo_ref: &RepoRef,
+    proposal: &nostr::Event,
+) -> Option<String> {
+    let references = get_proposal_commit_references(proposal);
+    if references.is_empty() {
+        return None;
+    }
+    let local_root_commit = git_repo.get_root_commit().ok();
+    let expected: Vec<String> = [
+        Some(repo_ref.root_commit.clone()).filter(|commit| !commit.is_empty()),
+        local_root_commit.map(|commit| commit.to_string()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if expected.is_empty() || expected.iter().any(|commit| references.contains(commit)) {
+        return None;
+    }
+    let id = proposal
+        .id
+        .to_bech32()
+        .unwrap_or_else(|_| proposal.id.to_hex());
+    Some(format!(
+        "proposal {id} doesn't reference this repository's earliest unique commit {} so may be for a different repository. don't apply it unless you are sure it belongs here",
+        expected[0],
+    ))
+}
+
 pub async fn get_repo_coordinates_when_remote_unknown(
     git_repo: &Repo,
     #[cfg(test)] client: &crate::client::MockConnect,
@@ -382,22 +785,104 @@ async fn get_repo_coordinates_from_maintainers_yaml(git_repo: &Repo) -> Result<C
     })
 }
 
+/// offer repositories announced in the local and global cache, those sharing
+/// this repository's root commit first and within those the user's own, such
+/// as their forks. None if there are none or the user wants to enter an
+/// address instead
+async fn pick_repo_from_cache(git_repo: &Repo) -> Result<Option<Coordinate>> {
+    let git_repo_path = git_repo.get_path()?;
+    let filter = nostr::Filter::default().kind(Kind::GitRepoAnnouncement);
+    let mut events = get_events_from_local_cache(git_repo_path, vec![filter.clone()])
+        .await
+        .unwrap_or_default();
+    events.extend(
+        get_event_from_global_cache(Some(git_repo_path), vec![filter])
+            .await
+            .unwrap_or_default(),
+    );
+    events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+
+    let root_commit = git_repo.get_root_commit().ok().map(|c| c.to_string());
+    let mut repos: Vec<(RepoRef, Timestamp)> = vec![];
+    for event in events {
+        if repos.iter().any(|(r, _)| {
+            r.trusted_maintainer.eq(&event.pubkey)
+                && event.tags.identifier() == Some(r.identifier.as_str())
+        }) {
+            continue;
+        }
+        let created_at = event.created_at;
+        if let Ok(repo_ref) = RepoRef::try_from((event, None)) {
+            repos.push((repo_ref, created_at));
+        }
+    }
+    if repos.is_empty() {
+        return Ok(None);
+    }
+    let user = get_likely_logged_in_user(git_repo_path)
+        .await
+        .ok()
+        .flatten();
+    let is_users = |r: &RepoRef| user.is_some_and(|user| user.eq(&r.trusted_maintainer));
+    // stable sort keeps most recently updated first
+    repos.sort_by_key(|(r, _)| (Some(&r.root_commit) != root_commit.as_ref(), !is_users(r)));
+
+    let mut choices = vec![];
+    for (repo_ref, created_at) in &repos {
+        choices.push(format!(
+            "{} ({}) by {}, updated {}{}",
+            repo_ref.name,
+            repo_ref.identifier,
+            if is_users(repo_ref) {
+                "you".to_string()
+            } else {
+                get_author_display_name(Some(git_repo_path), &repo_ref.trusted_maintainer).await
+            },
+            time_ago(*created_at),
+            if Some(&repo_ref.root_commit) == root_commit.as_ref() {
+                ", shares this repository's history"
+            } else {
+                ""
+            },
+        ));
+    }
+    choices.push("enter a nostr address instead".to_string());
+    let selected = Interactor::default().fuzzy_choice(
+        PromptChoiceParms::default()
+            .with_prompt("nostr repository")
+            .with_choices(choices),
+    )?;
+    Ok(repos
+        .get(selected)
+        .map(|(repo_ref, _)| repo_ref.coordinate_with_hint()))
+}
+
+fn time_ago(timestamp: Timestamp) -> String {
+    let seconds = Timestamp::now().as_u64().saturating_sub(timestamp.as_u64());
+    match seconds {
+        0..=3_599 => "just now".to_string(),
+        3_600..=86_399 => format!("{} hours ago", seconds / 3_600),
+        86_400..=2_591_999 => format!("{} days ago", seconds / 86_400),
+        _ => format!("{} months ago", seconds / 2_592_000),
+    }
+}
+
 async fn get_repo_coordinate_from_user_prompt(
     git_repo: &Repo,
     #[cfg(test)] client: &crate::client::MockConnect,
     #[cfg(not(test))] client: &Client,
 ) -> Result<Coordinate> {
-    // TODO: present list of events filter by root_commit
-    // TODO: fallback to search based on identifier
-    let dim = Style::new().color256(247);
-    println!(
-        "{}",
-        dim.apply_to(
-            "hint: https://gitworkshop.dev/repos lists repositories and their nostr address"
-        ),
-    );
     let git_repo_path = git_repo.get_path()?;
-    let coordinate = {
+    let coordinate = if let Some(coordinate) = pick_repo_from_cache(git_repo).await? {
+        coordinate
+    } else {
+        let dim = theme::dim();
+        println!(
+            "{}",
+            dim.apply_to(
+                "hint: https://gitworkshop.dev/repos lists repositories and their nostr address"
+            ),
+        );
         loop {
             let input = Interactor::default()
                 .input(PromptInputParms::default().with_prompt("nostr repository"))?;
@@ -535,6 +1020,17 @@ pub fn save_repo_config_to_yaml(
     .context("failed to write maintainers to maintainers.yaml file serde_yaml")
 }
 
+pub static DEFAULT_VIEWER_URL: &str = "https://gitworkshop.dev";
+
+/// web client to link to from git config `nostr.viewer-url`, falling back to
+/// `viewer-url` in ngit settings
+pub fn get_viewer_url(git_repo: &Option<&Repo>) -> String {
+    get_git_config_item(git_repo, "nostr.viewer-url")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| crate::settings::settings().viewer_url.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use test_utils::*;
@@ -548,10 +1044,21 @@ mod tests {
             description: "test description".to_string(),
             root_commit: "5e664e5a7845cd1373c79f580ca4fe29ab5b34d2".to_string(),
             git_server: vec!["https://localhost:1000".to_string()],
+            blossoms: vec![],
+            bundles: vec![],
             web: vec![
                 "https://exampleproject.xyz".to_string(),
                 "https://gitworkshop.dev/123".to_string(),
             ],
+            license: None,
+            language: None,
+            hashtags: vec![],
+            homepage: None,
+            contact: None,
+            previous_identifiers: vec![],
+            renamed_to: None,
+            upstream: None,
+            org: None,
             relays: vec![
                 RelayUrl::parse("ws://relay1.io").unwrap(),
                 RelayUrl::parse("ws://relay2.io").unwrap(),
@@ -695,6 +1202,303 @@ mod tests {
                 vec![TEST_KEY_1_KEYS.public_key(), TEST_KEY_2_KEYS.public_key()],
             )
         }
+
+        #[tokio::test]
+        async fn blossoms() {
+            let event = RepoRef {
+                blossoms: vec!["https://blossom.example.com".to_string()],
+                ..RepoRef::try_from((create().await, None)).unwrap()
+            }
+            .to_event(&TEST_KEY_1_SIGNER)
+            .await
+            .unwrap();
+            assert_eq!(
+                RepoRef::try_from((event, None)).unwrap().blossoms,
+                vec!["https://blossom.example.com"],
+            )
+        }
+
+        #[tokio::test]
+        async fn bundles() {
+            let event = RepoRef {
+                bundles: vec!["https://cdn.example.com/repo.bundle".to_string()],
+                ..RepoRef::try_from((create().await, None)).unwrap()
+            }
+            .to_event(&TEST_KEY_1_SIGNER)
+            .await
+            .unwrap();
+            assert_eq!(
+                RepoRef::try_from((event, None)).unwrap().bundles,
+                vec!["https://cdn.example.com/repo.bundle"],
+            )
+        }
+
+        #[tokio::test]
+        async fn extra_metadata() {
+            let event = RepoRef {
+                license: Some("MIT".to_string()),
+                language: Some("rust".to_string()),
+                hashtags: vec!["nostr".to_string(), "git".to_string()],
+                homepage: Some("https://example.com".to_string()),
+                contact: Some("dev@example.com".to_string()),
+                ..RepoRef::try_from((create().await, None)).unwrap()
+            }
+            .to_event(&TEST_KEY_1_SIGNER)
+            .await
+            .unwrap();
+            let repo_ref = RepoRef::try_from((event, None)).unwrap();
+            assert_eq!(repo_ref.license, Some("MIT".to_string()));
+            assert_eq!(repo_ref.language, Some("rust".to_string()));
+            assert_eq!(repo_ref.hashtags, vec!["nostr", "git"]);
+            assert_eq!(repo_ref.homepage, Some("https://example.com".to_string()));
+            assert_eq!(repo_ref.contact, Some("dev@example.com".to_string()));
+            assert_eq!(
+                repo_ref.metadata_summary(),
+                Some(
+                    "MIT · rust · #nostr #git · https://example.com · contact: dev@example.com"
+                        .to_string()
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn no_metadata_summary_when_no_extra_metadata() {
+            assert!(
+                RepoRef::try_from((create().await, None))
+                    .unwrap()
+                    .metadata_summary()
+                    .is_none()
+            )
+        }
+
+        #[tokio::test]
+        async fn blossoms_empty_when_no_tag() {
+            assert!(
+                RepoRef::try_from((create().await, None))
+                    .unwrap()
+                    .blossoms
+                    .is_empty()
+            )
+        }
+    }
+
+    mod rename {
+        use super::*;
+
+        #[tokio::test]
+        async fn previous_identifiers_and_renamed_to_round_trip() -> Result<()> {
+            let repo_ref = RepoRef::try_from((create().await, None))?;
+            let renamed = RepoRef {
+                identifier: "new-name".to_string(),
+                previous_identifiers: vec![repo_ref.identifier.clone()],
+                ..repo_ref.clone()
+            };
+            let parsed = RepoRef::try_from((renamed.to_event(&TEST_KEY_1_SIGNER).await?, None))?;
+            assert_eq!(
+                parsed.previous_identifiers,
+                vec![repo_ref.identifier.clone()]
+            );
+            assert_eq!(parsed.renamed_to, None);
+
+            let tombstone = RepoRef::try_from((
+                repo_ref
+                    .to_rename_tombstone("new-name")
+                    .to_event(&TEST_KEY_1_SIGNER)
+                    .await?,
+                None,
+            ))?;
+            assert_eq!(tombstone.identifier, repo_ref.identifier);
+            assert_eq!(tombstone.renamed_to, Some("new-name".to_string()));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn coordinates_include_previous_identifiers() -> Result<()> {
+            let repo_ref = RepoRef {
+                identifier: "new-name".to_string(),
+                previous_identifiers: vec!["old-name".to_string()],
+                ..RepoRef::try_from((create().await, None))?
+            };
+            let coordinates = repo_ref.coordinates_including_previous_identifiers();
+            assert_eq!(coordinates.len(), repo_ref.coordinates().len() * 2);
+            assert!(coordinates.contains(&Coordinate {
+                kind: Kind::GitRepoAnnouncement,
+                public_key: TEST_KEY_1_KEYS.public_key(),
+                identifier: "old-name".to_string(),
+                relays: vec![],
+            }));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn org_round_trips() -> Result<()> {
+        let org = Coordinate {
+            kind: Kind::FollowSet,
+            public_key: TEST_KEY_2_KEYS.public_key(),
+            identifier: "example-org".to_string(),
+            relays: vec![],
+        };
+        let event = RepoRef {
+            org: Some(org.clone()),
+            ..RepoRef::try_from((create().await, None))?
+        }
+        .to_event(&TEST_KEY_1_SIGNER)
+        .await?;
+        assert_eq!(RepoRef::try_from((event, None))?.org, Some(org));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upstream_round_trips() -> Result<()> {
+        let upstream = Coordinate {
+            kind: Kind::GitRepoAnnouncement,
+            public_key: TEST_KEY_2_KEYS.public_key(),
+            identifier: "upstream-repo".to_string(),
+            relays: vec![],
+        };
+        let event = RepoRef {
+            upstream: Some(upstream.clone()),
+            ..RepoRef::try_from((create().await, None))?
+        }
+        .to_event(&TEST_KEY_1_SIGNER)
+        .await?;
+        assert_eq!(RepoRef::try_from((event, None))?.upstream, Some(upstream));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn links_use_viewer_url_and_first_relay() -> Result<()> {
+        let repo_ref = RepoRef::try_from((create().await, None))?;
+        let (web, uri) = repo_ref.links("https://viewer.example/")?;
+        let naddr = repo_ref.coordinate_with_hint().to_bech32()?;
+        assert_eq!(web, format!("https://viewer.example/repo/{naddr}"));
+        assert_eq!(uri, format!("nostr:{naddr}"));
+
+        let event_id = EventId::all_zeros();
+        let nevent = Nip19Event::new(event_id, vec!["ws://relay1.io"]).to_bech32()?;
+        let (web, njump, uri) =
+            repo_ref.event_links("https://viewer.example", &event_id, Kind::GitIssue)?;
+        assert_eq!(
+            web,
+            format!("https://viewer.example/repo/{naddr}/issue/{nevent}")
+        );
+        assert_eq!(njump, format!("https://njump.me/{nevent}"));
+        assert_eq!(uri, format!("nostr:{nevent}"));
+        Ok(())
+    }
+
+    mod get_announcement_differences {
+        use super::*;
+
+        #[tokio::test]
+        async fn lists_fields_that_differ_between_maintainers() -> Result<()> {
+            let repo_ref = RepoRef::try_from((create().await, None))?;
+            let other = RepoRef {
+                git_server: vec!["https://other.example.com".to_string()],
+                ..repo_ref.clone()
+            }
+            .to_event(&(Arc::new(TEST_KEY_2_KEYS.clone()) as Arc<dyn NostrSigner>))
+            .await?;
+            let mut events = repo_ref.events.clone();
+            assert!(
+                get_announcement_differences(&RepoRef {
+                    events: events.clone(),
+                    ..repo_ref.clone()
+                })
+                .is_empty()
+            );
+            events.insert(
+                Coordinate {
+                    kind: other.kind,
+                    identifier: repo_ref.identifier.clone(),
+                    public_key: other.pubkey,
+                    relays: vec![],
+                },
+                other,
+            );
+            let differences = get_announcement_differences(&RepoRef {
+                events,
+                ..repo_ref.clone()
+            });
+            assert_eq!(differences.len(), 1);
+            assert_eq!(differences[0].0, "git servers");
+            assert!(differences[0].1.contains(&(
+                TEST_KEY_2_KEYS.public_key(),
+                "https://other.example.com".to_string()
+            )));
+            Ok(())
+        }
+    }
+
+    mod get_lineage_warnings {
+        use test_utils::git::GitTestRepo;
+
+        use super::*;
+
+        #[tokio::test]
+        async fn warns_when_earliest_unique_commit_missing() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let mut repo_ref = RepoRef::try_from((create().await, None))?;
+            assert_eq!(get_lineage_warnings(&git_repo, &repo_ref, &[]).len(), 1);
+
+            repo_ref.root_commit = git_repo.get_root_commit()?.to_string();
+            assert!(get_lineage_warnings(&git_repo, &repo_ref, &[]).is_empty());
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn warns_when_proposal_root_commit_missing() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let repo_ref = RepoRef {
+                root_commit: git_repo.get_root_commit()?.to_string(),
+                ..RepoRef::try_from((create().await, None))?
+            };
+            let proposal = |root_commit: String| {
+                nostr::EventBuilder::new(Kind::GitPatch, "")
+                    .tags([Tag::from_standardized(TagStandard::Reference(root_commit))])
+                    .sign_with_keys(&TEST_KEY_1_KEYS)
+            };
+            let same_root = proposal(repo_ref.root_commit.clone())?;
+            assert!(get_lineage_warnings(&git_repo, &repo_ref, &[same_root]).is_empty());
+
+            let other_root = proposal("5e664e5a7845cd1373c79f580ca4fe29ab5b34d2".to_string())?;
+            assert_eq!(
+                get_lineage_warnings(&git_repo, &repo_ref, &[other_root]).len(),
+                1
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn matches_earliest_unique_commit_in_any_r_tag() -> Result<()> {
+            let test_repo = GitTestRepo::default();
+            let tip = test_repo.populate()?;
+            let git_repo = Repo::from_path(&test_repo.dir)?;
+            let repo_ref = RepoRef {
+                root_commit: git_repo.get_root_commit()?.to_string(),
+                ..RepoRef::try_from((create().await, None))?
+            };
+            let proposal = |commits: [String; 2]| {
+                nostr::EventBuilder::new(Kind::GitPatch, "")
+                    .tags(commits.map(|c| Tag::from_standardized(TagStandard::Reference(c))))
+                    .sign_with_keys(&TEST_KEY_1_KEYS)
+            };
+            let root_second = proposal([tip.to_string(), repo_ref.root_commit.clone()])?;
+            assert!(get_proposal_lineage_warning(&git_repo, &repo_ref, &root_second).is_none());
+
+            // the patch commit exists locally but the root doesn't match
+            let other_root = proposal([
+                "5e664e5a7845cd1373c79f580ca4fe29ab5b34d2".to_string(),
+                tip.to_string(),
+            ])?;
+            assert!(get_proposal_lineage_warning(&git_repo, &repo_ref, &other_root).is_some());
+            Ok(())
+        }
     }
 
     mod to_event {
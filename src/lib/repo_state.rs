@@ -3,18 +3,29 @@ use std::collections::HashMap;
 use anyhow::{Context, Result};
 use git2::Oid;
 
+use crate::git_events::without_future_dated;
+
 pub struct RepoState {
     pub identifier: String,
     pub state: HashMap<String, String>,
+    /// sha256 hashes of packfiles hosted on blossom servers, oldest first
+    pub packs: Vec<String>,
     pub event: nostr::Event,
 }
 
 impl RepoState {
-    pub fn try_from(mut state_events: Vec<nostr::Event>) -> Result<Self> {
+    pub fn try_from(state_events: Vec<nostr::Event>) -> Result<Self> {
+        let mut state_events = without_future_dated(state_events);
         state_events.sort_by_key(|e| e.created_at);
         let event = state_events.first().context("no state events")?;
         let mut state = HashMap::new();
+        let mut packs = vec![];
         for tag in event.tags.iter() {
+            if let [t, hash] = tag.as_slice() {
+                if t == "pack" && hash.len() == 64 {
+                    packs.push(hash.to_owned());
+                }
+            }
             if let Some(name) = tag.as_slice().first() {
                 if ["refs/heads/", "refs/tags", "HEAD"]
                     .iter()
@@ -35,6 +46,7 @@ impl RepoState {
                 .context("existing event must have an identifier")?
                 .to_string(),
             state,
+            packs,
             event: event.clone(),
         })
     }
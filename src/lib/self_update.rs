@@ -0,0 +1,285 @@
+//! ngit releases published on nostr as NIP-94 file metadata events (kind
+//! 1063) by a maintainer of ngit's own repository, each referencing the
+//! repository with an `a` tag and describing one binary:
+//!
+//! ```text
+//! ["version", "1.7.0"], ["arch", "x86_64-linux"], ["bin", "ngit"],
+//! ["url", "https://..."], ["x", "<sha256>"]
+//! ```
+
+use std::{fs::create_dir_all, path::PathBuf};
+
+use anyhow::{Context, Result, bail};
+use nostr::{Event, Filter, Kind, PublicKey, SingleLetterTag, Timestamp, nips::nip01::Coordinate};
+use nostr_sdk::Alphabet;
+
+#[cfg(not(test))]
+use crate::client::Client;
+#[cfg(test)]
+use crate::client::MockConnect;
+use crate::{
+    blossom::sha256_hex,
+    client::Connect,
+    download::{download_resumable, partial_download_dir},
+    get_dirs,
+    git_events::tag_value,
+    repo_ref::RepoRef,
+    settings::settings,
+};
+
+/// the maintainer of ngit's repository announcement
+pub static NGIT_MAINTAINER: &str =
+    "npub15qydau2hjma6ngxkl2cyar74wzyjshvl65za5k5rl69264ar2exs5cyejr";
+pub static NGIT_IDENTIFIER: &str = "ngit";
+
+/// releases are looked for at most this often during fetches
+static UPDATE_CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    /// `<arch>-<os>`, eg. `x86_64-linux` or `aarch64-macos`
+    pub arch: String,
+    /// `ngit` or `git-remote-nostr`
+    pub bin: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl Release {
+    fn from_event(event: &Event) -> Option<Self> {
+        Some(Self {
+            version: tag_value(event, "version")
+                .ok()?
+                .trim_start_matches('v')
+                .to_string(),
+            arch: tag_value(event, "arch").ok()?,
+            bin: tag_value(event, "bin").unwrap_or_else(|_| "ngit".to_string()),
+            url: tag_value(event, "url").ok()?,
+            sha256: tag_value(event, "x").ok()?.to_lowercase(),
+        })
+    }
+}
+
+/// the platform release binaries are built for, in the form of the `arch` tag
+pub fn current_arch() -> String {
+    format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+/// `major.minor.patch` as numbers, ignoring pre-release suffixes
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+/// whether `candidate` is a later version than `current`
+pub fn is_newer_version(current: &str, candidate: &str) -> bool {
+    version_key(candidate) > version_key(current)
+}
+
+pub fn ngit_coordinate() -> Result<Coordinate> {
+    Ok(Coordinate {
+        kind: Kind::GitRepoAnnouncement,
+        public_key: PublicKey::parse(NGIT_MAINTAINER)?,
+        identifier: NGIT_IDENTIFIER.to_string(),
+        relays: vec![],
+    })
+}
+
+/// releases from `events` published by a maintainer listed in the latest of
+/// the ngit announcements among them
+fn trusted_releases(events: &[Event], coordinate: &Coordinate) -> Vec<Release> {
+    let maintainers: Vec<PublicKey> = events
+        .iter()
+        .filter(|e| {
+            e.kind == Kind::GitRepoAnnouncement
+                && e.pubkey == coordinate.public_key
+                && e.tags.identifier() == Some(coordinate.identifier.as_str())
+        })
+        .max_by_key(|e| e.created_at)
+        .and_then(|e| RepoRef::try_from((e.clone(), Some(coordinate.public_key))).ok())
+        .map_or_else(
+            || vec![coordinate.public_key],
+            |repo_ref| repo_ref.maintainers,
+        );
+    events
+        .iter()
+        .filter(|e| e.kind == Kind::FileMetadata && maintainers.contains(&e.pubkey))
+        .filter_map(Release::from_event)
+        .collect()
+}
+
+/// the latest release of each binary for this platform, newest first
+pub async fn fetch_latest_releases(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+) -> Result<Vec<Release>> {
+    let coordinate = ngit_coordinate()?;
+    let events = client
+        .get_events(
+            client.get_fallback_relays().clone(),
+            vec![
+                Filter::default()
+                    .kind(Kind::GitRepoAnnouncement)
+                    .author(coordinate.public_key)
+                    .identifier(coordinate.identifier.clone()),
+                Filter::default().kind(Kind::FileMetadata).custom_tag(
+                    SingleLetterTag::lowercase(Alphabet::A),
+                    [coordinate.to_string()],
+                ),
+            ],
+        )
+        .await?;
+    let arch = current_arch();
+    let mut releases: Vec<Release> = trusted_releases(&events, &coordinate)
+        .into_iter()
+        .filter(|release| release.arch == arch)
+        .collect();
+    releases.sort_by_key(|release| std::cmp::Reverse(version_key(&release.version)));
+    let mut latest: Vec<Release> = vec![];
+    for release in releases {
+        if !latest.iter().any(|r| r.bin == release.bin) {
+            latest.push(release);
+        }
+    }
+    Ok(latest)
+}
+
+/// download `release` and check it matches the sha256 in its event
+pub async fn download_release(release: &Release) -> Result<Vec<u8>> {
+    let binary = download_resumable(&release.url, &partial_download_dir(get_dirs()?.cache_dir()))
+        .await
+        .context(format!("failed to download {}", release.url))?;
+    let sha256 = sha256_hex(&binary);
+    if sha256 != release.sha256 {
+        bail!(
+            "{} has sha256 {sha256} but the release event lists {}. not installing it",
+            release.url,
+            release.sha256
+        );
+    }
+    Ok(binary)
+}
+
+fn update_check_path() -> Result<PathBuf> {
+    let cache_dir = get_dirs()?.cache_dir().to_path_buf();
+    create_dir_all(&cache_dir).context("failed to create cache directory")?;
+    Ok(cache_dir.join("update-check"))
+}
+
+/// `<checked at> <latest version>`
+fn read_update_check() -> Option<(u64, String)> {
+    let contents = std::fs::read_to_string(update_check_path().ok()?).ok()?;
+    let (checked_at, version) = contents.trim().split_once(' ')?;
+    Some((checked_at.parse().ok()?, version.to_string()))
+}
+
+/// with `update-check` enabled, a hint to run `ngit self-update` when a newer
+/// ngit has been released. relays are asked at most once a day
+pub async fn update_hint(
+    #[cfg(test)] client: &MockConnect,
+    #[cfg(not(test))] client: &Client,
+) -> Option<String> {
+    if !settings().update_check || settings().test_mode {
+        return None;
+    }
+    let now = Timestamp::now().as_u64();
+    let latest = match read_update_check() {
+        Some((checked_at, version))
+            if now.saturating_sub(checked_at) < UPDATE_CHECK_INTERVAL_SECS =>
+        {
+            version
+        }
+        _ => {
+            let version = fetch_latest_releases(client)
+                .await
+                .ok()?
+                .into_iter()
+                .find(|release| release.bin == "ngit")
+                .map_or_else(
+                    || env!("CARGO_PKG_VERSION").to_string(),
+                    |release| release.version,
+                );
+            let _ = std::fs::write(update_check_path().ok()?, format!("{now} {version}"));
+            version
+        }
+    };
+    is_newer_version(env!("CARGO_PKG_VERSION"), &latest).then(|| {
+        format!(
+            "ngit v{latest} is available (you have v{}). run `ngit self-update` to upgrade",
+            env!("CARGO_PKG_VERSION")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(is_newer_version("1.9.0", "1.10.0"));
+        assert!(is_newer_version("v1.2.3", "1.2.4-rc1"));
+        assert!(!is_newer_version("1.2.3", "1.2.3"));
+        assert!(!is_newer_version("2.0.0", "1.99.99"));
+    }
+
+    #[test]
+    fn only_releases_from_maintainers_are_trusted() -> Result<()> {
+        let maintainer = Keys::generate();
+        let impostor = Keys::generate();
+        let coordinate = Coordinate {
+            kind: Kind::GitRepoAnnouncement,
+            public_key: maintainer.public_key(),
+            identifier: NGIT_IDENTIFIER.to_string(),
+            relays: vec![],
+        };
+        let release = |keys: &Keys, url: &str| {
+            EventBuilder::new(Kind::FileMetadata, "")
+                .tags([
+                    Tag::coordinate(coordinate.clone()),
+                    Tag::custom(nostr::TagKind::Custom("version".into()), ["1.2.3"]),
+                    Tag::custom(nostr::TagKind::Custom("arch".into()), [current_arch()]),
+                    Tag::custom(nostr::TagKind::Custom("url".into()), [url]),
+                    Tag::custom(nostr::TagKind::Custom("x".into()), ["ABC123"]),
+                ])
+                .sign_with_keys(keys)
+        };
+        let events = vec![
+            release(&maintainer, "https://example.com/ngit")?,
+            release(&impostor, "https://evil.example/ngit")?,
+        ];
+        let trusted = vec![Release {
+            version: "1.2.3".to_string(),
+            arch: current_arch(),
+            bin: "ngit".to_string(),
+            url: "https://example.com/ngit".to_string(),
+            sha256: "abc123".to_string(),
+        }];
+        assert_eq!(trusted_releases(&events, &coordinate), trusted);
+
+        // the maintainer's announcements of other repositories don't count
+        let other_repo = EventBuilder::new(Kind::GitRepoAnnouncement, "")
+            .tags([
+                Tag::identifier("other-repo"),
+                Tag::custom(
+                    nostr::TagKind::Custom("maintainers".into()),
+                    [impostor.public_key().to_hex()],
+                ),
+            ])
+            .sign_with_keys(&maintainer)?;
+        assert_eq!(
+            trusted_releases(&[events, vec![other_repo]].concat(), &coordinate),
+            trusted
+        );
+        Ok(())
+    }
+}
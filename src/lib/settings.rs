@@ -0,0 +1,442 @@
+use std::{path::Path, sync::OnceLock};
+
+use anyhow::{Context, Result};
+use toml_edit::{Document, Item};
+
+use crate::{
+    get_dirs,
+    git::{Repo, RepoActions, get_git_config_item},
+    git_events::DEFAULT_FUTURE_TOLERANCE_SECS,
+    repo_ref::DEFAULT_VIEWER_URL,
+    theme::{DEFAULT_DIM_STYLE, DEFAULT_ERROR_STYLE, DEFAULT_SUCCESS_STYLE},
+    trailers::DEFAULT_TRAILER_TEMPLATE,
+};
+
+/// config file under the repository root, shared by contributors
+pub static REPO_CONFIG_PATH: &str = ".ngit/config.toml";
+
+/// keys [`REPO_CONFIG_PATH`] can set. it is committed by whoever can push, so
+/// relays, update checks, signing and maintainer trust can only be set in
+/// `~/.config/ngit/config.toml` or git config
+pub static REPO_CONFIG_KEYS: [&str; 6] = [
+    "viewer-url",
+    "notifications",
+    "log-level",
+    "color",
+    "styles",
+    "trailers",
+];
+
+/// settings layered from built-in defaults, `~/.config/ngit/config.toml`,
+/// `.ngit/config.toml`, git config and the environment. `--log-level` is
+/// applied on top by the binaries
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// set by `NGITTEST` so integration tests use local relays and caches and
+    /// skip prompts, spinners and the os keyring
+    pub test_mode: bool,
+    pub fallback_relays: Vec<String>,
+    pub more_fallback_relays: Vec<String>,
+    pub blaster_relays: Vec<String>,
+    pub fallback_signer_relays: Vec<String>,
+    pub viewer_url: String,
+    pub notifications: bool,
+    pub log_level: Option<String>,
+    /// how far into the future an event's `created_at` can be before it is
+    /// treated as clock-skewed
+    pub future_tolerance_secs: u64,
+    /// auto, always or never. `--color` and `NO_COLOR` take precedence
+    pub color: Option<String>,
+    pub styles: Styles,
+    /// look for newer ngit releases on nostr during fetches. off by default
+    pub update_check: bool,
+    /// template for trailers added to commits applied with `git am` from
+    /// `ngit list`. none by default
+    pub trailers: Option<String>,
+    /// minutes a decrypted ncryptsec stays unlocked after the password is
+    /// entered. 0, the default, doesn't start the unlock agent
+    pub unlock_timeout_mins: u64,
+    /// show each event and ask before signing it
+    pub confirm_signing: bool,
+    /// NIP-26 delegation tag from `ngit account delegate`, set on bot / CI
+    /// machines
+    pub delegation: Option<String>,
+    /// device used when global git config has no signer
+    pub hardware_signer: Option<String>,
+    /// trust maintainers only listed by co-maintainers without asking
+    pub open_maintainer_trust: bool,
+}
+
+/// styles in console's dotted syntax, eg. `247` or `blue.bold`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Styles {
+    pub dim: String,
+    pub success: String,
+    pub error: String,
+}
+
+impl Default for Styles {
+    fn default() -> Self {
+        Self {
+            dim: DEFAULT_DIM_STYLE.to_string(),
+            success: DEFAULT_SUCCESS_STYLE.to_string(),
+            error: DEFAULT_ERROR_STYLE.to_string(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn defaults(test_mode: bool) -> Self {
+        let strings = |relays: &[&str]| relays.iter().map(ToString::to_string).collect();
+        if test_mode {
+            Self {
+                test_mode,
+                fallback_relays: strings(&["ws://localhost:8051", "ws://localhost:8052"]),
+                more_fallback_relays: strings(&["ws://localhost:8055", "ws://localhost:8056"]),
+                blaster_relays: strings(&["ws://localhost:8057"]),
+                fallback_signer_relays: strings(&["ws://localhost:8051"]),
+                viewer_url: DEFAULT_VIEWER_URL.to_string(),
+                notifications: false,
+                log_level: None,
+                future_tolerance_secs: DEFAULT_FUTURE_TOLERANCE_SECS,
+                color: None,
+                styles: Styles::default(),
+                update_check: false,
+                trailers: None,
+                unlock_timeout_mins: 0,
+                confirm_signing: false,
+                delegation: None,
+                hardware_signer: None,
+                open_maintainer_trust: false,
+            }
+        } else {
+            Self {
+                test_mode,
+                fallback_relays: strings(&[
+                    "wss://relay.damus.io", // free, good reliability, known to delete messages
+                    "wss://nos.lol",
+                    "wss://relay.nostr.band",
+                ]),
+                more_fallback_relays: strings(&[
+                    "wss://purplerelay.com", // free but reliability not tested
+                    "wss://purplepages.es",  // for profile events but unreliable
+                    "wss://relayable.org",   // free but not always reliable
+                ]),
+                blaster_relays: vec![],
+                fallback_signer_relays: strings(&["wss://relay.nsec.app"]),
+                viewer_url: DEFAULT_VIEWER_URL.to_string(),
+                notifications: false,
+                log_level: None,
+                future_tolerance_secs: DEFAULT_FUTURE_TOLERANCE_SECS,
+                color: None,
+                styles: Styles::default(),
+                update_check: false,
+                trailers: None,
+                unlock_timeout_mins: 0,
+                confirm_signing: false,
+                delegation: None,
+                hardware_signer: None,
+                open_maintainer_trust: false,
+            }
+        }
+    }
+
+    /// every layer for `git_repo`, or the current directory's repository
+    pub fn load(git_repo: &Option<&Repo>) -> Result<Self> {
+        let test_mode = std::env::var("NGITTEST").is_ok();
+        let mut settings = Self::defaults(test_mode);
+        if !test_mode {
+            settings.apply_file(&get_dirs()?.config_dir().join("config.toml"))?;
+        }
+        if let Some(git_repo) = git_repo {
+            let path = git_repo.get_path()?.join(REPO_CONFIG_PATH);
+            if let Ok(toml) = std::fs::read_to_string(&path) {
+                settings
+                    .apply_repo_toml(&toml)
+                    .context(format!("failed to read settings in {}", path.display()))?;
+            }
+        }
+        settings.apply_git_config(git_repo);
+        settings.apply_env();
+        Ok(settings)
+    }
+
+    fn apply_file(&mut self, path: &Path) -> Result<()> {
+        if let Ok(toml) = std::fs::read_to_string(path) {
+            self.apply_toml(&toml)
+                .context(format!("failed to read settings in {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// ```toml
+    /// viewer-url = "https://gitworkshop.dev"
+    /// notifications = true
+    /// log-level = "debug"
+    /// future-tolerance-secs = 900
+    /// color = "auto"
+    /// update-check = true
+    /// trailers = "Proposal-Id: {proposal}\nReviewed-by: {reviewer}"
+    /// unlock-timeout = 15
+    /// confirm-signing = true
+    /// delegation = '["delegation", ...]'
+    /// hardware-signer = "/dev/ttyACM0"
+    /// maintainer-trust = "open"
+    /// [styles]
+    /// dim = "247"
+    /// success = "green"
+    /// error = "red"
+    /// [relays]
+    /// fallback = ["wss://relay.damus.io"]
+    /// more-fallback = []
+    /// blaster = []
+    /// signer = ["wss://relay.nsec.app"]
+    /// ```
+    pub fn apply_toml(&mut self, toml: &str) -> Result<()> {
+        self.apply_document(&toml.parse::<Document>()?);
+        Ok(())
+    }
+
+    /// only [`REPO_CONFIG_KEYS`], warning about any others
+    pub fn apply_repo_toml(&mut self, toml: &str) -> Result<()> {
+        let mut document = toml.parse::<Document>()?;
+        let ignored: Vec<String> = document
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .filter(|key| !REPO_CONFIG_KEYS.contains(&key.as_str()))
+            .collect();
+        for key in ignored {
+            document.remove(&key);
+            eprintln!(
+                "warning: ignoring {key} in {REPO_CONFIG_PATH}. set it in ~/.config/ngit/config.toml or git config instead"
+            );
+        }
+        self.apply_document(&document);
+        Ok(())
+    }
+
+    fn apply_document(&mut self, document: &Document) {
+        let root = document.as_item();
+        if let Some(viewer_url) = root.get("viewer-url").and_then(Item::as_str) {
+            self.viewer_url = viewer_url.to_string();
+        }
+        if let Some(notifications) = root.get("notifications").and_then(Item::as_bool) {
+            self.notifications = notifications;
+        }
+        if let Some(log_level) = root.get("log-level").and_then(Item::as_str) {
+            self.log_level = Some(log_level.to_string());
+        }
+        if let Some(secs) = root.get("future-tolerance-secs").and_then(Item::as_integer) {
+            self.future_tolerance_secs = u64::try_from(secs).unwrap_or_default();
+        }
+        if let Some(color) = root.get("color").and_then(Item::as_str) {
+            self.color = Some(color.to_string());
+        }
+        if let Some(update_check) = root.get("update-check").and_then(Item::as_bool) {
+            self.update_check = update_check;
+        }
+        if let Some(trailers) = root.get("trailers") {
+            if let Some(enabled) = trailers.as_bool() {
+                self.trailers = enabled.then(|| DEFAULT_TRAILER_TEMPLATE.to_string());
+            } else if let Some(template) = trailers.as_str() {
+                self.trailers = Some(template.to_string());
+            }
+        }
+        if let Some(minutes) = root.get("unlock-timeout").and_then(Item::as_integer) {
+            self.unlock_timeout_mins = u64::try_from(minutes).unwrap_or_default();
+        }
+        if let Some(confirm_signing) = root.get("confirm-signing").and_then(Item::as_bool) {
+            self.confirm_signing = confirm_signing;
+        }
+        if let Some(delegation) = root.get("delegation").and_then(Item::as_str) {
+            self.delegation = Some(delegation.to_string());
+        }
+        if let Some(device) = root.get("hardware-signer").and_then(Item::as_str) {
+            self.hardware_signer = Some(device.to_string());
+        }
+        if let Some(trust) = root.get("maintainer-trust").and_then(Item::as_str) {
+            self.open_maintainer_trust = trust.eq("open");
+        }
+        let style = |name: &str| root.get("styles")?.get(name)?.as_str().map(str::to_string);
+        if let Some(style) = style("dim") {
+            self.styles.dim = style;
+        }
+        if let Some(style) = style("success") {
+            self.styles.success = style;
+        }
+        if let Some(style) = style("error") {
+            self.styles.error = style;
+        }
+        let relays = |name: &str| -> Option<Vec<String>> {
+            Some(
+                root.get("relays")?
+                    .get(name)?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|relay| relay.as_str().map(str::to_string))
+                    .collect(),
+            )
+        };
+        if let Some(relays) = relays("fallback") {
+            self.fallback_relays = relays;
+        }
+        if let Some(relays) = relays("more-fallback") {
+            self.more_fallback_relays = relays;
+        }
+        if let Some(relays) = relays("blaster") {
+            self.blaster_relays = relays;
+        }
+        if let Some(relays) = relays("signer") {
+            self.fallback_signer_relays = relays;
+        }
+    }
+
+    /// `nostr.viewer-url`, `nostr.notifications`, `nostr.log-level`,
+    /// `nostr.future-tolerance-secs`, `nostr.color`, `nostr.update-check`,
+    /// `nostr.trailers`, `nostr.unlock-timeout`, `nostr.confirm-signing`,
+    /// `nostr.delegation`, `nostr.maintainer-trust` and comma separated
+    /// `nostr.fallback-relays`. `nostr.hardware-signer` is read with the rest
+    /// of the login
+    fn apply_git_config(&mut self, git_repo: &Option<&Repo>) {
+        let item = |key: &str| get_git_config_item(git_repo, key).ok().flatten();
+        if let Some(viewer_url) = item("nostr.viewer-url") {
+            self.viewer_url = viewer_url;
+        }
+        if let Some(notifications) = item("nostr.notifications") {
+            self.notifications = notifications.eq("true");
+        }
+        if let Some(log_level) = item("nostr.log-level") {
+            self.log_level = Some(log_level);
+        }
+        if let Some(secs) = item("nostr.future-tolerance-secs").and_then(|s| s.parse().ok()) {
+            self.future_tolerance_secs = secs;
+        }
+        if let Some(color) = item("nostr.color") {
+            self.color = Some(color);
+        }
+        if let Some(update_check) = item("nostr.update-check") {
+            self.update_check = update_check.eq("true");
+        }
+        if let Some(trailers) = item("nostr.trailers") {
+            self.trailers = match trailers.as_str() {
+                "true" => Some(DEFAULT_TRAILER_TEMPLATE.to_string()),
+                "false" | "" => None,
+                _ => Some(trailers),
+            };
+        }
+        if let Some(minutes) = item("nostr.unlock-timeout").and_then(|m| m.trim().parse().ok()) {
+            self.unlock_timeout_mins = minutes;
+        }
+        if let Some(confirm_signing) = item("nostr.confirm-signing") {
+            self.confirm_signing = confirm_signing.eq("true");
+        }
+        if let Some(delegation) = item("nostr.delegation") {
+            self.delegation = Some(delegation);
+        }
+        if let Some(trust) = item("nostr.maintainer-trust") {
+            self.open_maintainer_trust = trust.eq("open");
+        }
+        if let Some(relays) = item("nostr.fallback-relays") {
+            self.fallback_relays = split_relays(&relays);
+        }
+    }
+
+    /// `NGIT_LOG` and comma separated `NGIT_FALLBACK_RELAYS`
+    fn apply_env(&mut self) {
+        if let Ok(log_level) = std::env::var("NGIT_LOG") {
+            self.log_level = Some(log_level);
+        }
+        if let Ok(relays) = std::env::var("NGIT_FALLBACK_RELAYS") {
+            self.fallback_relays = split_relays(&relays);
+        }
+    }
+}
+
+fn split_relays(relays: &str) -> Vec<String> {
+    relays
+        .split(',')
+        .map(str::trim)
+        .filter(|relay| !relay.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// settings for the repository in the current directory, loaded once
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| {
+        let git_repo = Repo::discover().ok();
+        Settings::load(&git_repo.as_ref()).unwrap_or_else(|error| {
+            eprintln!("warning: {error:#}");
+            let mut settings = Settings::defaults(std::env::var("NGITTEST").is_ok());
+            settings.apply_env();
+            settings
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_overrides_only_keys_it_sets() -> Result<()> {
+        let mut settings = Settings::defaults(false);
+        settings
+            .apply_toml("notifications = true\n[relays]\nfallback = [\"wss://relay.example\"]\n")?;
+        assert!(settings.notifications);
+        assert_eq!(settings.fallback_relays, vec!["wss://relay.example"]);
+        assert_eq!(
+            settings.more_fallback_relays,
+            Settings::defaults(false).more_fallback_relays
+        );
+        assert_eq!(settings.viewer_url, DEFAULT_VIEWER_URL);
+        assert_eq!(
+            settings.future_tolerance_secs,
+            DEFAULT_FUTURE_TOLERANCE_SECS
+        );
+        settings.apply_toml("[styles]\ndim = \"bright.black\"\n")?;
+        assert_eq!(settings.styles.dim, "bright.black");
+        assert_eq!(settings.styles.error, DEFAULT_ERROR_STYLE);
+        settings.apply_toml("trailers = true\n")?;
+        assert_eq!(settings.trailers.as_deref(), Some(DEFAULT_TRAILER_TEMPLATE));
+        settings.apply_toml("trailers = \"Proposal-Id: {proposal}\"\n")?;
+        assert_eq!(
+            settings.trailers.as_deref(),
+            Some("Proposal-Id: {proposal}")
+        );
+        assert!(settings.apply_toml("relays = [").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn repo_toml_only_sets_repo_keys() -> Result<()> {
+        let mut settings = Settings::defaults(false);
+        settings.apply_repo_toml(
+            "viewer-url = \"https://viewer.example\"\nupdate-check = true\nconfirm-signing = false\nmaintainer-trust = \"open\"\n[relays]\nsigner = [\"wss://relay.example\"]\n[styles]\nerror = \"blue\"\n",
+        )?;
+        assert_eq!(settings.viewer_url, "https://viewer.example");
+        assert_eq!(settings.styles.error, "blue");
+        let mut expected = Settings::defaults(false);
+        expected.viewer_url = settings.viewer_url.clone();
+        expected.styles.error = settings.styles.error.clone();
+        assert_eq!(settings, expected);
+
+        settings.apply_toml("confirm-signing = true\nunlock-timeout = 15\n")?;
+        assert!(settings.confirm_signing);
+        assert_eq!(settings.unlock_timeout_mins, 15);
+        settings.apply_repo_toml("confirm-signing = false\nunlock-timeout = 0\n")?;
+        assert!(settings.confirm_signing);
+        assert_eq!(settings.unlock_timeout_mins, 15);
+        Ok(())
+    }
+
+    #[test]
+    fn comma_separated_relays() {
+        assert_eq!(
+            split_relays("wss://a.example, wss://b.example,"),
+            vec!["wss://a.example", "wss://b.example"]
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! short numbers (#1, #2, ...) for a repository's proposals and issues, so
+//! people can refer to "proposal 42" instead of an event id. numbers are
+//! handed out in the order events were created and never reused, and are kept
+//! in `.git/ngit-numbers`, one event id per line.
+//!
+//! clones number what they have seen, so a maintainer can publish the
+//! numbering as a NIP-51 bookmark set that every clone adopts.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use nostr::{Event, EventBuilder, EventId, Filter, Kind, SingleLetterTag, Tag, TagStandard};
+use nostr_sdk::{Alphabet, NostrSigner};
+
+use crate::{
+    client::{get_events_from_local_cache, get_proposals_and_revisions_from_cache, sign_event},
+    git_events::event_is_revision_root,
+    repo_ref::RepoRef,
+};
+
+/// marks a bookmark set as a repository's numbering
+pub static NUMBERS_HASHTAG: &str = "ngit-numbers";
+
+static NUMBERS_PATH: &str = ".git/ngit-numbers";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShortNumbers {
+    /// `ids[0]` is #1
+    ids: Vec<EventId>,
+}
+
+impl ShortNumbers {
+    /// numbers saved in this clone. lines that aren't event ids are skipped
+    pub fn load(git_repo_path: &Path) -> Self {
+        Self {
+            ids: std::fs::read_to_string(git_repo_path.join(NUMBERS_PATH))
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| EventId::from_hex(line.trim()).ok())
+                .collect(),
+        }
+    }
+
+    pub fn save(&self, git_repo_path: &Path) -> Result<()> {
+        let contents: String = self.ids.iter().map(|id| format!("{id}\n")).collect();
+        std::fs::write(git_repo_path.join(NUMBERS_PATH), contents)
+            .context(format!("failed to write {NUMBERS_PATH}"))
+    }
+
+    pub fn number_of(&self, id: &EventId) -> Option<usize> {
+        self.ids.iter().position(|i| i == id).map(|i| i + 1)
+    }
+
+    pub fn id_of(&self, number: usize) -> Option<EventId> {
+        self.ids.get(number.checked_sub(1)?).copied()
+    }
+
+    /// number `events` not numbered yet, oldest first. returns whether any
+    /// were added
+    pub fn assign(&mut self, events: &[&Event]) -> bool {
+        let mut new: Vec<&&Event> = events
+            .iter()
+            .filter(|e| !self.ids.contains(&e.id))
+            .collect();
+        new.sort_by_key(|e| (e.created_at, e.id));
+        new.dedup_by_key(|e| e.id);
+        self.ids.extend(new.iter().map(|e| e.id));
+        !new.is_empty()
+    }
+
+    /// take the numbering in a maintainer's `index`, keeping events it
+    /// doesn't cover numbered after it. returns whether any number changed
+    pub fn anchor(&mut self, index: &[EventId]) -> bool {
+        let mut ids: Vec<EventId> = vec![];
+        for id in index.iter().chain(self.ids.iter()) {
+            if !ids.contains(id) {
+                ids.push(*id);
+            }
+        }
+        let changed = ids != self.ids;
+        self.ids = ids;
+        changed
+    }
+
+    /// ids with their numbers, #1 first
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &EventId)> {
+        self.ids.iter().enumerate().map(|(i, id)| (i + 1, id))
+    }
+
+    fn index_event_builder(&self, repo_ref: &RepoRef) -> EventBuilder {
+        EventBuilder::new(Kind::BookmarkSet, "").tags(
+            [
+                vec![
+                    Tag::identifier(index_identifier(repo_ref)),
+                    Tag::hashtag(NUMBERS_HASHTAG),
+                    Tag::coordinate(repo_ref.coordinate_with_hint()),
+                    Tag::custom(
+                        nostr::TagKind::Custom(std::borrow::Cow::Borrowed("alt")),
+                        vec![format!(
+                            "proposal and issue numbers for git repository: {}",
+                            repo_ref.name
+                        )],
+                    ),
+                ],
+                self.ids.iter().map(|id| Tag::event(*id)).collect(),
+            ]
+            .concat(),
+        )
+    }
+
+    /// the bookmark set a maintainer publishes to anchor the numbering
+    pub async fn to_index_event(
+        &self,
+        repo_ref: &RepoRef,
+        signer: &Arc<dyn NostrSigner>,
+    ) -> Result<Event> {
+        sign_event(self.index_event_builder(repo_ref), signer)
+            .await
+            .context("failed to create numbering event")
+    }
+}
+
+fn index_identifier(repo_ref: &RepoRef) -> String {
+    format!("{NUMBERS_HASHTAG}/{}", repo_ref.identifier)
+}
+
+/// `42` or `#42`
+pub fn parse_short_number(input: &str) -> Option<usize> {
+    input
+        .trim()
+        .trim_start_matches('#')
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+}
+
+/// the event ids, in order, of the latest numbering among `events` published
+/// by one of `repo_ref`'s maintainers
+pub fn index_from_events(events: &[Event], repo_ref: &RepoRef) -> Option<Vec<EventId>> {
+    let identifier = index_identifier(repo_ref);
+    events
+        .iter()
+        .filter(|e| {
+            e.kind == Kind::BookmarkSet
+                && repo_ref.maintainers.contains(&e.pubkey)
+                && e.tags.identifier() == Some(identifier.as_str())
+        })
+        .max_by_key(|e| e.created_at)
+        .map(|e| {
+            e.tags
+                .iter()
+                .filter_map(|t| match t.as_standardized() {
+                    Some(TagStandard::Event { event_id, .. }) => Some(*event_id),
+                    _ => None,
+                })
+                .collect()
+        })
+}
+
+/// numbers for the repository's proposals and issues in the cache. new ones
+/// are numbered and a maintainer's published numbering is adopted, saving
+/// any change
+pub async fn get_short_numbers(git_repo_path: &Path, repo_ref: &RepoRef) -> Result<ShortNumbers> {
+    let coordinates = repo_ref.coordinates_including_previous_identifiers();
+    let proposals: Vec<Event> =
+        get_proposals_and_revisions_from_cache(git_repo_path, coordinates.clone())
+            .await?
+            .into_iter()
+            .filter(|e| !event_is_revision_root(e))
+            .collect();
+    let issues = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            Filter::default().kind(Kind::GitIssue).custom_tag(
+                SingleLetterTag::lowercase(Alphabet::A),
+                coordinates
+                    .iter()
+                    .map(std::string::ToString::to_string)
+                    .collect::<Vec<String>>(),
+            ),
+        ],
+    )
+    .await?;
+    let index_events = get_events_from_local_cache(
+        git_repo_path,
+        vec![
+            Filter::default()
+                .kind(Kind::BookmarkSet)
+                .authors(repo_ref.maintainers.clone())
+                .identifier(index_identifier(repo_ref)),
+        ],
+    )
+    .await?;
+
+    let mut numbers = ShortNumbers::load(git_repo_path);
+    let mut changed =
+        index_from_events(&index_events, repo_ref).is_some_and(|index| numbers.anchor(&index));
+    changed |= numbers.assign(&proposals.iter().chain(issues.iter()).collect::<Vec<_>>());
+    if changed {
+        numbers.save(git_repo_path)?;
+    }
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{Keys, Timestamp};
+    use test_utils::{TEST_KEY_1_KEYS, TEST_KEY_2_KEYS, generate_repo_ref_event};
+
+    use super::*;
+
+    fn proposal(created_at: u64) -> Result<Event> {
+        Ok(EventBuilder::new(Kind::GitPatch, "")
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(&Keys::generate())?)
+    }
+
+    #[test]
+    fn numbers_are_assigned_oldest_first_and_never_change() -> Result<()> {
+        let (first, second, third) = (proposal(100)?, proposal(200)?, proposal(300)?);
+        let mut numbers = ShortNumbers::default();
+        assert!(numbers.assign(&[&second, &first]));
+        assert_eq!(numbers.number_of(&first.id), Some(1));
+        assert_eq!(numbers.number_of(&second.id), Some(2));
+        // an older event found later gets the next number
+        let older = proposal(50)?;
+        assert!(numbers.assign(&[&third, &older, &first]));
+        assert_eq!(numbers.number_of(&third.id), Some(4));
+        assert_eq!(numbers.id_of(3), Some(older.id));
+        assert!(!numbers.assign(&[&first, &second]));
+        assert_eq!(numbers.id_of(0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn anchoring_takes_the_maintainers_numbers_first() -> Result<()> {
+        let (first, second, local_only) = (proposal(100)?, proposal(200)?, proposal(300)?);
+        let mut numbers = ShortNumbers::default();
+        numbers.assign(&[&first, &local_only]);
+        assert!(numbers.anchor(&[second.id, first.id]));
+        assert_eq!(
+            numbers.iter().map(|(_, id)| *id).collect::<Vec<_>>(),
+            vec![second.id, first.id, local_only.id]
+        );
+        assert!(!numbers.anchor(&[second.id, first.id]));
+        Ok(())
+    }
+
+    #[test]
+    fn index_is_the_latest_from_a_maintainer() -> Result<()> {
+        let repo_ref = RepoRef::try_from((generate_repo_ref_event(), None))?;
+        let (first, second) = (proposal(100)?, proposal(200)?);
+        let mut numbers = ShortNumbers::default();
+        numbers.assign(&[&first]);
+        let older = numbers
+            .index_event_builder(&repo_ref)
+            .custom_created_at(Timestamp::from(1000))
+            .sign_with_keys(&TEST_KEY_2_KEYS)?;
+        numbers.assign(&[&second]);
+        let latest = numbers
+            .index_event_builder(&repo_ref)
+            .custom_created_at(Timestamp::from(2000))
+            .sign_with_keys(&TEST_KEY_1_KEYS)?;
+        let impostor = ShortNumbers::default()
+            .index_event_builder(&repo_ref)
+            .custom_created_at(Timestamp::from(3000))
+            .sign_with_keys(&Keys::generate())?;
+        assert_eq!(
+            index_from_events(&[older, latest, impostor], &repo_ref),
+            Some(vec![first.id, second.id])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn short_numbers_parse_with_or_without_hash() {
+        assert_eq!(parse_short_number("42"), Some(42));
+        assert_eq!(parse_short_number(" #7 "), Some(7));
+        assert_eq!(parse_short_number("0"), None);
+        assert_eq!(parse_short_number("note1abc"), None);
+    }
+}
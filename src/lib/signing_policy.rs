@@ -0,0 +1,121 @@
+use anyhow::{Result, bail};
+use nostr::{Kind, UnsignedEvent};
+
+use crate::{
+    cli_interactor::{Interactor, InteractorPrompt, PromptChoiceParms},
+    git::{get_git_config_item, save_git_config_item},
+};
+
+/// max characters of content shown before asking to sign
+static CONTENT_PREVIEW_LEN: usize = 500;
+
+/// max characters of each tag shown before asking to sign
+static TAG_PREVIEW_LEN: usize = 100;
+
+/// enabled with `git config --global nostr.confirm-signing true` or
+/// `confirm-signing` in `~/.config/ngit/config.toml`
+pub fn confirm_signing_enabled() -> bool {
+    crate::settings::settings().confirm_signing
+}
+
+/// kinds in `nostr.confirm-signing-allow` are signed without asking, eg.
+/// `1617,1630`
+fn is_kind_allowed(allowlist: &str, kind: Kind) -> bool {
+    allowlist
+        .split(',')
+        .filter_map(|k| k.trim().parse::<u16>().ok())
+        .any(|k| Kind::from(k) == kind)
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() > max_len {
+        format!("{}...", s.chars().take(max_len).collect::<String>())
+    } else {
+        s.to_string()
+    }
+}
+
+/// kind, tags and truncated content of an event about to be signed
+fn format_event_for_confirmation(unsigned: &UnsignedEvent) -> Vec<String> {
+    let mut lines = vec![format!("kind: {}", unsigned.kind.as_u16())];
+    if !unsigned.tags.is_empty() {
+        lines.push("tags:".to_string());
+        for tag in unsigned.tags.iter() {
+            lines.push(format!(
+                "  {}",
+                truncate(&tag.as_slice().join(" "), TAG_PREVIEW_LEN)
+            ));
+        }
+    }
+    if !unsigned.content.is_empty() {
+        lines.push("content:".to_string());
+        for line in truncate(&unsigned.content, CONTENT_PREVIEW_LEN).lines() {
+            lines.push(format!("  {line}"));
+        }
+    }
+    lines
+}
+
+/// show the event and ask for approval unless its kind is allowlisted.
+/// errors if the user declines.
+pub fn confirm_signing(unsigned: &UnsignedEvent) -> Result<()> {
+    let allowlist = get_git_config_item(&None, "nostr.confirm-signing-allow")?.unwrap_or_default();
+    if is_kind_allowed(&allowlist, unsigned.kind) {
+        return Ok(());
+    }
+    eprintln!("about to sign:");
+    for line in format_event_for_confirmation(unsigned) {
+        eprintln!("  {line}");
+    }
+    match Interactor::default().choice(
+        PromptChoiceParms::default()
+            .with_default(0)
+            .with_prompt("sign this event?")
+            .with_choices(vec![
+                "sign".to_string(),
+                format!(
+                    "sign and don't ask again for kind {}",
+                    unsigned.kind.as_u16()
+                ),
+                "reject".to_string(),
+            ]),
+    )? {
+        0 => Ok(()),
+        1 => {
+            let allowlist = if allowlist.trim().is_empty() {
+                unsigned.kind.as_u16().to_string()
+            } else {
+                format!("{allowlist},{}", unsigned.kind.as_u16())
+            };
+            save_git_config_item(&None, "nostr.confirm-signing-allow", &allowlist)
+        }
+        _ => bail!("signing rejected"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+
+    use super::*;
+
+    #[test]
+    fn is_kind_allowed_parses_comma_separated_kinds() {
+        assert!(is_kind_allowed("1617, 1630", Kind::from(1630)));
+        assert!(!is_kind_allowed("1617,1630", Kind::from(1621)));
+        assert!(!is_kind_allowed("", Kind::from(1617)));
+    }
+
+    #[test]
+    fn format_event_for_confirmation_truncates_content() {
+        let unsigned = EventBuilder::new(Kind::from(1617), "a".repeat(600))
+            .tags([Tag::hashtag("root")])
+            .build(Keys::generate().public_key());
+        let lines = format_event_for_confirmation(&unsigned);
+        assert_eq!(lines[0], "kind: 1617");
+        assert_eq!(lines[1], "tags:");
+        assert_eq!(lines[2], "  t root");
+        assert_eq!(lines[3], "content:");
+        assert_eq!(lines[4], format!("  {}...", "a".repeat(500)));
+    }
+}
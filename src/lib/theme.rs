@@ -0,0 +1,103 @@
+//! colors used in terminal output. `--color`, `NO_COLOR` and the `color`
+//! setting decide whether to use them and `[styles]` in the ngit config
+//! overrides the defaults, eg. for light terminals where 256-color grey is hard
+//! to read
+
+use anyhow::{Result, bail};
+use console::Style;
+
+use crate::settings::settings;
+
+pub static DEFAULT_DIM_STYLE: &str = "247";
+pub static DEFAULT_SUCCESS_STYLE: &str = "green";
+pub static DEFAULT_ERROR_STYLE: &str = "red";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(choice: &str) -> Result<Self> {
+        Ok(match choice.trim() {
+            "auto" => Self::Auto,
+            "always" => Self::Always,
+            "never" => Self::Never,
+            _ => bail!("invalid color choice \"{choice}\". expected auto, always or never"),
+        })
+    }
+}
+
+/// `--color` if given, then `NO_COLOR` when set to anything, then the `color`
+/// setting
+pub fn color_choice(flag: Option<&str>) -> Result<ColorChoice> {
+    if let Some(flag) = flag {
+        return flag.parse();
+    }
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return Ok(ColorChoice::Never);
+    }
+    settings()
+        .color
+        .as_deref()
+        .map_or(Ok(ColorChoice::Auto), str::parse)
+}
+
+/// enable or disable colors for console, indicatif and dialoguer output
+pub fn init(flag: Option<&str>) -> Result<()> {
+    match color_choice(flag)? {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => {
+            console::set_colors_enabled(true);
+            console::set_colors_enabled_stderr(true);
+        }
+        ColorChoice::Never => {
+            console::set_colors_enabled(false);
+            console::set_colors_enabled_stderr(false);
+        }
+    }
+    Ok(())
+}
+
+/// a style in console's dotted syntax, eg. `247`, `dim`, `blue.bold` or
+/// `black.on_white`
+fn parse_style(style: &str) -> Style {
+    Style::from_dotted_str(style)
+}
+
+/// secondary details such as relay names and links
+pub fn dim() -> Style {
+    parse_style(&settings().styles.dim)
+}
+
+pub fn success() -> Style {
+    parse_style(&settings().styles.success)
+}
+
+pub fn error() -> Style {
+    parse_style(&settings().styles.error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_flag_wins_and_invalid_choices_error() -> Result<()> {
+        assert_eq!(color_choice(Some("never"))?, ColorChoice::Never);
+        assert_eq!(color_choice(Some("always"))?, ColorChoice::Always);
+        assert!(color_choice(Some("sometimes")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn styles_parse_256_colors_and_names() {
+        assert_eq!(parse_style("247"), Style::new().color256(247));
+        assert_eq!(parse_style("blue.bold"), Style::new().blue().bold());
+    }
+}
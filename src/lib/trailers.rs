@@ -0,0 +1,220 @@
+//! trailers appended to commits applied from a proposal so history links back
+//! to its review on nostr. set `trailers` in `.ngit/config.toml`, or git
+//! config `nostr.trailers`, to `true` for the default template or to a
+//! template of its own:
+//!
+//! ```text
+//! Proposal-Id: {proposal}
+//! Reviewed-by: {reviewer}
+//! ```
+
+use nostr::{Event, Kind, PublicKey};
+
+pub static DEFAULT_TRAILER_TEMPLATE: &str = "Proposal-Id: {proposal}\nReviewed-by: {reviewer}";
+
+/// a trailer per line of `template`. lines with `{reviewer}` are repeated for
+/// each reviewer, so are left out when there are none
+pub fn render_trailers(template: &str, proposal: &str, reviewers: &[String]) -> Vec<String> {
+    let mut trailers = vec![];
+    for line in template
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let line = line.replace("{proposal}", proposal);
+        if line.contains("{reviewer}") {
+            trailers.extend(
+                reviewers
+                    .iter()
+                    .map(|reviewer| line.replace("{reviewer}", reviewer)),
+            );
+        } else {
+            trailers.push(line);
+        }
+    }
+    trailers
+}
+
+/// `maintainers` who left NIP-25 `+` reactions to the proposal or its patches,
+/// other than the proposal's author, in the order they reacted. anyone can
+/// react so reactions from others aren't treated as reviews
+pub fn approvers(
+    proposal: &Event,
+    reactions: &[Event],
+    maintainers: &[PublicKey],
+) -> Vec<PublicKey> {
+    let mut reactions: Vec<&Event> = reactions
+        .iter()
+        .filter(|e| e.kind == Kind::Reaction && matches!(e.content.trim(), "+" | ""))
+        .filter(|e| e.pubkey != proposal.pubkey && maintainers.contains(&e.pubkey))
+        .collect();
+    reactions.sort_by_key(|e| e.created_at);
+    let mut approvers = vec![];
+    for reaction in reactions {
+        if !approvers.contains(&reaction.pubkey) {
+            approvers.push(reaction.pubkey);
+        }
+    }
+    approvers
+}
+
+/// `name <npub>` for a reviewer trailer. names come from profiles so control
+/// characters, which could start new trailers or lines, are dropped
+pub fn reviewer(name: &str, npub: &str) -> String {
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.is_empty() {
+        npub.to_string()
+    } else {
+        format!("{name} <{npub}>")
+    }
+}
+
+/// `Token: value`, as `git interpret-trailers` recognises them
+fn is_trailer(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(token, _)| {
+        !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// the `git format-patch` output in `content` with `trailers` added to the end
+/// of its commit message, joining an existing block of trailers such as
+/// `Signed-off-by`. trailers already in the message aren't repeated
+pub fn append_trailers(content: &str, trailers: &[String]) -> String {
+    let Some(message_start) = content.find("\n\n").map(|i| i + 2) else {
+        return content.to_string();
+    };
+    let Some(message_end) = ["\n---\n", "\ndiff --git "]
+        .iter()
+        .filter_map(|separator| content[message_start - 1..].find(separator))
+        .min()
+        .map(|i| message_start + i)
+    else {
+        return content.to_string();
+    };
+    let message = content[message_start..message_end].trim_end();
+    let new_trailers: Vec<&String> = trailers
+        .iter()
+        .filter(|trailer| !message.lines().any(|line| line.trim() == trailer.trim()))
+        .collect();
+    if new_trailers.is_empty() {
+        return content.to_string();
+    }
+    let mut patched = content[..message_start].to_string();
+    patched.push_str(message);
+    if !message.is_empty() {
+        let last_paragraph = message.rsplit("\n\n").next().unwrap_or_default();
+        patched.push_str(if last_paragraph.lines().all(is_trailer) {
+            "\n"
+        } else {
+            "\n\n"
+        });
+    }
+    for trailer in new_trailers {
+        patched.push_str(trailer);
+        patched.push('\n');
+    }
+    patched.push_str(&content[message_end..]);
+    patched
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    #[test]
+    fn reviewer_lines_repeat_per_reviewer() {
+        assert_eq!(
+            render_trailers(
+                DEFAULT_TRAILER_TEMPLATE,
+                "nevent1abc",
+                &["alice <npub1a>".to_string(), "bob <npub1b>".to_string()]
+            ),
+            vec![
+                "Proposal-Id: nevent1abc",
+                "Reviewed-by: alice <npub1a>",
+                "Reviewed-by: bob <npub1b>",
+            ]
+        );
+        assert_eq!(
+            render_trailers(DEFAULT_TRAILER_TEMPLATE, "nevent1abc", &[]),
+            vec!["Proposal-Id: nevent1abc"]
+        );
+    }
+
+    #[test]
+    fn approvers_exclude_the_author_dislikes_and_non_maintainers() -> anyhow::Result<()> {
+        let author = Keys::generate();
+        let reviewer = Keys::generate();
+        let critic = Keys::generate();
+        let stranger = Keys::generate();
+        let proposal = EventBuilder::new(Kind::GitPatch, "").sign_with_keys(&author)?;
+        let reactions = vec![
+            EventBuilder::new(Kind::Reaction, "+").sign_with_keys(&author)?,
+            EventBuilder::new(Kind::Reaction, "+").sign_with_keys(&reviewer)?,
+            EventBuilder::new(Kind::Reaction, "+").sign_with_keys(&reviewer)?,
+            EventBuilder::new(Kind::Reaction, "-").sign_with_keys(&critic)?,
+            EventBuilder::new(Kind::Reaction, "+").sign_with_keys(&stranger)?,
+        ];
+        assert_eq!(
+            approvers(
+                &proposal,
+                &reactions,
+                &[
+                    author.public_key(),
+                    reviewer.public_key(),
+                    critic.public_key(),
+                ]
+            ),
+            vec![reviewer.public_key()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reviewer_names_cant_add_lines() {
+        assert_eq!(
+            reviewer("alice\nSigned-off-by: mallory", "npub1a"),
+            "aliceSigned-off-by: mallory <npub1a>"
+        );
+        assert_eq!(reviewer(" \r\n", "npub1a"), "npub1a");
+    }
+
+    static PATCH: &str = "From 431b84edc0d2fa118d63faa3c2db9c73d630a5ae Mon Sep 17 00:00:00 2001\nFrom: Joe Bloggs <joe.bloggs@pm.me>\nDate: Thu, 1 Jan 1970 00:00:00 +0000\nSubject: [PATCH] add t1.md\n\n";
+
+    #[test]
+    fn trailers_go_before_the_diffstat() {
+        let trailers = vec!["Proposal-Id: nevent1abc".to_string()];
+        assert_eq!(
+            append_trailers(
+                &format!("{PATCH}explain why\n---\n t1.md | 1 +\n"),
+                &trailers
+            ),
+            format!("{PATCH}explain why\n\nProposal-Id: nevent1abc\n---\n t1.md | 1 +\n")
+        );
+        assert_eq!(
+            append_trailers(&format!("{PATCH}---\n t1.md | 1 +\n"), &trailers),
+            format!("{PATCH}Proposal-Id: nevent1abc\n---\n t1.md | 1 +\n")
+        );
+    }
+
+    #[test]
+    fn trailers_join_existing_trailers_without_repeating() {
+        let content =
+            format!("{PATCH}explain why\n\nSigned-off-by: Joe Bloggs\n---\n t1.md | 1 +\n");
+        assert_eq!(
+            append_trailers(
+                &content,
+                &[
+                    "Signed-off-by: Joe Bloggs".to_string(),
+                    "Proposal-Id: nevent1abc".to_string(),
+                ]
+            ),
+            format!(
+                "{PATCH}explain why\n\nSigned-off-by: Joe Bloggs\nProposal-Id: nevent1abc\n---\n t1.md | 1 +\n"
+            )
+        );
+    }
+}
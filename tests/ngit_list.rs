@@ -197,17 +197,19 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!(
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, None)?;
@@ -314,17 +316,19 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(0, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!(
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -434,18 +438,20 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("add d3.md"), // commit msg title
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#4 add d3.md"), // commit msg title
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(0, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!(
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -509,18 +515,20 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("add d3.md"), // commit msg title
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#4 add d3.md"), // commit msg title
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(0, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!(
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -629,17 +637,19 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!(
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -651,15 +661,17 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch (2 ahead 0 behind 'main')"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -716,17 +728,19 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!(
                                     "create and checkout proposal branch (2 ahead 0 behind 'main')"
                                 ),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -738,15 +752,17 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch (2 ahead 0 behind 'main')"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -823,15 +839,17 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch and apply 1 appendments"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -893,15 +911,17 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout proposal branch and apply 1 appendments"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -992,7 +1012,8 @@ mod when_main_branch_is_uptodate {
                                 test_repo.dir.join("ammended-commit.md"),
                                 "some content",
                             )?;
-                            test_repo.stage_and_commit("add ammended-commit.md")?;
+                            let local_only_commit =
+                                test_repo.stage_and_commit("add ammended-commit.md")?;
                             test_repo.checkout("main")?;
 
                             // run test
@@ -1000,18 +1021,30 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             p.expect_eventually("--force`\r\n")?;
+                            p.expect("1 local-only commits would be affected by updating:\r\n")?;
+                            p.expect(format!(
+                                "  {} add ammended-commit.md\r\n",
+                                &local_only_commit.to_string()[..7]
+                            ))?;
+                            p.expect(
+                                "they can be rebased onto the new revision without conflicts\r\n",
+                            )?;
 
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout local branch with unpublished changes"),
                                 format!("discard unpublished changes and checkout new revision"),
+                                format!("rebase 1 local-only commits onto the new revision"),
+                                format!("checkout new revision as '{branch_name}-latest'"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 "back".to_string(),
                             ])?;
                             c.succeeds_with(1, true, Some(0))?;
@@ -1063,7 +1096,10 @@ mod when_main_branch_is_uptodate {
                             let (_, test_repo) =
                                 create_proposals_and_repo_with_proposal_pulled_and_checkedout(1)?;
 
-                            amend_last_commit(&test_repo, "add ammended-commit.md")?;
+                            let branch_name =
+                                amend_last_commit(&test_repo, "add ammended-commit.md")?;
+                            let local_only_commit =
+                                test_repo.get_tip_of_local_branch(&branch_name)?;
                             test_repo.checkout("main")?;
 
                             // run test
@@ -1071,23 +1107,35 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             p.expect("you have an amended/rebase version the proposal that is unpublished\r\n")?;
                             p.expect("you have previously applied the latest version of the proposal (2 ahead 0 behind 'main') but your local proposal branch has amended or rebased it (2 ahead 0 behind 'main')\r\n")?;
                             p.expect("to view the latest proposal but retain your changes:\r\n")?;
                             p.expect("  1) create a new branch off the tip commit of this one to store your changes\r\n")?;
                             p.expect("  2) run `ngit list` and checkout the latest published version of this proposal\r\n")?;
                             p.expect("if you are confident in your changes consider running `ngit push --force`\r\n")?;
+                            p.expect("1 local-only commits would be affected by updating:\r\n")?;
+                            p.expect(format!(
+                                "  {} add ammended-commit.md\r\n",
+                                &local_only_commit.to_string()[..7]
+                            ))?;
+                            p.expect(
+                                "they can be rebased onto the new revision without conflicts\r\n",
+                            )?;
 
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout local branch with unpublished changes"),
                                 format!("discard unpublished changes and checkout new revision"),
+                                format!("rebase 1 local-only commits onto the new revision"),
+                                format!("checkout new revision as '{branch_name}-latest'"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 "back".to_string(),
                             ])?;
                             c.succeeds_with(1, true, Some(1))?;
@@ -1111,6 +1159,196 @@ mod when_main_branch_is_uptodate {
                         println!("{:?}", r55.events);
                         Ok(())
                     }
+
+                    #[tokio::test]
+                    #[serial]
+                    async fn out_reflects_third_choice_rebasing_local_only_commits_onto_new_revision()
+                    -> Result<()> {
+                        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+                            Relay::new(8051, None, None),
+                            Relay::new(8052, None, None),
+                            Relay::new(8053, None, None),
+                            Relay::new(8055, None, None),
+                            Relay::new(8056, None, None),
+                        );
+
+                        r51.events.push(generate_test_key_1_relay_list_event());
+                        r51.events.push(generate_test_key_1_metadata_event("fred"));
+                        r51.events.push(generate_repo_ref_event());
+
+                        r55.events.push(generate_repo_ref_event());
+                        r55.events.push(generate_test_key_1_metadata_event("fred"));
+                        r55.events.push(generate_test_key_1_relay_list_event());
+
+                        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+                            let (originating_repo, test_repo) =
+                                create_proposals_and_repo_with_proposal_pulled_and_checkedout(1)?;
+
+                            let branch_name =
+                                amend_last_commit(&test_repo, "add ammended-commit.md")?;
+                            let local_only_commit =
+                                test_repo.get_tip_of_local_branch(&branch_name)?;
+                            test_repo.checkout("main")?;
+
+                            // run test
+                            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+                            p.expect("fetching updates...\r\n")?;
+                            p.expect_eventually("\r\n")?; // some updates listed here
+                            let mut c = p.expect_choice("all proposals", vec![
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
+                            ])?;
+                            c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
+                            p.expect_eventually("--force`\r\n")?;
+                            p.expect("1 local-only commits would be affected by updating:\r\n")?;
+                            p.expect(format!(
+                                "  {} add ammended-commit.md\r\n",
+                                &local_only_commit.to_string()[..7]
+                            ))?;
+                            p.expect(
+                                "they can be rebased onto the new revision without conflicts\r\n",
+                            )?;
+
+                            let mut c = p.expect_choice("", vec![
+                                format!("checkout local branch with unpublished changes"),
+                                format!("discard unpublished changes and checkout new revision"),
+                                format!("rebase 1 local-only commits onto the new revision"),
+                                format!("checkout new revision as '{branch_name}-latest'"),
+                                format!("apply to current branch with `git am`"),
+                                format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
+                                "back".to_string(),
+                            ])?;
+                            c.succeeds_with(2, true, Some(2))?;
+                            p.expect_end_with("rebased 1 local-only commits onto the new revision of the proposal\r\n")?;
+
+                            assert_eq!(test_repo.get_checked_out_branch_name()?, branch_name);
+                            let rebased_tip = test_repo
+                                .git_repo
+                                .find_commit(test_repo.get_tip_of_local_branch(&branch_name)?)?;
+                            assert_eq!(rebased_tip.summary(), Some("add ammended-commit.md"));
+                            assert_eq!(
+                                rebased_tip.parent_id(0)?,
+                                originating_repo.get_tip_of_local_branch(FEATURE_BRANCH_NAME_1)?,
+                            );
+
+                            for p in [51, 52, 53, 55, 56] {
+                                relay::shutdown_relay(8000 + p)?;
+                            }
+                            Ok(())
+                        });
+
+                        // launch relay
+                        let _ = join!(
+                            r51.listen_until_close(),
+                            r52.listen_until_close(),
+                            r53.listen_until_close(),
+                            r55.listen_until_close(),
+                            r56.listen_until_close(),
+                        );
+                        cli_tester_handle.join().unwrap()?;
+                        Ok(())
+                    }
+
+                    #[tokio::test]
+                    #[serial]
+                    async fn out_reflects_fourth_choice_checking_out_new_revision_alongside_local_branch()
+                    -> Result<()> {
+                        let (mut r51, mut r52, mut r53, mut r55, mut r56) = (
+                            Relay::new(8051, None, None),
+                            Relay::new(8052, None, None),
+                            Relay::new(8053, None, None),
+                            Relay::new(8055, None, None),
+                            Relay::new(8056, None, None),
+                        );
+
+                        r51.events.push(generate_test_key_1_relay_list_event());
+                        r51.events.push(generate_test_key_1_metadata_event("fred"));
+                        r51.events.push(generate_repo_ref_event());
+
+                        r55.events.push(generate_repo_ref_event());
+                        r55.events.push(generate_test_key_1_metadata_event("fred"));
+                        r55.events.push(generate_test_key_1_relay_list_event());
+
+                        let cli_tester_handle = std::thread::spawn(move || -> Result<()> {
+                            let (originating_repo, test_repo) =
+                                create_proposals_and_repo_with_proposal_pulled_and_checkedout(1)?;
+
+                            let branch_name =
+                                amend_last_commit(&test_repo, "add ammended-commit.md")?;
+                            let local_only_commit =
+                                test_repo.get_tip_of_local_branch(&branch_name)?;
+                            test_repo.checkout("main")?;
+
+                            // run test
+                            let mut p = CliTester::new_from_dir(&test_repo.dir, ["list"]);
+                            p.expect("fetching updates...\r\n")?;
+                            p.expect_eventually("\r\n")?; // some updates listed here
+                            let mut c = p.expect_choice("all proposals", vec![
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
+                            ])?;
+                            c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
+                            p.expect_eventually("--force`\r\n")?;
+                            p.expect("1 local-only commits would be affected by updating:\r\n")?;
+                            p.expect(format!(
+                                "  {} add ammended-commit.md\r\n",
+                                &local_only_commit.to_string()[..7]
+                            ))?;
+                            p.expect(
+                                "they can be rebased onto the new revision without conflicts\r\n",
+                            )?;
+
+                            let mut c = p.expect_choice("", vec![
+                                format!("checkout local branch with unpublished changes"),
+                                format!("discard unpublished changes and checkout new revision"),
+                                format!("rebase 1 local-only commits onto the new revision"),
+                                format!("checkout new revision as '{branch_name}-latest'"),
+                                format!("apply to current branch with `git am`"),
+                                format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
+                                "back".to_string(),
+                            ])?;
+                            c.succeeds_with(3, true, Some(3))?;
+                            p.expect_end_with(&format!(
+                                "checked out new revision as '{branch_name}-latest'. your unpublished changes remain on '{branch_name}'\r\n"
+                            ))?;
+
+                            assert_eq!(
+                                test_repo.get_checked_out_branch_name()?,
+                                format!("{branch_name}-latest"),
+                            );
+                            assert_eq!(
+                                test_repo
+                                    .get_tip_of_local_branch(&format!("{branch_name}-latest"))?,
+                                originating_repo.get_tip_of_local_branch(FEATURE_BRANCH_NAME_1)?,
+                            );
+                            assert_eq!(
+                                test_repo.get_tip_of_local_branch(&branch_name)?,
+                                local_only_commit,
+                            );
+
+                            for p in [51, 52, 53, 55, 56] {
+                                relay::shutdown_relay(8000 + p)?;
+                            }
+                            Ok(())
+                        });
+
+                        // launch relay
+                        let _ = join!(
+                            r51.listen_until_close(),
+                            r52.listen_until_close(),
+                            r53.listen_until_close(),
+                            r55.listen_until_close(),
+                            r56.listen_until_close(),
+                        );
+                        cli_tester_handle.join().unwrap()?;
+                        Ok(())
+                    }
                 }
 
                 #[tokio::test]
@@ -1168,11 +1406,12 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             p.expect(
                                 "local proposal branch exists with 1 unpublished commits on top of the most up-to-date version of the proposal (3 ahead 0 behind 'main')\r\n",
                             )?;
@@ -1244,11 +1483,12 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             p.expect(
                                 "local proposal branch exists with 1 unpublished commits on top of the most up-to-date version of the proposal (3 ahead 0 behind 'main')\r\n",
                             )?;
@@ -1340,17 +1580,19 @@ mod when_main_branch_is_uptodate {
                             p.expect("fetching updates...\r\n")?;
                             p.expect_eventually("\r\n")?; // some updates listed here
                             let mut c = p.expect_choice("all proposals", vec![
-                                format!("\"{PROPOSAL_TITLE_3}\""),
-                                format!("\"{PROPOSAL_TITLE_2}\""),
-                                format!("\"{PROPOSAL_TITLE_1}\""),
+                                format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                format!("#1 \"{PROPOSAL_TITLE_1}\""),
                             ])?;
                             c.succeeds_with(2, true, None)?;
+                            p.expect("by fred\r\n")?;
                             p.expect("updated proposal available (2 ahead 0 behind 'main'). existing version is 2 ahead 1 behind 'main'\r\n")?;
                             let mut c = p.expect_choice("", vec![
                                 format!("checkout and overwrite existing proposal branch"),
                                 format!("checkout existing outdated proposal branch"),
                                 format!("apply to current branch with `git am`"),
                                 format!("download to ./patches"),
+                                format!("preview cover letter and diffstat"),
                                 format!("back"),
                             ])?;
                             c.succeeds_with(0, true, Some(0))?;
@@ -1407,17 +1649,19 @@ mod when_main_branch_is_uptodate {
                                 p.expect("fetching updates...\r\n")?;
                                 p.expect_eventually("\r\n")?; // some updates listed here
                                 let mut c = p.expect_choice("all proposals", vec![
-                                    format!("\"{PROPOSAL_TITLE_3}\""),
-                                    format!("\"{PROPOSAL_TITLE_2}\""),
-                                    format!("\"{PROPOSAL_TITLE_1}\""),
+                                    format!("#3 \"{PROPOSAL_TITLE_3}\""),
+                                    format!("#2 \"{PROPOSAL_TITLE_2}\""),
+                                    format!("#1 \"{PROPOSAL_TITLE_1}\""),
                                 ])?;
                                 c.succeeds_with(2, true, None)?;
+                                p.expect("by fred\r\n")?;
                                 p.expect("updated proposal available (2 ahead 0 behind 'main'). existing version is 2 ahead 1 behind 'main'\r\n")?;
                                 let mut c = p.expect_choice("", vec![
                                     format!("checkout and overwrite existing proposal branch"),
                                     format!("checkout existing outdated proposal branch"),
                                     format!("apply to current branch with `git am`"),
                                     format!("download to ./patches"),
+                                    format!("preview cover letter and diffstat"),
                                     format!("back"),
                                 ])?;
                                 c.succeeds_with(0, true, Some(0))?;
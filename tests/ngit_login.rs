@@ -9,6 +9,7 @@ fn show_first_time_login_choices(p: &mut CliTester) -> Result<CliTesterChoicePro
     p.expect_choice("login to nostr", vec![
         "secret key (nsec / ncryptsec)".to_string(),
         "nostr connect (remote signer)".to_string(),
+        "browser extension (NIP-07)".to_string(),
         "create account".to_string(),
         "help".to_string(),
     ])
@@ -18,6 +19,7 @@ fn first_time_login_choices_succeeds_with_nsec(p: &mut CliTester, nsec: &str) ->
     p.expect_choice("login to nostr", vec![
         "secret key (nsec / ncryptsec)".to_string(),
         "nostr connect (remote signer)".to_string(),
+        "browser extension (NIP-07)".to_string(),
         "create account".to_string(),
         "help".to_string(),
     ])?
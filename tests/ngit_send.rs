@@ -162,6 +162,8 @@ fn expect_msgs_after(p: &mut CliTester) -> Result<()> {
     p.expect_eventually("\r\n")?;
     p.expect("view in another client:  https://njump.me/")?;
     p.expect_eventually("\r\n")?;
+    p.expect("share with:              nostr:nevent1")?;
+    p.expect_eventually("\r\n")?;
     Ok(())
 }
 